@@ -39,8 +39,14 @@
 
 	;       Second section: Stack setup
 	global  stack_bottom
+	global  stack_guard
 	section .bss
-	align   16
+	align   4096
+
+stack_guard:
+	;   Unmapped once paging is set up, an overflow faults instead of
+	;   running into the rest of .bss (see memory::protect_kernel_image)
+	resb 4096
 
 stack_bottom:
 	;WARNING Do not change value without change it in memory/stack.rs
@@ -94,9 +100,10 @@ higher_half:
 	;    Init idt
 	call idt_init
 
-	;    Initiate PIC
-	push 28
-	push 20
+	;    Initiate PIC, moving IRQs past the CPU exceptions
+	;    (master at 0x20, slave at 0x28, see PIC1_OFFSET in pic.rs)
+	push 0x28
+	push 0x20
 	call pic_remap
 	add  esp, 8
 
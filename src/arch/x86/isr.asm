@@ -0,0 +1,126 @@
+; Entry stubs of the CPU exceptions.
+;
+; Every stub pushes a zero error code unless the CPU pushed one, then its
+; vector, and jumps to exception_common. That saves the general purpose and
+; segment registers so the stack holds an ExceptionContext (see
+; exceptions.rs), calls exception_dispatch with a pointer to it, and restores
+; everything from it before returning with iret.
+;
+; int 0x80 takes the same path to syscall_dispatch instead.
+
+section .text
+extern  exception_dispatch
+extern  syscall_dispatch
+extern  double_fault_task
+global  exception_stub_table
+global  syscall_stub
+global  double_fault_task_entry
+
+%macro EXCEPTION_STUB 1
+exception_stub_%1:
+	push 0
+	push %1
+	jmp  exception_common
+%endmacro
+
+%macro EXCEPTION_STUB_ERROR_CODE 1
+exception_stub_%1:
+	push %1
+	jmp  exception_common
+%endmacro
+
+EXCEPTION_STUB 0
+EXCEPTION_STUB 1
+EXCEPTION_STUB 2
+EXCEPTION_STUB 3
+EXCEPTION_STUB 4
+EXCEPTION_STUB 5
+EXCEPTION_STUB 6
+EXCEPTION_STUB 7
+EXCEPTION_STUB_ERROR_CODE 8
+EXCEPTION_STUB 9
+EXCEPTION_STUB_ERROR_CODE 10
+EXCEPTION_STUB_ERROR_CODE 11
+EXCEPTION_STUB_ERROR_CODE 12
+EXCEPTION_STUB_ERROR_CODE 13
+EXCEPTION_STUB_ERROR_CODE 14
+EXCEPTION_STUB 15
+EXCEPTION_STUB 16
+EXCEPTION_STUB_ERROR_CODE 17
+EXCEPTION_STUB 18
+EXCEPTION_STUB 19
+EXCEPTION_STUB 20
+EXCEPTION_STUB_ERROR_CODE 21
+EXCEPTION_STUB 22
+EXCEPTION_STUB 23
+EXCEPTION_STUB 24
+EXCEPTION_STUB 25
+EXCEPTION_STUB 26
+EXCEPTION_STUB 27
+EXCEPTION_STUB 28
+EXCEPTION_STUB_ERROR_CODE 29
+EXCEPTION_STUB_ERROR_CODE 30
+EXCEPTION_STUB 31
+
+; Saves an ExceptionContext, calls %2 with it and returns from the interrupt
+%macro CONTEXT_ENTRY 2
+%1:
+	pusha
+	push ds
+	push es
+	push fs
+	push gs
+
+	;   Kernel data segment, the interrupted code may have had others
+	mov ax, 0x10
+	mov ds, ax
+	mov es, ax
+
+	;    The System V ABI wants the direction flag cleared
+	cld
+	push esp
+	call %2
+	add  esp, 4
+
+	pop gs
+	pop fs
+	pop es
+	pop ds
+	popa
+
+	;   Vector and error code
+	add esp, 8
+	iret
+%endmacro
+
+CONTEXT_ENTRY exception_common, exception_dispatch
+
+; Entry of int 0x80, the one gate ring 3 may use
+syscall_stub:
+	push 0
+	push 0x80
+	jmp  syscall_common
+
+CONTEXT_ENTRY syscall_common, syscall_dispatch
+
+; Entry of the double fault task, which the task gate of vector 8 switches
+; to (see tss.rs). It starts on its own stack with only the error code on
+; it, which is where double_fault_task finds its argument after the call.
+double_fault_task_entry:
+	cld
+	call double_fault_task
+
+.hang:
+	cli
+	hlt
+	jmp .hang
+
+section .rodata
+
+;   Addresses of the stubs, by vector
+exception_stub_table:
+%assign vector 0
+%rep    32
+	dd exception_stub_%+vector
+%assign vector vector + 1
+%endrep
@@ -0,0 +1,31 @@
+; Switching between kernel threads, see task/thread.rs.
+;
+; switch_to saves the callee-saved registers and EFLAGS on the stack of the
+; running thread, stores its stack pointer where the first argument points
+; and pops the same from the stack the second argument names. spawn builds
+; the stack of a new thread to look like one switched away from, so the ret
+; lands in its start function.
+
+section .text
+global  switch_to
+
+; switch_to(usize *old_esp, usize new_esp)
+switch_to:
+	mov eax, [esp + 4]
+	mov edx, [esp + 8]
+
+	push ebp
+	push ebx
+	push esi
+	push edi
+	pushf
+
+	mov [eax], esp
+	mov esp, edx
+
+	popf
+	pop edi
+	pop esi
+	pop ebx
+	pop ebp
+	ret
@@ -0,0 +1,102 @@
+; Switching to ring 3 and back, see usermode.rs.
+;
+; usermode_iret loads the user data segments and irets to a ring 3 entry
+; point. usermode_run saves the callee-saved registers and EFLAGS on the
+; kernel stack first and stores the stack pointer where its third argument
+; points, so the exit syscall can return from usermode_run with
+; usermode_return.
+
+section .text
+global  usermode_iret
+global  usermode_run
+global  usermode_return
+global  user_payload_start
+global  user_payload_end
+
+USER_CODE_SELECTOR   equ 0x18 | 3
+USER_DATA_SELECTOR   equ 0x20 | 3
+KERNEL_DATA_SELECTOR equ 0x10
+EFLAGS_IF            equ 1 << 9
+
+; ! usermode_iret(u32 entry, u32 user_stack)
+usermode_iret:
+	mov ecx, [esp + 4]
+	mov edx, [esp + 8]
+
+	mov ax, USER_DATA_SELECTOR
+	mov ds, ax
+	mov es, ax
+	mov fs, ax
+	mov gs, ax
+
+	;    The frame of an interrupt from ring 3, iret pops it
+	push USER_DATA_SELECTOR
+	push edx
+	pushf
+	or   dword [esp], EFLAGS_IF
+	push USER_CODE_SELECTOR
+	push ecx
+	iret
+
+; u32 usermode_run(u32 entry, u32 user_stack, u32 *kernel_esp)
+usermode_run:
+	push ebp
+	push ebx
+	push esi
+	push edi
+	pushf
+
+	;   Past the five saved words and the return address
+	mov eax, [esp + 32]
+	mov [eax], esp
+
+	;    user_stack, then entry, each 4 deeper after a push
+	push dword [esp + 28]
+	push dword [esp + 28]
+	call usermode_iret
+
+; ! usermode_return(u32 kernel_esp, u32 value)
+usermode_return:
+	mov eax, [esp + 8]
+	mov esp, [esp + 4]
+
+	mov cx, KERNEL_DATA_SELECTOR
+	mov ds, cx
+	mov es, cx
+	mov fs, cx
+	mov gs, cx
+
+	popf
+	pop edi
+	pop esi
+	pop ebx
+	pop ebp
+	ret
+
+; The demo payload, copied to a user page. Position independent, it writes
+; its message to the console and exits with a value the kernel checks.
+SYS_EXIT  equ 1
+SYS_WRITE equ 4
+
+user_payload_start:
+	mov  eax, SYS_WRITE
+	mov  ebx, 1
+	call .here
+.here:
+	pop  ecx
+	add  ecx, .message - .here
+	mov  edx, .message_end - .message
+	int  0x80
+
+	mov eax, SYS_EXIT
+	mov ebx, 0x600d
+	int 0x80
+
+	;   Not reached, exit does not return
+.hang:
+	jmp .hang
+
+.message:
+	db "Hello from ring 3", 10
+.message_end:
+user_payload_end:
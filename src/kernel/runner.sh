@@ -18,7 +18,13 @@ else
     export QEMUFLAGS="-serial stdio"
 fi
 
-qemu-system-i386 -cdrom kernel.iso $QEMUFLAGS
+# Scratch disk for the ATA driver, the primary master
+if [ ! -f disk.img ]; then
+    dd if=/dev/zero of=disk.img bs=1M count=4 2>/dev/null
+fi
+
+qemu-system-i386 -cdrom kernel.iso -boot d \
+    -drive file=disk.img,format=raw,index=0,media=disk $QEMUFLAGS
 EXIT=$?
 
 if [ "$EXIT" -ne 33 ]; then
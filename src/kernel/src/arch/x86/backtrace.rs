@@ -0,0 +1,98 @@
+//! Stack backtraces along the saved frame pointer chain.
+//!
+//! The kernel is built with frame pointers (see `.cargo/config.toml`), so
+//! every function starts with `push ebp; mov ebp, esp`: `[ebp]` holds the
+//! caller's EBP and `[ebp + 4]` the return address into the caller.
+//! `boot.asm` clears EBP before calling `kernel_main`, which ends the chain.
+//!
+//! Addresses are named with the [symbol table](crate::symbols) when it has
+//! them.
+
+use crate::{
+	memory::{get_kernel_text, paging::translate, VirtAddr},
+	println_serial,
+	symbols::Symbolized,
+	tty::serial::{print_unlocked, SERIAL},
+};
+use core::{arch::asm, fmt, ptr};
+
+/// Frames walked before giving up, a corrupted chain may loop.
+pub const MAX_FRAMES: usize = 32;
+
+/// The return addresses along a frame pointer chain, innermost first.
+pub struct Frames {
+	ebp: usize,
+	depth: usize,
+}
+
+impl Iterator for Frames {
+	type Item = VirtAddr;
+
+	/// Moves to the caller's frame. Stops at the end of the chain, after
+	/// [`MAX_FRAMES`], at a frame pointer that is misaligned or not mapped
+	/// and at a return address outside the kernel's code.
+	fn next(&mut self) -> Option<VirtAddr> {
+		if self.ebp == 0 || self.depth == MAX_FRAMES {
+			return None;
+		}
+		if self.ebp % 4 != 0 || !is_mapped(self.ebp) || !is_mapped(self.ebp + 4)
+		{
+			return None;
+		}
+
+		let frame: *const usize = ptr::with_exposed_provenance(self.ebp);
+		let (caller_ebp, return_address) =
+			unsafe { (frame.read(), frame.add(1).read()) };
+
+		let return_address = VirtAddr::new(return_address);
+		if !get_kernel_text().contains(&return_address) {
+			return None;
+		}
+
+		// Callers' frames lie above, a chain going down is corrupted
+		self.ebp = match caller_ebp > self.ebp {
+			true => caller_ebp,
+			false => 0,
+		};
+		self.depth += 1;
+		return Some(return_address);
+	}
+}
+
+fn is_mapped(address: usize) -> bool {
+	return translate(VirtAddr::new(address)).is_some();
+}
+
+/// Returns the return addresses along the chain starting at `start_ebp`.
+pub fn frames(start_ebp: usize) -> Frames {
+	return Frames {
+		ebp: start_ebp,
+		depth: 0,
+	};
+}
+
+/// Returns the frame pointer of the calling function.
+#[inline(always)]
+pub fn current_ebp() -> usize {
+	let ebp: usize;
+	unsafe { asm!("mov {}, ebp", out(reg) ebp, options(nomem, nostack)) };
+	return ebp;
+}
+
+/// Prints the return addresses along the chain starting at `start_ebp` to
+/// serial, one per line with the function it is in. Does not wait for the port
+/// if the interrupted code holds it.
+pub fn print_backtrace(start_ebp: usize) {
+	print_line(format_args!("Backtrace:"));
+	for (depth, address) in frames(start_ebp).enumerate() {
+		print_line(format_args!("  #{:<2} {}", depth, Symbolized(address)));
+	}
+}
+
+fn print_line(args: fmt::Arguments) {
+	if SERIAL.is_locked() {
+		print_unlocked(format_args!("{}\n", args));
+	} else {
+		println_serial!("{}", args);
+	}
+}
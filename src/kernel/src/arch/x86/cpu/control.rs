@@ -9,6 +9,43 @@ pub fn cli() {
 	}
 }
 
+#[inline]
+#[doc(hidden)]
+pub fn sti() {
+	unsafe {
+		asm!("sti", options(nomem, nostack));
+	}
+}
+
+/// Returns whether interrupts are enabled, EFLAGS.IF.
+#[inline]
+#[doc(hidden)]
+pub fn interrupts_enabled() -> bool {
+	let eflags: u32;
+
+	unsafe {
+		asm!("pushfd", "pop {}", out(reg) eflags, options(nomem, preserves_flags))
+	};
+
+	eflags & (1 << 9) != 0
+}
+
+/// Runs `f` with interrupts disabled, then enables them again if they were
+/// enabled before.
+#[inline]
+#[doc(hidden)]
+pub fn without_interrupts<R>(f: impl FnOnce() -> R) -> R {
+	let enabled = interrupts_enabled();
+	cli();
+
+	let result = f();
+
+	if enabled {
+		sti();
+	}
+	result
+}
+
 #[inline]
 #[doc(hidden)]
 pub fn halt() {
@@ -37,6 +74,20 @@ pub fn cr3() -> PhysAddr {
 	PhysAddr::new(cr3)
 }
 
+/// Loads the page directory at `directory` into CR3.
+///
+/// # Safety
+///
+/// The page directory must map the running code, its stack and everything
+/// else the kernel touches while it is loaded.
+#[inline]
+#[doc(hidden)]
+pub unsafe fn set_cr3(directory: PhysAddr) {
+	unsafe {
+		asm!("mov cr3, {}", in(reg) directory.as_usize(), options(nostack, preserves_flags));
+	}
+}
+
 #[inline]
 #[doc(hidden)]
 pub fn cr2() -> VirtAddr {
@@ -56,3 +107,27 @@ pub fn invlpg(addr: VirtAddr) {
 		asm!("invlpg [{}]", in(reg) addr.as_usize(), options(nostack, preserves_flags));
 	}
 }
+
+/// Flushes every non-global TLB entry by reloading CR3.
+#[inline]
+#[doc(hidden)]
+pub fn flush_tlb() {
+	unsafe {
+		asm!("mov {0}, cr3", "mov cr3, {0}", out(reg) _, options(nostack, preserves_flags));
+	}
+}
+
+/// Sets CR0.WP, so the kernel faults on writes to read-only pages too.
+#[inline]
+#[doc(hidden)]
+pub fn enable_write_protect() {
+	unsafe {
+		asm!(
+			"mov {0}, cr0",
+			"or {0}, 1 << 16",
+			"mov cr0, {0}",
+			out(reg) _,
+			options(nostack, preserves_flags)
+		);
+	}
+}
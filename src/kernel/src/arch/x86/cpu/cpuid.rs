@@ -0,0 +1,312 @@
+//! CPUID wrapper and the boot-time cache of CPU feature flags.
+
+use crate::sync::OnceLock;
+use core::arch::{
+	asm,
+	x86::{CpuidResult, __cpuid_count},
+};
+
+/// EFLAGS bit that can only be toggled on CPUs implementing CPUID.
+const EFLAGS_ID: u32 = 1 << 21;
+
+const BRAND_FIRST_LEAF: u32 = 0x8000_0002;
+const BRAND_LAST_LEAF: u32 = 0x8000_0004;
+
+static CPU_FEATURES: OnceLock<CpuFeatures> = OnceLock::new();
+
+/// Returns whether the CPU implements CPUID, by checking whether the ID flag
+/// in EFLAGS can be toggled.
+pub fn has_cpuid() -> bool {
+	let original: u32;
+	let toggled: u32;
+
+	unsafe {
+		asm!(
+			"pushfd",
+			"pop {original}",
+			"mov {toggled}, {original}",
+			"xor {toggled}, {id}",
+			"push {toggled}",
+			"popfd",
+			"pushfd",
+			"pop {toggled}",
+			"push {original}",
+			"popfd",
+			original = out(reg) original,
+			toggled = out(reg) toggled,
+			id = const EFLAGS_ID,
+		);
+	}
+
+	return (original ^ toggled) & EFLAGS_ID != 0;
+}
+
+/// Executes CPUID for `leaf`/`subleaf`.
+///
+/// Returns `None` on CPUs without CPUID (the ID flag in EFLAGS cannot be
+/// toggled) or if `leaf` is above the highest leaf of its range.
+pub fn cpuid(leaf: u32, subleaf: u32) -> Option<CpuidResult> {
+	if !has_cpuid() {
+		return None;
+	}
+
+	let max_leaf = unsafe { __cpuid_count(leaf & 0x8000_0000, 0) }.eax;
+	if leaf > max_leaf {
+		return None;
+	}
+
+	return Some(unsafe { __cpuid_count(leaf, subleaf) });
+}
+
+/// Returns the 12 byte vendor string, e.g. `GenuineIntel`.
+pub fn vendor() -> Option<[u8; 12]> {
+	let result = cpuid(0, 0)?;
+	let mut vendor = [0; 12];
+
+	vendor[0..4].copy_from_slice(&result.ebx.to_le_bytes());
+	vendor[4..8].copy_from_slice(&result.edx.to_le_bytes());
+	vendor[8..12].copy_from_slice(&result.ecx.to_le_bytes());
+
+	return Some(vendor);
+}
+
+/// Returns the 48 byte, NUL padded processor brand string.
+pub fn brand() -> Option<[u8; 48]> {
+	let mut brand = [0; 48];
+
+	for (i, leaf) in (BRAND_FIRST_LEAF..=BRAND_LAST_LEAF).enumerate() {
+		let result = cpuid(leaf, 0)?;
+		let chunk = &mut brand[i * 16..(i + 1) * 16];
+
+		chunk[0..4].copy_from_slice(&result.eax.to_le_bytes());
+		chunk[4..8].copy_from_slice(&result.ebx.to_le_bytes());
+		chunk[8..12].copy_from_slice(&result.ecx.to_le_bytes());
+		chunk[12..16].copy_from_slice(&result.edx.to_le_bytes());
+	}
+
+	return Some(brand);
+}
+
+/// Family, model and stepping of the processor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CpuSignature {
+	/// Family, including the extended family.
+	pub family: u32,
+	/// Model, including the extended model.
+	pub model: u32,
+	/// Stepping.
+	pub stepping: u32,
+}
+
+/// Decodes the processor signature from leaf 1.
+pub fn signature() -> Option<CpuSignature> {
+	let eax = cpuid(1, 0)?.eax;
+
+	let mut family = (eax >> 8) & 0xf;
+	let mut model = (eax >> 4) & 0xf;
+
+	if family == 0xf {
+		family += (eax >> 20) & 0xff;
+	}
+	if family == 0x6 || family >= 0xf {
+		model += ((eax >> 16) & 0xf) << 4;
+	}
+
+	return Some(CpuSignature {
+		family,
+		model,
+		stepping: eax & 0xf,
+	});
+}
+
+/* -------------------------------------- */
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+	Ecx,
+	Edx,
+}
+
+/// A feature reported in EDX or ECX of CPUID leaf 1.
+#[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Feature {
+	Fpu,
+	Vme,
+	De,
+	Pse,
+	Tsc,
+	Msr,
+	Pae,
+	Mce,
+	Cx8,
+	Apic,
+	Sep,
+	Mtrr,
+	Pge,
+	Mca,
+	Cmov,
+	Pat,
+	Pse36,
+	Clflush,
+	Mmx,
+	Fxsr,
+	Sse,
+	Sse2,
+	Htt,
+	Sse3,
+	Pclmulqdq,
+	Ssse3,
+	Fma,
+	Cx16,
+	Sse41,
+	Sse42,
+	X2apic,
+	Movbe,
+	Popcnt,
+	Aes,
+	Xsave,
+	Avx,
+	F16c,
+	Rdrand,
+	Hypervisor,
+}
+
+impl Feature {
+	/// Every decoded feature, in register and bit order.
+	pub const ALL: [Feature; 39] = [
+		Feature::Fpu,
+		Feature::Vme,
+		Feature::De,
+		Feature::Pse,
+		Feature::Tsc,
+		Feature::Msr,
+		Feature::Pae,
+		Feature::Mce,
+		Feature::Cx8,
+		Feature::Apic,
+		Feature::Sep,
+		Feature::Mtrr,
+		Feature::Pge,
+		Feature::Mca,
+		Feature::Cmov,
+		Feature::Pat,
+		Feature::Pse36,
+		Feature::Clflush,
+		Feature::Mmx,
+		Feature::Fxsr,
+		Feature::Sse,
+		Feature::Sse2,
+		Feature::Htt,
+		Feature::Sse3,
+		Feature::Pclmulqdq,
+		Feature::Ssse3,
+		Feature::Fma,
+		Feature::Cx16,
+		Feature::Sse41,
+		Feature::Sse42,
+		Feature::X2apic,
+		Feature::Movbe,
+		Feature::Popcnt,
+		Feature::Aes,
+		Feature::Xsave,
+		Feature::Avx,
+		Feature::F16c,
+		Feature::Rdrand,
+		Feature::Hypervisor,
+	];
+
+	/// Returns the lowercase name Linux uses in `/proc/cpuinfo`.
+	pub const fn name(self) -> &'static str {
+		return self.location().0;
+	}
+
+	const fn location(self) -> (&'static str, Register, u32) {
+		match self {
+			Feature::Fpu => return ("fpu", Register::Edx, 0),
+			Feature::Vme => return ("vme", Register::Edx, 1),
+			Feature::De => return ("de", Register::Edx, 2),
+			Feature::Pse => return ("pse", Register::Edx, 3),
+			Feature::Tsc => return ("tsc", Register::Edx, 4),
+			Feature::Msr => return ("msr", Register::Edx, 5),
+			Feature::Pae => return ("pae", Register::Edx, 6),
+			Feature::Mce => return ("mce", Register::Edx, 7),
+			Feature::Cx8 => return ("cx8", Register::Edx, 8),
+			Feature::Apic => return ("apic", Register::Edx, 9),
+			Feature::Sep => return ("sep", Register::Edx, 11),
+			Feature::Mtrr => return ("mtrr", Register::Edx, 12),
+			Feature::Pge => return ("pge", Register::Edx, 13),
+			Feature::Mca => return ("mca", Register::Edx, 14),
+			Feature::Cmov => return ("cmov", Register::Edx, 15),
+			Feature::Pat => return ("pat", Register::Edx, 16),
+			Feature::Pse36 => return ("pse36", Register::Edx, 17),
+			Feature::Clflush => return ("clflush", Register::Edx, 19),
+			Feature::Mmx => return ("mmx", Register::Edx, 23),
+			Feature::Fxsr => return ("fxsr", Register::Edx, 24),
+			Feature::Sse => return ("sse", Register::Edx, 25),
+			Feature::Sse2 => return ("sse2", Register::Edx, 26),
+			Feature::Htt => return ("ht", Register::Edx, 28),
+			Feature::Sse3 => return ("pni", Register::Ecx, 0),
+			Feature::Pclmulqdq => return ("pclmulqdq", Register::Ecx, 1),
+			Feature::Ssse3 => return ("ssse3", Register::Ecx, 9),
+			Feature::Fma => return ("fma", Register::Ecx, 12),
+			Feature::Cx16 => return ("cx16", Register::Ecx, 13),
+			Feature::Sse41 => return ("sse4_1", Register::Ecx, 19),
+			Feature::Sse42 => return ("sse4_2", Register::Ecx, 20),
+			Feature::X2apic => return ("x2apic", Register::Ecx, 21),
+			Feature::Movbe => return ("movbe", Register::Ecx, 22),
+			Feature::Popcnt => return ("popcnt", Register::Ecx, 23),
+			Feature::Aes => return ("aes", Register::Ecx, 25),
+			Feature::Xsave => return ("xsave", Register::Ecx, 26),
+			Feature::Avx => return ("avx", Register::Ecx, 28),
+			Feature::F16c => return ("f16c", Register::Ecx, 29),
+			Feature::Rdrand => return ("rdrand", Register::Ecx, 30),
+			Feature::Hypervisor => return ("hypervisor", Register::Ecx, 31),
+		}
+	}
+}
+
+/// The raw feature flags of CPUID leaf 1.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CpuFeatures {
+	/// ECX of leaf 1.
+	pub ecx: u32,
+	/// EDX of leaf 1.
+	pub edx: u32,
+}
+
+impl CpuFeatures {
+	/// Reads the flags from the CPU. Without CPUID every flag is clear.
+	pub fn detect() -> Self {
+		match cpuid(1, 0) {
+			Some(result) => {
+				return CpuFeatures {
+					ecx: result.ecx,
+					edx: result.edx,
+				};
+			}
+			None => return CpuFeatures::default(),
+		}
+	}
+
+	/// Returns whether the CPU reports `feature`.
+	pub const fn has(&self, feature: Feature) -> bool {
+		let (_, register, bit) = feature.location();
+		let value = match register {
+			Register::Ecx => self.ecx,
+			Register::Edx => self.edx,
+		};
+
+		return value & (1 << bit) != 0;
+	}
+}
+
+/// Caches the feature flags so later queries do not execute CPUID.
+pub fn init_cpu_features() {
+	CPU_FEATURES.get_or_init(CpuFeatures::detect);
+}
+
+/// Returns the feature flags cached at boot, detecting them on first use.
+pub fn cpu_features() -> CpuFeatures {
+	return *CPU_FEATURES.get_or_init(CpuFeatures::detect);
+}
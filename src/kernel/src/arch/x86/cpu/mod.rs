@@ -1,5 +1,7 @@
 mod control;
+mod cpuid;
 mod reset;
 
 pub use control::*;
+pub use cpuid::*;
 pub use reset::*;
@@ -1,14 +1,54 @@
-use super::cpu::reboot;
+use super::{
+	backtrace::print_backtrace,
+	cpu::halt_loop,
+	page_fault,
+	pic::{self, IRQ_COUNT, PIC1_OFFSET},
+	tss,
+};
 use crate::{
-	arch::x86::cpu::{cr2, halt},
+	arch::x86::cpu::halt,
+	memory::{get_stack_guard, VirtAddr},
 	println, println_serial,
+	tty::{output, serial, status},
 };
+use core::{arch::asm, fmt, ptr};
+
+/// `println!` for the handlers. Writes straight to serial instead while the
+/// interrupted code holds a lock `println!` needs, waiting for it would
+/// never end.
+macro_rules! report {
+	($($arg:tt)*) => {
+		if output::is_locked() {
+			serial::print_unlocked(format_args!("{}\n", format_args!($($arg)*)));
+		} else {
+			println!($($arg)*);
+		}
+	};
+}
+
+/// `println_serial!` for the handlers, see [`report!`].
+macro_rules! report_serial {
+	($($arg:tt)*) => {
+		if serial::SERIAL.is_locked() {
+			serial::print_unlocked(format_args!("{}\n", format_args!($($arg)*)));
+		} else {
+			println_serial!($($arg)*);
+		}
+	};
+}
 
 pub type InterruptHandler = extern "x86-interrupt" fn(InterruptFrame);
 pub type InterruptHandlerWithError =
 	extern "x86-interrupt" fn(frame: InterruptFrame, _error_code: u32);
 
+/// Handles a CPU exception. Returning resumes the interrupted code with the
+/// registers in the context, changes included.
+pub type ExceptionHandler = fn(&mut ExceptionContext);
+
 /// CPU-pushed interrupt stack frame in 32-bit mode
+///
+/// `stack_pointer` and `stack_segment` are only pushed when the interrupt
+/// came from user mode.
 #[repr(C)]
 #[derive(Debug)]
 pub struct InterruptFrame {
@@ -19,215 +59,468 @@ pub struct InterruptFrame {
 	pub stack_segment: u32,
 }
 
-#[derive(Copy, Clone)]
-pub enum InterruptHandlerType {
-	Regular(InterruptHandler),
-	WithErrorCode(InterruptHandlerWithError),
-}
-
-pub static INTERRUPT_HANDLERS: [InterruptHandlerType; 21] = [
-	InterruptHandlerType::Regular(divide_by_zero_handler),
-	InterruptHandlerType::Regular(debug_interrupt_handler),
-	InterruptHandlerType::Regular(non_maskable_interrupt_handler),
-	InterruptHandlerType::Regular(breakpoint_handler),
-	InterruptHandlerType::Regular(overflow_handler),
-	InterruptHandlerType::Regular(bound_range_exceeded_handler),
-	InterruptHandlerType::Regular(invalid_opcode),
-	InterruptHandlerType::Regular(device_not_available),
-	InterruptHandlerType::WithErrorCode(double_fault),
-	InterruptHandlerType::Regular(coprocessor_segment_overrun),
-	InterruptHandlerType::WithErrorCode(invalid_tss),
-	InterruptHandlerType::WithErrorCode(segment_not_present),
-	InterruptHandlerType::WithErrorCode(stack_segment_fault),
-	InterruptHandlerType::WithErrorCode(general_protection_fault),
-	InterruptHandlerType::WithErrorCode(page_fault),
-	InterruptHandlerType::Regular(x87_floating_point),
-	InterruptHandlerType::WithErrorCode(alignment_check),
-	InterruptHandlerType::Regular(machine_check),
-	InterruptHandlerType::Regular(simd_floating_point),
-	InterruptHandlerType::Regular(virtualization),
-	InterruptHandlerType::WithErrorCode(security_exception),
-];
+/// The registers `exception_common` in `isr.asm` saves, in the order they
+/// are on the stack: the segment registers, then what `pusha` pushes.
+///
+/// `esp` is the stack pointer `pusha` saw, below the vector, see
+/// [`ExceptionContext::stack_pointer`] for the interrupted code's.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct Registers {
+	pub gs: u32,
+	pub fs: u32,
+	pub es: u32,
+	pub ds: u32,
+	pub edi: u32,
+	pub esi: u32,
+	pub ebp: u32,
+	pub esp: u32,
+	pub ebx: u32,
+	pub edx: u32,
+	pub ecx: u32,
+	pub eax: u32,
+}
 
-pub extern "x86-interrupt" fn divide_by_zero_handler(frame: InterruptFrame) {
-	println!("EXCEPTION: DIVIDE BY ZERO (#DE)");
-	println!("===============================");
+/// Everything on the stack when an exception handler runs, built by the
+/// entry stubs in `isr.asm`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct ExceptionContext {
+	pub regs: Registers,
+	pub vector: u32,
+	/// The CPU's error code, 0 for exceptions without one.
+	pub error_code: u32,
+	pub frame: InterruptFrame,
+}
 
-	println!("Instruction Pointer: 0x{:08x}", frame.instruction_pointer);
-	println!("Code Segment: 0x{:04x}", frame.code_segment);
-	println!("EFLAGS: 0x{:08x}", frame.eflags);
-	println!("Stack Pointer: 0x{:08x}", frame.stack_pointer);
-	println!("Stack Segment: 0x{:04x}", frame.stack_segment);
+/// Number of stack words a register dump shows.
+const STACK_DUMP_WORDS: usize = 8;
 
-	if frame.code_segment & 0x3 == 0 {
-		println!("CRITICAL: Divide by zero in kernel code!");
-		panic!("KERNEL PANIC: Cannot divide by zero in kernel mode");
+/// Bytes between the `esp` `pusha` saved and the interrupted code's stack:
+/// the vector, the error code and the frame pushed without a privilege
+/// change.
+const KERNEL_FRAME_SIZE: u32 = 5 * 4;
+
+impl ExceptionContext {
+	/// Returns whether the exception interrupted kernel code.
+	pub fn from_kernel(&self) -> bool {
+		return self.frame.code_segment & 0x3 == 0;
 	}
 
-	println!("User process attempted division by zero");
-	println!("Terminating process...");
+	/// Returns the stack pointer of the interrupted code.
+	pub fn stack_pointer(&self) -> u32 {
+		match self.from_kernel() {
+			true => return self.regs.esp + KERNEL_FRAME_SIZE,
+			false => return self.frame.stack_pointer,
+		}
+	}
 
-	halt();
+	/// Returns the stack segment of the interrupted code.
+	pub fn stack_segment(&self) -> u32 {
+		if !self.from_kernel() {
+			return self.frame.stack_segment;
+		}
+
+		// Kernel code runs on the stack the handler runs on
+		let ss: u16;
+		unsafe { asm!("mov {0:x}, ss", out(reg) ss, options(nomem, nostack)) };
+		return ss as u32;
+	}
+}
+
+impl fmt::Display for ExceptionContext {
+	/// Formats the registers in a fixed layout, followed by the top of the
+	/// stack for kernel code.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let regs = &self.regs;
+
+		writeln!(
+			f,
+			"EAX={:08x} EBX={:08x} ECX={:08x} EDX={:08x}",
+			regs.eax, regs.ebx, regs.ecx, regs.edx
+		)?;
+		writeln!(
+			f,
+			"ESI={:08x} EDI={:08x} EBP={:08x} ESP={:08x}",
+			regs.esi,
+			regs.edi,
+			regs.ebp,
+			self.stack_pointer()
+		)?;
+		writeln!(
+			f,
+			"EIP={:08x} EFLAGS={:08x} VECTOR={} ERROR={:08x}",
+			self.frame.instruction_pointer,
+			self.frame.eflags,
+			self.vector,
+			self.error_code
+		)?;
+		write!(
+			f,
+			"CS={:04x} DS={:04x} ES={:04x} FS={:04x} GS={:04x} SS={:04x}",
+			self.frame.code_segment & 0xffff,
+			regs.ds & 0xffff,
+			regs.es & 0xffff,
+			regs.fs & 0xffff,
+			regs.gs & 0xffff,
+			self.stack_segment() & 0xffff
+		)?;
+
+		// A user stack may not be mapped, the kernel one is the handler's own
+		if !self.from_kernel() {
+			return Ok(());
+		}
+
+		let stack: *const u32 =
+			ptr::with_exposed_provenance(self.stack_pointer() as usize);
+		for row in 0..STACK_DUMP_WORDS / 4 {
+			let address = unsafe { stack.add(row * 4) };
+			write!(f, "\n{:08x}:", address as usize)?;
+
+			for i in 0..4 {
+				let word = unsafe { address.add(i).read_volatile() };
+				write!(f, " {:08x}", word)?;
+			}
+		}
+
+		return Ok(());
+	}
 }
 
-pub extern "x86-interrupt" fn debug_interrupt_handler(frame: InterruptFrame) {
-	println!("EXCEPTION: DEBUG EXCEPTION (#DB)");
-	println!("===============================");
+/// Number of vectors reserved for CPU exceptions, the remapped IRQs follow.
+pub const EXCEPTION_COUNT: usize = 32;
 
-	println_serial!("{:?}", frame);
+extern "C" {
+	/// Addresses of the entry stubs in `isr.asm`, by vector.
+	#[link_name = "exception_stub_table"]
+	static EXCEPTION_STUBS: [usize; EXCEPTION_COUNT];
 }
 
-pub extern "x86-interrupt" fn non_maskable_interrupt_handler(
-	frame: InterruptFrame,
-) {
-	println!("Non-maskable interrupt (NMI)");
-	println_serial!("{:?}", frame);
+/// Returns the address of the entry stub for exception `vector`, which the
+/// IDT points at.
+pub fn exception_stub(vector: usize) -> usize {
+	return unsafe { EXCEPTION_STUBS[vector] };
 }
 
-pub extern "x86-interrupt" fn breakpoint_handler(frame: InterruptFrame) {
-	println!("Breakpoint exception (#BP)");
-	println_serial!("{:?}", frame);
+/// Handlers of the CPU exceptions, by vector. `None` for the reserved ones
+/// and those without a handler, which are reported as unhandled. A double
+/// fault never comes here, it switches to [`double_fault_task`].
+pub static EXCEPTION_HANDLERS: [Option<ExceptionHandler>; EXCEPTION_COUNT] = [
+	Some(divide_by_zero_handler),
+	Some(debug_interrupt_handler),
+	Some(non_maskable_interrupt_handler),
+	Some(breakpoint_handler),
+	Some(overflow_handler),
+	Some(bound_range_exceeded_handler),
+	Some(invalid_opcode),
+	Some(device_not_available),
+	None,
+	Some(coprocessor_segment_overrun),
+	Some(invalid_tss),
+	Some(segment_not_present),
+	Some(stack_segment_fault),
+	Some(general_protection_fault),
+	Some(page_fault),
+	None,
+	Some(x87_floating_point),
+	Some(alignment_check),
+	Some(machine_check),
+	Some(simd_floating_point),
+	Some(virtualization),
+	None,
+	None,
+	None,
+	None,
+	None,
+	None,
+	None,
+	None,
+	None,
+	Some(security_exception),
+	None,
+];
+
+/// Called by `exception_common` in `isr.asm` for every CPU exception.
+#[no_mangle]
+extern "C" fn exception_dispatch(context: &mut ExceptionContext) {
+	match EXCEPTION_HANDLERS
+		.get(context.vector as usize)
+		.copied()
+		.flatten()
+	{
+		Some(handler) => handler(context),
+		None => unhandled_exception(context),
+	}
 }
 
-pub extern "x86-interrupt" fn overflow_handler(frame: InterruptFrame) {
-	println!("Overflow exception (#OF)");
-	println_serial!("{:?}", frame);
+/// Row `$row` of [`DEFAULT_HANDLERS`], the stubs of vectors `$row * 16` up
+/// to `$row * 16 + 15`.
+macro_rules! default_handler_row {
+	($row:literal) => {
+		[
+			default_handler_stub::<{ $row * 16 }>,
+			default_handler_stub::<{ $row * 16 + 1 }>,
+			default_handler_stub::<{ $row * 16 + 2 }>,
+			default_handler_stub::<{ $row * 16 + 3 }>,
+			default_handler_stub::<{ $row * 16 + 4 }>,
+			default_handler_stub::<{ $row * 16 + 5 }>,
+			default_handler_stub::<{ $row * 16 + 6 }>,
+			default_handler_stub::<{ $row * 16 + 7 }>,
+			default_handler_stub::<{ $row * 16 + 8 }>,
+			default_handler_stub::<{ $row * 16 + 9 }>,
+			default_handler_stub::<{ $row * 16 + 10 }>,
+			default_handler_stub::<{ $row * 16 + 11 }>,
+			default_handler_stub::<{ $row * 16 + 12 }>,
+			default_handler_stub::<{ $row * 16 + 13 }>,
+			default_handler_stub::<{ $row * 16 + 14 }>,
+			default_handler_stub::<{ $row * 16 + 15 }>,
+		]
+	};
 }
 
-pub extern "x86-interrupt" fn bound_range_exceeded_handler(
+/// One stub per vector past the exceptions, by row of 16. The CPU does not
+/// tell a handler which vector it runs for, so each stub passes its own to
+/// [`unhandled_interrupt`].
+static DEFAULT_HANDLERS: [[InterruptHandler; 16]; 16 - EXCEPTION_COUNT / 16] = [
+	default_handler_row!(2),
+	default_handler_row!(3),
+	default_handler_row!(4),
+	default_handler_row!(5),
+	default_handler_row!(6),
+	default_handler_row!(7),
+	default_handler_row!(8),
+	default_handler_row!(9),
+	default_handler_row!(10),
+	default_handler_row!(11),
+	default_handler_row!(12),
+	default_handler_row!(13),
+	default_handler_row!(14),
+	default_handler_row!(15),
+];
+
+/// Returns the handler reporting an interrupt on `vector` that nothing
+/// handles, or `None` for the exceptions, which always go through
+/// [`exception_stub`].
+pub fn default_handler(vector: u8) -> Option<InterruptHandler> {
+	let index = (vector as usize).checked_sub(EXCEPTION_COUNT)?;
+
+	return Some(DEFAULT_HANDLERS[index / 16][index % 16]);
+}
+
+extern "x86-interrupt" fn default_handler_stub<const VECTOR: u8>(
 	frame: InterruptFrame,
 ) {
-	println!("BOUND range exceeded exception (#BR)");
-	println_serial!("{:?}", frame);
+	unhandled_interrupt(VECTOR, &frame);
 }
 
-pub extern "x86-interrupt" fn invalid_opcode(frame: InterruptFrame) {
-	println!("Invalid opcode exception (#UD)");
-	println_serial!("{:?}", frame);
+/// Reports an exception without a handler, which is fatal.
+fn unhandled_exception(context: &mut ExceptionContext) {
+	take_output();
+	status::release();
+	report!(
+		"EXCEPTION: UNHANDLED VECTOR {} (0x{:02x})",
+		context.vector,
+		context.vector
+	);
+	report!("===============================");
+	report_context(context);
+
+	panic!("KERNEL PANIC: Unhandled exception {}", context.vector);
 }
 
-pub extern "x86-interrupt" fn device_not_available(frame: InterruptFrame) {
-	println!("Device not available exception (#NM)");
-	println_serial!("{:?}", frame);
+/// Reports an interrupt past the exceptions without a handler.
+///
+/// A hardware IRQ is acknowledged and masked, so the line cannot keep
+/// firing, unless the PIC raised it spuriously. Anything else comes from an
+/// `int` instruction and returns to it.
+fn unhandled_interrupt(vector: u8, frame: &InterruptFrame) {
+	let irqs = PIC1_OFFSET..PIC1_OFFSET + IRQ_COUNT as u8;
+
+	if irqs.contains(&vector) {
+		let irq = vector - PIC1_OFFSET;
+		if pic::handle_spurious(irq) {
+			return;
+		}
+
+		report_serial!(
+			"Unhandled IRQ {} (vector 0x{:02x}), masking it",
+			irq,
+			vector
+		);
+		report_serial!("{:?}", frame);
+		pic::set_mask(irq);
+		pic::send_eoi(irq);
+	} else {
+		report!("Unhandled interrupt 0x{:02x} (not a hardware IRQ)", vector);
+		report_serial!("{:?}", frame);
+	}
 }
 
-pub extern "x86-interrupt" fn double_fault(
-	frame: InterruptFrame,
-	_error_code: u32,
-) {
-	println!("Double fault exception (#DF)");
-	println_serial!("{:?}", frame);
+/// Prints the register dump of a fatal exception on screen and on serial.
+fn report_context(context: &ExceptionContext) {
+	report!("{}", context);
+	report_serial!("{}", context);
+}
 
-	reboot();
+/// Breaks the output locks before a fatal report, so it shows on screen even
+/// if the interrupted code was printing.
+fn take_output() {
+	// Safety: the handlers calling this never return to the interrupted code.
+	unsafe { output::force_unlock() };
 }
 
-pub extern "x86-interrupt" fn coprocessor_segment_overrun(
-	frame: InterruptFrame,
-) {
-	println!("Coprocessor segment overrun");
-	println_serial!("{:?}", frame);
+pub fn divide_by_zero_handler(context: &mut ExceptionContext) {
+	take_output();
+	status::release();
+	report!("EXCEPTION: DIVIDE BY ZERO (#DE)");
+	report!("===============================");
+	report_context(context);
+
+	if context.from_kernel() {
+		report!("CRITICAL: Divide by zero in kernel code!");
+		panic!("KERNEL PANIC: Cannot divide by zero in kernel mode");
+	}
+
+	report!("User process attempted division by zero");
+	report!("Terminating process...");
+
+	halt();
 }
 
-pub extern "x86-interrupt" fn invalid_tss(
-	frame: InterruptFrame,
-	_error_code: u32,
-) {
-	println!("Invalid TSS exception (#TS)");
-	println_serial!("{:?}", frame);
+pub fn debug_interrupt_handler(context: &mut ExceptionContext) {
+	report!("EXCEPTION: DEBUG EXCEPTION (#DB)");
+	report!("===============================");
+
+	report_serial!("{}", context);
 }
 
-pub extern "x86-interrupt" fn segment_not_present(
-	frame: InterruptFrame,
-	_error_code: u32,
-) {
-	println!("Segment not present exception (#NP)");
-	println_serial!("{:?}", frame);
+pub fn non_maskable_interrupt_handler(context: &mut ExceptionContext) {
+	report!("Non-maskable interrupt (NMI)");
+	report_serial!("{}", context);
 }
 
-pub extern "x86-interrupt" fn stack_segment_fault(
-	frame: InterruptFrame,
-	_error_code: u32,
-) {
-	println!("Stack-segment fault (#SS)");
-	println_serial!("{:?}", frame);
+pub fn breakpoint_handler(context: &mut ExceptionContext) {
+	report!("Breakpoint exception (#BP)");
+	report_serial!("{}", context);
 }
 
-pub extern "x86-interrupt" fn general_protection_fault(
-	frame: InterruptFrame,
-	_error_code: u32,
-) {
-	println!("EXCEPTION: GENERAL PROTECTION FAULT (#GP)");
-	println!("===============================");
+pub fn overflow_handler(context: &mut ExceptionContext) {
+	report!("Overflow exception (#OF)");
+	report_serial!("{}", context);
+}
 
-	println!("Error Code: 0x{:04x}", _error_code);
-	println!("Debug information: {:?}", frame);
-	println_serial!("Debug information: {:?}", frame);
+pub fn bound_range_exceeded_handler(context: &mut ExceptionContext) {
+	report!("BOUND range exceeded exception (#BR)");
+	report_serial!("{}", context);
+}
 
-	halt();
+pub fn invalid_opcode(context: &mut ExceptionContext) {
+	report!("Invalid opcode exception (#UD)");
+	report_context(context);
 }
 
-pub extern "x86-interrupt" fn page_fault(
-	frame: InterruptFrame,
-	error_code: u32,
-) {
-	println_serial!("EXCEPTION: PAGE FAULT");
-	println_serial!("=====================");
+pub fn device_not_available(context: &mut ExceptionContext) {
+	report!("Device not available exception (#NM)");
+	report_serial!("{}", context);
+}
 
-	let faulting_address = cr2();
+/// Runs as the double fault task, on a stack of its own, with the error code
+/// the CPU pushed. The registers of the code that faulted are in the
+/// kernel's TSS.
+///
+/// There is nothing to return to, the stack of the interrupted code may be
+/// gone, so it halts after the report.
+#[no_mangle]
+extern "C" fn double_fault_task(error_code: u32) -> ! {
+	take_output();
+	status::release();
+
+	let task = tss::interrupted_task();
+	report!("EXCEPTION: DOUBLE FAULT (#DF)");
+	report!("===============================");
+	report!("{}", task);
+	report_serial!("{}", task);
+	report!("Error code {:08x}, old ESP {:08x}", error_code, task.esp);
+	report_serial!("Error code {:08x}, old ESP {:08x}", error_code, task.esp);
+
+	let overflow =
+		get_stack_guard().contains(&VirtAddr::new(task.esp as usize));
+	if overflow {
+		report!("CRITICAL: Kernel stack overflow");
+		report_serial!("CRITICAL: Kernel stack overflow");
+	}
+	print_backtrace(task.ebp as usize);
 
-	println_serial!("Faulting Address: 0x{:x}", faulting_address.as_usize());
-	println_serial!("Error Code: 0x{:04x}", error_code);
+	#[cfg(all(test, feature = "double-fault-test"))]
+	crate::tests::unit::double_fault_tests::double_fault_reported(
+		&task, overflow,
+	);
 
-	if (error_code & 1) == 0 {
-		println_serial!("  - Reason: Page not present.");
-	} else {
-		println_serial!("  - Reason: Protection violation.");
-	}
+	halt_loop();
+}
 
-	// You can also interpret the error code bits for more info:
-	// bit 0: 0 = Supervisor, 1 = User mode
-	// bit 1: 0 = Read, 1 = Write
-	// bit 2: 0 = Not present, 1 = Protection violation
-	// ... and others
+pub fn coprocessor_segment_overrun(context: &mut ExceptionContext) {
+	report!("Coprocessor segment overrun");
+	report_serial!("{}", context);
+}
+
+pub fn invalid_tss(context: &mut ExceptionContext) {
+	report!("Invalid TSS exception (#TS)");
+	report_serial!("{}", context);
+}
+
+pub fn segment_not_present(context: &mut ExceptionContext) {
+	report!("Segment not present exception (#NP)");
+	report_serial!("{}", context);
+}
+
+pub fn stack_segment_fault(context: &mut ExceptionContext) {
+	report!("Stack-segment fault (#SS)");
+	report_serial!("{}", context);
+}
+
+pub fn general_protection_fault(context: &mut ExceptionContext) {
+	take_output();
+	status::release();
+	report!("EXCEPTION: GENERAL PROTECTION FAULT (#GP)");
+	report!("===============================");
+	report_context(context);
+	print_backtrace(context.regs.ebp as usize);
 
-	println_serial!("Interrupt Frame: {:?}", frame);
-	println_serial!("Halting.");
 	halt();
 }
 
-pub extern "x86-interrupt" fn x87_floating_point(frame: InterruptFrame) {
-	println!("x87 floating-point exception (#MF)");
-	println_serial!("{:?}", frame);
+pub fn page_fault(context: &mut ExceptionContext) {
+	page_fault::handle(context);
 }
 
-pub extern "x86-interrupt" fn alignment_check(
-	frame: InterruptFrame,
-	_error_code: u32,
-) {
-	println!("Alignment check exception (#AC)");
-	println_serial!("{:?}", frame);
+pub fn x87_floating_point(context: &mut ExceptionContext) {
+	report!("x87 floating-point exception (#MF)");
+	report_serial!("{}", context);
 }
 
-pub extern "x86-interrupt" fn machine_check(frame: InterruptFrame) {
-	println!("Machine check exception (#MC)");
-	println_serial!("{:?}", frame);
+pub fn alignment_check(context: &mut ExceptionContext) {
+	report!("Alignment check exception (#AC)");
+	report_serial!("{}", context);
 }
 
-pub extern "x86-interrupt" fn simd_floating_point(frame: InterruptFrame) {
-	println!("SIMD floating-point exception (#XM)");
+pub fn machine_check(context: &mut ExceptionContext) {
+	report!("Machine check exception (#MC)");
+	report_serial!("{}", context);
+}
+
+pub fn simd_floating_point(context: &mut ExceptionContext) {
+	report!("SIMD floating-point exception (#XM)");
 
-	println_serial!("{:?}", frame);
+	report_serial!("{}", context);
 }
 
-pub extern "x86-interrupt" fn virtualization(frame: InterruptFrame) {
-	println!("Virtualization exception (#VE)");
-	println_serial!("{:?}", frame);
+pub fn virtualization(context: &mut ExceptionContext) {
+	report!("Virtualization exception (#VE)");
+	report_serial!("{}", context);
 }
 
-pub extern "x86-interrupt" fn security_exception(
-	frame: InterruptFrame,
-	_error_code: u32,
-) {
-	println!("Security exception (#SX)");
-	println_serial!("{:?}", frame);
+pub fn security_exception(context: &mut ExceptionContext) {
+	report!("Security exception (#SX)");
+	report_serial!("{}", context);
 }
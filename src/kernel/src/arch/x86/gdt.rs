@@ -6,8 +6,9 @@
 //! For more information go to:
 //! <https://wiki.osdev.org/Global_Descriptor_Table>
 
-use super::DescriptorTable;
+use super::{tss, DescriptorTable};
 use crate::arch::x86::diagnostics::cpu::check_protection_status;
+use core::{arch::asm, ptr};
 
 extern "C" {
 	// src/arch/{target}/gdt.asm
@@ -20,14 +21,23 @@ extern "C" {
 #[repr(C, align(8))]
 pub struct Gate(pub u64);
 
-/// Represents the complete Global Descriptor Table containing 5 descriptor
+/// Represents the complete Global Descriptor Table containing 7 descriptor
 /// entries:
 /// - Entry 0: Null Descriptor (required by CPU)
 /// - Entry 1: Kernel Code Segment
 /// - Entry 2: Kernel Data Segment
 /// - Entry 3: User Code Segment
 /// - Entry 4: User Data Segment
-pub type GdtGates = [Gate; 5];
+/// - Entry 5: Kernel TSS, see [`tss::TSS_SELECTOR`]
+/// - Entry 6: Double fault TSS, see [`tss::DOUBLE_FAULT_TSS_SELECTOR`]
+pub type GdtGates = [Gate; 7];
+
+/// Access byte of an available 32-bit TSS: present, ring 0, system segment
+/// of type 0x9.
+pub const TSS_AVAILABLE: u8 = 0b10001001;
+
+/// Type bit the CPU sets in the access byte of a TSS while its task runs.
+pub const TSS_BUSY: u8 = 0b00000010;
 
 #[doc(hidden)]
 impl Gate {
@@ -48,6 +58,17 @@ impl Gate {
 		return c;
 	}
 
+	/// Creates the descriptor of a 32-bit TSS at `base`, with byte
+	/// granularity.
+	pub const fn tss(base: u32, limit: u32) -> Self {
+		return Self::new(base, limit, TSS_AVAILABLE, 0b0000);
+	}
+
+	/// Returns whether the descriptor is a TSS whose task is running.
+	pub fn is_busy_tss(&mut self) -> bool {
+		return self.access() == TSS_AVAILABLE | TSS_BUSY;
+	}
+
 	#[inline]
 	pub fn base(&mut self) -> u32 {
 		return (((self.0 >> 16) & 0xffffff) | (((self.0 >> 56) & 0xff) << 24))
@@ -102,12 +123,14 @@ impl Gate {
 
 #[no_mangle]
 #[link_section = ".gdt"]
-static GDT_ENTRIES: GdtGates = [
+static mut GDT_ENTRIES: GdtGates = [
 	Gate(0), // [0] Null Descriptor (CPU requirement)
 	Gate::new(0, !0, 0b10011010, 0b1100), // [1] Kernel Code: Ring 0, executable
 	Gate::new(0, !0, 0b10010010, 0b1100), // [2] Kernel Data: Ring 0, writable
 	Gate::new(0, !0, 0b11111010, 0b1100), // [3] User Code: Ring 3, executable
 	Gate::new(0, !0, 0b11110010, 0b1100), // [4] User Data: Ring 3, writable
+	Gate(0), // [5] Kernel TSS, set by gdt_init
+	Gate(0), // [6] Double fault TSS, set by gdt_init
 ];
 
 /// Initializes the Global Descriptor Table (GDT) for the system.
@@ -123,12 +146,55 @@ pub fn gdt_init() {
 
 	let gdt_descriptor = DescriptorTable {
 		size: (size_of::<GdtGates>() - 1) as u16,
-		offset: &GDT_ENTRIES as *const _ as u32,
+		offset: &raw const GDT_ENTRIES as *const _ as u32,
 	};
 
 	unsafe {
+		// The TSS addresses are only known at link time
+		GDT_ENTRIES[5] =
+			Gate::tss(tss::tss_address(), tss::TaskStateSegment::LIMIT);
+		GDT_ENTRIES[6] = Gate::tss(
+			tss::double_fault_tss_address(),
+			tss::TaskStateSegment::LIMIT,
+		);
+
 		gdt_flush(&gdt_descriptor as *const _);
 	}
+	tss::init();
 
 	check_protection_status();
 }
+
+/// Returns the limit and the base address of the loaded GDT, as `sgdt`
+/// stores them.
+pub fn gdtr() -> (u16, u32) {
+	let mut gdtr = DescriptorTable {
+		size: 0,
+		offset: 0,
+	};
+
+	// Safety: sgdt only writes the 6 bytes of the descriptor.
+	unsafe {
+		asm!("sgdt [{}]", in(reg) &mut gdtr, options(nostack, preserves_flags));
+	}
+
+	let DescriptorTable {
+		size,
+		offset,
+	} = gdtr;
+	return (size, offset);
+}
+
+/// Reads entry `index` of the loaded GDT, `None` if the table ends before
+/// it.
+pub fn read_descriptor(index: usize) -> Option<Gate> {
+	let (limit, base) = gdtr();
+	if (index + 1) * size_of::<Gate>() > limit as usize + 1 {
+		return None;
+	}
+
+	let gate: *const u64 =
+		ptr::with_exposed_provenance(base as usize + index * size_of::<Gate>());
+	// Safety: the entry is inside the table the CPU uses.
+	return Some(Gate(unsafe { gate.read_volatile() }));
+}
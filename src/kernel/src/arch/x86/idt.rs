@@ -12,18 +12,36 @@
 use super::exceptions::{self, InterruptHandler, InterruptHandlerWithError};
 use crate::{
 	arch::x86::{
-		exceptions::{InterruptHandlerType, INTERRUPT_HANDLERS},
+		exceptions::{default_handler, exception_stub},
+		tss::DOUBLE_FAULT_TSS_SELECTOR,
 		DescriptorTable,
 	},
 	println_serial,
 	sync::Mutex,
 };
 use core::arch::asm;
-use lazy_static::lazy_static;
 
 #[doc(hidden)]
 pub const IDT_ENTRY_COUNT: usize = 256;
 
+/// Vector of the double fault exception, the one served by a task gate.
+pub const DOUBLE_FAULT_VECTOR: usize = 8;
+
+/// Vector of `int 0x80`, the one gate user mode may call, see
+/// [`crate::syscall`].
+pub const SYSCALL_VECTOR: usize = 0x80;
+
+/// Gate privilege level only the kernel may `int` through.
+pub const KERNEL_DPL: u8 = 0;
+
+/// Gate privilege level user mode may `int` through too.
+pub const USER_DPL: u8 = 3;
+
+extern "C" {
+	// src/arch/{target}/isr.asm
+	fn syscall_stub();
+}
+
 /// An Interrupt Descriptor Table entry.
 ///
 /// The generic parameter can either be `HandlerFunc` or
@@ -50,25 +68,57 @@ impl InterruptDescriptorEntry {
 	}
 
 	/// Configures an IDT entry with the specified interrupt handler
-	pub fn set_handler(&mut self, handler: InterruptHandler) {
-		self.pointer_low = (handler as usize & 0xffff) as u16;
-		self.selector = 0x08;
-		self.zero = 0;
-		self.type_attributes = 0b1000_1110;
-		self.pointer_high = ((handler as usize >> 16) & 0xffff) as u16;
+	///
+	/// `dpl` is the lowest privilege level allowed to call the gate with
+	/// `int`, [`KERNEL_DPL`] or [`USER_DPL`]. Hardware interrupts and
+	/// exceptions ignore it.
+	pub fn set_handler(&mut self, handler: InterruptHandler, dpl: u8) {
+		self.set_address(handler as usize, dpl);
 	}
 
 	/// Configures an IDT entry with the specified interrupt handler & error
-	/// code
+	/// code, see [`InterruptDescriptorEntry::set_handler`] for `dpl`.
 	pub fn set_handler_with_error_code(
 		&mut self,
 		handler: InterruptHandlerWithError,
+		dpl: u8,
 	) {
-		self.pointer_low = (handler as usize & 0xffff) as u16;
+		self.set_address(handler as usize, dpl);
+	}
+
+	/// Configures an IDT entry to jump to `address`, for entry stubs written
+	/// in assembly. See [`InterruptDescriptorEntry::set_handler`] for `dpl`.
+	pub fn set_address(&mut self, address: usize, dpl: u8) {
+		self.pointer_low = (address & 0xffff) as u16;
 		self.selector = 0x08;
 		self.zero = 0;
-		self.type_attributes = 0b1000_1110;
-		self.pointer_high = ((handler as usize >> 16) & 0xffff) as u16;
+		self.type_attributes = 0b1000_1110 | ((dpl & 0x3) << 5);
+		self.pointer_high = ((address >> 16) & 0xffff) as u16;
+	}
+
+	/// Returns the privilege level the gate was set up with.
+	pub fn privilege_level(&self) -> u8 {
+		return (self.type_attributes >> 5) & 0x3;
+	}
+
+	/// Configures an IDT entry as a task gate, switching to the task of the
+	/// TSS at `selector` in the GDT.
+	pub fn set_task_gate(&mut self, selector: u16) {
+		self.pointer_low = 0;
+		self.selector = selector;
+		self.zero = 0;
+		self.type_attributes = 0b1000_0101;
+		self.pointer_high = 0;
+	}
+
+	/// Returns whether the entry is a task gate.
+	pub fn is_task_gate(&self) -> bool {
+		return self.type_attributes & 0x1f == 0b0_0101;
+	}
+
+	/// Returns the segment selector of the entry.
+	pub fn selector(&self) -> u16 {
+		return self.selector;
 	}
 }
 
@@ -91,17 +141,25 @@ pub static mut IDT_ENTRIES: [InterruptDescriptorEntry; IDT_ENTRY_COUNT] =
 pub fn idt_init() {
 	use core::mem::size_of;
 	unsafe {
-		for i in 0..INTERRUPT_HANDLERS.len() {
-			match INTERRUPT_HANDLERS[i] {
-				InterruptHandlerType::Regular(handler) => {
-					IDT_ENTRIES[i].set_handler(handler);
-				}
-				InterruptHandlerType::WithErrorCode(handler) => {
-					IDT_ENTRIES[i].set_handler_with_error_code(handler);
-				}
+		// Every vector gets a handler, a stray one reports itself instead of
+		// triple faulting on an empty gate
+		let entries = &raw mut IDT_ENTRIES;
+		for vector in 0..IDT_ENTRY_COUNT {
+			let entry = &mut (*entries)[vector];
+
+			match default_handler(vector as u8) {
+				Some(handler) => entry.set_handler(handler, KERNEL_DPL),
+				None => entry.set_address(exception_stub(vector), KERNEL_DPL),
 			}
 		}
 
+		// The stack a double fault happens on may be the reason for it, so
+		// it switches to a task with a stack of its own
+		(*entries)[DOUBLE_FAULT_VECTOR]
+			.set_task_gate(DOUBLE_FAULT_TSS_SELECTOR);
+
+		(*entries)[SYSCALL_VECTOR].set_address(syscall_stub as usize, USER_DPL);
+
 		let idt_descriptor = DescriptorTable {
 			size: (size_of::<[InterruptDescriptorEntry; IDT_ENTRY_COUNT]>() - 1)
 				as u16,
@@ -26,7 +26,7 @@ pub fn inw(addr: u16) -> u16 {
 	let mut out: u16;
 
 	unsafe {
-		asm!("in al, dx", out("ax") out, in("dx") addr, options(nomem, nostack, preserves_flags));
+		asm!("in ax, dx", out("ax") out, in("dx") addr, options(nomem, nostack, preserves_flags));
 	}
 
 	return out;
@@ -46,7 +46,7 @@ pub fn inl(addr: u16) -> u32 {
 	let mut out: u32;
 
 	unsafe {
-		asm!("in al, dx", out("eax") out, in("dx") addr, options(nomem, nostack, preserves_flags));
+		asm!("in eax, dx", out("eax") out, in("dx") addr, options(nomem, nostack, preserves_flags));
 	}
 
 	return out;
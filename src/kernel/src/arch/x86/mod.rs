@@ -1,7 +1,11 @@
+pub mod backtrace;
 pub mod gdt;
 pub mod idt;
 pub mod multiboot;
+pub mod page_fault;
 pub mod pic;
+pub mod tss;
+pub mod usermode;
 
 /* -------------------------------------- */
 
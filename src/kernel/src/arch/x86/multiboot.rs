@@ -2,12 +2,17 @@
 //! information structure provided by the bootloader.
 
 use crate::{
+	collections::array_vec::ArrayVec,
+	log_warn,
 	memory::{
-		get_kernel_physical_end, MemorySegment, PhysAddr, RegionType, PAGE_SIZE,
+		get_kernel_physical_end,
+		paging::{phys_to_virt, translate},
+		MemorySegment, PhysAddr, RegionType, VirtAddr, PAGE_SIZE,
 	},
 	println_serial,
-	sync::{mutex::MutexGuard, Locked},
+	sync::{OnceLock, RwLock},
 };
+use core::{ffi::CStr, mem::size_of, ptr, str::from_utf8};
 
 #[allow(missing_docs)]
 #[cfg(target_arch = "x86")]
@@ -16,7 +21,7 @@ pub struct MultibootMmapEntry {
 	pub size: u32,
 	pub addr: u64,
 	pub len: u64,
-	pub entry_type: RegionType,
+	pub entry_type: u32,
 }
 
 #[cfg(target_arch = "x86_64")]
@@ -111,114 +116,452 @@ pub struct MultibootInfo {
 	/// Address of APM (Advanced Power Management) table.
 	/// Only valid if flags[10] is set.
 	apm_table: u32,
+
+	/// VBE control and mode information.
+	/// Only valid if flags[11] is set.
+	vbe_control_info: u32,
+	vbe_mode_info: u32,
+	vbe_mode: u16,
+	vbe_interface_seg: u16,
+	vbe_interface_off: u16,
+	vbe_interface_len: u16,
+
+	/// Physical address of the framebuffer.
+	/// Only valid if flags[12] is set, as are the other framebuffer fields.
+	framebuffer_addr: u64,
+
+	/// Bytes between the start of two pixel rows.
+	framebuffer_pitch: u32,
+
+	/// Width in pixels, or in characters for EGA text.
+	framebuffer_width: u32,
+
+	/// Height in pixels, or in characters for EGA text.
+	framebuffer_height: u32,
+
+	/// Bits per pixel.
+	framebuffer_bpp: u8,
+
+	/// 0 for an indexed palette, 1 for direct RGB, 2 for EGA text.
+	framebuffer_type: u8,
+
+	/// For direct RGB: position and size in bits of the red, green and blue
+	/// fields.
+	framebuffer_colour_info: [u8; 6],
 }
 
-/// Global static storage for the parsed memory map segments.
-///
-/// Initialized once during boot by `get_memory_region`. Access should be
-/// synchronized via the `Locked` wrapper. Maximum of 16 segments stored.
-// Note: lazy_static might be needed if Locked::new isn't const, or use
-// OnceCell. Assuming Locked::new is const based on previous context.
-// lazy_static! { // Use lazy_static if Locked::new() is not const
-pub static G_SEGMENTS: Locked<[MemorySegment; 16]> =
-	Locked::new([MemorySegment::empty(); 16]);
-
-/// Parses the Multiboot memory map and populates the provided `segments` array.
-///
-/// Iterates through the memory map entries provided by the `boot_info`
-/// structure, converts them into `MemorySegment` representations, and stores
-/// them in the `segments` slice. It reserves the region starting at physical
-/// address 0x0.
-///
-/// # Arguments
-/// * `segments` - A mutable array slice to be filled with parsed
-///   `MemorySegment` data. Must have a size of at least 16
-///   (`MAX_MEMORY_SEGMENTS`).
-/// * `boot_info` - A reference to the `MultibootInfo` structure provided by the
-///   bootloader.
-///
-/// # Panics
-/// Panics if the bootloader information does not contain a valid memory map
-/// (`flags` bit 6 not set), or if no memory regions are found in the map.
-#[allow(clippy::expect_used)]
-pub fn get_memory_region(boot_info: &MultibootInfo) {
-	use core::{mem, ptr};
+/// A module the bootloader loaded along with the kernel.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MultibootModule {
+	/// Physical address of the first byte of the module.
+	pub mod_start: u32,
+	/// Physical address after the last byte of the module.
+	pub mod_end: u32,
+	/// Physical address of the module's command line.
+	pub string: u32,
+	reserved: u32,
+}
 
-	if (boot_info.flags & (1 << 6)) == 0 {
-		panic!("CRITICAL: Bootloader did not provide a memory map!");
+impl MultibootModule {
+	/// Returns the command line the bootloader gave the module, the path of
+	/// its file with GRUB. Empty if there is none or it is not UTF-8.
+	///
+	/// It lies in memory the allocators hand out, [`save_modules`] copies it.
+	pub fn cmdline(&self) -> &'static str {
+		if self.string == 0 {
+			return "";
+		}
+		return c_string(self.string).unwrap_or("");
 	}
+}
 
-	let mut count = 0;
-	let mut mmap = boot_info.mmap_addr as usize;
-	let mmap_end = (boot_info.mmap_addr + boot_info.mmap_length) as usize;
-
-	while mmap < mmap_end {
-		unsafe {
-			let entry = (ptr::with_exposed_provenance_mut(mmap)
-				as *const MultibootMmapEntry)
-				.as_ref()
-				.expect("Failed to read memory map entry");
+// Helper reading the NUL terminated string the bootloader left at physical
+// address `addr`, `None` if it is not UTF-8
+fn c_string(addr: u32) -> Option<&'static str> {
+	let string: *const u8 = phys_to_virt(PhysAddr::new(addr as usize)).as_ptr();
+	// Safety: the bootloader strings are in the low memory the kernel maps.
+	let string = unsafe { CStr::from_ptr(string.cast()) };
+	return string.to_str().ok();
+}
 
-			// Ignore everything under 1Mb
-			if entry.addr < 0x100000 {
-				mmap += (entry.size as usize) + mem::size_of::<u32>();
-				continue;
-			}
+/// Most boot modules kept, see [`save_modules`].
+pub const MAX_BOOT_MODULES: usize = 16;
+
+/// Bytes kept of the module command lines, all of them together.
+pub const MODULE_CMDLINES_SIZE: usize = 1024;
+
+/// A module the bootloader loaded along with the kernel, see
+/// [`boot_modules`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Module {
+	/// Physical address of the first byte.
+	pub start: PhysAddr,
+	/// Physical address after the last byte.
+	pub end: PhysAddr,
+	/// The command line the bootloader gave it, copied at boot.
+	pub cmdline: &'static str,
+}
 
-			let phys_addr = get_kernel_physical_end();
-			let mut addr = PhysAddr::new(entry.addr as usize);
-			let mut len = entry.len as usize;
+impl Module {
+	/// Returns the size of the module in bytes.
+	pub fn len(&self) -> usize {
+		return self.end.as_usize().saturating_sub(self.start.as_usize());
+	}
 
-			if addr < phys_addr {
-				addr = phys_addr + PAGE_SIZE;
-				len = (entry.addr + entry.len) as usize - addr.as_usize();
-			}
+	/// Returns whether the module is empty.
+	pub fn is_empty(&self) -> bool {
+		return self.len() == 0;
+	}
 
-			G_SEGMENTS.lock()[count] =
-				MemorySegment::new(addr, len, entry.entry_type);
+	/// Returns the module's bytes through the kernel mapping of physical
+	/// memory, `None` if part of it is not mapped there.
+	pub fn bytes(&self) -> Option<&'static [u8]> {
+		let start = phys_to_virt(self.start);
+		let pages =
+			start.as_usize() & !(PAGE_SIZE - 1)..start.as_usize() + self.len();
+		if pages
+			.step_by(PAGE_SIZE)
+			.any(|page| translate(VirtAddr::new(page)).is_none())
+		{
+			return None;
+		}
 
-			let base_addr = entry.addr as usize;
-			let length = entry.len as usize;
-			let entry_type = entry.entry_type;
+		// Safety: the module is reserved at boot and every page is mapped.
+		return Some(unsafe {
+			core::slice::from_raw_parts(start.as_ptr(), self.len())
+		});
+	}
+}
 
-			println_serial!(
-				"  Entry {}: Base=0x{:08x}, Length=0x{:08x} ({} bytes), Type={:?}",
-				count,
-				base_addr,
-				length,
-				length,
-				entry_type,
+static MODULE_CMDLINES: OnceLock<ArrayVec<u8, MODULE_CMDLINES_SIZE>> =
+	OnceLock::new();
+static BOOT_MODULES: OnceLock<ArrayVec<Module, MAX_BOOT_MODULES>> =
+	OnceLock::new();
+
+/// Keeps the module list of `boot_info` for [`boot_modules`], with copies
+/// of the command lines. Has to run before the allocators are set up, they
+/// only keep the modules themselves reserved. Modules past
+/// [`MAX_BOOT_MODULES`] are dropped, and command lines past
+/// [`MODULE_CMDLINES_SIZE`] bytes left empty, with a warning.
+pub fn save_modules(boot_info: &MultibootInfo) {
+	let modules = boot_info.modules();
+	if modules.len() > MAX_BOOT_MODULES {
+		log_warn!(
+			"multiboot: {} modules, keeping the first {}",
+			modules.len(),
+			MAX_BOOT_MODULES
+		);
+	}
+	let modules = &modules[..modules.len().min(MAX_BOOT_MODULES)];
+
+	let mut cmdlines = ArrayVec::<u8, MODULE_CMDLINES_SIZE>::new();
+	let mut ranges = [const { 0..0 }; MAX_BOOT_MODULES];
+	for (module, range) in modules.iter().zip(ranges.iter_mut()) {
+		let cmdline = module.cmdline().as_bytes();
+		if cmdlines.len() + cmdline.len() > cmdlines.capacity() {
+			log_warn!(
+				"multiboot: no room left for module '{}'",
+				module.cmdline()
 			);
+			continue;
+		}
 
-			count += 1;
-			mmap += (entry.size as usize) + mem::size_of::<u32>();
+		let start = cmdlines.len();
+		for &byte in cmdline {
+			let _ = cmdlines.push(byte);
 		}
+		*range = start..cmdlines.len();
 	}
 
-	if count == 0 {
-		panic!("Could not find any memory regions in map (or map was empty)!");
+	let cmdlines = MODULE_CMDLINES.get_or_init(|| cmdlines);
+	let mut saved = ArrayVec::new();
+	for (module, range) in modules.iter().zip(ranges) {
+		let _ = saved.push(Module {
+			start: PhysAddr::new(module.mod_start as usize),
+			end: PhysAddr::new(module.mod_end as usize),
+			cmdline: from_utf8(&cmdlines[range]).unwrap_or(""),
+		});
 	}
+	let _ = BOOT_MODULES.set(saved);
 }
 
-pub fn get_biggest_available_segment_index() -> Option<usize> {
-	let segments_guard = G_SEGMENTS.lock();
-	let segments = &*segments_guard;
+/// Returns the modules the bootloader loaded, empty before
+/// [`save_modules`].
+pub fn boot_modules() -> &'static [Module] {
+	return BOOT_MODULES.get().map_or(&[], |modules| modules.as_slice());
+}
+
+/// Framebuffer type of direct RGB pixels.
+const FRAMEBUFFER_TYPE_RGB: u8 = 1;
+
+/// Position and size in bits of one colour channel in a pixel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColourField {
+	/// Index of the lowest bit.
+	pub position: u8,
+	/// Number of bits.
+	pub size: u8,
+}
 
-	let mut biggest_index: Option<usize> = None;
-	let mut current_max_size: usize = 0;
+/// A linear RGB framebuffer set up by the bootloader.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramebufferInfo {
+	/// Physical address of the first pixel.
+	pub addr: PhysAddr,
+	/// Bytes between the start of two pixel rows.
+	pub pitch: usize,
+	/// Width in pixels.
+	pub width: usize,
+	/// Height in pixels.
+	pub height: usize,
+	/// Bits per pixel.
+	pub bpp: u8,
+	/// Red channel.
+	pub red: ColourField,
+	/// Green channel.
+	pub green: ColourField,
+	/// Blue channel.
+	pub blue: ColourField,
+}
+
+impl MultibootInfo {
+	/// Returns the kernel command line (flags bit 2), `None` if there is
+	/// none or it is not UTF-8.
+	pub fn cmdline(&self) -> Option<&'static str> {
+		if self.flags & (1 << 2) == 0 || self.cmdline == 0 {
+			return None;
+		}
+
+		return c_string(self.cmdline);
+	}
+
+	/// Returns the modules the bootloader loaded (flags bit 3).
+	pub fn modules(&self) -> &[MultibootModule] {
+		if self.flags & (1 << 3) == 0 || self.mods_count == 0 {
+			return &[];
+		}
+
+		let modules: *const MultibootModule =
+			ptr::with_exposed_provenance(self.mods_addr as usize);
+		// Safety: the bootloader left `mods_count` module structures at
+		// `mods_addr`, which is identity mapped.
+		return unsafe {
+			core::slice::from_raw_parts(modules, self.mods_count as usize)
+		};
+	}
 
-	for (index, segment) in segments.iter().enumerate() {
-		if segment.segment_type() != RegionType::Available
-			|| segment.start_addr().as_usize() == 0x0
+	/// Returns the framebuffer if the bootloader set up a direct RGB one
+	/// (flags bit 12), rather than VGA text mode.
+	pub fn framebuffer(&self) -> Option<FramebufferInfo> {
+		if self.flags & (1 << 12) == 0
+			|| self.framebuffer_type != FRAMEBUFFER_TYPE_RGB
 		{
-			continue;
+			return None;
 		}
 
-		if biggest_index.is_none() || segment.size() > current_max_size {
-			current_max_size = segment.size();
-			biggest_index = Some(index);
+		// Framebuffers above 4 GiB cannot be reached without PAE.
+		let addr = usize::try_from(self.framebuffer_addr).ok()?;
+		let info = self.framebuffer_colour_info;
+		let field = |i: usize| ColourField {
+			position: info[i],
+			size: info[i + 1],
+		};
+
+		return Some(FramebufferInfo {
+			addr: PhysAddr::new(addr),
+			pitch: self.framebuffer_pitch as usize,
+			width: self.framebuffer_width as usize,
+			height: self.framebuffer_height as usize,
+			bpp: self.framebuffer_bpp,
+			red: field(0),
+			green: field(2),
+			blue: field(4),
+		});
+	}
+}
+
+/// Maximum number of memory map entries kept in [`G_SEGMENTS`], as many as
+/// memblock has regions.
+pub const MAX_MEMORY_SEGMENTS: usize = 64;
+
+/// Lowest address of available memory that is handed out.
+const LOW_MEMORY_END: usize = 0x100000;
+
+/// Global static storage for the parsed memory map segments.
+///
+/// Filled once during boot by [`get_memory_region`]. Available segments skip
+/// low memory and the kernel image, the other types are kept as reported.
+pub static G_SEGMENTS: RwLock<MemorySegments> =
+	RwLock::new(MemorySegments::new());
+
+/// The segments of the memory map in the order it lists them, up to
+/// [`MAX_MEMORY_SEGMENTS`].
+#[derive(Debug, Clone)]
+pub struct MemorySegments {
+	segments: ArrayVec<MemorySegment, MAX_MEMORY_SEGMENTS>,
+}
+
+impl MemorySegments {
+	/// Creates an empty list of segments.
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		return Self {
+			segments: ArrayVec::new(),
+		};
+	}
+
+	/// Appends `segment`. Returns `false` if the list is full.
+	#[must_use]
+	pub fn push(&mut self, segment: MemorySegment) -> bool {
+		return self.segments.push(segment).is_ok();
+	}
+
+	/// Returns the number of segments.
+	pub const fn len(&self) -> usize {
+		return self.segments.len();
+	}
+
+	/// Returns whether there are no segments.
+	pub const fn is_empty(&self) -> bool {
+		return self.segments.is_empty();
+	}
+
+	/// Returns the segments of every type.
+	pub fn as_slice(&self) -> &[MemorySegment] {
+		return &self.segments;
+	}
+
+	/// Returns an iterator over the segments of every type.
+	pub fn iter(&self) -> core::slice::Iter<'_, MemorySegment> {
+		return self.as_slice().iter();
+	}
+
+	/// Returns an iterator over the `Available` segments.
+	pub fn available_segments(&self) -> impl Iterator<Item = &MemorySegment> {
+		return self.iter().filter(|segment| {
+			return segment.segment_type() == RegionType::Available;
+		});
+	}
+
+	/// Returns the biggest `Available` segment, the first of them if several
+	/// are as big.
+	pub fn biggest_available(&self) -> Option<&MemorySegment> {
+		return self.available_segments().reduce(|biggest, segment| {
+			if segment.size() > biggest.size() {
+				return segment;
+			}
+			return biggest;
+		});
+	}
+}
+
+/// Parses the Multiboot memory map of `boot_info` into [`G_SEGMENTS`].
+///
+/// # Panics
+/// Panics if the bootloader information does not contain a valid memory map
+/// (`flags` bit 6 not set), or if no memory regions are found in the map.
+pub fn get_memory_region(boot_info: &MultibootInfo) {
+	if (boot_info.flags & (1 << 6)) == 0 {
+		panic!("CRITICAL: Bootloader did not provide a memory map!");
+	}
+
+	let mmap: *const u8 =
+		ptr::with_exposed_provenance(boot_info.mmap_addr as usize);
+	// Safety: the bootloader left `mmap_length` bytes of memory map at
+	// `mmap_addr`, which is identity mapped.
+	let mmap = unsafe {
+		core::slice::from_raw_parts(mmap, boot_info.mmap_length as usize)
+	};
+
+	if parse_memory_map(mmap) == 0 {
+		panic!("Could not find any memory regions in map (or map was empty)!");
+	}
+}
+
+/// Replaces [`G_SEGMENTS`] with the segments of the memory map entries in
+/// `mmap` and returns how many were recorded. Entries past
+/// [`MAX_MEMORY_SEGMENTS`] are dropped with a warning.
+pub fn parse_memory_map(mmap: &[u8]) -> usize {
+	let mut segments = G_SEGMENTS.write();
+	*segments = MemorySegments::new();
+
+	let mut dropped = 0;
+	let mut offset = 0;
+
+	while offset + size_of::<MultibootMmapEntry>() <= mmap.len() {
+		// Safety: the entry is in bounds, and read unaligned as the entries
+		// are packed.
+		let entry = unsafe {
+			ptr::read_unaligned(
+				mmap.as_ptr().add(offset).cast::<MultibootMmapEntry>(),
+			)
+		};
+		// `size` does not count itself
+		offset += entry.size as usize + size_of::<u32>();
+
+		let (base, length, entry_type) =
+			(entry.addr, entry.len, entry.entry_type);
+		println_serial!(
+			"  Entry: Base=0x{:08x}, Length=0x{:08x} ({} bytes), Type={}",
+			base,
+			length,
+			length,
+			entry_type,
+		);
+
+		let Some(segment) = to_segment(&entry) else {
+			continue;
+		};
+		if !segments.push(segment) {
+			dropped += 1;
 		}
 	}
 
-	return biggest_index;
+	if dropped > 0 {
+		log_warn!(
+			"Memory map has {} regions more than the {} kept, ignoring them",
+			dropped,
+			MAX_MEMORY_SEGMENTS
+		);
+	}
+
+	return segments.len();
+}
+
+// Helper turning a memory map entry into a segment, `None` if nothing of it
+// is usable or it lies above 4GiB
+fn to_segment(entry: &MultibootMmapEntry) -> Option<MemorySegment> {
+	let start = usize::try_from(entry.addr).ok()?;
+	let end = usize::try_from(entry.addr.saturating_add(entry.len))
+		.unwrap_or(usize::MAX);
+	let segment_type = RegionType::from_raw(entry.entry_type);
+
+	if segment_type != RegionType::Available {
+		return Some(MemorySegment::new(
+			PhysAddr::new(start),
+			end - start,
+			segment_type,
+		));
+	}
+
+	// Ignore available memory under 1Mb and below the end of the kernel
+	if start < LOW_MEMORY_END {
+		return None;
+	}
+	let kernel_end = get_kernel_physical_end().as_usize();
+	let start = if start < kernel_end {
+		kernel_end + PAGE_SIZE
+	} else {
+		start
+	};
+	if start >= end {
+		return None;
+	}
+
+	return Some(MemorySegment::new(
+		PhysAddr::new(start),
+		end - start,
+		segment_type,
+	));
 }
@@ -0,0 +1,187 @@
+//! Decoding and handling of page faults (#PF).
+//!
+//! The exception handler hands its context to [`handle`], which decodes the
+//! error code and CR2. Faults
+//! on a not present page in a range registered with
+//! [`register_fault_handler`] go to that range's callback, which can map the
+//! page and let the faulting instruction run again. Every other fault is
+//! reported on the serial port and the kernel panics.
+
+use super::{
+	backtrace::print_backtrace, cpu::cr2, exceptions::ExceptionContext,
+};
+use crate::{
+	memory::{classify_address, KernelRegion, VirtAddr},
+	println_serial,
+	sync::Mutex,
+};
+use core::fmt;
+
+/// Number of ranges [`register_fault_handler`] can register.
+pub const MAX_FAULT_HANDLERS: usize = 8;
+
+const ERROR_PRESENT: u32 = 1 << 0;
+const ERROR_WRITE: u32 = 1 << 1;
+const ERROR_USER: u32 = 1 << 2;
+const ERROR_RESERVED: u32 = 1 << 3;
+const ERROR_INSTRUCTION_FETCH: u32 = 1 << 4;
+
+/// Called for a not present fault in its range. Returns whether it resolved
+/// the fault, in which case the faulting instruction is retried.
+pub type FaultHandler = fn(&PageFault) -> bool;
+
+#[derive(Clone, Copy)]
+struct FaultRange {
+	start: usize,
+	len: usize,
+	handler: FaultHandler,
+}
+
+static FAULT_HANDLERS: Mutex<[Option<FaultRange>; MAX_FAULT_HANDLERS]> =
+	Mutex::named("FAULT_HANDLERS", [None; MAX_FAULT_HANDLERS]);
+
+/// A page fault, decoded from CR2 and the error code the CPU pushed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageFault {
+	/// The address whose access faulted.
+	pub address: VirtAddr,
+	/// The page was present, so the access broke its protection.
+	pub present: bool,
+	/// The access was a write.
+	pub write: bool,
+	/// The access came from user mode.
+	pub user: bool,
+	/// A paging structure has a reserved bit set.
+	pub reserved: bool,
+	/// The access was an instruction fetch.
+	pub instruction_fetch: bool,
+}
+
+impl PageFault {
+	/// Decodes the `error_code` of a fault on `address`.
+	pub const fn decode(address: VirtAddr, error_code: u32) -> Self {
+		return Self {
+			address,
+			present: error_code & ERROR_PRESENT != 0,
+			write: error_code & ERROR_WRITE != 0,
+			user: error_code & ERROR_USER != 0,
+			reserved: error_code & ERROR_RESERVED != 0,
+			instruction_fetch: error_code & ERROR_INSTRUCTION_FETCH != 0,
+		};
+	}
+
+	/// Returns the part of the address space the address falls in.
+	pub fn region(&self) -> KernelRegion {
+		return classify_address(self.address);
+	}
+}
+
+impl fmt::Display for PageFault {
+	/// Formats the fault as one line, like
+	/// `write to 0xd0001000 (not present, kernel, dynamic ranges)`.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let access = match (self.instruction_fetch, self.write) {
+			(true, _) => "fetch from",
+			(false, true) => "write to",
+			(false, false) => "read from",
+		};
+
+		write!(
+			f,
+			"{} {:#010x} ({}, {}, {}",
+			access,
+			self.address.as_usize(),
+			if self.present {
+				"protection"
+			} else {
+				"not present"
+			},
+			if self.user { "user" } else { "kernel" },
+			self.region().name()
+		)?;
+		if self.reserved {
+			write!(f, ", reserved bit")?;
+		}
+		return write!(f, ")");
+	}
+}
+
+/// Makes `handler` resolve not present faults in the `len` bytes from
+/// `start`, for ranges whose pages are mapped on first use.
+///
+/// # Panics
+/// Panics if all [`MAX_FAULT_HANDLERS`] slots are taken.
+pub fn register_fault_handler(
+	start: VirtAddr,
+	len: usize,
+	handler: FaultHandler,
+) {
+	let mut handlers = FAULT_HANDLERS.lock();
+
+	let Some(slot) = handlers.iter_mut().find(|slot| slot.is_none()) else {
+		panic!(
+			"register_fault_handler: no room for {:#x}",
+			start.as_usize()
+		);
+	};
+	*slot = Some(FaultRange {
+		start: start.as_usize(),
+		len,
+		handler,
+	});
+}
+
+/// Removes the handler registered for the range starting at `start`.
+pub fn unregister_fault_handler(start: VirtAddr) {
+	let mut handlers = FAULT_HANDLERS.lock();
+
+	for slot in handlers.iter_mut() {
+		if slot.is_some_and(|range| range.start == start.as_usize()) {
+			*slot = None;
+		}
+	}
+}
+
+/// Handles the page fault `context` describes. Returns if a registered
+/// handler resolved it, panics with a register dump otherwise.
+pub fn handle(context: &ExceptionContext) {
+	let fault = PageFault::decode(cr2(), context.error_code);
+	let instruction_pointer = context.frame.instruction_pointer;
+
+	if !fault.present {
+		if let Some(handler) = find_handler(fault.address) {
+			if handler(&fault) {
+				return;
+			}
+		}
+	}
+
+	println_serial!("EXCEPTION: PAGE FAULT (#PF)");
+	println_serial!("===========================");
+	println_serial!("Address:            {:#010x}", fault.address.as_usize());
+	println_serial!("Region:             {}", fault.region().name());
+	println_serial!("Instruction:        {:#010x}", instruction_pointer);
+	println_serial!("Error code:         {:#06x}", context.error_code);
+	println_serial!("  present:          {}", fault.present);
+	println_serial!("  write:            {}", fault.write);
+	println_serial!("  user:             {}", fault.user);
+	println_serial!("  reserved bit:     {}", fault.reserved);
+	println_serial!("  instruction fetch: {}", fault.instruction_fetch);
+	println_serial!("{}", context);
+	print_backtrace(context.regs.ebp as usize);
+
+	panic!("Page fault: {} at {:#010x}", fault, instruction_pointer);
+}
+
+// Helper to copy out the handler covering `address`. Gives up if the faulting
+// code holds the registry, so the fault is reported instead of deadlocking.
+fn find_handler(address: VirtAddr) -> Option<FaultHandler> {
+	let handlers = FAULT_HANDLERS.try_lock()?;
+	let address = address.as_usize();
+
+	return handlers
+		.iter()
+		.flatten()
+		.find(|range| (range.start..range.start + range.len).contains(&address))
+		.map(|range| range.handler);
+}
@@ -8,7 +8,13 @@
 //! It is important to note that APIC has replaced the 8259 PIC in more modern
 //! systems, especially those with multiple cores/processors.
 
-use super::io::{inb, io_wait, outb};
+use super::{
+	exceptions::InterruptHandler,
+	idt::{IDT_ENTRIES, KERNEL_DPL},
+	io::{inb, io_wait, outb},
+};
+use crate::sync::Mutex;
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 
 const PIC1: u16 = 0x20; /* IO base address for master PIC */
 const PIC2: u16 = 0xa0; /* IO base address for slave PIC */
@@ -30,12 +36,54 @@ const ICW4_BUF_MASTER: u8 = 0x0c; /* Buffered mode/master */
 const ICW4_SFNM: u8 = 0x10; /* Special fully nested (not) */
 
 const PIC_EOI: u8 = 0x20; /* End-of-interrupt command code */
+const OCW3_READ_ISR: u8 = 0x0b; /* Read the In-Service Register next */
+
+/// Vector of IRQ 0, as remapped in `boot.asm`. IRQ 0-7 follow the CPU
+/// exceptions at 0x20-0x27.
+pub const PIC1_OFFSET: u8 = 0x20;
+/// Vector of IRQ 8, IRQ 8-15 use 0x28-0x2f.
+pub const PIC2_OFFSET: u8 = 0x28;
+
+/// Number of IRQ lines of the two PICs.
+pub const IRQ_COUNT: usize = 16;
+
+/// The master line the slave is cascaded into.
+const CASCADE_IRQ: u8 = 2;
+
+/// Set once `pic_remap` ran, later remaps keep the masks.
+static REMAPPED: AtomicBool = AtomicBool::new(false);
+
+/// Name of the handler [`register_irq`] installed on each line.
+static IRQ_NAMES: Mutex<[Option<&'static str>; IRQ_COUNT]> =
+	Mutex::named("IRQ_NAMES", [None; IRQ_COUNT]);
+
+/// Interrupts acknowledged with [`send_eoi`] on each line.
+static IRQ_COUNTS: [AtomicU32; IRQ_COUNT] =
+	[const { AtomicU32::new(0) }; IRQ_COUNT];
+
+/// The state of one IRQ line, see [`irq_lines`].
+#[derive(Debug, Clone, Copy)]
+pub struct IrqLine {
+	/// The IRQ number.
+	pub irq: u8,
+	/// Whether the PIC holds the line back.
+	pub masked: bool,
+	/// Name given to [`register_irq`], `None` if nothing was registered.
+	pub handler: Option<&'static str>,
+	/// Number of interrupts handled on the line.
+	pub count: u32,
+}
 
 #[doc(hidden)]
 #[no_mangle]
 pub fn pic_remap(offset1: u8, offset2: u8) {
-	// let a1 = inb(PIC1_DATA);
-	// let a2 = inb(PIC2_DATA);
+	// The first remap runs at boot, with whatever masks the BIOS left, for
+	// vectors that are still CPU exceptions. Those lines stay masked until a
+	// handler is registered.
+	let (mask1, mask2) = match REMAPPED.swap(true, Ordering::Relaxed) {
+		true => get_masks(),
+		false => (0xff, 0xff),
+	};
 
 	// starts the initialization sequence (in cascade mode)
 	outb(PIC1_COMMAND, ICW1_INIT | ICW1_ICW4);
@@ -58,12 +106,9 @@ pub fn pic_remap(offset1: u8, offset2: u8) {
 	outb(PIC2_DATA, ICW4_8086);
 	io_wait();
 
-	outb(PIC1_DATA, 0xff);
-	outb(PIC2_DATA, 0xff);
-
-	// Unmask both PICs.
-	// outb(PIC1_DATA, a1);
-	// outb(PIC2_DATA, a2);
+	// Restore the masks
+	outb(PIC1_DATA, mask1);
+	outb(PIC2_DATA, mask2);
 }
 
 /// Perhaps the most common command issued to the PIC chips is the end of
@@ -72,10 +117,107 @@ pub fn pic_remap(offset1: u8, offset2: u8) {
 /// it is sufficient to issue this command only to the Master PIC; however if
 /// the IRQ came from the Slave PIC, it is necessary to issue the command to
 /// both PIC chips.
+///
+/// Also counts the interrupt for [`irq_lines`].
 pub fn send_eoi(irq: u8) {
+	if let Some(count) = IRQ_COUNTS.get(irq as usize) {
+		count.fetch_add(1, Ordering::Relaxed);
+	}
+
 	if irq >= 8 {
 		outb(PIC2_COMMAND, PIC_EOI);
 	}
 
 	outb(PIC1_COMMAND, PIC_EOI);
 }
+
+/// Returns whether `irq` is spurious, and must not be acknowledged.
+///
+/// A line that drops before the CPU took its interrupt makes the PIC raise
+/// IRQ 7, or IRQ 15 on the slave, with nothing in service. The master did
+/// see a spurious IRQ 15 on the cascade, so it gets its end of interrupt
+/// here.
+pub fn handle_spurious(irq: u8) -> bool {
+	let command = match irq {
+		7 => PIC1_COMMAND,
+		15 => PIC2_COMMAND,
+		_ => return false,
+	};
+
+	outb(command, OCW3_READ_ISR);
+	if inb(command) & (1 << 7) != 0 {
+		return false;
+	}
+
+	if irq == 15 {
+		outb(PIC1_COMMAND, PIC_EOI);
+	}
+	return true;
+}
+
+/// Returns the data port and mask bit of `irq`, `None` past [`IRQ_COUNT`].
+fn line(irq: u8) -> Option<(u16, u8)> {
+	match irq {
+		0..8 => return Some((PIC1_DATA, irq)),
+		8..16 => return Some((PIC2_DATA, irq - 8)),
+		_ => return None,
+	}
+}
+
+/// Holds `irq` back by setting its bit in the PIC mask.
+pub fn set_mask(irq: u8) {
+	if let Some((port, bit)) = line(irq) {
+		outb(port, inb(port) | (1 << bit));
+	}
+}
+
+/// Lets `irq` through to the CPU by clearing its bit in the PIC mask.
+pub fn clear_mask(irq: u8) {
+	let Some((port, bit)) = line(irq) else {
+		return;
+	};
+
+	outb(port, inb(port) & !(1 << bit));
+
+	// IRQs on the slave only arrive through the cascade on IRQ 2
+	if irq >= 8 {
+		clear_mask(CASCADE_IRQ);
+	}
+}
+
+/// Returns the masks of the master and the slave PIC, a set bit per masked
+/// line.
+pub fn get_masks() -> (u8, u8) {
+	return (inb(PIC1_DATA), inb(PIC2_DATA));
+}
+
+/// Installs `handler` for `irq` under `name` and unmasks the line.
+///
+/// The handler must end with [`send_eoi`], which also counts the interrupt.
+pub fn register_irq(irq: u8, name: &'static str, handler: InterruptHandler) {
+	if line(irq).is_none() {
+		return;
+	}
+
+	unsafe {
+		IDT_ENTRIES[(PIC1_OFFSET + irq) as usize]
+			.set_handler(handler, KERNEL_DPL);
+	}
+
+	IRQ_NAMES.lock()[irq as usize] = Some(name);
+	clear_mask(irq);
+}
+
+/// Returns the state of every IRQ line.
+pub fn irq_lines() -> [IrqLine; IRQ_COUNT] {
+	let (mask1, mask2) = get_masks();
+	let masks = ((mask2 as u16) << 8) | mask1 as u16;
+	let names = *IRQ_NAMES.lock();
+
+	return core::array::from_fn(|irq| IrqLine {
+		irq: irq as u8,
+		masked: masks & (1 << irq) != 0,
+		handler: names[irq],
+		count: IRQ_COUNTS[irq].load(Ordering::Relaxed),
+	});
+}
@@ -0,0 +1,211 @@
+//! Task State Segments (TSS).
+//!
+//! The kernel does not switch tasks in hardware, but the CPU still wants a
+//! TSS: it takes the ring 0 stack from the kernel's TSS when an interrupt
+//! comes from user mode, and saves the interrupted state there on a task
+//! switch.
+//!
+//! The one task switch there is happens on a double fault. The IDT routes
+//! vector 8 through a task gate to a second TSS with a stack of its own, so
+//! the handler still runs when the fault came from an overflowed or corrupt
+//! kernel stack, which an interrupt gate would push onto and triple fault.
+//!
+//! For more information go to:
+//! <https://wiki.osdev.org/Task_State_Segment>
+
+use super::cpu::cr3;
+use core::{arch::asm, fmt, mem::size_of, ptr};
+
+/// Selector of the kernel's TSS in the GDT, loaded into the task register.
+pub const TSS_SELECTOR: u16 = 0x28;
+
+/// Selector of the double fault task's TSS in the GDT.
+pub const DOUBLE_FAULT_TSS_SELECTOR: u16 = 0x30;
+
+/// Size of the stack the double fault handler runs on.
+pub const DOUBLE_FAULT_STACK_SIZE: usize = 8 * 1024;
+
+const KERNEL_CODE_SELECTOR: u32 = 0x08;
+const KERNEL_DATA_SELECTOR: u32 = 0x10;
+
+/// Reserved EFLAGS bit 1, always set. Interrupts stay off in the task.
+const EFLAGS_RESERVED: u32 = 1 << 1;
+
+/// A 32-bit Task State Segment, as laid out by the CPU.
+///
+/// The 16-bit selectors are stored in the low half of their field, the upper
+/// half is reserved.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+#[allow(missing_docs)]
+pub struct TaskStateSegment {
+	/// Selector of the task that switched here, for nested tasks.
+	pub link: u32,
+	pub esp0: u32,
+	pub ss0: u32,
+	pub esp1: u32,
+	pub ss1: u32,
+	pub esp2: u32,
+	pub ss2: u32,
+	pub cr3: u32,
+	pub eip: u32,
+	pub eflags: u32,
+	pub eax: u32,
+	pub ecx: u32,
+	pub edx: u32,
+	pub ebx: u32,
+	pub esp: u32,
+	pub ebp: u32,
+	pub esi: u32,
+	pub edi: u32,
+	pub es: u32,
+	pub cs: u32,
+	pub ss: u32,
+	pub ds: u32,
+	pub fs: u32,
+	pub gs: u32,
+	pub ldt: u32,
+	/// Debug trap flag in bit 0, I/O map base in the upper half.
+	pub trap_iomap: u32,
+}
+
+impl TaskStateSegment {
+	/// Limit of a TSS descriptor, the size without an I/O permission map.
+	pub const LIMIT: u32 = size_of::<Self>() as u32 - 1;
+
+	const fn new() -> Self {
+		return Self {
+			link: 0,
+			esp0: 0,
+			ss0: KERNEL_DATA_SELECTOR,
+			esp1: 0,
+			ss1: 0,
+			esp2: 0,
+			ss2: 0,
+			cr3: 0,
+			eip: 0,
+			eflags: EFLAGS_RESERVED,
+			eax: 0,
+			ecx: 0,
+			edx: 0,
+			ebx: 0,
+			esp: 0,
+			ebp: 0,
+			esi: 0,
+			edi: 0,
+			es: KERNEL_DATA_SELECTOR,
+			cs: KERNEL_CODE_SELECTOR,
+			ss: KERNEL_DATA_SELECTOR,
+			ds: KERNEL_DATA_SELECTOR,
+			fs: KERNEL_DATA_SELECTOR,
+			gs: KERNEL_DATA_SELECTOR,
+			ldt: 0,
+			// Past the limit, so there is no I/O permission map
+			trap_iomap: (size_of::<Self>() as u32) << 16,
+		};
+	}
+}
+
+impl fmt::Display for TaskStateSegment {
+	/// Formats the saved registers in the layout of an exception report.
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		writeln!(
+			f,
+			"EAX={:08x} EBX={:08x} ECX={:08x} EDX={:08x}",
+			self.eax, self.ebx, self.ecx, self.edx
+		)?;
+		writeln!(
+			f,
+			"ESI={:08x} EDI={:08x} EBP={:08x} ESP={:08x}",
+			self.esi, self.edi, self.ebp, self.esp
+		)?;
+		writeln!(
+			f,
+			"EIP={:08x} EFLAGS={:08x} CR3={:08x}",
+			self.eip, self.eflags, self.cr3
+		)?;
+		return write!(
+			f,
+			"CS={:04x} DS={:04x} ES={:04x} FS={:04x} GS={:04x} SS={:04x}",
+			self.cs & 0xffff,
+			self.ds & 0xffff,
+			self.es & 0xffff,
+			self.fs & 0xffff,
+			self.gs & 0xffff,
+			self.ss & 0xffff
+		);
+	}
+}
+
+extern "C" {
+	// src/arch/{target}/isr.asm
+	fn double_fault_task_entry();
+}
+
+/// The kernel's TSS, the task register points at it.
+static mut TSS: TaskStateSegment = TaskStateSegment::new();
+
+/// The TSS of the double fault task.
+static mut DOUBLE_FAULT_TSS: TaskStateSegment = TaskStateSegment::new();
+
+#[repr(C, align(16))]
+struct Stack([u8; DOUBLE_FAULT_STACK_SIZE]);
+
+static mut DOUBLE_FAULT_STACK: Stack = Stack([0; DOUBLE_FAULT_STACK_SIZE]);
+
+/// Returns the address of the kernel's TSS, for its GDT descriptor.
+pub fn tss_address() -> u32 {
+	return &raw const TSS as u32;
+}
+
+/// Returns the address of the double fault task's TSS, for its GDT
+/// descriptor.
+pub fn double_fault_tss_address() -> u32 {
+	return &raw const DOUBLE_FAULT_TSS as u32;
+}
+
+/// Sets up the double fault task and loads the task register. Called by
+/// `gdt_init` once the GDT holds both descriptors.
+pub fn init() {
+	let stack_top =
+		&raw const DOUBLE_FAULT_STACK as u32 + DOUBLE_FAULT_STACK_SIZE as u32;
+
+	unsafe {
+		DOUBLE_FAULT_TSS.eip = double_fault_task_entry as usize as u32;
+		DOUBLE_FAULT_TSS.esp = stack_top;
+		DOUBLE_FAULT_TSS.esp0 = stack_top;
+		// The boot page directory, which maps the whole kernel half
+		DOUBLE_FAULT_TSS.cr3 = cr3().as_usize() as u32;
+
+		asm!("ltr {0:x}", in(reg) TSS_SELECTOR, options(nomem, nostack));
+	}
+}
+
+/// Returns the selector in the task register.
+pub fn task_register() -> u16 {
+	let selector: u16;
+	unsafe {
+		asm!("str {0:x}", out(reg) selector, options(nomem, nostack));
+	}
+
+	return selector;
+}
+
+/// Sets the stack the CPU switches to when an interrupt comes from user
+/// mode.
+pub fn set_kernel_stack(esp0: u32) {
+	unsafe { TSS.esp0 = esp0 };
+}
+
+/// Returns the state of the code a double fault interrupted, which the task
+/// switch saved in the kernel's TSS.
+pub fn interrupted_task() -> TaskStateSegment {
+	return unsafe { ptr::read_volatile(&raw const TSS) };
+}
+
+/// Returns the address range of the double fault task's stack.
+pub fn double_fault_stack() -> core::ops::Range<u32> {
+	let start = &raw const DOUBLE_FAULT_STACK as u32;
+
+	return start..start + DOUBLE_FAULT_STACK_SIZE as u32;
+}
@@ -0,0 +1,178 @@
+//! Running code at CPL 3.
+//!
+//! [`enter`] irets to user code with the user selectors of the GDT. An
+//! interrupt from there switches to the kernel stack the TSS names, which
+//! [`run`] sets up before it enters, and the IDT gate of int 0x80 is the one
+//! user code may call, see [`crate::syscall`].
+//!
+//! [`run`] also saves where the kernel was, so the `exit` syscall can go
+//! back there instead of returning to user code. [`demo`] runs a small
+//! payload that way, the `user` console command calls it.
+
+use super::tss;
+use crate::memory::{
+	frame::FRAME_ALLOCATOR,
+	paging::{flags, map_range, phys_to_virt, unmap_page},
+	PhysAddr, VirtAddr, PAGE_SIZE,
+};
+use core::{
+	alloc::AllocError,
+	ptr,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+/// Code segment selector of ring 3, GDT entry 3 with RPL 3.
+pub const USER_CODE_SELECTOR: u16 = 0x18 | 3;
+
+/// Data and stack segment selector of ring 3, GDT entry 4 with RPL 3.
+pub const USER_DATA_SELECTOR: u16 = 0x20 | 3;
+
+/// Where [`demo`] maps its payload, the first page past the identity mapped
+/// low 4MiB.
+pub const DEMO_CODE: VirtAddr = VirtAddr::new(0x0040_0000);
+
+/// Where [`demo`] maps the user stack.
+pub const DEMO_STACK: VirtAddr = VirtAddr::new(0x0040_1000);
+
+/// What the demo payload exits with.
+pub const DEMO_VALUE: u32 = 0x600d;
+
+/// Size of the stack interrupts from ring 3 run on.
+const KERNEL_STACK_SIZE: usize = 8 * 1024;
+
+extern "C" {
+	// src/arch/{target}/usermode.asm
+	fn usermode_iret(entry: u32, user_stack: u32) -> !;
+	fn usermode_run(entry: u32, user_stack: u32, kernel_esp: *mut u32) -> u32;
+	fn usermode_return(kernel_esp: u32, value: u32) -> !;
+	static user_payload_start: u8;
+	static user_payload_end: u8;
+}
+
+#[repr(C, align(16))]
+struct Stack([u8; KERNEL_STACK_SIZE]);
+
+static mut KERNEL_STACK: Stack = Stack([0; KERNEL_STACK_SIZE]);
+
+/// Stack pointer [`run`] saved, 0 while no user code runs.
+static KERNEL_ESP: AtomicU32 = AtomicU32::new(0);
+
+/// Switches to ring 3 at `entry` with the stack pointer at `user_stack`,
+/// interrupts enabled.
+///
+/// # Safety
+///
+/// `entry` and the stack below `user_stack` must be mapped user accessible
+/// and the TSS must name a kernel stack for the interrupts coming back.
+pub unsafe fn enter(entry: VirtAddr, user_stack: VirtAddr) -> ! {
+	unsafe {
+		usermode_iret(entry.as_usize() as u32, user_stack.as_usize() as u32)
+	};
+}
+
+/// Runs user code like [`enter`] until it calls the `exit` syscall, and
+/// returns the code it passed.
+///
+/// # Safety
+///
+/// As for [`enter`].
+///
+/// # Panics
+///
+/// Panics if user code is already running.
+pub unsafe fn run(entry: VirtAddr, user_stack: VirtAddr) -> u32 {
+	assert_eq!(
+		KERNEL_ESP.load(Ordering::Relaxed),
+		0,
+		"user code is running"
+	);
+
+	let stack_top = &raw const KERNEL_STACK as u32 + KERNEL_STACK_SIZE as u32;
+	tss::set_kernel_stack(stack_top);
+
+	return unsafe {
+		usermode_run(
+			entry.as_usize() as u32,
+			user_stack.as_usize() as u32,
+			KERNEL_ESP.as_ptr(),
+		)
+	};
+}
+
+/// Returns whether [`run`] is running user code.
+pub fn is_running() -> bool {
+	return KERNEL_ESP.load(Ordering::Relaxed) != 0;
+}
+
+/// Ends the user code [`run`] runs, which returns `code`. Returns only if
+/// there is none, the user stack and registers are dropped otherwise.
+pub fn exit(code: u32) {
+	let esp = KERNEL_ESP.swap(0, Ordering::Relaxed);
+	if esp != 0 {
+		unsafe { usermode_return(esp, code) };
+	}
+}
+
+// Helper allocating a frame for `demo`
+#[allow(clippy::expect_used)]
+fn allocate_frame() -> Result<PhysAddr, AllocError> {
+	return FRAME_ALLOCATOR
+		.lock()
+		.expect("Frame has not been initialized yet")
+		.allocate_frame()
+		.ok_or(AllocError);
+}
+
+// Helper giving back a frame `demo` did not map
+#[allow(clippy::expect_used)]
+fn free_frame(frame: PhysAddr) {
+	FRAME_ALLOCATOR
+		.lock()
+		.expect("Frame has not been initialized yet")
+		.frame_put(frame);
+}
+
+// Helper mapping a frame of `demo` at `virt`, giving the frame back if that
+// fails
+fn map_user_page(
+	frame: PhysAddr,
+	virt: VirtAddr,
+	flags: u32,
+) -> Result<(), AllocError> {
+	let flags = flags | flags::PRESENT | flags::USER_ACCESSIBLE;
+
+	return map_range(frame, virt, PAGE_SIZE, flags).inspect_err(|_| {
+		free_frame(frame);
+	});
+}
+
+/// Maps the demo payload and a stack user accessible, runs it and returns
+/// what it exits with, see [`DEMO_VALUE`]. It writes a line to the console
+/// before that.
+pub fn demo() -> Result<u32, AllocError> {
+	let code = allocate_frame()?;
+	unsafe {
+		let start = &raw const user_payload_start;
+		let len = &raw const user_payload_end as usize - start as usize;
+		ptr::copy_nonoverlapping(start, phys_to_virt(code).as_mut_ptr(), len);
+	}
+	map_user_page(code, DEMO_CODE, 0)?;
+
+	let stack = allocate_frame().and_then(|stack| {
+		return map_user_page(stack, DEMO_STACK, flags::WRITABLE);
+	});
+	if let Err(error) = stack {
+		unmap_page(DEMO_CODE);
+		return Err(error);
+	}
+
+	// Safety: both pages are mapped user accessible, `run` sets ESP0.
+	let value = unsafe {
+		run(DEMO_CODE, VirtAddr::new(DEMO_STACK.as_usize() + PAGE_SIZE))
+	};
+
+	unmap_page(DEMO_STACK);
+	unmap_page(DEMO_CODE);
+
+	return Ok(value);
+}
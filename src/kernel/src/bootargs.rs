@@ -0,0 +1,151 @@
+//! The options on the kernel command line.
+//!
+//! The bootloader passes a command line in low physical memory, which the
+//! allocators hand out once they are up. [`init`] copies it into a static
+//! table first thing at boot and splits it at spaces into `key` and
+//! `key=value` options, GRUB's first word being the path of the kernel.
+//!
+//! The kernel reads:
+//!
+//! - `loglevel=<level>`, the level the log starts at, see `loglevel`.
+//! - `serial=off`, leaves the serial port alone, no log goes there.
+//! - `console=serial`, mirrors the console to the serial port from boot.
+//! - `debugcon=on`, copies the log and the console to the 0xE9 debug console.
+//! - `memtest=1`, runs the allocator stress test before the shell starts.
+
+use crate::{
+	arch::x86::multiboot::MultibootInfo, collections::array_vec::ArrayVec,
+	log_warn, sync::OnceLock,
+};
+use core::{ops::Range, str::from_utf8};
+
+/// Longest command line kept, longer ones are cut.
+pub const MAX_CMDLINE: usize = 256;
+
+/// Most options kept, the ones past it are dropped.
+pub const MAX_OPTIONS: usize = 16;
+
+/// An option, as ranges of the command line.
+#[derive(Debug, Clone)]
+struct BootOption {
+	key: Range<usize>,
+	value: Option<Range<usize>>,
+}
+
+/// A copy of the command line and the options in it.
+pub struct BootArgs {
+	raw: [u8; MAX_CMDLINE],
+	len: usize,
+	options: ArrayVec<BootOption, MAX_OPTIONS>,
+}
+
+static BOOT_ARGS: OnceLock<BootArgs> = OnceLock::new();
+
+impl BootArgs {
+	/// Copies `cmdline` and splits it into options, see the module
+	/// documentation. Warns about what does not fit.
+	pub fn parse(cmdline: &str) -> Self {
+		let mut args = Self {
+			raw: [0; MAX_CMDLINE],
+			len: 0,
+			options: ArrayVec::new(),
+		};
+
+		let mut len = cmdline.len().min(MAX_CMDLINE);
+		while !cmdline.is_char_boundary(len) {
+			len -= 1;
+		}
+		if len < cmdline.len() {
+			log_warn!("bootargs: command line cut at {} bytes", len);
+		}
+		args.raw[..len].copy_from_slice(&cmdline.as_bytes()[..len]);
+		args.len = len;
+
+		let mut dropped = 0;
+		let mut start = 0;
+		for word in cmdline[..len].split(' ') {
+			let key_end = word.find('=').unwrap_or(word.len());
+			let option = BootOption {
+				key: start..start + key_end,
+				value: (key_end < word.len())
+					.then(|| start + key_end + 1..start + word.len()),
+			};
+			start += word.len() + 1;
+
+			if !word.is_empty() && args.options.push(option).is_err() {
+				dropped += 1;
+			}
+		}
+		if dropped > 0 {
+			log_warn!(
+				"bootargs: {} options past the first {} dropped",
+				dropped,
+				MAX_OPTIONS
+			);
+		}
+
+		return args;
+	}
+
+	/// Returns the command line as it was passed, cut at [`MAX_CMDLINE`].
+	pub fn raw(&self) -> &str {
+		return from_utf8(&self.raw[..self.len]).unwrap_or("");
+	}
+
+	/// Returns the value of the last `key=value` option for `key`, an empty
+	/// one for a bare `key`.
+	pub fn get(&self, key: &str) -> Option<&str> {
+		let option = self
+			.options
+			.iter()
+			.rev()
+			.find(|option| self.text(&option.key) == key)?;
+
+		return Some(
+			option.value.as_ref().map_or("", |value| self.text(value)),
+		);
+	}
+
+	/// Returns whether `key` is on the command line, with a value or not.
+	pub fn has(&self, key: &str) -> bool {
+		return self.get(key).is_some();
+	}
+
+	/// Returns the number of options.
+	pub fn len(&self) -> usize {
+		return self.options.len();
+	}
+
+	/// Returns whether there are no options.
+	pub fn is_empty(&self) -> bool {
+		return self.options.is_empty();
+	}
+
+	// Helper returning the part of the command line at `range`
+	fn text(&self, range: &Range<usize>) -> &str {
+		return from_utf8(&self.raw[range.clone()]).unwrap_or("");
+	}
+}
+
+/// Copies the command line of `boot_info` (flags bit 2) and parses it. Has
+/// to run before the allocators are set up, later calls do nothing.
+pub fn init(boot_info: &MultibootInfo) {
+	let cmdline = boot_info.cmdline().unwrap_or("");
+	let _ = BOOT_ARGS.set(BootArgs::parse(cmdline));
+}
+
+/// Returns the value of option `key`, see [`BootArgs::get`]. `None` before
+/// [`init`].
+pub fn get(key: &str) -> Option<&'static str> {
+	return BOOT_ARGS.get().and_then(|args| args.get(key));
+}
+
+/// Returns whether option `key` was passed.
+pub fn has(key: &str) -> bool {
+	return get(key).is_some();
+}
+
+/// Returns the whole command line, empty before [`init`].
+pub fn raw() -> &'static str {
+	return BOOT_ARGS.get().map_or("", BootArgs::raw);
+}
@@ -1,9 +1,15 @@
 use std::{
-	env, fs,
+	env,
+	fmt::Write,
+	fs,
 	path::Path,
 	process::{exit, Command},
 };
 
+/// `nm -n -C` listing of the previous link, see `make all`. `make test`
+/// names the test kernel's own listing in `FERRITE_SYMBOLS` instead.
+const SYMBOL_LISTING: &str = "ferrite.sym";
+
 fn compile_asm(out_dir: &String) {
 	let arch_dir = Path::new("../arch/x86");
 	let asm_files = fs::read_dir(arch_dir).unwrap_or_else(|e| {
@@ -35,51 +41,67 @@ fn compile_asm(out_dir: &String) {
 	}
 }
 
-fn compile_c(out_dir: &String) {
-	let builtin = Path::new("./src/libc/builtin/");
-	let c_files = fs::read_dir(builtin).unwrap_or_else(|e| {
-		eprint!("Failed to read directory: {}", e);
-		exit(1);
-	});
-
-	for entry in c_files {
-		let path = entry.unwrap().path();
-		if path.extension().and_then(|s| s.to_str()) == Some("c") {
-			let file_stem = path.file_stem().unwrap().to_str().unwrap();
-			let output = format!("{}/{}.o", out_dir, file_stem);
+/// Returns the name of a Rust symbol without its `::h0123456789abcdef` hash.
+fn strip_hash(name: &str) -> &str {
+	match name.rsplit_once("::h") {
+		Some((path, hash))
+			if hash.len() == 16
+				&& hash.chars().all(|c| c.is_ascii_hexdigit()) =>
+		{
+			path
+		}
+		_ => name,
+	}
+}
 
-			println!("cargo:warning=Compiling {}", path.display());
+/// Writes the kernel symbol table, from the functions in the listing of the
+/// previous link. Without a listing the table is empty and backtraces show
+/// bare addresses.
+fn generate_symbols(out_dir: &String) {
+	println!("cargo:rerun-if-env-changed=FERRITE_SYMBOLS");
+	let path = env::var("FERRITE_SYMBOLS")
+		.unwrap_or_else(|_| String::from(SYMBOL_LISTING));
+	println!("cargo:rerun-if-changed={}", path);
+
+	let listing = fs::read_to_string(&path).unwrap_or_else(|_| {
+		println!("cargo:warning=No {}, backtraces are not symbolized", path);
+		String::new()
+	});
 
-			let status = Command::new("gcc")
-				.args([
-					"-c",
-					path.to_str().unwrap(),
-					"-o",
-					&output,
-					"-nostdlib",
-					"-ffreestanding",
-					"-fno-stack-protector",
-					"-mno-red-zone",
-					"-Wall",
-					"-Wextra",
-					"-Werror",
-					"-m32",
-					"-march=i386",
-					"-fPIC",
-					"-O3",
-					"-I./src/libc/builtin/",
-				])
-				.status()
-				.expect("Could not compile C file correctly");
+	let mut symbols: Vec<(usize, &str)> = listing
+		.lines()
+		.filter_map(|line| {
+			let mut fields = line.splitn(3, ' ');
+			let address = fields.next()?;
+			let kind = fields.next()?;
+			let name = fields.next()?;
 
-			if !status.success() {
-				eprintln!("C compilation failed for {}", path.display());
-				exit(1);
+			if !matches!(kind, "T" | "t" | "W" | "w") {
+				return None;
 			}
-
-			println!("cargo:rustc-link-arg={}", output);
-		}
+			let address = usize::from_str_radix(address, 16).ok()?;
+			return Some((address, strip_hash(name)));
+		})
+		.collect();
+	symbols.sort_by_key(|&(address, _)| address);
+	symbols.dedup_by_key(|&mut (address, _)| address);
+
+	let mut table = String::new();
+	let _ = writeln!(table, "#[link_section = \".ksymtab\"]");
+	let _ = writeln!(
+		table,
+		"static SYMBOL_TABLE: [(usize, &str); {}] = [",
+		symbols.len()
+	);
+	for (address, name) in symbols {
+		let _ = writeln!(table, "\t({:#x}, {:?}),", address, name);
 	}
+	let _ = writeln!(table, "];");
+
+	fs::write(format!("{}/symbols.rs", out_dir), table).unwrap_or_else(|e| {
+		eprint!("Failed to write the symbol table: {}", e);
+		exit(1);
+	});
 }
 
 fn main() {
@@ -88,10 +110,10 @@ fn main() {
 		exit(1);
 	});
 
-	compile_c(&out_dir);
-
 	compile_asm(&out_dir);
 
+	generate_symbols(&out_dir);
+
 	// Tell cargo where to find our objects
 	println!("cargo:rustc-link-search={}", out_dir);
 
@@ -105,9 +127,9 @@ fn main() {
 	// Watch for changes
 	println!("cargo:rerun-if-changed=../arch/x86/gdt.asm");
 	println!("cargo:rerun-if-changed=../arch/x86/boot.asm");
+	println!("cargo:rerun-if-changed=../arch/x86/isr.asm");
 	println!("cargo:rerun-if-changed=../arch/x86/paging.asm");
-	println!("cargo:rerun-if-changed=./src/libc/builtin/memset.c");
-	println!("cargo:rerun-if-changed=./src/libc/builtin/memcpy.c");
-	println!("cargo:rerun-if-changed=./src/libc/builtin/memcmp.c");
+	println!("cargo:rerun-if-changed=../arch/x86/usermode.asm");
+	println!("cargo:rerun-if-changed=../arch/x86/switch.asm");
 	println!("cargo:rerun-if-changed=../arch/x86/x86.ld");
 }
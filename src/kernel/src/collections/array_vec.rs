@@ -0,0 +1,222 @@
+//! A vector with a fixed capacity that lives inline, for code that runs
+//! before the heap exists.
+//!
+//! [`ArrayVec`] keeps up to `N` elements in an array and the number in use,
+//! so it works in statics and on the stack. Instead of growing, it hands the
+//! element back in a [`CapacityError`] when it is full.
+
+use core::{
+	fmt,
+	mem::MaybeUninit,
+	ops::{Deref, DerefMut},
+	ptr, slice,
+};
+
+/// The error of adding to a full [`ArrayVec`], holding the element that did
+/// not fit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CapacityError<T>(pub T);
+
+/// A vector of at most `N` elements stored inline, see the module
+/// documentation. Derefs to the slice of its elements.
+pub struct ArrayVec<T, const N: usize> {
+	items: [MaybeUninit<T>; N],
+	len: usize,
+}
+
+impl<T, const N: usize> ArrayVec<T, N> {
+	/// Creates an empty vector.
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		return Self {
+			items: [const { MaybeUninit::uninit() }; N],
+			len: 0,
+		};
+	}
+
+	/// Returns the number of elements.
+	pub const fn len(&self) -> usize {
+		return self.len;
+	}
+
+	/// Returns whether there are no elements.
+	pub const fn is_empty(&self) -> bool {
+		return self.len == 0;
+	}
+
+	/// Returns whether another element would not fit.
+	pub const fn is_full(&self) -> bool {
+		return self.len == N;
+	}
+
+	/// Returns the number of elements the vector can hold, `N`.
+	pub const fn capacity(&self) -> usize {
+		return N;
+	}
+
+	/// Appends `value`, or hands it back if the vector is full.
+	pub fn push(&mut self, value: T) -> Result<(), CapacityError<T>> {
+		if self.is_full() {
+			return Err(CapacityError(value));
+		}
+
+		self.items[self.len].write(value);
+		self.len += 1;
+		return Ok(());
+	}
+
+	/// Removes the last element and returns it.
+	pub fn pop(&mut self) -> Option<T> {
+		if self.len == 0 {
+			return None;
+		}
+
+		self.len -= 1;
+		// Safety: the element was in use and no longer counts as such.
+		return Some(unsafe { self.items[self.len].assume_init_read() });
+	}
+
+	/// Inserts `value` at `index`, shifting the elements after it up, or
+	/// hands it back if the vector is full.
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is greater than the length.
+	pub fn insert(
+		&mut self,
+		index: usize,
+		value: T,
+	) -> Result<(), CapacityError<T>> {
+		assert!(index <= self.len, "ArrayVec insert index out of bounds");
+		if self.is_full() {
+			return Err(CapacityError(value));
+		}
+
+		// Safety: both ranges are within the array, `index..len` is in use.
+		unsafe {
+			let at = self.items.as_mut_ptr().add(index);
+			ptr::copy(at, at.add(1), self.len - index);
+			(*at).write(value);
+		}
+		self.len += 1;
+		return Ok(());
+	}
+
+	/// Removes the element at `index` and returns it, shifting the elements
+	/// after it down.
+	///
+	/// # Panics
+	///
+	/// Panics if `index` is out of bounds.
+	pub fn remove(&mut self, index: usize) -> T {
+		assert!(index < self.len, "ArrayVec remove index out of bounds");
+
+		// Safety: `index` is in use, the elements after it are moved over it
+		// once it has been read.
+		unsafe {
+			let at = self.items.as_mut_ptr().add(index);
+			let value = (*at).assume_init_read();
+			ptr::copy(at.add(1), at, self.len - index - 1);
+			self.len -= 1;
+			return value;
+		}
+	}
+
+	/// Drops the elements from `len` on, if there are that many.
+	pub fn truncate(&mut self, len: usize) {
+		if len >= self.len {
+			return;
+		}
+
+		let tail = ptr::slice_from_raw_parts_mut(
+			self.as_mut_ptr().wrapping_add(len),
+			self.len - len,
+		);
+		// Changed first, so a panicking drop leaves nothing dropped twice.
+		self.len = len;
+		// Safety: the tail was in use and no longer counts as such.
+		unsafe { ptr::drop_in_place(tail) };
+	}
+
+	/// Drops every element.
+	pub fn clear(&mut self) {
+		self.truncate(0);
+	}
+
+	/// Returns the elements as a slice.
+	pub fn as_slice(&self) -> &[T] {
+		// Safety: the first `len` elements are initialized.
+		return unsafe { slice::from_raw_parts(self.as_ptr(), self.len) };
+	}
+
+	/// Returns the elements as a mutable slice.
+	pub fn as_mut_slice(&mut self) -> &mut [T] {
+		// Safety: the first `len` elements are initialized.
+		return unsafe {
+			slice::from_raw_parts_mut(self.as_mut_ptr(), self.len)
+		};
+	}
+
+	// Helper returning a pointer to the first element
+	fn as_ptr(&self) -> *const T {
+		return self.items.as_ptr().cast();
+	}
+
+	// Helper returning a mutable pointer to the first element
+	fn as_mut_ptr(&mut self) -> *mut T {
+		return self.items.as_mut_ptr().cast();
+	}
+}
+
+impl<T, const N: usize> Drop for ArrayVec<T, N> {
+	fn drop(&mut self) {
+		self.clear();
+	}
+}
+
+impl<T, const N: usize> Deref for ArrayVec<T, N> {
+	type Target = [T];
+
+	fn deref(&self) -> &[T] {
+		return self.as_slice();
+	}
+}
+
+impl<T, const N: usize> DerefMut for ArrayVec<T, N> {
+	fn deref_mut(&mut self) -> &mut [T] {
+		return self.as_mut_slice();
+	}
+}
+
+impl<T: Clone, const N: usize> Clone for ArrayVec<T, N> {
+	fn clone(&self) -> Self {
+		let mut clone = Self::new();
+		for item in self.iter() {
+			// Cannot fail, the clone has the same capacity
+			let _ = clone.push(item.clone());
+		}
+
+		return clone;
+	}
+}
+
+impl<T: fmt::Debug, const N: usize> fmt::Debug for ArrayVec<T, N> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		return f.debug_list().entries(self.iter()).finish();
+	}
+}
+
+impl<T: PartialEq, const N: usize> PartialEq for ArrayVec<T, N> {
+	fn eq(&self, other: &Self) -> bool {
+		return self.as_slice() == other.as_slice();
+	}
+}
+
+impl<'a, T, const N: usize> IntoIterator for &'a ArrayVec<T, N> {
+	type IntoIter = slice::Iter<'a, T>;
+	type Item = &'a T;
+
+	fn into_iter(self) -> Self::IntoIter {
+		return self.iter();
+	}
+}
@@ -0,0 +1,233 @@
+//! A fixed size set of bits over borrowed words.
+//!
+//! The allocators keep their bitmaps in memory they carve out themselves, so
+//! [`Bitmap`] does not own its storage, it wraps a `&'static mut [usize]`.
+//! Bit `n` lives in word `n / usize::BITS` at position `n % usize::BITS`.
+//! What a set bit means is up to the user. Range operations work a word at a
+//! time and are clamped to the length of the bitmap.
+
+use core::ops::Range;
+
+const BITS: usize = usize::BITS as usize;
+
+/// A bitmap of [`Bitmap::len`] bits, see the module documentation.
+#[derive(Debug)]
+pub struct Bitmap {
+	words: &'static mut [usize],
+	len: usize,
+}
+
+impl Bitmap {
+	/// Creates a bitmap of `len` bits kept in `words`, which are used as
+	/// they are.
+	///
+	/// # Panics
+	///
+	/// Panics if `words` holds fewer than `len` bits.
+	pub const fn new(words: &'static mut [usize], len: usize) -> Self {
+		assert!(
+			words.len() >= Self::words_for(len),
+			"Bitmap words too small"
+		);
+
+		return Self {
+			words,
+			len,
+		};
+	}
+
+	/// Creates a bitmap without any bits.
+	pub const fn empty() -> Self {
+		return Self {
+			words: &mut [],
+			len: 0,
+		};
+	}
+
+	/// Returns the number of words needed for `len` bits.
+	pub const fn words_for(len: usize) -> usize {
+		return len.div_ceil(BITS);
+	}
+
+	/// Returns the number of bits.
+	pub const fn len(&self) -> usize {
+		return self.len;
+	}
+
+	/// Returns whether the bitmap has no bits.
+	pub const fn is_empty(&self) -> bool {
+		return self.len == 0;
+	}
+
+	/// Returns whether bit `bit` is set.
+	///
+	/// # Panics
+	///
+	/// Panics if `bit` is out of range.
+	pub fn test(&self, bit: usize) -> bool {
+		assert!(bit < self.len, "Bitmap bit {} out of range", bit);
+
+		return self.words[bit / BITS] & (1 << (bit % BITS)) != 0;
+	}
+
+	/// Sets bit `bit`.
+	///
+	/// # Panics
+	///
+	/// Panics if `bit` is out of range.
+	pub fn set(&mut self, bit: usize) {
+		assert!(bit < self.len, "Bitmap bit {} out of range", bit);
+
+		self.words[bit / BITS] |= 1 << (bit % BITS);
+	}
+
+	/// Clears bit `bit`.
+	///
+	/// # Panics
+	///
+	/// Panics if `bit` is out of range.
+	pub fn clear(&mut self, bit: usize) {
+		assert!(bit < self.len, "Bitmap bit {} out of range", bit);
+
+		self.words[bit / BITS] &= !(1 << (bit % BITS));
+	}
+
+	/// Sets every bit of `range`.
+	pub fn set_range(&mut self, range: Range<usize>) {
+		for (word, mask) in self.masks(range) {
+			self.words[word] |= mask;
+		}
+	}
+
+	/// Clears every bit of `range`.
+	pub fn clear_range(&mut self, range: Range<usize>) {
+		for (word, mask) in self.masks(range) {
+			self.words[word] &= !mask;
+		}
+	}
+
+	/// Sets or clears every bit.
+	pub fn fill(&mut self, value: bool) {
+		match value {
+			true => self.set_range(0..self.len),
+			false => self.clear_range(0..self.len),
+		}
+	}
+
+	/// Returns whether no bit of `range` is set.
+	pub fn is_range_clear(&self, range: Range<usize>) -> bool {
+		return self
+			.masks(range)
+			.all(|(word, mask)| self.words[word] & mask == 0);
+	}
+
+	/// Returns whether every bit of `range` is set.
+	pub fn is_range_set(&self, range: Range<usize>) -> bool {
+		return self
+			.masks(range)
+			.all(|(word, mask)| self.words[word] & mask == mask);
+	}
+
+	/// Returns the number of set bits.
+	pub fn count_ones(&self) -> usize {
+		return self.count_ones_in(0..self.len);
+	}
+
+	/// Returns the number of set bits in `range`.
+	pub fn count_ones_in(&self, range: Range<usize>) -> usize {
+		return self
+			.masks(range)
+			.map(|(word, mask)| (self.words[word] & mask).count_ones() as usize)
+			.sum();
+	}
+
+	/// Returns the first clear bit.
+	pub fn find_first_zero(&self) -> Option<usize> {
+		return self.find_first_zero_in(0..self.len);
+	}
+
+	/// Returns the first clear bit of `range`.
+	pub fn find_first_zero_in(&self, range: Range<usize>) -> Option<usize> {
+		return self.masks(range).find_map(|(word, mask)| {
+			let zeros = !self.words[word] & mask;
+			return (zeros != 0)
+				.then(|| word * BITS + zeros.trailing_zeros() as usize);
+		});
+	}
+
+	/// Returns the first set bit of `range`.
+	pub fn find_first_one_in(&self, range: Range<usize>) -> Option<usize> {
+		return self.masks(range).find_map(|(word, mask)| {
+			let ones = self.words[word] & mask;
+			return (ones != 0)
+				.then(|| word * BITS + ones.trailing_zeros() as usize);
+		});
+	}
+
+	/// Returns the start of the first run of `len` clear bits.
+	pub fn find_zero_run(&self, len: usize) -> Option<usize> {
+		return self.find_zero_run_in(0..self.len, len, 1);
+	}
+
+	/// Returns the start of the first run of `len` clear bits in `range`
+	/// that starts on a multiple of `align`. Returns `None` for an empty run.
+	///
+	/// # Panics
+	///
+	/// Panics if `align` is zero.
+	pub fn find_zero_run_in(
+		&self,
+		range: Range<usize>,
+		len: usize,
+		align: usize,
+	) -> Option<usize> {
+		assert!(align > 0, "Bitmap run alignment must not be zero");
+		if len == 0 {
+			return None;
+		}
+
+		let range = self.clamp(range);
+		let mut start = range.start.checked_next_multiple_of(align)?;
+
+		while let Some(end) =
+			start.checked_add(len).filter(|&end| end <= range.end)
+		{
+			match self.find_first_one_in(start..end) {
+				Some(used) => {
+					start = (used + 1).checked_next_multiple_of(align)?
+				}
+				None => return Some(start),
+			}
+		}
+
+		return None;
+	}
+
+	// Helper to cut `range` down to the bits of the bitmap
+	fn clamp(&self, range: Range<usize>) -> Range<usize> {
+		let end = range.end.min(self.len);
+
+		return range.start.min(end)..end;
+	}
+
+	// Helper to walk the words `range` touches, with the mask of its bits in
+	// each of them
+	fn masks(
+		&self,
+		range: Range<usize>,
+	) -> impl Iterator<Item = (usize, usize)> {
+		let range = self.clamp(range);
+		let words = range.start / BITS..range.end.div_ceil(BITS);
+
+		return words.map(move |word| {
+			let low = range.start.max(word * BITS) - word * BITS;
+			let high = range.end.min(word * BITS + BITS) - word * BITS;
+			let below_high = match high {
+				BITS => usize::MAX,
+				_ => (1 << high) - 1,
+			};
+
+			return (word, below_high & !((1 << low) - 1));
+		});
+	}
+}
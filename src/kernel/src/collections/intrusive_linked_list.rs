@@ -17,6 +17,7 @@ pub struct IntrusiveNode<T: ?Sized> {
 	container: Option<NonNull<T>>,
 	next: Option<NonNull<IntrusiveNode<T>>>,
 	prev: Option<NonNull<IntrusiveNode<T>>>,
+	linked: bool,
 	_marker: core::marker::PhantomData<T>,
 }
 
@@ -35,6 +36,7 @@ impl<T: ?Sized> IntrusiveNode<T> {
 			container,
 			next: None,
 			prev: None,
+			linked: false,
 			_marker: PhantomData,
 		}
 	}
@@ -61,6 +63,38 @@ impl<T: ?Sized> IntrusiveNode<T> {
 	pub unsafe fn container_mut(&mut self) -> Option<&mut T> {
 		self.container.map(|mut ptr| unsafe { ptr.as_mut() })
 	}
+
+	/// Returns the node after this one, or `None` at the end of its list.
+	#[inline]
+	#[must_use]
+	#[allow(clippy::implicit_return)]
+	pub fn next(&self) -> Option<&IntrusiveNode<T>> {
+		self.next.map(|ptr| unsafe { ptr.as_ref() })
+	}
+
+	/// Returns `true` while the node is on a list.
+	#[inline]
+	#[must_use]
+	pub fn is_linked(&self) -> bool {
+		return self.linked;
+	}
+}
+
+/// A broken link found by [`IntrusiveLinkedList::debug_validate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntrusiveListError {
+	/// A node's neighbour does not point back at it, or the walk did not end
+	/// at the other end of the list.
+	BrokenLink,
+	/// A node on the list is not marked as linked.
+	NotLinked,
+	/// Walking the list found a different number of nodes than it counts.
+	LengthMismatch {
+		/// The length the list counts.
+		len: usize,
+		/// The number of nodes found, at most one more than `len`.
+		found: usize,
+	},
 }
 
 /// An intrusive doubly linked list manager.
@@ -99,6 +133,60 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 		self.len
 	}
 
+	/// Returns `true` if `ptr` is one of the nodes of this list. Walks at
+	/// most `len` nodes, so a cycle cannot hang it.
+	#[must_use]
+	pub fn contains(&self, ptr: NonNull<IntrusiveNode<T>>) -> bool {
+		return self.iter().any(|node| ptr::eq(node, ptr.as_ptr()));
+	}
+
+	/// Returns an iterator over the nodes, front to back. It stops after
+	/// `len` nodes.
+	pub fn iter(&self) -> Iter<'_, T> {
+		return Iter {
+			current: self.head,
+			remaining: self.len,
+			_marker: PhantomData,
+		};
+	}
+
+	/// Returns an iterator over the structs the nodes are embedded in, front
+	/// to back, skipping nodes without a back pointer.
+	pub fn containers(&self) -> impl Iterator<Item = &T> {
+		return self.iter().filter_map(IntrusiveNode::container);
+	}
+
+	/// Walks the list forward and backward, checking that every neighbour
+	/// points back, that the walks end at the tail and at the head, that
+	/// every node is marked linked and that both find `len` nodes.
+	///
+	/// Each walk stops one node past `len`, so a cycle cannot hang it.
+	pub fn debug_validate(&self) -> Result<(), IntrusiveListError> {
+		let forward = self.walk(
+			self.head,
+			self.tail,
+			|node| node.next,
+			|node| node.prev,
+		)?;
+		let backward = self.walk(
+			self.tail,
+			self.head,
+			|node| node.prev,
+			|node| node.next,
+		)?;
+
+		for found in [forward, backward] {
+			if found != self.len {
+				return Err(IntrusiveListError::LengthMismatch {
+					len: self.len,
+					found,
+				});
+			}
+		}
+
+		return Ok(());
+	}
+
 	/// Removes the specified node from the list (safe wrapper).
 	///
 	/// # Arguments
@@ -190,8 +278,52 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 
 // Private Interface
 impl<T: ?Sized> IntrusiveLinkedList<T> {
+	/// Walks from `first` along `step` and returns the number of nodes,
+	/// checking that `back` of each node leads to the one before and that
+	/// the walk ends on `last`. Stops one node past `len`.
+	fn walk(
+		&self,
+		first: Option<NonNull<IntrusiveNode<T>>>,
+		last: Option<NonNull<IntrusiveNode<T>>>,
+		step: fn(&IntrusiveNode<T>) -> Option<NonNull<IntrusiveNode<T>>>,
+		back: fn(&IntrusiveNode<T>) -> Option<NonNull<IntrusiveNode<T>>>,
+	) -> Result<usize, IntrusiveListError> {
+		let mut found = 0;
+		let mut previous = None;
+		let mut current = first;
+
+		while let Some(node_ptr) = current {
+			if found > self.len {
+				return Ok(found);
+			}
+
+			let node = unsafe { node_ptr.as_ref() };
+			if back(node) != previous {
+				return Err(IntrusiveListError::BrokenLink);
+			}
+			if !node.linked {
+				return Err(IntrusiveListError::NotLinked);
+			}
+
+			found += 1;
+			previous = current;
+			current = step(node);
+		}
+
+		if previous != last {
+			return Err(IntrusiveListError::BrokenLink);
+		}
+
+		return Ok(found);
+	}
+
 	unsafe fn remove_node(&mut self, mut node_ptr: NonNull<IntrusiveNode<T>>) {
 		let node = unsafe { node_ptr.as_mut() };
+		debug_assert!(
+			node.linked,
+			"Removing intrusive node {:p} that is on no list",
+			node_ptr
+		);
 
 		let prev_node_opt = node.prev;
 		let next_node_opt = node.next;
@@ -223,6 +355,7 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 
 		node.prev = None;
 		node.next = None;
+		node.linked = false;
 	}
 
 	fn pop_front_node(&mut self) -> Option<NonNull<IntrusiveNode<T>>> {
@@ -242,6 +375,7 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 		}
 
 		popped_node.prev = None;
+		popped_node.linked = false;
 		self.len -= 1;
 
 		Some(popped_node_ptr)
@@ -260,9 +394,15 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 		mut node_ptr: NonNull<IntrusiveNode<T>>,
 	) {
 		let node = unsafe { node_ptr.as_mut() };
+		debug_assert!(
+			!node.linked,
+			"Intrusive node {:p} is already linked",
+			node_ptr
+		);
 
 		node.next = self.head;
 		node.prev = None;
+		node.linked = true;
 
 		match self.head {
 			None => {
@@ -295,6 +435,7 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 		}
 
 		popped_node.next = None;
+		popped_node.linked = false;
 		self.len -= 1;
 
 		Some(popped_node_ptr)
@@ -313,9 +454,15 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 		mut node_ptr: NonNull<IntrusiveNode<T>>,
 	) {
 		let node = unsafe { node_ptr.as_mut() };
+		debug_assert!(
+			!node.linked,
+			"Intrusive node {:p} is already linked",
+			node_ptr
+		);
 
 		node.prev = self.tail;
 		node.next = None;
+		node.linked = true;
 
 		match self.tail {
 			None => {
@@ -331,3 +478,39 @@ impl<T: ?Sized> IntrusiveLinkedList<T> {
 		self.len += 1;
 	}
 }
+
+/// An iterator over the nodes of an `IntrusiveLinkedList`, see
+/// [`IntrusiveLinkedList::iter`].
+pub struct Iter<'a, T: ?Sized> {
+	current: Option<NonNull<IntrusiveNode<T>>>,
+	remaining: usize,
+	_marker: PhantomData<&'a IntrusiveNode<T>>,
+}
+
+impl<'a, T: ?Sized> Iterator for Iter<'a, T> {
+	type Item = &'a IntrusiveNode<T>;
+
+	fn next(&mut self) -> Option<&'a IntrusiveNode<T>> {
+		if self.remaining == 0 {
+			return None;
+		}
+
+		let node = unsafe { self.current?.as_ref() };
+		self.current = node.next;
+		self.remaining -= 1;
+		return Some(node);
+	}
+
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		return (0, Some(self.remaining));
+	}
+}
+
+impl<'a, T: ?Sized> IntoIterator for &'a IntrusiveLinkedList<T> {
+	type IntoIter = Iter<'a, T>;
+	type Item = &'a IntrusiveNode<T>;
+
+	fn into_iter(self) -> Iter<'a, T> {
+		return self.iter();
+	}
+}
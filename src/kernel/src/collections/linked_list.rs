@@ -1,7 +1,10 @@
 //! A doubly-linked list with owned nodes.
 
 use alloc::{alloc::Global, boxed::Box};
-use core::{alloc::Allocator, ptr::NonNull};
+use core::{
+	alloc::Allocator, iter::FusedIterator, marker::PhantomData, mem,
+	ptr::NonNull,
+};
 
 /// A node in a doubly-linked list.
 ///
@@ -45,6 +48,10 @@ pub struct LinkedList<T, A: Allocator = Global> {
 	alloc: A,
 }
 
+// The list owns its nodes, so it may move between contexts like its elements
+unsafe impl<T: Send, A: Allocator + Send> Send for LinkedList<T, A> {}
+unsafe impl<T: Sync, A: Allocator + Sync> Sync for LinkedList<T, A> {}
+
 // Private methods
 impl<T, A: Allocator> LinkedList<T, A> {
 	/// Removes and returns the first element from the list, or None if empty.
@@ -173,6 +180,29 @@ impl<T, A: Allocator> LinkedList<T, A> {
 
 		self.len -= 1;
 	}
+
+	/// Returns the node at `index`, walking from whichever end is closer, or
+	/// `None` if `index` is out of bounds.
+	fn node_at(&self, index: usize) -> Option<NonNull<Node<T>>> {
+		if index >= self.len {
+			return None;
+		}
+
+		let mut node;
+		if index < self.len / 2 {
+			node = self.head;
+			for _ in 0..index {
+				node = node.and_then(|node| unsafe { node.as_ref().next });
+			}
+		} else {
+			node = self.tail;
+			for _ in index + 1..self.len {
+				node = node.and_then(|node| unsafe { node.as_ref().prev });
+			}
+		}
+
+		return node;
+	}
 }
 
 impl<T> Default for LinkedList<T> {
@@ -244,8 +274,6 @@ impl<T, A: Allocator> LinkedList<T, A> {
 	/// Removes all elements from the `LinkedList`.
 	#[inline]
 	pub fn clear(&mut self) {
-		use core::mem;
-
 		// We need to drop the nodes while keeping self.alloc
 		// We can do this by moving (head, tail, len) into a new list that
 		// borrows self.alloc
@@ -325,6 +353,107 @@ impl<T, A: Allocator> LinkedList<T, A> {
 		}
 	}
 
+	/// Returns an iterator over the elements, front to back.
+	#[inline]
+	pub fn iter(&self) -> Iter<'_, T> {
+		return Iter {
+			head: self.head,
+			tail: self.tail,
+			len: self.len,
+			marker: PhantomData,
+		};
+	}
+
+	/// Returns an iterator over mutable references to the elements, front to
+	/// back.
+	#[inline]
+	pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+		return IterMut {
+			head: self.head,
+			tail: self.tail,
+			len: self.len,
+			marker: PhantomData,
+		};
+	}
+
+	/// Returns `true` if the list holds an element equal to `value`.
+	pub fn contains(&self, value: &T) -> bool
+	where
+		T: PartialEq,
+	{
+		return self.iter().any(|element| element == value);
+	}
+
+	/// Removes the first element equal to `value`. Returns whether there was
+	/// one.
+	pub fn remove_first(&mut self, value: &T) -> bool
+	where
+		T: PartialEq,
+	{
+		let mut cursor = self.cursor_front_mut();
+		while let Some(element) = cursor.current() {
+			if element == value {
+				cursor.remove_current();
+				return true;
+			}
+			cursor.move_next();
+		}
+
+		return false;
+	}
+
+	/// Keeps only the elements `f` returns `true` for, in one pass from the
+	/// front. The others are removed and dropped.
+	pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+		let mut cursor = self.cursor_front_mut();
+		while let Some(element) = cursor.current() {
+			match f(element) {
+				true => cursor.move_next(),
+				false => drop(cursor.remove_current()),
+			}
+		}
+	}
+
+	/// Moves every element of `other` to the back of the list in O(1),
+	/// leaving `other` empty.
+	///
+	/// The nodes move along with the elements, so they are later freed with
+	/// this list's allocator. Both lists have the same allocator type, which
+	/// has to be able to free what the other one allocated.
+	pub fn append(&mut self, other: &mut Self) {
+		let Some(tail) = self.tail else {
+			mem::swap(self, other);
+			return;
+		};
+
+		let Some(other_head) = other.head.take() else {
+			return;
+		};
+
+		// SAFETY: both nodes are alive and owned by the lists, which are
+		// borrowed mutably
+		unsafe {
+			(*tail.as_ptr()).next = Some(other_head);
+			(*other_head.as_ptr()).prev = Some(tail);
+		}
+
+		self.tail = other.tail.take();
+		self.len += mem::take(&mut other.len);
+	}
+
+	/// Provides a cursor at the front element.
+	///
+	/// The cursor is pointing to the "ghost" non-element if the list is empty.
+	#[inline]
+	#[must_use]
+	pub fn cursor_front(&self) -> Cursor<'_, T, A> {
+		Cursor {
+			index: 0,
+			current: self.head,
+			list: self,
+		}
+	}
+
 	/// Provides a cursor with editing operations at the front element.
 	///
 	/// The cursor is pointing to the "ghost" non-element if the list is empty.
@@ -339,10 +468,43 @@ impl<T, A: Allocator> LinkedList<T, A> {
 	}
 }
 
+impl<T, A: Allocator + Clone> LinkedList<T, A> {
+	/// Splits the list in two at `at`. Returns the elements from `at` on,
+	/// keeping the ones before it. The nodes are reused, none are allocated.
+	///
+	/// # Panics
+	///
+	/// Panics if `at` is greater than the length.
+	pub fn split_off(&mut self, at: usize) -> Self {
+		assert!(at <= self.len, "LinkedList split_off index out of bounds");
+
+		let Some(head) = self.node_at(at) else {
+			return Self::new_in(self.alloc.clone());
+		};
+
+		// SAFETY: the nodes are alive and owned by the list, which is
+		// borrowed mutably
+		let last = unsafe { (*head.as_ptr()).prev.take() };
+		match last {
+			Some(last) => unsafe { (*last.as_ptr()).next = None },
+			None => self.head = None,
+		}
+
+		let second = LinkedList {
+			head: Some(head),
+			tail: self.tail,
+			len: self.len - at,
+			alloc: self.alloc.clone(),
+		};
+		self.tail = last;
+		self.len = at;
+
+		return second;
+	}
+}
+
 unsafe impl<#[may_dangle] T, A: Allocator> Drop for LinkedList<T, A> {
 	fn drop(&mut self) {
-		use core::mem;
-
 		struct DropGuard<'a, T, A: Allocator>(&'a mut LinkedList<T, A>);
 
 		impl<'a, T, A: Allocator> Drop for DropGuard<'a, T, A> {
@@ -366,6 +528,189 @@ unsafe impl<#[may_dangle] T, A: Allocator> Drop for LinkedList<T, A> {
 	}
 }
 
+/// An iterator over the elements of a `LinkedList`, see
+/// [`LinkedList::iter`].
+///
+/// `len` counts the elements between `head` and `tail`, so iterating from
+/// both ends stops once they meet.
+pub struct Iter<'a, T: 'a> {
+	head: Option<NonNull<Node<T>>>,
+	tail: Option<NonNull<Node<T>>>,
+	len: usize,
+	marker: PhantomData<&'a Node<T>>,
+}
+
+impl<'a, T> Iterator for Iter<'a, T> {
+	type Item = &'a T;
+
+	#[inline]
+	fn next(&mut self) -> Option<&'a T> {
+		if self.len == 0 {
+			return None;
+		}
+
+		return self.head.map(|node| {
+			// SAFETY: the node is alive as long as the list is borrowed
+			let node = unsafe { &*node.as_ptr() };
+			self.len -= 1;
+			self.head = node.next;
+			return &node.element;
+		});
+	}
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		return (self.len, Some(self.len));
+	}
+}
+
+impl<'a, T> DoubleEndedIterator for Iter<'a, T> {
+	#[inline]
+	fn next_back(&mut self) -> Option<&'a T> {
+		if self.len == 0 {
+			return None;
+		}
+
+		return self.tail.map(|node| {
+			// SAFETY: as in next
+			let node = unsafe { &*node.as_ptr() };
+			self.len -= 1;
+			self.tail = node.prev;
+			return &node.element;
+		});
+	}
+}
+
+impl<T> ExactSizeIterator for Iter<'_, T> {}
+
+impl<T> FusedIterator for Iter<'_, T> {}
+
+impl<T> Clone for Iter<'_, T> {
+	fn clone(&self) -> Self {
+		return Iter {
+			..*self
+		};
+	}
+}
+
+/// An iterator over mutable references to the elements of a `LinkedList`,
+/// see [`LinkedList::iter_mut`].
+pub struct IterMut<'a, T: 'a> {
+	head: Option<NonNull<Node<T>>>,
+	tail: Option<NonNull<Node<T>>>,
+	len: usize,
+	marker: PhantomData<&'a mut Node<T>>,
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> {
+	type Item = &'a mut T;
+
+	#[inline]
+	fn next(&mut self) -> Option<&'a mut T> {
+		if self.len == 0 {
+			return None;
+		}
+
+		return self.head.map(|node| {
+			// SAFETY: the list is borrowed mutably and every node is handed
+			// out once, `len` stops the two ends from crossing
+			let node = unsafe { &mut *node.as_ptr() };
+			self.len -= 1;
+			self.head = node.next;
+			return &mut node.element;
+		});
+	}
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		return (self.len, Some(self.len));
+	}
+}
+
+impl<'a, T> DoubleEndedIterator for IterMut<'a, T> {
+	#[inline]
+	fn next_back(&mut self) -> Option<&'a mut T> {
+		if self.len == 0 {
+			return None;
+		}
+
+		return self.tail.map(|node| {
+			// SAFETY: as in next
+			let node = unsafe { &mut *node.as_ptr() };
+			self.len -= 1;
+			self.tail = node.prev;
+			return &mut node.element;
+		});
+	}
+}
+
+impl<T> ExactSizeIterator for IterMut<'_, T> {}
+
+impl<T> FusedIterator for IterMut<'_, T> {}
+
+/// An owning iterator over the elements of a `LinkedList`, created by
+/// `into_iter`. The elements left are dropped with it.
+pub struct IntoIter<T, A: Allocator = Global> {
+	list: LinkedList<T, A>,
+}
+
+impl<T, A: Allocator> Iterator for IntoIter<T, A> {
+	type Item = T;
+
+	#[inline]
+	fn next(&mut self) -> Option<T> {
+		return self.list.pop_front();
+	}
+
+	#[inline]
+	fn size_hint(&self) -> (usize, Option<usize>) {
+		return (self.list.len, Some(self.list.len));
+	}
+}
+
+impl<T, A: Allocator> DoubleEndedIterator for IntoIter<T, A> {
+	#[inline]
+	fn next_back(&mut self) -> Option<T> {
+		return self.list.pop_back();
+	}
+}
+
+impl<T, A: Allocator> ExactSizeIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> FusedIterator for IntoIter<T, A> {}
+
+impl<T, A: Allocator> IntoIterator for LinkedList<T, A> {
+	type IntoIter = IntoIter<T, A>;
+	type Item = T;
+
+	#[inline]
+	fn into_iter(self) -> IntoIter<T, A> {
+		return IntoIter {
+			list: self,
+		};
+	}
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a LinkedList<T, A> {
+	type IntoIter = Iter<'a, T>;
+	type Item = &'a T;
+
+	#[inline]
+	fn into_iter(self) -> Iter<'a, T> {
+		return self.iter();
+	}
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a mut LinkedList<T, A> {
+	type IntoIter = IterMut<'a, T>;
+	type Item = &'a mut T;
+
+	#[inline]
+	fn into_iter(self) -> IterMut<'a, T> {
+		return self.iter_mut();
+	}
+}
+
 /************************************* */
 
 /// A cursor over a `LinkedList`.
@@ -433,6 +778,58 @@ impl<'a, T, A: Allocator> Cursor<'a, T, A> {
 			},
 		}
 	}
+
+	/// Moves the cursor to the previous element of the `LinkedList`.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then this will move
+	/// it to the last element of the `LinkedList`. If it is pointing to the
+	/// first element of the `LinkedList` then this will move it to the "ghost"
+	/// non-element.
+	pub fn move_prev(&mut self) {
+		match self.current.take() {
+			// The cursor was on the ghost, the previous element is the tail
+			None => {
+				self.current = self.list.tail;
+				self.index = self.list.len.saturating_sub(1);
+			}
+
+			// Moving back from the head lands on the ghost again
+			Some(current) => unsafe {
+				self.current = current.as_ref().prev;
+				self.index = self.index.checked_sub(1).unwrap_or(self.list.len);
+			},
+		}
+	}
+
+	/// Returns a reference to the element after the current one, without
+	/// moving the cursor.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then this returns
+	/// the first element of the `LinkedList`.
+	#[must_use]
+	pub fn peek_next(&self) -> Option<&'a T> {
+		let next = match self.current {
+			None => self.list.head,
+			Some(current) => unsafe { current.as_ref().next },
+		};
+
+		return next.map(|next| unsafe { &(*next.as_ptr()).element });
+	}
+
+	/// Returns a reference to the element before the current one, without
+	/// moving the cursor.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then this returns
+	/// the last element of the `LinkedList`.
+	#[must_use]
+	pub fn peek_prev(&self) -> Option<&'a T> {
+		let prev = match self.current {
+			None => self.list.tail,
+			Some(current) => unsafe { current.as_ref().prev },
+		};
+
+		return prev.map(|prev| unsafe { &(*prev.as_ptr()).element });
+	}
 }
 
 /// A cursor over a `LinkedList` with editing operations.
@@ -493,6 +890,28 @@ impl<'a, T, A: Allocator> CursorMut<'_, T, A> {
 		}
 	}
 
+	/// Moves the cursor to the previous element of the `LinkedList`.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then this will move
+	/// it to the last element of the `LinkedList`. If it is pointing to the
+	/// first element of the `LinkedList` then this will move it to the "ghost"
+	/// non-element.
+	pub fn move_prev(&mut self) {
+		match self.current.take() {
+			// The cursor was on the ghost, the previous element is the tail
+			None => {
+				self.current = self.list.tail;
+				self.index = self.list.len.saturating_sub(1);
+			}
+
+			// Moving back from the head lands on the ghost again
+			Some(current) => unsafe {
+				self.current = current.as_ref().prev;
+				self.index = self.index.checked_sub(1).unwrap_or(self.list.len);
+			},
+		}
+	}
+
 	/// Returns a reference to the element that the cursor is currently
 	/// pointing to.
 	///
@@ -518,8 +937,95 @@ impl<'a, T, A: Allocator> CursorMut<'_, T, A> {
 			self.current = unlinked_node.as_ref().next;
 			self.list.unlink_node(unlinked_node);
 
-			let unlinked_node = Box::from_raw(unlinked_node.as_ptr());
+			let unlinked_node =
+				Box::from_raw_in(unlinked_node.as_ptr(), &self.list.alloc);
 			Some(unlinked_node.element)
 		}
 	}
+
+	/// Returns a reference to the element after the current one, without
+	/// moving the cursor.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then this returns
+	/// the first element of the `LinkedList`.
+	#[must_use]
+	pub fn peek_next(&mut self) -> Option<&mut T> {
+		let next = match self.current {
+			None => self.list.head,
+			Some(current) => unsafe { current.as_ref().next },
+		};
+
+		return next.map(|next| unsafe { &mut (*next.as_ptr()).element });
+	}
+
+	/// Returns a reference to the element before the current one, without
+	/// moving the cursor.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then this returns
+	/// the last element of the `LinkedList`.
+	#[must_use]
+	pub fn peek_prev(&mut self) -> Option<&mut T> {
+		let prev = match self.current {
+			None => self.list.tail,
+			Some(current) => unsafe { current.as_ref().prev },
+		};
+
+		return prev.map(|prev| unsafe { &mut (*prev.as_ptr()).element });
+	}
+
+	/// Inserts a new element into the `LinkedList` after the current one.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then the new
+	/// element is inserted at the front of the `LinkedList`.
+	pub fn insert_after(&mut self, item: T) {
+		let node = Box::new_in(Node::new(item), &self.list.alloc);
+		let node = NonNull::from(Box::leak(node));
+
+		let Some(current) = self.current else {
+			// SAFETY: node is a unique pointer to a node we boxed with the
+			// list's allocator and leaked
+			unsafe { self.list.push_front_node(node) };
+			return;
+		};
+
+		unsafe {
+			let next = current.as_ref().next;
+			(*node.as_ptr()).prev = Some(current);
+			(*node.as_ptr()).next = next;
+			(*current.as_ptr()).next = Some(node);
+			match next {
+				Some(next) => (*next.as_ptr()).prev = Some(node),
+				None => self.list.tail = Some(node),
+			}
+		}
+		self.list.len += 1;
+	}
+
+	/// Inserts a new element into the `LinkedList` before the current one.
+	///
+	/// If the cursor is pointing to the "ghost" non-element then the new
+	/// element is inserted at the end of the `LinkedList`.
+	pub fn insert_before(&mut self, item: T) {
+		let node = Box::new_in(Node::new(item), &self.list.alloc);
+		let node = NonNull::from(Box::leak(node));
+
+		let Some(current) = self.current else {
+			// SAFETY: as in insert_after
+			unsafe { self.list.push_back_node(node) };
+			return;
+		};
+
+		unsafe {
+			let prev = current.as_ref().prev;
+			(*node.as_ptr()).next = Some(current);
+			(*node.as_ptr()).prev = prev;
+			(*current.as_ptr()).prev = Some(node);
+			match prev {
+				Some(prev) => (*prev.as_ptr()).next = Some(node),
+				None => self.list.head = Some(node),
+			}
+		}
+		self.list.len += 1;
+		self.index += 1;
+	}
 }
@@ -1,2 +1,6 @@
+pub mod array_vec;
+pub mod bitmap;
 pub mod intrusive_linked_list;
 pub mod linked_list;
+pub mod range_map;
+pub mod ring_buffer;
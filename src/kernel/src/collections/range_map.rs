@@ -0,0 +1,221 @@
+//! An ordered map of non-overlapping address ranges.
+//!
+//! [`RangeMap`] keeps [`Range`]s sorted by start address in a [`LinkedList`],
+//! each with a tag saying what it is used for. It answers "which range holds
+//! this address" and "where is a gap of this size" by walking the list, so
+//! both are O(n) for now. Ranges may touch, but inserting one that overlaps
+//! another fails.
+
+use super::linked_list::{self, LinkedList};
+use alloc::alloc::Global;
+use core::{alloc::Allocator, iter, ops};
+
+/// `len` addresses from `start`, tagged with `tag`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Range<T> {
+	/// First address of the range.
+	pub start: usize,
+	/// Number of addresses in the range.
+	pub len: usize,
+	/// What the range is used for.
+	pub tag: T,
+}
+
+impl<T> Range<T> {
+	/// Returns the address after the range.
+	pub const fn end(&self) -> usize {
+		return self.start + self.len;
+	}
+
+	/// Returns whether `addr` is in the range.
+	pub const fn contains(&self, addr: usize) -> bool {
+		return self.start <= addr && addr < self.end();
+	}
+}
+
+/// Why [`RangeMap::insert`] rejected a range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeMapError {
+	/// The range has no addresses.
+	Empty,
+	/// The range runs past the last address.
+	Overflow,
+	/// The range overlaps one already in the map.
+	Overlap,
+}
+
+/// Non-overlapping ranges in address order, see the module documentation.
+pub struct RangeMap<T, A: Allocator = Global> {
+	ranges: LinkedList<Range<T>, A>,
+}
+
+impl<T> RangeMap<T> {
+	/// Creates an empty map whose nodes come from the heap.
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		return Self::new_in(Global);
+	}
+}
+
+impl<T, A: Allocator> RangeMap<T, A> {
+	/// Creates an empty map whose nodes come from `alloc`.
+	pub const fn new_in(alloc: A) -> Self {
+		return Self {
+			ranges: LinkedList::new_in(alloc),
+		};
+	}
+
+	/// Returns the number of ranges.
+	pub fn len(&self) -> usize {
+		return self.ranges.len();
+	}
+
+	/// Returns whether there are no ranges.
+	pub fn is_empty(&self) -> bool {
+		return self.ranges.is_empty();
+	}
+
+	/// Adds the `len` addresses from `start`, tagged with `tag`, keeping the
+	/// map in address order.
+	pub fn insert(
+		&mut self,
+		start: usize,
+		len: usize,
+		tag: T,
+	) -> Result<(), RangeMapError> {
+		if len == 0 {
+			return Err(RangeMapError::Empty);
+		}
+		let end = start.checked_add(len).ok_or(RangeMapError::Overflow)?;
+
+		let mut cursor = self.ranges.cursor_front_mut();
+		while let Some(range) = cursor.current() {
+			if range.start >= end {
+				break;
+			}
+			if range.end() > start {
+				return Err(RangeMapError::Overlap);
+			}
+			cursor.move_next();
+		}
+
+		// On the ghost this appends
+		cursor.insert_before(Range {
+			start,
+			len,
+			tag,
+		});
+		return Ok(());
+	}
+
+	/// Removes the range starting at `start` and returns it.
+	pub fn remove(&mut self, start: usize) -> Option<Range<T>> {
+		let mut cursor = self.ranges.cursor_front_mut();
+		while let Some(range) = cursor.current() {
+			if range.start == start {
+				return cursor.remove_current();
+			}
+			if range.start > start {
+				break;
+			}
+			cursor.move_next();
+		}
+
+		return None;
+	}
+
+	/// Returns the range holding `addr`.
+	pub fn find(&self, addr: usize) -> Option<&Range<T>> {
+		return self
+			.iter()
+			.take_while(|range| range.start <= addr)
+			.find(|range| range.contains(addr));
+	}
+
+	/// Returns the range holding `addr`, to change its tag.
+	pub fn find_mut(&mut self, addr: usize) -> Option<&mut Range<T>> {
+		return self
+			.ranges
+			.iter_mut()
+			.take_while(|range| range.start <= addr)
+			.find(|range| range.contains(addr));
+	}
+
+	/// Returns the start of the first gap of at least `len` addresses
+	/// between the ranges, inside `within`.
+	pub fn find_gap(
+		&self,
+		len: usize,
+		within: ops::Range<usize>,
+	) -> Option<usize> {
+		return self.find_gap_aligned(len, 1, within);
+	}
+
+	/// Like [`RangeMap::find_gap`], but the gap must hold `len` addresses
+	/// from a multiple of `align`, which is returned.
+	///
+	/// # Panics
+	///
+	/// Panics if `align` is zero.
+	pub fn find_gap_aligned(
+		&self,
+		len: usize,
+		align: usize,
+		within: ops::Range<usize>,
+	) -> Option<usize> {
+		assert!(align > 0, "RangeMap gap alignment must not be zero");
+		if len == 0 {
+			return None;
+		}
+
+		return self.gaps(within).find_map(|gap| {
+			let start = gap.start.checked_next_multiple_of(align)?;
+			return start
+				.checked_add(len)
+				.filter(|&end| end <= gap.end)
+				.map(|_| start);
+		});
+	}
+
+	/// Returns the stretches of `within` no range covers, in address order.
+	pub fn gaps(
+		&self,
+		within: ops::Range<usize>,
+	) -> impl Iterator<Item = ops::Range<usize>> + '_ {
+		let mut ranges = self.iter();
+		let mut start = within.start;
+
+		return iter::from_fn(move || {
+			while start < within.end {
+				let (end, next) = match ranges.next() {
+					Some(range) => {
+						(range.start.min(within.end), range.end().max(start))
+					}
+					None => (within.end, within.end),
+				};
+
+				let gap = start..end;
+				start = next;
+				if !gap.is_empty() {
+					return Some(gap);
+				}
+			}
+
+			return None;
+		});
+	}
+
+	/// Returns an iterator over the ranges in address order.
+	pub fn iter(&self) -> linked_list::Iter<'_, Range<T>> {
+		return self.ranges.iter();
+	}
+}
+
+impl<'a, T, A: Allocator> IntoIterator for &'a RangeMap<T, A> {
+	type IntoIter = linked_list::Iter<'a, Range<T>>;
+	type Item = &'a Range<T>;
+
+	fn into_iter(self) -> Self::IntoIter {
+		return self.iter();
+	}
+}
@@ -0,0 +1,103 @@
+//! A fixed capacity ring buffer for one producer and one consumer.
+//!
+//! [`SpscRing`] lets an interrupt handler hand data to the main loop without
+//! a lock the main loop may be holding. The producer only moves `tail` and
+//! the consumer only moves `head`, so each side just publishes its own index
+//! with `Release` and reads the other one with `Acquire`. Neither side ever
+//! waits: `push` fails when the ring is full and `pop` when it is empty.
+//!
+//! Both indices count up and wrap, their difference is the number of
+//! elements. `N` must be a power of two so that wrapping keeps `index % N`
+//! continuous.
+
+use core::{
+	cell::UnsafeCell,
+	mem::MaybeUninit,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// A ring of up to `N` elements, see the module documentation. Usable in a
+/// `static`.
+///
+/// Any number of contexts may call its methods, but at most one at a time
+/// may push and at most one at a time may pop.
+pub struct SpscRing<T: Copy, const N: usize> {
+	slots: [UnsafeCell<MaybeUninit<T>>; N],
+	head: AtomicUsize,
+	tail: AtomicUsize,
+}
+
+// Safety: a slot is only written by the producer before it publishes `tail`
+// and only read by the consumer after it saw that `tail`.
+unsafe impl<T: Copy + Send, const N: usize> Sync for SpscRing<T, N> {}
+
+impl<T: Copy, const N: usize> SpscRing<T, N> {
+	/// Creates an empty ring. Fails to compile if `N` is not a power of two.
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		const {
+			assert!(N.is_power_of_two(), "SpscRing size must be a power of two")
+		};
+
+		return Self {
+			slots: [const { UnsafeCell::new(MaybeUninit::uninit()) }; N],
+			head: AtomicUsize::new(0),
+			tail: AtomicUsize::new(0),
+		};
+	}
+
+	/// Appends `value`. Returns `false` and drops it if the ring is full.
+	///
+	/// Only called by the producer.
+	pub fn push(&self, value: T) -> bool {
+		let tail = self.tail.load(Ordering::Relaxed);
+		if tail.wrapping_sub(self.head.load(Ordering::Acquire)) == N {
+			return false;
+		}
+
+		// Safety: the slot is outside `head..tail`, the consumer does not
+		// touch it until the store below.
+		unsafe { (*self.slots[tail % N].get()).write(value) };
+		self.tail.store(tail.wrapping_add(1), Ordering::Release);
+		return true;
+	}
+
+	/// Removes the oldest element and returns it.
+	///
+	/// Only called by the consumer.
+	pub fn pop(&self) -> Option<T> {
+		let head = self.head.load(Ordering::Relaxed);
+		if head == self.tail.load(Ordering::Acquire) {
+			return None;
+		}
+
+		// Safety: the slot is inside `head..tail`, so the producer wrote it
+		// and does not reuse it until the store below.
+		let value = unsafe { (*self.slots[head % N].get()).assume_init() };
+		self.head.store(head.wrapping_add(1), Ordering::Release);
+		return Some(value);
+	}
+
+	/// Returns the number of elements waiting. Only a snapshot while the
+	/// other side is running.
+	pub fn len(&self) -> usize {
+		let head = self.head.load(Ordering::Acquire);
+
+		return self.tail.load(Ordering::Acquire).wrapping_sub(head);
+	}
+
+	/// Returns whether no element is waiting.
+	pub fn is_empty(&self) -> bool {
+		return self.len() == 0;
+	}
+
+	/// Returns whether a push would fail.
+	pub fn is_full(&self) -> bool {
+		return self.len() == N;
+	}
+
+	/// Returns the number of elements the ring can hold, `N`.
+	pub const fn capacity(&self) -> usize {
+		return N;
+	}
+}
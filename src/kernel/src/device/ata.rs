@@ -0,0 +1,398 @@
+//! ATA disks on the primary IDE channel, driven by PIO.
+//!
+//! The channel's task file is at ports 0x1F0-0x1F7 and its control block at
+//! 0x3F6. [`init`] selects the master and then the slave drive and sends
+//! each IDENTIFY DEVICE. The first one that answers as an ATA disk becomes
+//! the disk [`read_sectors`] and [`write_sectors`] work on. A drive with a
+//! signature in the LBA registers is an ATAPI drive, like a CD-ROM, and is
+//! skipped.
+//!
+//! Transfers take 28-bit LBAs and move a sector at a time through the data
+//! port, 256 words each. The channel's interrupt is turned off, the driver
+//! polls the status register instead: BSY has to clear and then DRQ set
+//! before each sector, and ERR or DF end the command with the error
+//! register decoded into an [`AtaError`]. Each wait gives up after
+//! [`POLL_LIMIT`] reads of the status, so a missing or hung drive does not
+//! hang the kernel with it.
+//!
+//! The disk is registered as the block device `ata0`, see [`AtaDisk`].
+
+use super::block::{self, check_block, BlockDevice};
+use crate::{
+	arch::x86::io::{inb, inw, outb, outw},
+	error::KernelError,
+	log_info, log_warn,
+	sync::Mutex,
+};
+use alloc::{boxed::Box, string::String};
+use core::fmt;
+
+/// Bytes in a sector.
+pub const SECTOR_SIZE: usize = 512;
+
+/// One past the last sector a 28-bit LBA reaches.
+pub const LBA28_LIMIT: u32 = 1 << 28;
+
+/// Reads of the status register before a wait times out.
+pub const POLL_LIMIT: u32 = 1_000_000;
+
+const IO_BASE: u16 = 0x1f0;
+const CONTROL_BASE: u16 = 0x3f6;
+
+const REG_DATA: u16 = IO_BASE;
+const REG_ERROR: u16 = IO_BASE + 1;
+const REG_SECTOR_COUNT: u16 = IO_BASE + 2;
+const REG_LBA_LOW: u16 = IO_BASE + 3;
+const REG_LBA_MID: u16 = IO_BASE + 4;
+const REG_LBA_HIGH: u16 = IO_BASE + 5;
+const REG_DRIVE: u16 = IO_BASE + 6;
+const REG_STATUS: u16 = IO_BASE + 7;
+const REG_COMMAND: u16 = IO_BASE + 7;
+const REG_ALT_STATUS: u16 = CONTROL_BASE;
+const REG_DEVICE_CONTROL: u16 = CONTROL_BASE;
+
+const STATUS_ERR: u8 = 1 << 0;
+const STATUS_DRQ: u8 = 1 << 3;
+const STATUS_DF: u8 = 1 << 5;
+const STATUS_BSY: u8 = 1 << 7;
+
+/// No interrupts from the channel, the driver polls.
+const CONTROL_NIEN: u8 = 1 << 1;
+
+/// The drive register with LBA addressing, the drive goes in bit 4.
+const DRIVE_LBA: u8 = 0xe0;
+
+const COMMAND_READ_SECTORS: u8 = 0x20;
+const COMMAND_WRITE_SECTORS: u8 = 0x30;
+const COMMAND_CACHE_FLUSH: u8 = 0xe7;
+const COMMAND_IDENTIFY: u8 = 0xec;
+
+/// Names of the error register bits, lowest first.
+const ERROR_NAMES: [&str; 8] = [
+	"address mark not found",
+	"track 0 not found",
+	"aborted command",
+	"media change requested",
+	"sector not found",
+	"media changed",
+	"uncorrectable data",
+	"bad block",
+];
+
+/// A drive on the channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drive {
+	/// The first drive.
+	Master,
+	/// The second drive.
+	Slave,
+}
+
+impl Drive {
+	/// Returns the name of the drive.
+	pub const fn name(self) -> &'static str {
+		match self {
+			Drive::Master => return "master",
+			Drive::Slave => return "slave",
+		}
+	}
+}
+
+/// Why a transfer failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AtaError {
+	/// No ATA disk is on the channel.
+	NoDisk,
+	/// The drive kept BSY set, or never set DRQ.
+	Timeout,
+	/// The drive reported a fault.
+	DeviceFault,
+	/// The drive failed the command, with the bits of its error register.
+	Device(u8),
+	/// The sectors are past the end of the disk or a 28-bit LBA.
+	OutOfRange,
+	/// The buffer is smaller than the sectors.
+	BufferTooSmall,
+}
+
+impl fmt::Display for AtaError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			AtaError::NoDisk => return f.write_str("no disk"),
+			AtaError::Timeout => return f.write_str("timed out"),
+			AtaError::DeviceFault => return f.write_str("device fault"),
+			AtaError::Device(error) => return f.write_str(error_name(*error)),
+			AtaError::OutOfRange => return f.write_str("sector out of range"),
+			AtaError::BufferTooSmall => return f.write_str("buffer too small"),
+		}
+	}
+}
+
+/// Returns the name of the lowest bit set in error register value `error`.
+pub fn error_name(error: u8) -> &'static str {
+	return ERROR_NAMES
+		.iter()
+		.enumerate()
+		.find(|&(bit, _)| error & (1 << bit) != 0)
+		.map_or("unknown error", |(_, name)| name);
+}
+
+/// What IDENTIFY DEVICE reported about a disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskInfo {
+	/// The drive the disk is.
+	pub drive: Drive,
+	/// The model string, without the padding.
+	pub model: String,
+	/// Sectors reachable with a 28-bit LBA.
+	pub sectors: u32,
+}
+
+impl DiskInfo {
+	/// Returns the size of the disk in bytes.
+	pub const fn size(&self) -> u64 {
+		return self.sectors as u64 * SECTOR_SIZE as u64;
+	}
+}
+
+/// Reads the model and the sector count out of the words IDENTIFY DEVICE
+/// returned. The model is 40 characters in words 27-46, the first of each
+/// pair in the high byte, and the 28-bit sector count is in words 60-61.
+pub fn parse_identify(drive: Drive, words: &[u16; 256]) -> DiskInfo {
+	let mut model = String::new();
+	for word in &words[27..47] {
+		for byte in word.to_be_bytes() {
+			model.push(match byte {
+				b' '..=b'~' => byte as char,
+				_ => ' ',
+			});
+		}
+	}
+
+	return DiskInfo {
+		drive,
+		model: String::from(model.trim()),
+		sectors: words[60] as u32 | ((words[61] as u32) << 16),
+	};
+}
+
+/// The disk [`init`] found, held for the whole of a command.
+static DISK: Mutex<Option<DiskInfo>> = Mutex::named("ATA", None);
+
+/// Looks for a disk on the primary channel, see the module documentation.
+pub fn init() {
+	outb(REG_DEVICE_CONTROL, CONTROL_NIEN);
+
+	// Nobody pulls the status lines of a channel without drives down
+	if inb(REG_STATUS) == 0xff {
+		return;
+	}
+
+	let mut disk = DISK.lock();
+	*disk = [Drive::Master, Drive::Slave].into_iter().find_map(identify);
+	let Some(info) = &*disk else {
+		return;
+	};
+	log_info!(
+		"ata: {} disk '{}', {} sectors",
+		info.drive.name(),
+		info.model,
+		info.sectors
+	);
+	drop(disk);
+
+	if let Err(error) = block::register("ata0", Box::new(AtaDisk)) {
+		log_warn!("ata: no block device ata0 ({})", error);
+	}
+}
+
+/// Returns what the disk reported, `None` without one.
+pub fn info() -> Option<DiskInfo> {
+	return DISK.lock().clone();
+}
+
+/// Reads `count` sectors starting at `lba` into `buf`. A `count` of 0 reads
+/// nothing, the drive would take it for 256.
+pub fn read_sectors(
+	lba: u32,
+	count: u8,
+	buf: &mut [u8],
+) -> Result<(), AtaError> {
+	if count == 0 {
+		return Ok(());
+	}
+	let disk = DISK.lock();
+	let info = disk.as_ref().ok_or(AtaError::NoDisk)?;
+	check_range(info, lba, count, buf.len())?;
+
+	start_command(info.drive, lba, count, COMMAND_READ_SECTORS);
+	for sector in buf.chunks_exact_mut(SECTOR_SIZE).take(count as usize) {
+		wait_data()?;
+		for word in sector.chunks_exact_mut(2) {
+			word.copy_from_slice(&inw(REG_DATA).to_le_bytes());
+		}
+	}
+
+	return Ok(());
+}
+
+/// Writes `count` sectors from `buf` starting at `lba`, and flushes the
+/// drive's cache. Like [`read_sectors`] otherwise.
+pub fn write_sectors(lba: u32, count: u8, buf: &[u8]) -> Result<(), AtaError> {
+	if count == 0 {
+		return Ok(());
+	}
+	let disk = DISK.lock();
+	let info = disk.as_ref().ok_or(AtaError::NoDisk)?;
+	check_range(info, lba, count, buf.len())?;
+
+	start_command(info.drive, lba, count, COMMAND_WRITE_SECTORS);
+	for sector in buf.chunks_exact(SECTOR_SIZE).take(count as usize) {
+		wait_data()?;
+		for word in sector.chunks_exact(2) {
+			outw(REG_DATA, u16::from_le_bytes([word[0], word[1]]));
+		}
+	}
+
+	wait_ready()?;
+	outb(REG_COMMAND, COMMAND_CACHE_FLUSH);
+	return wait_ready();
+}
+
+/// The disk [`init`] found as a [`BlockDevice`], a block is a sector.
+pub struct AtaDisk;
+
+impl BlockDevice for AtaDisk {
+	fn block_size(&self) -> usize {
+		return SECTOR_SIZE;
+	}
+
+	fn num_blocks(&self) -> u64 {
+		return info().map_or(0, |info| info.sectors as u64);
+	}
+
+	fn read_block(&self, lba: u64, buf: &mut [u8]) -> Result<(), KernelError> {
+		check_block(self, lba, buf.len())?;
+		return Ok(read_sectors(lba as u32, 1, buf)?);
+	}
+
+	fn write_block(&self, lba: u64, buf: &[u8]) -> Result<(), KernelError> {
+		check_block(self, lba, buf.len())?;
+		return Ok(write_sectors(lba as u32, 1, buf)?);
+	}
+}
+
+/// Checks that `count` sectors at `lba` are on the disk and fit a buffer of
+/// `len` bytes.
+fn check_range(
+	info: &DiskInfo,
+	lba: u32,
+	count: u8,
+	len: usize,
+) -> Result<(), AtaError> {
+	let end = lba.checked_add(count as u32).ok_or(AtaError::OutOfRange)?;
+	if end > info.sectors || end > LBA28_LIMIT {
+		return Err(AtaError::OutOfRange);
+	}
+	if len < count as usize * SECTOR_SIZE {
+		return Err(AtaError::BufferTooSmall);
+	}
+
+	return Ok(());
+}
+
+/// Selects `drive` and gives it the 400ns the spec asks for.
+fn select(drive: Drive, lba_top: u8) {
+	let slave = match drive {
+		Drive::Master => 0,
+		Drive::Slave => 1 << 4,
+	};
+	outb(REG_DRIVE, DRIVE_LBA | slave | (lba_top & 0x0f));
+	delay();
+}
+
+/// Waits 400ns, each read of the alternate status takes 100ns.
+fn delay() {
+	for _ in 0..4 {
+		inb(REG_ALT_STATUS);
+	}
+}
+
+/// Sends `command` for `count` sectors at `lba` to `drive`.
+fn start_command(drive: Drive, lba: u32, count: u8, command: u8) {
+	select(drive, (lba >> 24) as u8);
+	outb(REG_SECTOR_COUNT, count);
+	outb(REG_LBA_LOW, lba as u8);
+	outb(REG_LBA_MID, (lba >> 8) as u8);
+	outb(REG_LBA_HIGH, (lba >> 16) as u8);
+	outb(REG_COMMAND, command);
+	delay();
+}
+
+/// Waits for BSY to clear, decoding the error if the command failed.
+fn wait_ready() -> Result<(), AtaError> {
+	for _ in 0..POLL_LIMIT {
+		let status = inb(REG_STATUS);
+		if status & STATUS_BSY != 0 {
+			continue;
+		}
+
+		if status & STATUS_ERR != 0 {
+			return Err(AtaError::Device(inb(REG_ERROR)));
+		}
+		if status & STATUS_DF != 0 {
+			return Err(AtaError::DeviceFault);
+		}
+		return Ok(());
+	}
+
+	return Err(AtaError::Timeout);
+}
+
+/// Waits until the drive has the next sector or wants it.
+fn wait_data() -> Result<(), AtaError> {
+	wait_ready()?;
+
+	for _ in 0..POLL_LIMIT {
+		let status = inb(REG_STATUS);
+		if status & STATUS_ERR != 0 {
+			return Err(AtaError::Device(inb(REG_ERROR)));
+		}
+		if status & STATUS_DRQ != 0 {
+			return Ok(());
+		}
+	}
+
+	return Err(AtaError::Timeout);
+}
+
+/// Sends IDENTIFY DEVICE to `drive`, `None` if it is missing or not an ATA
+/// disk.
+fn identify(drive: Drive) -> Option<DiskInfo> {
+	select(drive, 0);
+	for register in [REG_SECTOR_COUNT, REG_LBA_LOW, REG_LBA_MID, REG_LBA_HIGH] {
+		outb(register, 0);
+	}
+	outb(REG_COMMAND, COMMAND_IDENTIFY);
+	delay();
+
+	if inb(REG_STATUS) == 0 {
+		return None;
+	}
+	for _ in 0..POLL_LIMIT {
+		if inb(REG_STATUS) & STATUS_BSY == 0 {
+			break;
+		}
+	}
+	// ATAPI and SATA drives answer with a signature instead
+	if inb(REG_LBA_MID) != 0 || inb(REG_LBA_HIGH) != 0 {
+		return None;
+	}
+	wait_data().ok()?;
+
+	let mut words = [0; 256];
+	for word in words.iter_mut() {
+		*word = inw(REG_DATA);
+	}
+	return Some(parse_identify(drive, &words));
+}
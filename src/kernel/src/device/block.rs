@@ -0,0 +1,217 @@
+//! Block devices, the disks filesystems sit on.
+//!
+//! A [`BlockDevice`] reads and writes whole blocks by number. Drivers
+//! [`register`] the devices they find under a name, `ata0` for the first
+//! ATA disk, and the users look them up by it with [`with_device`]. The
+//! registry's lock is held while the closure runs, so a device is never
+//! unregistered under a transfer.
+//!
+//! A [`RamDisk`] keeps its blocks in memory. [`init`] registers a small one,
+//! `ram0`, so the block layer can be tried without a disk.
+
+use crate::{
+	error::KernelError,
+	memory::{
+		vmalloc::{vfree, vmalloc},
+		VirtAddr,
+	},
+	sync::{Mutex, RwLock},
+};
+use alloc::{boxed::Box, string::String, vec::Vec};
+use core::ptr;
+
+/// Blocks of the RAM disk [`init`] registers.
+pub const RAM0_BLOCKS: u64 = 128;
+
+/// Bytes in a block of a [`RamDisk`].
+pub const RAM_BLOCK_SIZE: usize = 512;
+
+/// A device storing data in fixed size blocks.
+pub trait BlockDevice: Send + Sync {
+	/// Returns the size of a block in bytes.
+	fn block_size(&self) -> usize;
+
+	/// Returns the number of blocks.
+	fn num_blocks(&self) -> u64;
+
+	/// Reads block `lba` into the first [`block_size`](Self::block_size)
+	/// bytes of `buf`.
+	///
+	/// Returns `InvalidArgument` if `lba` is past the end or `buf` is
+	/// shorter than a block.
+	fn read_block(&self, lba: u64, buf: &mut [u8]) -> Result<(), KernelError>;
+
+	/// Writes the first [`block_size`](Self::block_size) bytes of `buf` to
+	/// block `lba`. Fails like [`read_block`](Self::read_block).
+	fn write_block(&self, lba: u64, buf: &[u8]) -> Result<(), KernelError>;
+}
+
+/// Checks that block `lba` is on `device` and a block fits `len` bytes, the
+/// arguments every [`BlockDevice`] method takes.
+pub fn check_block(
+	device: &dyn BlockDevice,
+	lba: u64,
+	len: usize,
+) -> Result<(), KernelError> {
+	if lba >= device.num_blocks() || len < device.block_size() {
+		return Err(KernelError::InvalidArgument);
+	}
+
+	return Ok(());
+}
+
+/// A block device in memory from [`vmalloc`], zeroed when created and gone
+/// when dropped.
+pub struct RamDisk {
+	/// Start of the blocks, locked for every copy.
+	base: Mutex<VirtAddr>,
+	blocks: u64,
+	block_size: usize,
+}
+
+impl RamDisk {
+	/// Creates a RAM disk of `blocks` blocks of `block_size` bytes.
+	///
+	/// Returns `InvalidArgument` if it would be empty and `OutOfMemory` if
+	/// there is no room for it.
+	pub fn new(blocks: u64, block_size: usize) -> Result<Self, KernelError> {
+		let len = usize::try_from(blocks)
+			.ok()
+			.and_then(|blocks| blocks.checked_mul(block_size))
+			.ok_or(KernelError::OutOfMemory)?;
+		if len == 0 {
+			return Err(KernelError::InvalidArgument);
+		}
+
+		let base = vmalloc(len).ok_or(KernelError::OutOfMemory)?;
+		// Safety: vmalloc mapped `len` bytes at `base`.
+		unsafe { ptr::write_bytes(base.as_mut_ptr::<u8>(), 0, len) };
+
+		return Ok(Self {
+			base: Mutex::named("RAM_DISK", base),
+			blocks,
+			block_size,
+		});
+	}
+}
+
+impl BlockDevice for RamDisk {
+	fn block_size(&self) -> usize {
+		return self.block_size;
+	}
+
+	fn num_blocks(&self) -> u64 {
+		return self.blocks;
+	}
+
+	fn read_block(&self, lba: u64, buf: &mut [u8]) -> Result<(), KernelError> {
+		check_block(self, lba, buf.len())?;
+
+		let base = self.base.lock();
+		// Safety: the block is inside the buffer, checked above.
+		unsafe {
+			let block = base.as_ptr::<u8>().add(lba as usize * self.block_size);
+			ptr::copy_nonoverlapping(block, buf.as_mut_ptr(), self.block_size);
+		}
+		return Ok(());
+	}
+
+	fn write_block(&self, lba: u64, buf: &[u8]) -> Result<(), KernelError> {
+		check_block(self, lba, buf.len())?;
+
+		let base = self.base.lock();
+		// Safety: the block is inside the buffer, checked above.
+		unsafe {
+			let block =
+				base.as_mut_ptr::<u8>().add(lba as usize * self.block_size);
+			ptr::copy_nonoverlapping(buf.as_ptr(), block, self.block_size);
+		}
+		return Ok(());
+	}
+}
+
+impl Drop for RamDisk {
+	fn drop(&mut self) {
+		vfree(*self.base.lock());
+	}
+}
+
+/// A registered device.
+struct Registered {
+	name: String,
+	device: Box<dyn BlockDevice>,
+}
+
+static DEVICES: RwLock<Vec<Registered>> = RwLock::new(Vec::new());
+
+/// What [`list`] reports about a device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockDeviceInfo {
+	/// The name it was registered under.
+	pub name: String,
+	/// Bytes in a block.
+	pub block_size: usize,
+	/// Number of blocks.
+	pub num_blocks: u64,
+}
+
+/// Registers a RAM disk as `ram0`.
+pub fn init() -> Result<(), KernelError> {
+	let ram0 = RamDisk::new(RAM0_BLOCKS, RAM_BLOCK_SIZE)?;
+	return register("ram0", Box::new(ram0));
+}
+
+/// Makes `device` available under `name`.
+///
+/// Returns `InvalidArgument` if the name is taken.
+pub fn register(
+	name: &str,
+	device: Box<dyn BlockDevice>,
+) -> Result<(), KernelError> {
+	let mut devices = DEVICES.write();
+	if devices.iter().any(|registered| registered.name == name) {
+		return Err(KernelError::InvalidArgument);
+	}
+
+	devices.push(Registered {
+		name: String::from(name),
+		device,
+	});
+	return Ok(());
+}
+
+/// Removes the device registered as `name` and returns it, `None` if there
+/// is none.
+pub fn unregister(name: &str) -> Option<Box<dyn BlockDevice>> {
+	let mut devices = DEVICES.write();
+	let index = devices
+		.iter()
+		.position(|registered| registered.name == name)?;
+
+	return Some(devices.remove(index).device);
+}
+
+/// Runs `f` with the device registered as `name`, `None` if there is none.
+pub fn with_device<R>(
+	name: &str,
+	f: impl FnOnce(&dyn BlockDevice) -> R,
+) -> Option<R> {
+	let devices = DEVICES.read();
+	let registered =
+		devices.iter().find(|registered| registered.name == name)?;
+
+	return Some(f(registered.device.as_ref()));
+}
+
+/// Returns the devices registered, in the order they were.
+pub fn list() -> Vec<BlockDeviceInfo> {
+	return DEVICES
+		.read()
+		.iter()
+		.map(|registered| BlockDeviceInfo {
+			name: registered.name.clone(),
+			block_size: registered.device.block_size(),
+			num_blocks: registered.device.num_blocks(),
+		})
+		.collect();
+}
@@ -15,12 +15,30 @@
 //! translates these hardware-level codes into ASCII characters that can be used
 //! by higher level software like a shell or text editor. Special consideration
 //! is given to key release codes (>0x80) to properly track modifier key states.
-
-use crate::arch::x86::io;
-use core::alloc;
+//!
+//! Until [`enable_interrupt`] is called the data port is polled. After that
+//! the IRQ 1 handler only reads every scan code as it arrives and schedules
+//! its decoding on the [workqueue](crate::task::workqueue). The worker queues
+//! the keys in a ring, which [`Keyboard::input`] drains.
+
+use crate::{
+	arch::x86::{
+		exceptions::InterruptFrame,
+		io,
+		pic::{register_irq, send_eoi},
+	},
+	collections::ring_buffer::SpscRing,
+	sync::Mutex,
+	task::workqueue::schedule_work,
+};
+use core::{
+	alloc,
+	sync::atomic::{AtomicBool, Ordering},
+};
 
 #[repr(u8)]
 #[allow(missing_docs)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyboardKey {
 	KeyEsc = 0x01,
 	Key1 = 0x02,
@@ -149,25 +167,83 @@ pub enum KeyboardKey {
 	KeyPause = 0xef,
 }
 
+/// Extended keys (cursor block, Home/End, ...) arrive as an `0xE0` prefix
+/// followed by a regular scan code. `KeyboardKey` stores them with the high bit
+/// set, e.g. `KeyCursorLeft = 0xE0 0x4B` becomes `0xcb`.
+const EXTENDED_PREFIX: u8 = 0xe0;
+
+const EXTENDED_KEYS: [KeyboardKey; 12] = [
+	KeyboardKey::KeyKeypadEnter,
+	KeyboardKey::KeyKeypadSlash,
+	KeyboardKey::KeyHome,
+	KeyboardKey::KeyCursorUp,
+	KeyboardKey::KeyPageUp,
+	KeyboardKey::KeyCursorLeft,
+	KeyboardKey::KeyCursorRight,
+	KeyboardKey::KeyEnd,
+	KeyboardKey::KeyCursorDown,
+	KeyboardKey::KeyPageDown,
+	KeyboardKey::KeyInsert,
+	KeyboardKey::KeyDelete,
+];
+
+const KEYBOARD_DATA_PORT: u16 = 0x60;
+const KEYBOARD_STATUS_PORT: u16 = 0x64;
+/// Status register bit set while a byte waits in the data port.
+const STATUS_OUTPUT_FULL: u8 = 0x01;
+
+const KEYBOARD_IRQ: u8 = 1;
+
+/// Decoded keys buffered until they are read.
+pub const KEY_BUFFER_SIZE: usize = 64;
+
+/// Keys the worker decoded from the interrupt's scan codes. Ones decoded
+/// while it is full are dropped.
+static KEYS: SpscRing<KeyEvent, KEY_BUFFER_SIZE> = SpscRing::new();
+/// Set once the IRQ feeds `KEYS`, the data port is then only read by the
+/// handler.
+static IRQ_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// A decoded key press, as handed to consumers like the console.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyEvent {
+	/// A key with an ASCII representation, including control characters.
+	Char(char),
+	/// A key without an ASCII representation (cursor keys, Home/End, ...).
+	Special(KeyboardKey),
+}
+
 #[must_use]
 #[doc(hidden)]
 pub struct Keyboard {
 	shift_pressed: bool,
 	ctrl_pressed: bool,
 	alt_pressed: bool,
+	extended: bool,
 }
 
+/// The PS/2 keyboard, shared by the console loop and commands that poll for
+/// input while they run (e.g. to notice Ctrl+C).
+pub static KEYBOARD: Mutex<Keyboard> =
+	Mutex::named("KEYBOARD", Keyboard::new());
+
 impl Default for Keyboard {
 	fn default() -> Self {
+		return Keyboard::new();
+	}
+}
+
+impl Keyboard {
+	/// Creates a keyboard with no modifiers held.
+	pub const fn new() -> Self {
 		return Keyboard {
 			shift_pressed: false,
 			ctrl_pressed: false,
 			alt_pressed: false,
+			extended: false,
 		};
 	}
-}
 
-impl Keyboard {
 	fn get_ascii(&self, scan_code: u8) -> char {
 		let shift_pressed = self.shift_pressed;
 		let ctrl_pressed = self.ctrl_pressed;
@@ -442,16 +518,58 @@ impl Keyboard {
 		}
 	}
 
-	// TODO: Clean up code
-	pub fn input(&mut self) -> Option<char> {
-		const KEYBOARD_DATA_PORT: u16 = 0x60;
-		const KEYBOARD_STATUS_PORT: u16 = 0x64;
+	fn get_extended(&self, scan_code: u8) -> Option<KeyEvent> {
+		let code = scan_code | 0x80;
+		let key = EXTENDED_KEYS.into_iter().find(|key| *key as u8 == code)?;
+
+		match key {
+			KeyboardKey::KeyKeypadEnter => return Some(KeyEvent::Char('\n')),
+			KeyboardKey::KeyKeypadSlash => return Some(KeyEvent::Char('/')),
+			key => return Some(KeyEvent::Special(key)),
+		}
+	}
+
+	fn get_function(&self, scan_code: u8) -> Option<KeyEvent> {
+		match scan_code {
+			0x3b..=0x44 | 0x57 | 0x58 => {
+				let key = unsafe {
+					core::mem::transmute::<u8, KeyboardKey>(scan_code)
+				};
+				return Some(KeyEvent::Special(key));
+			}
+			_ => return None,
+		}
+	}
+
+	/// Returns whether an Alt key is currently held down.
+	#[inline]
+	pub fn alt_pressed(&self) -> bool {
+		return self.alt_pressed;
+	}
+
+	/// Returns the next key pressed, if there is one waiting. Decoded by the
+	/// worker once the interrupt is enabled, from the data port before.
+	pub fn input(&mut self) -> Option<KeyEvent> {
+		if IRQ_ENABLED.load(Ordering::Acquire) {
+			return KEYS.pop();
+		}
 
-		if io::inb(KEYBOARD_STATUS_PORT) & 1 == 0 {
+		if io::inb(KEYBOARD_STATUS_PORT) & STATUS_OUTPUT_FULL == 0 {
 			return None;
 		}
+		return self.decode(io::inb(KEYBOARD_DATA_PORT));
+	}
 
-		let scan_code = io::inb(KEYBOARD_DATA_PORT);
+	// TODO: Clean up code
+	/// Tracks the modifiers in `scan_code` and returns the key it presses,
+	/// if any.
+	pub fn decode(&mut self, scan_code: u8) -> Option<KeyEvent> {
+		if scan_code == EXTENDED_PREFIX {
+			self.extended = true;
+			return None;
+		}
+
+		let extended = core::mem::take(&mut self.extended);
 
 		// Alt Pressed
 		if scan_code == 56 {
@@ -493,8 +611,41 @@ impl Keyboard {
 			return None;
 		}
 
+		if extended {
+			return self.get_extended(scan_code);
+		}
+
+		if let Some(event) = self.get_function(scan_code) {
+			return Some(event);
+		}
+
 		let c = self.get_ascii(scan_code);
+		if c == '\0' {
+			return None;
+		}
+
+		return Some(KeyEvent::Char(c));
+	}
+}
+
+/// Reads scan codes from the keyboard interrupt from now on, instead of
+/// polling the data port.
+pub fn enable_interrupt() {
+	IRQ_ENABLED.store(true, Ordering::Release);
+	register_irq(KEYBOARD_IRQ, "keyboard", keyboard_interrupt);
+}
 
-		return Some(c);
+/// Decodes a scan code from the interrupt, run by the worker.
+fn decode_scan_code(scan_code: usize) {
+	if let Some(key) = KEYBOARD.lock().decode(scan_code as u8) {
+		let _ = KEYS.push(key);
+	}
+}
+
+extern "x86-interrupt" fn keyboard_interrupt(_frame: InterruptFrame) {
+	if io::inb(KEYBOARD_STATUS_PORT) & STATUS_OUTPUT_FULL != 0 {
+		let scan_code = io::inb(KEYBOARD_DATA_PORT);
+		schedule_work(decode_scan_code, scan_code as usize);
 	}
+	send_eoi(KEYBOARD_IRQ);
 }
@@ -1 +1,13 @@
+/// ATA disks on the primary IDE channel
+pub mod ata;
+/// Block devices, their registry and RAM disks
+pub mod block;
 pub mod keyboard;
+/// PCI configuration space and the device scan
+pub mod pci;
+/// Programmable Interval Timer driving the tick counter
+pub mod pit;
+/// CMOS real-time clock
+pub mod rtc;
+/// PC speaker tones on PIT channel 2
+pub mod speaker;
@@ -0,0 +1,386 @@
+//! PCI configuration space and the devices on the bus.
+//!
+//! Configuration space is reached through configuration mechanism #1: the
+//! bus, device, function and register go to the address port 0xCF8, and the
+//! register is then read or written at the data port 0xCFC. Registers are 32
+//! bits wide and aligned, the low two bits of the offset are ignored.
+//!
+//! [`init`] scans every bus for functions once at boot. A vendor id of
+//! 0xFFFF means nothing answers there, and only devices whose header type
+//! has bit 7 set have functions past 0. The BARs of each function are sized
+//! by writing all ones and reading back which bits stuck, with decoding
+//! turned off in the command register meanwhile.
+
+use crate::{
+	arch::x86::io::{inl, outl},
+	log_info,
+	sync::{Mutex, OnceLock},
+};
+use alloc::vec::Vec;
+use core::fmt;
+
+const CONFIG_ADDRESS: u16 = 0xcf8;
+const CONFIG_DATA: u16 = 0xcfc;
+
+/// Set in the address to access configuration space.
+const CONFIG_ENABLE: u32 = 1 << 31;
+
+const REG_ID: u8 = 0x00;
+const REG_COMMAND: u8 = 0x04;
+const REG_CLASS: u8 = 0x08;
+const REG_HEADER: u8 = 0x0c;
+const REG_BAR0: u8 = 0x10;
+
+const COMMAND_IO: u32 = 1 << 0;
+const COMMAND_MEMORY: u32 = 1 << 1;
+
+const HEADER_MULTIFUNCTION: u8 = 0x80;
+const HEADER_TYPE_MASK: u8 = 0x7f;
+
+const NO_VENDOR: u16 = 0xffff;
+
+/// Number of BARs of a general device header.
+pub const BAR_COUNT: usize = 6;
+
+/// Devices on a bus.
+pub const DEVICES_PER_BUS: u8 = 32;
+
+/// Functions of a device.
+pub const FUNCTIONS_PER_DEVICE: u8 = 8;
+
+/// Serializes configuration accesses, they take two port accesses.
+static CONFIG: Mutex<()> = Mutex::named("PCI_CONFIG", ());
+
+// Helper building the value of the address port
+fn config_address(bus: u8, device: u8, function: u8, offset: u8) -> u32 {
+	return CONFIG_ENABLE
+		| ((bus as u32) << 16)
+		| (((device & 0x1f) as u32) << 11)
+		| (((function & 0x07) as u32) << 8)
+		| (offset & 0xfc) as u32;
+}
+
+/// Reads the configuration register at `offset` of a function.
+pub fn pci_read_config_u32(
+	bus: u8,
+	device: u8,
+	function: u8,
+	offset: u8,
+) -> u32 {
+	let _config = CONFIG.lock();
+	outl(
+		CONFIG_ADDRESS,
+		config_address(bus, device, function, offset),
+	);
+
+	return inl(CONFIG_DATA);
+}
+
+/// Writes `value` to the configuration register at `offset` of a function.
+pub fn pci_write_config_u32(
+	bus: u8,
+	device: u8,
+	function: u8,
+	offset: u8,
+	value: u32,
+) {
+	let _config = CONFIG.lock();
+	outl(
+		CONFIG_ADDRESS,
+		config_address(bus, device, function, offset),
+	);
+	outl(CONFIG_DATA, value);
+}
+
+/// Where a function is on the bus, printed as `bus:device.function`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PciAddress {
+	/// The bus, 0-255.
+	pub bus: u8,
+	/// The device on the bus, 0-31.
+	pub device: u8,
+	/// The function of the device, 0-7.
+	pub function: u8,
+}
+
+impl PciAddress {
+	/// Reads the configuration register at `offset`.
+	pub fn read(&self, offset: u8) -> u32 {
+		return pci_read_config_u32(
+			self.bus,
+			self.device,
+			self.function,
+			offset,
+		);
+	}
+
+	/// Writes `value` to the configuration register at `offset`.
+	pub fn write(&self, offset: u8, value: u32) {
+		pci_write_config_u32(
+			self.bus,
+			self.device,
+			self.function,
+			offset,
+			value,
+		);
+	}
+}
+
+impl fmt::Display for PciAddress {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		return write!(
+			f,
+			"{:02x}:{:02x}.{}",
+			self.bus, self.device, self.function
+		);
+	}
+}
+
+/// What a base address register maps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bar {
+	/// Nothing, or the upper half of the 64-bit BAR before it.
+	None,
+	/// A range of physical memory.
+	Memory {
+		/// Physical address of the first byte.
+		base: u64,
+		/// Bytes in the range.
+		size: u64,
+		/// Whether reads have no side effects, so they may be cached.
+		prefetchable: bool,
+		/// Whether the BAR takes the next one for the upper 32 bits.
+		wide: bool,
+	},
+	/// A range of I/O ports.
+	Io {
+		/// The first port.
+		port: u16,
+		/// Ports in the range.
+		size: u16,
+	},
+}
+
+impl Bar {
+	/// Decodes a BAR from its `value` and what reads back, `sized`, after
+	/// writing all ones to it. For a 64-bit memory BAR both carry the next
+	/// BAR in the upper half, for the others the upper half of `sized` is
+	/// all ones.
+	pub fn decode(value: u64, sized: u64) -> Bar {
+		if sized as u32 == 0 {
+			return Bar::None;
+		}
+
+		if value & 1 != 0 {
+			// The upper 16 bits may read back zero, ports are 16 bits
+			let mask = (sized as u32 | 0xffff_0000) & !0x3;
+			return Bar::Io {
+				port: (value as u32 & !0x3) as u16,
+				size: (!mask).wrapping_add(1) as u16,
+			};
+		}
+
+		return Bar::Memory {
+			base: value & !0xf,
+			size: (!(sized & !0xf)).wrapping_add(1),
+			prefetchable: value & (1 << 3) != 0,
+			wide: (value >> 1) & 0x3 == 0x2,
+		};
+	}
+}
+
+/// A function found by the scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PciDevice {
+	/// Where it is on the bus.
+	pub address: PciAddress,
+	/// Who made it.
+	pub vendor_id: u16,
+	/// What it is, the vendor assigns these.
+	pub device_id: u16,
+	/// What kind of device it is, see [`class_name`].
+	pub class: u8,
+	/// Refines the class.
+	pub subclass: u8,
+	/// The register interface, for the classes that have several.
+	pub prog_if: u8,
+	/// Revision of the device.
+	pub revision: u8,
+	/// Layout of the rest of the header, 0 for devices and 1 for PCI
+	/// bridges. The multi-function bit is masked out.
+	pub header_type: u8,
+	/// The BARs, the ones the header type does not have are `None`.
+	pub bars: [Bar; BAR_COUNT],
+}
+
+impl PciDevice {
+	/// Returns the name of the device's class.
+	pub fn class_name(&self) -> &'static str {
+		return class_name(self.class, self.subclass);
+	}
+}
+
+/// Returns a name for a class and subclass, the class's if the subclass is
+/// not one of the common ones.
+pub fn class_name(class: u8, subclass: u8) -> &'static str {
+	match (class, subclass) {
+		(0x01, 0x01) => return "IDE controller",
+		(0x01, 0x06) => return "SATA controller",
+		(0x01, 0x08) => return "NVMe controller",
+		(0x01, _) => return "mass storage controller",
+		(0x02, 0x00) => return "ethernet controller",
+		(0x02, _) => return "network controller",
+		(0x03, 0x00) => return "VGA controller",
+		(0x03, _) => return "display controller",
+		(0x04, 0x03) => return "audio device",
+		(0x04, _) => return "multimedia controller",
+		(0x05, _) => return "memory controller",
+		(0x06, 0x00) => return "host bridge",
+		(0x06, 0x01) => return "ISA bridge",
+		(0x06, 0x04) => return "PCI bridge",
+		(0x06, _) => return "bridge",
+		(0x07, _) => return "communication controller",
+		(0x08, _) => return "system peripheral",
+		(0x09, _) => return "input device",
+		(0x0c, 0x03) => return "USB controller",
+		(0x0c, 0x05) => return "SMBus controller",
+		(0x0c, _) => return "serial bus controller",
+		(0x0d, _) => return "wireless controller",
+		_ => return "unknown device",
+	}
+}
+
+static DEVICES: OnceLock<Vec<PciDevice>> = OnceLock::new();
+
+/// Scans the buses for functions, see the module documentation. Later calls
+/// do nothing.
+pub fn init() {
+	let devices = DEVICES.get_or_init(scan);
+	log_info!("pci: {} functions", devices.len());
+}
+
+/// Returns the functions found by [`init`], by address.
+pub fn devices() -> &'static [PciDevice] {
+	return DEVICES.get().map_or(&[], Vec::as_slice);
+}
+
+/// Returns the first function of class `class` and subclass `subclass`.
+pub fn find_class(class: u8, subclass: u8) -> Option<&'static PciDevice> {
+	return devices()
+		.iter()
+		.find(|device| device.class == class && device.subclass == subclass);
+}
+
+// Helper probing every function of every device on every bus
+fn scan() -> Vec<PciDevice> {
+	let mut devices = Vec::new();
+
+	for bus in 0..=u8::MAX {
+		for device in 0..DEVICES_PER_BUS {
+			let Some(first) = probe(PciAddress {
+				bus,
+				device,
+				function: 0,
+			}) else {
+				continue;
+			};
+			let multifunction = (first.address.read(REG_HEADER) >> 16) as u8
+				& HEADER_MULTIFUNCTION;
+			devices.push(first);
+
+			if multifunction == 0 {
+				continue;
+			}
+			for function in 1..FUNCTIONS_PER_DEVICE {
+				devices.extend(probe(PciAddress {
+					bus,
+					device,
+					function,
+				}));
+			}
+		}
+	}
+
+	return devices;
+}
+
+// Helper reading the header of the function at `address`, `None` if there
+// is none
+fn probe(address: PciAddress) -> Option<PciDevice> {
+	let id = address.read(REG_ID);
+	let vendor_id = id as u16;
+	if vendor_id == NO_VENDOR {
+		return None;
+	}
+
+	let class = address.read(REG_CLASS);
+	let header_type = (address.read(REG_HEADER) >> 16) as u8 & HEADER_TYPE_MASK;
+	let bars = match header_type {
+		0x00 => read_bars(address, BAR_COUNT),
+		0x01 => read_bars(address, 2),
+		_ => [Bar::None; BAR_COUNT],
+	};
+
+	return Some(PciDevice {
+		address,
+		vendor_id,
+		device_id: (id >> 16) as u16,
+		class: (class >> 24) as u8,
+		subclass: (class >> 16) as u8,
+		prog_if: (class >> 8) as u8,
+		revision: class as u8,
+		header_type,
+		bars,
+	});
+}
+
+// Helper sizing the first `count` BARs of the function at `address`
+fn read_bars(address: PciAddress, count: usize) -> [Bar; BAR_COUNT] {
+	let mut bars = [Bar::None; BAR_COUNT];
+
+	// No decoding while a BAR holds all ones, it would claim any address
+	let command = address.read(REG_COMMAND);
+	address.write(REG_COMMAND, command & !(COMMAND_IO | COMMAND_MEMORY));
+
+	let mut index = 0;
+	while index < count {
+		let (value, sized) = size_bar(address, index);
+		let mut bar =
+			Bar::decode(value as u64, sized as u64 | (0xffff_ffff << 32));
+
+		if let Bar::Memory {
+			wide: true, ..
+		} = bar
+		{
+			if index + 1 < count {
+				let (high, sized_high) = size_bar(address, index + 1);
+				bar = Bar::decode(
+					((high as u64) << 32) | value as u64,
+					((sized_high as u64) << 32) | sized as u64,
+				);
+			}
+			bars[index] = bar;
+			index += 2;
+			continue;
+		}
+
+		bars[index] = bar;
+		index += 1;
+	}
+
+	address.write(REG_COMMAND, command);
+	return bars;
+}
+
+// Helper returning the value of BAR `index` and what reads back after
+// writing all ones to it, which it gets back
+fn size_bar(address: PciAddress, index: usize) -> (u32, u32) {
+	let offset = REG_BAR0 + 4 * index as u8;
+
+	let value = address.read(offset);
+	address.write(offset, u32::MAX);
+	let sized = address.read(offset);
+	address.write(offset, value);
+
+	return (value, sized);
+}
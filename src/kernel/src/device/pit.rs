@@ -0,0 +1,122 @@
+//! 8253/8254 Programmable Interval Timer.
+//!
+//! Channel 0 is wired to IRQ 0. It is programmed as a rate generator so the
+//! timer interrupt fires [`DEFAULT_FREQUENCY_HZ`] times a second, or whatever
+//! [`set_frequency`] picked, driving the tick counter in [`crate::time`], the
+//! [status bar](crate::tty::status) and the time slices of the
+//! [scheduler](crate::task::scheduler).
+//!
+//! Channel 2 drives the [PC speaker](super::speaker), [`set_speaker_frequency`]
+//! sets the pitch of its square wave.
+
+use crate::{
+	arch::x86::{
+		cpu::without_interrupts,
+		exceptions::InterruptFrame,
+		io::outb,
+		pic::{register_irq, send_eoi},
+	},
+	task::scheduler,
+	time,
+	tty::status,
+};
+
+/// Frequency of the oscillator feeding the PIT.
+const PIT_BASE_FREQUENCY: u32 = 1_193_182;
+
+/// Highest pitch of the speaker, a divisor of 59. Channel 2 raises no
+/// interrupt, this keeps the tone audible and the divisor legal for mode 3.
+pub const MAX_SPEAKER_FREQUENCY_HZ: u32 = 20_000;
+
+/// Rate the timer interrupt fires at after [`init`].
+pub const DEFAULT_FREQUENCY_HZ: u32 = 1000;
+
+/// Lowest rate the 16-bit divisor reaches, 65535 rounds it to 19 Hz.
+pub const MIN_FREQUENCY_HZ: u32 = 19;
+
+/// Highest rate of the timer interrupt. The PIT goes far higher, mode 3
+/// takes divisors down to 2, but every tick is an interrupt.
+pub const MAX_FREQUENCY_HZ: u32 = 10_000;
+
+const CHANNEL0_DATA: u16 = 0x40;
+const CHANNEL2_DATA: u16 = 0x42;
+const COMMAND: u16 = 0x43;
+
+/// Channel 0, lobyte/hibyte access, mode 3 (square wave), binary.
+const CHANNEL0_SQUARE_WAVE: u8 = 0x36;
+
+/// Channel 2, lobyte/hibyte access, mode 3 (square wave), binary.
+const CHANNEL2_SQUARE_WAVE: u8 = 0xb6;
+
+const TIMER_IRQ: u8 = 0;
+
+/// Starts the timer interrupt at [`DEFAULT_FREQUENCY_HZ`].
+pub fn init() {
+	set_frequency(DEFAULT_FREQUENCY_HZ);
+	register_irq(TIMER_IRQ, "timer", timer_interrupt);
+}
+
+/// Returns the rate [`set_frequency`] uses for `hz`, without programming
+/// the PIT.
+pub fn clamp_frequency(hz: u32) -> u32 {
+	return hz.clamp(MIN_FREQUENCY_HZ, MAX_FREQUENCY_HZ);
+}
+
+/// Makes the timer interrupt fire `hz` times a second, clamped to
+/// [`MIN_FREQUENCY_HZ`]..=[`MAX_FREQUENCY_HZ`], and returns the rate used.
+///
+/// The uptime carries on from where it was, only later ticks count at the
+/// new rate.
+pub fn set_frequency(hz: u32) -> u32 {
+	let hz = clamp_frequency(hz);
+	let divisor = (PIT_BASE_FREQUENCY / hz) as u16;
+
+	// No tick may land between the new divisor and the new rate
+	without_interrupts(|| {
+		outb(COMMAND, CHANNEL0_SQUARE_WAVE);
+		outb(CHANNEL0_DATA, (divisor & 0xff) as u8);
+		outb(CHANNEL0_DATA, (divisor >> 8) as u8);
+
+		time::set_tick_rate(hz);
+	});
+
+	return hz;
+}
+
+/// Makes channel 2 put out a square wave of `hz`, clamped to
+/// [`MIN_FREQUENCY_HZ`]..=[`MAX_SPEAKER_FREQUENCY_HZ`], and returns the
+/// frequency used. The speaker only sounds it while it is gated on.
+pub fn set_speaker_frequency(hz: u32) -> u32 {
+	let hz = hz.clamp(MIN_FREQUENCY_HZ, MAX_SPEAKER_FREQUENCY_HZ);
+	let divisor = (PIT_BASE_FREQUENCY / hz) as u16;
+
+	// The command port is shared with channel 0
+	without_interrupts(|| {
+		outb(COMMAND, CHANNEL2_SQUARE_WAVE);
+		outb(CHANNEL2_DATA, (divisor & 0xff) as u8);
+		outb(CHANNEL2_DATA, (divisor >> 8) as u8);
+	});
+
+	return hz;
+}
+
+/// Returns the number of timer interrupts since boot, see [`time::ticks`].
+pub fn ticks() -> u64 {
+	return time::ticks();
+}
+
+/// Returns the milliseconds since the timer started, see
+/// [`time::uptime_ms`].
+pub fn uptime_ms() -> u64 {
+	return time::uptime_ms();
+}
+
+extern "x86-interrupt" fn timer_interrupt(_frame: InterruptFrame) {
+	time::tick();
+	status::tick();
+	send_eoi(TIMER_IRQ);
+
+	// May switch threads, this one returns from the interrupt when it runs
+	// again
+	scheduler::tick();
+}
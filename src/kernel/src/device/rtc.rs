@@ -0,0 +1,152 @@
+//! CMOS real-time clock.
+//!
+//! The RTC keeps wall-clock time in the CMOS, reached by writing a register
+//! index to port 0x70 and reading the value from port 0x71. Values may be BCD
+//! or binary and the hour may be in 12 hour format, both indicated by status
+//! register B.
+
+use crate::arch::x86::io::{inb, outb};
+use core::fmt;
+
+const CMOS_ADDRESS: u16 = 0x70;
+const CMOS_DATA: u16 = 0x71;
+
+/// Keeps NMIs disabled while a CMOS register is selected. [`rtc`] selects
+/// a register without it once done, which lets them through again.
+const NMI_DISABLE: u8 = 0x80;
+
+const REG_SECONDS: u8 = 0x00;
+const REG_MINUTES: u8 = 0x02;
+const REG_HOURS: u8 = 0x04;
+const REG_DAY: u8 = 0x07;
+const REG_MONTH: u8 = 0x08;
+const REG_YEAR: u8 = 0x09;
+const REG_STATUS_A: u8 = 0x0a;
+const REG_STATUS_B: u8 = 0x0b;
+const REG_STATUS_D: u8 = 0x0d;
+
+const STATUS_A_UPDATE_IN_PROGRESS: u8 = 0x80;
+const STATUS_B_24_HOUR: u8 = 0x02;
+const STATUS_B_BINARY: u8 = 0x04;
+const HOUR_PM: u8 = 0x80;
+
+/// The RTC only stores a two digit year. The century register is not
+/// standardized (its index comes from the ACPI FADT), so we assume 20xx.
+const CENTURY: u16 = 2000;
+
+/// A calendar date and time as read from the RTC.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+	/// Full year, e.g. 2025.
+	pub year: u16,
+	/// Month, 1-12.
+	pub month: u8,
+	/// Day of the month, 1-31.
+	pub day: u8,
+	/// Hour, 0-23.
+	pub hour: u8,
+	/// Minute, 0-59.
+	pub minute: u8,
+	/// Second, 0-59.
+	pub second: u8,
+}
+
+impl fmt::Display for DateTime {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		return write!(
+			f,
+			"{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+			self.year,
+			self.month,
+			self.day,
+			self.hour,
+			self.minute,
+			self.second
+		);
+	}
+}
+
+/// The raw, undecoded register values.
+#[derive(Clone, Copy, PartialEq, Eq)]
+struct RawTime {
+	second: u8,
+	minute: u8,
+	hour: u8,
+	day: u8,
+	month: u8,
+	year: u8,
+}
+
+/// Returns the current date and time.
+///
+/// The registers are read until two consecutive reads agree, so a read never
+/// mixes values from before and after an RTC update.
+pub fn rtc() -> DateTime {
+	let mut last = read_raw();
+
+	loop {
+		let current = read_raw();
+		if current == last {
+			let status_b = read_register(REG_STATUS_B);
+			// Status register D is read only, leaving it selected is safe
+			outb(CMOS_ADDRESS, REG_STATUS_D);
+			return decode(current, status_b);
+		}
+
+		last = current;
+	}
+}
+
+fn read_register(register: u8) -> u8 {
+	outb(CMOS_ADDRESS, NMI_DISABLE | register);
+	return inb(CMOS_DATA);
+}
+
+fn update_in_progress() -> bool {
+	return read_register(REG_STATUS_A) & STATUS_A_UPDATE_IN_PROGRESS != 0;
+}
+
+fn read_raw() -> RawTime {
+	while update_in_progress() {}
+
+	return RawTime {
+		second: read_register(REG_SECONDS),
+		minute: read_register(REG_MINUTES),
+		hour: read_register(REG_HOURS),
+		day: read_register(REG_DAY),
+		month: read_register(REG_MONTH),
+		year: read_register(REG_YEAR),
+	};
+}
+
+const fn bcd_to_binary(value: u8) -> u8 {
+	return (value & 0x0f) + (value >> 4) * 10;
+}
+
+fn decode(raw: RawTime, status_b: u8) -> DateTime {
+	let binary = status_b & STATUS_B_BINARY != 0;
+	let convert = |value: u8| match binary {
+		true => value,
+		false => bcd_to_binary(value),
+	};
+
+	let pm = raw.hour & HOUR_PM != 0;
+	let mut hour = convert(raw.hour & !HOUR_PM);
+
+	if status_b & STATUS_B_24_HOUR == 0 {
+		// 12 hour clock: 12 AM is midnight, 12 PM is noon
+		hour %= 12;
+		if pm {
+			hour += 12;
+		}
+	}
+
+	return DateTime {
+		year: CENTURY + convert(raw.year) as u16,
+		month: convert(raw.month),
+		day: convert(raw.day),
+		hour,
+		minute: convert(raw.minute),
+		second: convert(raw.second),
+	};
+}
@@ -0,0 +1,118 @@
+//! The PC speaker.
+//!
+//! PIT channel 2 puts out a square wave of the pitch asked for, see
+//! [`pit::set_speaker_frequency`], and bits 0 and 1 of port 0x61 gate it
+//! through to the speaker: bit 0 starts the channel counting, bit 1 connects
+//! its output.
+//!
+//! [`beep`] times the tone with the tick counter when it can. With
+//! interrupts off, in the panic handler for one, the ticks stand still, so
+//! it spins instead for as many rounds as [`init`] counted in a millisecond.
+
+use crate::{
+	arch::x86::{
+		cpu::{halt, interrupts_enabled, without_interrupts},
+		io::{inb, outb},
+	},
+	device::pit,
+	log_info, log_warn, time,
+};
+use core::{
+	hint,
+	sync::atomic::{AtomicU32, Ordering},
+};
+
+const SPEAKER_PORT: u16 = 0x61;
+
+/// Bit 0 gates channel 2, bit 1 connects it to the speaker.
+const SPEAKER_ON: u8 = 0x03;
+
+/// Spin rounds per millisecond until [`init`] measured them, a guess on the
+/// long side for the machines this runs on.
+const DEFAULT_SPINS_PER_MS: u32 = 100_000;
+
+/// Milliseconds [`init`] spins for to count the rounds.
+const CALIBRATION_MS: u64 = 10;
+
+static SPINS_PER_MS: AtomicU32 = AtomicU32::new(DEFAULT_SPINS_PER_MS);
+
+/// Counts the spin rounds in a millisecond against the tick counter, for
+/// timing beeps while interrupts are off. Needs the timer running, keeps
+/// the default otherwise.
+pub fn init() {
+	let hz = time::tick_rate() as u64;
+	if !interrupts_enabled() || hz == 0 {
+		log_warn!("speaker: no timer, beeps spin by a guess");
+		return;
+	}
+
+	// Start on a tick so the count covers whole ones
+	let ticks = (hz * CALIBRATION_MS / 1000).max(1);
+	let start = time::ticks();
+	while time::ticks() == start {
+		hint::spin_loop();
+	}
+
+	let end = start + 1 + ticks;
+	let mut spins: u64 = 0;
+	while time::ticks() < end {
+		spins += 1;
+		hint::spin_loop();
+	}
+
+	let per_ms = (spins * hz / (ticks * 1000)).clamp(1, u32::MAX as u64);
+	SPINS_PER_MS.store(per_ms as u32, Ordering::Relaxed);
+	log_info!("speaker: {} spins per ms", per_ms);
+}
+
+/// Returns the spin rounds in a millisecond, see [`init`].
+pub fn spins_per_ms() -> u32 {
+	return SPINS_PER_MS.load(Ordering::Relaxed);
+}
+
+/// Starts a tone of `freq_hz`, clamped to what the PIT can divide down to,
+/// and returns the frequency used. It sounds until [`stop`].
+pub fn play(freq_hz: u32) -> u32 {
+	let freq_hz = pit::set_speaker_frequency(freq_hz);
+
+	without_interrupts(|| outb(SPEAKER_PORT, inb(SPEAKER_PORT) | SPEAKER_ON));
+
+	return freq_hz;
+}
+
+/// Silences the speaker.
+pub fn stop() {
+	without_interrupts(|| outb(SPEAKER_PORT, inb(SPEAKER_PORT) & !SPEAKER_ON));
+}
+
+/// Returns whether the speaker is gated on.
+pub fn is_playing() -> bool {
+	return inb(SPEAKER_PORT) & SPEAKER_ON == SPEAKER_ON;
+}
+
+/// Sounds `freq_hz` for `duration_ms` milliseconds, see [`play`]. Takes no
+/// locks while interrupts are off, so the panic handler can beep.
+pub fn beep(freq_hz: u32, duration_ms: u32) {
+	play(freq_hz);
+	wait_ms(duration_ms);
+	stop();
+}
+
+// Helper waiting `ms` milliseconds on the tick counter, or spinning if the
+// ticks do not advance
+fn wait_ms(ms: u32) {
+	// Checked first, the tick rate is behind a lock
+	if interrupts_enabled() && time::tick_rate() != 0 {
+		let deadline = time::uptime_ms() + ms as u64;
+		while time::uptime_ms() < deadline {
+			halt();
+		}
+		return;
+	}
+
+	// Rounds like the ones `init` counted, reading the tick counter too
+	for _ in 0..spins_per_ms() as u64 * ms as u64 {
+		hint::black_box(time::ticks());
+		hint::spin_loop();
+	}
+}
@@ -0,0 +1,71 @@
+//! The error type kernel subsystems return.
+//!
+//! Subsystems with failures of their own keep their own error type, like
+//! [`ProtectError`] or [`AtaError`], and convert into [`KernelError`] where
+//! callers only need to know what kind of failure it was.
+
+use crate::{device::ata::AtaError, memory::paging::ProtectError};
+use core::{alloc::AllocError, fmt};
+
+/// What went wrong in a kernel call.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelError {
+	/// Not enough memory or address space was left.
+	OutOfMemory,
+	/// An argument was outside of what the call accepts.
+	InvalidArgument,
+	/// An address was not mapped.
+	NotMapped,
+	/// The subsystem was not initialized yet.
+	NotInitialized,
+	/// A device failed the transfer.
+	Io,
+}
+
+impl KernelError {
+	/// Returns a short description of the error for reports.
+	pub const fn name(self) -> &'static str {
+		match self {
+			KernelError::OutOfMemory => return "out of memory",
+			KernelError::InvalidArgument => return "invalid argument",
+			KernelError::NotMapped => return "not mapped",
+			KernelError::NotInitialized => return "not initialized",
+			KernelError::Io => return "I/O error",
+		}
+	}
+}
+
+impl fmt::Display for KernelError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		return f.write_str(self.name());
+	}
+}
+
+impl From<AllocError> for KernelError {
+	fn from(_: AllocError) -> Self {
+		return KernelError::OutOfMemory;
+	}
+}
+
+impl From<ProtectError> for KernelError {
+	fn from(error: ProtectError) -> Self {
+		match error {
+			ProtectError::NotMapped(_) => return KernelError::NotMapped,
+			ProtectError::LargePage(_) => return KernelError::InvalidArgument,
+		}
+	}
+}
+
+impl From<AtaError> for KernelError {
+	fn from(error: AtaError) -> Self {
+		match error {
+			AtaError::NoDisk => return KernelError::NotInitialized,
+			AtaError::OutOfRange | AtaError::BufferTooSmall => {
+				return KernelError::InvalidArgument
+			}
+			AtaError::Timeout | AtaError::DeviceFault | AtaError::Device(_) => {
+				return KernelError::Io
+			}
+		}
+	}
+}
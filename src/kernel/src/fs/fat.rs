@@ -0,0 +1,609 @@
+//! Read-only FAT16 and FAT32 filesystems on a block device.
+//!
+//! [`Fat::mount`] finds the BIOS parameter block in the first sector of the
+//! device, or in the first partition of its MBR, and works out where the
+//! FATs, the root directory and the clusters are. The FAT type follows from
+//! the number of clusters like the specification has it, FAT12 volumes are
+//! turned down. A FAT16 root directory is a fixed run of sectors, a FAT32
+//! one a cluster chain like any other directory.
+//!
+//! Paths are split at `/` and matched against the 8.3 names, ignoring case.
+//! Long names are skipped. [`Fat::open`] returns a [`File`] reading through
+//! a cluster buffer on the heap. Following a chain checks every cluster
+//! number against the volume and gives up after as many steps as there are
+//! clusters, so a corrupt FAT ends in a [`FatError`] rather than a hang.
+//!
+//! [`init`] mounts `ata0` at [`MOUNT_POINT`] if it has a FAT.
+
+use crate::{
+	device::block::{self, BlockDevice},
+	error::KernelError,
+	log_info,
+	sync::OnceLock,
+	util::bytes::{u16_at, u32_at},
+};
+use alloc::{string::String, vec, vec::Vec};
+use core::fmt;
+
+/// Where [`init`] mounts the disk, the paths of the console start with it.
+pub const MOUNT_POINT: &str = "/disk";
+
+/// Bytes in a directory entry.
+pub const DIR_ENTRY_SIZE: usize = 32;
+
+const ATTR_VOLUME_ID: u8 = 0x08;
+const ATTR_DIRECTORY: u8 = 0x10;
+const ATTR_LONG_NAME: u8 = 0x0f;
+
+const ENTRY_END: u8 = 0x00;
+const ENTRY_DELETED: u8 = 0xe5;
+
+const BOOT_SIGNATURE: u16 = 0xaa55;
+const PARTITION_TABLE: usize = 446;
+
+/// Clusters below this many make a FAT12 volume.
+const FAT16_MIN_CLUSTERS: u32 = 4085;
+
+/// Clusters below this many make a FAT16 volume, FAT32 otherwise.
+const FAT32_MIN_CLUSTERS: u32 = 65525;
+
+/// Why a volume could not be mounted or a file read.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatError {
+	/// No device is registered under the name.
+	NoDevice,
+	/// The device failed a read.
+	Device(KernelError),
+	/// Neither the first sector nor the first partition has a BPB.
+	NotFat,
+	/// A FAT12 volume, or one whose sectors are not the device's blocks.
+	Unsupported,
+	/// No file or directory has the name.
+	NotFound,
+	/// A part of the path is a file.
+	NotADirectory,
+	/// The path is a directory, not a file.
+	IsADirectory,
+	/// A chain leads to the cluster given, which is free, reserved or not
+	/// on the volume.
+	BadCluster(u32),
+	/// A chain is longer than the volume has clusters, it loops.
+	ChainLoop,
+	/// A chain ends before the size of its file.
+	ChainTooShort,
+}
+
+impl fmt::Display for FatError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FatError::NoDevice => return f.write_str("no such device"),
+			FatError::Device(error) => return write!(f, "device: {}", error),
+			FatError::NotFat => return f.write_str("no FAT filesystem"),
+			FatError::Unsupported => {
+				return f.write_str("unsupported FAT volume")
+			}
+			FatError::NotFound => return f.write_str("no such file"),
+			FatError::NotADirectory => return f.write_str("not a directory"),
+			FatError::IsADirectory => return f.write_str("is a directory"),
+			FatError::BadCluster(cluster) => {
+				return write!(f, "bad cluster {} in a chain", cluster)
+			}
+			FatError::ChainLoop => return f.write_str("cluster chain loops"),
+			FatError::ChainTooShort => {
+				return f.write_str("cluster chain shorter than the file")
+			}
+		}
+	}
+}
+
+impl From<KernelError> for FatError {
+	fn from(error: KernelError) -> Self {
+		return FatError::Device(error);
+	}
+}
+
+/// The FAT variant of a volume.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FatType {
+	/// 16-bit FAT entries and a fixed root directory.
+	Fat16,
+	/// 28-bit FAT entries and the root directory in a chain.
+	Fat32,
+}
+
+/// A file or directory in a directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+	/// The 8.3 name, `NAME.EXT`, without the padding.
+	pub name: String,
+	/// Whether it is a directory.
+	pub is_dir: bool,
+	/// Size of the file in bytes, 0 for directories.
+	pub size: u32,
+	/// First cluster of its data, 0 for an empty file.
+	cluster: u32,
+}
+
+/// Where the entries of a directory are.
+#[derive(Debug, Clone, Copy)]
+enum Dir {
+	/// The FAT16 root directory.
+	FixedRoot,
+	/// A chain starting at the cluster.
+	Chain(u32),
+}
+
+/// A mounted volume.
+#[derive(Debug, Clone)]
+pub struct Fat {
+	/// The name of the block device it is on.
+	device: String,
+	fat_type: FatType,
+	bytes_per_sector: usize,
+	sectors_per_cluster: u64,
+	/// First sector of the first FAT.
+	fat_start: u64,
+	/// First sector of the FAT16 root directory.
+	root_start: u64,
+	/// Sectors of the FAT16 root directory.
+	root_sectors: u64,
+	/// First cluster of the FAT32 root directory.
+	root_cluster: u32,
+	/// Sector of cluster 2, the first one.
+	data_start: u64,
+	/// Clusters on the volume, numbered from 2.
+	clusters: u32,
+}
+
+/// Returns whether `sector` looks like a boot sector with a BPB.
+fn is_bpb(sector: &[u8]) -> bool {
+	let bytes_per_sector = u16_at(sector, 11);
+	let sectors_per_cluster = sector[13];
+
+	return matches!(sector[0], 0xeb | 0xe9)
+		&& u16_at(sector, 510) == BOOT_SIGNATURE
+		&& (512..=4096).contains(&bytes_per_sector)
+		&& bytes_per_sector.is_power_of_two()
+		&& sectors_per_cluster.is_power_of_two()
+		&& u16_at(sector, 14) != 0
+		&& sector[16] != 0;
+}
+
+impl Fat {
+	/// Mounts the volume on the block device registered as `device`, see
+	/// the module documentation.
+	pub fn mount(device: &str) -> Result<Self, FatError> {
+		let block_size =
+			block::with_device(device, |device| device.block_size())
+				.ok_or(FatError::NoDevice)?;
+		if block_size < 512 {
+			return Err(FatError::Unsupported);
+		}
+
+		let mut sector = vec![0; block_size];
+		read_block(device, 0, &mut sector)?;
+
+		let mut start = 0;
+		if !is_bpb(&sector) {
+			// The first entry of the partition table then
+			let entry = &sector[PARTITION_TABLE..];
+			if u16_at(&sector, 510) != BOOT_SIGNATURE || entry[4] == 0 {
+				return Err(FatError::NotFat);
+			}
+			start = u32_at(entry, 8) as u64;
+			read_block(device, start, &mut sector)?;
+			if !is_bpb(&sector) {
+				return Err(FatError::NotFat);
+			}
+		}
+
+		let bytes_per_sector = u16_at(&sector, 11) as usize;
+		if bytes_per_sector != block_size {
+			return Err(FatError::Unsupported);
+		}
+		let sectors_per_cluster = sector[13] as u64;
+		let reserved = u16_at(&sector, 14) as u64;
+		let fats = sector[16] as u64;
+		let root_entries = u16_at(&sector, 17) as u64;
+		let total = match u16_at(&sector, 19) {
+			0 => u32_at(&sector, 32) as u64,
+			total => total as u64,
+		};
+		let sectors_per_fat = match u16_at(&sector, 22) {
+			0 => u32_at(&sector, 36) as u64,
+			sectors => sectors as u64,
+		};
+
+		let root_sectors = (root_entries * DIR_ENTRY_SIZE as u64)
+			.div_ceil(bytes_per_sector as u64);
+		let fat_start = start + reserved;
+		let root_start = fat_start + fats * sectors_per_fat;
+		let data_start = root_start + root_sectors;
+		let data_sectors = (start + total)
+			.checked_sub(data_start)
+			.ok_or(FatError::NotFat)?;
+		let clusters = (data_sectors / sectors_per_cluster) as u32;
+
+		if clusters < FAT16_MIN_CLUSTERS {
+			return Err(FatError::Unsupported);
+		}
+		let fat_type = match clusters < FAT32_MIN_CLUSTERS {
+			true => FatType::Fat16,
+			false => FatType::Fat32,
+		};
+
+		return Ok(Self {
+			device: String::from(device),
+			fat_type,
+			bytes_per_sector,
+			sectors_per_cluster,
+			fat_start,
+			root_start,
+			root_sectors,
+			root_cluster: u32_at(&sector, 44),
+			data_start,
+			clusters,
+		});
+	}
+
+	/// Returns whether the volume is FAT16 or FAT32.
+	pub fn fat_type(&self) -> FatType {
+		return self.fat_type;
+	}
+
+	/// Returns the name of the device the volume is on.
+	pub fn device(&self) -> &str {
+		return &self.device;
+	}
+
+	/// Returns the size of a cluster in bytes.
+	pub fn cluster_size(&self) -> usize {
+		return self.sectors_per_cluster as usize * self.bytes_per_sector;
+	}
+
+	/// Returns the entries of the directory at `path`, `/` or empty for the
+	/// root directory.
+	pub fn read_dir(&self, path: &str) -> Result<Vec<DirEntry>, FatError> {
+		let dir = self.find_dir(path)?;
+		return self.entries(dir);
+	}
+
+	/// Opens the file at `path` for reading.
+	pub fn open(&self, path: &str) -> Result<File<'_>, FatError> {
+		let (parent, name) = match path.trim_matches('/').rsplit_once('/') {
+			Some((parent, name)) => (parent, name),
+			None => ("", path.trim_matches('/')),
+		};
+		if name.is_empty() {
+			return Err(FatError::IsADirectory);
+		}
+
+		let entry = self.find(self.find_dir(parent)?, name)?;
+		if entry.is_dir {
+			return Err(FatError::IsADirectory);
+		}
+
+		return Ok(File {
+			fs: self,
+			first_cluster: entry.cluster,
+			size: entry.size,
+			position: 0,
+			buffer: Vec::new(),
+			cached: None,
+		});
+	}
+
+	/// Walks `path` down from the root directory.
+	fn find_dir(&self, path: &str) -> Result<Dir, FatError> {
+		let mut dir = self.root();
+
+		for name in path.split('/').filter(|name| !name.is_empty()) {
+			let entry = self.find(dir, name)?;
+			if !entry.is_dir {
+				return Err(FatError::NotADirectory);
+			}
+			// `..` of a directory right below the root points at cluster 0
+			dir = match entry.cluster {
+				0 => self.root(),
+				cluster => Dir::Chain(cluster),
+			};
+		}
+
+		return Ok(dir);
+	}
+
+	/// Returns the root directory.
+	fn root(&self) -> Dir {
+		match self.fat_type {
+			FatType::Fat16 => return Dir::FixedRoot,
+			FatType::Fat32 => return Dir::Chain(self.root_cluster),
+		}
+	}
+
+	/// Looks for `name` in `dir`, ignoring case.
+	fn find(&self, dir: Dir, name: &str) -> Result<DirEntry, FatError> {
+		return self
+			.entries(dir)?
+			.into_iter()
+			.find(|entry| entry.name.eq_ignore_ascii_case(name))
+			.ok_or(FatError::NotFound);
+	}
+
+	/// Reads the entries of `dir`, up to the end marker.
+	fn entries(&self, dir: Dir) -> Result<Vec<DirEntry>, FatError> {
+		let mut bytes = vec![0; self.bytes_per_sector];
+		let mut entries = Vec::new();
+
+		let sectors: Vec<u64> = match dir {
+			Dir::FixedRoot => (self.root_start..)
+				.take(self.root_sectors as usize)
+				.collect(),
+			Dir::Chain(first) => {
+				let mut sectors = Vec::new();
+				for cluster in self.chain(first)? {
+					let start = self.cluster_sector(cluster);
+					sectors.extend(start..start + self.sectors_per_cluster);
+				}
+				sectors
+			}
+		};
+
+		for sector in sectors {
+			read_block(&self.device, sector, &mut bytes)?;
+			for raw in bytes.chunks_exact(DIR_ENTRY_SIZE) {
+				match raw[0] {
+					ENTRY_END => return Ok(entries),
+					ENTRY_DELETED => continue,
+					_ => {}
+				}
+				let attr = raw[11];
+				if attr & ATTR_LONG_NAME == ATTR_LONG_NAME
+					|| attr & ATTR_VOLUME_ID != 0
+				{
+					continue;
+				}
+
+				let is_dir = attr & ATTR_DIRECTORY != 0;
+				entries.push(DirEntry {
+					name: short_name(raw),
+					is_dir,
+					size: if is_dir { 0 } else { u32_at(raw, 28) },
+					cluster: ((u16_at(raw, 20) as u32) << 16)
+						| u16_at(raw, 26) as u32,
+				});
+			}
+		}
+
+		return Ok(entries);
+	}
+
+	/// Collects the clusters of the chain starting at `first`.
+	fn chain(&self, first: u32) -> Result<Vec<u32>, FatError> {
+		let mut clusters = Vec::new();
+		let mut cluster = Some(first);
+
+		while let Some(current) = cluster {
+			if clusters.len() >= self.clusters as usize {
+				return Err(FatError::ChainLoop);
+			}
+			clusters.push(current);
+			cluster = self.next_cluster(current)?;
+		}
+
+		return Ok(clusters);
+	}
+
+	/// Returns the cluster after `cluster` in its chain, `None` at the end.
+	///
+	/// Returns `BadCluster` if `cluster` or the next one is not a cluster of
+	/// the volume, and a device error if the FAT cannot be read.
+	pub fn next_cluster(&self, cluster: u32) -> Result<Option<u32>, FatError> {
+		self.check_cluster(cluster)?;
+
+		let (offset, end) = match self.fat_type {
+			FatType::Fat16 => (cluster as usize * 2, 0xfff8),
+			FatType::Fat32 => (cluster as usize * 4, 0x0fff_fff8),
+		};
+		let sector = self.fat_start + (offset / self.bytes_per_sector) as u64;
+		let offset = offset % self.bytes_per_sector;
+
+		let mut bytes = vec![0; self.bytes_per_sector];
+		read_block(&self.device, sector, &mut bytes)?;
+		let next = match self.fat_type {
+			FatType::Fat16 => u16_at(&bytes, offset) as u32,
+			FatType::Fat32 => u32_at(&bytes, offset) & 0x0fff_ffff,
+		};
+
+		if next >= end {
+			return Ok(None);
+		}
+		self.check_cluster(next)?;
+		return Ok(Some(next));
+	}
+
+	/// Checks that `cluster` is on the volume.
+	fn check_cluster(&self, cluster: u32) -> Result<(), FatError> {
+		if !(2..self.clusters + 2).contains(&cluster) {
+			return Err(FatError::BadCluster(cluster));
+		}
+		return Ok(());
+	}
+
+	/// Returns the first sector of `cluster`.
+	fn cluster_sector(&self, cluster: u32) -> u64 {
+		return self.data_start
+			+ (cluster as u64 - 2) * self.sectors_per_cluster;
+	}
+
+	/// Reads `cluster` into `buf`, a cluster long.
+	fn read_cluster(
+		&self,
+		cluster: u32,
+		buf: &mut [u8],
+	) -> Result<(), FatError> {
+		let start = self.cluster_sector(cluster);
+		for (sector, bytes) in
+			(start..).zip(buf.chunks_exact_mut(self.bytes_per_sector))
+		{
+			read_block(&self.device, sector, bytes)?;
+		}
+
+		return Ok(());
+	}
+}
+
+/// Reads block `lba` of the device registered as `device`.
+fn read_block(device: &str, lba: u64, buf: &mut [u8]) -> Result<(), FatError> {
+	return block::with_device(device, |device: &dyn BlockDevice| {
+		return device.read_block(lba, buf);
+	})
+	.ok_or(FatError::NoDevice)?
+	.map_err(FatError::Device);
+}
+
+/// Turns the padded 8.3 name of `raw` into `NAME.EXT`.
+fn short_name(raw: &[u8]) -> String {
+	let mut name = String::new();
+
+	// 0x05 stands for a first byte of 0xe5, which marks deleted entries
+	match raw[0] {
+		0x05 => push_padded(&mut name, &[0xe5]),
+		_ => push_padded(&mut name, &raw[..1]),
+	}
+	push_padded(&mut name, &raw[1..8]);
+	if raw[8] != b' ' {
+		name.push('.');
+		push_padded(&mut name, &raw[8..11]);
+	}
+
+	return name;
+}
+
+/// Appends the bytes of a space padded name field to `name`.
+fn push_padded(name: &mut String, field: &[u8]) {
+	for &byte in field.iter().take_while(|&&byte| byte != b' ') {
+		name.push(match byte {
+			b' '..=b'~' => byte as char,
+			_ => '?',
+		});
+	}
+}
+
+/// A file opened by [`Fat::open`], read front to back.
+pub struct File<'a> {
+	fs: &'a Fat,
+	first_cluster: u32,
+	size: u32,
+	position: u32,
+	/// The cluster last read, allocated on the first read.
+	buffer: Vec<u8>,
+	/// Index in the chain and number of the cluster in `buffer`.
+	cached: Option<(u32, u32)>,
+}
+
+impl File<'_> {
+	/// Returns the size of the file in bytes.
+	pub fn size(&self) -> u32 {
+		return self.size;
+	}
+
+	/// Reads from the current position into `buf` and returns the number of
+	/// bytes read, 0 at the end of the file.
+	pub fn read(&mut self, buf: &mut [u8]) -> Result<usize, FatError> {
+		let cluster_size = self.fs.cluster_size();
+		let mut done = 0;
+
+		while done < buf.len() && self.position < self.size {
+			let index = self.position / cluster_size as u32;
+			let cluster = self.seek_cluster(index)?;
+			if self.buffer.is_empty() {
+				self.buffer = vec![0; cluster_size];
+			}
+			if self.cached != Some((index, cluster)) {
+				self.fs.read_cluster(cluster, &mut self.buffer)?;
+				self.cached = Some((index, cluster));
+			}
+
+			let offset = self.position as usize % cluster_size;
+			let len = (cluster_size - offset)
+				.min(buf.len() - done)
+				.min((self.size - self.position) as usize);
+			buf[done..done + len]
+				.copy_from_slice(&self.buffer[offset..offset + len]);
+
+			done += len;
+			self.position += len as u32;
+		}
+
+		return Ok(done);
+	}
+
+	/// Reads the rest of the file into a [`Vec`].
+	pub fn read_to_end(&mut self) -> Result<Vec<u8>, FatError> {
+		let mut data = vec![0; (self.size - self.position) as usize];
+		let len = self.read(&mut data)?;
+		data.truncate(len);
+
+		return Ok(data);
+	}
+
+	/// Returns cluster number `index` of the chain, walking on from
+	/// the cached one when it is not past it.
+	fn seek_cluster(&self, index: u32) -> Result<u32, FatError> {
+		let (mut at, mut cluster) = match self.cached {
+			Some((at, cluster)) if at <= index => (at, cluster),
+			_ => (0, self.first_cluster),
+		};
+		self.fs.check_cluster(cluster)?;
+
+		while at < index {
+			if at >= self.fs.clusters {
+				return Err(FatError::ChainLoop);
+			}
+			cluster = self
+				.fs
+				.next_cluster(cluster)?
+				.ok_or(FatError::ChainTooShort)?;
+			at += 1;
+		}
+
+		return Ok(cluster);
+	}
+}
+
+static MOUNTED: OnceLock<Fat> = OnceLock::new();
+
+/// Mounts `ata0` at [`MOUNT_POINT`] if it holds a FAT volume.
+pub fn init() {
+	let Ok(fat) = Fat::mount("ata0") else {
+		return;
+	};
+
+	log_info!(
+		"fat: {:?} volume on ata0 at {}",
+		fat.fat_type(),
+		MOUNT_POINT
+	);
+	let _ = MOUNTED.set(fat);
+}
+
+/// Returns the volume [`init`] mounted, `None` if there is none.
+pub fn mounted() -> Option<&'static Fat> {
+	return MOUNTED.get();
+}
+
+/// Returns the path on the mounted volume for a console path below
+/// [`MOUNT_POINT`], `None` for other paths.
+pub fn strip_mount_point(path: &str) -> Option<&str> {
+	let rest = path.strip_prefix(MOUNT_POINT)?;
+	if !rest.is_empty() && !rest.starts_with('/') {
+		return None;
+	}
+
+	return Some(rest);
+}
+
+/// Opens the file at `path` on the mounted volume.
+pub fn open(path: &str) -> Result<File<'static>, FatError> {
+	return mounted().ok_or(FatError::NoDevice)?.open(path);
+}
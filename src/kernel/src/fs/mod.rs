@@ -0,0 +1,10 @@
+//! Filesystems.
+//!
+//! [`ramfs`] serves the files of the initrd, a tar archive the bootloader
+//! loads as a module. [`fat`] reads FAT16 and FAT32 volumes off a block
+//! device.
+
+/// Module containing the read-only FAT16 and FAT32 driver.
+pub mod fat;
+/// Module containing the read-only filesystem over the initrd.
+pub mod ramfs;
@@ -0,0 +1,260 @@
+//! The initrd, a ustar archive the bootloader loads as a boot module.
+//!
+//! [`Ramfs::parse`] walks the 512 byte headers once and keeps the name of
+//! every regular file with its data, which stays in the module: nothing is
+//! copied. The archive ends at the first all zero header or at the end of
+//! the module. Directories, links and the like are skipped, a leading `./`
+//! or `/` is dropped from the names, and the ustar prefix is joined to the
+//! name with a `/`.
+//!
+//! [`init`] indexes the first boot module with the ustar magic, which
+//! [`open`] and [`entries`] then serve.
+
+use crate::{
+	arch::x86::multiboot::boot_modules, log_info, log_warn, sync::OnceLock,
+};
+use alloc::{string::String, vec::Vec};
+use core::{fmt, str::from_utf8};
+
+/// Size of a header and the unit data is padded to.
+pub const BLOCK_SIZE: usize = 512;
+
+const MAGIC: &[u8] = b"ustar";
+const MAGIC_OFFSET: usize = 257;
+
+/// Why a module is not an archive [`Ramfs::parse`] can index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RamfsError {
+	/// The header at the offset given does not have the ustar magic.
+	BadMagic(usize),
+	/// The checksum of the header at the offset given does not match.
+	BadChecksum(usize),
+	/// A size or checksum field of the header at the offset given is not an
+	/// octal number.
+	BadNumber(usize),
+	/// A name in the header at the offset given is not UTF-8.
+	BadName(usize),
+	/// The data of the header at the offset given runs past the archive.
+	Truncated(usize),
+}
+
+impl fmt::Display for RamfsError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			RamfsError::BadMagic(offset) => {
+				return write!(f, "no ustar header at {:#x}", offset)
+			}
+			RamfsError::BadChecksum(offset) => {
+				return write!(f, "bad header checksum at {:#x}", offset)
+			}
+			RamfsError::BadNumber(offset) => {
+				return write!(f, "bad number in the header at {:#x}", offset)
+			}
+			RamfsError::BadName(offset) => {
+				return write!(
+					f,
+					"name not UTF-8 in the header at {:#x}",
+					offset
+				)
+			}
+			RamfsError::Truncated(offset) => {
+				return write!(f, "file at {:#x} runs past the archive", offset)
+			}
+		}
+	}
+}
+
+/// A file of the archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry<'a> {
+	/// Path of the file, without a leading `/`.
+	pub name: String,
+	/// Offset of the data in the archive.
+	pub offset: usize,
+	/// The file's bytes.
+	pub data: &'a [u8],
+}
+
+impl Entry<'_> {
+	/// Returns the size of the file in bytes.
+	pub fn size(&self) -> usize {
+		return self.data.len();
+	}
+}
+
+/// The files of an archive, in archive order.
+#[derive(Debug, Clone, Default)]
+pub struct Ramfs<'a> {
+	entries: Vec<Entry<'a>>,
+}
+
+impl<'a> Ramfs<'a> {
+	/// Indexes the regular files of the ustar archive in `bytes`, see the
+	/// module documentation.
+	pub fn parse(bytes: &'a [u8]) -> Result<Self, RamfsError> {
+		let mut entries = Vec::new();
+		let mut offset = 0;
+
+		while let Some(header) = bytes.get(offset..offset + BLOCK_SIZE) {
+			if header.iter().all(|&byte| byte == 0) {
+				break;
+			}
+			if !is_header(header) {
+				return Err(RamfsError::BadMagic(offset));
+			}
+
+			let checksum = octal(&header[148..156])
+				.ok_or(RamfsError::BadNumber(offset))?;
+			if checksum != header_sum(header) {
+				return Err(RamfsError::BadChecksum(offset));
+			}
+			let size = octal(&header[124..136])
+				.ok_or(RamfsError::BadNumber(offset))?;
+
+			let start = offset + BLOCK_SIZE;
+			let data = start
+				.checked_add(size)
+				.and_then(|end| bytes.get(start..end))
+				.ok_or(RamfsError::Truncated(offset))?;
+
+			// Regular files, the old archives mark them with a NUL
+			if matches!(header[156], b'0' | 0) {
+				let name =
+					entry_name(header).ok_or(RamfsError::BadName(offset))?;
+				if !name.is_empty() {
+					entries.push(Entry {
+						name,
+						offset: start,
+						data,
+					});
+				}
+			}
+
+			offset = start + size.div_ceil(BLOCK_SIZE) * BLOCK_SIZE;
+		}
+
+		return Ok(Self {
+			entries,
+		});
+	}
+
+	/// Returns the data of the file at `path`, with or without a leading
+	/// `/`.
+	pub fn open(&self, path: &str) -> Option<&'a [u8]> {
+		let path = path.trim_start_matches('/');
+
+		return self
+			.entries
+			.iter()
+			.find(|entry| entry.name == path)
+			.map(|entry| entry.data);
+	}
+
+	/// Returns the files, in archive order.
+	pub fn entries(&self) -> &[Entry<'a>] {
+		return &self.entries;
+	}
+
+	/// Returns the number of files.
+	pub fn len(&self) -> usize {
+		return self.entries.len();
+	}
+
+	/// Returns whether the archive has no files.
+	pub fn is_empty(&self) -> bool {
+		return self.entries.is_empty();
+	}
+}
+
+/// Returns whether `bytes` starts with a ustar header.
+pub fn is_header(bytes: &[u8]) -> bool {
+	return bytes
+		.get(MAGIC_OFFSET..MAGIC_OFFSET + MAGIC.len())
+		.is_some_and(|magic| magic == MAGIC);
+}
+
+/// Reads an octal field, which ends at a NUL or a space.
+fn octal(field: &[u8]) -> Option<usize> {
+	let digits = field
+		.split(|&byte| byte == 0 || byte == b' ')
+		.find(|digits| !digits.is_empty())?;
+
+	let mut value: usize = 0;
+	for &digit in digits {
+		if !(b'0'..=b'7').contains(&digit) {
+			return None;
+		}
+		value = value.checked_mul(8)?.checked_add((digit - b'0') as usize)?;
+	}
+	return Some(value);
+}
+
+/// Sums the header bytes with the checksum field as spaces.
+fn header_sum(header: &[u8]) -> usize {
+	return header
+		.iter()
+		.enumerate()
+		.map(|(index, &byte)| match index {
+			148..156 => b' ' as usize,
+			_ => byte as usize,
+		})
+		.sum();
+}
+
+/// Joins the prefix and name fields, `None` if they are not UTF-8.
+fn entry_name(header: &[u8]) -> Option<String> {
+	let name = c_field(&header[0..100])?;
+	let prefix = c_field(&header[345..500])?;
+
+	let mut path = String::from(prefix);
+	if !path.is_empty() {
+		path.push('/');
+	}
+	path.push_str(name);
+
+	let path = path.trim_start_matches("./").trim_start_matches('/');
+	return Some(String::from(path));
+}
+
+/// Returns the text of a NUL padded field.
+fn c_field(field: &[u8]) -> Option<&str> {
+	let len = field
+		.iter()
+		.position(|&byte| byte == 0)
+		.unwrap_or(field.len());
+	return from_utf8(&field[..len]).ok();
+}
+
+static RAMFS: OnceLock<Ramfs<'static>> = OnceLock::new();
+
+/// Indexes the first boot module that is a ustar archive. Warns if it is
+/// malformed, the files before the error are lost as well then.
+pub fn init() {
+	let Some((index, bytes)) = boot_modules()
+		.iter()
+		.enumerate()
+		.filter_map(|(index, module)| Some((index, module.bytes()?)))
+		.find(|(_, bytes)| is_header(bytes))
+	else {
+		return;
+	};
+
+	match Ramfs::parse(bytes) {
+		Ok(ramfs) => {
+			log_info!("ramfs: {} files in module {}", ramfs.len(), index);
+			let _ = RAMFS.set(ramfs);
+		}
+		Err(error) => log_warn!("ramfs: module {}: {}", index, error),
+	}
+}
+
+/// Returns the data of the initrd file at `path`, `None` if there is no
+/// such file or no initrd.
+pub fn open(path: &str) -> Option<&'static [u8]> {
+	return RAMFS.get()?.open(path);
+}
+
+/// Returns the initrd files, none before [`init`] or without an initrd.
+pub fn entries() -> core::slice::Iter<'static, Entry<'static>> {
+	return RAMFS.get().map_or(&[][..], Ramfs::entries).iter();
+}
@@ -20,6 +20,7 @@
 #![feature(dropck_eyepatch)]
 #![feature(linked_list_cursors)]
 #![feature(allocator_api)]
+#![feature(alloc_error_handler)]
 #![deny(fuzzy_provenance_casts)] // Enforce proper pointer provenance
 #![warn(missing_docs)] // Require documentation for public items
 #![deny(unsafe_op_in_unsafe_fn)] // Require explicit unsafe blocks even in unsafe functions
@@ -47,10 +48,16 @@
 
 /// Specific Bare Metal support
 pub mod arch;
+/// Boot arguments - The options of the kernel command line
+pub mod bootargs;
 /// Collectiosn - Datatypes and structures
 pub mod collections;
 /// Device Support - Keyboard & Mouse
 pub mod device;
+/// Errors - The error type shared by kernel subsystems
+pub mod error;
+/// Filesystems - The initrd files
+pub mod fs;
 /// Libc - STD Library (Should move in future)
 pub mod libc;
 /// Macro directory
@@ -58,19 +65,45 @@ pub mod macros;
 pub mod memory;
 /// Panic
 pub mod panic;
+/// Symbols - The kernel symbol table for backtraces
+pub mod symbols;
 pub mod sync;
+/// Syscalls - The int 0x80 interface user mode calls the kernel through
+pub mod syscall;
+/// Tasks - Kernel threads and the scheduler
+pub mod task;
 /// Tests
 pub mod tests;
+/// Time keeping - Tick counter & uptime
+pub mod time;
 /// TTY Support - Specifically VGA
 pub mod tty;
+/// Utilities - Random numbers
+pub mod util;
 
 use alloc::boxed::Box;
-use arch::x86::multiboot::MultibootInfo;
-use core::ffi::c_void;
-use device::keyboard::Keyboard;
-use libc::console::console::Console;
+use arch::x86::{
+	cpu::init_cpu_features,
+	multiboot::{self, MultibootInfo},
+};
+use core::ptr;
+use device::{
+	ata, block,
+	keyboard::{self, KeyEvent, KeyboardKey, KEYBOARD},
+	pci, pit, speaker,
+};
+use libc::console::{
+	bin::memtest, console::Console, serial_input::SerialInput,
+	set_mirror_serial,
+};
 use memory::{allocator::memory_init, frame::FRAME_ALLOCATOR, FrameAllocator};
-use tty::serial::SERIAL;
+use tty::{
+	e9::{self, E9_OUTPUT},
+	log::{self, LogLevel},
+	output,
+	serial::{COM1, COM2, SERIAL},
+	vt::{self, VT_COUNT},
+};
 
 extern crate alloc;
 
@@ -78,11 +111,10 @@ extern crate alloc;
 
 const MAGIC_VALUE: u32 = 0x2badb002;
 
-extern "C" {
-	/* fn memcpy(dest: *mut c_void, src: *const c_void, n: usize) -> *mut c_void;
-	fn memset(str: *mut c_void, c: i32, len: usize) -> *mut c_void;
-	fn memcmp(s1: *const c_void, s2: *const c_void, n: usize) -> i32; */
-}
+/// Name of the kernel
+pub const NAME: &str = env!("CARGO_PKG_NAME");
+/// Version of the kernel
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /* -------------------------------------- */
 
@@ -95,13 +127,57 @@ pub extern "C" fn kernel_main(
 	if magic_number != MAGIC_VALUE {
 		panic!("Incorrect magic number.");
 	}
+	// Before the allocators can hand out the pages the command lines are on
+	bootargs::init(boot_info);
+	multiboot::save_modules(boot_info);
+	apply_loglevel();
+	if bootargs::get("debugcon") == Some("on") {
+		e9::set_enabled(true);
+		output::register(&E9_OUTPUT);
+	}
 
-	SERIAL.lock().init();
+	for port in [&COM1, &COM2] {
+		if ptr::eq(port, SERIAL) && bootargs::get("serial") == Some("off") {
+			continue;
+		}
+		let present = port.lock().init();
+		if !present && ptr::eq(port, SERIAL) {
+			let name = port.lock().name();
+			log_warn!("serial: no UART on {}, the log stays on screen", name);
+		}
+	}
+	init_cpu_features();
 
 	memory_init(boot_info);
+	memory::protect_kernel_image();
+	tty::framebuffer::init(boot_info);
+	fs::ramfs::init();
+	pci::init();
+	if let Err(error) = block::init() {
+		log_warn!("block: no RAM disk ({})", error);
+	}
+	ata::init();
+	fs::fat::init();
 
-	let mut keyboard = Keyboard::default();
-	let mut console = Console::default();
+	pit::init();
+	speaker::init();
+	if let Err(error) = task::init() {
+		log_warn!("task: no idle thread or worker ({})", error);
+	}
+	tty::status::init();
+	COM1.lock().enable_rx_interrupt();
+	COM2.lock().enable_rx_interrupt();
+	keyboard::enable_interrupt();
+
+	if bootargs::get("console") == Some("serial") {
+		set_mirror_serial(true);
+	}
+	if bootargs::get("memtest") == Some("1") {
+		memtest::memtest(&[]);
+	}
+
+	let mut consoles: [Option<Console>; VT_COUNT] = [const { None }; VT_COUNT];
+	consoles[vt::active()] = Some(Console::default());
 
 	#[cfg(test)]
 	test_main();
@@ -111,12 +187,68 @@ pub extern "C" fn kernel_main(
 
 	println_serial!("{} - {}", test1, test2);
 
+	let mut serial_input = SerialInput::new();
+
 	loop {
-		let c = match keyboard.input() {
-			Some(key) => key,
-			None => continue,
+		// Input typed on COM1 drives the active console like the keyboard
+		// does, and turns on the mirror so the output shows up there too.
+		let serial_key = SERIAL
+			.lock()
+			.read_byte()
+			.and_then(|byte| serial_input.feed(byte));
+		if let Some(key) = serial_key {
+			set_mirror_serial(true);
+			if let Some(console) = &mut consoles[vt::active()] {
+				console.handle_key(key);
+			}
+			continue;
+		}
+
+		let keyboard_key = {
+			let mut keyboard = KEYBOARD.lock();
+			keyboard.input().map(|key| (key, keyboard.alt_pressed()))
+		};
+		let Some((key, alt_pressed)) = keyboard_key else {
+			// The worker decodes what comes in meanwhile
+			time::sleep_ms(0);
+			continue;
 		};
 
-		console.add_buffer(c);
+		if let Some(index) = terminal_hotkey(alt_pressed, key) {
+			vt::switch_to(index);
+			consoles[vt::active()].get_or_insert_with(Console::default);
+			continue;
+		}
+
+		if let Some(console) = &mut consoles[vt::active()] {
+			console.handle_key(key);
+		}
+	}
+}
+
+/// Starts the log at the level `loglevel=` names, if it names one.
+fn apply_loglevel() {
+	let Some(name) = bootargs::get("loglevel") else {
+		return;
+	};
+
+	match LogLevel::from_name(name) {
+		Some(level) => log::set_level(level),
+		None => log_warn!("bootargs: unknown log level '{}'", name),
+	}
+}
+
+/// Maps Alt+F1 to Alt+F4 to the index of the terminal they switch to.
+fn terminal_hotkey(alt_pressed: bool, key: KeyEvent) -> Option<usize> {
+	if !alt_pressed {
+		return None;
+	}
+
+	match key {
+		KeyEvent::Special(KeyboardKey::KeyF1) => return Some(0),
+		KeyEvent::Special(KeyboardKey::KeyF2) => return Some(1),
+		KeyEvent::Special(KeyboardKey::KeyF3) => return Some(2),
+		KeyEvent::Special(KeyboardKey::KeyF4) => return Some(3),
+		_ => return None,
 	}
 }
@@ -0,0 +1,53 @@
+use crate::{
+	device::speaker, libc::console::parse::parse_usize, println, time,
+};
+
+/// Pitch of a beep without arguments.
+const DEFAULT_FREQ_HZ: u32 = 880;
+
+/// Length of a beep without a length.
+const DEFAULT_MS: u32 = 200;
+
+/// Longest beep, so a typo does not hold the console for minutes.
+const MAX_MS: u32 = 10_000;
+
+/// Sounds the PC speaker at the given pitch for the given time.
+pub fn beep(args: &[&str]) {
+	let (freq, ms) = match args {
+		[] => (None, None),
+		[freq] => (Some(*freq), None),
+		[freq, ms] => (Some(*freq), Some(*ms)),
+		_ => {
+			println!("usage: beep [freq] [ms]");
+			return;
+		}
+	};
+
+	let freq_hz = match freq.map(parse_usize) {
+		None => DEFAULT_FREQ_HZ,
+		Some(Some(freq_hz)) if freq_hz > 0 => {
+			freq_hz.min(u32::MAX as usize) as u32
+		}
+		Some(_) => {
+			println!("beep: invalid frequency '{}'", freq.unwrap_or_default());
+			return;
+		}
+	};
+	let duration_ms = match ms.map(parse_usize) {
+		None => DEFAULT_MS,
+		Some(Some(ms)) if ms <= MAX_MS as usize => ms as u32,
+		Some(_) => {
+			println!(
+				"beep: invalid duration '{}', up to {} ms",
+				ms.unwrap_or_default(),
+				MAX_MS
+			);
+			return;
+		}
+	};
+
+	if time::tick_rate() == 0 {
+		println!("beep: no timer running, the length is a guess");
+	}
+	speaker::beep(freq_hz, duration_ms);
+}
@@ -0,0 +1,24 @@
+use crate::{device::block, println};
+
+/// Lists the registered block devices with their block size and count.
+pub fn blkdev() {
+	let devices = block::list();
+	if devices.is_empty() {
+		println!("blkdev: no block devices");
+		return;
+	}
+
+	println!(
+		"{:<8} {:>6} {:>10} {:>10}",
+		"name", "block", "blocks", "KiB"
+	);
+	for device in devices {
+		println!(
+			"{:<8} {:>6} {:>10} {:>10}",
+			device.name,
+			device.block_size,
+			device.num_blocks,
+			device.num_blocks * device.block_size as u64 / 1024
+		);
+	}
+}
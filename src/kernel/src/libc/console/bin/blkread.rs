@@ -0,0 +1,28 @@
+use crate::{
+	device::block,
+	libc::console::{bin::hexdump::dump_slice, parse::parse_usize},
+	println,
+};
+use alloc::vec;
+
+/// Dumps block `lba` of a registered block device, whichever driver it is.
+pub fn blkread(args: &[&str]) {
+	let [name, lba] = args else {
+		println!("usage: blkread <device> <lba>");
+		return;
+	};
+	let Some(lba) = parse_usize(lba) else {
+		println!("blkread: invalid block '{}'", lba);
+		return;
+	};
+
+	let read = block::with_device(name, |device| {
+		let mut data = vec![0; device.block_size()];
+		return device.read_block(lba as u64, &mut data).map(|()| data);
+	});
+	match read {
+		None => println!("blkread: no device '{}'", name),
+		Some(Err(error)) => println!("blkread: {}: {}", name, error),
+		Some(Ok(data)) => dump_slice(&data, 0),
+	}
+}
@@ -0,0 +1,7 @@
+use crate::{memory::allocator::shrink_caches, println};
+
+/// Returns the free slabs of every cache to the buddy allocator.
+pub fn cache_shrink() {
+	let freed = shrink_caches();
+	println!("cache_shrink: freed {} slabs", freed);
+}
@@ -0,0 +1,87 @@
+use crate::{
+	fs::{fat, ramfs},
+	print, println,
+	tty::pager,
+};
+use core::str::from_utf8;
+
+/// Bytes read from a FAT file at a time.
+const CHUNK: usize = 512;
+
+/// Prints a file of the initrd, or of the FAT volume for paths below
+/// [`fat::MOUNT_POINT`]. Bytes that are not UTF-8 show up as ■, like the
+/// characters the screen has no glyph for.
+pub fn cat(args: &[&str]) {
+	let [path] = args else {
+		println!("usage: cat <path>");
+		return;
+	};
+
+	if let Some(path) = fat::strip_mount_point(path) {
+		cat_fat(path);
+		return;
+	}
+
+	let Some(data) = ramfs::open(path) else {
+		println!("cat: no file '{}'", path);
+		return;
+	};
+	if !print_text(data).is_empty() {
+		print!("\u{25a0}");
+	}
+}
+
+// Helper printing a file of the FAT volume a chunk at a time
+fn cat_fat(path: &str) {
+	let mut file = match fat::open(path) {
+		Ok(file) => file,
+		Err(error) => {
+			println!("cat: {}: {}", path, error);
+			return;
+		}
+	};
+
+	// Room for the start of a character cut at the end of a chunk
+	let mut buf = [0; CHUNK + 3];
+	let mut carried = 0;
+	while !pager::aborted() {
+		let len = match file.read(&mut buf[carried..carried + CHUNK]) {
+			Ok(0) => break,
+			Ok(len) => carried + len,
+			Err(error) => {
+				println!("\ncat: {}: {}", path, error);
+				return;
+			}
+		};
+
+		carried = print_text(&buf[..len]).len();
+		buf.copy_within(len - carried..len, 0);
+	}
+	if carried > 0 {
+		print!("\u{25a0}");
+	}
+}
+
+// Helper printing `data`, ■ for the bytes that are not UTF-8. Returns the
+// bytes of a character cut at the end, which are not printed yet.
+fn print_text(mut data: &[u8]) -> &[u8] {
+	while !data.is_empty() && !pager::aborted() {
+		match from_utf8(data) {
+			Ok(text) => {
+				print!("{}", text);
+				break;
+			}
+			Err(error) => {
+				let (text, rest) = data.split_at(error.valid_up_to());
+				print!("{}", from_utf8(text).unwrap_or(""));
+				let Some(len) = error.error_len() else {
+					return rest;
+				};
+				print!("\u{25a0}");
+				data = &rest[len..];
+			}
+		}
+	}
+
+	return &[];
+}
@@ -0,0 +1,6 @@
+use crate::{bootargs, println};
+
+/// Prints the kernel command line the bootloader passed.
+pub fn cmdline() {
+	println!("{}", bootargs::raw());
+}
@@ -0,0 +1,49 @@
+use crate::{
+	arch::x86::cpu::{brand, cpu_features, signature, vendor, Feature},
+	print, println,
+	tty::VGA_WIDTH,
+};
+use core::str::from_utf8;
+
+const FEATURES_LABEL: &str = "Features: ";
+
+/// Prints the vendor, brand, signature and features reported by CPUID.
+pub fn cpuinfo() {
+	let Some(vendor) = vendor() else {
+		println!("cpuinfo: CPUID is not supported by this CPU");
+		return;
+	};
+
+	println!("Vendor:   {}", from_utf8(&vendor).unwrap_or("unknown"));
+
+	if let Some(brand) = brand() {
+		let len = brand.iter().position(|b| *b == 0).unwrap_or(brand.len());
+		let brand = from_utf8(&brand[..len]).unwrap_or("unknown");
+		println!("Brand:    {}", brand.trim());
+	}
+
+	if let Some(sig) = signature() {
+		println!(
+			"Family:   {}, Model: {}, Stepping: {}",
+			sig.family, sig.model, sig.stepping
+		);
+	}
+
+	let features = cpu_features();
+	let mut col = FEATURES_LABEL.len();
+
+	print!("{}", FEATURES_LABEL);
+	for feature in Feature::ALL.iter().filter(|f| features.has(**f)) {
+		let name = feature.name();
+
+		if col + name.len() + 1 >= VGA_WIDTH {
+			println!();
+			print!("{:width$}", "", width = FEATURES_LABEL.len());
+			col = FEATURES_LABEL.len();
+		}
+
+		print!("{} ", name);
+		col += name.len() + 1;
+	}
+	println!();
+}
@@ -0,0 +1,6 @@
+use crate::{device::rtc::rtc, println};
+
+/// Prints the current date and time from the RTC.
+pub fn date() {
+	println!("{}", rtc());
+}
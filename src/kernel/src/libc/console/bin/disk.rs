@@ -0,0 +1,60 @@
+use crate::{
+	device::ata::{self, SECTOR_SIZE},
+	libc::console::{bin::hexdump::dump_slice, parse::parse_usize},
+	println,
+};
+
+/// Shows the ATA disk, dumps one of its sectors or fills one with a byte.
+pub fn disk(args: &[&str]) {
+	match args {
+		["info"] => info(),
+		["read", lba] => {
+			let Some(lba) = parse_lba(lba) else {
+				return;
+			};
+			let mut sector = [0; SECTOR_SIZE];
+			match ata::read_sectors(lba, 1, &mut sector) {
+				Ok(()) => dump_slice(&sector, 0),
+				Err(error) => println!("disk: {}", error),
+			}
+		}
+		["write", lba, byte] => {
+			let Some(lba) = parse_lba(lba) else {
+				return;
+			};
+			let Some(byte) =
+				parse_usize(byte).and_then(|b| u8::try_from(b).ok())
+			else {
+				println!("disk: invalid byte '{}'", byte);
+				return;
+			};
+			if let Err(error) = ata::write_sectors(lba, 1, &[byte; SECTOR_SIZE])
+			{
+				println!("disk: {}", error);
+			}
+		}
+		_ => println!("usage: disk info | read <lba> | write <lba> <byte>"),
+	}
+}
+
+// Helper printing the model and size of the disk
+fn info() {
+	let Some(info) = ata::info() else {
+		println!("disk: no ATA disk on the primary channel");
+		return;
+	};
+
+	println!("drive:   primary {}", info.drive.name());
+	println!("model:   {}", info.model);
+	println!("sectors: {} ({} KiB)", info.sectors, info.size() / 1024);
+}
+
+// Helper parsing a sector number, complaining if it is not one
+fn parse_lba(lba: &str) -> Option<u32> {
+	let parsed = parse_usize(lba).and_then(|lba| u32::try_from(lba).ok());
+	if parsed.is_none() {
+		println!("disk: invalid sector '{}'", lba);
+	}
+
+	return parsed;
+}
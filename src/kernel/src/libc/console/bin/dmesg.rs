@@ -0,0 +1,69 @@
+use crate::{
+	println,
+	tty::{
+		klog::{KLOG, MAX_RECORD_TEXT},
+		log::{LogLevel, Timestamp},
+		pager,
+	},
+};
+use core::str::from_utf8;
+
+/// Replays the kernel log. `dmesg -c` clears it afterwards.
+pub fn dmesg(args: &[&str]) {
+	let clear = match args {
+		[] => false,
+		["-c"] => true,
+		_ => {
+			println!("usage: dmesg [-c]");
+			return;
+		}
+	};
+
+	let (mut seq, end, dropped) = {
+		let klog = KLOG.lock();
+		(0, klog.next_seq(), klog.dropped())
+	};
+
+	if dropped > 0 {
+		println!("dmesg: {} older records were dropped", dropped);
+	}
+
+	// Copy one record at a time so the log is not locked while printing.
+	let mut text = [0; MAX_RECORD_TEXT];
+	while seq < end && !pager::aborted() {
+		let Some(record) = KLOG.lock().read(seq, &mut text) else {
+			break;
+		};
+		if record.seq >= end {
+			break;
+		}
+
+		let bytes = &text[..record.len];
+		let text = match from_utf8(bytes) {
+			Ok(text) => text,
+			Err(e) => from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+		};
+		println!(
+			"{} {} {}",
+			Timestamp(record.timestamp_ms),
+			level_str(record.level),
+			text
+		);
+
+		seq = record.seq + 1;
+	}
+
+	if clear {
+		KLOG.lock().clear();
+	}
+}
+
+fn level_str(level: LogLevel) -> &'static str {
+	match level {
+		LogLevel::Error => return "[ERROR]",
+		LogLevel::Warn => return "[WARN]",
+		LogLevel::Info => return "[INFO]",
+		LogLevel::Debug => return "[DEBUG]",
+		LogLevel::Trace => return "[TRACE]",
+	}
+}
@@ -0,0 +1,13 @@
+use crate::{print, println};
+
+/// Prints its arguments separated by a single space, followed by a newline.
+pub fn echo(args: &[&str]) {
+	for (i, arg) in args.iter().enumerate() {
+		if i > 0 {
+			print!(" ");
+		}
+		print!("{}", arg);
+	}
+
+	println!();
+}
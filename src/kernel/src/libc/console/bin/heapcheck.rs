@@ -0,0 +1,12 @@
+use crate::{memory::heapcheck::heap_check, println};
+
+/// Cross checks the allocator bookkeeping, the details of any problem go to
+/// the kernel log.
+pub fn heapcheck() {
+	match heap_check() {
+		Ok(()) => println!("heapcheck: ok"),
+		Err(problems) => {
+			println!("heapcheck: {} problems found, see dmesg", problems)
+		}
+	}
+}
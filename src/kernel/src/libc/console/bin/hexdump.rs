@@ -0,0 +1,130 @@
+use crate::{
+	libc::console::parse::{parse_hex, parse_usize},
+	memory::{paging::translate, VirtAddr, PAGE_SIZE},
+	print, println,
+};
+use core::ptr;
+
+const DEFAULT_LEN: usize = 256;
+const BYTES_PER_LINE: usize = 16;
+
+/// Dumps `len` bytes (default 256) starting at a virtual address, refusing
+/// to read from unmapped pages.
+pub fn hexdump(args: &[&str]) {
+	let (addr, len) = match args {
+		[addr] => (parse_hex(addr), Some(DEFAULT_LEN)),
+		[addr, len] => (parse_hex(addr), parse_usize(len)),
+		_ => {
+			println!("usage: hexdump <addr> [len]");
+			return;
+		}
+	};
+
+	let Some(addr) = addr else {
+		println!("hexdump: invalid address '{}'", args[0]);
+		return;
+	};
+	let Some(len) = len else {
+		println!("hexdump: invalid length '{}'", args[1]);
+		return;
+	};
+	if len == 0 {
+		return;
+	}
+	let Some(end) = addr.checked_add(len - 1) else {
+		println!("hexdump: range wraps around the address space");
+		return;
+	};
+
+	if let Some(page) = first_unmapped_page(addr, end) {
+		println!("hexdump: page {:#010x} is not mapped", page);
+		return;
+	}
+
+	dump(addr, end);
+}
+
+/// Prints the bytes from `start` to `end` inclusive, 16 to a line. Every
+/// page in between has to be mapped.
+pub fn dump(start: usize, end: usize) {
+	let mut line = start & !(BYTES_PER_LINE - 1);
+	loop {
+		print_line(line, start, end);
+
+		match line.checked_add(BYTES_PER_LINE) {
+			Some(next) if next <= end => line = next,
+			_ => break,
+		}
+	}
+}
+
+/// Returns the base of the first page in `start..=end` without a mapping.
+fn first_unmapped_page(start: usize, end: usize) -> Option<usize> {
+	let mut page = start & !(PAGE_SIZE - 1);
+
+	loop {
+		if translate(VirtAddr::new(page)).is_none() {
+			return Some(page);
+		}
+
+		match page.checked_add(PAGE_SIZE) {
+			Some(next) if next <= end => page = next,
+			_ => return None,
+		}
+	}
+}
+
+/// Prints `bytes` like [`dump`], with the lines numbered from `offset`
+/// instead of their address.
+pub fn dump_slice(bytes: &[u8], offset: usize) {
+	for (index, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+		let mut line = [None; BYTES_PER_LINE];
+		for (byte, &value) in line.iter_mut().zip(chunk) {
+			*byte = Some(value);
+		}
+		print_bytes(offset + index * BYTES_PER_LINE, &line);
+	}
+}
+
+/// Prints one line of the dump, leaving blanks for bytes outside
+/// `start..=end`.
+fn print_line(line: usize, start: usize, end: usize) {
+	let mut bytes = [None; BYTES_PER_LINE];
+
+	for (i, byte) in bytes.iter_mut().enumerate() {
+		let addr = line + i;
+		if (start..=end).contains(&addr) {
+			*byte = Some(unsafe {
+				ptr::with_exposed_provenance::<u8>(addr).read_volatile()
+			});
+		}
+	}
+
+	print_bytes(line, &bytes);
+}
+
+/// Prints a line labelled `line`, blanks for the bytes that are `None`.
+fn print_bytes(line: usize, bytes: &[Option<u8>; BYTES_PER_LINE]) {
+	print!("{:08x}  ", line);
+	for (i, byte) in bytes.iter().enumerate() {
+		if i == BYTES_PER_LINE / 2 {
+			print!(" ");
+		}
+		match byte {
+			Some(b) => print!("{:02x} ", b),
+			None => print!("   "),
+		}
+	}
+
+	print!(" |");
+	for byte in bytes.iter() {
+		match byte {
+			Some(b) if b.is_ascii_graphic() || *b == b' ' => {
+				print!("{}", *b as char)
+			}
+			Some(_) => print!("."),
+			None => print!(" "),
+		}
+	}
+	println!("|");
+}
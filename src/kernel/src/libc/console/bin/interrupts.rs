@@ -0,0 +1,19 @@
+use crate::{arch::x86::pic::irq_lines, println};
+
+/// Prints one row per IRQ line: whether it is masked, the handler registered
+/// for it and how many interrupts it delivered.
+pub fn interrupts() {
+	println!(
+		"{:<4} {:<8} {:<10} {:>10}",
+		"irq", "state", "handler", "count"
+	);
+	for line in irq_lines() {
+		println!(
+			"{:<4} {:<8} {:<10} {:>10}",
+			line.irq,
+			if line.masked { "masked" } else { "unmasked" },
+			line.handler.unwrap_or("-"),
+			line.count
+		);
+	}
+}
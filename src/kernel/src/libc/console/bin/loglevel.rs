@@ -0,0 +1,22 @@
+use crate::{
+	print, println,
+	tty::log::{self, LogLevel},
+};
+
+/// Shows or sets the most verbose level the log macros emit.
+pub fn loglevel(args: &[&str]) {
+	match args {
+		[] => println!("log level: {}", log::level().name()),
+		[name] => match LogLevel::from_name(name) {
+			Some(level) => log::set_level(level),
+			None => {
+				print!("loglevel: unknown level '{}', expected one of:", name);
+				for level in LogLevel::ALL {
+					print!(" {}", level.name());
+				}
+				println!();
+			}
+		},
+		_ => println!("usage: loglevel [level]"),
+	}
+}
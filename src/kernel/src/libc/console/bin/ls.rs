@@ -0,0 +1,64 @@
+use crate::{
+	fs::{fat, ramfs},
+	println,
+	tty::pager,
+};
+
+/// Lists the files of the initrd with their sizes, or a directory of the
+/// FAT volume for paths below [`fat::MOUNT_POINT`].
+pub fn ls(args: &[&str]) {
+	match args {
+		[] => ls_initrd(),
+		[path] => match fat::strip_mount_point(path) {
+			Some(path) => ls_fat(path),
+			None if path.trim_matches('/').is_empty() => ls_initrd(),
+			None => {
+				println!("ls: '{}' is not below {}", path, fat::MOUNT_POINT)
+			}
+		},
+		_ => println!("usage: ls [path]"),
+	}
+}
+
+// Helper listing the files of the initrd
+fn ls_initrd() {
+	let mut entries = ramfs::entries().peekable();
+	if entries.peek().is_none() {
+		println!("ls: no initrd");
+	}
+
+	for entry in entries {
+		if pager::aborted() {
+			break;
+		}
+		println!("{:>8}  {}", entry.size(), entry.name);
+	}
+	if fat::mounted().is_some() {
+		println!("{:>8}  {}/", "<dir>", fat::MOUNT_POINT.trim_matches('/'));
+	}
+}
+
+// Helper listing the directory at `path` of the FAT volume
+fn ls_fat(path: &str) {
+	let Some(fs) = fat::mounted() else {
+		println!("ls: nothing mounted at {}", fat::MOUNT_POINT);
+		return;
+	};
+
+	let entries = match fs.read_dir(path) {
+		Ok(entries) => entries,
+		Err(error) => {
+			println!("ls: {}: {}", path, error);
+			return;
+		}
+	};
+	for entry in entries {
+		if pager::aborted() {
+			break;
+		}
+		match entry.is_dir {
+			true => println!("{:>8}  {}/", "<dir>", entry.name),
+			false => println!("{:>8}  {}", entry.size, entry.name),
+		}
+	}
+}
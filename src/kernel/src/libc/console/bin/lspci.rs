@@ -0,0 +1,62 @@
+use crate::{
+	device::pci::{self, Bar},
+	println,
+	tty::pager,
+};
+
+/// Prints one line per PCI function: its address, vendor and device id,
+/// class and the class's name. `lspci -v` adds the BARs.
+pub fn lspci(args: &[&str]) {
+	let verbose = match args {
+		[] => false,
+		["-v"] => true,
+		_ => {
+			println!("usage: lspci [-v]");
+			return;
+		}
+	};
+
+	for device in pci::devices() {
+		if pager::aborted() {
+			break;
+		}
+		println!(
+			"{} {:04x}:{:04x} {:02x}{:02x} {}",
+			device.address,
+			device.vendor_id,
+			device.device_id,
+			device.class,
+			device.subclass,
+			device.class_name()
+		);
+
+		if !verbose {
+			continue;
+		}
+		for (index, bar) in device.bars.iter().enumerate() {
+			match *bar {
+				Bar::None => {}
+				Bar::Memory {
+					base,
+					size,
+					prefetchable,
+					wide,
+				} => println!(
+					"        bar{} memory at {:#010x} ({} bytes{}{})",
+					index,
+					base,
+					size,
+					if wide { ", 64-bit" } else { "" },
+					if prefetchable { ", prefetchable" } else { "" }
+				),
+				Bar::Io {
+					port,
+					size,
+				} => println!(
+					"        bar{} io ports {:#06x} ({} ports)",
+					index, port, size
+				),
+			}
+		}
+	}
+}
@@ -0,0 +1,154 @@
+use crate::{
+	arch::x86::multiboot::G_SEGMENTS,
+	memory::{
+		allocator::{slab_stats, BUDDY_PAGE_ALLOCATOR},
+		frame::FRAME_ALLOCATOR,
+		kmem::kmem_cache_stats,
+		zone::{zone_range, ZONES, ZONE_DMA},
+		PAGE_SIZE,
+	},
+	print, println,
+};
+
+const ORDERS_PER_LINE: usize = 6;
+
+#[doc(hidden)]
+pub fn meminfo() {
+	print_physical();
+	print_frames();
+	print_zones();
+	print_buddy();
+	print_slabs();
+}
+
+fn print_physical() {
+	let segments = G_SEGMENTS.read().clone();
+
+	let total: usize = segments.iter().map(|s| s.size()).sum();
+	let available: usize =
+		segments.available_segments().map(|s| s.size()).sum();
+
+	println!(
+		"Physical: {:>10} KiB total, {:>10} KiB available",
+		total / 1024,
+		available / 1024
+	);
+
+	for segment in segments.iter() {
+		let start = segment.start_addr().as_usize();
+		println!(
+			"  {:#010x} - {:#010x} {:?}",
+			start,
+			start + (segment.size() - 1),
+			segment.segment_type()
+		);
+	}
+}
+
+fn print_frames() {
+	let stats = match FRAME_ALLOCATOR.lock() {
+		Some(frames) => frames.stats(),
+		None => {
+			println!("Frames:   not initialized");
+			return;
+		}
+	};
+
+	println!(
+		"Frames:   {:>10} total, {:>8} used, {:>8} free ({} KiB)",
+		stats.total_frames,
+		stats.used_frames,
+		stats.free_frames,
+		stats.free_frames * PAGE_SIZE / 1024
+	);
+}
+
+fn print_zones() {
+	let segments = G_SEGMENTS.read().clone();
+	let Some(frames) = FRAME_ALLOCATOR.lock() else {
+		return;
+	};
+
+	for (zone, name) in ZONES {
+		let Some(range) = zone_range(zone) else {
+			continue;
+		};
+
+		let ram: usize = segments
+			.available_segments()
+			.map(|segment| {
+				let start = segment.start_addr().max(range.start);
+				let end =
+					(segment.start_addr() + segment.size()).min(range.end);
+				return end.as_usize().saturating_sub(start.as_usize());
+			})
+			.sum();
+
+		print!(
+			"  zone {:<6} {:>10} KiB RAM, {:>10} KiB free",
+			name,
+			ram / 1024,
+			frames.free_frames_in(range) * PAGE_SIZE / 1024
+		);
+		match zone == ZONE_DMA {
+			true => println!(
+				" ({} KiB held for DMA)",
+				frames.dma_reserve_free() * PAGE_SIZE / 1024
+			),
+			false => println!(),
+		}
+	}
+}
+
+fn print_buddy() {
+	let (stats, check) = match BUDDY_PAGE_ALLOCATOR.lock() {
+		Some(buddy) => (buddy.stats(), buddy.debug_check()),
+		None => {
+			println!("Buddy:    not initialized");
+			return;
+		}
+	};
+
+	println!(
+		"Buddy:    {:>10} KiB total, {:>10} KiB free",
+		stats.total_bytes / 1024,
+		stats.free_bytes / 1024
+	);
+
+	for order in 0..=stats.max_order {
+		if order % ORDERS_PER_LINE == 0 {
+			if order != 0 {
+				println!();
+			}
+			print!("  free blocks:");
+		}
+		print!(" {:>2}:{:<6}", order, stats.free_blocks[order]);
+	}
+	println!();
+
+	match check {
+		Ok(()) => println!("  free lists: ok"),
+		Err(e) => println!("  free lists: CORRUPT, {:?}", e),
+	}
+}
+
+fn print_slabs() {
+	let Some(caches) = slab_stats() else {
+		println!("Slab:     not initialized");
+		return;
+	};
+
+	let kmem_caches = kmem_cache_stats();
+	let all = caches.iter().chain(kmem_caches.iter().flatten());
+
+	let (count, bytes, objects) = all.fold((0, 0, 0), |(n, b, o), cache| {
+		return (n + 1, b + cache.bytes(), o + cache.objects_in_use);
+	});
+
+	println!(
+		"Slab:     {:>10} KiB in {} caches, {} objects in use (see slabinfo)",
+		bytes / 1024,
+		count,
+		objects
+	);
+}
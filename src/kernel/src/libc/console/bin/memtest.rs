@@ -0,0 +1,157 @@
+use crate::{
+	libc::console::parse::parse_usize,
+	println,
+	util::rand::{rand_range, shuffle},
+};
+use alloc::alloc::{alloc, dealloc, Layout};
+
+const DEFAULT_ITERATIONS: usize = 10;
+const MAX_BLOCKS: usize = 64;
+/// Allocations tried per batch, refused ones included.
+const MAX_ATTEMPTS: usize = MAX_BLOCKS * 4;
+const MAX_BLOCK_SIZE: usize = 8192;
+/// Upper bound of the bytes live at once, so the test cannot exhaust the heap.
+const BATCH_BUDGET: usize = 64 * 1024;
+
+#[derive(Clone, Copy)]
+struct Block {
+	ptr: *mut u8,
+	size: usize,
+}
+
+/// Counters of one or more batches.
+#[derive(Default)]
+struct Totals {
+	allocated: usize,
+	bytes: usize,
+	refused: usize,
+	corrupted: usize,
+}
+
+/// Allocates, fills, verifies and frees random sized blocks through the global
+/// allocator.
+pub fn memtest(args: &[&str]) {
+	let iterations = match args {
+		[] => DEFAULT_ITERATIONS,
+		[n] => match parse_usize(n) {
+			Some(n) if n > 0 => n,
+			_ => {
+				println!("memtest: invalid iteration count '{}'", n);
+				return;
+			}
+		},
+		_ => {
+			println!("usage: memtest [iterations]");
+			return;
+		}
+	};
+
+	let mut totals = Totals::default();
+
+	for i in 1..=iterations {
+		let batch = run_batch();
+		println!(
+			"memtest: {}/{}: {} blocks, {} bytes, {} refused, {} corrupted",
+			i,
+			iterations,
+			batch.allocated,
+			batch.bytes,
+			batch.refused,
+			batch.corrupted
+		);
+
+		totals.allocated += batch.allocated;
+		totals.bytes += batch.bytes;
+		totals.refused += batch.refused;
+		totals.corrupted += batch.corrupted;
+	}
+
+	if totals.corrupted > 0 {
+		println!(
+			"memtest: FAIL, {} of {} blocks corrupted",
+			totals.corrupted, totals.allocated
+		);
+	} else {
+		println!(
+			"memtest: PASS, {} blocks ({} bytes) verified, {} refused",
+			totals.allocated, totals.bytes, totals.refused
+		);
+	}
+}
+
+fn run_batch() -> Totals {
+	let mut blocks = [Block {
+		ptr: core::ptr::null_mut(),
+		size: 0,
+	}; MAX_BLOCKS];
+	let mut count = 0;
+	let mut totals = Totals::default();
+
+	for _ in 0..MAX_ATTEMPTS {
+		if count == MAX_BLOCKS {
+			break;
+		}
+
+		let size = rand_range(1, MAX_BLOCK_SIZE);
+		if totals.bytes + size > BATCH_BUDGET {
+			break;
+		}
+
+		let Ok(layout) = Layout::from_size_align(size, 1) else {
+			break;
+		};
+		let ptr = unsafe { alloc(layout) };
+		if ptr.is_null() {
+			totals.refused += 1;
+			continue;
+		}
+
+		fill(ptr, size);
+		blocks[count] = Block {
+			ptr,
+			size,
+		};
+		count += 1;
+		totals.allocated += 1;
+		totals.bytes += size;
+	}
+
+	let blocks = &mut blocks[..count];
+	for block in blocks.iter() {
+		if !verify(block.ptr, block.size) {
+			totals.corrupted += 1;
+		}
+	}
+
+	shuffle(blocks);
+	for block in blocks.iter() {
+		if let Ok(layout) = Layout::from_size_align(block.size, 1) {
+			unsafe { dealloc(block.ptr, layout) };
+		}
+	}
+
+	return totals;
+}
+
+/// The byte expected at `addr`, so overlapping blocks corrupt each other.
+fn pattern(addr: usize) -> u8 {
+	return (addr ^ (addr >> 8) ^ (addr >> 16)) as u8;
+}
+
+fn fill(ptr: *mut u8, size: usize) {
+	for i in 0..size {
+		let byte = unsafe { ptr.add(i) };
+		unsafe { byte.write_volatile(pattern(byte.addr())) };
+	}
+}
+
+fn verify(ptr: *mut u8, size: usize) -> bool {
+	for i in 0..size {
+		let byte = unsafe { ptr.add(i) };
+		if unsafe { byte.read_volatile() } != pattern(byte.addr()) {
+			return false;
+		}
+	}
+
+	return true;
+}
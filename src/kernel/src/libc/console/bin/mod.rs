@@ -1,3 +1,73 @@
+/// Sounds the PC speaker
+pub mod beep;
+/// Lists the registered block devices
+pub mod blkdev;
+/// Dumps a block of a block device
+pub mod blkread;
+/// Returns the free slabs of the slab caches to the buddy allocator
+pub mod cache_shrink;
+/// Prints a file of the initrd or the FAT volume
+pub mod cat;
+/// Prints the kernel command line
+pub mod cmdline;
+/// Prints what CPUID reports about the processor
+pub mod cpuinfo;
+/// Prints the date and time from the RTC
+pub mod date;
+/// Reads and writes sectors of the ATA disk
+pub mod disk;
+/// Replays the kernel log ring buffer
+pub mod dmesg;
+/// Prints its arguments back to the console
+pub mod echo;
 /// Prints the current Entries of the GDT (Should be moved in future)
 pub mod gdt;
+/// Cross checks the bookkeeping of the heap allocators
+pub mod heapcheck;
+/// Dumps a range of virtual memory as hex and ASCII
+pub mod hexdump;
 pub mod idt;
+/// Prints the mask, handler and count of every IRQ line
+pub mod interrupts;
+/// Shows or changes the runtime log level
+pub mod loglevel;
+/// Lists the files of the initrd or the FAT volume
+pub mod ls;
+/// Lists the PCI functions by class
+pub mod lspci;
+/// Prints physical memory and allocator usage
+pub mod meminfo;
+/// Stress tests the global allocator with random sized blocks
+pub mod memtest;
+/// Shows or switches the VGA text mode
+pub mod mode;
+/// Dumps the first bytes of a boot module
+pub mod modhex;
+/// Lists the boot modules with their sizes and command lines
+pub mod modinfo;
+/// Prints the mapped regions of the current address space
+pub mod pagetables;
+/// Reads and writes single words of virtual memory
+pub mod peek;
+/// Changes whether single pages are mapped writable
+pub mod protect;
+/// Lists the processes with their state and stack usage
+pub mod ps;
+/// Runs an ELF executable the bootloader loaded as a module
+pub mod run;
+/// Toggles mirroring the console to the serial port
+pub mod serialmirror;
+/// Prints the usage of every slab cache
+pub mod slabinfo;
+/// Puts threads to sleep for different times and checks when they wake
+pub mod sleeptest;
+/// Names the kernel function an address lies in
+pub mod sym;
+/// Runs two kernel processes whose threads take turns printing
+pub mod threads;
+/// Time since boot and timed waits
+pub mod uptime;
+/// Runs a payload in ring 3 and comes back through int 0x80
+pub mod user;
+/// Shows the counters of the deferred work queue
+pub mod workqueue;
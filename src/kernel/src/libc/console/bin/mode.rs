@@ -0,0 +1,29 @@
+use crate::{
+	println,
+	tty::{output, tty::WRITER, vga, VGA_WIDTH},
+};
+
+/// Shows the text mode, or switches to 80x25 or 80x50 and clears the screen.
+pub fn mode(args: &[&str]) {
+	let switched = match args {
+		[] => {
+			let height = WRITER.lock().height();
+			println!("{}x{}", VGA_WIDTH, height);
+			return;
+		}
+		["80x25"] => vga::set_text_mode_80x25(),
+		["80x50"] => vga::set_text_mode_80x50(),
+		_ => {
+			println!("usage: mode [80x25|80x50]");
+			return;
+		}
+	};
+
+	if !switched {
+		println!("mode: not available on a framebuffer console");
+		return;
+	}
+
+	// The writer is already clear, this resets the other sinks to match.
+	output::clear();
+}
@@ -0,0 +1,37 @@
+use crate::{
+	arch::x86::multiboot::boot_modules,
+	libc::console::{bin::hexdump::dump_slice, parse::parse_usize},
+	println,
+};
+
+const DEFAULT_LEN: usize = 256;
+
+/// Dumps the first `len` bytes (default 256) of a boot module, to check
+/// what the bootloader loaded.
+pub fn modhex(args: &[&str]) {
+	let (index, len) = match args {
+		[index] => (index, Some(DEFAULT_LEN)),
+		[index, len] => (index, parse_usize(len)),
+		_ => {
+			println!("usage: modhex <module-index> [len]");
+			return;
+		}
+	};
+
+	let modules = boot_modules();
+	let Some(module) = parse_usize(index).and_then(|index| modules.get(index))
+	else {
+		println!("modhex: no module '{}', {} loaded", index, modules.len());
+		return;
+	};
+	let Some(len) = len else {
+		println!("modhex: invalid length '{}'", args[1]);
+		return;
+	};
+	let Some(bytes) = module.bytes() else {
+		println!("modhex: module {} is not mapped", index);
+		return;
+	};
+
+	dump_slice(&bytes[..len.min(bytes.len())], 0);
+}
@@ -0,0 +1,23 @@
+use crate::{arch::x86::multiboot::boot_modules, println};
+
+/// Lists the modules the bootloader loaded, where they are in physical
+/// memory, their size and their command line.
+pub fn modinfo() {
+	let modules = boot_modules();
+	if modules.is_empty() {
+		println!("modinfo: no boot modules");
+		return;
+	}
+
+	println!("idx  start      end        size      cmdline");
+	for (index, module) in modules.iter().enumerate() {
+		println!(
+			"{:>3}  {:#010x} {:#010x} {:>8}  {}",
+			index,
+			module.start.as_usize(),
+			module.end.as_usize(),
+			module.len(),
+			module.cmdline
+		);
+	}
+}
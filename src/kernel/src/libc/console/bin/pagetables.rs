@@ -0,0 +1,96 @@
+use crate::{
+	libc::console::parse::parse_usize,
+	memory::{
+		paging::{flags, for_each_mapped_run, page_walk},
+		VirtAddr,
+	},
+	println,
+};
+
+/// Prints the mapped regions of the current address space, or how one
+/// address is translated.
+pub fn pagetables(args: &[&str]) {
+	match args {
+		[] => print_runs(),
+		[addr] => match parse_usize(addr) {
+			Some(addr) => print_walk(VirtAddr::new(addr)),
+			None => println!("pagetables: invalid address '{}'", addr),
+		},
+		_ => println!("usage: pagetables [addr]"),
+	}
+}
+
+fn print_runs() {
+	println!("virtual                   physical       size  flags");
+
+	let mut runs = 0;
+	for_each_mapped_run(|run| {
+		let last = run.virt.as_usize() + (run.size - 1);
+		println!(
+			"{:#010x}-{:#010x} -> {:#010x} {:>7} KiB  {}",
+			run.virt.as_usize(),
+			last,
+			run.phys.as_usize(),
+			run.size / 1024,
+			Flags(run.flags)
+		);
+		runs += 1;
+	});
+	println!("{} runs", runs);
+}
+
+fn print_walk(virt: VirtAddr) {
+	let walk = page_walk(virt);
+
+	println!(
+		"PDE[{}] = {:#010x}  {}",
+		walk.pde_index,
+		walk.pde,
+		Flags(walk.pde)
+	);
+	if walk.pde & flags::PRESENT == 0 {
+		println!("{:#010x} is not mapped", virt.as_usize());
+		return;
+	}
+
+	let Some(pte) = walk.pte else {
+		let phys =
+			(walk.pde & 0xffc0_0000) as usize | (virt.as_usize() & 0x3f_ffff);
+		println!("{:#010x} -> {:#010x} (4MiB page)", virt.as_usize(), phys);
+		return;
+	};
+
+	println!("PTE[{}] = {:#010x}  {}", walk.pte_index, pte, Flags(pte));
+	if pte & flags::PRESENT == 0 {
+		println!("{:#010x} is not mapped", virt.as_usize());
+		return;
+	}
+
+	let phys = (pte & 0xffff_f000) as usize | (virt.as_usize() & 0xfff);
+	println!("{:#010x} -> {:#010x}", virt.as_usize(), phys);
+}
+
+/// Formats the P, W, U and PS flag bits of an entry, `-` for each clear one.
+struct Flags(u32);
+
+impl core::fmt::Display for Flags {
+	fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+		let bits = [
+			(flags::PRESENT, "P"),
+			(flags::WRITABLE, "W"),
+			(flags::USER_ACCESSIBLE, "U"),
+			(flags::PAGE_SIZE_EXT, "PS"),
+		];
+
+		for (i, (bit, name)) in bits.iter().enumerate() {
+			if i > 0 {
+				f.write_str(" ")?;
+			}
+			match self.0 & bit {
+				0 => f.write_str(&"--"[..name.len()])?,
+				_ => f.write_str(name)?,
+			}
+		}
+		return Ok(());
+	}
+}
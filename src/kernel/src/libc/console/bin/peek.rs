@@ -0,0 +1,80 @@
+use crate::{
+	libc::console::parse::parse_usize,
+	memory::{
+		paging::{flags, page_flags},
+		VirtAddr,
+	},
+	println,
+};
+use core::{mem::align_of, ptr};
+
+/// Reads and prints the 32-bit value at a mapped, aligned virtual address.
+pub fn peek(args: &[&str]) {
+	let [addr] = args else {
+		println!("usage: peek <addr>");
+		return;
+	};
+
+	let Some(addr) = parse_addr("peek", addr) else {
+		return;
+	};
+	if page_flags(VirtAddr::new(addr)).is_none() {
+		println!("peek: {:#010x} is not mapped", addr);
+		return;
+	}
+
+	let value =
+		unsafe { ptr::with_exposed_provenance::<u32>(addr).read_volatile() };
+	println!("{:#010x}: {:#010x} ({})", addr, value, value);
+}
+
+/// Writes a 32-bit value to a mapped, writable, aligned virtual address and
+/// prints what reads back.
+pub fn poke(args: &[&str]) {
+	let [addr, value] = args else {
+		println!("usage: poke <addr> <value>");
+		return;
+	};
+
+	let Some(addr) = parse_addr("poke", addr) else {
+		return;
+	};
+	let Some(value) = parse_usize(value).and_then(|v| u32::try_from(v).ok())
+	else {
+		println!("poke: invalid 32-bit value '{}'", value);
+		return;
+	};
+
+	match page_flags(VirtAddr::new(addr)) {
+		None => {
+			println!("poke: {:#010x} is not mapped", addr);
+			return;
+		}
+		Some(f) if f & flags::WRITABLE == 0 => {
+			println!("poke: {:#010x} is read-only", addr);
+			return;
+		}
+		Some(_) => {}
+	}
+
+	let ptr = ptr::with_exposed_provenance_mut::<u32>(addr);
+	let read_back = unsafe {
+		ptr.write_volatile(value);
+		ptr.read_volatile()
+	};
+	println!("{:#010x}: {:#010x} ({})", addr, read_back, read_back);
+}
+
+fn parse_addr(cmd: &str, arg: &str) -> Option<usize> {
+	let Some(addr) = parse_usize(arg) else {
+		println!("{}: invalid address '{}'", cmd, arg);
+		return None;
+	};
+
+	if addr % align_of::<u32>() != 0 {
+		println!("{}: {:#010x} is not 4-byte aligned", cmd, addr);
+		return None;
+	}
+
+	return Some(addr);
+}
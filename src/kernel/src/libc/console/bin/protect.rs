@@ -0,0 +1,55 @@
+use crate::{
+	libc::console::parse::parse_usize,
+	memory::{
+		paging::{flags, page_flags, protect_page, ProtectError},
+		VirtAddr, PAGE_SIZE,
+	},
+	println,
+};
+
+/// Maps the page containing a virtual address read-only.
+pub fn ro(args: &[&str]) {
+	set_writable("ro", args, false);
+}
+
+/// Maps the page containing a virtual address writable.
+pub fn rw(args: &[&str]) {
+	set_writable("rw", args, true);
+}
+
+fn set_writable(cmd: &str, args: &[&str], writable: bool) {
+	let [arg] = args else {
+		println!("usage: {} <addr>", cmd);
+		return;
+	};
+
+	let Some(addr) = parse_usize(arg) else {
+		println!("{}: invalid address '{}'", cmd, arg);
+		return;
+	};
+	let page = VirtAddr::new(addr).align_down(PAGE_SIZE);
+
+	let Some(current) = page_flags(page) else {
+		println!("{}: {:#010x} is not mapped", cmd, page.as_usize());
+		return;
+	};
+	let new = match writable {
+		true => current | flags::WRITABLE,
+		false => current & !flags::WRITABLE,
+	};
+
+	match protect_page(page, new) {
+		Ok(()) if writable => {
+			println!("{}: {:#010x} is writable", cmd, page.as_usize())
+		}
+		Ok(()) => println!("{}: {:#010x} is read-only", cmd, page.as_usize()),
+		Err(ProtectError::NotMapped(_)) => {
+			println!("{}: {:#010x} is not mapped", cmd, page.as_usize())
+		}
+		Err(ProtectError::LargePage(_)) => println!(
+			"{}: {:#010x} is part of a 4MiB page",
+			cmd,
+			page.as_usize()
+		),
+	}
+}
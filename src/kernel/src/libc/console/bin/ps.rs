@@ -0,0 +1,23 @@
+use crate::{
+	println,
+	task::{process, thread::STACK_SIZE},
+};
+
+/// Prints the pid, state, stack usage and name of every process.
+pub fn ps() {
+	println!("{:>5} {:<8} {:>11} {}", "PID", "STATE", "STACK", "NAME");
+	for process in process::list() {
+		let state = process.state.name();
+		match process.stack_used {
+			Some(used) => println!(
+				"{:>5} {:<8} {:>5}/{:<5} {}",
+				process.pid, state, used, STACK_SIZE, process.name
+			),
+			None => println!(
+				"{:>5} {:<8} {:>11} {}",
+				process.pid, state, "-", process.name
+			),
+		}
+	}
+	println!("{} processes", process::count());
+}
@@ -0,0 +1,96 @@
+use crate::{
+	arch::x86::multiboot::{boot_modules, Module},
+	fs::ramfs,
+	libc::console::parse::parse_usize,
+	println,
+	task::{
+		elf,
+		process::{self, Pid},
+		thread,
+	},
+};
+
+/// Lists the boot modules without an argument. With a module index or the
+/// path of an initrd file, loads it as an ELF executable, runs it in a
+/// process of its own and waits for it to exit.
+pub fn run(args: &[&str]) {
+	let modules = boot_modules();
+
+	let target = match args {
+		[] => {
+			list(modules);
+			return;
+		}
+		[target] => *target,
+		_ => {
+			println!("usage: run [module-index|path]");
+			return;
+		}
+	};
+	let (bytes, path) = match parse_usize(target) {
+		Some(index) => {
+			let Some(module) = modules.get(index) else {
+				println!(
+					"run: no module '{}', {} loaded",
+					target,
+					modules.len()
+				);
+				return;
+			};
+			let Some(bytes) = module.bytes() else {
+				println!("run: module {} is not mapped", target);
+				return;
+			};
+			(bytes, module.cmdline)
+		}
+		None => {
+			let Some(bytes) = ramfs::open(target) else {
+				println!("run: no file '{}'", target);
+				return;
+			};
+			(bytes, target)
+		}
+	};
+
+	let image = match elf::load(bytes) {
+		Ok(image) => image,
+		Err(error) => {
+			println!("run: {}: {}", target, error);
+			return;
+		}
+	};
+	match process::create_user_process(name_of(path), image) {
+		Ok(pid) => wait(pid),
+		Err(error) => println!("run: {}", error),
+	}
+}
+
+// Helper printing the index, size and command line of every module
+fn list(modules: &[Module]) {
+	if modules.is_empty() {
+		println!("run: no boot modules");
+		return;
+	}
+
+	for (index, module) in modules.iter().enumerate() {
+		println!("{:>3} {:>8} bytes  {}", index, module.len(), module.cmdline);
+	}
+}
+
+// Helper naming the process after the last part of the path, a module's
+// command line starting with it
+fn name_of(path: &str) -> &str {
+	let path = path.split(' ').next().unwrap_or("");
+
+	match path.rsplit('/').next() {
+		Some(name) if !name.is_empty() => return name,
+		_ => return "module",
+	}
+}
+
+// Helper waiting for the main thread of `pid` to exit
+fn wait(pid: Pid) {
+	if let Some(id) = process::main_thread(pid) {
+		thread::join(id);
+	}
+}
@@ -0,0 +1,17 @@
+use crate::{
+	libc::console::{mirror_serial, set_mirror_serial},
+	println,
+};
+
+/// Shows or toggles mirroring of the console output to the serial port.
+pub fn serialmirror(args: &[&str]) {
+	match args {
+		[] => {
+			let state = if mirror_serial() { "on" } else { "off" };
+			println!("serial mirror: {}", state);
+		}
+		["on"] => set_mirror_serial(true),
+		["off"] => set_mirror_serial(false),
+		_ => println!("usage: serialmirror [on|off]"),
+	}
+}
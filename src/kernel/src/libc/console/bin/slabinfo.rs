@@ -0,0 +1,32 @@
+use crate::{
+	memory::{allocator::slab_stats, kmem::kmem_cache_stats},
+	println,
+};
+
+/// Prints one row per slab cache, the size classes first, then the caches
+/// made with `kmem_cache_create`.
+pub fn slabinfo() {
+	let Some(caches) = slab_stats() else {
+		println!("slabinfo: slab caches not initialized");
+		return;
+	};
+	let kmem_caches = kmem_cache_stats();
+
+	println!(
+		"{:<14} {:>6} {:>6} {:>6} {:>6} {:>6} {:>8} {:>10}",
+		"# name", "objsz", "perslb", "full", "part", "free", "active", "pinned"
+	);
+	for cache in caches.iter().chain(kmem_caches.iter().flatten()) {
+		println!(
+			"{:<14} {:>6} {:>6} {:>6} {:>6} {:>6} {:>8} {:>10}",
+			cache.name,
+			cache.object_size,
+			cache.objects_per_slab,
+			cache.slabs_full,
+			cache.slabs_partial,
+			cache.slabs_free,
+			cache.objects_in_use,
+			cache.bytes()
+		);
+	}
+}
@@ -0,0 +1,92 @@
+use crate::{
+	println,
+	task::{scheduler, thread},
+	time,
+};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+/// How long each sleeper sleeps, in the order they are spawned.
+const SLEEPS_MS: [u64; 3] = [300, 100, 200];
+
+/// Sleepers that woke so far.
+static WOKEN: AtomicUsize = AtomicUsize::new(0);
+
+/// The place each sleeper woke in.
+static WAKE_ORDER: [AtomicUsize; 3] = [const { AtomicUsize::new(0) }; 3];
+
+// Helper sleeping for sleeper `index` and telling when it woke
+fn sleeper(index: usize) {
+	let start = time::uptime_ms();
+	time::sleep_ms(SLEEPS_MS[index]);
+	let woke = time::uptime_ms();
+
+	WAKE_ORDER[index]
+		.store(WOKEN.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+	println!(
+		"sleeper {} (id {}): asked for {} ms, slept {} ms, woke at {} ms",
+		index,
+		thread::current(),
+		SLEEPS_MS[index],
+		woke - start,
+		woke
+	);
+}
+
+fn sleeper_0() {
+	sleeper(0);
+}
+
+fn sleeper_1() {
+	sleeper(1);
+}
+
+fn sleeper_2() {
+	sleeper(2);
+}
+
+/// Spawns threads sleeping different times, checks they wake shortest first
+/// and tells how much of the time the idle thread had the CPU.
+pub fn sleeptest() {
+	if time::tick_rate() == 0 {
+		println!("sleeptest: no timer running");
+		return;
+	}
+
+	WOKEN.store(0, Ordering::Relaxed);
+	let start_ticks = time::ticks();
+	let start_idle = scheduler::idle_ticks();
+
+	let mut ids = [None; 3];
+	for (id, entry) in ids.iter_mut().zip([sleeper_0, sleeper_1, sleeper_2]) {
+		match thread::spawn(entry) {
+			Ok(spawned) => *id = Some(spawned),
+			Err(error) => println!("sleeptest: {}", error),
+		}
+	}
+	for id in ids.into_iter().flatten() {
+		thread::join(id);
+	}
+
+	let mut in_order = true;
+	for (a, sleep_a) in SLEEPS_MS.iter().enumerate() {
+		for (b, sleep_b) in SLEEPS_MS.iter().enumerate() {
+			let before = WAKE_ORDER[a].load(Ordering::Relaxed)
+				< WAKE_ORDER[b].load(Ordering::Relaxed);
+			if sleep_a < sleep_b && !before {
+				in_order = false;
+			}
+		}
+	}
+
+	let ticks = time::ticks() - start_ticks;
+	let idle = scheduler::idle_ticks().wrapping_sub(start_idle);
+	println!(
+		"sleeptest: woke {}, idle for {} of {} ticks",
+		match in_order {
+			true => "in order",
+			false => "OUT OF ORDER",
+		},
+		idle,
+		ticks
+	);
+}
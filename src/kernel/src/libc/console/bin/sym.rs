@@ -0,0 +1,28 @@
+use crate::{
+	libc::console::parse::parse_usize,
+	memory::VirtAddr,
+	println,
+	symbols::{self, Symbolized},
+};
+
+/// Prints the kernel function an address lies in.
+pub fn sym(args: &[&str]) {
+	let [addr] = args else {
+		println!("usage: sym <addr>");
+		return;
+	};
+
+	let Some(addr) = parse_usize(addr) else {
+		println!("sym: invalid address '{}'", addr);
+		return;
+	};
+
+	if symbols::count() == 0 {
+		println!("sym: the kernel was built without a symbol table");
+		return;
+	}
+	match symbols::resolve(VirtAddr::new(addr)) {
+		Some(_) => println!("{}", Symbolized(VirtAddr::new(addr))),
+		None => println!("sym: no symbol for {:#010x}", addr),
+	}
+}
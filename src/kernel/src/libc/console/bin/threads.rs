@@ -0,0 +1,57 @@
+use crate::{
+	println,
+	task::{
+		self, process,
+		thread::{self, ThreadId},
+	},
+};
+
+/// Lines each demo thread prints.
+const ROUNDS: usize = 3;
+
+// Helper printing a line per round, yielding to the other thread after each
+fn take_turns(name: char) {
+	for round in 0..ROUNDS {
+		println!(
+			"thread {} (id {}): round {}",
+			name,
+			thread::current(),
+			round
+		);
+		task::yield_now();
+	}
+}
+
+fn thread_a() {
+	take_turns('A');
+}
+
+fn thread_b() {
+	take_turns('B');
+}
+
+// Helper creating a process running `entry`, returning its main thread
+fn start(name: &str, entry: fn()) -> Option<ThreadId> {
+	match process::create_kernel_process(name, entry) {
+		Ok(pid) => return process::main_thread(pid),
+		Err(error) => {
+			println!("threads: {}", error);
+			return None;
+		}
+	}
+}
+
+/// Creates two processes whose threads take turns printing, and waits for
+/// both to exit.
+pub fn threads() {
+	let Some(a) = start("thread_a", thread_a) else {
+		return;
+	};
+	let b = start("thread_b", thread_b);
+
+	thread::join(a);
+	if let Some(b) = b {
+		thread::join(b);
+	}
+	println!("threads: done, {} thread left", thread::count());
+}
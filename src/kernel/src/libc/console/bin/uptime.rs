@@ -0,0 +1,46 @@
+use crate::{
+	arch::x86::cpu::halt,
+	device::keyboard::{KeyEvent, KEYBOARD},
+	libc::console::parse::parse_usize,
+	println, time,
+};
+
+/// Prints the time since the timer started.
+pub fn uptime() {
+	let ms = time::uptime_ms();
+
+	println!(
+		"up {}.{:03} seconds ({} ticks)",
+		ms / 1000,
+		ms % 1000,
+		time::ticks()
+	);
+}
+
+/// Waits for the given number of milliseconds, or until Ctrl+C is pressed.
+pub fn sleep(args: &[&str]) {
+	let [ms] = args else {
+		println!("usage: sleep <ms>");
+		return;
+	};
+	let Some(ms) = parse_usize(ms) else {
+		println!("sleep: invalid duration '{}'", ms);
+		return;
+	};
+
+	if time::tick_rate() == 0 {
+		println!("sleep: no timer running");
+		return;
+	}
+
+	let deadline = time::uptime_ms().saturating_add(ms as u64);
+	while time::uptime_ms() < deadline {
+		if KEYBOARD.lock().input() == Some(KeyEvent::Char('\x03')) {
+			println!("^C");
+			return;
+		}
+
+		// The timer interrupt wakes us up at least once per tick.
+		halt();
+	}
+}
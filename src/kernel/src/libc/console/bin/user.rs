@@ -0,0 +1,16 @@
+use crate::{
+	arch::x86::usermode::{self, DEMO_VALUE},
+	println,
+};
+
+/// Runs the ring 3 demo payload and tells whether it came back with the
+/// value it should.
+pub fn user() {
+	match usermode::demo() {
+		Ok(DEMO_VALUE) => println!("user: back in ring 0"),
+		Ok(value) => {
+			println!("user: back with {:#x}, expected {:#x}", value, DEMO_VALUE)
+		}
+		Err(_) => println!("user: out of memory for the user pages"),
+	}
+}
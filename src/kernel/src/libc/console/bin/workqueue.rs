@@ -0,0 +1,15 @@
+use crate::{println, task::workqueue};
+
+/// Prints what happened to the work interrupt handlers deferred.
+pub fn workqueue() {
+	let stats = workqueue::stats();
+
+	match workqueue::worker_id() {
+		Some(id) => println!("worker:    thread {}", id),
+		None => println!("worker:    not running"),
+	}
+	println!("scheduled: {}", stats.scheduled);
+	println!("executed:  {}", stats.executed);
+	println!("dropped:   {} (queue full)", stats.dropped);
+	println!("pending:   {} of {}", stats.pending, workqueue::QUEUE_SIZE);
+}
@@ -0,0 +1,85 @@
+//! Registry of the commands the console can run.
+//!
+//! The builtins live in a constant table in `console.rs`. Other subsystems add
+//! their own with [`register_command`] during init, without touching the
+//! console. Registered commands are kept in a fixed-size table behind a
+//! `Mutex`, and are copied out before running so a command may register
+//! others.
+
+use super::console::Console;
+use crate::sync::Mutex;
+
+/// Maximum number of commands that can be added with [`register_command`].
+pub const MAX_REGISTERED_COMMANDS: usize = 32;
+
+/// A console command.
+#[derive(Clone, Copy)]
+pub struct Command {
+	/// Name the command is invoked by.
+	pub name: &'static str,
+	/// Usage line shown by `help`, e.g. `hexdump <addr> [len]`.
+	pub usage: &'static str,
+	/// One line description shown by `help`.
+	pub help: &'static str,
+	/// Runs the command with its arguments, excluding the name.
+	pub run: fn(&mut Console, &[&str]),
+}
+
+/// Reasons [`register_command`] can fail.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterError {
+	/// A command with the same name already exists.
+	AlreadyExists,
+	/// All [`MAX_REGISTERED_COMMANDS`] slots are in use.
+	RegistryFull,
+}
+
+static REGISTRY: Mutex<[Option<Command>; MAX_REGISTERED_COMMANDS]> =
+	Mutex::new([None; MAX_REGISTERED_COMMANDS]);
+
+/// Makes `command` available in every console.
+pub fn register_command(command: Command) -> Result<(), RegisterError> {
+	if builtin(command.name).is_some() {
+		return Err(RegisterError::AlreadyExists);
+	}
+
+	let mut registry = REGISTRY.lock();
+
+	if registry.iter().flatten().any(|c| c.name == command.name) {
+		return Err(RegisterError::AlreadyExists);
+	}
+
+	match registry.iter_mut().find(|slot| slot.is_none()) {
+		Some(slot) => {
+			*slot = Some(command);
+			return Ok(());
+		}
+		None => return Err(RegisterError::RegistryFull),
+	}
+}
+
+/// Looks up a builtin or registered command by name.
+pub fn find_command(name: &str) -> Option<Command> {
+	if let Some(command) = builtin(name) {
+		return Some(command);
+	}
+
+	return REGISTRY
+		.lock()
+		.iter()
+		.flatten()
+		.find(|c| c.name == name)
+		.copied();
+}
+
+/// Calls `f` for every builtin, then every registered command.
+pub fn for_each_command(mut f: impl FnMut(&Command)) {
+	Console::BUILTINS.iter().for_each(&mut f);
+
+	let registry = *REGISTRY.lock();
+	registry.iter().flatten().for_each(f);
+}
+
+fn builtin(name: &str) -> Option<Command> {
+	return Console::BUILTINS.iter().find(|c| c.name == name).copied();
+}
@@ -1,16 +1,33 @@
 use crate::{
 	arch::x86::cpu::reboot,
-	libc::console::bin::{gdt, idt},
+	device::keyboard::{KeyEvent, KeyboardKey, KEYBOARD},
+	libc::console::{
+		bin::{
+			beep, blkdev, blkread, cache_shrink, cat, cmdline, cpuinfo, date,
+			disk, dmesg, echo, gdt, heapcheck, hexdump, idt, interrupts,
+			loglevel, ls, lspci, meminfo, memtest, mode, modhex, modinfo,
+			pagetables, peek, protect, ps, run, serialmirror, slabinfo,
+			sleeptest, sym, threads, uptime, user, workqueue,
+		},
+		command::{find_command, for_each_command, Command},
+		line::LineBuffer,
+		serial_input::SerialInput,
+	},
 	print, print_serial, println, set_fg_color,
-	tty::{tty::WRITER, VGA_HEIGHT},
+	sync::Mutex,
+	tty::{output, pager, serial::SERIAL, tty::WRITER, VGA_WIDTH},
 };
-use core::str::from_utf8;
+use core::{mem, str::from_utf8};
+
+/// Maximum number of arguments passed to a command, excluding its name.
+const MAX_ARGS: usize = 16;
 
 #[doc(hidden)]
 pub struct Console {
-	b_pos: usize,
-	buffer: [u8; 256],
+	cursor: usize,
+	buffer: LineBuffer,
 	prompt: &'static str,
+	prompt_col: usize,
 }
 
 impl Default for Console {
@@ -25,19 +42,45 @@ impl Default for Console {
 	/// Returns a new `Console` instance ready to accept input.
 	/// ```
 	fn default() -> Self {
-		let console = Console {
-			b_pos: 0,
-			buffer: [0; 256],
+		let mut console = Console {
+			cursor: 0,
+			buffer: LineBuffer::new(),
 			prompt: "[42]$ ",
+			prompt_col: 0,
 		};
 		set_fg_color!(VgaColour::White);
 
-		print!("{}", console.prompt);
+		console.print_prompt();
 		return console;
 	}
 }
 
 impl Console {
+	/// Processes a single key press for the shell.
+	///
+	/// Characters are forwarded to [`Console::add_buffer`], while the cursor
+	/// block keys edit the line in place:
+	/// - Left/Right move the cursor by one character
+	/// - Home/End jump to the start or the end of the line
+	/// - Delete removes the character under the cursor
+	pub fn handle_key(&mut self, key: KeyEvent) {
+		match key {
+			KeyEvent::Char(c) => self.add_buffer(c),
+			KeyEvent::Special(KeyboardKey::KeyCursorLeft) => {
+				self.move_cursor(self.cursor.saturating_sub(1));
+			}
+			KeyEvent::Special(KeyboardKey::KeyCursorRight) => {
+				self.move_cursor((self.cursor + 1).min(self.buffer.len()));
+			}
+			KeyEvent::Special(KeyboardKey::KeyHome) => self.move_cursor(0),
+			KeyEvent::Special(KeyboardKey::KeyEnd) => {
+				self.move_cursor(self.buffer.len())
+			}
+			KeyEvent::Special(KeyboardKey::KeyDelete) => self.delete(),
+			KeyEvent::Special(_) => {}
+		}
+	}
+
 	/// Processes a single character of input for the shell.
 	///
 	/// This function handles all input to the shell, managing special
@@ -50,18 +93,30 @@ impl Console {
 	///   - A printable character (stored in buffer and displayed)
 	///   - Newline ('\n') to execute the current command
 	///   - Backspace ('\x08') to delete the last character
+	///   - Ctrl+C ('\x03') to abandon the line
+	///   - Ctrl+U ('\x15') to erase the line
+	///   - Ctrl+L ('\x0c') to clear the screen, keeping the line
 	///
 	/// # Behavior
-	/// - Regular characters are stored in the buffer and displayed
+	/// - Regular characters are inserted at the cursor and displayed
 	/// - Newline triggers command execution
-	/// - Backspace removes the last character
-	/// - Buffer overflow and invalid characters are ignored
+	/// - Backspace removes the character before the cursor
+	/// - Ctrl+C prints `^C` and starts over on a fresh prompt
+	/// - Characters past the longest line and other control characters are
+	///   ignored
 	///
 	/// # Implementation Details
-	/// The function maintains a buffer position (b_pos) that:
-	/// - Increases with each added character
-	/// - Is bounded by the buffer size (256 bytes)
-	/// - Is adjusted when backspace is used
+	/// The line is a [`LineBuffer`] that:
+	/// - Grows with each added character
+	/// - Stays inline up to 256 bytes and moves to the heap beyond that, up to
+	///   [`MAX_LINE`] bytes
+	/// - Is freed again once the line is executed or abandoned
+	///
+	/// The cursor is an index into the buffer between 0 and its length.
+	/// Inserting in the middle of the line shifts the tail right and redraws
+	/// it.
+	///
+	/// [`MAX_LINE`]: crate::libc::console::line::MAX_LINE
 	///
 	/// # Example
 	/// ```
@@ -73,60 +128,515 @@ impl Console {
 		match c {
 			'\n' => self.execute(),
 			'\x08' => self.backspace(),
-			c if self.b_pos < self.buffer.len() - 1 => {
-				self.buffer[self.b_pos] = c as u8;
-				self.b_pos += 1;
-				print!("{}", c);
+			'\x03' => self.cancel_line(),
+			'\x15' => self.erase_line(),
+			'\x0c' => {
+				self.clear_screen();
+				self.redraw_line();
 			}
-			_ => {} // Buffer full or invalid character
+			c if c.is_ascii_graphic() || c == ' ' => self.insert(c as u8),
+			_ => {} // Invalid character
 		}
 	}
 
+	/// Abandons the current line like a shell does on Ctrl+C.
+	fn cancel_line(&mut self) {
+		self.move_cursor(self.buffer.len());
+		println!("^C");
+
+		self.reset_buffer();
+		self.new_prompt();
+	}
+
+	/// Erases the current line, leaving the cursor right after the prompt.
+	fn erase_line(&mut self) {
+		self.move_cursor(0);
+
+		let erase = self.buffer.len();
+		self.reset_buffer();
+		self.redraw_from(0, erase);
+	}
+
+	/// Prints the prompt and the whole buffer at the writer position, leaving
+	/// the writer at the cursor.
+	fn redraw_line(&mut self) {
+		self.print_prompt();
+		self.redraw_from(0, 0);
+	}
+
+	fn reset_buffer(&mut self) {
+		self.buffer.clear();
+		self.cursor = 0;
+	}
+
+	fn insert(&mut self, byte: u8) {
+		let at = self.cursor;
+		if !self.buffer.insert(at, byte) {
+			return;
+		}
+		self.cursor += 1;
+
+		self.redraw_from(at, 0);
+	}
+
+	/// Deletes the character before the cursor. Index 0 is the first cell
+	/// after the prompt, so the prompt itself can never be erased.
 	fn backspace(&mut self) {
-		if self.b_pos == 0 {
+		if self.cursor == 0 {
+			return;
+		}
+
+		self.move_cursor(self.cursor - 1);
+		self.delete();
+	}
+
+	fn delete(&mut self) {
+		let at = self.cursor;
+		if self.buffer.remove(at).is_none() {
 			return;
 		}
 
-		self.buffer[self.b_pos] = 0;
-		self.b_pos -= 1;
-		WRITER.lock().clear_char();
+		self.redraw_from(at, 1);
+	}
+
+	/// Reprints the line from index `from` (where the writer currently is) to
+	/// the end, blanks `erase` trailing cells left over from a deletion, and
+	/// moves the writer back to the cursor.
+	fn redraw_from(&self, from: usize, erase: usize) {
+		if let Ok(tail) = from_utf8(&self.buffer.as_bytes()[from..]) {
+			print!("{}", tail);
+		}
+
+		for _ in 0..erase {
+			print!(" ");
+		}
+
+		self.sync_writer(self.buffer.len() + erase, self.cursor);
+	}
+
+	fn move_cursor(&mut self, to: usize) {
+		self.sync_writer(self.cursor, to);
+		self.cursor = to;
+	}
+
+	/// Moves the writer from the screen cell of buffer index `from` to the
+	/// cell of index `to`. Index `i` lives at offset `prompt_col + i` from the
+	/// start of the row the prompt was printed on.
+	fn sync_writer(&self, from: usize, to: usize) {
+		if from == to {
+			return;
+		}
+
+		let (position, height) = {
+			let writer = WRITER.lock();
+			(writer.position(), writer.usable_height())
+		};
+		let (col, row) =
+			Self::screen_position(self.prompt_col, position, height, from, to);
+
+		output::cursor_to(col, row);
+	}
+
+	/// Computes where the writer has to go to reach buffer index `to`, given
+	/// that it currently sits at `writer` (column, row) on index `from` of a
+	/// screen `height` rows tall.
+	///
+	/// Positions past the bottom row are clamped to the pending-wrap cell
+	/// after the last column, so the next byte scrolls as usual.
+	#[doc(hidden)]
+	pub fn screen_position(
+		prompt_col: usize,
+		writer: (usize, usize),
+		height: usize,
+		from: usize,
+		to: usize,
+	) -> (usize, usize) {
+		let (col, mut row) = writer;
+
+		// The writer sits past the last column until the next byte wraps.
+		if col >= VGA_WIDTH {
+			row += 1;
+		}
+
+		let from_row = (prompt_col + from) / VGA_WIDTH;
+		let to_row = (prompt_col + to) / VGA_WIDTH;
+		let row = (row + to_row).saturating_sub(from_row);
+		let col = (prompt_col + to) % VGA_WIDTH;
+
+		match row {
+			row if row < height => return (col, row),
+			_ => return (VGA_WIDTH, height - 1),
+		}
+	}
+
+	fn print_prompt(&mut self) {
+		print!("{}", self.prompt);
+		self.prompt_col = WRITER.lock().position().0 % VGA_WIDTH;
 	}
 
 	fn execute(&mut self) {
+		self.move_cursor(self.buffer.len());
 		println!();
 
-		match from_utf8(&self.buffer[..self.b_pos]) {
-			Ok(cmd) => match cmd.trim() {
-				"reboot" => reboot(),
-				"gdt" => gdt::print_gdt(),
-				"clear" => self.clear_screen(),
-				"help" => self.print_help(),
-				"panic" => panic!("Test panic"),
-				"idt" => idt::print_idt(),
-				"" => {}
-				_ => println!("{}: command not found", cmd.trim()),
-			},
+		// Commands get `&mut self`, so they run on the line taken out of it.
+		// Dropping it afterwards frees the heap if a long line spilled over.
+		let line = mem::take(&mut self.buffer);
+		self.cursor = 0;
+
+		// Output longer than a screen waits for a key press every page.
+		pager::begin(read_char);
+		match from_utf8(line.as_bytes()) {
+			Ok(line) => self.run(line),
 			Err(_) => println!("Invalid UTF-8 sequence"),
 		}
+		pager::end();
 
-		self.buffer = [0; 256];
-		self.b_pos = 0;
+		self.new_prompt();
+	}
 
-		WRITER.lock().set_position(0, VGA_HEIGHT - 1);
-		WRITER.lock().clear_line();
-		print!("{}", self.prompt);
+	/// Prints the prompt at the start of a row, after output that did not
+	/// end with a newline.
+	fn new_prompt(&mut self) {
+		if WRITER.lock().position().0 != 0 {
+			println!();
+		}
+		self.print_prompt();
+	}
+
+	/// Splits `line` on whitespace into a command name and its arguments and
+	/// dispatches it. The arguments are borrowed from the line, so no heap is
+	/// needed.
+	fn run(&mut self, line: &str) {
+		let mut words = line.split_whitespace();
+		let name = match words.next() {
+			Some(name) => name,
+			None => return,
+		};
+
+		let mut argv = [""; MAX_ARGS];
+		let mut argc = 0;
+		for word in words {
+			if argc == MAX_ARGS {
+				println!("{}: too many arguments (max {})", name, MAX_ARGS);
+				return;
+			}
+
+			argv[argc] = word;
+			argc += 1;
+		}
+		let args = &argv[..argc];
+
+		match find_command(name) {
+			Some(command) => (command.run)(self, args),
+			None => println!("{}: command not found", name),
+		}
 	}
 
 	#[inline]
 	fn clear_screen(&mut self) {
-		WRITER.lock().clear_screen();
+		output::clear();
 	}
 
 	fn print_help(&self) {
 		println!("Available commands:");
-		println!("  reboot  - Restart the system");
-		println!("  gdt     - Print Global Descriptor Table");
-		println!("  clear   - Clear the screen");
-		println!("  help    - Show this help message");
+		for_each_command(|command| {
+			println!("  {:<22} - {}", command.usage, command.help);
+		});
+	}
+}
+
+/// Decodes the serial bytes [`read_char`] reads, kept between calls so a key
+/// split across them is still recognised.
+static PAGER_SERIAL_INPUT: Mutex<SerialInput> = Mutex::new(SerialInput::new());
+
+/// Blocks until a character key is pressed on the keyboard or the serial
+/// port, for answering the pager.
+fn read_char() -> char {
+	let mut serial_input = PAGER_SERIAL_INPUT.lock();
+
+	loop {
+		if let Some(KeyEvent::Char(c)) = KEYBOARD.lock().input() {
+			return c;
+		}
+
+		let serial_key = SERIAL
+			.lock()
+			.read_byte()
+			.and_then(|byte| serial_input.feed(byte));
+		if let Some(KeyEvent::Char(c)) = serial_key {
+			return c;
+		}
 	}
 }
+
+impl Console {
+	/// Commands every console has, see [`register_command`] for adding more.
+	///
+	/// [`register_command`]: crate::libc::console::command::register_command
+	pub const BUILTINS: [Command; 44] = [
+		Command {
+			name: "beep",
+			usage: "beep [freq] [ms]",
+			help: "Sound the PC speaker, 880 Hz for 200 ms by default",
+			run: |_, args| beep::beep(args),
+		},
+		Command {
+			name: "blkdev",
+			usage: "blkdev",
+			help: "List the block devices",
+			run: |_, _| blkdev::blkdev(),
+		},
+		Command {
+			name: "blkread",
+			usage: "blkread <device> <lba>",
+			help: "Hexdump a block of a block device",
+			run: |_, args| blkread::blkread(args),
+		},
+		Command {
+			name: "cache_shrink",
+			usage: "cache_shrink",
+			help: "Free the unused slabs of the slab caches",
+			run: |_, _| cache_shrink::cache_shrink(),
+		},
+		Command {
+			name: "cat",
+			usage: "cat <path>",
+			help: "Print a file of the initrd or /disk",
+			run: |_, args| cat::cat(args),
+		},
+		Command {
+			name: "clear",
+			usage: "clear",
+			help: "Clear the screen",
+			run: |console, _| console.clear_screen(),
+		},
+		Command {
+			name: "cmdline",
+			usage: "cmdline",
+			help: "Show the kernel command line",
+			run: |_, _| cmdline::cmdline(),
+		},
+		Command {
+			name: "cpuinfo",
+			usage: "cpuinfo",
+			help: "Show CPU vendor and features",
+			run: |_, _| cpuinfo::cpuinfo(),
+		},
+		Command {
+			name: "date",
+			usage: "date",
+			help: "Show the current date and time",
+			run: |_, _| date::date(),
+		},
+		Command {
+			name: "disk",
+			usage: "disk info | read <lba> | write <lba> <byte>",
+			help: "Show, read or write the ATA disk",
+			run: |_, args| disk::disk(args),
+		},
+		Command {
+			name: "dmesg",
+			usage: "dmesg [-c]",
+			help: "Print (and clear) the kernel log",
+			run: |_, args| dmesg::dmesg(args),
+		},
+		Command {
+			name: "echo",
+			usage: "echo [args...]",
+			help: "Print the arguments",
+			run: |_, args| echo::echo(args),
+		},
+		Command {
+			name: "gdt",
+			usage: "gdt",
+			help: "Print Global Descriptor Table",
+			run: |_, _| gdt::print_gdt(),
+		},
+		Command {
+			name: "heapcheck",
+			usage: "heapcheck",
+			help: "Check the heap allocators for corruption",
+			run: |_, _| heapcheck::heapcheck(),
+		},
+		Command {
+			name: "help",
+			usage: "help",
+			help: "Show this help message",
+			run: |console, _| console.print_help(),
+		},
+		Command {
+			name: "hexdump",
+			usage: "hexdump <addr> [len]",
+			help: "Dump memory as hex and ASCII",
+			run: |_, args| hexdump::hexdump(args),
+		},
+		Command {
+			name: "idt",
+			usage: "idt",
+			help: "Print Interrupt Descriptor Table",
+			run: |_, _| idt::print_idt(),
+		},
+		Command {
+			name: "interrupts",
+			usage: "interrupts",
+			help: "Show the state of every IRQ line",
+			run: |_, _| interrupts::interrupts(),
+		},
+		Command {
+			name: "loglevel",
+			usage: "loglevel [level]",
+			help: "Show or set the log level",
+			run: |_, args| loglevel::loglevel(args),
+		},
+		Command {
+			name: "ls",
+			usage: "ls [path]",
+			help: "List the files of the initrd or a directory of /disk",
+			run: |_, args| ls::ls(args),
+		},
+		Command {
+			name: "lspci",
+			usage: "lspci [-v]",
+			help: "List the PCI functions",
+			run: |_, args| lspci::lspci(args),
+		},
+		Command {
+			name: "meminfo",
+			usage: "meminfo",
+			help: "Show memory and allocator usage",
+			run: |_, _| meminfo::meminfo(),
+		},
+		Command {
+			name: "memtest",
+			usage: "memtest [iterations]",
+			help: "Stress test the allocators",
+			run: |_, args| memtest::memtest(args),
+		},
+		Command {
+			name: "mode",
+			usage: "mode [80x25|80x50]",
+			help: "Show or switch the text mode",
+			run: |_, args| mode::mode(args),
+		},
+		Command {
+			name: "modhex",
+			usage: "modhex <module-index> [len]",
+			help: "Hexdump the start of a boot module",
+			run: |_, args| modhex::modhex(args),
+		},
+		Command {
+			name: "modinfo",
+			usage: "modinfo",
+			help: "List the boot modules",
+			run: |_, _| modinfo::modinfo(),
+		},
+		Command {
+			name: "pagetables",
+			usage: "pagetables [addr]",
+			help: "Show the page mappings",
+			run: |_, args| pagetables::pagetables(args),
+		},
+		Command {
+			name: "panic",
+			usage: "panic",
+			help: "Trigger a kernel panic",
+			run: |_, _| panic!("Test panic"),
+		},
+		Command {
+			name: "peek",
+			usage: "peek <addr>",
+			help: "Read a 32-bit word",
+			run: |_, args| peek::peek(args),
+		},
+		Command {
+			name: "poke",
+			usage: "poke <addr> <value>",
+			help: "Write a 32-bit word",
+			run: |_, args| peek::poke(args),
+		},
+		Command {
+			name: "ps",
+			usage: "ps",
+			help: "List the processes",
+			run: |_, _| ps::ps(),
+		},
+		Command {
+			name: "reboot",
+			usage: "reboot",
+			help: "Restart the system",
+			run: |_, _| reboot(),
+		},
+		Command {
+			name: "ro",
+			usage: "ro <addr>",
+			help: "Map a page read-only",
+			run: |_, args| protect::ro(args),
+		},
+		Command {
+			name: "run",
+			usage: "run [module-index|path]",
+			help: "List the boot modules or run one, or a file, in ring 3",
+			run: |_, args| run::run(args),
+		},
+		Command {
+			name: "rw",
+			usage: "rw <addr>",
+			help: "Map a page writable",
+			run: |_, args| protect::rw(args),
+		},
+		Command {
+			name: "serialmirror",
+			usage: "serialmirror [on|off]",
+			help: "Copy console output to COM1",
+			run: |_, args| serialmirror::serialmirror(args),
+		},
+		Command {
+			name: "slabinfo",
+			usage: "slabinfo",
+			help: "Show slab cache usage",
+			run: |_, _| slabinfo::slabinfo(),
+		},
+		Command {
+			name: "sleep",
+			usage: "sleep <ms>",
+			help: "Wait, Ctrl+C to stop early",
+			run: |_, args| uptime::sleep(args),
+		},
+		Command {
+			name: "sleeptest",
+			usage: "sleeptest",
+			help: "Check threads wake from sleep in order",
+			run: |_, _| sleeptest::sleeptest(),
+		},
+		Command {
+			name: "sym",
+			usage: "sym <addr>",
+			help: "Show the function an address is in",
+			run: |_, args| sym::sym(args),
+		},
+		Command {
+			name: "threads",
+			usage: "threads",
+			help: "Run two threads taking turns",
+			run: |_, _| threads::threads(),
+		},
+		Command {
+			name: "uptime",
+			usage: "uptime",
+			help: "Show time since boot",
+			run: |_, _| uptime::uptime(),
+		},
+		Command {
+			name: "user",
+			usage: "user",
+			help: "Run a demo payload in ring 3",
+			run: |_, _| user::user(),
+		},
+		Command {
+			name: "workqueue",
+			usage: "workqueue",
+			help: "Show deferred work counters",
+			run: |_, _| workqueue::workqueue(),
+		},
+	];
+}
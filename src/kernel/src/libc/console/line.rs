@@ -0,0 +1,167 @@
+//! Console input line.
+//!
+//! Lines up to [`INLINE_CAPACITY`] bytes are kept inline, so typing works
+//! before the heap is up. The first byte past that moves the line into a
+//! block from [`kmalloc`], which grows up to [`MAX_LINE`] bytes and is freed
+//! again by [`LineBuffer::clear`].
+
+use crate::memory::kalloc::{kfree, kmalloc};
+use core::{alloc::Layout, ptr::NonNull, slice};
+
+/// Bytes stored without touching the heap.
+pub const INLINE_CAPACITY: usize = 256;
+
+/// Longest line accepted.
+pub const MAX_LINE: usize = 1024;
+
+/// A line of input that spills to the heap once it outgrows its inline buffer.
+pub struct LineBuffer {
+	inline: [u8; INLINE_CAPACITY],
+	len: usize,
+	/// Block of `capacity` bytes holding the line once it spilled.
+	heap: Option<NonNull<u8>>,
+	capacity: usize,
+}
+
+unsafe impl Send for LineBuffer {}
+
+impl Default for LineBuffer {
+	fn default() -> Self {
+		return Self::new();
+	}
+}
+
+impl Drop for LineBuffer {
+	fn drop(&mut self) {
+		self.clear();
+	}
+}
+
+impl LineBuffer {
+	/// Creates an empty line without allocating.
+	pub const fn new() -> Self {
+		return Self {
+			inline: [0; INLINE_CAPACITY],
+			len: 0,
+			heap: None,
+			capacity: INLINE_CAPACITY,
+		};
+	}
+
+	/// Returns the number of bytes in the line.
+	pub fn len(&self) -> usize {
+		return self.len;
+	}
+
+	/// Returns whether the line is empty.
+	pub fn is_empty(&self) -> bool {
+		return self.len == 0;
+	}
+
+	/// Returns whether the line has moved to the heap.
+	pub fn is_spilled(&self) -> bool {
+		return self.heap.is_some();
+	}
+
+	/// Returns the line as one contiguous slice.
+	pub fn as_bytes(&self) -> &[u8] {
+		match self.heap {
+			Some(heap) => {
+				return unsafe {
+					slice::from_raw_parts(heap.as_ptr(), self.len)
+				}
+			}
+			None => return &self.inline[..self.len],
+		}
+	}
+
+	/// Inserts `byte` at index `at`, shifting the rest of the line right.
+	///
+	/// Returns `false` and leaves the line unchanged if it is already
+	/// [`MAX_LINE`] bytes long or the heap cannot hold a longer line.
+	pub fn insert(&mut self, at: usize, byte: u8) -> bool {
+		if at > self.len || self.len == MAX_LINE {
+			return false;
+		}
+
+		if self.len == self.capacity && !self.grow() {
+			return false;
+		}
+
+		let len = self.len;
+		let storage = self.storage();
+		storage.copy_within(at..len, at + 1);
+		storage[at] = byte;
+
+		self.len += 1;
+		return true;
+	}
+
+	/// Removes the byte at index `at`, shifting the rest of the line left.
+	pub fn remove(&mut self, at: usize) -> Option<u8> {
+		if at >= self.len {
+			return None;
+		}
+
+		let len = self.len;
+		let storage = self.storage();
+		let byte = storage[at];
+		storage.copy_within(at + 1..len, at);
+
+		self.len -= 1;
+		return Some(byte);
+	}
+
+	/// Empties the line and releases its heap allocation, if any.
+	pub fn clear(&mut self) {
+		if let Some(heap) = self.heap.take() {
+			let layout = Self::heap_layout(self.capacity);
+			unsafe { kfree(heap, layout) };
+		}
+
+		self.capacity = INLINE_CAPACITY;
+		self.len = 0;
+	}
+
+	/// Returns all `capacity` bytes the line is stored in.
+	fn storage(&mut self) -> &mut [u8] {
+		match self.heap {
+			Some(heap) => {
+				return unsafe {
+					slice::from_raw_parts_mut(heap.as_ptr(), self.capacity)
+				}
+			}
+			None => return &mut self.inline,
+		}
+	}
+
+	/// Moves the line into a heap block of double the capacity, up to
+	/// [`MAX_LINE`]. Fails instead of panicking when the allocator is not up
+	/// yet or out of memory.
+	fn grow(&mut self) -> bool {
+		let capacity = (self.capacity * 2).clamp(INLINE_CAPACITY * 2, MAX_LINE);
+		let Ok(block) = kmalloc(Self::heap_layout(capacity)) else {
+			return false;
+		};
+
+		let len = self.len;
+		unsafe {
+			block
+				.as_ptr()
+				.copy_from_nonoverlapping(self.as_bytes().as_ptr(), len)
+		};
+
+		self.clear();
+		self.heap = Some(block);
+		self.capacity = capacity;
+		self.len = len;
+		return true;
+	}
+
+	/// Returns the layout of a heap block of `capacity` bytes.
+	const fn heap_layout(capacity: usize) -> Layout {
+		// Safety: an alignment of 1 is a power of two and `capacity` is at
+		// most `MAX_LINE`.
+		return unsafe { Layout::from_size_align_unchecked(capacity, 1) };
+	}
+}
@@ -0,0 +1,26 @@
+//! Copying console output to the serial port.
+
+use crate::tty::{
+	output::{is_registered, register, unregister},
+	serial::SERIAL_OUTPUT,
+	tty::WRITER,
+};
+
+/// Makes `print!` also write its output to COM1, for running headless.
+pub fn set_mirror_serial(on: bool) {
+	if !on {
+		unregister(&SERIAL_OUTPUT);
+		return;
+	}
+
+	if !is_registered(&SERIAL_OUTPUT) {
+		let (col, row) = WRITER.lock().position();
+		SERIAL_OUTPUT.sync_position(col, row);
+		register(&SERIAL_OUTPUT);
+	}
+}
+
+/// Returns whether `print!` output is mirrored to COM1.
+pub fn mirror_serial() -> bool {
+	return is_registered(&SERIAL_OUTPUT);
+}
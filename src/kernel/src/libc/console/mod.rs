@@ -1,4 +1,17 @@
 #[doc(hidden)]
 pub mod bin;
 #[doc(hidden)]
+pub mod command;
+#[doc(hidden)]
 pub mod console;
+#[doc(hidden)]
+pub mod line;
+#[doc(hidden)]
+pub mod mirror;
+#[doc(hidden)]
+pub mod parse;
+#[doc(hidden)]
+pub mod serial_input;
+
+pub use command::{register_command, Command};
+pub use mirror::{mirror_serial, set_mirror_serial};
@@ -0,0 +1,25 @@
+//! Number parsing for command arguments.
+
+/// Parses a hexadecimal number, with or without a `0x` prefix.
+pub fn parse_hex(s: &str) -> Option<usize> {
+	let digits = s
+		.strip_prefix("0x")
+		.or_else(|| s.strip_prefix("0X"))
+		.unwrap_or(s);
+
+	if digits.is_empty() {
+		return None;
+	}
+
+	return usize::from_str_radix(digits, 16).ok();
+}
+
+/// Parses a number, as hexadecimal if it has a `0x` prefix and as decimal
+/// otherwise.
+pub fn parse_usize(s: &str) -> Option<usize> {
+	if s.starts_with("0x") || s.starts_with("0X") {
+		return parse_hex(s);
+	}
+
+	return s.parse().ok();
+}
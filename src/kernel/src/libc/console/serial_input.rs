@@ -0,0 +1,77 @@
+//! Decoding of bytes typed into a serial terminal.
+//!
+//! Terminals send Enter as CR, Backspace as DEL (0x7F) and the cursor keys as
+//! ANSI escape sequences. [`SerialInput`] turns them into the same
+//! [`KeyEvent`]s the PS/2 keyboard produces, so the console does not care
+//! where its input came from.
+
+use crate::device::keyboard::{KeyEvent, KeyboardKey};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+	Ground,
+	/// After ESC.
+	Escape,
+	/// After `ESC [`, holding the numeric parameter read so far.
+	Csi(u8),
+}
+
+/// Translates a stream of serial bytes into key events.
+pub struct SerialInput {
+	state: State,
+}
+
+impl Default for SerialInput {
+	fn default() -> Self {
+		return Self::new();
+	}
+}
+
+impl SerialInput {
+	/// Creates a decoder expecting the start of a key.
+	pub const fn new() -> Self {
+		return Self {
+			state: State::Ground,
+		};
+	}
+
+	/// Feeds one received byte, returning the key it completes, if any.
+	pub fn feed(&mut self, byte: u8) -> Option<KeyEvent> {
+		match (self.state, byte) {
+			(State::Ground, 0x1b) => self.state = State::Escape,
+			(State::Ground, b'\r') => return Some(KeyEvent::Char('\n')),
+			(State::Ground, 0x7f) => return Some(KeyEvent::Char('\x08')),
+			(State::Ground, byte) if byte.is_ascii() => {
+				return Some(KeyEvent::Char(byte as char));
+			}
+			(State::Ground, _) => {}
+			(State::Escape, b'[') => self.state = State::Csi(0),
+			(State::Csi(n), b'0'..=b'9') => {
+				self.state = State::Csi(
+					n.saturating_mul(10).saturating_add(byte - b'0'),
+				);
+			}
+			(State::Csi(n), _) => {
+				self.state = State::Ground;
+				return Self::csi_key(n, byte).map(KeyEvent::Special);
+			}
+			(State::Escape, _) => self.state = State::Ground,
+		}
+
+		return None;
+	}
+
+	/// Maps the final byte of `ESC [ n <byte>` to a key.
+	fn csi_key(n: u8, byte: u8) -> Option<KeyboardKey> {
+		match (n, byte) {
+			(_, b'A') => return Some(KeyboardKey::KeyCursorUp),
+			(_, b'B') => return Some(KeyboardKey::KeyCursorDown),
+			(_, b'C') => return Some(KeyboardKey::KeyCursorRight),
+			(_, b'D') => return Some(KeyboardKey::KeyCursorLeft),
+			(_, b'H') | (1, b'~') => return Some(KeyboardKey::KeyHome),
+			(_, b'F') | (4, b'~') => return Some(KeyboardKey::KeyEnd),
+			(3, b'~') => return Some(KeyboardKey::KeyDelete),
+			_ => return None,
+		}
+	}
+}
@@ -0,0 +1,202 @@
+//! The memory routines the compiler lowers copies, fills and comparisons to.
+//!
+//! `core` expects `memcpy`, `memmove`, `memset` and `memcmp` to be there, a
+//! `ptr::copy` or a struct assignment may end up calling them. They move a
+//! word at a time where they can: when both pointers sit at the same offset
+//! in a word, the bytes up to the next word boundary go one by one, then
+//! whole words, then the tail. Pointers at different offsets are copied
+//! byte by byte, as i386 code here never relies on unaligned word accesses.
+//!
+//! The stores are volatile so LLVM does not recognize the loops as a copy or
+//! a fill and turn them back into calls to these very functions.
+
+use core::mem::size_of;
+
+const WORD: usize = size_of::<usize>();
+
+// Helper returning whether `a` and `b` are at the same offset in a word
+fn co_aligned(a: usize, b: usize) -> bool {
+	return (a ^ b) % WORD == 0;
+}
+
+// Helper copying `n` bytes from `src` to `dest`, first byte first
+//
+// Safety: both ranges are valid, `dest` does not overlap the part of `src`
+// after it.
+unsafe fn copy_forward(dest: *mut u8, src: *const u8, n: usize) {
+	let mut i = 0;
+
+	if co_aligned(dest.addr(), src.addr()) {
+		let head = (dest.addr().wrapping_neg() % WORD).min(n);
+		while i < head {
+			// Safety: `i < n`, see the function.
+			unsafe { dest.add(i).write_volatile(src.add(i).read()) };
+			i += 1;
+		}
+		while n - i >= WORD {
+			// Safety: `i + WORD <= n`, word aligned past the head.
+			unsafe {
+				let word = src.add(i).cast::<usize>().read();
+				dest.add(i).cast::<usize>().write_volatile(word);
+			}
+			i += WORD;
+		}
+	}
+
+	while i < n {
+		// Safety: `i < n`.
+		unsafe { dest.add(i).write_volatile(src.add(i).read()) };
+		i += 1;
+	}
+}
+
+// Helper copying `n` bytes from `src` to `dest`, last byte first
+//
+// Safety: both ranges are valid, `dest` does not overlap the part of `src`
+// before it.
+unsafe fn copy_backward(dest: *mut u8, src: *const u8, n: usize) {
+	let mut left = n;
+
+	// The ends are co-aligned if the starts are
+	if co_aligned(dest.addr(), src.addr()) {
+		let tail = (dest.addr() + n) % WORD;
+		let stop = n - tail.min(n);
+		while left > stop {
+			left -= 1;
+			// Safety: `left < n`.
+			unsafe { dest.add(left).write_volatile(src.add(left).read()) };
+		}
+		while left >= WORD {
+			left -= WORD;
+			// Safety: `left + WORD <= n`, word aligned below the tail.
+			unsafe {
+				let word = src.add(left).cast::<usize>().read();
+				dest.add(left).cast::<usize>().write_volatile(word);
+			}
+		}
+	}
+
+	while left > 0 {
+		left -= 1;
+		// Safety: `left < n`.
+		unsafe { dest.add(left).write_volatile(src.add(left).read()) };
+	}
+}
+
+/// Copies `n` bytes from `src` to `dest` and returns `dest`.
+///
+/// # Safety
+/// `src` must be valid for reading and `dest` for writing `n` bytes, and the
+/// two ranges must not overlap.
+#[no_mangle]
+pub unsafe extern "C" fn memcpy(
+	dest: *mut u8,
+	src: *const u8,
+	n: usize,
+) -> *mut u8 {
+	// Safety: the caller's promise.
+	unsafe { copy_forward(dest, src, n) };
+
+	return dest;
+}
+
+/// Copies `n` bytes from `src` to `dest`, which may overlap, and returns
+/// `dest`.
+///
+/// # Safety
+/// `src` must be valid for reading and `dest` for writing `n` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn memmove(
+	dest: *mut u8,
+	src: *const u8,
+	n: usize,
+) -> *mut u8 {
+	// Front to back unless that overwrites source bytes not copied yet
+	if dest.addr().wrapping_sub(src.addr()) >= n {
+		// Safety: the caller's promise, `dest` is not inside `src` past its
+		// start.
+		unsafe { copy_forward(dest, src, n) };
+	} else {
+		// Safety: the caller's promise, `dest` starts inside `src`.
+		unsafe { copy_backward(dest, src, n) };
+	}
+
+	return dest;
+}
+
+/// Sets `n` bytes at `dest` to the low byte of `c` and returns `dest`.
+///
+/// # Safety
+/// `dest` must be valid for writing `n` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn memset(dest: *mut u8, c: i32, n: usize) -> *mut u8 {
+	let byte = c as u8;
+	let word = usize::from_ne_bytes([byte; WORD]);
+	let head = (dest.addr().wrapping_neg() % WORD).min(n);
+	let mut i = 0;
+
+	while i < head {
+		// Safety: `i < n`.
+		unsafe { dest.add(i).write_volatile(byte) };
+		i += 1;
+	}
+	while n - i >= WORD {
+		// Safety: `i + WORD <= n`, word aligned past the head.
+		unsafe { dest.add(i).cast::<usize>().write_volatile(word) };
+		i += WORD;
+	}
+	while i < n {
+		// Safety: `i < n`.
+		unsafe { dest.add(i).write_volatile(byte) };
+		i += 1;
+	}
+
+	return dest;
+}
+
+/// Compares `n` bytes at `s1` and `s2` and returns the difference of the
+/// first pair that differs, as unsigned bytes, 0 if none does.
+///
+/// # Safety
+/// `s1` and `s2` must be valid for reading `n` bytes.
+#[no_mangle]
+pub unsafe extern "C" fn memcmp(s1: *const u8, s2: *const u8, n: usize) -> i32 {
+	let mut i = 0;
+
+	// Skip the equal words, the bytes of the first different one decide
+	if co_aligned(s1.addr(), s2.addr()) {
+		let head = (s1.addr().wrapping_neg() % WORD).min(n);
+		while i < head {
+			// Safety: `i < n`.
+			let (a, b) = unsafe { (s1.add(i).read(), s2.add(i).read()) };
+			if a != b {
+				return a as i32 - b as i32;
+			}
+			i += 1;
+		}
+		while n - i >= WORD {
+			// Safety: `i + WORD <= n`, word aligned past the head.
+			let (a, b) = unsafe {
+				(
+					s1.add(i).cast::<usize>().read(),
+					s2.add(i).cast::<usize>().read(),
+				)
+			};
+			if a != b {
+				break;
+			}
+			i += WORD;
+		}
+	}
+
+	while i < n {
+		// Safety: `i < n`.
+		let (a, b) = unsafe { (s1.add(i).read(), s2.add(i).read()) };
+		if a != b {
+			return a as i32 - b as i32;
+		}
+		i += 1;
+	}
+
+	return 0;
+}
@@ -1,2 +1,4 @@
 /// A Minimal Console - Shelly
 pub mod console;
+/// The memcpy, memmove, memset and memcmp the compiler calls
+pub mod mem;
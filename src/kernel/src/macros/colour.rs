@@ -1,18 +1,18 @@
-/// Changes the foreground color of the VGA text output
+/// Changes the foreground color of the console output
 #[macro_export]
 macro_rules! set_fg_color {
 	($colour:expr) => {{
-		use $crate::tty::{tty::WRITER, VgaColour};
-		WRITER.lock().colour_code.set_foreground_colour($colour);
+		use $crate::tty::{output, VgaColour};
+		output::update_colour(|colour| colour.set_foreground_colour($colour));
 	}};
 }
 
-/// Changes the background color of the VGA text output
+/// Changes the background color of the console output
 #[macro_export]
 macro_rules! set_bg_color {
 	($colour:expr) => {{
-		use $crate::tty::{tty::WRITER, VgaColour};
-		WRITER.lock().colour_code.set_background_colour($colour);
+		use $crate::tty::{output, VgaColour};
+		output::update_colour(|colour| colour.set_background_colour($colour));
 	}};
 }
 
@@ -20,11 +20,11 @@ macro_rules! set_bg_color {
 #[macro_export]
 macro_rules! with_fg_color {
     ($colour:expr, $($code:tt)*) => {{
-        use $crate::tty::{tty::WRITER, VgaColour};
-        let original = WRITER.lock().colour_code.get_foreground_colour();
-        WRITER.lock().colour_code.set_foreground_colour($colour);
+        use $crate::tty::{output, VgaColour};
+        let original =
+            output::update_colour(|colour| colour.set_foreground_colour($colour));
         let result = { $($code)* };
-        WRITER.lock().colour_code.set_foreground_colour(original);
+        output::set_colour(original);
         result
     }};
 }
@@ -33,11 +33,11 @@ macro_rules! with_fg_color {
 #[macro_export]
 macro_rules! with_bg_color {
     ($colour:expr, $($code:tt)*) => {{
-        use $crate::tty::{tty::WRITER, VgaColour};
-        let original = WRITER.lock().colour_code.get_background_colour();
-        WRITER.lock().colour_code.set_background_colour($colour);
+        use $crate::tty::{output, VgaColour};
+        let original =
+            output::update_colour(|colour| colour.set_background_colour($colour));
         let result = { $($code)* };
-        WRITER.lock().colour_code.set_background_colour(original);
+        output::set_colour(original);
         result
     }};
 }
@@ -46,14 +46,13 @@ macro_rules! with_bg_color {
 #[macro_export]
 macro_rules! with_colors {
     ($fg:expr, $bg:expr, $($code:tt)*) => {{
-        use $crate::tty::{tty::WRITER, VgaColour};
-        let original_fg = WRITER.lock().colour_code.get_foreground_colour();
-        let original_bg = WRITER.lock().colour_code.get_background_colour();
-        WRITER.lock().colour_code.set_foreground_colour($fg);
-        WRITER.lock().colour_code.set_background_colour($bg);
+        use $crate::tty::{output, VgaColour};
+        let original = output::update_colour(|colour| {
+            colour.set_foreground_colour($fg);
+            colour.set_background_colour($bg);
+        });
         let result = { $($code)* };
-        WRITER.lock().colour_code.set_foreground_colour(original_fg);
-        WRITER.lock().colour_code.set_background_colour(original_bg);
+        output::set_colour(original);
         result
     }};
 }
@@ -4,13 +4,15 @@
 #[macro_export]
 macro_rules! log_error {
     ($($arg:tt)*) => {{
-        $crate::tty::log::_log(
-            $crate::tty::log::LogLevel::Error,
-            file!(),
-            line!(),
-            module_path!(),
-            format_args!($($arg)*)
-        );
+        if $crate::tty::log::enabled($crate::tty::log::LogLevel::Error) {
+            $crate::tty::log::_log(
+                $crate::tty::log::LogLevel::Error,
+                file!(),
+                line!(),
+                module_path!(),
+                format_args!($($arg)*)
+            );
+        }
     }};
 }
 
@@ -18,13 +20,15 @@ macro_rules! log_error {
 #[macro_export]
 macro_rules! log_warn {
     ($($arg:tt)*) => {{
-        $crate::tty::log::_log(
-            $crate::tty::log::LogLevel::Warn,
-            file!(),
-            line!(),
-            module_path!(),
-            format_args!($($arg)*)
-        );
+        if $crate::tty::log::enabled($crate::tty::log::LogLevel::Warn) {
+            $crate::tty::log::_log(
+                $crate::tty::log::LogLevel::Warn,
+                file!(),
+                line!(),
+                module_path!(),
+                format_args!($($arg)*)
+            );
+        }
     }};
 }
 
@@ -32,13 +36,15 @@ macro_rules! log_warn {
 #[macro_export]
 macro_rules! log_info {
     ($($arg:tt)*) => {{
-        $crate::tty::log::_log(
-            $crate::tty::log::LogLevel::Info,
-            file!(),
-            line!(),
-            module_path!(),
-            format_args!($($arg)*)
-        );
+        if $crate::tty::log::enabled($crate::tty::log::LogLevel::Info) {
+            $crate::tty::log::_log(
+                $crate::tty::log::LogLevel::Info,
+                file!(),
+                line!(),
+                module_path!(),
+                format_args!($($arg)*)
+            );
+        }
     }};
 }
 
@@ -48,7 +54,7 @@ macro_rules! log_info {
 macro_rules! log_debug {
     ($($arg:tt)*) => {{
         #[cfg(debug_assertions)]
-        {
+        if $crate::tty::log::enabled($crate::tty::log::LogLevel::Debug) {
             $crate::tty::log::_log(
                 $crate::tty::log::LogLevel::Debug,
                 file!(),
@@ -59,3 +65,21 @@ macro_rules! log_debug {
         }
     }};
 }
+
+/// Logs a formatted trace message to serial and the kernel log (only in debug
+/// builds and when the level is raised to `Trace`).
+#[macro_export]
+macro_rules! log_trace {
+    ($($arg:tt)*) => {{
+        #[cfg(debug_assertions)]
+        if $crate::tty::log::enabled($crate::tty::log::LogLevel::Trace) {
+            $crate::tty::log::_log(
+                $crate::tty::log::LogLevel::Trace,
+                file!(),
+                line!(),
+                module_path!(),
+                format_args!($($arg)*)
+            );
+        }
+    }};
+}
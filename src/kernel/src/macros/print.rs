@@ -1,4 +1,4 @@
-use crate::tty::tty::WRITER;
+use crate::tty::output;
 use core::fmt;
 
 /// Prints formatted text to the VGA buffer.
@@ -64,6 +64,5 @@ macro_rules! println {
 
 #[doc(hidden)]
 pub fn _print(args: fmt::Arguments) {
-	use core::fmt::Write;
-	let _ = WRITER.lock().write_fmt(args);
+	output::print(args);
 }
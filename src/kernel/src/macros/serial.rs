@@ -1,4 +1,10 @@
-use crate::tty::serial::SERIAL;
+use crate::{
+	sync::IrqMutexGuard,
+	tty::{
+		e9,
+		serial::{Serial, SERIAL},
+	},
+};
 use core::fmt;
 
 #[doc(hidden)]
@@ -14,8 +20,34 @@ macro_rules! println_serial {
     ($($arg:tt)*) => ($crate::print_serial!("{}\n", format_args!($($arg)*)));
 }
 
+/// Writes to the log port, or to the 0xE9 debug console while the port has
+/// no UART (before serial init, for one) or when the debug console is
+/// [enabled](e9::set_enabled).
 #[doc(hidden)]
 pub fn _print_serial(args: fmt::Arguments) {
+	print_with(SERIAL.lock(), args);
+}
+
+/// Like [`_print_serial`], but breaks the port's lock if it is held.
+///
+/// # Safety
+/// The holder must never run again, as for the panic handler.
+#[doc(hidden)]
+pub unsafe fn _print_serial_forced(args: fmt::Arguments) {
+	print_with(unsafe { SERIAL.force_lock() }, args);
+}
+
+// Helper writing through the locked port
+fn print_with(mut serial: IrqMutexGuard<Serial>, args: fmt::Arguments) {
 	use core::fmt::Write;
-	let _ = SERIAL.lock().write_fmt(args);
+
+	let present = serial.is_present();
+	if present {
+		let _ = serial.write_fmt(args);
+	}
+	drop(serial);
+
+	if !present || e9::enabled() {
+		e9::print(args);
+	}
 }
@@ -1,56 +1,72 @@
 //! Defines the kernel's global memory allocator instance.
 
 use super::{
-	buddy::BuddyAllocator, memblock::MemBlockAllocator, slab::SlabCache,
+	buddy::BuddyAllocator,
+	memblock::MemBlockAllocator,
+	node_pool::NODE_SLOT_SIZE,
+	slab::{SlabCache, SlabCheckError, SlabStats},
+	virt_range::VIRT_RANGE_NODES,
 	NodePoolAllocator,
 };
 use crate::{
-	arch::x86::multiboot::{
-		get_biggest_available_segment_index, get_memory_region, MultibootInfo,
-		G_SEGMENTS,
-	},
+	arch::x86::multiboot::{get_memory_region, MultibootInfo, G_SEGMENTS},
 	collections::linked_list::Node,
-	log_debug, log_info,
+	log_debug, log_error, log_info,
 	memory::{
 		allocator,
 		frame::FRAME_ALLOCATOR,
+		get_kernel_physical_end, get_kernel_physical_start,
 		get_kernel_virtual_end,
-		paging::{flags, map_page},
-		FrameAllocator, PhysAddr, VirtAddr, NODE_POOL_VIRT_START, PAGE_SIZE,
+		paging::{flags, map_page, map_range, unmap_page_keep_frame},
+		FrameAllocator, PhysAddr, VirtAddr, BUDDY_WINDOW_SIZE,
+		BUDDY_WINDOW_VIRT_START, KERNEL_OFFSET, NODE_POOL_VIRT_START,
+		PAGE_SIZE,
 	},
 	print_serial, println_serial,
-	sync::Locked,
+	sync::{Global, Locked, MappedMutexGuard, MutexGuard},
 };
 use core::{
 	alloc::{GlobalAlloc, Layout},
-	cell::OnceCell,
 	ptr,
 };
 
-const SLAB_CACHE_COUNT: usize = 9;
+/// Number of size classes served by the slab caches.
+pub const SLAB_CACHE_COUNT: usize = 9;
 const CACHE_SIZES: [usize; SLAB_CACHE_COUNT] =
 	[4, 8, 16, 32, 64, 128, 256, 512, 1024];
+const CACHE_NAMES: [&str; SLAB_CACHE_COUNT] = [
+	"kmalloc-4",
+	"kmalloc-8",
+	"kmalloc-16",
+	"kmalloc-32",
+	"kmalloc-64",
+	"kmalloc-128",
+	"kmalloc-256",
+	"kmalloc-512",
+	"kmalloc-1024",
+];
 
 // 1. Define static for the EARLY allocator (MemBlock) NO #[global_allocator]
-//    attribute here!
+//    attribute here! Unlike the others it is an `Option`, since `memory_init`
+//    decommissions it again.
 #[allow(missing_docs)]
-pub static EARLY_PHYSICAL_ALLOCATOR: Locked<OnceCell<MemBlockAllocator>> =
-	Locked::new(OnceCell::new());
+pub static EARLY_PHYSICAL_ALLOCATOR: Locked<Option<MemBlockAllocator>> =
+	Locked::named("EARLY_PHYSICAL_ALLOCATOR", None);
 
 // 2. Define another static which is in charge to reserve space for the Buddy
 //    Allocator meant for the `free_list`.
 #[allow(missing_docs)]
-pub static NODE_POOL_ALLOCATOR: Locked<OnceCell<NodePoolAllocator>> =
-	Locked::new(OnceCell::new());
+pub static NODE_POOL_ALLOCATOR: Global<NodePoolAllocator> =
+	Global::named("NODE_POOL_ALLOCATOR");
 
 // 2. Define statics for the LATER allocators (Buddy + Slab) These need
-//    initialization logic. Using OnceCell is one way.
+//    initialization logic, `memory_init` sets them up once.
 #[allow(missing_docs)]
-pub static BUDDY_PAGE_ALLOCATOR: Locked<OnceCell<BuddyAllocator>> =
-	Locked::new(OnceCell::new());
+pub static BUDDY_PAGE_ALLOCATOR: Global<BuddyAllocator> =
+	Global::named("BUDDY_PAGE_ALLOCATOR");
 
-static SLAB_CACHES: Locked<OnceCell<[SlabCache; SLAB_CACHE_COUNT]>> =
-	Locked::new(OnceCell::new());
+static SLAB_CACHES: Global<[SlabCache; SLAB_CACHE_COUNT]> =
+	Global::named("SLAB_CACHES");
 
 // 3. Define the actual GLOBAL ALLOCATOR static. This will WRAP access to the
 //    KERNEL_HEAP_ALLOCATOR once initialized.
@@ -63,50 +79,273 @@ static GLOBAL_ALLOCATOR: Locked<KernelAllocator> = Locked::new(KernelAllocator);
 #[allow(clippy::expect_used)]
 unsafe impl GlobalAlloc for Locked<KernelAllocator> {
 	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-		if layout.size() == 0 {
+		if layout.size() == 0 || layout.align() > PAGE_SIZE {
 			return ptr::null_mut();
 		}
 
-		// TODO: If there is no cache Buddy Allocator should take over
-		let index = CACHE_SIZES
-			.iter()
-			.position(|&cache_size| cache_size >= layout.size())
-			.expect("dealloc: No suitable cache found");
-
-		match SLAB_CACHES.lock().get_mut() {
-			Some(caches) => {
-				let cache = caches
-					.get_mut(index)
-					.expect("FATAL: Slab cache out of bounds during dealloc!");
-
-				unsafe { cache.alloc(layout) }
-			}
-			None => ptr::null_mut(),
+		let ptr = unsafe { heap_alloc(layout) };
+		if !ptr.is_null() || shrink_caches() == 0 {
+			return ptr;
 		}
+
+		// Freeing the cached slabs may have made enough room.
+		unsafe { heap_alloc(layout) }
 	}
 
 	#[allow(clippy::implicit_return)]
 	#[allow(clippy::expect_used)]
 	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-		// TODO: If there is no cache Buddy Allocator should take over
-		let index = CACHE_SIZES
-			.iter()
-			.position(|&cache_size| cache_size >= layout.size())
-			.expect("dealloc: No suitable cache found for size {}");
-
-		match SLAB_CACHES.lock().get_mut() {
-			Some(alloc_array) => {
-				let alloc = alloc_array
-					.get_mut(index)
-					.expect("FATAL: Slab cache out of bounds during dealloc!");
+		let Some(index) = cache_index(layout) else {
+			unsafe { dealloc_pages(ptr, layout) };
+			return;
+		};
 
-				unsafe { alloc.dealloc(ptr, layout) };
-			}
+		match slab_cache(index) {
+			Some(mut cache) => unsafe { cache.dealloc(ptr, layout) },
 			None => {
 				panic!("Heap allocator not initialized yet! Cannot deallocate.")
 			}
 		};
 	}
+
+	#[allow(clippy::implicit_return)]
+	unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+		let ptr = unsafe { self.alloc(layout) };
+		if !ptr.is_null() {
+			unsafe { ptr::write_bytes(ptr, 0, layout.size()) };
+		}
+
+		ptr
+	}
+
+	#[allow(clippy::implicit_return)]
+	unsafe fn realloc(
+		&self,
+		ptr: *mut u8,
+		layout: Layout,
+		new_size: usize,
+	) -> *mut u8 {
+		let Ok(new_layout) = Layout::from_size_align(new_size, layout.align())
+		else {
+			return ptr::null_mut();
+		};
+
+		if same_block(layout, new_layout) {
+			return ptr;
+		}
+
+		let new_ptr = unsafe { self.alloc(new_layout) };
+		if !new_ptr.is_null() {
+			unsafe {
+				ptr::copy_nonoverlapping(
+					ptr,
+					new_ptr,
+					layout.size().min(new_size),
+				);
+				self.dealloc(ptr, layout);
+			}
+		}
+
+		new_ptr
+	}
+}
+
+/// Allocates `layout` from its slab cache or from the buddy allocator, without
+/// trying to make room when that fails.
+#[allow(clippy::implicit_return)]
+unsafe fn heap_alloc(layout: Layout) -> *mut u8 {
+	let Some(index) = cache_index(layout) else {
+		return unsafe { alloc_pages(layout) };
+	};
+
+	match slab_cache(index) {
+		Some(mut cache) => unsafe { cache.alloc(layout) },
+		None => ptr::null_mut(),
+	}
+}
+
+/// Locks the slab cache at `index`, or returns `None` before the caches are
+/// initialized. The other caches are locked along with it.
+#[allow(clippy::expect_used)]
+fn slab_cache(index: usize) -> Option<MappedMutexGuard<'static, SlabCache>> {
+	return SLAB_CACHES.lock().map(|caches| {
+		return MutexGuard::map(caches, |caches| {
+			return caches
+				.get_mut(index)
+				.expect("FATAL: Slab cache index out of bounds!");
+		});
+	});
+}
+
+/// Returns every free slab of every cache to the buddy allocator and returns
+/// how many slabs were freed.
+pub fn shrink_caches() -> usize {
+	return SLAB_CACHES
+		.with(|caches| caches.iter_mut().map(SlabCache::shrink).sum())
+		.unwrap_or(0);
+}
+
+/// Returns the slab size class serving `layout`, or `None` if it takes whole
+/// pages from the buddy allocator instead. The classes are powers of two
+/// aligned to their size, so an alignment above the size bumps the class.
+///
+/// Both `alloc` and `dealloc` pick the allocator from the layout alone, so
+/// they always agree on who owns a pointer.
+fn cache_index(layout: Layout) -> Option<usize> {
+	let size = layout.size().max(layout.align());
+
+	return CACHE_SIZES
+		.iter()
+		.position(|&cache_size| cache_size >= size);
+}
+
+/// Returns whether a block allocated for `old` also fits `new`, because both
+/// map to the same slab size class or to the same number of pages.
+fn same_block(old: Layout, new: Layout) -> bool {
+	match (cache_index(old), cache_index(new)) {
+		(Some(old), Some(new)) => return old == new,
+		(None, None) => return page_layout(old) == page_layout(new),
+		_ => return false,
+	}
+}
+
+/// Rounds `layout` up to whole, page aligned pages, the layout the buddy
+/// allocator sees for it.
+fn page_layout(layout: Layout) -> Option<Layout> {
+	let size = layout.size().checked_next_multiple_of(PAGE_SIZE)?;
+
+	return Layout::from_size_align(size, PAGE_SIZE).ok();
+}
+
+/// Allocates `layout` as whole pages from the buddy allocator and maps them
+/// into the buddy window. Returns null if the buddy is out of memory or the
+/// block lies past the end of the window.
+unsafe fn alloc_pages(layout: Layout) -> *mut u8 {
+	let Some(layout) = page_layout(layout) else {
+		return ptr::null_mut();
+	};
+
+	let (paddr, offset) = {
+		let Some(mut buddy) = BUDDY_PAGE_ALLOCATOR.lock() else {
+			return ptr::null_mut();
+		};
+
+		let phys_ptr = unsafe { buddy.alloc(layout) };
+		if phys_ptr.is_null() {
+			return ptr::null_mut();
+		}
+
+		let paddr: PhysAddr = (phys_ptr as usize).into();
+		let offset = paddr - buddy.base();
+		if offset + layout.size() > BUDDY_WINDOW_SIZE {
+			unsafe { buddy.dealloc(phys_ptr, layout) };
+			return ptr::null_mut();
+		}
+
+		(paddr, offset)
+	};
+
+	let vaddr = VirtAddr::new(BUDDY_WINDOW_VIRT_START + offset);
+	for page in (0..layout.size()).step_by(PAGE_SIZE) {
+		map_page(paddr + page, vaddr + page, flags::PRESENT | flags::WRITABLE);
+	}
+
+	return vaddr.as_mut_ptr();
+}
+
+/// Unmaps a block from [`alloc_pages`] and gives it back to the buddy
+/// allocator.
+unsafe fn dealloc_pages(ptr: *mut u8, layout: Layout) {
+	let Some(layout) = page_layout(layout) else {
+		return;
+	};
+
+	let vaddr = VirtAddr::new(ptr as usize);
+	for page in (0..layout.size()).step_by(PAGE_SIZE) {
+		unmap_page_keep_frame(vaddr + page);
+	}
+
+	let offset = vaddr.as_usize() - BUDDY_WINDOW_VIRT_START;
+	match BUDDY_PAGE_ALLOCATOR.lock() {
+		Some(mut buddy) => {
+			let paddr = buddy.base() + offset;
+			unsafe { buddy.dealloc(paddr.as_mut_ptr(), layout) };
+		}
+		None => {
+			panic!("Buddy allocator not initialized yet! Cannot deallocate.")
+		}
+	}
+}
+
+/// Called when an infallible allocation, like `Box::new` or a `Vec` push,
+/// gets null from the heap.
+#[alloc_error_handler]
+fn alloc_error(layout: Layout) -> ! {
+	log_error!(
+		"Failed to allocate {} bytes aligned to {}",
+		layout.size(),
+		layout.align()
+	);
+	panic!(
+		"kernel OOM: allocation of {} bytes (align {}) failed",
+		layout.size(),
+		layout.align()
+	);
+}
+
+/// Returns a snapshot of every slab size class, or `None` before the caches
+/// are initialized.
+pub fn slab_stats() -> Option<[SlabStats; SLAB_CACHE_COUNT]> {
+	return SLAB_CACHES.with(|caches| caches.each_ref().map(SlabCache::stats));
+}
+
+/// Runs [`SlabCache::debug_check`] on every slab size class, or returns
+/// `None` before the caches are initialized.
+pub fn slab_check(
+) -> Option<[(&'static str, Result<(), SlabCheckError>); SLAB_CACHE_COUNT]> {
+	return SLAB_CACHES.with(|caches| {
+		caches
+			.each_ref()
+			.map(|cache| (cache.name(), cache.debug_check()))
+	});
+}
+
+/// Reserves the kernel image, the multiboot information, its memory map and
+/// the boot modules in `memblock`, so none of it is handed out.
+fn reserve_boot_data(
+	memblock: &mut MemBlockAllocator,
+	boot_info: &MultibootInfo,
+) {
+	let kernel_start = get_kernel_physical_start();
+	let kernel_size = get_kernel_physical_end() - kernel_start;
+	let mut fits = memblock.reserve(kernel_start, kernel_size);
+
+	let info = ptr::from_ref(boot_info).addr();
+	let info = PhysAddr::new(info.checked_sub(KERNEL_OFFSET).unwrap_or(info));
+	fits &= memblock.reserve(info, size_of::<MultibootInfo>());
+
+	fits &= memblock.reserve(
+		PhysAddr::new(boot_info.mmap_addr as usize),
+		boot_info.mmap_length as usize,
+	);
+
+	let modules = boot_info.modules();
+	if let Some(first) = modules.first() {
+		fits &= memblock.reserve(
+			PhysAddr::new(ptr::from_ref(first).addr()),
+			size_of_val(modules),
+		);
+	}
+	for module in modules {
+		fits &= memblock.reserve(
+			PhysAddr::new(module.mod_start as usize),
+			module.mod_end.saturating_sub(module.mod_start) as usize,
+		);
+	}
+
+	if !fits {
+		panic!("memblock: MAX_REGION is full while reserving boot data");
+	}
 }
 
 /// Initializes the kernel's memory management system.
@@ -128,38 +367,33 @@ pub fn memory_init(boot_info: &MultibootInfo) {
 
 	{
 		let mut memblock = EARLY_PHYSICAL_ALLOCATOR.lock();
-		memblock.get_or_init(MemBlockAllocator::new);
-		memblock
-			.get_mut()
-			.expect("Failed to initialize memory block allocator.")
-			.init();
+		let memblock = memblock.get_or_insert_with(MemBlockAllocator::new);
+		memblock.init();
+		reserve_boot_data(memblock, boot_info);
 	}
 	log_debug!("Initialized Memblock",);
 
-	FRAME_ALLOCATOR.lock().get_or_init(FrameAllocator::new);
-	FRAME_ALLOCATOR
-		.lock()
-		.get_mut()
-		.expect("Frame Allocator not created yet")
-		.init();
+	let biggest_segment = G_SEGMENTS
+		.read()
+		.biggest_available()
+		.expect("No segment available")
+		.size();
 
-	log_debug!("Initialized Frame Allocator",);
-
-	let index =
-		get_biggest_available_segment_index().expect("No segment available");
-
-	let needed_nodes = G_SEGMENTS.lock()[index].size() / PAGE_SIZE;
+	// Page aligned, the reservation is mapped as the pool below
+	let needed_nodes = biggest_segment / PAGE_SIZE + VIRT_RANGE_NODES;
 	let pool_layout = Layout::from_size_align(
-		needed_nodes * size_of::<Node<usize>>(),
-		align_of::<Node<usize>>(),
+		needed_nodes * NODE_SLOT_SIZE,
+		align_of::<Node<usize>>().max(PAGE_SIZE),
 	)
 	.expect("Error while creating a layout");
 
+	// Reserved before the frame allocator takes the free regions, so its
+	// frames are not handed out again
 	let ptr = {
 		let mut memblock_guard = EARLY_PHYSICAL_ALLOCATOR.lock();
 		unsafe {
 			memblock_guard
-				.get_mut()
+				.as_mut()
 				.expect("MemBlock not available")
 				.alloc(pool_layout)
 		}
@@ -169,6 +403,13 @@ pub fn memory_init(boot_info: &MultibootInfo) {
 		panic!("Failed to allocate node pool from MemBlock");
 	}
 
+	FRAME_ALLOCATOR
+		.get_or_init(FrameAllocator::new)
+		.lock()
+		.init();
+
+	log_debug!("Initialized Frame Allocator",);
+
 	let kernel_end_addr = get_kernel_virtual_end();
 	let node_pool_virt_start = VirtAddr::new(NODE_POOL_VIRT_START);
 
@@ -185,23 +426,16 @@ pub fn memory_init(boot_info: &MultibootInfo) {
 		pool_layout.size()
 	);
 
-	let mut current_vaddr = node_pool_virt_start;
-	let end_vaddr = VirtAddr::new(NODE_POOL_VIRT_START + pool_layout.size());
-
-	while current_vaddr < end_vaddr {
-		let frame = FRAME_ALLOCATOR
-			.lock()
-			.get()
-			.expect("Frame Allocator does not exist")
-			.allocate_frame()
-			.expect("Failed to allocate frame for node pool");
-
-		map_page(frame, current_vaddr, flags::PRESENT | flags::WRITABLE);
-		current_vaddr = VirtAddr::new(current_vaddr.as_usize() + PAGE_SIZE);
-	}
-
 	let pool_base_addr: PhysAddr = (ptr as usize).into();
-	NODE_POOL_ALLOCATOR.lock().get_or_init(|| {
+	map_range(
+		pool_base_addr,
+		node_pool_virt_start,
+		pool_layout.size(),
+		flags::PRESENT | flags::WRITABLE,
+	)
+	.expect("Failed to map the node pool");
+
+	NODE_POOL_ALLOCATOR.get_or_init(|| {
 		NodePoolAllocator::new(node_pool_virt_start, needed_nodes)
 	});
 
@@ -213,7 +447,7 @@ pub fn memory_init(boot_info: &MultibootInfo) {
 	let base: PhysAddr = {
 		let guard = EARLY_PHYSICAL_ALLOCATOR.lock();
 		let memblock = guard
-			.get()
+			.as_ref()
 			.expect("Failed to get memblock from early allocator");
 
 		memblock
@@ -224,20 +458,20 @@ pub fn memory_init(boot_info: &MultibootInfo) {
 			.expect("No non-empty memory regions available")
 	};
 
-	BUDDY_PAGE_ALLOCATOR
-		.lock()
-		.get_or_init(|| BuddyAllocator::new(base));
+	BUDDY_PAGE_ALLOCATOR.get_or_init(|| BuddyAllocator::new(base));
 
 	log_debug!("Initialized Buddy Page Allocator",);
 
-	SLAB_CACHES
-		.lock()
-		.get_or_init(|| CACHE_SIZES.map(|size| SlabCache::new(size, 0)));
+	SLAB_CACHES.get_or_init(|| {
+		core::array::from_fn(|i| {
+			SlabCache::new(CACHE_NAMES[i], CACHE_SIZES[i], 1, 0)
+		})
+	});
 
 	log_debug!("Initialized Slab Caches",);
 
 	EARLY_PHYSICAL_ALLOCATOR.lock().take();
-	if EARLY_PHYSICAL_ALLOCATOR.lock().get().is_some() {
+	if EARLY_PHYSICAL_ALLOCATOR.lock().is_some() {
 		panic!(
 			"EARLY_PHYSICAL_ALLOCATOR (memblock) has not been decommissioned."
 		);
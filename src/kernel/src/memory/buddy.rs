@@ -1,30 +1,129 @@
 //! Implements a physical memory allocator using the buddy system algorithm.
 
 use super::{
-	allocator::EARLY_PHYSICAL_ALLOCATOR, memblock::MemRegion,
-	node_pool::NodeAllocatorWrapper, MemorySegment, PhysAddr, PAGE_SIZE,
+	addr::{align_down, align_up},
+	allocator::EARLY_PHYSICAL_ALLOCATOR,
+	memblock::MemRegion,
+	node_pool::NodeAllocatorWrapper,
+	zone::zone_range,
+	MemorySegment, PhysAddr, RegionType, PAGE_SIZE,
+};
+#[cfg(feature = "buddy-debug")]
+use super::{
+	paging::{flags, map_page, unmap_page_keep_table},
+	VirtAddr, BUDDY_POISON_PAGE,
 };
 use crate::{
-	arch::x86::multiboot::G_SEGMENTS, collections::linked_list::LinkedList,
-	memory::NodePoolAllocator, println_serial,
+	arch::x86::multiboot::G_SEGMENTS,
+	collections::{bitmap::Bitmap, linked_list::LinkedList},
+	log_error,
+	memory::NodePoolAllocator,
+	println_serial,
 };
-use core::{alloc::Layout, ptr};
+use core::{alloc::Layout, ops::Range, ptr};
+
+/// Number of free lists kept by the buddy allocator.
+pub const MAX_ORDERS: usize = 32;
+
+/// Marks a page that does not start a block.
+const NOT_ALLOCATED: u8 = u8::MAX;
+/// Set in the order record of a block that is on a free list.
+const FREE: u8 = 0x80;
+
+/// Fills free blocks in debug mode, only their first and last page for blocks
+/// bigger than [`POISON_FULL_LIMIT`].
+#[cfg(feature = "buddy-debug")]
+pub const POISON_FREE: u8 = 0xde;
+/// Blocks up to this size are poisoned and checked completely.
+#[cfg(feature = "buddy-debug")]
+const POISON_FULL_LIMIT: usize = 64 * 1024;
+
+/// A snapshot of the buddy allocator's counters, see
+/// [`BuddyAllocator::stats`].
+#[derive(Debug, Clone, Copy)]
+pub struct BuddyStats {
+	/// Number of bytes managed by the allocator.
+	pub total_bytes: usize,
+	/// Number of bytes currently on the free lists.
+	pub free_bytes: usize,
+	/// Size in bytes of an order 0 block.
+	pub min_block_size: usize,
+	/// Highest order the allocator hands out.
+	pub max_order: usize,
+	/// Number of free blocks per order.
+	pub free_blocks: [usize; MAX_ORDERS],
+}
 
-const MAX_ORDERS: usize = 32;
+/// A free list inconsistency found by [`BuddyAllocator::debug_check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BuddyCheckError {
+	/// A free block reaches outside the managed memory.
+	OutOfRange {
+		/// Start of the block.
+		addr: PhysAddr,
+		/// Order of the list holding it.
+		order: usize,
+	},
+	/// A free block does not start at a multiple of its size.
+	Misaligned {
+		/// Start of the block.
+		addr: PhysAddr,
+		/// Order of the list holding it.
+		order: usize,
+	},
+	/// A free block has pages marked allocated in the bitmap.
+	MarkedAllocated {
+		/// Start of the block.
+		addr: PhysAddr,
+		/// Order of the list holding it.
+		order: usize,
+	},
+	/// A free block's order record does not match the list holding it.
+	OrderMismatch {
+		/// Start of the block.
+		addr: PhysAddr,
+		/// Order of the list holding it.
+		order: usize,
+	},
+	/// Two free blocks share memory, or one block is listed twice.
+	Overlap {
+		/// Start of the first block.
+		addr: PhysAddr,
+		/// Order of the first block.
+		order: usize,
+		/// Start of the block it overlaps.
+		other: PhysAddr,
+		/// Order of the block it overlaps.
+		other_order: usize,
+	},
+}
 
 /// Manages physical memory allocation using a buddy system with power-of-two
 /// block sizes.
 ///
 /// Tracks free blocks using linked lists for each size order and a bitmap
 /// (`map`) to mark allocated/free status of the smallest block size
-/// (`min_block_size`).
+/// (`min_block_size`). The order of every block is recorded at its first
+/// page, so freeing never depends on the caller rounding its layout the same
+/// way and only blocks really on a free list are merged, which keeps the
+/// holes between memory regions out of the lists.
 pub struct BuddyAllocator {
 	base: PhysAddr,
+	/// Bytes from `base` to the end of the highest managed page.
 	size: usize,
+	/// Bytes in the free blocks the allocator was seeded with.
+	total_bytes: usize,
 	min_block_size: usize,
 	max_order: usize,
 	free_lists: [LinkedList<PhysAddr, NodeAllocatorWrapper>; MAX_ORDERS],
-	map: &'static mut [usize],
+	map: Bitmap,
+	/// Order of the block starting at each page, with [`FREE`] set while it
+	/// is on a free list, [`NOT_ALLOCATED`] for pages that start no block.
+	orders: &'static mut [u8],
+	/// Whether free blocks are poisoned, only for allocators over real
+	/// memory.
+	#[cfg(feature = "buddy-debug")]
+	poison: bool,
 }
 
 unsafe impl Send for BuddyAllocator {}
@@ -33,10 +132,10 @@ unsafe impl Sync for BuddyAllocator {}
 impl BuddyAllocator {
 	/// Creates and initializes a new `BuddyAllocator`.
 	///
-	/// Calculates the required size based on `G_SEGMENTS`, determines the
-	/// necessary orders, allocates memory for the internal tracking bitmap
-	/// using the `EARLY_PHYSICAL_ALLOCATOR`, and initializes the free lists
-	/// with the largest initial block(s).
+	/// Manages the `Available` segments of `G_SEGMENTS` from `base` up to the
+	/// end of the highest one, allocating the bitmap and the block order
+	/// records for that span from the `EARLY_PHYSICAL_ALLOCATOR`. See
+	/// [`BuddyAllocator::with_metadata`] for how the free lists are seeded.
 	///
 	/// # Arguments
 	///
@@ -52,25 +151,10 @@ impl BuddyAllocator {
 	pub fn new(base: PhysAddr) -> Self {
 		use core::mem::{align_of, size_of};
 
-		let mut size = 0;
-		for segment in G_SEGMENTS.lock().iter() {
-			size += segment.size();
-		}
-
-		size -= base.as_usize();
+		let segments = G_SEGMENTS.read().clone();
+		let blocks_count = Self::span(base, segments.as_slice()) / PAGE_SIZE;
 
-		let min_block_size = PAGE_SIZE;
-		let blocks_count = size / min_block_size;
-		let mut max_order = 0;
-		let mut temp = blocks_count;
-		while temp > 1 {
-			temp >>= 1;
-			max_order += 1;
-		}
-
-		let bitmap_bits = blocks_count;
-		let bitmap_bytes = bitmap_bits.div_ceil(8);
-		let bitmap_words = bitmap_bytes.div_ceil(size_of::<usize>());
+		let bitmap_words = Bitmap::words_for(blocks_count);
 		let bitmap_size = bitmap_words * size_of::<usize>();
 
 		let bitmap_layout =
@@ -80,7 +164,7 @@ impl BuddyAllocator {
 		let bitmap_ptr: *mut u8 = unsafe {
 			EARLY_PHYSICAL_ALLOCATOR
 				.lock()
-				.get_mut()
+				.as_mut()
 				.expect("Could not access early physical allocator")
 				.alloc(bitmap_layout)
 		};
@@ -95,29 +179,150 @@ impl BuddyAllocator {
 				bitmap_words,
 			)
 		};
-		for word in map.iter_mut() {
-			*word = 0;
+
+		let orders_layout = Layout::array::<u8>(blocks_count)
+			.expect("Error while creating the Buddy Order Layout");
+
+		let orders_ptr: *mut u8 = unsafe {
+			EARLY_PHYSICAL_ALLOCATOR
+				.lock()
+				.as_mut()
+				.expect("Could not access early physical allocator")
+				.alloc(orders_layout)
+		};
+
+		if orders_ptr.is_null() && blocks_count > 0 {
+			panic!("Failed to allocate memory for buddy allocator orders");
 		}
 
-		const EMPTY_LIST: LinkedList<PhysAddr, NodeAllocatorWrapper> =
-			LinkedList::new_in(NodeAllocatorWrapper);
-		let mut free_lists = [EMPTY_LIST; MAX_ORDERS];
+		let orders = match blocks_count {
+			0 => &mut [],
+			_ => unsafe {
+				core::slice::from_raw_parts_mut(orders_ptr, blocks_count)
+			},
+		};
 
-		println_serial!("BuddyAllocator::new: Initializing with base 0x{:x}, size {}, min_block_size {}, max_order {}",
-            base.as_usize(), size, min_block_size, max_order);
+		let allocator =
+			Self::with_metadata(base, segments.as_slice(), map, orders);
+		#[cfg(feature = "buddy-debug")]
+		let allocator = allocator.with_poison();
+
+		return allocator;
+	}
+
+	/// Returns the number of bytes from `base` to the page aligned end of the
+	/// highest `Available` segment in `segments`, the span a
+	/// `BuddyAllocator` over them keeps metadata for.
+	pub fn span(base: PhysAddr, segments: &[MemorySegment]) -> usize {
+		return segments
+			.iter()
+			.filter(|segment| segment.segment_type() == RegionType::Available)
+			.map(|segment| {
+				align_down(
+					segment.start_addr().as_usize() + segment.size(),
+					PAGE_SIZE,
+				)
+			})
+			.max()
+			.map_or(0, |end| end.saturating_sub(base.as_usize()));
+	}
+
+	/// Creates a `BuddyAllocator` over the `Available` parts of `segments` at
+	/// or above `base`, keeping its bitmap in `map` and the block orders in
+	/// `orders`.
+	///
+	/// Each region is split into the largest blocks that fit it and are
+	/// aligned to their size, so holes between regions never reach a free
+	/// list. `map` needs a bit and `orders` a byte for every page of
+	/// [`BuddyAllocator::span`].
+	///
+	/// # Panics
+	///
+	/// Panics if `map` or `orders` is too small for the span.
+	pub fn with_metadata(
+		base: PhysAddr,
+		segments: &[MemorySegment],
+		map: &'static mut [usize],
+		orders: &'static mut [u8],
+	) -> Self {
+		let size = Self::span(base, segments);
+		let min_block_size = PAGE_SIZE;
+		let blocks_count = size / min_block_size;
 
-		if blocks_count > 0 {
-			free_lists[max_order].push_back(base);
-			println_serial!("BuddyAllocator::new: Added initial block 0x{:x} to free_lists[{}]", base.as_usize(), max_order);
+		assert!(
+			map.len() >= Bitmap::words_for(blocks_count),
+			"Buddy bitmap too small"
+		);
+		assert!(
+			orders.len() >= blocks_count,
+			"Buddy order records too small"
+		);
+
+		let mut max_order = 0;
+		while max_order + 1 < MAX_ORDERS && 2 << max_order <= blocks_count {
+			max_order += 1;
 		}
 
-		Self {
+		// Pages outside the free blocks, the holes included, stay allocated.
+		let mut map = Bitmap::new(map, blocks_count);
+		map.fill(true);
+		orders.fill(NOT_ALLOCATED);
+
+		const EMPTY_LIST: LinkedList<PhysAddr, NodeAllocatorWrapper> =
+			LinkedList::new_in(NodeAllocatorWrapper);
+
+		let mut allocator = Self {
 			base,
 			size,
+			total_bytes: 0,
 			min_block_size,
 			max_order,
-			free_lists,
+			free_lists: [EMPTY_LIST; MAX_ORDERS],
 			map,
+			orders,
+			#[cfg(feature = "buddy-debug")]
+			poison: false,
+		};
+
+		println_serial!("BuddyAllocator::new: Initializing with base 0x{:x}, span {}, min_block_size {}, max_order {}",
+            base.as_usize(), size, min_block_size, max_order);
+
+		for segment in segments {
+			if segment.segment_type() != RegionType::Available {
+				continue;
+			}
+
+			let start = segment.start_addr().as_usize().max(base.as_usize());
+			let end = segment.start_addr().as_usize() + segment.size();
+			allocator.add_region(
+				PhysAddr::new(align_up(start, PAGE_SIZE)),
+				PhysAddr::new(align_down(end, PAGE_SIZE)),
+			);
+		}
+
+		return allocator;
+	}
+
+	/// Puts `[start, end)` on the free lists as the largest blocks that fit,
+	/// each aligned to its size relative to `base`.
+	fn add_region(&mut self, start: PhysAddr, end: PhysAddr) {
+		let mut addr = start;
+
+		while addr < end {
+			let offset = addr - self.base;
+			let mut order = 0;
+			while order < self.max_order
+				&& offset % (self.min_block_size << (order + 1)) == 0
+				&& (self.min_block_size << (order + 1)) <= end - addr
+			{
+				order += 1;
+			}
+
+			self.push_free(addr, order);
+			self.total_bytes += self.min_block_size << order;
+			println_serial!("BuddyAllocator::new: Added initial block 0x{:x} to free_lists[{}]", addr.as_usize(), order);
+
+			addr = addr + (self.min_block_size << order);
 		}
 	}
 
@@ -149,90 +354,258 @@ impl BuddyAllocator {
 		}
 	}
 
+	/// Allocates a block like [`BuddyAllocator::alloc`], but only from the
+	/// physical memory of `zones`, a mask of the [`zone`](super::zone)
+	/// flags.
+	///
+	/// Returns null if `zones` names no zone or no free block within them
+	/// fits `layout`.
+	///
+	/// # Safety
+	///
+	/// As for [`BuddyAllocator::alloc`].
+	pub unsafe fn alloc_in_zone(
+		&mut self,
+		layout: Layout,
+		zones: u32,
+	) -> *mut u8 {
+		let block = zone_range(zones)
+			.and_then(|range| self.find_free_block_in(layout, range));
+
+		match block {
+			Some(addr) => return addr.as_mut_ptr(),
+			None => return ptr::null_mut(),
+		}
+	}
+
 	/// Deallocates a previously allocated block of physical memory.
 	///
-	/// Marks the block associated with `ptr` and `layout` as free in the
-	/// bitmap. Attempts to merge the freed block with its buddy if the buddy
-	/// is also free, repeating the merge process for larger blocks if
+	/// Marks the block at `ptr` as free in the bitmap, using the order it was
+	/// allocated with. Attempts to merge the freed block with its buddy if the
+	/// buddy is also free, repeating the merge process for larger blocks if
 	/// possible. The resulting free block (original or merged) is added to the
 	/// appropriate free list.
 	///
+	/// # Panics
+	///
+	/// Panics if `ptr` does not start an allocated block, which catches double
+	/// frees, or if `layout` is larger than the block.
+	///
 	/// # Safety
 	///
 	/// The caller *must* ensure that `ptr` was previously returned by a call to
-	/// `alloc` on *this* allocator instance with the *exact same* `layout`.
-	/// Deallocating with an incorrect `layout`, freeing the same block twice,
-	/// or freeing a pointer not allocated by this allocator results in
-	/// undefined behavior.
+	/// `alloc` on *this* allocator instance, and that nothing uses the block
+	/// afterwards.
 	pub unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
 		let addr: PhysAddr = (ptr as usize).into();
 		println_serial!("BuddyAllocator::dealloc: Deallocating physical address 0x{:x} with size {}, align {}", addr.as_usize(), layout.size(), layout.align());
 
 		let i = self.get_block_index(addr);
-		let required_size = layout.size().max(layout.align());
+		let order = match self.orders.get(i) {
+			Some(&order) if order & FREE == 0 => order as usize,
+			_ => panic!(
+				"BuddyAllocator::dealloc: 0x{:x} is not an allocated block",
+				addr.as_usize()
+			),
+		};
 
-		let mut order = 0;
-		while self.min_block_size * (1 << order) < required_size {
-			order += 1;
-			if order >= MAX_ORDERS {
-				panic!("Layout implies an order larger than MAX_ORDERS during dealloc");
-			}
-		}
-		println_serial!("BuddyAllocator::dealloc: Calculated initial order {} for deallocation", order);
+		let required_size = layout.size().max(layout.align());
+		assert!(
+			required_size <= self.min_block_size << order,
+			"BuddyAllocator::dealloc: layout of {} bytes does not fit the order {} block at 0x{:x}",
+			required_size,
+			order,
+			addr.as_usize()
+		);
+		self.orders[i] = NOT_ALLOCATED;
 
 		self.mark_free(i, order);
+		#[cfg(feature = "buddy-debug")]
+		self.poison_block(addr, order);
 		println_serial!("BuddyAllocator::dealloc: Marked index {} (addr 0x{:x}) as free in bitmap at order {}", i, addr.as_usize(), order);
 
 		let mut current_addr = addr;
 		let mut current_order = order;
 
-		while current_order < MAX_ORDERS - 1 {
+		while current_order < self.max_order {
 			let buddy_addr = self.find_buddy_addr(current_addr, current_order);
 			let buddy_index = self.get_block_index(buddy_addr);
 
 			println_serial!("BuddyAllocator::dealloc: Checking merge for block 0x{:x} (order {}). Buddy is 0x{:x} (index {})", current_addr.as_usize(), current_order, buddy_addr.as_usize(), buddy_index);
 
-			if !self.is_free(buddy_index, current_order) {
+			if self.orders.get(buddy_index)
+				!= Some(&(FREE | current_order as u8))
+			{
 				println_serial!("BuddyAllocator::dealloc: Buddy 0x{:x} (order {}) not free or mergable. Stopping merge.", buddy_addr.as_usize(), current_order);
 				break;
 			}
 
 			println_serial!("BuddyAllocator::dealloc: Merging block 0x{:x} (order {}) with buddy 0x{:x}", current_addr.as_usize(), current_order, buddy_addr.as_usize());
 
-			self.remove_from_free_list(buddy_addr, current_order);
+			// Not merging with a buddy the lists do not agree is free
+			if !self.remove_from_free_list(buddy_addr, current_order) {
+				break;
+			}
+			self.orders[buddy_index] = NOT_ALLOCATED;
 
 			current_addr = current_addr.min(buddy_addr);
 			current_order += 1;
 		}
 
 		println_serial!("BuddyAllocator::dealloc: Final merged block 0x{:x} added to free_lists[{}]", current_addr.as_usize(), current_order);
-		self.free_lists[current_order].push_back(current_addr);
+		self.push_free(current_addr, current_order);
 	}
 
-	/// Panics on error
-	fn remove_from_free_list(&mut self, addr: PhysAddr, order: usize) {
-		if order >= MAX_ORDERS {
-			panic!("Invalid order {} provided to remove_from_free_list", order);
+	/// Returns the physical address of the first byte the allocator manages.
+	pub fn base(&self) -> PhysAddr {
+		return self.base;
+	}
+
+	/// Returns a snapshot of the allocator's counters.
+	///
+	/// Only reads the free list lengths, so it is cheap and never allocates.
+	pub fn stats(&self) -> BuddyStats {
+		let mut free_blocks = [0; MAX_ORDERS];
+		let mut free_bytes = 0;
+
+		for (order, list) in self.free_lists.iter().enumerate() {
+			free_blocks[order] = list.len();
+			free_bytes += list.len() * (self.min_block_size << order);
 		}
 
-		let list = &mut self.free_lists[order];
+		return BuddyStats {
+			total_bytes: self.total_bytes,
+			free_bytes,
+			min_block_size: self.min_block_size,
+			max_order: self.max_order,
+			free_blocks,
+		};
+	}
 
-		let mut cursor = list.cursor_front_mut();
+	/// Walks every free list and checks that each block lies in the managed
+	/// memory, is aligned to its order, is marked free in the bitmap and in
+	/// its order record, and overlaps no other free block.
+	///
+	/// The overlap check compares every pair of free blocks, so this is meant
+	/// for tests and debugging, not for hot paths.
+	pub fn debug_check(&self) -> Result<(), BuddyCheckError> {
+		for (order, list) in self.free_lists.iter().enumerate() {
+			for &addr in list {
+				self.check_free_block(addr, order)?;
+				self.check_no_overlap(addr, order)?;
+			}
+		}
 
-		while cursor.current().is_some() {
-			if let Some(value_ref) = cursor.current() {
-				if *value_ref == addr {
-					cursor.remove_current();
-					return;
+		return Ok(());
+	}
+
+	fn check_free_block(
+		&self,
+		addr: PhysAddr,
+		order: usize,
+	) -> Result<(), BuddyCheckError> {
+		let block_size = self.min_block_size << order;
+		let in_range = addr >= self.base
+			&& (addr - self.base)
+				.checked_add(block_size)
+				.is_some_and(|end| end <= self.size);
+
+		if !in_range {
+			return Err(BuddyCheckError::OutOfRange {
+				addr,
+				order,
+			});
+		}
+
+		if (addr - self.base) % block_size != 0 {
+			return Err(BuddyCheckError::Misaligned {
+				addr,
+				order,
+			});
+		}
+
+		let i = self.get_block_index(addr);
+		if !self.is_free(i, order) {
+			return Err(BuddyCheckError::MarkedAllocated {
+				addr,
+				order,
+			});
+		}
+
+		if self.orders[i] != FREE | order as u8 {
+			return Err(BuddyCheckError::OrderMismatch {
+				addr,
+				order,
+			});
+		}
+
+		return Ok(());
+	}
+
+	/// Compares the free block at `addr` with every other free block. The
+	/// block itself is skipped the first time it is met, so a block listed
+	/// twice counts as overlapping.
+	fn check_no_overlap(
+		&self,
+		addr: PhysAddr,
+		order: usize,
+	) -> Result<(), BuddyCheckError> {
+		let end = addr + (self.min_block_size << order);
+		let mut skipped_self = false;
+
+		for (other_order, list) in self.free_lists.iter().enumerate() {
+			for &other in list {
+				if other == addr && other_order == order && !skipped_self {
+					skipped_self = true;
+					continue;
+				}
+
+				let other_end = other + (self.min_block_size << other_order);
+				if addr < other_end && other < end {
+					return Err(BuddyCheckError::Overlap {
+						addr,
+						order,
+						other,
+						other_order,
+					});
 				}
 			}
-			cursor.move_next();
 		}
 
-		panic!(
-        "Failed to remove address {:#x} from free_list[{}]: address not found in list!",
-        addr.as_usize(), order
-    );
+		return Ok(());
+	}
+
+	/// Adds the block at `addr` to the free list of `order` and records it as
+	/// free, making it a merge candidate for its buddy.
+	fn push_free(&mut self, addr: PhysAddr, order: usize) {
+		let i = self.get_block_index(addr);
+
+		self.orders[i] = FREE | order as u8;
+		self.free_lists[order].push_back(addr);
+	}
+
+	/// Removes the block at `addr` from the free list of `order`. A missing
+	/// block is an accounting bug, it is logged and reported as `false`.
+	///
+	/// # Panics
+	///
+	/// Panics if `order` is out of range.
+	fn remove_from_free_list(&mut self, addr: PhysAddr, order: usize) -> bool {
+		if order >= MAX_ORDERS {
+			panic!("Invalid order {} provided to remove_from_free_list", order);
+		}
+
+		if self.free_lists[order].remove_first(&addr) {
+			return true;
+		}
+
+		log_error!(
+			"BuddyAllocator: block 0x{:x} missing from free_list[{}]",
+			addr.as_usize(),
+			order
+		);
+		debug_assert!(false, "buddy block missing from its free list");
+		return false;
 	}
 
 	#[inline(always)]
@@ -269,18 +642,158 @@ impl BuddyAllocator {
 
 		let block_addr = self.free_lists[k].pop_back()?;
 
-		while k > required_order {
-			let buddy_offset = self.min_block_size * (1 << (k - 1));
-			let buddy_addr = block_addr + buddy_offset;
+		#[cfg(feature = "buddy-debug")]
+		self.report_poison(block_addr, k);
+
+		self.split(block_addr, k, block_addr, required_order);
+
+		Some(block_addr)
+	}
+
+	/// Finds a free block of memory of the requested size that lies within
+	/// `range`, taking it out of a bigger free block that reaches into
+	/// `range` if needed.
+	fn find_free_block_in(
+		&mut self,
+		layout: Layout,
+		range: Range<PhysAddr>,
+	) -> Option<PhysAddr> {
+		let required_size = layout.size().max(layout.align());
+		let required_order = (0..MAX_ORDERS)
+			.find(|&k| self.min_block_size << k >= required_size)?;
+		let size = self.min_block_size << required_order;
+		let base = self.base.as_usize();
+
+		for k in required_order..MAX_ORDERS {
+			let block_size = self.min_block_size << k;
+			let mut found = None;
+
+			let mut cursor = self.free_lists[k].cursor_front_mut();
+			while let Some(&mut block) = cursor.current() {
+				// Aligned from the base like every block, so it can be split
+				// off
+				let low = range.start.max(block).as_usize();
+				let target = base + (low - base).next_multiple_of(size);
+				let end = (block.as_usize().saturating_add(block_size))
+					.min(range.end.as_usize());
+
+				if target.checked_add(size).is_some_and(|t| t <= end) {
+					cursor.remove_current();
+					found = Some((block, PhysAddr::new(target)));
+					break;
+				}
+				cursor.move_next();
+			}
+
+			if let Some((block, target)) = found {
+				#[cfg(feature = "buddy-debug")]
+				self.report_poison(block, k);
 
-			self.free_lists[k - 1].push_back(buddy_addr);
+				self.split(block, k, target, required_order);
+				return Some(target);
+			}
+		}
+
+		return None;
+	}
 
+	/// Splits the free block of order `k` at `block`, which is off the free
+	/// lists, down to the block of `order` at `target` and marks that one
+	/// allocated. The other halves go back on the free lists.
+	fn split(
+		&mut self,
+		mut block: PhysAddr,
+		mut k: usize,
+		target: PhysAddr,
+		order: usize,
+	) {
+		while k > order {
 			k -= 1;
+			let upper = block + (self.min_block_size << k);
+			let (keep, other) = match target >= upper {
+				true => (upper, block),
+				false => (block, upper),
+			};
+
+			// Only the ends of the split block were poisoned if it was big
+			#[cfg(feature = "buddy-debug")]
+			self.poison_block(other, k);
+			self.push_free(other, k);
+
+			block = keep;
 		}
 
-		self.mark_allocated(block_addr, required_order);
+		self.mark_allocated(target, order);
+		let i = self.get_block_index(target);
+		self.orders[i] = order as u8;
+	}
 
-		Some(block_addr)
+	/// Logs it if the free block of order `k` at `addr` about to be handed
+	/// out lost its poison.
+	#[cfg(feature = "buddy-debug")]
+	fn report_poison(&self, addr: PhysAddr, k: usize) {
+		if let Err(offset) = self.check_poison(addr, k) {
+			log_error!(
+				"BuddyAllocator: order {} block 0x{:x} written after free, first change at offset {:#x}",
+				k,
+				addr.as_usize(),
+				offset
+			);
+		}
+	}
+
+	/// Poisons every block on the free lists and turns poisoning on.
+	#[cfg(feature = "buddy-debug")]
+	fn with_poison(mut self) -> Self {
+		for (order, list) in self.free_lists.iter().enumerate() {
+			for &addr in list {
+				Self::poison_pages(addr, self.min_block_size << order);
+			}
+		}
+
+		self.poison = true;
+		return self;
+	}
+
+	/// Fills the free block of `order` at `addr` with [`POISON_FREE`].
+	#[cfg(feature = "buddy-debug")]
+	fn poison_block(&self, addr: PhysAddr, order: usize) {
+		if self.poison {
+			Self::poison_pages(addr, self.min_block_size << order);
+		}
+	}
+
+	/// Checks that the free block of `order` at `addr` still holds its
+	/// poison, which the allocator does before handing a block out. Returns
+	/// the offset of the first changed byte otherwise.
+	#[cfg(feature = "buddy-debug")]
+	pub fn check_poison(
+		&self,
+		addr: PhysAddr,
+		order: usize,
+	) -> Result<(), usize> {
+		if !self.poison {
+			return Ok(());
+		}
+
+		for offset in poisoned_pages(self.min_block_size << order) {
+			let changed = with_page(addr + offset, |page| {
+				return page.iter().position(|&byte| byte != POISON_FREE);
+			});
+			if let Some(changed) = changed {
+				return Err(offset + changed);
+			}
+		}
+
+		return Ok(());
+	}
+
+	// Helper to poison the pages of a `size` byte block that are checked
+	#[cfg(feature = "buddy-debug")]
+	fn poison_pages(addr: PhysAddr, size: usize) {
+		for offset in poisoned_pages(size) {
+			with_page(addr + offset, |page| page.fill(POISON_FREE));
+		}
 	}
 
 	#[inline(always)]
@@ -293,54 +806,62 @@ impl BuddyAllocator {
 
 		let blocks_to_mark = 1 << order;
 
-		for j in 0..blocks_to_mark {
-			let current = i + j;
-			let word = current / (usize::BITS as usize);
-			let bit_pos = current % (usize::BITS as usize);
-			let mask = 1 << bit_pos;
-
-			if word >= self.map.len() {
-				panic!("Allocation address out of map bounds");
-			}
+		if i + blocks_to_mark > self.map.len() {
+			panic!("Allocation address out of map bounds");
+		}
 
-			println_serial!("BuddyAllocator::mark_allocated: Marking index {} (addr 0x{:x}) to index {} as allocated (order {})",
+		println_serial!("BuddyAllocator::mark_allocated: Marking index {} (addr 0x{:x}) to index {} as allocated (order {})",
               i, addr.as_usize(), i + blocks_to_mark - 1, order);
 
-			self.map[word] |= mask;
-		}
+		self.map.set_range(i..i + blocks_to_mark);
 	}
 
 	fn mark_free(&mut self, i: usize, order: usize) {
 		let blocks_to_mark = 1 << order;
 
-		for j in 0..blocks_to_mark {
-			let current = i + j;
-			let word = current / (usize::BITS as usize);
-			let bit_pos = current % (usize::BITS as usize);
-			let mask = 1 << bit_pos;
-
-			if word >= self.map.len() {
-				panic!("Allocation address out of map bounds");
-			}
-
-			self.map[word] &= !mask;
+		if i + blocks_to_mark > self.map.len() {
+			panic!("Allocation address out of map bounds");
 		}
+
+		self.map.clear_range(i..i + blocks_to_mark);
 	}
 
 	fn is_free(&self, i: usize, order: usize) -> bool {
 		let blocks_to_check = 1 << order;
 
-		for j in 0..blocks_to_check {
-			let current = i + j;
-			let word = current / (usize::BITS as usize);
-			let bit_pos = current % (usize::BITS as usize);
-			let mask = 1 << bit_pos;
+		i + blocks_to_check <= self.map.len()
+			&& self.map.is_range_clear(i..i + blocks_to_check)
+	}
+}
 
-			if word >= self.map.len() || self.map[word] & mask != 0 {
-				return false;
-			}
-		}
+/// Returns the offsets of the pages poisoned in a free block of `size`
+/// bytes, all of them up to [`POISON_FULL_LIMIT`] and the first and last
+/// beyond, which keeps freeing big blocks cheap.
+#[cfg(feature = "buddy-debug")]
+fn poisoned_pages(size: usize) -> impl Iterator<Item = usize> {
+	let (head, tail) = match size > POISON_FULL_LIMIT {
+		true => (PAGE_SIZE, size - PAGE_SIZE),
+		false => (size, size),
+	};
+
+	return (0..head)
+		.step_by(PAGE_SIZE)
+		.chain((tail..size).step_by(PAGE_SIZE));
+}
 
-		true
-	}
+/// Maps the physical page `page` at [`BUDDY_POISON_PAGE`] while `f` runs. The
+/// buddy lock keeps the page to one user at a time. The slot's page table
+/// stays, so only its entry changes from one page to the next.
+#[cfg(feature = "buddy-debug")]
+fn with_page<R>(
+	page: PhysAddr,
+	f: impl FnOnce(&mut [u8; PAGE_SIZE]) -> R,
+) -> R {
+	let virt = VirtAddr::new(BUDDY_POISON_PAGE);
+
+	map_page(page, virt, flags::PRESENT | flags::WRITABLE);
+	let result = f(unsafe { &mut *virt.as_mut_ptr() });
+	unmap_page_keep_table(virt);
+
+	return result;
 }
@@ -1,34 +1,115 @@
 use super::{
 	allocator::EARLY_PHYSICAL_ALLOCATOR, get_kernel_physical_end,
-	get_kernel_physical_start, MemorySegment, PhysAddr, KERNEL_OFFSET,
-	PAGE_SIZE,
+	get_kernel_physical_start, zone::DMA_ZONE_END, MemBlockAllocator,
+	MemorySegment, PhysAddr, KERNEL_OFFSET, PAGE_SIZE,
+};
+use crate::{
+	collections::bitmap::Bitmap,
+	log_warn,
+	sync::{Global, Mutex},
 };
-use crate::{log_warn, sync::Mutex};
 use core::{
-	cell::OnceCell,
+	alloc::Layout,
+	ops::Range,
+	ptr,
 	sync::atomic::{AtomicUsize, Ordering},
 	usize,
 };
 
 const TOTAL_FRAMES: usize = usize::MAX / PAGE_SIZE + 1;
-const BITMAP_ENTRY_SIZE_BITS: usize = u64::BITS as usize;
-const BITMAP_ARRAY_SIZE: usize =
-	(TOTAL_FRAMES + BITMAP_ENTRY_SIZE_BITS - 1) / BITMAP_ENTRY_SIZE_BITS;
+const FRAME_WORDS_LEN: usize = Bitmap::words_for(TOTAL_FRAMES);
+
+pub static FRAME_ALLOCATOR: Global<FrameAllocator> =
+	Global::named("FRAME_ALLOCATOR");
+
+/// One bit per frame, set while the frame is used. Empty until
+/// [`FrameAllocator::init`] puts it over `FRAME_WORDS`.
+static FRAME_BITMAP: Mutex<Bitmap> =
+	Mutex::named("FRAME_BITMAP", Bitmap::empty());
+
+/// Storage of `FRAME_BITMAP`, every frame starts used.
+static mut FRAME_WORDS: [usize; FRAME_WORDS_LEN] =
+	[usize::MAX; FRAME_WORDS_LEN];
+
+/// Number of owners of every frame up to the highest usable one, parallel to
+/// the bitmap. Allocated from memblock by [`FrameAllocator::init`]. Always
+/// locked after `FRAME_BITMAP`.
+static FRAME_REFCOUNTS: Mutex<&'static mut [u16]> =
+	Mutex::named("FRAME_REFCOUNTS", &mut []);
+
+/// Frames below [`DMA_ZONE_END`] set aside by [`FrameAllocator::init`] that
+/// only [`FrameAllocator::allocate_frame_in`] hands out, once no other frame
+/// in the asked range is free. Always locked after `FRAME_BITMAP`.
+static DMA_RESERVE: Mutex<DmaReserve> = Mutex::named(
+	"DMA_RESERVE",
+	DmaReserve {
+		start: 0,
+		free: 0,
+	},
+);
+
+/// Number of frames in the DMA reserve, one per bit of its free mask.
+pub const DMA_RESERVE_FRAMES: usize = u64::BITS as usize;
+
+/// The frames of the DMA reserve. They stay marked used in the bitmap.
+struct DmaReserve {
+	/// First frame of the reserve.
+	start: usize,
+	/// Bit `n` is set while frame `start + n` is free.
+	free: u64,
+}
+
+impl DmaReserve {
+	/// Returns whether `frame_idx` belongs to the reserve.
+	fn contains(&self, frame_idx: usize) -> bool {
+		return (self.start..self.start + DMA_RESERVE_FRAMES)
+			.contains(&frame_idx)
+			&& self.start != 0;
+	}
 
-pub static FRAME_ALLOCATOR: Mutex<OnceCell<FrameAllocator>> =
-	Mutex::new(OnceCell::new());
+	/// Takes the first free frame of the reserve within `frames`.
+	fn take(&mut self, frames: &Range<usize>) -> Option<usize> {
+		let bit = (0..DMA_RESERVE_FRAMES).find(|&bit| {
+			return self.free & (1 << bit) != 0
+				&& frames.contains(&(self.start + bit));
+		})?;
 
-static FRAME_BITMAP: Mutex<[u64; BITMAP_ARRAY_SIZE]> =
-	Mutex::new([u64::MAX; BITMAP_ARRAY_SIZE]);
+		self.free &= !(1 << bit);
+		return Some(self.start + bit);
+	}
+
+	/// Returns the number of free frames of the reserve within `frames`.
+	fn free_in(&self, frames: &Range<usize>) -> usize {
+		return (0..DMA_RESERVE_FRAMES)
+			.filter(|&bit| {
+				return self.free & (1 << bit) != 0
+					&& frames.contains(&(self.start + bit));
+			})
+			.count();
+	}
+}
+
+/// A snapshot of the frame allocator's usage, see [`FrameAllocator::stats`].
+#[derive(Debug, Clone, Copy)]
+pub struct FrameStats {
+	/// Number of frames that were usable after initialization.
+	pub total_frames: usize,
+	/// Number of those frames that are currently handed out.
+	pub used_frames: usize,
+	/// Number of frames that can still be allocated.
+	pub free_frames: usize,
+}
 
 pub struct FrameAllocator {
 	next_free_idx: AtomicUsize,
+	total_frames: AtomicUsize,
 }
 
 impl FrameAllocator {
 	pub const fn new() -> Self {
 		Self {
 			next_free_idx: AtomicUsize::new(0),
+			total_frames: AtomicUsize::new(0),
 		}
 	}
 
@@ -37,11 +118,29 @@ impl FrameAllocator {
 	/// MUST be called only once during kernel initialization.
 	pub fn init(&self) {
 		let mut bitmap = FRAME_BITMAP.lock();
-		let guard = EARLY_PHYSICAL_ALLOCATOR.lock();
-		let regions = guard
-			.get()
-			.expect("Memblock has not been initialized")
-			.mem_region();
+		// Safety: the only reference to the words, init runs once.
+		*bitmap = Bitmap::new(
+			unsafe { &mut *ptr::addr_of_mut!(FRAME_WORDS) },
+			TOTAL_FRAMES,
+		);
+		let mut guard = EARLY_PHYSICAL_ALLOCATOR.lock();
+		let memblock =
+			guard.as_mut().expect("Memblock has not been initialized");
+
+		// Taken first, so its frames are no longer an available region below
+		let tracked_frames = memblock
+			.mem_region()
+			.iter()
+			.map(|region| {
+				(region.base() + region.size()).as_usize() / PAGE_SIZE
+			})
+			.max()
+			.unwrap_or(0)
+			.min(TOTAL_FRAMES);
+		*FRAME_REFCOUNTS.lock() =
+			Self::alloc_refcounts(memblock, tracked_frames);
+
+		let regions = memblock.mem_region();
 
 		for region in regions.iter() {
 			let start_addr = region.base();
@@ -51,31 +150,92 @@ impl FrameAllocator {
 				(start_addr.as_usize() + PAGE_SIZE - 1) / PAGE_SIZE;
 			let last_frame_idx = end_addr.as_usize() / PAGE_SIZE;
 
-			for frame_idx in first_frame_idx..last_frame_idx {
-				if frame_idx < TOTAL_FRAMES {
-					let entry_idx = frame_idx / BITMAP_ENTRY_SIZE_BITS;
-					let bit_idx = frame_idx % BITMAP_ENTRY_SIZE_BITS;
-					bitmap[entry_idx] &= !(1 << bit_idx);
-				}
-			}
+			bitmap.clear_range(first_frame_idx..last_frame_idx);
 		}
 
 		let kernel_start_frame =
 			get_kernel_physical_start().as_usize() / PAGE_SIZE;
 		let kernel_end_frame =
 			(get_kernel_physical_end().as_usize() + PAGE_SIZE - 1) / PAGE_SIZE;
-		self.mark_range_used(&mut bitmap, kernel_start_frame, kernel_end_frame);
+		bitmap.set_range(kernel_start_frame..kernel_end_frame);
 
-		let bitmap_virt_addr = bitmap.as_ptr() as usize;
+		let bitmap_virt_addr = ptr::addr_of!(FRAME_WORDS) as usize;
 		let bitmap_phys_addr = bitmap_virt_addr
 			.checked_sub(KERNEL_OFFSET)
 			.expect("Failed to calculate bitmap physical address");
-		let bitmap_size_bytes = BITMAP_ARRAY_SIZE * size_of::<u64>();
+		let bitmap_size_bytes = FRAME_WORDS_LEN * size_of::<usize>();
 
 		let bitmap_start_frame = bitmap_phys_addr / PAGE_SIZE;
 		let bitmap_end_frame =
 			(bitmap_phys_addr + bitmap_size_bytes + PAGE_SIZE - 1) / PAGE_SIZE;
-		self.mark_range_used(&mut bitmap, bitmap_start_frame, bitmap_end_frame);
+		bitmap.set_range(bitmap_start_frame..bitmap_end_frame);
+		Self::reserve_dma(&mut bitmap);
+
+		self.total_frames
+			.store(Self::count_free(&bitmap), Ordering::Relaxed);
+	}
+
+	// Helper to set aside the highest free run of frames below the DMA zone
+	// end as the DMA reserve, the frames general allocations reach last
+	fn reserve_dma(bitmap: &mut Bitmap) {
+		let runs = DMA_ZONE_END.as_usize() / PAGE_SIZE / DMA_RESERVE_FRAMES;
+
+		// Run 0 is left out, a reserve starting at frame 0 means none
+		let start = (1..runs).rev().map(|run| run * DMA_RESERVE_FRAMES).find(
+			|&start| {
+				return bitmap
+					.is_range_clear(start..start + DMA_RESERVE_FRAMES);
+			},
+		);
+
+		match start {
+			Some(start) => {
+				bitmap.set_range(start..start + DMA_RESERVE_FRAMES);
+				*DMA_RESERVE.lock() = DmaReserve {
+					start,
+					free: u64::MAX,
+				};
+			}
+			None => log_warn!("No free low memory left for the DMA reserve"),
+		}
+	}
+
+	// Helper to allocate the zeroed refcount array for `count` frames
+	#[allow(clippy::expect_used)]
+	fn alloc_refcounts(
+		memblock: &mut MemBlockAllocator,
+		count: usize,
+	) -> &'static mut [u16] {
+		if count == 0 {
+			return &mut [];
+		}
+
+		let layout = Layout::array::<u16>(count)
+			.expect("Error while creating the frame refcount layout");
+		let ptr = unsafe { memblock.alloc(layout) } as *mut u16;
+		if ptr.is_null() {
+			panic!("Failed to allocate the frame refcounts from MemBlock");
+		}
+
+		unsafe {
+			ptr.write_bytes(0, count);
+			return core::slice::from_raw_parts_mut(ptr, count);
+		}
+	}
+
+	/// Returns the current frame usage, derived from the bitmap.
+	///
+	/// Walks the bitmap without allocating, so it is safe to call while
+	/// memory is low.
+	pub fn stats(&self) -> FrameStats {
+		let free_frames = Self::count_free(&FRAME_BITMAP.lock());
+		let total_frames = self.total_frames.load(Ordering::Relaxed);
+
+		return FrameStats {
+			total_frames,
+			used_frames: total_frames.saturating_sub(free_frames),
+			free_frames,
+		};
 	}
 
 	/// Allocates a single physical frame.
@@ -83,73 +243,201 @@ impl FrameAllocator {
 		let mut bitmap = FRAME_BITMAP.lock();
 		let start_idx = self.next_free_idx.load(Ordering::Relaxed);
 
-		for entry_idx in start_idx..BITMAP_ARRAY_SIZE {
-			if bitmap[entry_idx] != u64::MAX {
-				for bit_idx in 0..BITMAP_ENTRY_SIZE_BITS {
-					let mask = 1 << bit_idx;
-					if (bitmap[entry_idx] & mask) == 0 {
-						let frame_idx =
-							entry_idx * BITMAP_ENTRY_SIZE_BITS + bit_idx;
+		let frame_idx = bitmap.find_first_zero_in(start_idx..TOTAL_FRAMES)?;
+		bitmap.set(frame_idx);
+		Self::set_refcount(frame_idx, 1);
 
-						if frame_idx >= TOTAL_FRAMES {
-							continue;
-						}
+		self.next_free_idx.store(frame_idx, Ordering::Relaxed);
 
-						bitmap[entry_idx] |= mask;
+		return Some(PhysAddr::new(frame_idx * PAGE_SIZE));
+	}
 
-						self.next_free_idx.store(entry_idx, Ordering::Relaxed);
+	/// Allocates a single physical frame that lies within `range`, for devices
+	/// that only reach part of physical memory, see [`super::zone`].
+	///
+	/// Frames of the DMA reserve are only taken once no other frame in
+	/// `range` is free.
+	pub fn allocate_frame_in(
+		&self,
+		range: Range<PhysAddr>,
+	) -> Option<PhysAddr> {
+		let frames = range.start.as_usize().div_ceil(PAGE_SIZE)
+			..(range.end.as_usize() / PAGE_SIZE).min(TOTAL_FRAMES);
+		let mut bitmap = FRAME_BITMAP.lock();
 
-						return Some(PhysAddr::new(frame_idx * PAGE_SIZE));
-					}
-				}
+		let frame_idx = match bitmap.find_first_zero_in(frames.clone()) {
+			Some(frame_idx) => {
+				bitmap.set(frame_idx);
+				frame_idx
 			}
+			None => DMA_RESERVE.lock().take(&frames)?,
+		};
+
+		Self::set_refcount(frame_idx, 1);
+		return Some(PhysAddr::new(frame_idx * PAGE_SIZE));
+	}
+
+	/// Returns the number of frames within `range` that can still be
+	/// allocated, those of the DMA reserve included.
+	pub fn free_frames_in(&self, range: Range<PhysAddr>) -> usize {
+		let frames = range.start.as_usize().div_ceil(PAGE_SIZE)
+			..(range.end.as_usize() / PAGE_SIZE).min(TOTAL_FRAMES);
+		let bitmap = FRAME_BITMAP.lock();
+		let frames =
+			frames.start.min(bitmap.len())..frames.end.min(bitmap.len());
+		let reserved = DMA_RESERVE.lock().free_in(&frames);
+
+		return frames.len() - bitmap.count_ones_in(frames) + reserved;
+	}
+
+	/// Returns the number of free frames in the DMA reserve.
+	pub fn dma_reserve_free(&self) -> usize {
+		let _bitmap = FRAME_BITMAP.lock();
+		return DMA_RESERVE.lock().free.count_ones() as usize;
+	}
+
+	/// Allocates `count` physically contiguous frames, the first of which
+	/// starts on a multiple of `align_frames` frames.
+	///
+	/// Used for buffers a device reads by physical address, which must not be
+	/// scattered over the frames one page happens to map.
+	pub fn allocate_contiguous(
+		&self,
+		count: usize,
+		align_frames: usize,
+	) -> Option<PhysAddr> {
+		assert!(align_frames > 0, "Frame alignment must not be zero");
+		if count == 0 {
+			return None;
+		}
+
+		let mut bitmap = FRAME_BITMAP.lock();
+		let start_idx = self.next_free_idx.load(Ordering::Relaxed);
+
+		let frame_idx = bitmap.find_zero_run_in(
+			start_idx..TOTAL_FRAMES,
+			count,
+			align_frames,
+		)?;
+		bitmap.set_range(frame_idx..frame_idx + count);
+		for i in frame_idx..frame_idx + count {
+			Self::set_refcount(i, 1);
 		}
 
-		None
+		return Some(PhysAddr::new(frame_idx * PAGE_SIZE));
 	}
 
-	/// Deallocates a single physical frame.
+	/// Drops a reference to a single physical frame, see
+	/// [`FrameAllocator::frame_put`].
 	pub fn deallocate_frame(&self, frame: PhysAddr) {
+		self.frame_put(frame);
+	}
+
+	/// Drops a reference to each of `count` frames from
+	/// [`FrameAllocator::allocate_contiguous`].
+	pub fn deallocate_contiguous(&self, start: PhysAddr, count: usize) {
+		for i in 0..count {
+			self.frame_put(start + i * PAGE_SIZE);
+		}
+	}
+
+	/// Adds a reference to an allocated frame, for a second mapping of it.
+	/// Every reference is dropped with [`FrameAllocator::frame_put`].
+	pub fn frame_get(&self, frame: PhysAddr) {
+		let _bitmap = FRAME_BITMAP.lock();
+		let mut refcounts = FRAME_REFCOUNTS.lock();
+
+		match refcounts.get_mut(frame.as_usize() / PAGE_SIZE) {
+			Some(0) | None => {
+				log_warn!("Reference taken to a free frame: {:?}", frame)
+			}
+			Some(count) => match count.checked_add(1) {
+				Some(new) => *count = new,
+				None => log_warn!("Too many references to frame: {:?}", frame),
+			},
+		}
+	}
+
+	/// Drops a reference to `frame` and frees it once nothing refers to it.
+	/// Returns whether the frame was freed.
+	pub fn frame_put(&self, frame: PhysAddr) -> bool {
+		let mut bitmap = FRAME_BITMAP.lock();
+
+		{
+			let mut refcounts = FRAME_REFCOUNTS.lock();
+			if let Some(count) = refcounts.get_mut(frame.as_usize() / PAGE_SIZE)
+			{
+				if *count == 0 {
+					log_warn!("Reference dropped to a free frame: {:?}", frame);
+					return false;
+				}
+
+				*count -= 1;
+				if *count > 0 {
+					return false;
+				}
+			}
+		}
+
+		return self.release(&mut bitmap, frame);
+	}
+
+	/// Returns the number of references to `frame`, 0 for free frames.
+	pub fn refcount(&self, frame: PhysAddr) -> u16 {
+		let refcounts = FRAME_REFCOUNTS.lock();
+		return refcounts
+			.get(frame.as_usize() / PAGE_SIZE)
+			.copied()
+			.unwrap_or(0);
+	}
+
+	// Helper to set the refcount of a frame, if it is tracked
+	fn set_refcount(frame_idx: usize, count: u16) {
+		if let Some(refcount) = FRAME_REFCOUNTS.lock().get_mut(frame_idx) {
+			*refcount = count;
+		}
+	}
+
+	// Helper to clear the bit of a used frame and move the hint back to it
+	fn release(&self, bitmap: &mut Bitmap, frame: PhysAddr) -> bool {
 		let frame_idx = frame.as_usize() / PAGE_SIZE;
-		if frame_idx >= TOTAL_FRAMES {
+		if frame_idx >= bitmap.len() {
 			log_warn!(
 				"Attempted to deallocate frame outside tracked range: {:?}",
 				frame
 			);
-			return;
+			return false;
 		}
 
-		let entry_idx = frame_idx / BITMAP_ENTRY_SIZE_BITS;
-		let bit_idx = frame_idx % BITMAP_ENTRY_SIZE_BITS;
-		let mask = 1 << bit_idx;
+		{
+			let mut reserve = DMA_RESERVE.lock();
+			if reserve.contains(frame_idx) {
+				let bit = 1 << (frame_idx - reserve.start);
+				if reserve.free & bit != 0 {
+					log_warn!("Double free detected for frame: {:?}", frame);
+					return false;
+				}
 
-		let mut bitmap = FRAME_BITMAP.lock();
+				reserve.free |= bit;
+				return true;
+			}
+		}
 
-		if (bitmap[entry_idx] & mask) == 0 {
+		if !bitmap.test(frame_idx) {
 			log_warn!("Double free detected for frame: {:?}", frame);
-			return;
+			return false;
 		}
 
-		bitmap[entry_idx] &= !mask;
+		bitmap.clear(frame_idx);
 
-		if entry_idx < self.next_free_idx.load(Ordering::Relaxed) {
-			self.next_free_idx.store(entry_idx, Ordering::Relaxed);
+		if frame_idx < self.next_free_idx.load(Ordering::Relaxed) {
+			self.next_free_idx.store(frame_idx, Ordering::Relaxed);
 		}
+		return true;
 	}
 
-	// Helper to mark a range as used (sets bits)
-	fn mark_range_used(
-		&self,
-		bitmap: &mut [u64; BITMAP_ARRAY_SIZE],
-		start_frame: usize,
-		end_frame: usize,
-	) {
-		for frame_idx in start_frame..end_frame {
-			if frame_idx < TOTAL_FRAMES {
-				let entry_idx = frame_idx / BITMAP_ENTRY_SIZE_BITS;
-				let bit_idx = frame_idx % BITMAP_ENTRY_SIZE_BITS;
-				bitmap[entry_idx] |= 1 << bit_idx;
-			}
-		}
+	// Helper to count the free (cleared) bits of the bitmap
+	fn count_free(bitmap: &Bitmap) -> usize {
+		return bitmap.len() - bitmap.count_ones();
 	}
 }
@@ -0,0 +1,62 @@
+//! Cross checks of the heap allocators' bookkeeping.
+//!
+//! [`heap_check`] runs the slab and buddy checks and compares the node pool
+//! bitmap with the nodes on the lists it backs. Every inconsistency is logged
+//! and counted instead of panicking, so it can triage corruption on a
+//! running system. It never allocates.
+
+use super::{
+	allocator::{slab_check, BUDDY_PAGE_ALLOCATOR, NODE_POOL_ALLOCATOR},
+	kmem::kmem_cache_check,
+	DYNAMIC_VIRT_RANGES,
+};
+use crate::log_error;
+
+/// Checks the slab caches, the buddy free lists and the node pool, logging
+/// every inconsistency found. Returns how many there were on failure.
+///
+/// Takes the allocator locks one after the other, so it must not be called
+/// with any of them held.
+pub fn heap_check() -> Result<(), usize> {
+	let mut problems = 0;
+
+	let caches = slab_check().into_iter().flatten();
+	let kmem_caches = kmem_cache_check().into_iter().flatten();
+	for (name, result) in caches.chain(kmem_caches) {
+		if let Err(e) = result {
+			log_error!("heapcheck: slab cache {}: {:?}", name, e);
+			problems += 1;
+		}
+	}
+
+	// The buddy free lists and the allocated dynamic ranges are the node
+	// pool's lists, the free ranges are the gaps between those and take none
+	let mut list_nodes = 0;
+	if let Some(buddy) = BUDDY_PAGE_ALLOCATOR.lock() {
+		if let Err(e) = buddy.debug_check() {
+			log_error!("heapcheck: buddy: {:?}", e);
+			problems += 1;
+		}
+		list_nodes += buddy.stats().free_blocks.iter().sum::<usize>();
+	}
+	if let Some(ranges) = DYNAMIC_VIRT_RANGES.lock() {
+		list_nodes += ranges.used_ranges();
+	}
+
+	if let Some(pool) = NODE_POOL_ALLOCATOR.lock() {
+		let allocated = pool.allocated();
+		if allocated != list_nodes {
+			log_error!(
+				"heapcheck: node pool has {} slots in use, the lists hold {} nodes",
+				allocated,
+				list_nodes
+			);
+			problems += 1;
+		}
+	}
+
+	if problems > 0 {
+		return Err(problems);
+	}
+	return Ok(());
+}
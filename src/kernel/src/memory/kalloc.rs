@@ -0,0 +1,63 @@
+//! Fallible kernel heap allocation.
+//!
+//! The helpers here take their memory from the slab caches or the buddy
+//! allocator exactly like `Box` and `Vec` do, but hand failure back as a
+//! [`KernelError`] instead of going through the allocation error handler.
+
+use super::PAGE_SIZE;
+use crate::error::KernelError;
+use alloc::alloc::{alloc, alloc_zeroed, dealloc};
+use core::{alloc::Layout, ptr::NonNull};
+
+/// Allocates `layout` from the kernel heap. The memory is not zeroed.
+///
+/// Returns `InvalidArgument` for a zero sized layout or an alignment above
+/// `PAGE_SIZE`, and `OutOfMemory` when the heap has no room. Free the block
+/// with [`kfree`].
+pub fn kmalloc(layout: Layout) -> Result<NonNull<u8>, KernelError> {
+	check_layout(layout)?;
+
+	let ptr = unsafe { alloc(layout) };
+	return NonNull::new(ptr).ok_or(KernelError::OutOfMemory);
+}
+
+/// Allocates `layout` like [`kmalloc`], with the memory zeroed.
+pub fn kzalloc(layout: Layout) -> Result<NonNull<u8>, KernelError> {
+	check_layout(layout)?;
+
+	let ptr = unsafe { alloc_zeroed(layout) };
+	return NonNull::new(ptr).ok_or(KernelError::OutOfMemory);
+}
+
+/// Frees a block returned by [`kmalloc`] or [`kzalloc`].
+///
+/// # Safety
+/// `ptr` must come from one of them with the same `layout` and must not be
+/// used afterwards.
+pub unsafe fn kfree(ptr: NonNull<u8>, layout: Layout) {
+	unsafe { dealloc(ptr.as_ptr(), layout) };
+}
+
+/// Allocates room for one `T` like [`kmalloc`]. The memory is not
+/// initialized, write it before reading it. Free it with [`kfree_obj`].
+pub fn kmalloc_obj<T>() -> Result<NonNull<T>, KernelError> {
+	return kmalloc(Layout::new::<T>()).map(NonNull::cast);
+}
+
+/// Frees the room for a `T` from [`kmalloc_obj`], without dropping the `T`.
+///
+/// # Safety
+/// `ptr` must come from [`kmalloc_obj`] for the same `T` and must not be used
+/// afterwards.
+pub unsafe fn kfree_obj<T>(ptr: NonNull<T>) {
+	unsafe { kfree(ptr.cast(), Layout::new::<T>()) };
+}
+
+// Helper rejecting the layouts the heap cannot serve
+fn check_layout(layout: Layout) -> Result<(), KernelError> {
+	if layout.size() == 0 || layout.align() > PAGE_SIZE {
+		return Err(KernelError::InvalidArgument);
+	}
+
+	return Ok(());
+}
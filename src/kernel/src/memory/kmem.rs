@@ -0,0 +1,93 @@
+//! Named caches of fixed size objects for kernel subsystems.
+//!
+//! [`kmem_cache_create`] builds a [`SlabCache`] next to the global
+//! allocator's size classes and records it in a fixed size registry, so
+//! `meminfo` can list it. The cache itself is boxed from one of the size
+//! classes, never from the cache being created, and caches live until the
+//! kernel stops.
+
+use super::{
+	slab::{SlabCheckError, SlabStats},
+	SlabCache,
+};
+use crate::sync::{Locked, Mutex};
+use alloc::boxed::Box;
+use core::ptr::NonNull;
+
+/// Number of caches [`kmem_cache_create`] can register.
+pub const MAX_KMEM_CACHES: usize = 16;
+
+static KMEM_CACHES: Mutex<
+	[Option<&'static Locked<SlabCache>>; MAX_KMEM_CACHES],
+> = Mutex::named("KMEM_CACHES", [None; MAX_KMEM_CACHES]);
+
+/// Creates and registers a cache called `name` for objects of `size` bytes
+/// aligned to `align`, with slabs of `PAGE_SIZE << slab_order` bytes.
+///
+/// Must be called after `memory_init`.
+///
+/// # Panics
+/// Panics if all [`MAX_KMEM_CACHES`] slots are taken, if the heap cannot
+/// hold the cache, or if [`SlabCache::new`] rejects the parameters.
+pub fn kmem_cache_create(
+	name: &'static str,
+	size: usize,
+	align: usize,
+	slab_order: usize,
+) -> &'static Locked<SlabCache> {
+	let cache = SlabCache::new(name, size, align, slab_order);
+	let mut registry = KMEM_CACHES.lock();
+
+	let Some(slot) = registry.iter_mut().find(|slot| slot.is_none()) else {
+		panic!("kmem_cache_create: no room for cache '{}'", name);
+	};
+
+	let cache: &'static Locked<SlabCache> =
+		Box::leak(Box::new(Locked::new(cache)));
+	*slot = Some(cache);
+
+	return cache;
+}
+
+/// Allocates one object from `cache`, or returns `None` when out of memory.
+pub fn kmem_cache_alloc(cache: &Locked<SlabCache>) -> Option<NonNull<u8>> {
+	return cache.with(|cache| {
+		let layout = cache.object_layout();
+		return NonNull::new(unsafe { cache.alloc(layout) });
+	});
+}
+
+/// Returns an object to `cache`.
+///
+/// # Safety
+/// `ptr` must come from [`kmem_cache_alloc`] on the same cache and must not
+/// be used afterwards.
+pub unsafe fn kmem_cache_free(cache: &Locked<SlabCache>, ptr: NonNull<u8>) {
+	cache.with(|cache| {
+		let layout = cache.object_layout();
+		unsafe { cache.dealloc(ptr.as_ptr(), layout) };
+	});
+}
+
+/// Runs [`SlabCache::debug_check`] on every registered cache, in creation
+/// order.
+pub fn kmem_cache_check(
+) -> [Option<(&'static str, Result<(), SlabCheckError>)>; MAX_KMEM_CACHES] {
+	let registry = KMEM_CACHES.lock();
+
+	return core::array::from_fn(|i| {
+		registry[i].map(|cache| {
+			let cache = cache.lock();
+			return (cache.name(), cache.debug_check());
+		})
+	});
+}
+
+/// Returns a usage snapshot of every registered cache, in creation order.
+pub fn kmem_cache_stats() -> [Option<SlabStats>; MAX_KMEM_CACHES] {
+	let registry = KMEM_CACHES.lock();
+
+	return core::array::from_fn(|i| {
+		registry[i].map(|cache| cache.lock().stats())
+	});
+}
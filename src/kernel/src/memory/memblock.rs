@@ -8,6 +8,7 @@
 use super::{MemorySegment, PhysAddr, RegionType};
 use crate::{
 	arch::x86::multiboot::{get_memory_region, MultibootInfo, G_SEGMENTS},
+	collections::array_vec::ArrayVec,
 	memory::PAGE_SIZE,
 	println, println_serial,
 	sync::{mutex::MutexGuard, Locked},
@@ -63,10 +64,10 @@ impl MemRegion {
 /// `memblock` allocator metadata
 #[derive(Debug)]
 pub struct MemBlockAllocator {
-	memory_region: [MemRegion; MAX_REGION],
-	reserved_region: [MemRegion; MAX_REGION],
-	memory_count: usize,
-	reserved_count: usize,
+	memory_region: ArrayVec<MemRegion, MAX_REGION>,
+	reserved_region: ArrayVec<MemRegion, MAX_REGION>,
+	/// The unused rest of the page small allocations are carved from.
+	chunk: MemRegion,
 }
 
 unsafe impl Send for MemBlockAllocator {}
@@ -79,13 +80,10 @@ impl MemBlockAllocator {
 	/// regions. This is typically called very early in the boot process.
 	#[allow(clippy::new_without_default)]
 	pub const fn new() -> Self {
-		const EMPTY: MemRegion = MemRegion::empty();
-
 		Self {
-			memory_region: [EMPTY; MAX_REGION],
-			reserved_region: [EMPTY; MAX_REGION],
-			memory_count: 0,
-			reserved_count: 0,
+			memory_region: ArrayVec::new(),
+			reserved_region: ArrayVec::new(),
+			chunk: MemRegion::empty(),
 		}
 	}
 
@@ -94,39 +92,32 @@ impl MemBlockAllocator {
 	/// Initializes the allocator with zero available and zero reserved memory
 	/// regions. This is typically called very early in the boot process.
 	pub fn init(&mut self) {
-		let segments = G_SEGMENTS.lock();
-
-		for segment in segments.iter() {
-			// TODO: Might add other RegionTypes
-			#[allow(clippy::single_match)]
-			match segment.segment_type() {
-				RegionType::Available => {
-					if !self.add(segment.start_addr(), segment.size()) {
-						panic!("memblock: MAX_COUNT is full in memory segment");
-					}
-				}
-				_ => {}
+		let segments = G_SEGMENTS.read();
+
+		for segment in segments.available_segments() {
+			if !self.add(segment.start_addr(), segment.size()) {
+				panic!("memblock: MAX_COUNT is full in memory segment");
 			}
 		}
 	}
 
 	/// Returns a reference of the current length of `mem_region`
 	pub const fn mem_count(&self) -> usize {
-		self.memory_count
+		self.memory_region.len()
 	}
 
 	/// Returns a reference to the current `memregion`
-	pub const fn mem_region(&self) -> &[MemRegion; MAX_REGION] {
+	pub fn mem_region(&self) -> &[MemRegion] {
 		&self.memory_region
 	}
 
 	/// Returns a reference of the current length of `reserved_region`
 	pub const fn reserved_count(&self) -> usize {
-		self.reserved_count
+		self.reserved_region.len()
 	}
 
 	/// Returns a reference to the current `reserved_region`
-	pub const fn reserved_region(&self) -> &[MemRegion; MAX_REGION] {
+	pub fn reserved_region(&self) -> &[MemRegion] {
 		&self.reserved_region
 	}
 
@@ -135,6 +126,9 @@ impl MemBlockAllocator {
 	/// Attempts to find a region of memory that satisfies the size and
 	/// alignment requirements specified in the layout. If successful, returns
 	/// a pointer to the allocated memory; otherwise, returns a null pointer.
+	/// Requests smaller than a page are served by [`alloc_small`].
+	///
+	/// [`alloc_small`]: MemBlockAllocator::alloc_small
 	///
 	/// # Safety
 	/// This function is unsafe because improper use may lead to memory
@@ -146,12 +140,54 @@ impl MemBlockAllocator {
 	/// # Returns
 	/// A pointer to the allocated memory or null if allocation fails
 	pub unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
-		match self.find_free_region(layout.size(), layout.align()) {
+		let addr = if layout.size() < PAGE_SIZE && layout.align() <= PAGE_SIZE {
+			self.alloc_small(layout.size(), layout.align())
+		} else {
+			self.find_free_region(layout.size(), layout.align())
+		};
+
+		match addr {
 			Some(addr) => addr.as_mut_ptr(),
 			None => ptr::null_mut(),
 		}
 	}
 
+	/// Allocates `size` bytes aligned to `align` from the rest of the current
+	/// chunk, a page that small allocations share. Takes a new page with
+	/// [`find_free_region`], which reserves it as a whole, when the chunk has
+	/// no room left.
+	///
+	/// Returns `None` for sizes of zero or above `PAGE_SIZE`, or if no page is
+	/// left.
+	///
+	/// [`find_free_region`]: MemBlockAllocator::find_free_region
+	pub fn alloc_small(
+		&mut self,
+		size: usize,
+		align: usize,
+	) -> Option<PhysAddr> {
+		if size == 0 || size > PAGE_SIZE || align > PAGE_SIZE {
+			return None;
+		}
+
+		let chunk_end = self.chunk.base.as_usize() + self.chunk.size;
+		let mut start = self.chunk.base.as_usize().next_multiple_of(align);
+
+		if self.chunk.is_empty() || start + size > chunk_end {
+			let page = self.find_free_region(PAGE_SIZE, PAGE_SIZE)?;
+			self.chunk = MemRegion::new(page, PAGE_SIZE);
+			start = page.as_usize();
+		}
+
+		let chunk_end = self.chunk.base.as_usize() + self.chunk.size;
+		self.chunk = MemRegion::new(
+			PhysAddr::new(start + size),
+			chunk_end - start - size,
+		);
+
+		return Some(PhysAddr::new(start));
+	}
+
 	/// Deallocates previously allocated memory.
 	///
 	/// This function is not implemented for MemBlockAllocator and will panic if
@@ -170,46 +206,139 @@ impl MemBlockAllocator {
 	fn remove(&mut self, region_type: RegionType, index: usize) {
 		match region_type {
 			RegionType::Available => {
-				for i in index..self.memory_count - 1 {
-					self.memory_region[i] = self.memory_region[i + 1];
-				}
-
-				self.memory_region[self.memory_count - 1] = MemRegion::empty();
-				self.memory_count -= 1;
+				self.memory_region.remove(index);
 			}
 			RegionType::Reserved => {
-				for i in index..self.reserved_count - 1 {
-					self.reserved_region[i] = self.reserved_region[i + 1];
-				}
-
-				self.reserved_region[self.reserved_count - 1] =
-					MemRegion::empty();
-				self.reserved_count -= 1;
+				self.reserved_region.remove(index);
 			}
 			_ => {}
 		}
 	}
 
+	/// Adds `size` bytes at `base` to the available regions, merged with
+	/// the regions it touches or overlaps. The regions stay sorted by base.
+	///
+	/// Returns `false` if a new region was needed and the array is full.
+	#[must_use]
+	pub fn add(&mut self, base: PhysAddr, size: usize) -> bool {
+		return Self::insert(&mut self.memory_region, base, size);
+	}
+
+	/// Adds `size` bytes at `base` to the reserved regions, like [`add`].
+	///
+	/// [`add`]: MemBlockAllocator::add
 	#[must_use]
-	fn add(&mut self, base: PhysAddr, size: usize) -> bool {
-		if self.memory_count >= MAX_REGION {
-			return false;
+	pub fn reserved(&mut self, base: PhysAddr, size: usize) -> bool {
+		return Self::insert(&mut self.reserved_region, base, size);
+	}
+
+	/// Takes `size` bytes at `base`, widened to whole pages, out of the
+	/// available regions, splitting the regions it cuts into, and records
+	/// them as reserved, so they are never handed out.
+	///
+	/// Returns `false` if a region array is full.
+	#[must_use]
+	pub fn reserve(&mut self, base: PhysAddr, size: usize) -> bool {
+		if size == 0 {
+			return true;
 		}
 
-		self.memory_region[self.memory_count] = MemRegion::new(base, size);
-		self.memory_count += 1;
+		let start = base.as_usize() & !(PAGE_SIZE - 1);
+		let end = base
+			.as_usize()
+			.saturating_add(size)
+			.checked_next_multiple_of(PAGE_SIZE)
+			.unwrap_or(usize::MAX & !(PAGE_SIZE - 1));
+		let mut fits = true;
 
-		return true;
+		let mut i = 0;
+		while i < self.memory_region.len() {
+			let region = self.memory_region[i];
+			let region_start = region.base.as_usize();
+			let region_end = region_start + region.size;
+
+			if region_end <= start || region_start >= end {
+				i += 1;
+				continue;
+			}
+
+			// The pieces put back do not overlap, so the scan skips them
+			self.remove(RegionType::Available, i);
+			if region_start < start {
+				fits &= self.add(region.base, start - region_start);
+			}
+			if region_end > end {
+				fits &= self.add(PhysAddr::new(end), region_end - end);
+			}
+		}
+
+		return self.reserved(PhysAddr::new(start), end - start) && fits;
 	}
 
-	#[must_use]
-	fn reserved(&mut self, base: PhysAddr, size: usize) -> bool {
-		if self.reserved_count >= MAX_REGION {
-			return false;
+	/// Prints the available and the reserved regions to the serial port.
+	pub fn debug_dump(&self) {
+		println_serial!(
+			"memblock: {} available regions",
+			self.memory_region.len()
+		);
+		for region in &self.memory_region {
+			println_serial!(
+				"  [{:#010x} - {:#010x}) {} bytes",
+				region.base.as_usize(),
+				region.base.as_usize() + region.size,
+				region.size
+			);
+		}
+
+		println_serial!(
+			"memblock: {} reserved regions",
+			self.reserved_region.len()
+		);
+		for region in &self.reserved_region {
+			println_serial!(
+				"  [{:#010x} - {:#010x}) {} bytes",
+				region.base.as_usize(),
+				region.base.as_usize() + region.size,
+				region.size
+			);
+		}
+	}
+
+	// Helper inserting a region in `regions` sorted by base, merging it with
+	// every region it touches or overlaps
+	fn insert(
+		regions: &mut ArrayVec<MemRegion, MAX_REGION>,
+		base: PhysAddr,
+		size: usize,
+	) -> bool {
+		if size == 0 {
+			return true;
+		}
+
+		let mut start = base.as_usize();
+		let mut end = start.saturating_add(size);
+
+		// The regions neither touch nor overlap, so their ends are sorted too
+		let first = regions
+			.iter()
+			.position(|region| region.base.as_usize() + region.size >= start)
+			.unwrap_or(regions.len());
+		let mut last = first;
+		while last < regions.len() && regions[last].base.as_usize() <= end {
+			start = start.min(regions[last].base.as_usize());
+			end = end.max(regions[last].base.as_usize() + regions[last].size);
+			last += 1;
 		}
 
-		self.reserved_region[self.reserved_count] = MemRegion::new(base, size);
-		self.reserved_count += 1;
+		let merged = MemRegion::new(PhysAddr::new(start), end - start);
+		if first == last {
+			return regions.insert(first, merged).is_ok();
+		}
+
+		regions[first] = merged;
+		for _ in first + 1..last {
+			regions.remove(first + 1);
+		}
 
 		return true;
 	}
@@ -233,7 +362,7 @@ impl MemBlockAllocator {
 		size: usize,
 		align: usize,
 	) -> Option<PhysAddr> {
-		if self.memory_count == 0 || size == 0 {
+		if self.memory_region.is_empty() || size == 0 {
 			return None;
 		}
 
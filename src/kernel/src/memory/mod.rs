@@ -12,18 +12,29 @@ pub mod addr;
 pub mod allocator;
 pub mod buddy;
 pub mod frame;
+pub mod heapcheck;
+pub mod kalloc;
+pub mod kmem;
 pub mod memblock;
 pub mod node_pool;
 pub mod paging;
 pub mod slab;
+pub mod virt_range;
+pub mod vmalloc;
+pub mod zone;
 
+use crate::{
+	arch::x86::cpu::enable_write_protect,
+	sync::{Global, Mutex},
+};
 pub use addr::{PhysAddr, VirtAddr};
 pub use buddy::BuddyAllocator;
-use core::sync::atomic::{AtomicUsize, Ordering};
+use core::ops::Range;
 pub use frame::FrameAllocator;
 pub use memblock::MemBlockAllocator;
 pub use node_pool::NodePoolAllocator;
 pub use slab::SlabCache;
+use virt_range::VirtRangeAllocator;
 
 /* -------------------------------------- */
 
@@ -31,6 +42,12 @@ extern "C" {
 	static _kernel_virtual_end: u8;
 	static _kernel_physical_start: u8;
 	static _kernel_physical_end: u8;
+	static _text_start: u8;
+	static _text_end: u8;
+	static _rodata_start: u8;
+	static _rodata_end: u8;
+	// src/arch/{target}/boot.asm
+	static stack_guard: u8;
 }
 
 /// Function to get the physical start address of the kernel image.
@@ -48,6 +65,67 @@ pub fn get_kernel_virtual_end() -> VirtAddr {
 	unsafe { VirtAddr::new(&_kernel_virtual_end as *const u8 as usize) }
 }
 
+/// Returns the virtual address range of the kernel's code.
+pub fn get_kernel_text() -> Range<VirtAddr> {
+	unsafe {
+		VirtAddr::new(&_text_start as *const u8 as usize)
+			..VirtAddr::new(&_text_end as *const u8 as usize)
+	}
+}
+
+/// Returns the address range of the unmapped page below the boot stack.
+pub fn get_stack_guard() -> Range<VirtAddr> {
+	let start = unsafe { &stack_guard as *const u8 as usize };
+
+	return VirtAddr::new(start)..VirtAddr::new(start + PAGE_SIZE);
+}
+
+/// Maps the kernel's `.text` and `.rodata` read-only and makes the CPU honour
+/// that in kernel mode too, so stray writes to code or constants fault
+/// instead of corrupting them silently. Unmaps the guard page below the boot
+/// stack, so overflowing it faults too.
+///
+/// The boot page directory maps the kernel with 4MiB pages, those covering
+/// the sections are split into page tables first.
+#[allow(clippy::expect_used)]
+pub fn protect_kernel_image() {
+	let sections = unsafe {
+		[
+			(
+				&_text_start as *const u8 as usize,
+				&_text_end as *const u8 as usize,
+			),
+			(
+				&_rodata_start as *const u8 as usize,
+				&_rodata_end as *const u8 as usize,
+			),
+		]
+	};
+
+	for (start, end) in sections {
+		let first = start & !(paging::PAGE_SIZE_4MIB - 1);
+		for large_page in (first..end).step_by(paging::PAGE_SIZE_4MIB) {
+			paging::split_large_page(VirtAddr::new(large_page))
+				.expect("No frame left to split the kernel mapping");
+		}
+
+		paging::protect_range(
+			VirtAddr::new(start),
+			end - start,
+			paging::flags::PRESENT,
+		)
+		.expect("Failed to map the kernel image read-only");
+	}
+
+	let guard = get_stack_guard().start;
+	paging::split_large_page(guard)
+		.expect("No frame left to split the kernel mapping");
+	// The frame is part of the kernel image
+	paging::unmap_page_keep_frame(guard);
+
+	enable_write_protect();
+}
+
 /* -------------------------------------- */
 
 /// The offset of the kernel
@@ -67,30 +145,122 @@ pub enum RegionType {
 	BadMemory = 5,
 }
 
+impl RegionType {
+	/// Returns the region type of a multiboot memory map entry type, or
+	/// `Unknown` for types it does not define.
+	pub const fn from_raw(value: u32) -> Self {
+		match value {
+			1 => return RegionType::Available,
+			2 => return RegionType::Reserved,
+			3 => return RegionType::AcpiReclaimable,
+			4 => return RegionType::AcpiNvs,
+			5 => return RegionType::BadMemory,
+			_ => return RegionType::Unknown,
+		}
+	}
+}
+
 /* -------------------------------------- */
 
 const NODE_POOL_VIRT_START: usize = 0xc1000000;
 
 const VIRT_START: usize = 0xd000_0000;
 const VIRT_SIZE: usize = 1024 * 1024 * 128;
-const VIRT_END: usize = VIRT_START + VIRT_SIZE;
 
-static NEXT_FREE_VIRT_ADDR: AtomicUsize = AtomicUsize::new(VIRT_START);
+/// Window the heap maps buddy blocks into for allocations too big for a slab
+/// cache. A block at offset `n` from the buddy base always lands at
+/// `BUDDY_WINDOW_VIRT_START + n`, so freed blocks give their addresses back.
+const BUDDY_WINDOW_VIRT_START: usize = 0xe000_0000;
+const BUDDY_WINDOW_SIZE: usize = 1024 * 1024 * 256;
+
+/// Page the buddy allocator maps free blocks at to poison and check them in
+/// `buddy-debug` builds, in the gap below the heap window.
+#[cfg(feature = "buddy-debug")]
+const BUDDY_POISON_PAGE: usize = BUDDY_WINDOW_VIRT_START - PAGE_SIZE;
+
+/// The part of the kernel's address space an address falls in, see
+/// [`classify_address`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KernelRegion {
+	/// The loaded kernel image, code and statics.
+	KernelImage,
+	/// The pool the linked list nodes of the allocators come from.
+	NodePool,
+	/// The window of [`allocate_dynamic_virt_range`], slabs and vmalloc.
+	DynamicRanges,
+	/// The window big heap allocations are mapped into.
+	HeapWindow,
+	/// None of the above.
+	Wild,
+}
+
+impl KernelRegion {
+	/// Returns a short name of the region for reports.
+	pub const fn name(self) -> &'static str {
+		match self {
+			KernelRegion::KernelImage => return "kernel image",
+			KernelRegion::NodePool => return "node pool",
+			KernelRegion::DynamicRanges => return "dynamic ranges",
+			KernelRegion::HeapWindow => return "heap window",
+			KernelRegion::Wild => return "wild",
+		}
+	}
+}
+
+/// Tells which part of the kernel's address space `virt` belongs to.
+pub fn classify_address(virt: VirtAddr) -> KernelRegion {
+	let addr = virt.as_usize();
+	let image_start = get_kernel_physical_start().as_usize() + KERNEL_OFFSET;
+
+	if (image_start..get_kernel_virtual_end().as_usize()).contains(&addr) {
+		return KernelRegion::KernelImage;
+	}
+	if (NODE_POOL_VIRT_START..VIRT_START).contains(&addr) {
+		return KernelRegion::NodePool;
+	}
+	if (VIRT_START..VIRT_START + VIRT_SIZE).contains(&addr) {
+		return KernelRegion::DynamicRanges;
+	}
+	if (BUDDY_WINDOW_VIRT_START..BUDDY_WINDOW_VIRT_START + BUDDY_WINDOW_SIZE)
+		.contains(&addr)
+	{
+		return KernelRegion::HeapWindow;
+	}
+
+	return KernelRegion::Wild;
+}
+
+static DYNAMIC_VIRT_RANGES: Global<VirtRangeAllocator> =
+	Global::named("DYNAMIC_VIRT_RANGES");
 
 /// Function to allocate a contiguous block of virtual address space
 /// Returns the start virtual address of the allocated block, or None if out of
 /// space.
 pub fn allocate_dynamic_virt_range(size: usize) -> Option<VirtAddr> {
-	let size = (size + PAGE_SIZE - 1) & !(PAGE_SIZE - 1);
-	let current_start = NEXT_FREE_VIRT_ADDR.fetch_add(size, Ordering::SeqCst);
-	let allocation_end = current_start.checked_add(size)?;
+	return allocate_dynamic_virt_range_aligned(size, PAGE_SIZE);
+}
 
-	if allocation_end > VIRT_END {
-		NEXT_FREE_VIRT_ADDR.fetch_sub(size, Ordering::SeqCst);
-		return None;
-	}
+/// Like [`allocate_dynamic_virt_range`], but the block starts at a multiple of
+/// `align`, which must be a power of two of at least `PAGE_SIZE`.
+pub fn allocate_dynamic_virt_range_aligned(
+	size: usize,
+	align: usize,
+) -> Option<VirtAddr> {
+	let ranges = DYNAMIC_VIRT_RANGES.get_or_init(|| {
+		return VirtRangeAllocator::new(VirtAddr::new(VIRT_START), VIRT_SIZE);
+	});
+	return ranges.lock().allocate(size, align);
+}
 
-	Some(VirtAddr::new(current_start))
+/// Gives a block from [`allocate_dynamic_virt_range`] back, so its addresses
+/// can be handed out again. `size` must be the size it was allocated with.
+/// Returns false, after logging a warning, if no such block was allocated.
+///
+/// The caller must have unmapped the block.
+pub fn free_dynamic_virt_range(addr: VirtAddr, size: usize) -> bool {
+	return DYNAMIC_VIRT_RANGES
+		.with(|ranges| ranges.free(addr, size))
+		.unwrap_or(false);
 }
 
 /* -------------------------------------- */
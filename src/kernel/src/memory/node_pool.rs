@@ -4,8 +4,8 @@
 
 use super::{allocator::NODE_POOL_ALLOCATOR, PhysAddr, VirtAddr};
 use crate::{
-	collections::linked_list::Node,
-	log_error,
+	collections::{bitmap::Bitmap, linked_list::Node},
+	log_error, log_trace,
 	memory::{allocator::EARLY_PHYSICAL_ALLOCATOR, PAGE_SIZE},
 	println_serial,
 	sync::Locked,
@@ -17,6 +17,11 @@ use core::{
 	ptr::{self, NonNull},
 };
 
+/// Size of one pool slot. Fits the nodes of lists of up to two words, like
+/// the buddy free lists and the dynamic virtual ranges.
+pub const NODE_SLOT_SIZE: usize = size_of::<Node<[usize; 2]>>();
+const NODE_ALIGN: usize = align_of::<Node<usize>>();
+
 // --- Node Allocator Wrapper (for GlobalAlloc trait) ---
 
 /// A zero-sized type that implements `core::alloc::Allocator`.
@@ -35,8 +40,8 @@ unsafe impl Allocator for NodeAllocatorWrapper {
 	/// # Safety
 	/// Relies on the underlying `NodePoolAllocator::alloc` being sound.
 	fn allocate(&self, layout: Layout) -> Result<NonNull<[u8]>, AllocError> {
-		let mut guard = NODE_POOL_ALLOCATOR.lock();
-		let pool_allocator = guard.get_mut().ok_or(AllocError)?;
+		let mut pool_allocator =
+			NODE_POOL_ALLOCATOR.lock().ok_or(AllocError)?;
 
 		let ptr = unsafe { pool_allocator.alloc(layout) };
 
@@ -59,8 +64,7 @@ unsafe impl Allocator for NodeAllocatorWrapper {
 	/// - Relies on the underlying `NodePoolAllocator::dealloc` being sound.
 	#[allow(clippy::expect_used)]
 	unsafe fn deallocate(&self, ptr: NonNull<u8>, layout: Layout) {
-		let mut guard = NODE_POOL_ALLOCATOR.lock();
-		let pool_allocator = guard.get_mut().expect(
+		let mut pool_allocator = NODE_POOL_ALLOCATOR.lock().expect(
 			"NODE_POOL_ALLOCATOR accessed before deallocation/initialization",
 		);
 
@@ -78,7 +82,7 @@ unsafe impl Allocator for NodeAllocatorWrapper {
 #[derive(Debug)]
 pub struct NodePoolAllocator {
 	base: VirtAddr,
-	map: &'static mut [usize],
+	map: Bitmap,
 	capacity: usize,
 	// NOTE: Consider storing node_size and node_align here too.
 }
@@ -92,7 +96,7 @@ impl NodePoolAllocator {
 	/// # Arguments
 	/// * `base`: The starting physical address of the node storage pool. Must
 	///   be aligned for `Node<usize>`.
-	/// * `capacity`: The total number of `Node<usize>`-sized slots the pool
+	/// * `capacity`: The total number of [`NODE_SLOT_SIZE`] slots the pool
 	///   should manage.
 	#[allow(clippy::expect_used)]
 	pub fn new(base: VirtAddr, capacity: usize) -> Self {
@@ -104,13 +108,13 @@ impl NodePoolAllocator {
 		);
 		assert!(capacity > 0, "Node pool capacity must be > 0");
 
-		let bitmap_words_needed = capacity.div_ceil(usize::BITS as usize);
+		let bitmap_words_needed = Bitmap::words_for(capacity);
 		let bitmap_layout = Layout::array::<usize>(bitmap_words_needed)
 			.expect("Failed to create layout for bitmap");
 
 		let bitmap_ptr = {
 			let mut memblock_guard = EARLY_PHYSICAL_ALLOCATOR.lock();
-			let allocator = memblock_guard.get_mut().expect(
+			let allocator = memblock_guard.as_mut().expect(
 				"EARLY_PHYSICAL_ALLOCATOR not available for NodePool bitmap",
 			);
 
@@ -123,14 +127,16 @@ impl NodePoolAllocator {
 
 		let bitmap_base_addr = bitmap_ptr as usize;
 
-		let map_slice: &'static mut [usize] = unsafe {
-			let slice = slice::from_raw_parts_mut(
-				with_exposed_provenance_mut(bitmap_base_addr),
-				bitmap_words_needed,
-			);
-			slice.fill(0);
-			slice
-		};
+		let mut map = Bitmap::new(
+			unsafe {
+				slice::from_raw_parts_mut(
+					with_exposed_provenance_mut(bitmap_base_addr),
+					bitmap_words_needed,
+				)
+			},
+			capacity,
+		);
+		map.fill(false);
 
 		println_serial!(
             "NodePoolAllocator initialized: base={:#x}, capacity={}, bitmap={:#x} ({} words)",
@@ -142,31 +148,34 @@ impl NodePoolAllocator {
 
 		return Self {
 			base,
-			map: map_slice,
+			map,
 			capacity,
 		};
 	}
 
+	/// Returns the number of slots handed out, counted from the bitmap.
+	pub fn allocated(&self) -> usize {
+		return self.map.count_ones();
+	}
+
 	/// Allocates a single node slot from the pool. (Internal Method)
 	///
-	/// Checks if the requested layout matches the expected `Node<usize>`
-	/// size/alignment. Finds a free slot using the bitmap, marks it allocated,
-	/// and returns its raw pointer. Returns `null_mut` if the layout is
-	/// incorrect or the pool is full.
+	/// Checks if the requested layout fits a [`NODE_SLOT_SIZE`] slot. Finds a
+	/// free slot using the bitmap, marks it allocated, and returns its raw
+	/// pointer. Returns `null_mut` if the layout is incorrect or the pool is
+	/// full.
 	///
 	/// # Safety
 	/// The caller must ensure the returned pointer is used correctly according
-	/// to the provided `layout` (which must match `Node<usize>`). The memory
+	/// to the provided `layout` (which must fit a slot). The memory
 	/// is not zeroed. Requires `&mut self` for bitmap modification.
 	pub unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
-		const NODE_SIZE: usize = size_of::<Node<usize>>();
-		const NODE_ALIGN: usize = align_of::<Node<usize>>();
-
-		if layout.size() != NODE_SIZE {
+		if layout.size() > NODE_SLOT_SIZE {
 			log_error!(
-                "NodePoolAllocator::alloc: Incorrect size (expected {}, got {})",
-                NODE_SIZE, layout.size()
-            );
+				"NodePoolAllocator::alloc: Incorrect size (at most {}, got {})",
+				NODE_SLOT_SIZE,
+				layout.size()
+			);
 			return ptr::null_mut();
 		}
 		if layout.align() > NODE_ALIGN {
@@ -180,9 +189,9 @@ impl NodePoolAllocator {
 		match self.find_block() {
 			Some(index) => {
 				self.mark_allocated(index);
-				let addr = self.base + (index * NODE_SIZE);
+				let addr = self.base + (index * NODE_SLOT_SIZE);
 
-				println_serial!(
+				log_trace!(
 					"NodePoolAllocator::alloc: Allocated block {}, Addr: {:#x}",
 					index,
 					addr.as_usize()
@@ -205,13 +214,10 @@ impl NodePoolAllocator {
 	/// # Safety
 	/// - `ptr` must point to the start of a node slot previously allocated from
 	///   *this* pool.
-	/// - `layout` must match the layout used for allocation (`Node<usize>`).
+	/// - `layout` must match the layout used for allocation.
 	/// - Requires `&mut self` for bitmap modification.
 	pub unsafe fn dealloc(&mut self, ptr: *mut u8, layout: Layout) {
-		const NODE_SIZE: usize = size_of::<Node<usize>>();
-		const NODE_ALIGN: usize = align_of::<Node<usize>>();
-
-		if layout.size() != NODE_SIZE || layout.align() > NODE_ALIGN {
+		if layout.size() > NODE_SLOT_SIZE || layout.align() > NODE_ALIGN {
 			println_serial!(
                 "NodePoolAllocator::dealloc: Incorrect layout provided. Ptr={:p}", ptr
             );
@@ -222,7 +228,8 @@ impl NodePoolAllocator {
 		let addr: VirtAddr = (ptr as usize).into();
 		let base_usize = self.base.as_usize();
 		let addr_usize = addr.as_usize();
-		let pool_end = base_usize.saturating_add(self.capacity * NODE_SIZE);
+		let pool_end =
+			base_usize.saturating_add(self.capacity * NODE_SLOT_SIZE);
 
 		if addr < self.base || addr_usize >= pool_end {
 			println_serial!(
@@ -234,7 +241,7 @@ impl NodePoolAllocator {
 		}
 
 		let offset = addr_usize.saturating_sub(base_usize);
-		if offset % NODE_SIZE != 0 {
+		if offset % NODE_SLOT_SIZE != 0 {
 			println_serial!(
                 "NodePoolAllocator::dealloc: Pointer {:#x} not aligned to a node start within pool.",
                 addr_usize
@@ -243,10 +250,10 @@ impl NodePoolAllocator {
 			return;
 		}
 
-		let index = offset / NODE_SIZE;
+		let index = offset / NODE_SLOT_SIZE;
 		self.mark_deallocated(index);
 
-		println_serial!(
+		log_trace!(
 			"NodePoolAllocator::dealloc: Deallocated block {}, Addr: {:#x}",
 			index,
 			addr_usize
@@ -258,18 +265,14 @@ impl NodePoolAllocator {
 	fn mark_allocated(&mut self, index: usize) {
 		assert!(index < self.capacity, "mark_allocated: Index out of bounds");
 
-		let word_index = index / (usize::BITS as usize);
-		let bit_index = index % (usize::BITS as usize);
-		let mask = 1 << bit_index;
-
-		if (self.map[word_index] & mask) != 0 {
+		if self.map.test(index) {
 			panic!(
 				"NodePoolAllocator: Double allocation detected at index {}!",
 				index
 			);
 		}
 
-		self.map[word_index] |= mask;
+		self.map.set(index);
 	}
 
 	/// (Internal) Marks the bit corresponding to `index` as free (0).
@@ -280,34 +283,19 @@ impl NodePoolAllocator {
 			"mark_deallocated: Index out of bounds"
 		);
 
-		let word_index = index / (usize::BITS as usize);
-		let bit_index = index % (usize::BITS as usize);
-		let mask = 1 << bit_index;
-
-		if (self.map[word_index] & mask) == 0 {
+		if !self.map.test(index) {
 			panic!(
                 "NodePoolAllocator: Double free or freeing unallocated block detected at index {}!",
                 index
             );
 		}
 
-		self.map[word_index] &= !mask;
+		self.map.clear(index);
 	}
 
 	/// (Internal) Finds the index of the first free slot (0-bit) in the bitmap.
 	/// Returns `Some(index)` if found, `None` if the pool is full.
 	fn find_block(&self) -> Option<usize> {
-		for (word_index, &word) in self.map.iter().enumerate() {
-			if word != usize::MAX {
-				let bit_index = (!word).trailing_zeros() as usize;
-				let block_index =
-					word_index * (usize::BITS as usize) + bit_index;
-				if block_index < self.capacity {
-					return Some(block_index);
-				}
-			}
-		}
-
-		return None;
+		return self.map.find_first_zero();
 	}
 }
@@ -1,13 +1,15 @@
 use super::{FrameAllocator, PhysAddr, VirtAddr, KERNEL_OFFSET};
 use crate::{
-	arch::x86::cpu::{cr3, invlpg},
+	arch::x86::cpu::{cr3, flush_tlb, invlpg, set_cr3},
 	log_debug,
 	memory::{frame::FRAME_ALLOCATOR, PAGE_SIZE},
 	println_serial,
 };
-use core::arch::asm;
+use alloc::vec::Vec;
+use core::{alloc::AllocError, arch::asm};
 
-const PAGE_SIZE_4MIB: usize = 4 * 1024 * 1024;
+/// Size of the pages a PDE with `PAGE_SIZE_EXT` maps.
+pub const PAGE_SIZE_4MIB: usize = 4 * 1024 * 1024;
 
 const PDE_PRESENT: u32 = 1 << 0;
 const PDE_WRITABLE: u32 = 1 << 1;
@@ -18,6 +20,10 @@ const ADDR_MASK_4KIB_PTE: u32 = 0xfffff000;
 const ADDR_MASK_4MIB_PDE: u32 = 0xffc00000;
 const ADDR_MASK_PDE_TO_PT: u32 = 0xfffff000;
 
+/// Ranges of more pages than this are unmapped with a single TLB flush
+/// instead of one `invlpg` per page.
+const TLB_FLUSH_THRESHOLD: usize = 32;
+
 pub mod flags {
 	pub const PRESENT: u32 = 1 << 0;
 	pub const WRITABLE: u32 = 1 << 1;
@@ -26,18 +32,188 @@ pub mod flags {
 }
 
 #[inline]
-#[allow(clippy::expect_used)]
 pub fn map_page(phys_addr: PhysAddr, virt_addr: VirtAddr, flags: u32) {
-	use core::ptr;
-
 	assert!(phys_addr.is_aligned(PAGE_SIZE));
 	assert!(virt_addr.is_aligned(PAGE_SIZE));
 
+	if try_map_page(cr3(), phys_addr, virt_addr, flags).is_err() {
+		panic!(
+			"Allocation Failed: Could not allocate frame for new page table"
+		);
+	}
+}
+
+/// Maps `size` bytes, rounded up to pages, from `phys` at `virt`.
+///
+/// If a page table cannot be allocated partway through, the pages mapped so
+/// far are unmapped again and `AllocError` is returned, so the range is
+/// either mapped completely or not at all. The frames stay with the caller,
+/// see [`unmap_range`].
+pub fn map_range(
+	phys: PhysAddr,
+	virt: VirtAddr,
+	size: usize,
+	flags: u32,
+) -> Result<(), AllocError> {
+	assert!(phys.is_aligned(PAGE_SIZE));
+	assert!(virt.is_aligned(PAGE_SIZE));
+
+	let size = size.next_multiple_of(PAGE_SIZE);
+	for offset in (0..size).step_by(PAGE_SIZE) {
+		if try_map_page(cr3(), phys + offset, virt + offset, flags).is_err() {
+			unmap_range(virt, offset);
+			return Err(AllocError);
+		}
+	}
+
+	return Ok(());
+}
+
+/// Removes the mappings of `size` bytes, rounded up to pages, from `virt`.
+///
+/// Unlike [`unmap_page`] the mapped frames are left to the caller, as
+/// [`map_range`] took them from it. Ranges of more than
+/// [`TLB_FLUSH_THRESHOLD`] pages flush the whole TLB once at the end.
+pub fn unmap_range(virt: VirtAddr, size: usize) {
+	assert!(virt.is_aligned(PAGE_SIZE));
+
+	let pages = size.div_ceil(PAGE_SIZE);
+	let flush_each = pages <= TLB_FLUSH_THRESHOLD;
+
+	for page in 0..pages {
+		clear_mapping(cr3(), virt + page * PAGE_SIZE, flush_each, false);
+	}
+	if !flush_each {
+		flush_tlb();
+	}
+}
+
+/// Why [`protect_page`] could not change a mapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProtectError {
+	/// The page is not mapped.
+	NotMapped(VirtAddr),
+	/// The page is part of a 4MiB mapping, see [`split_large_page`].
+	LargePage(VirtAddr),
+}
+
+/// Replaces the flags of the mapped 4KiB page at `virt` with `flags`,
+/// keeping the frame it maps.
+pub fn protect_page(virt: VirtAddr, flags: u32) -> Result<(), ProtectError> {
+	assert!(virt.is_aligned(PAGE_SIZE));
+
+	let pte = pte_mut(virt)?;
+	*pte = (*pte & ADDR_MASK_4KIB_PTE) | (flags & 0xfff) | flags::PRESENT;
+
+	invlpg(virt);
+	return Ok(());
+}
+
+/// Replaces the flags of every page of `size` bytes, rounded up to pages,
+/// from `virt` like [`protect_page`].
+///
+/// Checks the whole range first, so on an error no page was changed.
+pub fn protect_range(
+	virt: VirtAddr,
+	size: usize,
+	flags: u32,
+) -> Result<(), ProtectError> {
+	assert!(virt.is_aligned(PAGE_SIZE));
+
+	let pages = size.div_ceil(PAGE_SIZE);
+	for page in 0..pages {
+		pte_mut(virt + page * PAGE_SIZE)?;
+	}
+
+	for page in 0..pages {
+		let pte = pte_mut(virt + page * PAGE_SIZE)?;
+		*pte = (*pte & ADDR_MASK_4KIB_PTE) | (flags & 0xfff) | flags::PRESENT;
+
+		if pages <= TLB_FLUSH_THRESHOLD {
+			invlpg(virt + page * PAGE_SIZE);
+		}
+	}
+	if pages > TLB_FLUSH_THRESHOLD {
+		flush_tlb();
+	}
+
+	return Ok(());
+}
+
+/// Replaces the 4MiB mapping containing `virt` with a page table mapping the
+/// same frames with the same flags, so single pages of it can be changed.
+/// Does nothing if `virt` is not part of a 4MiB mapping.
+#[allow(clippy::expect_used)]
+pub fn split_large_page(virt: VirtAddr) -> Result<(), AllocError> {
+	let page_directory: &mut [u32; 1024] =
+		unsafe { &mut *(phys_to_virt(cr3()).as_mut_ptr()) };
+	let pde_ref = &mut page_directory[virt.as_usize() >> 22];
+
+	if (*pde_ref & flags::PRESENT) == 0
+		|| (*pde_ref & flags::PAGE_SIZE_EXT) == 0
+	{
+		return Ok(());
+	}
+
+	let pt_frame = FRAME_ALLOCATOR
+		.lock()
+		.expect("Frame has not been initialized yet")
+		.allocate_frame()
+		.ok_or(AllocError)?;
+	let page_table: &mut [u32; 1024] =
+		unsafe { &mut *(phys_to_virt(pt_frame).as_mut_ptr()) };
+
+	let base = *pde_ref & ADDR_MASK_4MIB_PDE;
+	let pte_flags = *pde_ref & 0xfff & !flags::PAGE_SIZE_EXT;
+	for (i, pte) in page_table.iter_mut().enumerate() {
+		*pte = (base + (i * PAGE_SIZE) as u32) | pte_flags;
+	}
+
+	*pde_ref = (pt_frame.as_usize() as u32) | (pte_flags & 0x1f);
+	flush_tlb();
+	return Ok(());
+}
+
+// Helper returning the PTE of the mapped 4KiB page at `virt`
+fn pte_mut(virt: VirtAddr) -> Result<&'static mut u32, ProtectError> {
+	let page_directory: &[u32; 1024] =
+		unsafe { &*(phys_to_virt(cr3()).as_ptr()) };
+	let pde = page_directory[virt.as_usize() >> 22];
+
+	if (pde & flags::PRESENT) == 0 {
+		return Err(ProtectError::NotMapped(virt));
+	}
+	if (pde & flags::PAGE_SIZE_EXT) != 0 {
+		return Err(ProtectError::LargePage(virt));
+	}
+
+	let pt_phys_addr = PhysAddr::new((pde & ADDR_MASK_PDE_TO_PT) as usize);
+	let page_table: &mut [u32; 1024] =
+		unsafe { &mut *(phys_to_virt(pt_phys_addr).as_mut_ptr()) };
+	let pte = &mut page_table[(virt.as_usize() >> 12) & 0x3ff];
+
+	if (*pte & flags::PRESENT) == 0 {
+		return Err(ProtectError::NotMapped(virt));
+	}
+
+	return Ok(pte);
+}
+
+// Helper doing the work of `map_page` in the page directory at `directory`,
+// failing if a page table is needed and no frame is left for it
+#[allow(clippy::expect_used)]
+fn try_map_page(
+	directory: PhysAddr,
+	phys_addr: PhysAddr,
+	virt_addr: VirtAddr,
+	flags: u32,
+) -> Result<(), AllocError> {
+	use core::ptr;
+
 	let vaddr = virt_addr.as_usize();
 	let paddr = phys_addr.as_usize();
 
-	let pd_paddr = cr3();
-	let pd_vaddr = phys_to_virt(pd_paddr);
+	let pd_vaddr = phys_to_virt(directory);
 
 	let page_directory: &mut [u32; 1024] =
 		unsafe { &mut *(pd_vaddr.as_mut_ptr()) };
@@ -46,11 +222,10 @@ pub fn map_page(phys_addr: PhysAddr, virt_addr: VirtAddr, flags: u32) {
 	let pt_phys_addr: PhysAddr;
 	if (*pde_ref & flags::PRESENT) == 0 {
 		let new_pt_frame = FRAME_ALLOCATOR
-            .lock()
-            .get()
-            .expect("Frame has not been initialized yet")
-            .allocate_frame()
-            .expect("Allocation Failed: Could not allocate frame for new page table");
+			.lock()
+			.expect("Frame has not been initialized yet")
+			.allocate_frame()
+			.ok_or(AllocError)?;
 
 		pt_phys_addr = new_pt_frame;
 		let new_pt_virt_addr = phys_to_virt(new_pt_frame);
@@ -59,14 +234,18 @@ pub fn map_page(phys_addr: PhysAddr, virt_addr: VirtAddr, flags: u32) {
 			unsafe { &mut *(new_pt_virt_addr.as_mut_ptr()) };
 		new_page_table.iter_mut().for_each(|entry| *entry = 0);
 
-		*pde_ref =
-			(new_pt_frame.as_usize() as u32) | flags::PRESENT | flags::WRITABLE; // Set PRESENT and WRITABLE for the PDE
+		// The PTEs decide, but ring 3 needs the bit on both levels
+		*pde_ref = (new_pt_frame.as_usize() as u32)
+			| flags::PRESENT
+			| flags::WRITABLE
+			| (flags & flags::USER_ACCESSIBLE);
 	} else if (*pde_ref & flags::PAGE_SIZE_EXT) != 0 {
 		panic!(
 			"Conflict: Tried to map 4KiB page into a 4MiB mapped region: {:#x}",
 			virt_addr.as_usize()
 		);
 	} else {
+		*pde_ref |= flags & flags::USER_ACCESSIBLE;
 		pt_phys_addr = PhysAddr::new((*pde_ref & ADDR_MASK_PDE_TO_PT) as usize);
 	}
 
@@ -78,17 +257,56 @@ pub fn map_page(phys_addr: PhysAddr, virt_addr: VirtAddr, flags: u32) {
 
 	*pte_ref = (paddr as u32) | (flags & 0xfff) | flags::PRESENT;
 
-	invlpg(virt_addr);
+	if directory == cr3() {
+		invlpg(virt_addr);
+	}
+	return Ok(());
 }
 
+/// Removes the mapping of `virt_addr` and drops its reference to the mapped
+/// frame, which is freed once no other mapping refers to it.
 #[inline]
 pub fn unmap_page(virt_addr: VirtAddr) {
-	use core::ptr;
+	let mapped_frame_phys_addr = unmap_page_keep_frame(virt_addr);
+
+	FRAME_ALLOCATOR
+		.lock()
+		.expect("Frame has not been initialized yet")
+		.frame_put(mapped_frame_phys_addr);
+}
 
+/// Removes the mapping of `virt_addr` like [`unmap_page`], but leaves the
+/// mapped frame to the caller instead of giving it back to the frame
+/// allocator. Returns that frame.
+#[inline]
+pub fn unmap_page_keep_frame(virt_addr: VirtAddr) -> PhysAddr {
 	assert!(virt_addr.is_aligned(PAGE_SIZE));
 
-	let pd_paddr = cr3();
-	let pd_vaddr = phys_to_virt(pd_paddr);
+	return clear_mapping(cr3(), virt_addr, true, false);
+}
+
+/// Removes the mapping of `virt_addr` like [`unmap_page_keep_frame`], but
+/// keeps its page table even once it is empty. For a slot that is mapped over
+/// and over, so mapping it again allocates nothing.
+#[inline]
+pub fn unmap_page_keep_table(virt_addr: VirtAddr) -> PhysAddr {
+	assert!(virt_addr.is_aligned(PAGE_SIZE));
+
+	return clear_mapping(cr3(), virt_addr, true, true);
+}
+
+// Helper clearing the PTE of `virt_addr` in the page directory at
+// `directory` and freeing its page table once empty, unless `keep_table` is
+// set. Leaves flushing the TLB to the caller unless `flush` is set.
+fn clear_mapping(
+	directory: PhysAddr,
+	virt_addr: VirtAddr,
+	flush: bool,
+	keep_table: bool,
+) -> PhysAddr {
+	use core::ptr;
+
+	let pd_vaddr = phys_to_virt(directory);
 
 	let page_directory: &mut [u32; 1024] =
 		unsafe { &mut *(pd_vaddr.as_mut_ptr()) };
@@ -126,13 +344,13 @@ pub fn unmap_page(virt_addr: VirtAddr) {
 
 	*pte_ref = 0;
 
-	invlpg(virt_addr);
+	if flush && directory == cr3() {
+		invlpg(virt_addr);
+	}
 
-	FRAME_ALLOCATOR
-		.lock()
-		.get()
-		.expect("Frame has not been initialized yet")
-		.deallocate_frame(mapped_frame_phys_addr);
+	if keep_table {
+		return mapped_frame_phys_addr;
+	}
 
 	let mut page_table_is_empty = true;
 	for i in 0..1024 {
@@ -145,21 +363,26 @@ pub fn unmap_page(virt_addr: VirtAddr) {
 	if page_table_is_empty {
 		FRAME_ALLOCATOR
 			.lock()
-			.get()
 			.expect("Frame allocator not initialized for PT deallocation")
 			.deallocate_frame(pt_phys_addr);
 
 		*pde_ref = 0;
 	}
+
+	return mapped_frame_phys_addr;
 }
 
 #[inline]
 #[must_use]
 pub fn translate(virt_addr: VirtAddr) -> Option<PhysAddr> {
+	return translate_in(cr3(), virt_addr);
+}
+
+// Helper doing the work of `translate` in the page directory at `directory`
+fn translate_in(directory: PhysAddr, virt_addr: VirtAddr) -> Option<PhysAddr> {
 	use core::ptr;
 
-	let paddr = cr3();
-	let pd_virt_addr = phys_to_virt(paddr);
+	let pd_virt_addr = phys_to_virt(directory);
 	let page_directory = unsafe { &*(pd_virt_addr.as_ptr()) as &[u32; 1024] };
 	let pde = page_directory[virt_addr.as_usize() >> 22];
 
@@ -201,6 +424,364 @@ pub fn translate(virt_addr: VirtAddr) -> Option<PhysAddr> {
 	Some(PhysAddr::new(frame_phys_addr + offset_in_page))
 }
 
+/// Returns the flags that apply to the page containing `virt_addr`, or `None`
+/// if it is not mapped.
+///
+/// For 4KiB pages, `WRITABLE` and `USER_ACCESSIBLE` are only reported if both
+/// the PDE and the PTE grant them, as the CPU requires.
+#[must_use]
+pub fn page_flags(virt_addr: VirtAddr) -> Option<u32> {
+	let pd_virt_addr = phys_to_virt(cr3());
+	let page_directory = unsafe { &*(pd_virt_addr.as_ptr()) as &[u32; 1024] };
+	let pde = page_directory[virt_addr.as_usize() >> 22];
+
+	if (pde & flags::PRESENT) == 0 {
+		return None;
+	}
+
+	if (pde & flags::PAGE_SIZE_EXT) != 0 {
+		return Some(pde & 0xfff);
+	}
+
+	let pt_phys_addr = PhysAddr::new((pde & ADDR_MASK_PDE_TO_PT) as usize);
+	let pt_virt_addr = phys_to_virt(pt_phys_addr);
+
+	let page_table: &[u32; 1024] = unsafe { &*(pt_virt_addr.as_ptr()) };
+	let pte = page_table[(virt_addr.as_usize() >> 12) & 0x3ff];
+
+	if (pte & flags::PRESENT) == 0 {
+		return None;
+	}
+
+	let inherited = pde | !(flags::WRITABLE | flags::USER_ACCESSIBLE);
+	return Some(pte & inherited & 0xfff);
+}
+
+/// A run of virtual addresses mapped to contiguous physical addresses with
+/// the same flags, see [`for_each_mapped_run`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MappedRun {
+	/// First virtual address of the run.
+	pub virt: VirtAddr,
+	/// Physical address `virt` maps to.
+	pub phys: PhysAddr,
+	/// Length of the run in bytes.
+	pub size: usize,
+	/// Flags of the pages like [`page_flags`] reports them, with
+	/// `PAGE_SIZE_EXT` for 4MiB pages.
+	pub flags: u32,
+}
+
+/// The page directory and page table entries translating an address, see
+/// [`page_walk`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PageWalk {
+	/// Index of the PDE in the page directory.
+	pub pde_index: usize,
+	/// The raw PDE.
+	pub pde: u32,
+	/// Index of the PTE in its page table.
+	pub pte_index: usize,
+	/// The raw PTE, `None` if the PDE maps no page table.
+	pub pte: Option<u32>,
+}
+
+/// Walks the current page directory and calls `f` for every run of mapped
+/// pages, in address order. Adjacent pages are merged into one run if they
+/// map adjacent frames with the same flags, so a 16MiB offset mapping is a
+/// single run.
+pub fn for_each_mapped_run(mut f: impl FnMut(MappedRun)) {
+	let page_directory: &[u32; 1024] =
+		unsafe { &*(phys_to_virt(cr3()).as_ptr()) };
+	let mut run: Option<MappedRun> = None;
+
+	let mut add = |page: MappedRun| {
+		match &mut run {
+			Some(current)
+				if current.virt.as_usize().checked_add(current.size)
+					== Some(page.virt.as_usize())
+					&& current.phys.as_usize().checked_add(current.size)
+						== Some(page.phys.as_usize())
+					&& current.flags == page.flags =>
+			{
+				current.size += page.size;
+				return;
+			}
+			Some(current) => f(*current),
+			None => {}
+		}
+		run = Some(page);
+	};
+
+	for (pde_index, &pde) in page_directory.iter().enumerate() {
+		if (pde & flags::PRESENT) == 0 {
+			continue;
+		}
+
+		let virt = VirtAddr::new(pde_index << 22);
+		if (pde & flags::PAGE_SIZE_EXT) != 0 {
+			add(MappedRun {
+				virt,
+				phys: PhysAddr::new((pde & ADDR_MASK_4MIB_PDE) as usize),
+				size: PAGE_SIZE_4MIB,
+				flags: pde & (flags::PAGE_SIZE_EXT | 0x1f),
+			});
+			continue;
+		}
+
+		let pt_phys_addr = PhysAddr::new((pde & ADDR_MASK_PDE_TO_PT) as usize);
+		let page_table: &[u32; 1024] =
+			unsafe { &*(phys_to_virt(pt_phys_addr).as_ptr()) };
+		let inherited = pde | !(flags::WRITABLE | flags::USER_ACCESSIBLE);
+
+		for (pte_index, &pte) in page_table.iter().enumerate() {
+			if (pte & flags::PRESENT) == 0 {
+				continue;
+			}
+
+			add(MappedRun {
+				virt: virt + pte_index * PAGE_SIZE,
+				phys: PhysAddr::new((pte & ADDR_MASK_4KIB_PTE) as usize),
+				size: PAGE_SIZE,
+				flags: pte & inherited & 0x1f,
+			});
+		}
+	}
+
+	if let Some(last) = run {
+		f(last);
+	}
+}
+
+/// Returns the entries the current page directory uses to translate `virt`.
+pub fn page_walk(virt: VirtAddr) -> PageWalk {
+	let page_directory: &[u32; 1024] =
+		unsafe { &*(phys_to_virt(cr3()).as_ptr()) };
+	let pde_index = virt.as_usize() >> 22;
+	let pde = page_directory[pde_index];
+	let pte_index = (virt.as_usize() >> 12) & 0x3ff;
+
+	let pte = match (pde & flags::PRESENT, pde & flags::PAGE_SIZE_EXT) {
+		(0, _) => None,
+		(_, 0) => {
+			let pt_phys_addr =
+				PhysAddr::new((pde & ADDR_MASK_PDE_TO_PT) as usize);
+			let page_table: &[u32; 1024] =
+				unsafe { &*(phys_to_virt(pt_phys_addr).as_ptr()) };
+			Some(page_table[pte_index])
+		}
+		_ => None,
+	};
+
+	return PageWalk {
+		pde_index,
+		pde,
+		pte_index,
+		pte,
+	};
+}
+
+/// First PDE of the kernel half of an address space.
+const KERNEL_PDE_START: usize = KERNEL_OFFSET >> 22;
+
+/// A page directory that can be loaded instead of the current one.
+///
+/// Its user half starts empty and is its own. Its kernel half, everything
+/// from `KERNEL_OFFSET` including the node pool and the heap windows, is
+/// copied from the page directory it was cloned from, so it shares their page
+/// tables: pages mapped in them show up in every address space. Page tables
+/// the address space allocates itself are freed when it is dropped, the
+/// shared ones never are, and so are the frames [`AddressSpace::map_zeroed`]
+/// allocated.
+pub struct AddressSpace {
+	directory: PhysAddr,
+	/// One bit per PDE whose page table this address space allocated.
+	owned_tables: [u32; 32],
+	/// Frames mapped with `map_zeroed`, freed with the address space.
+	owned_frames: Vec<PhysAddr>,
+}
+
+impl AddressSpace {
+	/// Allocates a page directory with the kernel half of the current one
+	/// and an empty user half.
+	#[allow(clippy::expect_used)]
+	pub fn new_kernel_clone() -> Result<Self, AllocError> {
+		let directory = FRAME_ALLOCATOR
+			.lock()
+			.expect("Frame has not been initialized yet")
+			.allocate_frame()
+			.ok_or(AllocError)?;
+
+		let current: &[u32; 1024] = unsafe { &*(phys_to_virt(cr3()).as_ptr()) };
+		let page_directory: &mut [u32; 1024] =
+			unsafe { &mut *(phys_to_virt(directory).as_mut_ptr()) };
+
+		page_directory[..KERNEL_PDE_START].fill(0);
+		page_directory[KERNEL_PDE_START..]
+			.copy_from_slice(&current[KERNEL_PDE_START..]);
+
+		return Ok(Self {
+			directory,
+			owned_tables: [0; 32],
+			owned_frames: Vec::new(),
+		});
+	}
+
+	/// Shares the identity mapped low 4MiB of the current page directory,
+	/// so the kernel still reaches the VGA buffer while this one is loaded.
+	/// The mapping is not user accessible, and nothing else may be mapped
+	/// there.
+	pub fn share_identity_map(&mut self) {
+		let current: &[u32; 1024] = unsafe { &*(phys_to_virt(cr3()).as_ptr()) };
+		let page_directory: &mut [u32; 1024] =
+			unsafe { &mut *(phys_to_virt(self.directory).as_mut_ptr()) };
+
+		page_directory[0] = current[0];
+	}
+
+	/// Returns the physical address of the page directory.
+	pub const fn directory(&self) -> PhysAddr {
+		return self.directory;
+	}
+
+	/// Returns whether the page directory is the one loaded in CR3.
+	pub fn is_active(&self) -> bool {
+		return cr3() == self.directory;
+	}
+
+	/// Loads the page directory into CR3.
+	///
+	/// # Safety
+	///
+	/// The user half is not mapped afterwards, so the caller must not touch
+	/// anything only mapped there, like the identity mapped VGA buffer the
+	/// writer prints to, until it switches back. The address space must stay
+	/// alive as long as it is loaded.
+	pub unsafe fn switch_to(&self) {
+		unsafe { set_cr3(self.directory) };
+	}
+
+	/// Maps the 4KiB page at `phys` at `virt` in this address space,
+	/// allocating a page table if needed. The frame stays with the caller,
+	/// like with [`map_range`].
+	pub fn map(
+		&mut self,
+		phys: PhysAddr,
+		virt: VirtAddr,
+		flags: u32,
+	) -> Result<(), AllocError> {
+		assert!(phys.is_aligned(PAGE_SIZE));
+		assert!(virt.is_aligned(PAGE_SIZE));
+
+		let index = virt.as_usize() >> 22;
+		let had_table = (self.pde(index) & flags::PRESENT) != 0;
+
+		try_map_page(self.directory, phys, virt, flags)?;
+		if !had_table {
+			self.owned_tables[index / 32] |= 1 << (index % 32);
+		}
+
+		return Ok(());
+	}
+
+	/// Allocates a frame, fills it with zeroes and maps it at `virt` like
+	/// [`AddressSpace::map`]. The frame belongs to the address space, which
+	/// frees it when dropped, and is returned to fill it.
+	#[allow(clippy::expect_used)]
+	pub fn map_zeroed(
+		&mut self,
+		virt: VirtAddr,
+		flags: u32,
+	) -> Result<PhysAddr, AllocError> {
+		let frames = FRAME_ALLOCATOR
+			.lock()
+			.expect("Frame has not been initialized yet");
+		let frame = frames.allocate_frame().ok_or(AllocError)?;
+		drop(frames);
+
+		unsafe {
+			phys_to_virt(frame)
+				.as_mut_ptr::<u8>()
+				.write_bytes(0, PAGE_SIZE)
+		};
+		if let Err(error) = self.map(frame, virt, flags) {
+			FRAME_ALLOCATOR
+				.lock()
+				.expect("Frame has not been initialized yet")
+				.deallocate_frame(frame);
+			return Err(error);
+		}
+
+		self.owned_frames.push(frame);
+		return Ok(frame);
+	}
+
+	/// Removes the mapping of `virt` in this address space and returns the
+	/// frame it mapped, which stays with the caller, one of
+	/// [`AddressSpace::map_zeroed`] included. Page tables of this address
+	/// space are freed once empty, shared ones are kept.
+	///
+	/// Panics if `virt` is not mapped, like [`unmap_page`].
+	pub fn unmap(&mut self, virt: VirtAddr) -> PhysAddr {
+		assert!(virt.is_aligned(PAGE_SIZE));
+
+		let index = virt.as_usize() >> 22;
+		let owned = self.owns_table(index);
+		let frame = clear_mapping(self.directory, virt, true, !owned);
+
+		if owned && (self.pde(index) & flags::PRESENT) == 0 {
+			self.owned_tables[index / 32] &= !(1 << (index % 32));
+		}
+		self.owned_frames.retain(|&owned| owned != frame);
+
+		return frame;
+	}
+
+	/// Returns the physical address `virt` maps to in this address space,
+	/// like [`translate`].
+	#[must_use]
+	pub fn translate(&self, virt: VirtAddr) -> Option<PhysAddr> {
+		return translate_in(self.directory, virt);
+	}
+
+	// Helper returning the PDE at `index`
+	fn pde(&self, index: usize) -> u32 {
+		let page_directory: &[u32; 1024] =
+			unsafe { &*(phys_to_virt(self.directory).as_ptr()) };
+		return page_directory[index];
+	}
+
+	// Helper returning whether this address space allocated the page table of
+	// the PDE at `index`
+	const fn owns_table(&self, index: usize) -> bool {
+		return (self.owned_tables[index / 32] & (1 << (index % 32))) != 0;
+	}
+}
+
+impl Drop for AddressSpace {
+	#[allow(clippy::expect_used)]
+	fn drop(&mut self) {
+		assert!(!self.is_active(), "Dropping the loaded address space");
+
+		let frames = FRAME_ALLOCATOR
+			.lock()
+			.expect("Frame has not been initialized yet");
+
+		for &frame in &self.owned_frames {
+			frames.deallocate_frame(frame);
+		}
+		for index in 0..1024 {
+			if self.owns_table(index) {
+				let pde = self.pde(index);
+				frames.deallocate_frame(PhysAddr::new(
+					(pde & ADDR_MASK_PDE_TO_PT) as usize,
+				));
+			}
+		}
+		frames.deallocate_frame(self.directory);
+	}
+}
+
 pub fn phys_to_virt(paddr: PhysAddr) -> VirtAddr {
 	VirtAddr::new(paddr.as_usize() + KERNEL_OFFSET)
 }
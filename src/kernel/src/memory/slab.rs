@@ -1,16 +1,22 @@
 //! Implements a slab allocator for fixed-size memory allocations.
 
 use super::{VirtAddr, PAGE_SIZE};
+#[cfg(feature = "slab-debug")]
+use crate::util::rand::rand_range;
 use crate::{
-	collections::intrusive_linked_list::{IntrusiveLinkedList, IntrusiveNode},
-	log_debug, log_error,
+	collections::intrusive_linked_list::{
+		IntrusiveLinkedList, IntrusiveListError, IntrusiveNode,
+	},
+	log_debug, log_error, log_trace,
 	memory::{
-		allocate_dynamic_virt_range,
+		allocate_dynamic_virt_range_aligned,
 		allocator::BUDDY_PAGE_ALLOCATOR,
-		paging::{flags, map_page, phys_to_virt},
+		free_dynamic_virt_range,
+		paging::{
+			flags, map_page, phys_to_virt, translate, unmap_page_keep_frame,
+		},
 		PhysAddr,
 	},
-	println_serial,
 	sync::Locked,
 };
 use core::{
@@ -20,6 +26,45 @@ use core::{
 	ptr::NonNull,
 };
 
+/// Bytes after every object that are checked for overflows on free.
+const RED_ZONE_SIZE: usize = match cfg!(feature = "slab-debug") {
+	true => size_of::<usize>(),
+	false => 0,
+};
+
+/// Fills the free objects, after the free list link, in debug mode.
+#[cfg(feature = "slab-debug")]
+pub const POISON_FREE: u8 = 0x6b;
+/// Written in the red zone behind every object in debug mode.
+#[cfg(feature = "slab-debug")]
+pub const RED_ZONE_CANARY: usize = 0x5a5a_5a5a;
+
+/// Corruption found by the `slab-debug` checks.
+#[cfg(feature = "slab-debug")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlabDebugError {
+	/// A free object was written to, so something used it after freeing.
+	UseAfterFree {
+		/// Offset of the first changed byte in the object.
+		offset: usize,
+	},
+	/// The canary behind an object changed, so a write ran past its end.
+	Overflow,
+}
+
+/// The list of a [`SlabCache`] a slab is on, which follows from how many of
+/// its objects are handed out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SlabList {
+	Full,
+	Partial,
+	Free,
+}
+
+/// Free slabs a cache keeps for reuse, [`SlabCache::dealloc`] returns any
+/// beyond this to the buddy allocator.
+const MAX_FREE_SLABS: usize = 2;
+
 #[derive(Debug)]
 #[repr(C)]
 struct Slab {
@@ -30,18 +75,79 @@ struct Slab {
 	objects_in_use: usize,
 }
 
+/// A snapshot of a slab cache's usage, see [`SlabCache::stats`].
+#[derive(Debug, Clone, Copy)]
+pub struct SlabStats {
+	/// Name the cache was created with.
+	pub name: &'static str,
+	/// Size in bytes of the objects handed out by the cache.
+	pub object_size: usize,
+	/// Number of objects that fit in one slab.
+	pub objects_per_slab: usize,
+	/// Size in bytes of one slab.
+	pub slab_size: usize,
+	/// Number of slabs with every object in use.
+	pub slabs_full: usize,
+	/// Number of slabs with some objects in use.
+	pub slabs_partial: usize,
+	/// Number of slabs with no objects in use.
+	pub slabs_free: usize,
+	/// Number of objects currently allocated from the cache.
+	pub objects_in_use: usize,
+}
+
+impl SlabStats {
+	/// Returns the number of slabs the cache holds, whatever their state.
+	pub fn slabs(&self) -> usize {
+		return self.slabs_full + self.slabs_partial + self.slabs_free;
+	}
+
+	/// Returns the number of bytes the cache's slabs take up.
+	pub fn bytes(&self) -> usize {
+		return self.slabs() * self.slab_size;
+	}
+}
+
+/// A bookkeeping inconsistency found by [`SlabCache::debug_check`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SlabCheckError {
+	/// A slab is on a list that does not match its object count.
+	WrongList,
+	/// A slab is on more than one list.
+	OnTwoLists,
+	/// The slab object counts do not add up to the cache's count.
+	CountMismatch,
+	/// A slab's back pointer names another cache.
+	WrongCache,
+	/// A slab counts more objects in use than it holds.
+	TooManyObjects,
+	/// A slab's free list points outside its objects.
+	FreeListEscapes {
+		/// Address of the offending entry.
+		object: usize,
+	},
+	/// A slab's free list is shorter or longer than its free object count,
+	/// so it was cut or has a cycle.
+	FreeListLength,
+	/// The links of one of the slab lists are broken.
+	BrokenList(IntrusiveListError),
+}
+
 /// Represents a single slab of memory containing multiple fixed-size objects.
 /// This struct itself resides at the beginning of the allocated slab memory.
 pub struct SlabCache {
+	name: &'static str,
 	slabs_full: IntrusiveLinkedList<Slab>,
 	slabs_partial: IntrusiveLinkedList<Slab>,
 	slabs_free: IntrusiveLinkedList<Slab>,
 
 	object_size: usize,
+	object_align: usize,
+	/// Distance between two objects, the object plus its red zone.
+	stride: usize,
 	slab_order: usize,
 	objects_per_slab: usize,
-	// name: &'static str,
-	// lock: Spinlock
+	objects_in_use: usize,
 }
 
 unsafe impl Send for SlabCache {}
@@ -49,11 +155,11 @@ unsafe impl Sync for SlabCache {}
 
 unsafe impl GlobalAlloc for Locked<SlabCache> {
 	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
-		unsafe { self.lock().alloc(layout) }
+		self.with(|cache| unsafe { cache.alloc(layout) })
 	}
 
 	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
-		unsafe { self.lock().dealloc(ptr, layout) }
+		self.with(|cache| unsafe { cache.dealloc(ptr, layout) })
 	}
 }
 
@@ -69,12 +175,31 @@ impl SlabCache {
 	/// The caller receives a raw pointer to uninitialized memory. The layout
 	/// size must be appropriate for this cache (<= `self.object_size`). This
 	/// function assumes exclusive mutable access (`&mut self`).
-	#[allow(clippy::expect_used)]
 	pub unsafe fn alloc(&mut self, layout: Layout) -> *mut u8 {
-		use core::ptr;
-
 		debug_assert!(layout.size() <= self.object_size);
 
+		let object = self.take_object();
+
+		#[cfg(feature = "slab-debug")]
+		if let Some(object) = NonNull::new(object) {
+			if let Err(e) = self.check_poison(object) {
+				self.report(e, object);
+			}
+			if let Some(sample) = self.sample_free(object) {
+				if let Err(e) = self.check_poison(sample) {
+					self.report(e, sample);
+				}
+			}
+		}
+
+		object
+	}
+
+	/// Pops a free object, creating a new slab if every slab is full.
+	#[allow(clippy::expect_used)]
+	fn take_object(&mut self) -> *mut u8 {
+		use core::ptr;
+
 		if !self.slabs_partial.is_empty() {
 			match self.slabs_partial.pop_front() {
 				Some(node) => {
@@ -96,22 +221,12 @@ impl SlabCache {
 		log_debug!("Creating a new cache...");
 
 		let size_to_alloc = (1 << self.slab_order) * PAGE_SIZE;
-		let vaddr_range = allocate_dynamic_virt_range(size_to_alloc)
-			.expect("Ran out of dynamic kernel virtual address space!");
-
-		let phys_ptr: *mut u8 = {
-			let mut buddy = BUDDY_PAGE_ALLOCATOR.lock();
-
-			match buddy.get_mut() {
-				Some(buddy) => {
-					let layout =
-						Layout::from_size_align(size_to_alloc, PAGE_SIZE)
-							.expect("Failed to create Buddy Layout");
+		let slab_layout = Layout::from_size_align(size_to_alloc, PAGE_SIZE)
+			.expect("Failed to create Buddy Layout");
 
-					unsafe { buddy.alloc(layout) }
-				}
-				None => return ptr::null_mut(),
-			}
+		let phys_ptr: *mut u8 = match BUDDY_PAGE_ALLOCATOR.lock() {
+			Some(mut buddy) => unsafe { buddy.alloc(slab_layout) },
+			None => return ptr::null_mut(),
 		};
 
 		if phys_ptr.is_null() {
@@ -119,12 +234,24 @@ impl SlabCache {
 			return ptr::null_mut();
 		}
 
+		// `dealloc` finds the header by masking, so the slab must be aligned
+		// to its size.
+		let Some(vaddr_range) =
+			allocate_dynamic_virt_range_aligned(size_to_alloc, size_to_alloc)
+		else {
+			log_error!("Ran out of dynamic kernel virtual address space!");
+			if let Some(mut buddy) = BUDDY_PAGE_ALLOCATOR.lock() {
+				unsafe { buddy.dealloc(phys_ptr, slab_layout) };
+			}
+			return ptr::null_mut();
+		};
+
 		let paddr_start: PhysAddr = (phys_ptr as usize).into();
 
 		for i in 0..(size_to_alloc / PAGE_SIZE) {
 			let current_paddr = paddr_start + i * PAGE_SIZE;
 			let current_vaddr = vaddr_range + i * PAGE_SIZE;
-			println_serial!(
+			log_trace!(
 				"Mapping vAddr: 0x{:x} - pAddr: 0x{:x}",
 				current_vaddr.as_usize(),
 				current_paddr.as_usize()
@@ -141,11 +268,11 @@ impl SlabCache {
 		let slab_size = (1 << self.slab_order) * PAGE_SIZE;
 
 		let object_start =
-			(vaddr_range + size_of::<Slab>()).align_up(align_of::<usize>());
+			(vaddr_range + size_of::<Slab>()).align_up(self.object_align);
 		let object_end = vaddr_range + slab_size;
 		let object_area_size = object_end.as_usize() - object_start.as_usize();
 
-		let objects_in_slab = object_area_size / self.object_size;
+		let objects_in_slab = object_area_size / self.stride;
 		let object_to_return_ptr = self
 			.setup_free_list(object_start, objects_in_slab)
 			.expect("Newly initialized slab has no free objects!")
@@ -156,7 +283,7 @@ impl SlabCache {
 			let next_free_raw =
 				unsafe { *(object_to_return_ptr as *const *mut u8) };
 			next_free_object = NonNull::new(next_free_raw);
-			println_serial!("Free Object: {:?}", next_free_object);
+			log_trace!("Free Object: {:?}", next_free_object);
 		}
 
 		unsafe {
@@ -174,13 +301,16 @@ impl SlabCache {
 
 		let node_ptr = unsafe { &raw mut (*slab_ptr).list };
 
+		let list = self.list_for(1);
 		log_debug!(
-			"Added new slab {:p} node {:p} to partial list",
+			"Added new slab {:p} node {:p} to {:?} list",
 			slab_ptr,
-			node_ptr
+			node_ptr,
+			list
 		);
 
-		self.slabs_partial.push_back(NonNull::new(node_ptr));
+		self.push_slab(NonNull::new(node_ptr), list);
+		self.objects_in_use += 1;
 
 		object_to_return_ptr
 	}
@@ -208,6 +338,13 @@ impl SlabCache {
 			"Object size too small for free list link"
 		);
 
+		#[cfg(feature = "slab-debug")]
+		if let Some(object) = NonNull::new(ptr) {
+			if let Err(e) = self.check_red_zone(object) {
+				self.report(e, object);
+			}
+		}
+
 		let vaddr: VirtAddr = (ptr as usize).into();
 		let slab_alloc_size = (1 << self.slab_order) * PAGE_SIZE;
 
@@ -223,21 +360,27 @@ impl SlabCache {
 				};
 
 				unsafe { (ptr as *mut usize).write(next_free_ptr_val) };
+				#[cfg(feature = "slab-debug")]
+				self.poison(ptr);
 
 				slab.first_free_object = NonNull::new(ptr);
 
 				let node_ptr = NonNull::new(ptr::addr_of_mut!(slab.list));
 				log_debug!("SlabCache::dealloc: ptr={:p}, slab={:p}, obj_in_use={}, moving slab node {:?}",
                 ptr, slab_ptr, slab.objects_in_use, node_ptr);
-				if slab.objects_in_use == self.objects_per_slab {
-					self.slabs_full.remove(node_ptr);
-					self.slabs_partial.push_back(node_ptr);
-				} else if slab.objects_in_use == 1 {
-					self.slabs_partial.remove(node_ptr);
-					self.slabs_free.push_back(node_ptr);
+				let from = self.list_for(slab.objects_in_use);
+				slab.objects_in_use -= 1;
+				self.objects_in_use -= 1;
+
+				let to = self.list_for(slab.objects_in_use);
+				if from != to {
+					self.list_mut(from).remove(node_ptr);
+					self.push_slab(node_ptr, to);
 				}
 
-				slab.objects_in_use -= 1;
+				if self.slabs_free.len() > MAX_FREE_SLABS {
+					self.shrink_to(MAX_FREE_SLABS);
+				}
 			}
 			None => {
 				log_error!(
@@ -250,47 +393,366 @@ impl SlabCache {
 	}
 }
 
+/// Returns the alignment of the objects in a cache of `size` byte objects.
+///
+/// A power of two size is its own alignment, up to a page, so asking for a
+/// bigger size class also gets a stricter alignment. Other sizes only get
+/// the alignment of the free list link.
+fn object_align(size: usize) -> usize {
+	if size.is_power_of_two() {
+		return size.clamp(align_of::<usize>(), PAGE_SIZE);
+	}
+
+	return align_of::<usize>();
+}
+
 // Public Interface
 impl SlabCache {
-	/// Creates a new `SlabCache` for objects of `size` bytes.
+	/// Creates a new `SlabCache` called `name` for objects of `size` bytes,
+	/// aligned to at least `align`.
 	///
-	/// Calculates the number of objects fitting in a slab based on the
-	/// `slab_order` (which determines the total slab size = `PAGE_SIZE` <<
-	/// `slab_order`).
+	/// Objects get the stricter of `align` and the natural alignment of
+	/// `size`, and are padded to a multiple of it. Calculates the number of
+	/// objects fitting in a slab based on the `slab_order` (which determines
+	/// the total slab size = `PAGE_SIZE` << `slab_order`).
 	///
 	/// # Panics
-	/// Panics if the calculated slab size is too small to hold even one object
-	/// plus the required `Slab` metadata.
-	pub fn new(size: usize, slab_order: usize) -> Self {
-		let object_align = align_of::<usize>();
+	/// Panics if `align` is not a power of two up to `PAGE_SIZE`, or if the
+	/// calculated slab size is too small to hold even one object plus the
+	/// required `Slab` metadata.
+	pub fn new(
+		name: &'static str,
+		size: usize,
+		align: usize,
+		slab_order: usize,
+	) -> Self {
+		assert!(
+			align.is_power_of_two() && align <= PAGE_SIZE,
+			"Slab cache '{}' has an invalid alignment {}",
+			name,
+			align
+		);
+
+		let object_align = object_align(size).max(align);
+		let size = size.max(size_of::<usize>()).next_multiple_of(object_align);
+		let stride = (size + RED_ZONE_SIZE).next_multiple_of(object_align);
 		let metadata_size = size_of::<Slab>();
 		let slab_size = PAGE_SIZE << slab_order;
 
 		let offset = (metadata_size + object_align - 1) & !(object_align - 1);
 		let usable_space = slab_size - offset;
 
-		let mut objects_per_slab = 0;
-		if size > 0 {
-			objects_per_slab = usable_space / size;
-		};
+		let objects_per_slab = usable_space / stride;
 
-		if objects_per_slab == 0 && size > 0 {
+		if objects_per_slab == 0 {
 			panic!("Slab order {} is too small for object size {} with on-slab metadata!", slab_order, size);
 		}
 
 		Self {
+			name,
 			slabs_full: IntrusiveLinkedList::new(),
 			slabs_partial: IntrusiveLinkedList::new(),
 			slabs_free: IntrusiveLinkedList::new(),
 			object_size: size,
+			object_align,
+			stride,
 			slab_order,
 			objects_per_slab,
+			objects_in_use: 0,
 		}
 	}
+
+	/// Walks the three slab lists and checks that every slab is on the list
+	/// its object count calls for, that no slab is on two lists, and that the
+	/// per slab object counts add up to the cache's count. The links of each
+	/// list are validated first.
+	///
+	/// Each slab must also point back to this cache, count no more objects
+	/// than fit in it, and have a free list of exactly its free objects that
+	/// stays inside the slab. The free list walk stops after that many
+	/// entries, so a cycle cannot hang it.
+	pub fn debug_check(&self) -> Result<(), SlabCheckError> {
+		let mut objects_in_use = 0;
+
+		for list in [SlabList::Full, SlabList::Partial, SlabList::Free] {
+			let slabs = match list {
+				SlabList::Full => &self.slabs_full,
+				SlabList::Partial => &self.slabs_partial,
+				SlabList::Free => &self.slabs_free,
+			};
+			slabs.debug_validate().map_err(SlabCheckError::BrokenList)?;
+
+			for node in slabs {
+				let slab = node.container().ok_or(SlabCheckError::WrongList)?;
+				if self.list_for(slab.objects_in_use) != list {
+					return Err(SlabCheckError::WrongList);
+				}
+
+				let node_ptr = NonNull::from(node);
+				let lists =
+					[&self.slabs_full, &self.slabs_partial, &self.slabs_free];
+				if lists.iter().filter(|l| l.contains(node_ptr)).count() != 1 {
+					return Err(SlabCheckError::OnTwoLists);
+				}
+				self.check_slab(slab)?;
+
+				objects_in_use += slab.objects_in_use;
+			}
+		}
+
+		if objects_in_use != self.objects_in_use {
+			return Err(SlabCheckError::CountMismatch);
+		}
+
+		return Ok(());
+	}
+
+	// Helper checking the back pointer, object count and free list of `slab`
+	fn check_slab(&self, slab: &Slab) -> Result<(), SlabCheckError> {
+		if !core::ptr::eq(slab.cache, self) {
+			return Err(SlabCheckError::WrongCache);
+		}
+		if slab.objects_in_use > self.objects_per_slab {
+			return Err(SlabCheckError::TooManyObjects);
+		}
+
+		let first = slab.base_vaddr.as_usize();
+		let end = first + self.objects_per_slab * self.stride;
+		let mut object = slab.first_free_object;
+
+		for _ in 0..self.objects_per_slab - slab.objects_in_use {
+			let Some(entry) = object else {
+				return Err(SlabCheckError::FreeListLength);
+			};
+
+			let addr = entry.as_ptr().addr();
+			if addr < first || addr >= end || (addr - first) % self.stride != 0
+			{
+				return Err(SlabCheckError::FreeListEscapes {
+					object: addr,
+				});
+			}
+			object = NonNull::new(unsafe { *entry.as_ptr().cast::<*mut u8>() });
+		}
+
+		if object.is_some() {
+			return Err(SlabCheckError::FreeListLength);
+		}
+
+		return Ok(());
+	}
+
+	/// Returns the name the cache was created with.
+	pub fn name(&self) -> &'static str {
+		return self.name;
+	}
+
+	/// Returns every free slab to the buddy allocator and returns how many
+	/// were freed.
+	pub fn shrink(&mut self) -> usize {
+		return self.shrink_to(0);
+	}
+
+	/// Returns the layout of one object of this cache.
+	pub fn object_layout(&self) -> Layout {
+		// Safety: `new` checked the alignment and padded the size to it.
+		return unsafe {
+			Layout::from_size_align_unchecked(
+				self.object_size,
+				self.object_align,
+			)
+		};
+	}
+
+	/// Returns a snapshot of the cache's usage.
+	///
+	/// Only reads counters, so it is cheap and never allocates.
+	pub fn stats(&self) -> SlabStats {
+		return SlabStats {
+			name: self.name,
+			object_size: self.object_size,
+			objects_per_slab: self.objects_per_slab,
+			slab_size: PAGE_SIZE << self.slab_order,
+			slabs_full: self.slabs_full.len(),
+			slabs_partial: self.slabs_partial.len(),
+			slabs_free: self.slabs_free.len(),
+			objects_in_use: self.objects_in_use,
+		};
+	}
 }
 
 // Private interface
 impl SlabCache {
+	/// Frees slabs from `slabs_free` until at most `keep` are left and
+	/// returns how many were freed.
+	#[allow(clippy::expect_used)]
+	fn shrink_to(&mut self, keep: usize) -> usize {
+		let slab_size = PAGE_SIZE << self.slab_order;
+		let layout = Layout::from_size_align(slab_size, PAGE_SIZE)
+			.expect("Failed to create Buddy Layout");
+		let mut freed = 0;
+
+		while self.slabs_free.len() > keep {
+			let Some(node) = self.slabs_free.pop_front() else {
+				break;
+			};
+
+			// The header lives in the pages freed below, only its address is
+			// used from here on.
+			let base = VirtAddr::new(node.as_ptr() as usize & !(slab_size - 1));
+			let Some(paddr) = translate(base) else {
+				log_error!(
+					"Free slab at 0x{:x} is not mapped",
+					base.as_usize()
+				);
+				continue;
+			};
+
+			for page in (0..slab_size).step_by(PAGE_SIZE) {
+				unmap_page_keep_frame(base + page);
+			}
+			free_dynamic_virt_range(base, slab_size);
+
+			match BUDDY_PAGE_ALLOCATOR.lock() {
+				Some(mut buddy) => unsafe {
+					buddy.dealloc(paddr.as_mut_ptr(), layout)
+				},
+				None => {
+					log_error!("Buddy allocator gone, leaking free slab");
+					continue;
+				}
+			}
+
+			log_debug!(
+				"Returned slab 0x{:x} to the buddy allocator",
+				base.as_usize()
+			);
+			freed += 1;
+		}
+
+		return freed;
+	}
+
+	/// Fills a free object after its free list link with [`POISON_FREE`] and
+	/// arms the canary in its red zone.
+	#[cfg(feature = "slab-debug")]
+	fn poison(&self, object: *mut u8) {
+		let link = size_of::<usize>();
+
+		unsafe {
+			object
+				.add(link)
+				.write_bytes(POISON_FREE, self.object_size - link);
+			object
+				.add(self.object_size)
+				.cast::<usize>()
+				.write_unaligned(RED_ZONE_CANARY);
+		}
+	}
+
+	/// Checks that the free `object` still holds its poison, which
+	/// [`SlabCache::alloc`] does before handing it out.
+	#[cfg(feature = "slab-debug")]
+	pub fn check_poison(
+		&self,
+		object: NonNull<u8>,
+	) -> Result<(), SlabDebugError> {
+		let link = size_of::<usize>();
+		let bytes = unsafe {
+			core::slice::from_raw_parts(object.as_ptr(), self.object_size)
+		};
+
+		match bytes[link..].iter().position(|&byte| byte != POISON_FREE) {
+			Some(offset) => {
+				return Err(SlabDebugError::UseAfterFree {
+					offset: link + offset,
+				})
+			}
+			None => return Ok(()),
+		}
+	}
+
+	/// Returns a free object picked at random from the slab `object` is in,
+	/// `None` if it has none left. [`SlabCache::alloc`] checks its poison
+	/// too, so a write to a freed object is found even while it is not
+	/// handed out again.
+	#[cfg(feature = "slab-debug")]
+	pub fn sample_free(&self, object: NonNull<u8>) -> Option<NonNull<u8>> {
+		let slab_alloc_size = (1 << self.slab_order) * PAGE_SIZE;
+		let slab_ptr: *const Slab = core::ptr::with_exposed_provenance(
+			object.as_ptr() as usize & !(slab_alloc_size - 1),
+		);
+		let slab = unsafe { slab_ptr.as_ref() }?;
+
+		let free = self.objects_per_slab.checked_sub(slab.objects_in_use)?;
+		if free == 0 {
+			return None;
+		}
+
+		let mut sample = slab.first_free_object?;
+		for _ in 0..rand_range(0, free - 1) {
+			let next = unsafe { *sample.as_ptr().cast::<*mut u8>() };
+			sample = NonNull::new(next)?;
+		}
+
+		return Some(sample);
+	}
+
+	/// Checks the canary behind `object`, which [`SlabCache::dealloc`] does
+	/// before taking it back.
+	#[cfg(feature = "slab-debug")]
+	pub fn check_red_zone(
+		&self,
+		object: NonNull<u8>,
+	) -> Result<(), SlabDebugError> {
+		let canary = unsafe {
+			object
+				.as_ptr()
+				.add(self.object_size)
+				.cast::<usize>()
+				.read_unaligned()
+		};
+
+		if canary != RED_ZONE_CANARY {
+			return Err(SlabDebugError::Overflow);
+		}
+
+		return Ok(());
+	}
+
+	/// Logs the corruption with a dump of the object and its red zone, then
+	/// panics.
+	#[cfg(feature = "slab-debug")]
+	fn report(&self, error: SlabDebugError, object: NonNull<u8>) -> ! {
+		log_error!(
+			"Slab cache '{}': {:?} at {:p}",
+			self.name,
+			error,
+			object.as_ptr()
+		);
+
+		let bytes = unsafe {
+			core::slice::from_raw_parts(
+				object.as_ptr(),
+				self.object_size + RED_ZONE_SIZE,
+			)
+		};
+		for (row, chunk) in bytes.chunks(16).enumerate() {
+			log_error!(
+				"  {:p}: {:02x?}",
+				object.as_ptr().wrapping_add(row * 16),
+				chunk
+			);
+		}
+
+		panic!(
+			"Slab cache '{}' corrupted: {:?} at {:p}",
+			self.name,
+			error,
+			object.as_ptr()
+		);
+	}
+
 	fn setup_free_list(
 		&self,
 		start: VirtAddr,
@@ -302,7 +764,7 @@ impl SlabCache {
 			return None;
 		}
 
-		println_serial!(
+		log_trace!(
 			"setup_free_list: start=0x{:x}, count={}, object_size={}",
 			start.as_usize(),
 			count,
@@ -311,15 +773,18 @@ impl SlabCache {
 
 		let mut current_ptr = start.as_mut_ptr::<u8>();
 		for i in 0..(count - 1) {
-			let next_ptr_val = start.add((i + 1) * self.object_size);
+			let next_ptr_val = start.add((i + 1) * self.stride);
 			unsafe {
 				ptr::write(current_ptr as *mut usize, next_ptr_val.as_usize())
 			};
-			current_ptr =
-				start.add((i + 1) * self.object_size).as_mut_ptr::<u8>();
+			#[cfg(feature = "slab-debug")]
+			self.poison(current_ptr);
+			current_ptr = start.add((i + 1) * self.stride).as_mut_ptr::<u8>();
 		}
 
 		unsafe { ptr::write(current_ptr as *mut usize, 0) };
+		#[cfg(feature = "slab-debug")]
+		self.poison(current_ptr);
 		NonNull::new(start.as_mut_ptr::<u8>())
 	}
 
@@ -328,7 +793,7 @@ impl SlabCache {
 		mut popped_node: NonNull<IntrusiveNode<Slab>>,
 	) -> Option<*mut u8> {
 		let slab = unsafe { popped_node.as_mut().container_mut()? };
-		println_serial!("Slab: {:?}", slab);
+		log_trace!("Slab: {:?}", slab);
 
 		let object_ptr = slab.first_free_object.take()?.as_ptr();
 
@@ -338,14 +803,74 @@ impl SlabCache {
 		};
 
 		slab.objects_in_use += 1;
+		self.objects_in_use += 1;
 
-		if slab.objects_in_use == self.objects_per_slab {
-			println_serial!("Slab {:p} is full", popped_node.as_ptr());
-			self.slabs_full.push_front(Some(popped_node));
-		} else {
-			self.slabs_partial.push_front(Some(popped_node));
+		let list = self.list_for(slab.objects_in_use);
+		if list == SlabList::Full {
+			log_trace!("Slab {:p} is full", popped_node.as_ptr());
 		}
+		self.push_slab(Some(popped_node), list);
 
 		Some(object_ptr)
 	}
+
+	/// Returns the list a slab with `objects_in_use` objects handed out
+	/// belongs on.
+	fn list_for(&self, objects_in_use: usize) -> SlabList {
+		if objects_in_use == 0 {
+			return SlabList::Free;
+		}
+		if objects_in_use >= self.objects_per_slab {
+			return SlabList::Full;
+		}
+
+		return SlabList::Partial;
+	}
+
+	fn list_mut(&mut self, list: SlabList) -> &mut IntrusiveLinkedList<Slab> {
+		match list {
+			SlabList::Full => return &mut self.slabs_full,
+			SlabList::Partial => return &mut self.slabs_partial,
+			SlabList::Free => return &mut self.slabs_free,
+		}
+	}
+
+	/// Puts a slab that is on no list at the front of `list`.
+	fn push_slab(
+		&mut self,
+		node: Option<NonNull<IntrusiveNode<Slab>>>,
+		list: SlabList,
+	) {
+		debug_assert!(
+			node.is_some_and(|node| !unsafe { node.as_ref() }.is_linked()),
+			"Slab node {:?} is already on a list",
+			node
+		);
+
+		self.list_mut(list).push_front(node);
+		#[cfg(feature = "slab-debug")]
+		self.validate_lists();
+	}
+
+	/// Logs it if the links of one of the slab lists broke, checked after
+	/// every move in debug mode.
+	#[cfg(feature = "slab-debug")]
+	fn validate_lists(&self) {
+		let lists = [
+			("full", &self.slabs_full),
+			("partial", &self.slabs_partial),
+			("free", &self.slabs_free),
+		];
+
+		for (name, list) in lists {
+			if let Err(err) = list.debug_validate() {
+				log_error!(
+					"SlabCache {}: {} list broken: {:?}",
+					self.name,
+					name,
+					err
+				);
+			}
+		}
+	}
 }
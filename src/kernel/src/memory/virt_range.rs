@@ -0,0 +1,111 @@
+//! Allocator for ranges of a window of kernel virtual address space.
+//!
+//! Hands out page aligned ranges first fit from the gaps between the ranges
+//! already handed out, which a [`RangeMap`] keeps in address order, so freed
+//! ranges merge with their free neighbours on their own. A free must match an
+//! allocation. The map takes its nodes from the node pool.
+
+use super::{node_pool::NodeAllocatorWrapper, VirtAddr, PAGE_SIZE};
+use crate::{collections::range_map::RangeMap, log_warn};
+use core::ops::Range;
+
+/// Node pool slots reserved for the range lists of the dynamic window.
+pub const VIRT_RANGE_NODES: usize = 4096;
+
+/// A range of virtual addresses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VirtRange {
+	/// First address of the range.
+	pub start: usize,
+	/// Length of the range in bytes.
+	pub len: usize,
+}
+
+impl VirtRange {
+	/// Returns the address after the range.
+	pub const fn end(&self) -> usize {
+		return self.start + self.len;
+	}
+}
+
+/// First fit allocator over a window of virtual address space, see the
+/// module documentation.
+pub struct VirtRangeAllocator {
+	window: VirtRange,
+	used: RangeMap<(), NodeAllocatorWrapper>,
+}
+
+unsafe impl Send for VirtRangeAllocator {}
+
+impl VirtRangeAllocator {
+	/// Creates an allocator whose whole window, `len` bytes from `start`, is
+	/// free. Both must be page aligned.
+	pub fn new(start: VirtAddr, len: usize) -> Self {
+		assert!(start.is_aligned(PAGE_SIZE) && len % PAGE_SIZE == 0);
+
+		return Self {
+			window: VirtRange {
+				start: start.as_usize(),
+				len,
+			},
+			used: RangeMap::new_in(NodeAllocatorWrapper),
+		};
+	}
+
+	/// Allocates `size` bytes, rounded up to pages, starting at a multiple of
+	/// `align`, which must be a power of two of at least `PAGE_SIZE`.
+	pub fn allocate(&mut self, size: usize, align: usize) -> Option<VirtAddr> {
+		debug_assert!(align.is_power_of_two() && align >= PAGE_SIZE);
+		let size = size.checked_next_multiple_of(PAGE_SIZE)?;
+
+		let start = self.used.find_gap_aligned(size, align, self.window())?;
+		self.used.insert(start, size, ()).ok()?;
+		return Some(VirtAddr::new(start));
+	}
+
+	/// Frees the range of `size` bytes at `addr` that [`allocate`] handed
+	/// out. Logs a warning and keeps everything as it was if there is no
+	/// such allocation.
+	///
+	/// [`allocate`]: VirtRangeAllocator::allocate
+	pub fn free(&mut self, addr: VirtAddr, size: usize) -> bool {
+		let start = addr.as_usize();
+		let len = size.next_multiple_of(PAGE_SIZE);
+
+		let allocated = self
+			.used
+			.find(start)
+			.is_some_and(|range| range.start == start && range.len == len);
+		if !allocated {
+			log_warn!(
+				"Freeing virtual range {:#x}+{:#x} that was not allocated",
+				start,
+				len
+			);
+			return false;
+		}
+
+		self.used.remove(start);
+		return true;
+	}
+
+	/// Returns the number of free ranges, one when nothing is allocated.
+	pub fn free_ranges(&self) -> usize {
+		return self.used.gaps(self.window()).count();
+	}
+
+	/// Returns the number of ranges handed out.
+	pub fn used_ranges(&self) -> usize {
+		return self.used.len();
+	}
+
+	/// Returns the number of bytes that are free.
+	pub fn free_bytes(&self) -> usize {
+		return self.used.gaps(self.window()).map(|gap| gap.len()).sum();
+	}
+
+	// Helper returning the window as an address range
+	fn window(&self) -> Range<usize> {
+		return self.window.start..self.window.end();
+	}
+}
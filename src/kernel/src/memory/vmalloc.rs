@@ -0,0 +1,135 @@
+//! Virtually contiguous kernel buffers backed by scattered frames.
+//!
+//! [`vmalloc`] maps one frame from the frame allocator per page into a range
+//! of the dynamic virtual window, so big buffers work even when no physically
+//! contiguous run of that size is left. The areas handed out are recorded in
+//! a list of nodes from [`kmalloc_obj`], so [`vfree`] only needs the start
+//! address.
+
+use super::{
+	allocate_dynamic_virt_range,
+	frame::FRAME_ALLOCATOR,
+	free_dynamic_virt_range,
+	kalloc::{kfree_obj, kmalloc_obj},
+	paging::{flags, map_page, unmap_page},
+	virt_range::VirtRange,
+	VirtAddr, PAGE_SIZE,
+};
+use crate::{log_warn, sync::Mutex};
+use core::ptr::NonNull;
+
+static VMALLOC_AREAS: Mutex<Areas> = Mutex::named("VMALLOC_AREAS", Areas(None));
+
+/// The first of the areas [`vmalloc`] handed out.
+struct Areas(Option<NonNull<Area>>);
+
+unsafe impl Send for Areas {}
+
+/// An area [`vmalloc`] handed out and the next one in the list.
+struct Area {
+	range: VirtRange,
+	next: Option<NonNull<Area>>,
+}
+
+/// Allocates `size` bytes, rounded up to pages, of virtually contiguous
+/// memory. The memory is not zeroed.
+///
+/// Returns `None` if `size` is zero or there is not enough virtual address
+/// space or frames.
+pub fn vmalloc(size: usize) -> Option<VirtAddr> {
+	if size == 0 {
+		return None;
+	}
+
+	let len = size.checked_next_multiple_of(PAGE_SIZE)?;
+	let area = kmalloc_obj::<Area>().ok()?;
+	let Some(start) = allocate_dynamic_virt_range(len) else {
+		unsafe { kfree_obj(area) };
+		return None;
+	};
+
+	for offset in (0..len).step_by(PAGE_SIZE) {
+		// Unlocked before mapping, which may need a frame for a page table
+		let frame = FRAME_ALLOCATOR
+			.lock()
+			.and_then(|frames| frames.allocate_frame());
+
+		let Some(frame) = frame else {
+			unmap_area(start, offset);
+			free_dynamic_virt_range(start, len);
+			unsafe { kfree_obj(area) };
+			return None;
+		};
+
+		map_page(frame, start + offset, flags::PRESENT | flags::WRITABLE);
+	}
+
+	let mut areas = VMALLOC_AREAS.lock();
+	unsafe {
+		area.write(Area {
+			range: VirtRange {
+				start: start.as_usize(),
+				len,
+			},
+			next: areas.0,
+		})
+	};
+	areas.0 = Some(area);
+
+	return Some(start);
+}
+
+/// Frees an area from [`vmalloc`], unmapping its pages and giving their
+/// frames and its addresses back. Logs a warning if `addr` is not the start
+/// of such an area.
+pub fn vfree(addr: VirtAddr) {
+	let Some(len) = take_area(addr) else {
+		log_warn!("vfree: no vmalloc area at {:#x}", addr.as_usize());
+		return;
+	};
+
+	unmap_area(addr, len);
+	free_dynamic_virt_range(addr, len);
+}
+
+/// Returns the size of the [`vmalloc`] area starting at `addr`.
+pub fn vmalloc_size(addr: VirtAddr) -> Option<usize> {
+	let areas = VMALLOC_AREAS.lock();
+	let mut node = areas.0;
+
+	while let Some(area) = node {
+		let area = unsafe { area.as_ref() };
+		if area.range.start == addr.as_usize() {
+			return Some(area.range.len);
+		}
+		node = area.next;
+	}
+
+	return None;
+}
+
+// Helper to remove the area at `addr` from the registry, returning its size
+fn take_area(addr: VirtAddr) -> Option<usize> {
+	let mut areas = VMALLOC_AREAS.lock();
+	let mut link = &mut areas.0;
+
+	while let Some(mut area) = *link {
+		let node = unsafe { area.as_mut() };
+		if node.range.start == addr.as_usize() {
+			let len = node.range.len;
+			*link = node.next;
+			unsafe { kfree_obj(area) };
+			return Some(len);
+		}
+		link = &mut node.next;
+	}
+
+	return None;
+}
+
+// Helper to unmap the first `len` bytes of an area, freeing their frames
+fn unmap_area(start: VirtAddr, len: usize) {
+	for offset in (0..len).step_by(PAGE_SIZE) {
+		unmap_page(start + offset);
+	}
+}
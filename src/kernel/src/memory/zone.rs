@@ -0,0 +1,40 @@
+//! Physical memory zones, the address limits an allocation can ask for.
+//!
+//! ISA DMA and some older devices only reach the first 16 MiB of physical
+//! memory, [`ZONE_DMA`]. Everything above is [`ZONE_NORMAL`]. Zones are bit
+//! flags, so `ZONE_DMA | ZONE_NORMAL` accepts memory from anywhere.
+
+use super::PhysAddr;
+use core::ops::Range;
+
+/// Memory below [`DMA_ZONE_END`].
+pub const ZONE_DMA: u32 = 1 << 0;
+/// Memory at or above [`DMA_ZONE_END`].
+pub const ZONE_NORMAL: u32 = 1 << 1;
+
+/// First physical address ISA DMA cannot reach.
+pub const DMA_ZONE_END: PhysAddr = PhysAddr::new(16 * 1024 * 1024);
+
+/// Every zone with the name `meminfo` shows for it.
+pub const ZONES: [(u32, &str); 2] =
+	[(ZONE_DMA, "DMA"), (ZONE_NORMAL, "Normal")];
+
+/// Returns the physical addresses the `zones` cover, or `None` if `zones`
+/// names no zone. The end of [`ZONE_NORMAL`] is the last address, as the
+/// first one past 4 GiB does not fit.
+pub const fn zone_range(zones: u32) -> Option<Range<PhysAddr>> {
+	let start = match zones & ZONE_DMA != 0 {
+		true => PhysAddr::new(0),
+		false => DMA_ZONE_END,
+	};
+	let end = match zones & ZONE_NORMAL != 0 {
+		true => PhysAddr::new(usize::MAX),
+		false => DMA_ZONE_END,
+	};
+
+	if zones & (ZONE_DMA | ZONE_NORMAL) == 0 {
+		return None;
+	}
+
+	return Some(start..end);
+}
@@ -1,15 +1,37 @@
-use crate::{println, println_serial, with_fg_color};
+use crate::{
+	arch::x86::{
+		backtrace::{current_ebp, print_backtrace},
+		cpu::{cli, halt_loop},
+	},
+	macros, println, println_serial,
+	tty::panic_screen,
+	with_fg_color,
+};
 use core::panic::PanicInfo;
 
+/// Pitch and length of the beep a panic makes, for machines without a
+/// screen.
+#[cfg(not(test))]
+const PANIC_BEEP_HZ: u32 = 880;
+#[cfg(not(test))]
+const PANIC_BEEP_MS: u32 = 300;
+
 #[cfg(not(test))]
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-	with_fg_color!(VgaColour::Red, {
-		println!("{}", info);
-		println_serial!("{}", info);
-	});
+	cli();
+	// The panicking code never releases its locks
+	#[cfg(feature = "lockdep")]
+	crate::sync::lockdep::disable();
+	panic_screen::show(info);
+	// Interrupts are off, so it spins for the length
+	crate::device::speaker::beep(PANIC_BEEP_HZ, PANIC_BEEP_MS);
+	// Safety: interrupts are off and the panicking code never runs again,
+	// so whoever held the port is gone.
+	unsafe { macros::serial::_print_serial_forced(format_args!("{}\n", info)) };
+	print_backtrace(current_ebp());
 
-	loop {}
+	halt_loop();
 }
 
 #[cfg(test)]
@@ -17,6 +39,9 @@ fn panic(info: &PanicInfo) -> ! {
 fn panic(_info: &PanicInfo) -> ! {
 	use crate::tests::{exit_qemu, QFAILURE};
 
+	#[cfg(feature = "lockdep")]
+	crate::sync::lockdep::disable();
+
 	with_fg_color!(VgaColour::Red, {
 		println_serial!("[failed]\n");
 		println_serial!("Error: {}\n", _info);
@@ -0,0 +1,84 @@
+//! The kernel's symbol table, to name the addresses in backtraces.
+//!
+//! `build.rs` generates the table from `ferrite.sym`, the `nm` listing of the
+//! previous link. `make all` writes it and relinks until the listing stops
+//! changing. The table sits at the end of `.rodata`, after all code, so its
+//! size does not move the functions it names. `make test` does the same for
+//! the test kernel with a listing of its own. Its symbolization test fails
+//! without one, unless the build sets `FERRITE_NO_SYMBOLS`.
+//!
+//! A kernel built some other way embeds whatever listing is there, maybe one
+//! of another binary. The table is only used if it lists [`count`] at the
+//! address it has in this image, otherwise it counts as empty and addresses
+//! stay bare rather than getting wrong names.
+
+use crate::{
+	memory::{get_kernel_text, VirtAddr},
+	sync::OnceLock,
+};
+use core::fmt;
+
+include!(concat!(env!("OUT_DIR"), "/symbols.rs"));
+
+/// The table if it matches this image, see the module documentation.
+static SYMBOLS: OnceLock<&'static [(usize, &'static str)]> = OnceLock::new();
+
+// Helper returning the table, empty if it is of another binary
+fn symbols() -> &'static [(usize, &'static str)] {
+	return SYMBOLS.get_or_init(|| {
+		let here = count as usize;
+		let matches = SYMBOL_TABLE.iter().any(|&(start, name)| {
+			start == here && name.ends_with("symbols::count")
+		});
+
+		match matches {
+			true => return &SYMBOL_TABLE,
+			false => return &[],
+		}
+	});
+}
+
+/// Returns the function `address` lies in and the offset into it, or `None`
+/// outside the kernel's code or before the first known function.
+pub fn resolve(address: VirtAddr) -> Option<(&'static str, usize)> {
+	if !get_kernel_text().contains(&address) {
+		return None;
+	}
+
+	let address = address.as_usize();
+	let symbols = symbols();
+	let index = symbols.partition_point(|&(start, _)| start <= address);
+	let (start, name) = symbols.get(index.checked_sub(1)?)?;
+
+	return Some((name, address - start));
+}
+
+/// Returns the number of functions in the table, 0 if it was built without
+/// a listing or with the listing of another binary.
+pub fn count() -> usize {
+	return symbols().len();
+}
+
+/// Returns whether the table was built from a listing that is not of this
+/// binary, so it is not used.
+pub fn is_stale() -> bool {
+	return count() == 0 && !SYMBOL_TABLE.is_empty();
+}
+
+/// Formats an address as `0xc0101234 kernel::main+0x12`, or bare if it
+/// resolves to nothing.
+#[derive(Clone, Copy)]
+pub struct Symbolized(pub VirtAddr);
+
+impl fmt::Display for Symbolized {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		write!(f, "{:#010x}", self.0.as_usize())?;
+
+		match resolve(self.0) {
+			Some((name, offset)) => {
+				return write!(f, " {}+{:#x}", name, offset)
+			}
+			None => return Ok(()),
+		}
+	}
+}
@@ -0,0 +1,71 @@
+use super::{Mutex, MutexGuard, OnceLock};
+
+/// A [`Mutex`] whose value is only set up at runtime, for statics such as the
+/// allocators that cannot be built in a const context.
+///
+/// Until [`Global::init`] runs every accessor returns `None`, so callers
+/// decide what an uninitialized global means for them instead of each one
+/// unwrapping an inner `OnceCell`.
+pub struct Global<T> {
+	name: Option<&'static str>,
+	cell: OnceLock<Mutex<T>>,
+}
+
+impl<T> Global<T> {
+	/// Creates a global with no value yet.
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		return Self {
+			name: None,
+			cell: OnceLock::new(),
+		};
+	}
+
+	/// Like [`Global::new`], for a lock called `name`, see [`Mutex::named`].
+	pub const fn named(name: &'static str) -> Self {
+		return Self {
+			name: Some(name),
+			cell: OnceLock::new(),
+		};
+	}
+
+	/// Sets the value. Hands `value` back if it was set before.
+	pub fn init(&self, value: T) -> Result<(), T> {
+		return self
+			.cell
+			.set(Mutex::with_name(self.name, value))
+			.map_err(Mutex::into_inner);
+	}
+
+	/// Returns the mutex, setting the value to what `f` returns first if it
+	/// was never set.
+	pub fn get_or_init(&self, f: impl FnOnce() -> T) -> &Mutex<T> {
+		return self.cell.get_or_init(|| Mutex::with_name(self.name, f()));
+	}
+
+	/// Returns the mutex, or `None` before [`Global::init`].
+	pub fn get(&self) -> Option<&Mutex<T>> {
+		return self.cell.get();
+	}
+
+	/// Locks the value, or returns `None` before [`Global::init`].
+	#[track_caller]
+	pub fn lock(&self) -> Option<MutexGuard<T>> {
+		match self.get() {
+			Some(mutex) => return Some(mutex.lock()),
+			None => return None,
+		}
+	}
+
+	/// Runs `f` on the locked value and returns what it returns, or `None`
+	/// before [`Global::init`].
+	#[track_caller]
+	pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> Option<R> {
+		return self.lock().map(|mut value| f(&mut value));
+	}
+
+	/// Returns whether the value was set.
+	pub fn is_initialized(&self) -> bool {
+		return self.cell.get().is_some();
+	}
+}
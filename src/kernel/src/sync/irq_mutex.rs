@@ -0,0 +1,132 @@
+use super::{Mutex, MutexGuard};
+use crate::arch::x86::cpu::{cli, interrupts_enabled, sti};
+use core::{
+	mem::ManuallyDrop,
+	ops::{Deref, DerefMut},
+};
+
+/// A [`Mutex`] that keeps interrupts disabled while it is held.
+///
+/// An interrupt handler spinning on a lock the code it interrupted holds
+/// waits forever, that code only runs again once the handler returns. Taking
+/// the lock with interrupts off rules that out for every lock an interrupt
+/// handler may take, see the [module documentation](super).
+pub struct IrqMutex<T> {
+	inner: Mutex<T>,
+}
+
+/// An RAII guard for an [`IrqMutex`].
+///
+/// Dropping it releases the lock and then enables interrupts again if they
+/// were enabled when it was taken. Nested guards must be dropped in the
+/// reverse order they were taken in, or interrupts come back too early.
+/// Like the [`MutexGuard`] inside, it keeps preemption disabled while it
+/// lives.
+pub struct IrqMutexGuard<'a, T> {
+	guard: ManuallyDrop<MutexGuard<'a, T>>,
+	interrupts: bool,
+}
+
+impl<T> IrqMutex<T> {
+	/// Creates a new `IrqMutex` in an unlocked state containing the provided
+	/// value.
+	pub const fn new(value: T) -> Self {
+		return Self {
+			inner: Mutex::new(value),
+		};
+	}
+
+	/// Like [`IrqMutex::new`], for a lock called `name`, see
+	/// [`Mutex::named`].
+	pub const fn named(name: &'static str, value: T) -> Self {
+		return Self {
+			inner: Mutex::named(name, value),
+		};
+	}
+
+	/// Disables interrupts and acquires the lock, see [`Mutex::lock`].
+	#[track_caller]
+	pub fn lock(&self) -> IrqMutexGuard<T> {
+		let interrupts = interrupts_enabled();
+		cli();
+
+		return IrqMutexGuard {
+			guard: ManuallyDrop::new(self.inner.lock()),
+			interrupts,
+		};
+	}
+
+	/// Acquires the lock if it is free, without spinning. Interrupts are
+	/// left as they were if it is not.
+	pub fn try_lock(&self) -> Option<IrqMutexGuard<T>> {
+		let interrupts = interrupts_enabled();
+		cli();
+
+		match self.inner.try_lock() {
+			Some(guard) => {
+				return Some(IrqMutexGuard {
+					guard: ManuallyDrop::new(guard),
+					interrupts,
+				})
+			}
+			None => {
+				if interrupts {
+					sti();
+				}
+				return None;
+			}
+		}
+	}
+
+	/// Acquires the lock, breaking it first if it is held.
+	///
+	/// # Safety
+	/// As for [`Mutex::force_lock`].
+	#[track_caller]
+	pub unsafe fn force_lock(&self) -> IrqMutexGuard<T> {
+		if let Some(guard) = self.try_lock() {
+			return guard;
+		}
+
+		unsafe { self.force_unlock() };
+		return self.lock();
+	}
+
+	/// Releases the lock no matter who holds it. Does not touch interrupts,
+	/// the holder's guard is never dropped.
+	///
+	/// # Safety
+	/// As for [`Mutex::force_unlock`].
+	pub unsafe fn force_unlock(&self) {
+		unsafe { self.inner.force_unlock() };
+	}
+
+	/// Returns whether the lock is held, see [`Mutex::is_locked`].
+	pub fn is_locked(&self) -> bool {
+		return self.inner.is_locked();
+	}
+}
+
+impl<T> Deref for IrqMutexGuard<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		return &self.guard;
+	}
+}
+
+impl<T> DerefMut for IrqMutexGuard<'_, T> {
+	fn deref_mut(&mut self) -> &mut T {
+		return &mut self.guard;
+	}
+}
+
+impl<T> Drop for IrqMutexGuard<'_, T> {
+	fn drop(&mut self) {
+		// Safety: the guard is not used after this.
+		unsafe { ManuallyDrop::drop(&mut self.guard) };
+		if self.interrupts {
+			sti();
+		}
+	}
+}
@@ -0,0 +1,310 @@
+//! Lock order checking, built with the `lockdep` feature.
+//!
+//! Every [`Mutex`](super::Mutex) built with a name (see
+//! [`Mutex::named`](super::Mutex::named)) belongs to the lock class of that
+//! name. Locks without a name are not tracked. While a lock is held its class
+//! sits on a stack of held classes (one, there is a single CPU), and taking
+//! another lock records the edge from every held class to the new one in an
+//! adjacency matrix.
+//!
+//! Taking a lock whose class can already reach a held class through those
+//! edges means two code paths take the same locks in opposite orders, which
+//! deadlocks as soon as they interleave. [`Mutex::lock`](super::Mutex::lock)
+//! panics then, naming both locks, as it does for a lock taken twice. It is
+//! reported the first time the second order shows up, whether or not it
+//! would have hung this time.
+//!
+//! `try_lock` never waits, so it records no edges, but a lock it took is held
+//! like any other.
+
+use super::mutex::Mutex;
+use crate::{arch::x86::cpu::without_interrupts, tty::serial::print_unlocked};
+use core::{
+	fmt,
+	panic::Location,
+	ptr,
+	sync::atomic::{AtomicBool, AtomicPtr, AtomicU8, AtomicUsize, Ordering},
+};
+
+/// Number of lock names that can be told apart.
+pub const MAX_LOCK_CLASSES: usize = 64;
+
+/// Number of tracked locks that can be held at once.
+pub const MAX_HELD_LOCKS: usize = 16;
+
+/// Lock classes, by name. `CLASS_COUNT` entries are in use.
+static CLASS_NAMES: [AtomicPtr<u8>; MAX_LOCK_CLASSES] =
+	[const { AtomicPtr::new(ptr::null_mut()) }; MAX_LOCK_CLASSES];
+static CLASS_LENS: [AtomicUsize; MAX_LOCK_CLASSES] =
+	[const { AtomicUsize::new(0) }; MAX_LOCK_CLASSES];
+static CLASS_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// `EDGES[a][b]` is set once class `b` was taken while `a` was held.
+static EDGES: [[AtomicBool; MAX_LOCK_CLASSES]; MAX_LOCK_CLASSES] =
+	[const { [const { AtomicBool::new(false) }; MAX_LOCK_CLASSES] };
+		MAX_LOCK_CLASSES];
+
+/// Classes of the locks held, oldest first.
+static HELD: [AtomicU8; MAX_HELD_LOCKS] =
+	[const { AtomicU8::new(0) }; MAX_HELD_LOCKS];
+static HELD_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+static ENABLED: AtomicBool = AtomicBool::new(true);
+
+/// The lock class of a [`Mutex`].
+pub struct LockClass {
+	name: Option<&'static str>,
+	/// Index in `CLASS_NAMES` plus one, 0 until first taken.
+	id: AtomicU8,
+}
+
+impl LockClass {
+	/// Creates the class of a lock called `name`, an untracked one for
+	/// `None`.
+	pub const fn new(name: Option<&'static str>) -> Self {
+		return Self {
+			name,
+			id: AtomicU8::new(0),
+		};
+	}
+
+	/// Returns the class index, registering the name on first use. `None`
+	/// for unnamed locks and once the table is full.
+	fn index(&self) -> Option<usize> {
+		let name = self.name?;
+
+		match self.id.load(Ordering::Relaxed) {
+			0 => {
+				let index = register(name)?;
+				self.id.store(index as u8 + 1, Ordering::Relaxed);
+				return Some(index);
+			}
+			id => return Some(id as usize - 1),
+		}
+	}
+}
+
+/// An acquisition [`check`] refuses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockdepError {
+	/// The lock is already held.
+	Recursive(&'static str),
+	/// `held` was taken while holding `acquiring` before, the opposite order.
+	Inversion {
+		/// The lock held now.
+		held: &'static str,
+		/// The lock being taken.
+		acquiring: &'static str,
+	},
+}
+
+impl fmt::Display for LockdepError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			Self::Recursive(name) => {
+				return write!(f, "lock {} taken while already held", name)
+			}
+			Self::Inversion {
+				held,
+				acquiring,
+			} => {
+				return write!(
+					f,
+					"lock {} taken while holding {}, but {} was taken while holding {} before",
+					acquiring, held, held, acquiring
+				)
+			}
+		}
+	}
+}
+
+/// Returns the class index of `name`, adding it if it is new.
+fn register(name: &'static str) -> Option<usize> {
+	return without_interrupts(|| {
+		let count = CLASS_COUNT.load(Ordering::Relaxed);
+		if let Some(index) = (0..count).find(|&index| class_name(index) == name)
+		{
+			return Some(index);
+		}
+
+		if count == MAX_LOCK_CLASSES {
+			give_up(format_args!(
+				"more than {} lock classes",
+				MAX_LOCK_CLASSES
+			));
+			return None;
+		}
+
+		CLASS_NAMES[count].store(name.as_ptr().cast_mut(), Ordering::Relaxed);
+		CLASS_LENS[count].store(name.len(), Ordering::Relaxed);
+		CLASS_COUNT.store(count + 1, Ordering::Relaxed);
+		return Some(count);
+	});
+}
+
+fn class_name(index: usize) -> &'static str {
+	let ptr = CLASS_NAMES[index].load(Ordering::Relaxed);
+	let len = CLASS_LENS[index].load(Ordering::Relaxed);
+
+	// Safety: stored from a `&'static str` by `register`.
+	return unsafe {
+		core::str::from_utf8_unchecked(core::slice::from_raw_parts(ptr, len))
+	};
+}
+
+/// Stops checking after telling serial why, when the tables are too small
+/// to keep going.
+fn give_up(reason: fmt::Arguments) {
+	ENABLED.store(false, Ordering::Relaxed);
+	print_unlocked(format_args!("lockdep: {}, checking stops\n", reason));
+}
+
+/// Returns whether `to` can be reached from `from` along recorded edges.
+fn reaches(from: usize, to: usize) -> bool {
+	let mut visited = [false; MAX_LOCK_CLASSES];
+	let mut stack = [0; MAX_LOCK_CLASSES];
+	let mut len = 1;
+	stack[0] = from;
+	visited[from] = true;
+
+	while len > 0 {
+		len -= 1;
+		let class = stack[len];
+		if class == to {
+			return true;
+		}
+
+		let count = CLASS_COUNT.load(Ordering::Relaxed);
+		for (next, edge) in EDGES[class][..count].iter().enumerate() {
+			if !visited[next] && edge.load(Ordering::Relaxed) {
+				visited[next] = true;
+				stack[len] = next;
+				len += 1;
+			}
+		}
+	}
+
+	return false;
+}
+
+fn held() -> impl Iterator<Item = usize> {
+	return HELD[..HELD_COUNT.load(Ordering::Relaxed)]
+		.iter()
+		.map(|class| class.load(Ordering::Relaxed) as usize);
+}
+
+// Helper checking `class` against the held classes
+fn check_class(class: usize) -> Result<(), LockdepError> {
+	for held in held() {
+		if held == class {
+			return Err(LockdepError::Recursive(class_name(class)));
+		}
+		if reaches(class, held) {
+			return Err(LockdepError::Inversion {
+				held: class_name(held),
+				acquiring: class_name(class),
+			});
+		}
+	}
+
+	return Ok(());
+}
+
+/// Returns whether taking `mutex` now fits the recorded lock order, without
+/// taking it or recording anything.
+pub fn check<T>(mutex: &Mutex<T>) -> Result<(), LockdepError> {
+	if !ENABLED.load(Ordering::Relaxed) {
+		return Ok(());
+	}
+
+	return match mutex.lock_class().index() {
+		Some(class) => without_interrupts(|| check_class(class)),
+		None => Ok(()),
+	};
+}
+
+/// Checks a lock about to be waited for and records it as held.
+///
+/// # Panics
+///
+/// Panics if it is already held or taking it inverts the recorded order.
+pub(super) fn acquire(lock: &LockClass, caller: &Location) {
+	if !ENABLED.load(Ordering::Relaxed) {
+		return;
+	}
+	let Some(class) = lock.index() else {
+		return;
+	};
+
+	without_interrupts(|| {
+		if let Err(error) = check_class(class) {
+			// The panic handler takes locks of its own
+			ENABLED.store(false, Ordering::Relaxed);
+			panic!("lockdep: {} at {}", error, caller);
+		}
+
+		for held in held() {
+			EDGES[held][class].store(true, Ordering::Relaxed);
+		}
+		push(class);
+	});
+}
+
+/// Records a lock `try_lock` took as held.
+pub(super) fn acquired_try(lock: &LockClass) {
+	if !ENABLED.load(Ordering::Relaxed) {
+		return;
+	}
+	if let Some(class) = lock.index() {
+		without_interrupts(|| push(class));
+	}
+}
+
+fn push(class: usize) {
+	let count = HELD_COUNT.load(Ordering::Relaxed);
+	if count == MAX_HELD_LOCKS {
+		give_up(format_args!("more than {} locks held", MAX_HELD_LOCKS));
+		return;
+	}
+
+	HELD[count].store(class as u8, Ordering::Relaxed);
+	HELD_COUNT.store(count + 1, Ordering::Relaxed);
+}
+
+/// Forgets a released lock. Guards may be dropped in any order, so the
+/// newest entry of its class is removed wherever it is.
+pub(super) fn release(lock: &LockClass) {
+	if !ENABLED.load(Ordering::Relaxed) {
+		return;
+	}
+	let Some(class) = lock.index() else {
+		return;
+	};
+
+	without_interrupts(|| {
+		let count = HELD_COUNT.load(Ordering::Relaxed);
+		let Some(position) = (0..count)
+			.rev()
+			.find(|&i| HELD[i].load(Ordering::Relaxed) as usize == class)
+		else {
+			return;
+		};
+
+		for i in position..count - 1 {
+			HELD[i]
+				.store(HELD[i + 1].load(Ordering::Relaxed), Ordering::Relaxed);
+		}
+		HELD_COUNT.store(count - 1, Ordering::Relaxed);
+	});
+}
+
+/// Turns checking off for good, for the panic handler: the code that held
+/// the locks never releases them.
+pub fn disable() {
+	ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Returns whether the order is still checked.
+pub fn is_enabled() -> bool {
+	return ENABLED.load(Ordering::Relaxed);
+}
@@ -23,6 +23,14 @@ impl<A> Locked<A> {
 		};
 	}
 
+	/// Like [`Locked::new`], for a lock called `name`, see
+	/// [`Mutex::named`].
+	pub const fn named(name: &'static str, inner: A) -> Self {
+		return Self {
+			inner: Mutex::named(name, inner),
+		};
+	}
+
 	/// Acquires the mutex and returns a guard that provides access to the inner
 	/// value. The mutex will be automatically released when the guard is
 	/// dropped.
@@ -40,4 +48,16 @@ impl<A> Locked<A> {
 	pub fn lock(&self) -> MutexGuard<A> {
 		return self.inner.lock();
 	}
+
+	/// Runs `f` on the inner value with the mutex held and returns what it
+	/// returns. The mutex is released as soon as `f` does, so no guard can
+	/// be kept around by accident.
+	///
+	/// # Examples
+	/// ```
+	/// let size = PROTECTED.with(|value| value.size());
+	/// ```
+	pub fn with<R>(&self, f: impl FnOnce(&mut A) -> R) -> R {
+		return f(&mut self.inner.lock());
+	}
 }
@@ -2,11 +2,52 @@
 //!
 //! This module provides basic tools for ensuring safe access to shared data
 //! in concurrent contexts, such as mutexes and wrappers for synchronized data.
+//!
+//! # Interrupt context
+//!
+//! A handler must never spin on a lock the code it interrupted may hold.
+//! Locks taken from interrupt context are either an [`IrqMutex`], so nobody
+//! holds them with interrupts on, or only ever taken there with `try_lock`:
+//!
+//! - `WRITER` and `SERIAL` are [`IrqMutex`]es. The timer redraws the status
+//!   bar, exception handlers print.
+//! - The time `EPOCH` is an [`IrqMutex`], the status bar reads the uptime.
+//! - The output sinks list and the serial sink's position are only tried by
+//!   exception handlers, see `tty::output::is_locked`.
+//! - `FAULT_HANDLERS` is only tried by the page fault handler.
+//! - The timer interrupt only takes `SCHEDULER`, and the allocators behind its
+//!   queues, when the [preempt count](preempt) says the code it interrupted
+//!   holds no lock.
+//! - The keyboard and serial receive paths schedule work on the
+//!   [workqueue](crate::task::workqueue), whose
+//!   [`SpscRing`](crate::collections::ring_buffer::SpscRing) takes no lock at
+//!   all.
+//!
+//! Every other lock, the memory allocators among them, must not be taken
+//! from interrupt context.
 
+/// Module containing the runtime-initialized `Global<T>` mutex.
+pub mod global;
+/// Module containing the interrupt-disabling `IrqMutex<T>`.
+pub mod irq_mutex;
+#[cfg(feature = "lockdep")]
+pub mod lockdep;
 /// Module containing the `Locked<T>` wrapper type for mutex-protected data.
 pub mod locked;
 /// Module containing the spinlock-based `Mutex<T>` implementation.
 pub mod mutex;
+/// Module containing `Once`, `OnceLock<T>` and `Lazy<T>` for values set up
+/// once at runtime.
+pub mod once;
+/// Module containing the preempt-disable count the scheduler checks.
+pub mod preempt;
+/// Module containing the spinning reader-writer `RwLock<T>`.
+pub mod rwlock;
 
+pub use global::Global;
+pub use irq_mutex::{IrqMutex, IrqMutexGuard};
 pub use locked::Locked;
-pub use mutex::Mutex;
+pub use mutex::{MappedMutexGuard, Mutex, MutexGuard};
+pub use once::{Lazy, Once, OnceLock};
+pub use preempt::PreemptGuard;
+pub use rwlock::{RwLock, RwLockReadGuard, RwLockWriteGuard};
@@ -1,9 +1,19 @@
+use super::preempt;
 use core::{
 	cell::UnsafeCell,
+	hint,
+	marker::PhantomData,
+	mem,
 	ops::{Deref, DerefMut},
+	ptr::NonNull,
 	sync::atomic::{AtomicUsize, Ordering},
 };
 
+/// Spins after which a debug build reports a lock that does not come free,
+/// then again every as many spins.
+#[cfg(debug_assertions)]
+pub const SPIN_REPORT_THRESHOLD: u32 = 100_000_000;
+
 /// A simple spinlock-based mutual exclusion primitive.
 ///
 /// This Mutex uses an atomic usize to track the lock state (0=unlocked,
@@ -11,6 +21,8 @@ use core::{
 /// data `T`.
 pub struct Mutex<T> {
 	state: AtomicUsize,
+	#[cfg(feature = "lockdep")]
+	class: super::lockdep::LockClass,
 	value: UnsafeCell<T>,
 }
 
@@ -18,11 +30,24 @@ pub struct Mutex<T> {
 ///
 /// When this structure is dropped (goes out of scope), the lock will be
 /// automatically released. Provides access to the protected data via `Deref`
-/// and `DerefMut`.
+/// and `DerefMut`. Preemption stays disabled while it lives, see
+/// [`preempt`].
 pub struct MutexGuard<'a, T> {
 	mutex: &'a Mutex<T>,
 }
 
+/// A [`MutexGuard`] narrowed to a part of the protected data, given out by
+/// [`MutexGuard::map`].
+///
+/// The lock stays held until it is dropped, like the guard it came from.
+pub struct MappedMutexGuard<'a, U> {
+	state: &'a AtomicUsize,
+	#[cfg(feature = "lockdep")]
+	class: &'a super::lockdep::LockClass,
+	value: NonNull<U>,
+	marker: PhantomData<&'a mut U>,
+}
+
 unsafe impl<T: Send> Send for Mutex<T> {}
 unsafe impl<T: Send> Sync for Mutex<T> {}
 
@@ -31,12 +56,34 @@ impl<T> Mutex<T> {
 	/// Creates a new `Mutex` in an unlocked state containing the provided
 	/// value.
 	pub const fn new(value: T) -> Self {
+		Self::with_name(None, value)
+	}
+
+	/// Like [`Mutex::new`], for a lock called `name`. With the `lockdep`
+	/// feature the order it is taken in relative to other named locks is
+	/// checked, see [`lockdep`](super::lockdep).
+	pub const fn named(name: &'static str, value: T) -> Self {
+		Self::with_name(Some(name), value)
+	}
+
+	#[cfg_attr(not(feature = "lockdep"), allow(unused_variables))]
+	pub(super) const fn with_name(
+		name: Option<&'static str>,
+		value: T,
+	) -> Self {
 		Self {
 			state: AtomicUsize::new(0),
+			#[cfg(feature = "lockdep")]
+			class: super::lockdep::LockClass::new(name),
 			value: UnsafeCell::new(value),
 		}
 	}
 
+	#[cfg(feature = "lockdep")]
+	pub(super) fn lock_class(&self) -> &super::lockdep::LockClass {
+		&self.class
+	}
+
 	/// Acquires the mutex lock, spinning until it becomes available.
 	///
 	/// This function will block the current thread (by spinning) until the lock
@@ -45,14 +92,131 @@ impl<T> Mutex<T> {
 	///
 	/// # Panics
 	/// This implementation does not handle potential deadlocks (e.g., trying
-	/// to lock the same mutex twice on the same thread).
+	/// to lock the same mutex twice on the same thread). Debug builds print
+	/// the lock and the caller to serial every [`SPIN_REPORT_THRESHOLD`]
+	/// spins, so a lock taken twice shows up instead of hanging silently.
+	/// With the `lockdep` feature, a named lock taken twice or out of order
+	/// panics before it spins.
+	#[track_caller]
 	pub fn lock(&self) -> MutexGuard<T> {
-		while self.state.swap(1, Ordering::Acquire) == 1 {}
+		#[cfg(feature = "lockdep")]
+		super::lockdep::acquire(&self.class, core::panic::Location::caller());
+		preempt::disable();
+		#[cfg(debug_assertions)]
+		let mut spins: u32 = 0;
+
+		while self.state.swap(1, Ordering::Acquire) == 1 {
+			#[cfg(debug_assertions)]
+			{
+				spins += 1;
+				if spins == SPIN_REPORT_THRESHOLD {
+					spins = 0;
+					self.report_stuck(core::panic::Location::caller());
+				}
+			}
+			hint::spin_loop();
+		}
 
 		MutexGuard {
 			mutex: self,
 		}
 	}
+
+	/// Consumes the mutex and returns the value it protected.
+	pub fn into_inner(self) -> T {
+		self.value.into_inner()
+	}
+
+	/// Acquires the mutex lock, breaking it first if it is held.
+	///
+	/// # Safety
+	/// As for [`force_unlock`](Self::force_unlock): the holder must never
+	/// touch the value again.
+	#[track_caller]
+	pub unsafe fn force_lock(&self) -> MutexGuard<T> {
+		if let Some(guard) = self.try_lock() {
+			return guard;
+		}
+
+		unsafe { self.force_unlock() };
+		return self.lock();
+	}
+
+	/// Tells serial that the caller at `location` still waits for the lock.
+	/// Does not lock the port, it may be the lock that is stuck.
+	#[cfg(debug_assertions)]
+	#[cold]
+	fn report_stuck(&self, location: &core::panic::Location) {
+		crate::tty::serial::print_unlocked(format_args!(
+			"Mutex {:p} still locked after {} spins, waiting at {}\n",
+			self, SPIN_REPORT_THRESHOLD, location
+		));
+	}
+
+	/// Releases the lock no matter who holds it.
+	///
+	/// # Safety
+	/// The holder must never touch the value again, e.g. because it is the
+	/// code that panicked. Its guard must not be dropped afterwards either.
+	pub unsafe fn force_unlock(&self) {
+		if self.is_locked() {
+			#[cfg(feature = "lockdep")]
+			super::lockdep::release(&self.class);
+			// The holder's guard never gives its preempt count back
+			preempt::enable();
+		}
+		self.state.store(0, Ordering::Release);
+	}
+
+	/// Acquires the mutex lock if it is free, without spinning.
+	///
+	/// Meant for interrupt handlers, which would deadlock spinning on a lock
+	/// the code they interrupted holds.
+	pub fn try_lock(&self) -> Option<MutexGuard<T>> {
+		if self.state.swap(1, Ordering::Acquire) == 1 {
+			None
+		} else {
+			#[cfg(feature = "lockdep")]
+			super::lockdep::acquired_try(&self.class);
+			preempt::disable();
+			Some(MutexGuard {
+				mutex: self,
+			})
+		}
+	}
+
+	/// Returns whether the lock is held. Only a snapshot unless interrupts
+	/// are off.
+	pub fn is_locked(&self) -> bool {
+		self.state.load(Ordering::Relaxed) == 1
+	}
+}
+
+#[allow(clippy::implicit_return)]
+impl<'a, T> MutexGuard<'a, T> {
+	/// Narrows the guard to the part of the value `f` returns, e.g. one entry
+	/// of a locked array. The lock is held until the returned guard drops.
+	///
+	/// An associated function, called as `MutexGuard::map(guard, f)`, so it
+	/// does not shadow a `map` of `T` such as `Option::map`.
+	pub fn map<U>(
+		this: Self,
+		f: impl FnOnce(&mut T) -> &mut U,
+	) -> MappedMutexGuard<'a, U> {
+		let mutex = this.mutex;
+		// Safety: the lock is held, and keeps being held by the mapped guard.
+		let value = NonNull::from(f(unsafe { &mut *mutex.value.get() }));
+
+		// The mapped guard releases the lock instead
+		mem::forget(this);
+		MappedMutexGuard {
+			state: &mutex.state,
+			#[cfg(feature = "lockdep")]
+			class: &mutex.class,
+			value,
+			marker: PhantomData,
+		}
+	}
 }
 
 #[allow(clippy::implicit_return)]
@@ -73,6 +237,56 @@ impl<T> DerefMut for MutexGuard<'_, T> {
 
 impl<T> Drop for MutexGuard<'_, T> {
 	fn drop(&mut self) {
+		#[cfg(feature = "lockdep")]
+		super::lockdep::release(&self.mutex.class);
 		self.mutex.state.store(0, Ordering::Release);
+		preempt::enable();
+	}
+}
+
+#[allow(clippy::implicit_return)]
+impl<'a, U> MappedMutexGuard<'a, U> {
+	/// Narrows the guard further, see [`MutexGuard::map`].
+	pub fn map<V>(
+		this: Self,
+		f: impl FnOnce(&mut U) -> &mut V,
+	) -> MappedMutexGuard<'a, V> {
+		// Safety: the lock is held, and keeps being held by the new guard.
+		let value = NonNull::from(f(unsafe { &mut *this.value.as_ptr() }));
+		let mapped = MappedMutexGuard {
+			state: this.state,
+			#[cfg(feature = "lockdep")]
+			class: this.class,
+			value,
+			marker: PhantomData,
+		};
+
+		mem::forget(this);
+		mapped
+	}
+}
+
+#[allow(clippy::implicit_return)]
+impl<U> Deref for MappedMutexGuard<'_, U> {
+	type Target = U;
+
+	fn deref(&self) -> &U {
+		unsafe { self.value.as_ref() }
+	}
+}
+
+#[allow(clippy::implicit_return)]
+impl<U> DerefMut for MappedMutexGuard<'_, U> {
+	fn deref_mut(&mut self) -> &mut U {
+		unsafe { self.value.as_mut() }
+	}
+}
+
+impl<U> Drop for MappedMutexGuard<'_, U> {
+	fn drop(&mut self) {
+		#[cfg(feature = "lockdep")]
+		super::lockdep::release(self.class);
+		self.state.store(0, Ordering::Release);
+		preempt::enable();
 	}
 }
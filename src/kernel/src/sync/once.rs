@@ -0,0 +1,183 @@
+use crate::arch::x86::cpu::without_interrupts;
+use core::{
+	cell::UnsafeCell,
+	hint,
+	mem::MaybeUninit,
+	ops::Deref,
+	sync::atomic::{AtomicU8, Ordering},
+};
+
+const INCOMPLETE: u8 = 0;
+const RUNNING: u8 = 1;
+const COMPLETE: u8 = 2;
+
+/// Runs a piece of code exactly once.
+///
+/// The code runs with interrupts disabled, so a handler can never find it
+/// half done on this core and spin on it forever.
+pub struct Once {
+	state: AtomicU8,
+}
+
+impl Once {
+	/// Creates a `Once` that has not run yet.
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		return Self {
+			state: AtomicU8::new(INCOMPLETE),
+		};
+	}
+
+	/// Runs `f` if no call ran before, otherwise waits until the one that did
+	/// has finished.
+	///
+	/// # Panics
+	///
+	/// Panics if `f` calls `call_once` on the same `Once`, which would wait
+	/// for itself.
+	pub fn call_once(&self, f: impl FnOnce()) {
+		if self.is_completed() {
+			return;
+		}
+
+		if self
+			.state
+			.compare_exchange(
+				INCOMPLETE,
+				RUNNING,
+				Ordering::Acquire,
+				Ordering::Acquire,
+			)
+			.is_ok()
+		{
+			without_interrupts(f);
+			self.state.store(COMPLETE, Ordering::Release);
+			return;
+		}
+
+		// Interrupts are off while it runs, so on a single core the only
+		// one who can see it running is the initializer itself
+		if self.state.load(Ordering::Acquire) == RUNNING {
+			panic!("Once::call_once called from its own initializer");
+		}
+		while !self.is_completed() {
+			hint::spin_loop();
+		}
+	}
+
+	/// Returns whether a call to [`Once::call_once`] finished.
+	pub fn is_completed(&self) -> bool {
+		return self.state.load(Ordering::Acquire) == COMPLETE;
+	}
+}
+
+/// A value that is set once at runtime and read-only after that, like
+/// `std::sync::OnceLock`.
+pub struct OnceLock<T> {
+	once: Once,
+	value: UnsafeCell<MaybeUninit<T>>,
+}
+
+unsafe impl<T: Send> Send for OnceLock<T> {}
+unsafe impl<T: Send + Sync> Sync for OnceLock<T> {}
+
+impl<T> OnceLock<T> {
+	/// Creates an empty `OnceLock`.
+	#[allow(clippy::new_without_default)]
+	pub const fn new() -> Self {
+		return Self {
+			once: Once::new(),
+			value: UnsafeCell::new(MaybeUninit::uninit()),
+		};
+	}
+
+	/// Returns the value, or `None` if it is not set yet.
+	pub fn get(&self) -> Option<&T> {
+		if !self.once.is_completed() {
+			return None;
+		}
+
+		// Safety: the value was written before the state became complete and
+		// is never written again.
+		return Some(unsafe { (*self.value.get()).assume_init_ref() });
+	}
+
+	/// Returns the value mutably, or `None` if it is not set yet.
+	pub fn get_mut(&mut self) -> Option<&mut T> {
+		if !self.once.is_completed() {
+			return None;
+		}
+
+		// Safety: as for `get`, and `&mut self` rules out any other access.
+		return Some(unsafe { self.value.get_mut().assume_init_mut() });
+	}
+
+	/// Returns the value, setting it to what `f` returns first if there is
+	/// none. See [`Once::call_once`] for when `f` is called.
+	pub fn get_or_init(&self, f: impl FnOnce() -> T) -> &T {
+		self.once.call_once(|| {
+			// Safety: only the one running initializer writes the value.
+			unsafe { (*self.value.get()).write(f()) };
+		});
+
+		return match self.get() {
+			Some(value) => value,
+			None => unreachable!("OnceLock is empty after initialization"),
+		};
+	}
+
+	/// Sets the value. Hands `value` back if there already is one.
+	pub fn set(&self, value: T) -> Result<(), T> {
+		let mut value = Some(value);
+		self.get_or_init(|| {
+			return match value.take() {
+				Some(value) => value,
+				None => unreachable!(),
+			};
+		});
+
+		match value {
+			Some(value) => return Err(value),
+			None => return Ok(()),
+		}
+	}
+}
+
+impl<T> Drop for OnceLock<T> {
+	fn drop(&mut self) {
+		if self.once.is_completed() {
+			// Safety: the value is set and dropped only here.
+			unsafe { self.value.get_mut().assume_init_drop() };
+		}
+	}
+}
+
+/// A value built by `init` the first time it is used, like
+/// `std::sync::LazyLock`.
+pub struct Lazy<T> {
+	cell: OnceLock<T>,
+	init: fn() -> T,
+}
+
+impl<T> Lazy<T> {
+	/// Creates a `Lazy` that builds its value with `init`.
+	pub const fn new(init: fn() -> T) -> Self {
+		return Self {
+			cell: OnceLock::new(),
+			init,
+		};
+	}
+
+	/// Builds the value now if it was not used yet.
+	pub fn force(this: &Self) -> &T {
+		return this.cell.get_or_init(this.init);
+	}
+}
+
+impl<T> Deref for Lazy<T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		return Lazy::force(self);
+	}
+}
@@ -0,0 +1,56 @@
+//! The preempt-disable count.
+//!
+//! The timer interrupt switches threads only while the count is zero. Every
+//! [`Mutex`](super::Mutex) guard, the ones inside an
+//! [`IrqMutex`](super::IrqMutex) among them, counts while it is held: a
+//! thread switched away from with a spinlock held would leave the next one
+//! to take it spinning forever on the single CPU.
+//!
+//! The count belongs to the running thread. Nothing switches while it is
+//! above zero, so a thread must not yield with a lock held either.
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+static PREEMPT_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Keeps preemption disabled while it lives, see [`disable`].
+pub struct PreemptGuard(());
+
+impl PreemptGuard {
+	/// Disables preemption until the guard is dropped.
+	#[allow(clippy::new_without_default)]
+	pub fn new() -> Self {
+		disable();
+		return Self(());
+	}
+}
+
+impl Drop for PreemptGuard {
+	fn drop(&mut self) {
+		enable();
+	}
+}
+
+/// Disables preemption until a matching [`enable`].
+pub fn disable() {
+	PREEMPT_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Undoes one [`disable`]. An extra call is ignored.
+pub fn enable() {
+	let _ = PREEMPT_COUNT.fetch_update(
+		Ordering::Relaxed,
+		Ordering::Relaxed,
+		|count| count.checked_sub(1),
+	);
+}
+
+/// Returns the number of [`disable`]s not undone yet.
+pub fn count() -> usize {
+	return PREEMPT_COUNT.load(Ordering::Relaxed);
+}
+
+/// Returns whether the running thread may be switched away from.
+pub fn is_preemptible() -> bool {
+	return count() == 0;
+}
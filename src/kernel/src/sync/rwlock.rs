@@ -0,0 +1,175 @@
+use core::{
+	cell::UnsafeCell,
+	hint,
+	ops::{Deref, DerefMut},
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Set while a writer holds the lock.
+const WRITER: usize = 1;
+/// Set while a writer waits, which keeps new readers out.
+const WRITER_WAITING: usize = 1 << 1;
+/// One reader, the reader count is stored above the two flags.
+const READER: usize = 1 << 2;
+
+/// A spinning reader-writer lock.
+///
+/// Any number of readers or a single writer hold it at a time. The state word
+/// holds the reader count and two flags, [`WRITER`] and [`WRITER_WAITING`]. A
+/// waiting writer stops new readers from getting in, so a steady stream of
+/// readers cannot starve it.
+pub struct RwLock<T> {
+	state: AtomicUsize,
+	value: UnsafeCell<T>,
+}
+
+/// An RAII guard for shared access to a [`RwLock`], given out by
+/// [`RwLock::read`].
+pub struct RwLockReadGuard<'a, T> {
+	lock: &'a RwLock<T>,
+}
+
+/// An RAII guard for exclusive access to a [`RwLock`], given out by
+/// [`RwLock::write`].
+pub struct RwLockWriteGuard<'a, T> {
+	lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: Send> Send for RwLock<T> {}
+unsafe impl<T: Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+	/// Creates a new `RwLock` in an unlocked state containing the provided
+	/// value.
+	pub const fn new(value: T) -> Self {
+		return Self {
+			state: AtomicUsize::new(0),
+			value: UnsafeCell::new(value),
+		};
+	}
+
+	/// Acquires shared access, spinning while a writer holds the lock or
+	/// waits for it.
+	pub fn read(&self) -> RwLockReadGuard<T> {
+		loop {
+			if let Some(guard) = self.try_read() {
+				return guard;
+			}
+			hint::spin_loop();
+		}
+	}
+
+	/// Acquires shared access if no writer holds the lock or waits for it.
+	pub fn try_read(&self) -> Option<RwLockReadGuard<T>> {
+		let mut state = self.state.load(Ordering::Relaxed);
+
+		// Only retries while other readers come and go
+		while state & (WRITER | WRITER_WAITING) == 0 {
+			match self.state.compare_exchange_weak(
+				state,
+				state + READER,
+				Ordering::Acquire,
+				Ordering::Relaxed,
+			) {
+				Ok(_) => {
+					return Some(RwLockReadGuard {
+						lock: self,
+					})
+				}
+				Err(current) => state = current,
+			}
+		}
+
+		return None;
+	}
+
+	/// Acquires exclusive access, spinning until every reader and any other
+	/// writer is gone.
+	pub fn write(&self) -> RwLockWriteGuard<T> {
+		loop {
+			if let Some(guard) = self.try_write() {
+				return guard;
+			}
+
+			self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+			hint::spin_loop();
+		}
+	}
+
+	/// Acquires exclusive access if nobody holds the lock.
+	pub fn try_write(&self) -> Option<RwLockWriteGuard<T>> {
+		let state = self.state.load(Ordering::Relaxed);
+		if state & !WRITER_WAITING != 0 {
+			return None;
+		}
+
+		// Clears the waiting flag, other waiting writers set it again
+		return self
+			.state
+			.compare_exchange(
+				state,
+				WRITER,
+				Ordering::Acquire,
+				Ordering::Relaxed,
+			)
+			.ok()
+			.map(|_| RwLockWriteGuard {
+				lock: self,
+			});
+	}
+
+	/// Returns the number of readers holding the lock.
+	pub fn readers(&self) -> usize {
+		return self.state.load(Ordering::Relaxed) / READER;
+	}
+
+	/// Returns whether a writer holds the lock.
+	pub fn is_write_locked(&self) -> bool {
+		return self.state.load(Ordering::Relaxed) & WRITER != 0;
+	}
+
+	/// Marks a writer as waiting, like one spinning in [`RwLock::write`]
+	/// would, which a single core test cannot have.
+	#[cfg(test)]
+	pub(crate) fn mark_writer_waiting(&self) {
+		self.state.fetch_or(WRITER_WAITING, Ordering::Relaxed);
+	}
+}
+
+impl<T> Deref for RwLockReadGuard<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		// Safety: no writer can get in while the guard lives.
+		return unsafe { &*self.lock.value.get() };
+	}
+}
+
+impl<T> Drop for RwLockReadGuard<'_, T> {
+	fn drop(&mut self) {
+		self.lock.state.fetch_sub(READER, Ordering::Release);
+	}
+}
+
+impl<T> Deref for RwLockWriteGuard<'_, T> {
+	type Target = T;
+
+	fn deref(&self) -> &T {
+		// Safety: the guard is the only access while it lives.
+		return unsafe { &*self.lock.value.get() };
+	}
+}
+
+impl<T> DerefMut for RwLockWriteGuard<'_, T> {
+	fn deref_mut(&mut self) -> &mut T {
+		// Safety: the guard is the only access while it lives.
+		return unsafe { &mut *self.lock.value.get() };
+	}
+}
+
+impl<T> Drop for RwLockWriteGuard<'_, T> {
+	fn drop(&mut self) {
+		// Keeps the waiting flag of another writer
+		self.lock.state.fetch_and(!WRITER, Ordering::Release);
+	}
+}
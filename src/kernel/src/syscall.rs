@@ -0,0 +1,142 @@
+//! The system calls, made with `int 0x80`.
+//!
+//! EAX holds the call number, EBX, ECX and EDX its arguments, and the result
+//! comes back in EAX: a value on success, or minus an [`Errno`]. The numbers
+//! are those of Linux on i386. An unknown number returns `-ENOSYS`, nothing
+//! user mode passes in makes the kernel fault.
+//!
+//! The IDT gate of vector 0x80 has DPL 3, the kernel may make the calls too.
+//! Buffers are only checked to be user accessible for callers in ring 3.
+
+use crate::{
+	arch::x86::{exceptions::ExceptionContext, usermode},
+	memory::{
+		paging::{flags, page_flags, translate},
+		VirtAddr, PAGE_SIZE,
+	},
+	print,
+	task::process,
+};
+use core::slice;
+
+/// `exit(code)`, ends the user code [`usermode::run`] runs.
+pub const SYS_EXIT: u32 = 1;
+/// `write(fd, buf, len)`, writes to the console. Only fd 1 and 2 exist.
+pub const SYS_WRITE: u32 = 4;
+/// `getpid()`, 0 for code outside of any process.
+pub const SYS_GETPID: u32 = 20;
+
+/// Number of entries in the dispatch table.
+pub const SYSCALL_COUNT: usize = 21;
+
+/// Why a system call failed, with the Linux error numbers.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Errno {
+	/// There is no process to act on.
+	Srch = 3,
+	/// The file descriptor does not exist.
+	BadFd = 9,
+	/// A buffer is not mapped, or not for the caller.
+	Fault = 14,
+	/// The call number does not exist.
+	NoSys = 38,
+}
+
+impl Errno {
+	/// Returns the EAX of a call failing with this error.
+	pub const fn as_return(self) -> u32 {
+		return (self as u32).wrapping_neg();
+	}
+}
+
+/// A system call, given EBX, ECX and EDX and whether the caller runs in
+/// ring 3.
+pub type Syscall = fn(args: [u32; 3], from_user: bool) -> Result<u32, Errno>;
+
+/// The system calls, by number. `None` for the ones that do not exist.
+static SYSCALLS: [Option<Syscall>; SYSCALL_COUNT] = {
+	let mut table: [Option<Syscall>; SYSCALL_COUNT] = [None; SYSCALL_COUNT];
+	table[SYS_EXIT as usize] = Some(sys_exit);
+	table[SYS_WRITE as usize] = Some(sys_write);
+	table[SYS_GETPID as usize] = Some(sys_getpid);
+	table
+};
+
+/// Called by `syscall_common` in `isr.asm` for int 0x80.
+#[no_mangle]
+extern "C" fn syscall_dispatch(context: &mut ExceptionContext) {
+	let regs = &context.regs;
+	let args = [regs.ebx, regs.ecx, regs.edx];
+
+	context.regs.eax = match SYSCALLS.get(regs.eax as usize).copied().flatten()
+	{
+		Some(syscall) => match syscall(args, !context.from_kernel()) {
+			Ok(value) => value,
+			Err(errno) => errno.as_return(),
+		},
+		None => Errno::NoSys.as_return(),
+	};
+}
+
+/// Returns the `len` bytes at `buf` if they are mapped, and user accessible
+/// for a caller in ring 3.
+fn user_buffer(
+	buf: u32,
+	len: u32,
+	from_user: bool,
+) -> Result<&'static [u8], Errno> {
+	if len == 0 {
+		return Ok(&[]);
+	}
+	let end = buf.checked_add(len - 1).ok_or(Errno::Fault)?;
+
+	let first = buf as usize & !(PAGE_SIZE - 1);
+	for page in (first..=end as usize).step_by(PAGE_SIZE) {
+		let page = VirtAddr::new(page);
+		if translate(page).is_none() {
+			return Err(Errno::Fault);
+		}
+
+		let user = page_flags(page)
+			.is_some_and(|flags| flags & flags::USER_ACCESSIBLE != 0);
+		if from_user && !user {
+			return Err(Errno::Fault);
+		}
+	}
+
+	// Safety: every page of the buffer is mapped, for the caller too.
+	return Ok(unsafe {
+		slice::from_raw_parts(
+			VirtAddr::new(buf as usize).as_ptr(),
+			len as usize,
+		)
+	});
+}
+
+fn sys_exit([code, _, _]: [u32; 3], _: bool) -> Result<u32, Errno> {
+	usermode::exit(code);
+
+	// Only returns without user code to end
+	return Err(Errno::Srch);
+}
+
+fn sys_write([fd, buf, len]: [u32; 3], from_user: bool) -> Result<u32, Errno> {
+	if fd != 1 && fd != 2 {
+		return Err(Errno::BadFd);
+	}
+
+	let bytes = user_buffer(buf, len, from_user)?;
+	for chunk in bytes.utf8_chunks() {
+		print!("{}", chunk.valid());
+		if !chunk.invalid().is_empty() {
+			print!("{}", char::REPLACEMENT_CHARACTER);
+		}
+	}
+
+	return Ok(len);
+}
+
+fn sys_getpid(_: [u32; 3], _: bool) -> Result<u32, Errno> {
+	return Ok(process::current_pid().0);
+}
@@ -0,0 +1,379 @@
+//! Loading ELF32 executables into an address space of their own.
+//!
+//! [`parse`] checks the file header and the program headers without
+//! touching memory, so a malformed file is turned down with an [`ElfError`]
+//! naming what is wrong with it. [`load`] then maps every `PT_LOAD` segment
+//! at the address it asks for in a fresh [`AddressSpace`], user accessible
+//! and writable only if the segment is, copies its bytes from the file and
+//! leaves the rest of it, the `.bss`, zeroed. A user stack goes right below
+//! the kernel half.
+//!
+//! Segments must lie between [`USER_START`] and the stack: the low 4MiB stay
+//! the kernel's identity mapping, see [`AddressSpace::share_identity_map`].
+
+use crate::{
+	memory::{
+		paging::{flags, phys_to_virt, AddressSpace},
+		VirtAddr, PAGE_SIZE,
+	},
+	util::bytes::{u16_at, u32_at},
+};
+use alloc::vec::Vec;
+use core::{alloc::AllocError, fmt, ptr};
+
+/// Lowest address a segment may be loaded at, past the identity mapping.
+pub const USER_START: usize = 0x0040_0000;
+
+/// Where the kernel half starts and the user stack ends.
+pub const USER_END: usize = 0xc000_0000;
+
+/// Pages of the user stack.
+pub const USER_STACK_PAGES: usize = 4;
+
+/// First address of the user stack, segments stay below it.
+pub const USER_STACK_BOTTOM: usize = USER_END - USER_STACK_PAGES * PAGE_SIZE;
+
+/// Most pages the segments of an executable may span together, 16MiB.
+pub const MAX_IMAGE_PAGES: usize = 4096;
+
+const ELF_MAGIC: [u8; 4] = [0x7f, b'E', b'L', b'F'];
+const ELFCLASS32: u8 = 1;
+const ELFDATA2LSB: u8 = 1;
+const EV_CURRENT: u8 = 1;
+const ET_EXEC: u16 = 2;
+const EM_386: u16 = 3;
+const PT_LOAD: u32 = 1;
+const PF_X: u32 = 1 << 0;
+const PF_W: u32 = 1 << 1;
+
+/// Size of the ELF32 file header.
+const HEADER_SIZE: usize = 52;
+
+/// Size of an ELF32 program header.
+const PROGRAM_HEADER_SIZE: usize = 32;
+
+/// Why a file is not an executable [`load`] can run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ElfError {
+	/// The file is shorter than the ELF header.
+	TooShort,
+	/// The file does not start with the ELF magic.
+	BadMagic,
+	/// The file is not a 32-bit ELF.
+	Not32Bit,
+	/// The file is not little endian.
+	NotLittleEndian,
+	/// The ELF version is not 1.
+	BadVersion,
+	/// The file is not an executable, but an object or a library.
+	NotExecutable,
+	/// The file is not for i386.
+	WrongMachine,
+	/// The program headers are not all inside the file, or of the wrong
+	/// size.
+	BadProgramHeaders,
+	/// No segment is to be loaded.
+	NoLoadSegments,
+	/// A segment's file bytes are not all inside the file.
+	SegmentOutsideFile,
+	/// A segment has more bytes in the file than in memory.
+	SegmentTooBig,
+	/// A segment is outside of the user half, at the address given.
+	BadSegmentAddress(VirtAddr),
+	/// The entry point is not in an executable segment.
+	BadEntry(VirtAddr),
+	/// The segments span more than [`MAX_IMAGE_PAGES`] pages.
+	ImageTooBig,
+	/// No memory was left for the address space.
+	OutOfMemory,
+}
+
+impl fmt::Display for ElfError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			ElfError::TooShort => {
+				return f.write_str("file shorter than an ELF header")
+			}
+			ElfError::BadMagic => return f.write_str("not an ELF file"),
+			ElfError::Not32Bit => return f.write_str("not a 32-bit ELF"),
+			ElfError::NotLittleEndian => {
+				return f.write_str("not a little endian ELF")
+			}
+			ElfError::BadVersion => return f.write_str("unknown ELF version"),
+			ElfError::NotExecutable => {
+				return f.write_str("not an executable ELF")
+			}
+			ElfError::WrongMachine => return f.write_str("not an i386 ELF"),
+			ElfError::BadProgramHeaders => {
+				return f.write_str("program headers outside of the file")
+			}
+			ElfError::NoLoadSegments => {
+				return f.write_str("no segment to load")
+			}
+			ElfError::SegmentOutsideFile => {
+				return f.write_str("segment data outside of the file")
+			}
+			ElfError::SegmentTooBig => {
+				return f.write_str("segment larger in the file than in memory")
+			}
+			ElfError::BadSegmentAddress(addr) => {
+				return write!(
+					f,
+					"segment at {:#x} outside of user memory",
+					addr.as_usize()
+				)
+			}
+			ElfError::BadEntry(addr) => {
+				return write!(
+					f,
+					"entry point {:#x} not in a code segment",
+					addr.as_usize()
+				)
+			}
+			ElfError::ImageTooBig => {
+				return write!(
+					f,
+					"segments larger than {} pages",
+					MAX_IMAGE_PAGES
+				)
+			}
+			ElfError::OutOfMemory => return f.write_str("out of memory"),
+		}
+	}
+}
+
+impl From<AllocError> for ElfError {
+	fn from(_: AllocError) -> Self {
+		return ElfError::OutOfMemory;
+	}
+}
+
+/// A `PT_LOAD` segment of an executable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+	/// Address of the first byte in memory.
+	pub vaddr: usize,
+	/// Bytes in memory, the ones past `file_size` are zero.
+	pub mem_size: usize,
+	/// Offset of the segment's bytes in the file.
+	pub offset: usize,
+	/// Bytes in the file.
+	pub file_size: usize,
+	/// Whether the program may write to it.
+	pub writable: bool,
+	/// Whether it holds code.
+	pub executable: bool,
+}
+
+impl Segment {
+	/// Returns the address after the last byte in memory.
+	pub const fn end(&self) -> usize {
+		return self.vaddr + self.mem_size;
+	}
+
+	/// Returns the number of pages the segment touches in memory.
+	pub const fn pages(&self) -> usize {
+		return self.end().div_ceil(PAGE_SIZE) - self.vaddr / PAGE_SIZE;
+	}
+}
+
+/// What [`parse`] found in an executable.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Executable {
+	/// Where the program starts.
+	pub entry: VirtAddr,
+	/// The segments to load, in file order.
+	pub segments: Vec<Segment>,
+}
+
+/// A program loaded by [`load`], ready to enter.
+pub struct Image {
+	/// The address space holding the segments and the stack.
+	pub space: AddressSpace,
+	/// Where the program starts.
+	pub entry: VirtAddr,
+	/// The stack pointer it starts with.
+	pub stack_top: VirtAddr,
+}
+
+/// Checks that `bytes` is an i386 executable whose segments all fit the
+/// user half, and returns its entry point and segments.
+pub fn parse(bytes: &[u8]) -> Result<Executable, ElfError> {
+	if bytes.len() < HEADER_SIZE {
+		return Err(ElfError::TooShort);
+	}
+	if bytes[..4] != ELF_MAGIC {
+		return Err(ElfError::BadMagic);
+	}
+	if bytes[4] != ELFCLASS32 {
+		return Err(ElfError::Not32Bit);
+	}
+	if bytes[5] != ELFDATA2LSB {
+		return Err(ElfError::NotLittleEndian);
+	}
+	if bytes[6] != EV_CURRENT {
+		return Err(ElfError::BadVersion);
+	}
+	if u16_at(bytes, 16) != ET_EXEC {
+		return Err(ElfError::NotExecutable);
+	}
+	if u16_at(bytes, 18) != EM_386 {
+		return Err(ElfError::WrongMachine);
+	}
+
+	let entry = u32_at(bytes, 24) as usize;
+	let ph_offset = u32_at(bytes, 28) as usize;
+	let ph_size = u16_at(bytes, 42) as usize;
+	let ph_count = u16_at(bytes, 44) as usize;
+
+	let table_end = ph_count
+		.checked_mul(PROGRAM_HEADER_SIZE)
+		.and_then(|size| size.checked_add(ph_offset));
+	if ph_size != PROGRAM_HEADER_SIZE
+		|| table_end.is_none_or(|end| end > bytes.len())
+	{
+		return Err(ElfError::BadProgramHeaders);
+	}
+
+	let mut segments = Vec::new();
+	for index in 0..ph_count {
+		let header = &bytes[ph_offset + index * PROGRAM_HEADER_SIZE..];
+		if u32_at(header, 0) != PT_LOAD {
+			continue;
+		}
+
+		let segment = Segment {
+			offset: u32_at(header, 4) as usize,
+			vaddr: u32_at(header, 8) as usize,
+			file_size: u32_at(header, 16) as usize,
+			mem_size: u32_at(header, 20) as usize,
+			writable: u32_at(header, 24) & PF_W != 0,
+			executable: u32_at(header, 24) & PF_X != 0,
+		};
+		check_segment(&segment, bytes.len())?;
+		segments.push(segment);
+	}
+
+	if segments.is_empty() {
+		return Err(ElfError::NoLoadSegments);
+	}
+	if segments.iter().map(Segment::pages).sum::<usize>() > MAX_IMAGE_PAGES {
+		return Err(ElfError::ImageTooBig);
+	}
+	if !segments.iter().any(|segment| {
+		segment.executable && (segment.vaddr..segment.end()).contains(&entry)
+	}) {
+		return Err(ElfError::BadEntry(VirtAddr::new(entry)));
+	}
+
+	return Ok(Executable {
+		entry: VirtAddr::new(entry),
+		segments,
+	});
+}
+
+/// Checks that `segment` is inside a file of `file_len` bytes and the user
+/// half.
+fn check_segment(segment: &Segment, file_len: usize) -> Result<(), ElfError> {
+	if segment.file_size > segment.mem_size {
+		return Err(ElfError::SegmentTooBig);
+	}
+	if segment
+		.offset
+		.checked_add(segment.file_size)
+		.is_none_or(|end| end > file_len)
+	{
+		return Err(ElfError::SegmentOutsideFile);
+	}
+
+	let fits = segment
+		.vaddr
+		.checked_add(segment.mem_size)
+		.is_some_and(|end| end <= USER_STACK_BOTTOM);
+	if segment.vaddr < USER_START || !fits {
+		return Err(ElfError::BadSegmentAddress(VirtAddr::new(segment.vaddr)));
+	}
+
+	return Ok(());
+}
+
+/// Loads the executable in `bytes` into a new address space, see the module
+/// documentation. Nothing is left allocated if it fails.
+pub fn load(bytes: &[u8]) -> Result<Image, ElfError> {
+	let executable = parse(bytes)?;
+
+	let mut space = AddressSpace::new_kernel_clone()?;
+	space.share_identity_map();
+
+	// A page two segments share gets mapped once, writable if either is
+	let mut pages: Vec<(usize, u32)> = Vec::new();
+	pages
+		.try_reserve_exact(executable.segments.iter().map(Segment::pages).sum())
+		.map_err(|_| ElfError::OutOfMemory)?;
+	for segment in &executable.segments {
+		let page_flags = match segment.writable {
+			true => flags::WRITABLE,
+			false => 0,
+		};
+		let first = segment.vaddr & !(PAGE_SIZE - 1);
+		for page in (first..segment.end()).step_by(PAGE_SIZE) {
+			pages.push((page, page_flags));
+		}
+	}
+	pages.sort_unstable_by_key(|&(page, _)| page);
+	pages.dedup_by(|next, kept| {
+		if next.0 != kept.0 {
+			return false;
+		}
+		kept.1 |= next.1;
+		return true;
+	});
+
+	for (page, page_flags) in pages {
+		space.map_zeroed(
+			VirtAddr::new(page),
+			flags::PRESENT | flags::USER_ACCESSIBLE | page_flags,
+		)?;
+	}
+	for segment in &executable.segments {
+		copy_segment(&space, segment, bytes);
+	}
+
+	for page in 0..USER_STACK_PAGES {
+		space.map_zeroed(
+			VirtAddr::new(USER_STACK_BOTTOM + page * PAGE_SIZE),
+			flags::PRESENT | flags::USER_ACCESSIBLE | flags::WRITABLE,
+		)?;
+	}
+
+	return Ok(Image {
+		space,
+		entry: executable.entry,
+		stack_top: VirtAddr::new(USER_END),
+	});
+}
+
+/// Copies the file bytes of `segment` into the frames `space` maps for it, a
+/// page at a time.
+fn copy_segment(space: &AddressSpace, segment: &Segment, bytes: &[u8]) {
+	let mut copied = 0;
+
+	while copied < segment.file_size {
+		let virt = segment.vaddr + copied;
+		let in_page = virt % PAGE_SIZE;
+		let len = (PAGE_SIZE - in_page).min(segment.file_size - copied);
+
+		let Some(frame) = space.translate(VirtAddr::new(virt - in_page)) else {
+			unreachable!("segment page {:#x} not mapped", virt);
+		};
+		let source = &bytes[segment.offset + copied..][..len];
+		// Safety: the frame is mapped in the kernel half and a whole page.
+		unsafe {
+			let destination =
+				phys_to_virt(frame).as_mut_ptr::<u8>().add(in_page);
+			ptr::copy_nonoverlapping(source.as_ptr(), destination, len);
+		}
+
+		copied += len;
+	}
+}
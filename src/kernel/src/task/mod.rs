@@ -0,0 +1,34 @@
+//! Kernel threads and switching between them.
+//!
+//! [`thread`] creates threads and ends them, [`scheduler`] decides which one
+//! runs next. [`time::sleep_ms`] puts one to sleep, and [`workqueue`] runs
+//! the work interrupt handlers defer in a thread of its own. A [`process`]
+//! bundles a thread with an address space and a pid.
+//!
+//! [`time::sleep_ms`]: crate::time::sleep_ms
+
+use crate::error::KernelError;
+
+/// Module containing the ELF32 loader for user programs.
+pub mod elf;
+/// Module containing `Process`, the pids and the process table.
+pub mod process;
+/// Module containing the round-robin ready queue, `yield_now` and the
+/// preemption from the timer.
+pub mod scheduler;
+/// Module containing `Thread`, `spawn` and `exit`.
+pub mod thread;
+/// Module containing the deferred work queue and its worker thread.
+pub mod workqueue;
+
+pub use process::{create_kernel_process, create_user_process, Pid, Process};
+pub use scheduler::yield_now;
+pub use thread::{spawn, Thread, ThreadId, ThreadState};
+
+/// Spawns the idle thread and the workqueue worker.
+pub fn init() -> Result<(), KernelError> {
+	scheduler::init()?;
+	workqueue::init()?;
+
+	return Ok(());
+}
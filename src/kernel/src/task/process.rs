@@ -0,0 +1,298 @@
+//! Processes, the address space and threads a program runs with.
+//!
+//! A process owns an [`AddressSpace`] and, for now, a single thread, its
+//! main thread. [`create_kernel_process`] wraps a kernel thread that way:
+//! the address space is a clone of the kernel half the thread never loads.
+//! [`create_user_process`] runs a program [`elf::load`] loaded instead: its
+//! thread loads the address space and enters the program in ring 3, and
+//! once the `exit` syscall brought it back it loads the kernel's again and
+//! exits, which frees the program's memory.
+//!
+//! The process table lists them by [`Pid`]. Pid 0 stands for the kernel
+//! itself, the threads outside of any process, and the others are handed
+//! out lowest free first, so the pid of a process that exited is reused.
+//! The table entry goes away when the main thread exits, which drops the
+//! address space, and the scheduler frees the thread stack.
+
+use super::{
+	elf::{self, Image},
+	thread::{self, ThreadId, ThreadState},
+};
+use crate::{
+	arch::x86::{
+		cpu::{cr3, set_cr3, without_interrupts},
+		usermode,
+	},
+	error::KernelError,
+	memory::{paging::AddressSpace, VirtAddr},
+	println,
+	sync::Mutex,
+};
+use alloc::{string::String, vec::Vec};
+use core::fmt;
+
+/// Number of pids, the kernel's included.
+pub const MAX_PIDS: usize = 64;
+
+/// Identifies a process. 0 is the kernel, see the module documentation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Pid(pub u32);
+
+impl fmt::Display for Pid {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		return write!(f, "{}", self.0);
+	}
+}
+
+/// A process in the process table.
+pub struct Process {
+	pid: Pid,
+	name: String,
+	/// Never loaded while the process only runs kernel code.
+	space: AddressSpace,
+	/// The thread that runs the process, it ends with it.
+	main_thread: ThreadId,
+	/// Where the program of a user process starts, and its stack pointer.
+	user_entry: Option<(VirtAddr, VirtAddr)>,
+}
+
+impl Process {
+	/// Returns the process's pid.
+	pub fn pid(&self) -> Pid {
+		return self.pid;
+	}
+
+	/// Returns the name the process was created with.
+	pub fn name(&self) -> &str {
+		return &self.name;
+	}
+
+	/// Returns the address space of the process.
+	pub fn space(&self) -> &AddressSpace {
+		return &self.space;
+	}
+
+	/// Returns the id of the main thread.
+	pub fn main_thread(&self) -> ThreadId {
+		return self.main_thread;
+	}
+
+	/// Returns whether the process runs a program in ring 3.
+	pub fn is_user(&self) -> bool {
+		return self.user_entry.is_some();
+	}
+
+	/// Returns what the main thread is doing, `Dead` once it exited.
+	pub fn state(&self) -> ThreadState {
+		return thread::state_of(self.main_thread).unwrap_or(ThreadState::Dead);
+	}
+}
+
+/// One bit per pid, set while it is in use.
+struct PidAllocator {
+	used: [u32; MAX_PIDS / 32],
+}
+
+impl PidAllocator {
+	/// Takes the lowest free pid, never 0.
+	fn allocate(&mut self) -> Option<Pid> {
+		let pid = (1..MAX_PIDS).find(|&pid| !self.is_used(pid))?;
+		self.used[pid / 32] |= 1 << (pid % 32);
+
+		return Some(Pid(pid as u32));
+	}
+
+	/// Gives `pid` back for the next process.
+	fn free(&mut self, pid: Pid) {
+		let pid = pid.0 as usize;
+		self.used[pid / 32] &= !(1 << (pid % 32));
+	}
+
+	// Helper returning whether `pid` is handed out
+	const fn is_used(&self, pid: usize) -> bool {
+		return (self.used[pid / 32] & (1 << (pid % 32))) != 0;
+	}
+}
+
+struct ProcessTable {
+	pids: PidAllocator,
+	processes: Vec<Process>,
+}
+
+/// The processes alive. Taken before `SCHEDULER`, never inside it.
+static PROCESSES: Mutex<ProcessTable> = Mutex::named(
+	"PROCESSES",
+	ProcessTable {
+		pids: PidAllocator {
+			used: [0; MAX_PIDS / 32],
+		},
+		processes: Vec::new(),
+	},
+);
+
+/// What [`list`] reports about a process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProcessInfo {
+	/// The process's pid.
+	pub pid: Pid,
+	/// The name it was created with.
+	pub name: String,
+	/// What its main thread is doing.
+	pub state: ThreadState,
+	/// Bytes of its main thread's stack used so far.
+	pub stack_used: Option<usize>,
+}
+
+/// Creates a process named `name` whose main thread runs `entry`, and
+/// returns its pid. The thread is queued like with [`thread::spawn`].
+///
+/// Returns `OutOfMemory` when no pid is free or there is no room for the
+/// address space or the stack.
+pub fn create_kernel_process(
+	name: &str,
+	entry: fn(),
+) -> Result<Pid, KernelError> {
+	let space = AddressSpace::new_kernel_clone()?;
+	return create(name, space, entry, None);
+}
+
+/// Creates a process named `name` running the program in `image` in ring 3,
+/// and returns its pid. Like [`create_kernel_process`] otherwise.
+///
+/// Only one process runs user code at a time, see [`usermode::run`].
+pub fn create_user_process(
+	name: &str,
+	image: Image,
+) -> Result<Pid, KernelError> {
+	let entry = Some((image.entry, image.stack_top));
+	return create(name, image.space, user_main, entry);
+}
+
+// Helper listing a process with a new main thread running `entry`
+fn create(
+	name: &str,
+	space: AddressSpace,
+	entry: fn(),
+	user_entry: Option<(VirtAddr, VirtAddr)>,
+) -> Result<Pid, KernelError> {
+	// Held until the process is listed, for the thread to find it
+	let mut table = PROCESSES.lock();
+	let pid = table.pids.allocate().ok_or(KernelError::OutOfMemory)?;
+	let main_thread = match thread::spawn(entry) {
+		Ok(id) => id,
+		Err(error) => {
+			table.pids.free(pid);
+			return Err(error);
+		}
+	};
+
+	table.processes.push(Process {
+		pid,
+		name: String::from(name),
+		space,
+		main_thread,
+		user_entry,
+	});
+	return Ok(pid);
+}
+
+/// The main thread of a user process. Runs the program until it exits and
+/// tells what it exited with.
+fn user_main() {
+	let id = thread::current();
+	let kernel_directory = cr3();
+
+	// No switch while the address space is loaded, the others run on the
+	// kernel's
+	let exited = without_interrupts(|| {
+		let table = PROCESSES.lock();
+		let process = table
+			.processes
+			.iter()
+			.find(|process| process.main_thread == id)?;
+		let (entry, stack_top) = process.user_entry?;
+		let pid = process.pid;
+
+		// Safety: the process, and with it the address space, is only
+		// removed once this thread exits.
+		unsafe { process.space.switch_to() };
+		drop(table);
+
+		// Safety: `elf::load` mapped the program and its stack user
+		// accessible.
+		let code = unsafe { usermode::run(entry, stack_top) };
+		unsafe { set_cr3(kernel_directory) };
+
+		return Some((pid, code));
+	});
+
+	if let Some((pid, code)) = exited {
+		println!("process {} exited with {}", pid, code);
+	}
+}
+
+/// Removes the process thread `id` is the main thread of from the table
+/// and frees its pid and address space. Called by [`thread::exit`].
+pub(super) fn thread_exited(id: ThreadId) {
+	let mut table = PROCESSES.lock();
+	let Some(index) = table
+		.processes
+		.iter()
+		.position(|process| process.main_thread == id)
+	else {
+		return;
+	};
+
+	let process = table.processes.remove(index);
+	table.pids.free(process.pid);
+	drop(table);
+
+	// Frees the address space outside of the table lock
+	drop(process);
+}
+
+/// Returns the pid of the process running, 0 for threads outside of any.
+pub fn current_pid() -> Pid {
+	let id = thread::current();
+
+	return PROCESSES
+		.lock()
+		.processes
+		.iter()
+		.find(|process| process.main_thread == id)
+		.map_or(Pid(0), Process::pid);
+}
+
+/// Returns the main thread of process `pid`, `None` if it does not exist.
+pub fn main_thread(pid: Pid) -> Option<ThreadId> {
+	return PROCESSES
+		.lock()
+		.processes
+		.iter()
+		.find(|process| process.pid == pid)
+		.map(Process::main_thread);
+}
+
+/// Returns the processes alive, by pid.
+pub fn list() -> Vec<ProcessInfo> {
+	let table = PROCESSES.lock();
+	let mut processes: Vec<ProcessInfo> = table
+		.processes
+		.iter()
+		.map(|process| ProcessInfo {
+			pid: process.pid,
+			name: process.name.clone(),
+			state: process.state(),
+			stack_used: thread::stack_used(process.main_thread),
+		})
+		.collect();
+	drop(table);
+
+	processes.sort_by_key(|process| process.pid);
+	return processes;
+}
+
+/// Returns the number of processes alive.
+pub fn count() -> usize {
+	return PROCESSES.lock().processes.len();
+}
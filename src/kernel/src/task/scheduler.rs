@@ -0,0 +1,362 @@
+//! The round-robin ready queue.
+//!
+//! A thread runs until it calls [`yield_now`], sleeps, exits or uses up its
+//! time slice, and the next one is the thread that waited longest. The
+//! running thread is not in the queue, switching away puts it at the back.
+//! Sleeping threads wait in a list sorted by the tick they wake at.
+//!
+//! The idle thread [`init`] spawns only runs when no other thread is ready.
+//! It halts the CPU until the next interrupt, and hands it on as soon as a
+//! sleeper woke.
+//!
+//! The timer interrupt calls [`tick`], which wakes the sleepers due and
+//! switches once the slice is over. It does so only while nothing keeps the
+//! running thread from being preempted: no lock held (see [`preempt`]) and,
+//! for switching, no user code running, which has the single kernel stack
+//! the TSS names. Until then it tries again on every tick.
+//!
+//! Switches happen with interrupts off, and the `SCHEDULER` lock is released
+//! before `switch_to`, so the thread switched to can take it again.
+
+use super::thread::{self, Thread, ThreadId, ThreadState};
+use crate::{
+	arch::x86::{
+		cpu::{cli, halt, without_interrupts},
+		usermode,
+	},
+	collections::linked_list::LinkedList,
+	error::KernelError,
+	sync::{preempt, Mutex},
+	time,
+};
+use alloc::boxed::Box;
+use core::{
+	mem,
+	sync::atomic::{AtomicBool, AtomicU32, Ordering},
+};
+
+/// Timer ticks a thread runs before [`tick`] switches away from it.
+pub const TIME_SLICE_TICKS: u32 = 10;
+
+struct Scheduler {
+	/// The running thread. `None` until the boot thread first switches away.
+	current: Option<Box<Thread>>,
+	/// Threads waiting to run, the next one first.
+	ready: LinkedList<Box<Thread>>,
+	/// Sleeping threads, the one to wake first in front.
+	sleeping: LinkedList<Box<Thread>>,
+	/// The idle thread while it does not run. `None` before [`init`].
+	idle: Option<Box<Thread>>,
+	idle_id: Option<ThreadId>,
+	/// Threads that exited, freed by the next thread to run.
+	dead: LinkedList<Box<Thread>>,
+	/// Id of the thread spawned last.
+	last_id: u32,
+}
+
+static SCHEDULER: Mutex<Scheduler> = Mutex::named(
+	"SCHEDULER",
+	Scheduler {
+		current: None,
+		ready: LinkedList::new(),
+		sleeping: LinkedList::new(),
+		idle: None,
+		idle_id: None,
+		dead: LinkedList::new(),
+		last_id: 0,
+	},
+);
+
+/// Ticks left of the running thread's time slice.
+static SLICE_LEFT: AtomicU32 = AtomicU32::new(TIME_SLICE_TICKS);
+
+/// Whether the idle thread is the running one.
+static IDLE_RUNNING: AtomicBool = AtomicBool::new(false);
+
+/// Ticks that found the idle thread running.
+static IDLE_TICKS: AtomicU32 = AtomicU32::new(0);
+
+impl Scheduler {
+	/// Takes the running thread out, to queue it somewhere.
+	fn take_current(&mut self) -> Box<Thread> {
+		return self
+			.current
+			.take()
+			.unwrap_or_else(|| Box::new(Thread::boot()));
+	}
+
+	/// Returns the thread to run when the running one stops, the idle thread
+	/// if no other is ready.
+	fn pick_next(&mut self) -> Option<Box<Thread>> {
+		return self.ready.pop_front().or_else(|| self.idle.take());
+	}
+
+	/// Makes `next` the running thread and returns the stack pointer to
+	/// switch to.
+	fn run(&mut self, mut next: Box<Thread>) -> usize {
+		next.set_state(ThreadState::Running);
+		let esp = next.esp;
+		IDLE_RUNNING.store(Some(next.id()) == self.idle_id, Ordering::Relaxed);
+		self.current = Some(next);
+		SLICE_LEFT.store(TIME_SLICE_TICKS, Ordering::Relaxed);
+
+		return esp;
+	}
+
+	/// Puts a thread switched away from back where it waits to run again,
+	/// and returns where its stack pointer is saved.
+	fn park(&mut self, mut thread: Box<Thread>) -> *mut usize {
+		thread.set_state(ThreadState::Ready);
+		if Some(thread.id()) != self.idle_id {
+			return push_back(&mut self.ready, thread);
+		}
+
+		let idle = self.idle.insert(thread);
+		return &raw mut idle.esp;
+	}
+
+	/// Moves the sleepers due at tick `now` to the ready queue.
+	fn wake_sleepers(&mut self, now: u64) {
+		while self
+			.sleeping
+			.front()
+			.is_some_and(|thread| thread.wake_at() <= now)
+		{
+			if let Some(mut thread) = self.sleeping.pop_front() {
+				thread.set_state(ThreadState::Ready);
+				self.ready.push_back(thread);
+			}
+		}
+	}
+}
+
+// Helper queuing `thread` at the back of `list` and returning where its
+// stack pointer is saved, which stays put while it is queued
+fn push_back(
+	list: &mut LinkedList<Box<Thread>>,
+	thread: Box<Thread>,
+) -> *mut usize {
+	list.push_back(thread);
+
+	match list.back_mut() {
+		Some(thread) => return &raw mut thread.esp,
+		None => unreachable!("queue is empty right after a push"),
+	}
+}
+
+// Helper inserting `thread` behind the sleepers waking no later than it,
+// like `push_back`
+fn insert_sleeper(
+	list: &mut LinkedList<Box<Thread>>,
+	thread: Box<Thread>,
+) -> *mut usize {
+	let mut cursor = list.cursor_front_mut();
+	while cursor
+		.current()
+		.is_some_and(|sleeper| sleeper.wake_at() <= thread.wake_at())
+	{
+		cursor.move_next();
+	}
+	cursor.insert_before(thread);
+
+	match cursor.peek_prev() {
+		Some(thread) => return &raw mut thread.esp,
+		None => unreachable!("no sleeper right after an insert"),
+	}
+}
+
+/// Spawns the idle thread, which runs whenever no other thread is ready.
+pub fn init() -> Result<(), KernelError> {
+	let id = next_id();
+	let idle = Box::new(Thread::new(id, idle)?);
+
+	let mut scheduler = SCHEDULER.lock();
+	scheduler.idle = Some(idle);
+	scheduler.idle_id = Some(id);
+
+	return Ok(());
+}
+
+/// The idle thread, parks the CPU until an interrupt made a thread ready.
+fn idle() {
+	loop {
+		halt();
+		yield_now();
+	}
+}
+
+/// Switches to the next ready thread and returns once the running one is
+/// switched to again. Returns at once if no other thread is ready.
+pub fn yield_now() {
+	without_interrupts(|| {
+		let mut scheduler = SCHEDULER.lock();
+		let Some(next) = scheduler.ready.pop_front() else {
+			return;
+		};
+
+		let previous = scheduler.take_current();
+		let old_esp = scheduler.park(previous);
+		let new_esp = scheduler.run(next);
+		drop(scheduler);
+
+		// Safety: the previous thread stays queued until it is switched to.
+		unsafe { thread::switch(old_esp, new_esp) };
+		reap();
+	});
+}
+
+/// Puts the running thread to sleep until tick `wake_at`, and returns once
+/// it woke and was switched to again, see [`time::sleep_ms`].
+///
+/// Returns `false` right away if there is no other thread to run meanwhile,
+/// as before [`init`].
+pub fn sleep_until(wake_at: u64) -> bool {
+	return without_interrupts(|| {
+		let mut scheduler = SCHEDULER.lock();
+		let Some(next) = scheduler.pick_next() else {
+			return false;
+		};
+
+		let mut current = scheduler.take_current();
+		current.set_state(ThreadState::Sleeping);
+		current.set_wake_at(wake_at);
+		let old_esp = insert_sleeper(&mut scheduler.sleeping, current);
+		let new_esp = scheduler.run(next);
+		drop(scheduler);
+
+		// Safety: the sleeper stays in the list until it is switched to.
+		unsafe { thread::switch(old_esp, new_esp) };
+		reap();
+		return true;
+	});
+}
+
+/// Wakes the sleepers due, counts down the running thread's time slice and
+/// switches to the next ready thread when it is over. Called by the timer
+/// interrupt after its EOI, so the ticks keep coming while another thread
+/// runs.
+pub fn tick() {
+	if IDLE_RUNNING.load(Ordering::Relaxed) {
+		IDLE_TICKS.fetch_add(1, Ordering::Relaxed);
+	}
+	let left = SLICE_LEFT.load(Ordering::Relaxed).saturating_sub(1);
+	SLICE_LEFT.store(left, Ordering::Relaxed);
+
+	// No lock is held then, `SCHEDULER` and the allocators included
+	if !preempt::is_preemptible() {
+		return;
+	}
+	SCHEDULER.lock().wake_sleepers(time::ticks());
+
+	if left > 0 || usermode::is_running() {
+		return;
+	}
+	SLICE_LEFT.store(TIME_SLICE_TICKS, Ordering::Relaxed);
+	yield_now();
+}
+
+/// Ends the running thread, see [`thread::exit`].
+pub(super) fn exit_current() -> ! {
+	cli();
+
+	let mut scheduler = SCHEDULER.lock();
+	let Some(next) = scheduler.pick_next() else {
+		drop(scheduler);
+		panic!("thread {} exited with no thread left to run", current_id());
+	};
+
+	let mut current = scheduler.take_current();
+	current.set_state(ThreadState::Dead);
+	let old_esp = push_back(&mut scheduler.dead, current);
+	let new_esp = scheduler.run(next);
+	drop(scheduler);
+
+	// Safety: the exited thread is only freed once `next` runs.
+	unsafe { thread::switch(old_esp, new_esp) };
+	unreachable!("switched to an exited thread");
+}
+
+/// Frees the threads that exited. The thread switched to calls it right
+/// after the switch, no longer on their stacks.
+pub(super) fn reap() {
+	let dead = mem::take(&mut SCHEDULER.lock().dead);
+	drop(dead);
+}
+
+/// Queues a new thread behind the ready ones.
+pub(super) fn add(thread: Thread) {
+	let thread = Box::new(thread);
+	SCHEDULER.lock().ready.push_back(thread);
+}
+
+/// Returns the id for the next thread spawned.
+pub(super) fn next_id() -> ThreadId {
+	let mut scheduler = SCHEDULER.lock();
+	scheduler.last_id += 1;
+
+	return ThreadId(scheduler.last_id);
+}
+
+/// Returns the function the running thread runs.
+pub(super) fn current_entry() -> Option<fn()> {
+	return SCHEDULER
+		.lock()
+		.current
+		.as_ref()
+		.and_then(|thread| thread.entry());
+}
+
+/// Returns the id of the running thread.
+pub(super) fn current_id() -> ThreadId {
+	return SCHEDULER
+		.lock()
+		.current
+		.as_ref()
+		.map_or(ThreadId(0), |thread| thread.id());
+}
+
+/// Returns whether thread `id` is running, ready or sleeping.
+pub(super) fn is_alive(id: ThreadId) -> bool {
+	let scheduler = SCHEDULER.lock();
+	let current = scheduler
+		.current
+		.as_ref()
+		.map_or(ThreadId(0), |thread| thread.id());
+
+	return current == id
+		|| scheduler.ready.iter().any(|thread| thread.id() == id)
+		|| scheduler.sleeping.iter().any(|thread| thread.id() == id);
+}
+
+/// Calls `f` with thread `id` if it is running, ready or sleeping.
+pub(super) fn with_thread<R>(
+	id: ThreadId,
+	f: impl FnOnce(&Thread) -> R,
+) -> Option<R> {
+	let scheduler = SCHEDULER.lock();
+	let thread = scheduler
+		.current
+		.iter()
+		.chain(scheduler.ready.iter())
+		.chain(scheduler.sleeping.iter())
+		.chain(scheduler.idle.iter())
+		.find(|thread| thread.id() == id)?;
+
+	return Some(f(thread));
+}
+
+/// Returns the number of threads alive, not counting the idle thread.
+pub(super) fn count() -> usize {
+	let scheduler = SCHEDULER.lock();
+	let running = match IDLE_RUNNING.load(Ordering::Relaxed) {
+		true => 0,
+		false => 1,
+	};
+
+	return running + scheduler.ready.len() + scheduler.sleeping.len();
+}
+
+/// Returns the number of timer ticks that found the idle thread running.
+pub fn idle_ticks() -> u32 {
+	return IDLE_TICKS.load(Ordering::Relaxed);
+}
@@ -0,0 +1,297 @@
+//! Kernel threads, each running on a stack of its own.
+//!
+//! [`spawn`] allocates the stack and lays out the frame `switch_to` pops, so
+//! the first switch to the new thread returns into `thread_start`, which
+//! calls the entry function. The thread ends when that returns or calls
+//! [`exit`]. The code `kernel_main` runs on the boot stack is thread 0.
+//!
+//! New stacks are filled with [`STACK_POISON`], so [`Thread::stack_used`]
+//! finds how deep a thread got by looking for the first word overwritten.
+
+use super::{process, scheduler};
+use crate::{
+	arch::x86::cpu::sti,
+	error::KernelError,
+	memory::{
+		kalloc::{kfree, kmalloc},
+		PAGE_SIZE,
+	},
+	time,
+};
+use core::{alloc::Layout, fmt, mem::size_of, ptr::NonNull};
+
+/// Size of the kernel stack of a spawned thread.
+pub const STACK_SIZE: usize = 16 * 1024;
+
+/// Word a new stack is filled with, see [`Thread::stack_used`].
+pub const STACK_POISON: usize = 0x57ac_d00d;
+
+/// Number of words in a thread stack.
+const STACK_WORDS: usize = STACK_SIZE / size_of::<usize>();
+
+/// EFLAGS a new thread starts with, the reserved bit 1. `thread_start`
+/// enables interrupts once the scheduler is done switching.
+const INITIAL_EFLAGS: usize = 1 << 1;
+
+/// Page aligned, so the buddy allocator hands out whole pages.
+const STACK_LAYOUT: Layout =
+	match Layout::from_size_align(STACK_SIZE, PAGE_SIZE) {
+		Ok(layout) => layout,
+		Err(_) => panic!("invalid thread stack layout"),
+	};
+
+extern "C" {
+	// src/arch/{target}/switch.asm
+	fn switch_to(old_esp: *mut usize, new_esp: usize);
+}
+
+/// Switches from the running thread to the one whose stack pointer is
+/// `new_esp`, saving the running one's at `old_esp`. Returns when a switch
+/// comes back to it.
+///
+/// # Safety
+///
+/// `new_esp` must be what a previous switch saved, or a frame [`spawn`] laid
+/// out, and `old_esp` must stay valid until the switch back.
+pub(super) unsafe fn switch(old_esp: *mut usize, new_esp: usize) {
+	unsafe { switch_to(old_esp, new_esp) };
+}
+
+/// Identifies a thread. The boot thread is 0, spawned ones count up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ThreadId(pub u32);
+
+impl fmt::Display for ThreadId {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		return write!(f, "{}", self.0);
+	}
+}
+
+/// What a thread is doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThreadState {
+	/// It is the one on the CPU.
+	Running,
+	/// It waits in the ready queue.
+	Ready,
+	/// It waits for the tick it wakes at.
+	Sleeping,
+	/// It exited, its stack is freed by the next thread to run.
+	Dead,
+}
+
+impl ThreadState {
+	/// Returns the lowercase name of the state for listings.
+	pub const fn name(self) -> &'static str {
+		match self {
+			ThreadState::Running => return "running",
+			ThreadState::Ready => return "ready",
+			ThreadState::Sleeping => return "sleeping",
+			ThreadState::Dead => return "dead",
+		}
+	}
+}
+
+/// A kernel thread and the stack it runs on.
+pub struct Thread {
+	id: ThreadId,
+	state: ThreadState,
+	/// Stack pointer `switch_to` saved, while the thread is not running.
+	pub(super) esp: usize,
+	/// The stack from [`kmalloc`], `None` for the boot thread.
+	stack: Option<NonNull<u8>>,
+	entry: Option<fn()>,
+	/// Tick a sleeping thread wakes at.
+	wake_at: u64,
+}
+
+// Safety: the stack is only touched by the thread itself and freed once it
+// stopped running, there is a single CPU.
+unsafe impl Send for Thread {}
+
+/// The frame `switch_to` pops, lowest address first.
+#[repr(C)]
+struct InitialFrame {
+	eflags: usize,
+	edi: usize,
+	esi: usize,
+	ebx: usize,
+	/// 0, so backtraces stop at the thread's first frame.
+	ebp: usize,
+	eip: extern "C" fn() -> !,
+	/// Where `thread_start` would return to, it never does.
+	return_address: usize,
+}
+
+impl Thread {
+	/// Returns the thread the boot code becomes when the scheduler first
+	/// switches away from it.
+	pub(super) const fn boot() -> Self {
+		return Self {
+			id: ThreadId(0),
+			state: ThreadState::Running,
+			esp: 0,
+			stack: None,
+			entry: None,
+			wake_at: 0,
+		};
+	}
+
+	/// Allocates a stack and lays out the frame the first switch to the
+	/// thread pops.
+	pub(super) fn new(id: ThreadId, entry: fn()) -> Result<Self, KernelError> {
+		let stack = kmalloc(STACK_LAYOUT)?;
+
+		let frame = InitialFrame {
+			eflags: INITIAL_EFLAGS,
+			edi: 0,
+			esi: 0,
+			ebx: 0,
+			ebp: 0,
+			eip: thread_start,
+			return_address: 0,
+		};
+		// Safety: the frame fits the top of the stack just allocated.
+		let esp = unsafe {
+			let words = stack.as_ptr().cast::<usize>();
+			for index in 0..STACK_WORDS {
+				words.add(index).write(STACK_POISON);
+			}
+
+			let top = stack.as_ptr().add(STACK_SIZE);
+			let frame_ptr = top.cast::<InitialFrame>().sub(1);
+			frame_ptr.write(frame);
+			frame_ptr as usize
+		};
+
+		return Ok(Self {
+			id,
+			state: ThreadState::Ready,
+			esp,
+			stack: Some(stack),
+			entry: Some(entry),
+			wake_at: 0,
+		});
+	}
+
+	/// Returns the thread's id.
+	pub fn id(&self) -> ThreadId {
+		return self.id;
+	}
+
+	/// Returns what the thread is doing.
+	pub fn state(&self) -> ThreadState {
+		return self.state;
+	}
+
+	pub(super) fn set_state(&mut self, state: ThreadState) {
+		self.state = state;
+	}
+
+	/// Returns the function the thread runs, `None` for the boot thread.
+	pub fn entry(&self) -> Option<fn()> {
+		return self.entry;
+	}
+
+	/// Returns the tick the thread wakes at, if it sleeps.
+	pub fn wake_at(&self) -> u64 {
+		return self.wake_at;
+	}
+
+	pub(super) fn set_wake_at(&mut self, tick: u64) {
+		self.wake_at = tick;
+	}
+
+	/// Returns the most of its stack the thread used so far, the bytes from
+	/// the top down to the deepest word no longer [`STACK_POISON`]. `None`
+	/// for the boot thread.
+	pub fn stack_used(&self) -> Option<usize> {
+		let stack = self.stack?;
+		let words = stack.as_ptr().cast::<usize>();
+
+		// Safety: the stack stays allocated as long as the thread.
+		let untouched = (0..STACK_WORDS)
+			.take_while(
+				|&index| unsafe { words.add(index).read() } == STACK_POISON,
+			)
+			.count();
+
+		return Some(STACK_SIZE - untouched * size_of::<usize>());
+	}
+}
+
+impl Drop for Thread {
+	fn drop(&mut self) {
+		if let Some(stack) = self.stack.take() {
+			// Safety: allocated by `Thread::new`, the thread no longer runs.
+			unsafe { kfree(stack, STACK_LAYOUT) };
+		}
+	}
+}
+
+/// Where the first switch to a spawned thread returns to.
+extern "C" fn thread_start() -> ! {
+	scheduler::reap();
+	sti();
+
+	if let Some(entry) = scheduler::current_entry() {
+		entry();
+	}
+	exit();
+}
+
+/// Creates a thread running `entry` and queues it behind the ready ones. It
+/// first runs when the running thread yields.
+///
+/// Returns `OutOfMemory` when there is no room for its stack.
+pub fn spawn(entry: fn()) -> Result<ThreadId, KernelError> {
+	let id = scheduler::next_id();
+	let thread = Thread::new(id, entry)?;
+
+	scheduler::add(thread);
+	return Ok(id);
+}
+
+/// Ends the running thread and switches to the next ready one, which frees
+/// its stack. The process the thread is the main thread of goes with it.
+///
+/// # Panics
+///
+/// Panics if no other thread is left to run.
+pub fn exit() -> ! {
+	process::thread_exited(current());
+	scheduler::exit_current();
+}
+
+/// Returns the id of the running thread.
+pub fn current() -> ThreadId {
+	return scheduler::current_id();
+}
+
+/// Returns whether thread `id` is running, ready or sleeping.
+pub fn is_alive(id: ThreadId) -> bool {
+	return scheduler::is_alive(id);
+}
+
+/// Returns what thread `id` is doing, `None` once it exited.
+pub fn state_of(id: ThreadId) -> Option<ThreadState> {
+	return scheduler::with_thread(id, Thread::state);
+}
+
+/// Returns the stack thread `id` used so far, see [`Thread::stack_used`].
+pub fn stack_used(id: ThreadId) -> Option<usize> {
+	return scheduler::with_thread(id, Thread::stack_used).flatten();
+}
+
+/// Returns the number of threads alive, the running one included and the
+/// idle thread not.
+pub fn count() -> usize {
+	return scheduler::count();
+}
+
+/// Waits until thread `id` has exited, sleeping a tick at a time.
+pub fn join(id: ThreadId) {
+	while is_alive(id) {
+		time::sleep_ms(1);
+	}
+}
@@ -0,0 +1,104 @@
+//! Deferred work, the bottom halves of interrupt handlers.
+//!
+//! A handler reads what the hardware has for it, hands the rest of the job
+//! to [`schedule_work`] and returns. The thread of the `kworker` process
+//! [`init`] creates runs the queued functions in order, in thread context:
+//! they may take any lock and take as long as they need.
+//!
+//! The queue is an [`SpscRing`] with pushes made with interrupts off, so
+//! handlers and threads may all schedule work without a lock. Work scheduled
+//! while the queue is full is dropped and counted.
+
+use super::{process, thread::ThreadId};
+use crate::{
+	arch::x86::cpu::without_interrupts, collections::ring_buffer::SpscRing,
+	error::KernelError, sync::OnceLock, time,
+};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+/// Work items the queue holds before it drops new ones.
+pub const QUEUE_SIZE: usize = 256;
+
+/// A function to run later with its argument.
+#[derive(Clone, Copy)]
+struct Work {
+	func: fn(usize),
+	arg: usize,
+}
+
+static QUEUE: SpscRing<Work, QUEUE_SIZE> = SpscRing::new();
+static WORKER: OnceLock<ThreadId> = OnceLock::new();
+
+static SCHEDULED: AtomicU32 = AtomicU32::new(0);
+static EXECUTED: AtomicU32 = AtomicU32::new(0);
+static DROPPED: AtomicU32 = AtomicU32::new(0);
+
+/// What happened to the work scheduled since boot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WorkqueueStats {
+	/// Work queued.
+	pub scheduled: u32,
+	/// Work the worker ran.
+	pub executed: u32,
+	/// Work dropped because the queue was full.
+	pub dropped: u32,
+	/// Work waiting to run.
+	pub pending: usize,
+}
+
+/// Creates the `kworker` process, whose thread is the worker.
+pub fn init() -> Result<(), KernelError> {
+	let pid = process::create_kernel_process("kworker", worker)?;
+	if let Some(id) = process::main_thread(pid) {
+		let _ = WORKER.set(id);
+	}
+
+	return Ok(());
+}
+
+/// Queues `func(arg)` for the worker thread. Returns `false` and counts it
+/// dropped if the queue is full.
+///
+/// Callable from any context, interrupt handlers included.
+pub fn schedule_work(func: fn(usize), arg: usize) -> bool {
+	// One producer at a time, a handler cannot come in halfway
+	let queued = without_interrupts(|| {
+		QUEUE.push(Work {
+			func,
+			arg,
+		})
+	});
+
+	match queued {
+		true => SCHEDULED.fetch_add(1, Ordering::Relaxed),
+		false => DROPPED.fetch_add(1, Ordering::Relaxed),
+	};
+	return queued;
+}
+
+/// Returns the id of the worker thread, `None` before [`init`].
+pub fn worker_id() -> Option<ThreadId> {
+	return WORKER.get().copied();
+}
+
+/// Returns the counters and the number of items waiting.
+pub fn stats() -> WorkqueueStats {
+	return WorkqueueStats {
+		scheduled: SCHEDULED.load(Ordering::Relaxed),
+		executed: EXECUTED.load(Ordering::Relaxed),
+		dropped: DROPPED.load(Ordering::Relaxed),
+		pending: QUEUE.len(),
+	};
+}
+
+/// The worker thread, the only one taking work off the queue. Runs what is
+/// queued in order and sleeps until the next tick.
+fn worker() {
+	loop {
+		while let Some(work) = QUEUE.pop() {
+			(work.func)(work.arg);
+			EXECUTED.fetch_add(1, Ordering::Relaxed);
+		}
+		time::sleep_ms(0);
+	}
+}
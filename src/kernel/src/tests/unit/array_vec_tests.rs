@@ -0,0 +1,128 @@
+use crate::collections::array_vec::{ArrayVec, CapacityError};
+use alloc::{string::String, vec::Vec};
+use core::cell::Cell;
+
+// Helper counting its drops in a shared cell
+struct DropCounter<'a>(&'a Cell<usize>);
+
+impl Drop for DropCounter<'_> {
+	fn drop(&mut self) {
+		self.0.set(self.0.get() + 1);
+	}
+}
+
+#[test_case]
+fn test_array_vec_push_pop() {
+	let mut vec: ArrayVec<u32, 4> = ArrayVec::new();
+	assert!(vec.is_empty());
+	assert_eq!(vec.capacity(), 4);
+	assert_eq!(vec.pop(), None);
+
+	for i in 0..4 {
+		assert_eq!(vec.push(i), Ok(()));
+	}
+	assert!(vec.is_full());
+	assert_eq!(vec.push(9), Err(CapacityError(9)));
+	assert_eq!(vec.as_slice(), [0, 1, 2, 3]);
+
+	assert_eq!(vec.pop(), Some(3));
+	assert_eq!(vec.len(), 3);
+	assert_eq!(vec.push(7), Ok(()));
+	assert_eq!(vec.as_slice(), [0, 1, 2, 7]);
+}
+
+#[test_case]
+fn test_array_vec_insert_remove() {
+	let mut vec: ArrayVec<u32, 5> = ArrayVec::new();
+
+	assert_eq!(vec.insert(0, 2), Ok(()));
+	assert_eq!(vec.insert(0, 0), Ok(()));
+	assert_eq!(vec.insert(1, 1), Ok(()));
+	assert_eq!(vec.insert(3, 3), Ok(()));
+	assert_eq!(vec.as_slice(), [0, 1, 2, 3]);
+
+	assert_eq!(vec.remove(1), 1);
+	assert_eq!(vec.remove(2), 3);
+	assert_eq!(vec.as_slice(), [0, 2]);
+
+	for value in [5, 6, 7] {
+		assert_eq!(vec.insert(1, value), Ok(()));
+	}
+	assert_eq!(vec.insert(0, 8), Err(CapacityError(8)));
+	assert_eq!(vec.as_slice(), [0, 7, 6, 5, 2]);
+}
+
+#[test_case]
+fn test_array_vec_derefs_to_slice() {
+	let mut vec: ArrayVec<u32, 8> = ArrayVec::new();
+	for i in [5, 3, 8, 1] {
+		let _ = vec.push(i);
+	}
+
+	vec.sort_unstable();
+	assert_eq!(vec[0], 1);
+	assert_eq!(vec.iter().sum::<u32>(), 17);
+	assert_eq!((&vec).into_iter().count(), 4);
+	assert!(vec.contains(&8));
+
+	vec[0] = 10;
+	let collected: Vec<u32> = vec.iter().copied().collect();
+	assert_eq!(collected, [10, 3, 5, 8]);
+}
+
+#[test_case]
+fn test_array_vec_drops_its_elements() {
+	let drops = Cell::new(0);
+
+	{
+		let mut vec: ArrayVec<DropCounter, 4> = ArrayVec::new();
+		for _ in 0..3 {
+			assert!(vec.push(DropCounter(&drops)).is_ok());
+		}
+
+		// The rejected element comes back and is dropped by the caller
+		assert!(vec.push(DropCounter(&drops)).is_ok());
+		let rejected = vec.push(DropCounter(&drops));
+		assert!(rejected.is_err());
+		drop(rejected);
+		assert_eq!(drops.get(), 1);
+
+		drop(vec.remove(0));
+		drop(vec.pop());
+		assert_eq!(drops.get(), 3);
+	}
+
+	// The two left were dropped with the vector
+	assert_eq!(drops.get(), 5);
+}
+
+#[test_case]
+fn test_array_vec_truncate_and_clear() {
+	let drops = Cell::new(0);
+	let mut vec: ArrayVec<DropCounter, 8> = ArrayVec::new();
+	for _ in 0..6 {
+		let _ = vec.push(DropCounter(&drops));
+	}
+
+	vec.truncate(10);
+	assert_eq!(drops.get(), 0);
+	vec.truncate(4);
+	assert_eq!((vec.len(), drops.get()), (4, 2));
+
+	vec.clear();
+	assert_eq!((vec.len(), drops.get()), (0, 6));
+}
+
+#[test_case]
+fn test_array_vec_clone_of_owned_values() {
+	let mut vec: ArrayVec<String, 3> = ArrayVec::new();
+	let _ = vec.push(String::from("memblock"));
+	let _ = vec.push(String::from("buddy"));
+
+	let clone = vec.clone();
+	assert_eq!(clone, vec);
+
+	vec[0].push_str("-early");
+	assert_eq!(clone[0], "memblock");
+	assert_eq!(vec[0], "memblock-early");
+}
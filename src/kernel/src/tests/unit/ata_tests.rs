@@ -0,0 +1,74 @@
+use crate::device::ata::{
+	self, error_name, parse_identify, AtaError, Drive, SECTOR_SIZE,
+};
+
+#[test_case]
+fn test_ata_parse_identify() {
+	let mut words = [0u16; 256];
+	// "QEMU HARDDISK" with the bytes of each word swapped, space padded
+	let model = b"QEMU HARDDISK                           ";
+	for (word, pair) in words[27..47].iter_mut().zip(model.chunks_exact(2)) {
+		*word = u16::from_be_bytes([pair[0], pair[1]]);
+	}
+	words[60] = 0x2000;
+	words[61] = 0x0001;
+
+	let info = parse_identify(Drive::Slave, &words);
+	assert_eq!(info.drive, Drive::Slave);
+	assert_eq!(info.model, "QEMU HARDDISK");
+	assert_eq!(info.sectors, 0x1_2000);
+	assert_eq!(info.size(), 0x1_2000 * SECTOR_SIZE as u64);
+}
+
+#[test_case]
+fn test_ata_error_names() {
+	assert_eq!(error_name(0x04), "aborted command");
+	assert_eq!(error_name(0x14), "aborted command");
+	assert_eq!(error_name(0x40), "uncorrectable data");
+	assert_eq!(error_name(0), "unknown error");
+}
+
+#[test_case]
+fn test_ata_rejects_bad_ranges() {
+	let mut sector = [0; SECTOR_SIZE];
+	let Some(info) = ata::info() else {
+		assert_eq!(ata::read_sectors(0, 1, &mut sector), Err(AtaError::NoDisk));
+		return;
+	};
+
+	assert_eq!(
+		ata::read_sectors(info.sectors, 1, &mut sector),
+		Err(AtaError::OutOfRange)
+	);
+	assert_eq!(
+		ata::read_sectors(0, 2, &mut sector),
+		Err(AtaError::BufferTooSmall)
+	);
+	assert_eq!(ata::read_sectors(0, 0, &mut []), Ok(()));
+}
+
+#[test_case]
+fn test_ata_write_read_restore() {
+	// The runner attaches a scratch disk, without one there is nothing to do
+	let Some(info) = ata::info() else {
+		return;
+	};
+	let lba = info.sectors - 2;
+
+	let mut original = [0; 2 * SECTOR_SIZE];
+	ata::read_sectors(lba, 2, &mut original).unwrap();
+
+	let mut pattern = [0; 2 * SECTOR_SIZE];
+	for (index, byte) in pattern.iter_mut().enumerate() {
+		*byte = (index * 7 + 3) as u8;
+	}
+	ata::write_sectors(lba, 2, &pattern).unwrap();
+
+	let mut read = [0; 2 * SECTOR_SIZE];
+	ata::read_sectors(lba, 2, &mut read).unwrap();
+	ata::write_sectors(lba, 2, &original).unwrap();
+	assert_eq!(read, pattern);
+
+	ata::read_sectors(lba, 2, &mut read).unwrap();
+	assert_eq!(read, original);
+}
@@ -0,0 +1,31 @@
+use crate::{
+	arch::x86::backtrace::{current_ebp, frames, MAX_FRAMES},
+	memory::get_kernel_text,
+};
+
+#[inline(never)]
+fn nested(depth: usize) -> usize {
+	if depth == 0 {
+		return frames(current_ebp()).count();
+	}
+
+	return core::hint::black_box(nested(depth - 1));
+}
+
+#[test_case]
+fn test_backtrace_walks_callers() {
+	let text = get_kernel_text();
+	let frames_here = frames(current_ebp()).count();
+
+	assert!(frames_here > 0);
+	assert!(frames(current_ebp()).all(|address| text.contains(&address)));
+	assert_eq!(nested(3), (frames_here + 4).min(MAX_FRAMES));
+}
+
+#[test_case]
+fn test_backtrace_stops_on_bad_frame_pointer() {
+	assert_eq!(frames(0).count(), 0);
+	assert_eq!(frames(3).count(), 0);
+	// Not mapped, below the kernel
+	assert_eq!(frames(0x1000).count(), 0);
+}
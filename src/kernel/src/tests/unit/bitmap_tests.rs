@@ -0,0 +1,138 @@
+use crate::collections::bitmap::Bitmap;
+use alloc::{vec, vec::Vec};
+
+// Helper creating a bitmap of `len` bits over leaked words filled with `word`
+fn bitmap(len: usize, word: usize) -> Bitmap {
+	let words = vec![word; Bitmap::words_for(len)].leak();
+
+	return Bitmap::new(words, len);
+}
+
+// Helper listing the set bits
+fn ones(map: &Bitmap) -> Vec<usize> {
+	return (0..map.len()).filter(|&bit| map.test(bit)).collect();
+}
+
+#[test_case]
+fn test_bitmap_set_clear_test() {
+	let mut map = bitmap(100, 0);
+	assert_eq!(map.len(), 100);
+	assert_eq!(map.count_ones(), 0);
+
+	map.set(0);
+	map.set(31);
+	map.set(32);
+	map.set(99);
+	assert_eq!(ones(&map), [0, 31, 32, 99]);
+
+	map.clear(31);
+	assert!(!map.test(31));
+	assert!(map.test(32));
+	assert_eq!(map.count_ones(), 3);
+}
+
+#[test_case]
+fn test_bitmap_ranges_across_words() {
+	let mut map = bitmap(128, 0);
+
+	map.set_range(30..70);
+	assert_eq!(map.count_ones(), 40);
+	assert_eq!(map.count_ones_in(0..32), 2);
+	assert_eq!(map.count_ones_in(64..128), 6);
+	assert!(map.is_range_set(30..70));
+	assert!(!map.is_range_set(29..70));
+	assert!(map.is_range_clear(0..30));
+	assert!(map.is_range_clear(70..128));
+
+	map.clear_range(31..69);
+	assert_eq!(ones(&map), [30, 69]);
+
+	// Empty ranges touch nothing
+	map.set_range(40..40);
+	map.clear_range(30..30);
+	assert_eq!(ones(&map), [30, 69]);
+	assert!(map.is_range_clear(50..50));
+}
+
+#[test_case]
+fn test_bitmap_ranges_are_clamped() {
+	let mut map = bitmap(40, 0);
+
+	map.set_range(35..1000);
+	assert_eq!(ones(&map), [35, 36, 37, 38, 39]);
+	assert_eq!(map.count_ones_in(0..usize::MAX), 5);
+
+	// The bits past the length in the last word are left alone
+	map.fill(true);
+	assert_eq!(map.count_ones(), 40);
+	map.fill(false);
+	assert_eq!(map.count_ones(), 0);
+}
+
+#[test_case]
+fn test_bitmap_words_past_len_are_ignored() {
+	let map = bitmap(40, usize::MAX);
+	assert_eq!(map.count_ones(), 40);
+
+	let mut map = bitmap(40, 0);
+	map.set_range(0..40);
+	assert_eq!(map.find_first_zero(), None);
+	assert_eq!(map.find_zero_run(1), None);
+}
+
+#[test_case]
+fn test_bitmap_find_first() {
+	let mut map = bitmap(96, 0);
+	map.set_range(0..70);
+
+	assert_eq!(map.find_first_zero(), Some(70));
+	assert_eq!(map.find_first_zero_in(0..70), None);
+	assert_eq!(map.find_first_zero_in(75..96), Some(75));
+	assert_eq!(map.find_first_one_in(70..96), None);
+	assert_eq!(map.find_first_one_in(33..96), Some(33));
+
+	map.set(95);
+	assert_eq!(map.find_first_one_in(70..96), Some(95));
+}
+
+#[test_case]
+fn test_bitmap_find_zero_run() {
+	let mut map = bitmap(128, 0);
+	map.set(3);
+	map.set(40);
+	map.set(100);
+
+	assert_eq!(map.find_zero_run(3), Some(0));
+	assert_eq!(map.find_zero_run(4), Some(4));
+	assert_eq!(map.find_zero_run(36), Some(4));
+	assert_eq!(map.find_zero_run(37), Some(41));
+	assert_eq!(map.find_zero_run(59), Some(41));
+	assert_eq!(map.find_zero_run(60), None);
+	assert_eq!(map.find_zero_run(0), None);
+}
+
+#[test_case]
+fn test_bitmap_find_zero_run_aligned_in_range() {
+	let mut map = bitmap(128, 0);
+	map.set(3);
+	map.set(40);
+
+	assert_eq!(map.find_zero_run_in(0..128, 8, 8), Some(8));
+	assert_eq!(map.find_zero_run_in(0..128, 32, 32), Some(64));
+	assert_eq!(map.find_zero_run_in(1..128, 2, 1), Some(1));
+	assert_eq!(map.find_zero_run_in(41..50, 9, 1), Some(41));
+	assert_eq!(map.find_zero_run_in(41..50, 10, 1), None);
+	assert_eq!(map.find_zero_run_in(100..200, 28, 4), Some(100));
+	assert_eq!(map.find_zero_run_in(100..200, 29, 4), None);
+}
+
+#[test_case]
+fn test_bitmap_empty() {
+	let map = Bitmap::empty();
+
+	assert!(map.is_empty());
+	assert_eq!(map.count_ones(), 0);
+	assert_eq!(map.find_first_zero(), None);
+	assert_eq!(map.find_zero_run(1), None);
+	assert!(map.is_range_clear(0..10));
+}
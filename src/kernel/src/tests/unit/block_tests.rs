@@ -0,0 +1,86 @@
+use crate::{
+	device::block::{self, BlockDevice, RamDisk, RAM0_BLOCKS, RAM_BLOCK_SIZE},
+	error::KernelError,
+};
+use alloc::boxed::Box;
+
+#[test_case]
+fn test_ram_disk_starts_zeroed() {
+	let disk = RamDisk::new(4, RAM_BLOCK_SIZE).unwrap();
+	assert_eq!(disk.block_size(), RAM_BLOCK_SIZE);
+	assert_eq!(disk.num_blocks(), 4);
+
+	let mut block = [0xff; RAM_BLOCK_SIZE];
+	for lba in 0..4 {
+		disk.read_block(lba, &mut block).unwrap();
+		assert!(block.iter().all(|&byte| byte == 0));
+	}
+}
+
+#[test_case]
+fn test_ram_disk_write_read() {
+	let disk = RamDisk::new(8, 1024).unwrap();
+
+	for lba in 0..8 {
+		disk.write_block(lba, &[lba as u8 + 1; 1024]).unwrap();
+	}
+	let mut block = [0; 1024];
+	for lba in (0..8).rev() {
+		disk.read_block(lba, &mut block).unwrap();
+		assert!(block.iter().all(|&byte| byte == lba as u8 + 1));
+	}
+}
+
+#[test_case]
+fn test_ram_disk_rejects_bad_arguments() {
+	let disk = RamDisk::new(2, RAM_BLOCK_SIZE).unwrap();
+	let mut block = [0; RAM_BLOCK_SIZE];
+
+	assert_eq!(
+		disk.read_block(2, &mut block),
+		Err(KernelError::InvalidArgument)
+	);
+	assert_eq!(
+		disk.write_block(0, &block[..RAM_BLOCK_SIZE - 1]),
+		Err(KernelError::InvalidArgument)
+	);
+	assert_eq!(
+		RamDisk::new(0, RAM_BLOCK_SIZE).err(),
+		Some(KernelError::InvalidArgument)
+	);
+}
+
+#[test_case]
+fn test_block_registry() {
+	let disk = RamDisk::new(3, RAM_BLOCK_SIZE).unwrap();
+	disk.write_block(1, &[0x5a; RAM_BLOCK_SIZE]).unwrap();
+	block::register("ramtest", Box::new(disk)).unwrap();
+
+	let other = RamDisk::new(1, RAM_BLOCK_SIZE).unwrap();
+	assert_eq!(
+		block::register("ramtest", Box::new(other)),
+		Err(KernelError::InvalidArgument)
+	);
+
+	let info = block::list();
+	let listed = info.iter().find(|device| device.name == "ramtest").unwrap();
+	assert_eq!(listed.num_blocks, 3);
+	assert_eq!(listed.block_size, RAM_BLOCK_SIZE);
+
+	let mut data = [0; RAM_BLOCK_SIZE];
+	let read = block::with_device("ramtest", |device| {
+		return device.read_block(1, &mut data);
+	});
+	assert_eq!(read, Some(Ok(())));
+	assert!(data.iter().all(|&byte| byte == 0x5a));
+
+	assert!(block::unregister("ramtest").is_some());
+	assert!(block::with_device("ramtest", |_| ()).is_none());
+	assert!(block::unregister("ramtest").is_none());
+}
+
+#[test_case]
+fn test_ram0_registered_at_boot() {
+	let blocks = block::with_device("ram0", |device| device.num_blocks());
+	assert_eq!(blocks, Some(RAM0_BLOCKS));
+}
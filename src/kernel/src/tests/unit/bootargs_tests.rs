@@ -0,0 +1,58 @@
+use crate::bootargs::{BootArgs, MAX_CMDLINE, MAX_OPTIONS};
+use alloc::{format, string::String};
+
+#[test_case]
+fn test_bootargs_keys_and_values() {
+	let args =
+		BootArgs::parse("/boot/ferrite.bin loglevel=debug quiet memtest=1");
+
+	assert_eq!(args.len(), 4);
+	assert_eq!(args.get("loglevel"), Some("debug"));
+	assert_eq!(args.get("memtest"), Some("1"));
+	assert_eq!(args.get("quiet"), Some(""));
+	assert!(args.has("quiet"));
+	assert!(!args.has("serial"));
+	assert_eq!(args.get("serial"), None);
+}
+
+#[test_case]
+fn test_bootargs_last_option_wins() {
+	let args = BootArgs::parse("console=vga console=serial");
+
+	assert_eq!(args.get("console"), Some("serial"));
+}
+
+#[test_case]
+fn test_bootargs_skip_extra_spaces_and_keep_equals_in_values() {
+	let args = BootArgs::parse("  a=b=c   empty=  ");
+
+	assert_eq!(args.len(), 2);
+	assert_eq!(args.get("a"), Some("b=c"));
+	assert_eq!(args.get("empty"), Some(""));
+	assert_eq!(args.raw(), "  a=b=c   empty=  ");
+}
+
+#[test_case]
+fn test_bootargs_empty_command_line() {
+	let args = BootArgs::parse("");
+
+	assert!(args.is_empty());
+	assert_eq!(args.raw(), "");
+	assert_eq!(args.get(""), None);
+}
+
+#[test_case]
+fn test_bootargs_limits() {
+	let mut long = String::new();
+	for index in 0..MAX_OPTIONS + 4 {
+		long.push_str(&format!("k{}=v ", index));
+	}
+	let args = BootArgs::parse(&long);
+	assert_eq!(args.len(), MAX_OPTIONS);
+	assert!(args.has("k0"));
+	assert!(!args.has(&format!("k{}", MAX_OPTIONS)));
+
+	let cut = "x".repeat(MAX_CMDLINE + 10);
+	let args = BootArgs::parse(&cut);
+	assert_eq!(args.raw().len(), MAX_CMDLINE);
+}
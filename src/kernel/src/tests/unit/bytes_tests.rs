@@ -0,0 +1,10 @@
+use crate::util::bytes::{u16_at, u32_at};
+
+#[test_case]
+fn test_bytes_read_little_endian() {
+	let bytes = [0x11, 0x22, 0x33, 0x44, 0x55];
+
+	assert_eq!(u16_at(&bytes, 0), 0x2211);
+	assert_eq!(u16_at(&bytes, 3), 0x5544);
+	assert_eq!(u32_at(&bytes, 1), 0x5544_3322);
+}
@@ -0,0 +1,125 @@
+use crate::{
+	device::keyboard::{KeyEvent, KeyboardKey},
+	libc::console::{
+		command::{find_command, RegisterError},
+		line::{LineBuffer, INLINE_CAPACITY, MAX_LINE},
+		register_command,
+		serial_input::SerialInput,
+		Command,
+	},
+};
+
+const TEST_COMMAND: Command = Command {
+	name: "test-registry",
+	usage: "test-registry",
+	help: "Registered by the unit tests",
+	run: |_, _| {},
+};
+
+#[test_case]
+fn test_builtin_is_found() {
+	assert!(find_command("help").is_some());
+	assert!(find_command("no-such-command").is_none());
+}
+
+#[test_case]
+fn test_register_command() {
+	assert_eq!(register_command(TEST_COMMAND), Ok(()));
+	assert_eq!(
+		find_command("test-registry").map(|c| c.help),
+		Some(TEST_COMMAND.help)
+	);
+
+	assert_eq!(
+		register_command(TEST_COMMAND),
+		Err(RegisterError::AlreadyExists)
+	);
+}
+
+#[test_case]
+fn test_register_builtin_name_fails() {
+	let command = Command {
+		name: "help",
+		..TEST_COMMAND
+	};
+
+	assert_eq!(register_command(command), Err(RegisterError::AlreadyExists));
+}
+
+#[test_case]
+fn test_line_insert_remove_inline() {
+	let mut line = LineBuffer::new();
+
+	assert!(line.insert(0, b'a'));
+	assert!(line.insert(1, b'c'));
+	assert!(line.insert(1, b'b'));
+	assert_eq!(line.as_bytes(), b"abc");
+
+	assert_eq!(line.remove(0), Some(b'a'));
+	assert_eq!(line.remove(2), None);
+	assert_eq!(line.as_bytes(), b"bc");
+	assert!(!line.is_spilled());
+}
+
+#[test_case]
+fn test_line_spills_and_shrinks() {
+	let mut line = LineBuffer::new();
+
+	for i in 0..INLINE_CAPACITY {
+		assert!(line.insert(i, b'x'));
+	}
+	assert!(!line.is_spilled());
+
+	assert!(line.insert(0, b'y'));
+	assert!(line.is_spilled());
+	assert_eq!(line.len(), INLINE_CAPACITY + 1);
+	assert_eq!(line.as_bytes()[0], b'y');
+	assert_eq!(line.as_bytes()[INLINE_CAPACITY], b'x');
+
+	line.clear();
+	assert!(line.is_empty());
+	assert!(!line.is_spilled());
+}
+
+#[test_case]
+fn test_line_stops_at_max_line() {
+	let mut line = LineBuffer::new();
+
+	for i in 0..MAX_LINE {
+		assert!(line.insert(i, b'x'));
+	}
+
+	assert!(!line.insert(MAX_LINE, b'x'));
+	assert_eq!(line.len(), MAX_LINE);
+}
+
+#[test_case]
+fn test_serial_input_translates_terminal_keys() {
+	let mut input = SerialInput::new();
+
+	assert_eq!(input.feed(b'a'), Some(KeyEvent::Char('a')));
+	assert_eq!(input.feed(b'\r'), Some(KeyEvent::Char('\n')));
+	assert_eq!(input.feed(0x7f), Some(KeyEvent::Char('\x08')));
+}
+
+#[test_case]
+fn test_serial_input_decodes_escape_sequences() {
+	let mut input = SerialInput::new();
+
+	assert_eq!(input.feed(0x1b), None);
+	assert_eq!(input.feed(b'['), None);
+	assert_eq!(
+		input.feed(b'D'),
+		Some(KeyEvent::Special(KeyboardKey::KeyCursorLeft))
+	);
+
+	for byte in [0x1b, b'[', b'3'] {
+		assert_eq!(input.feed(byte), None);
+	}
+	assert_eq!(
+		input.feed(b'~'),
+		Some(KeyEvent::Special(KeyboardKey::KeyDelete))
+	);
+
+	assert_eq!(input.feed(b'x'), Some(KeyEvent::Char('x')));
+}
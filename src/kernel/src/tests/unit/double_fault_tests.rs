@@ -0,0 +1,47 @@
+use crate::{
+	arch::x86::tss::TaskStateSegment,
+	memory::{get_kernel_text, get_stack_guard, VirtAddr},
+	println_serial,
+	tests::{exit_qemu, QSUCCES},
+};
+use core::{
+	hint::black_box,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+static EXPECTED: AtomicBool = AtomicBool::new(false);
+
+#[inline(never)]
+fn overflow(depth: usize) -> usize {
+	let frame = black_box([depth; 64]);
+	if depth == usize::MAX {
+		return 0;
+	}
+
+	return overflow(depth + 1) + frame[depth % 64];
+}
+
+/// Overflows the kernel stack into its guard page. Every push of the #PF
+/// faults too, so the CPU raises #DF and [`double_fault_reported`] ends the
+/// run.
+#[test_case]
+fn test_double_fault_on_stack_overflow() {
+	EXPECTED.store(true, Ordering::Relaxed);
+	black_box(overflow(0));
+
+	panic!("the stack overflow did not double fault");
+}
+
+/// Called by the double fault task after its report, with the state of the
+/// task that faulted.
+pub fn double_fault_reported(task: &TaskStateSegment, overflow: bool) {
+	assert!(EXPECTED.load(Ordering::Relaxed), "unexpected double fault");
+	assert!(overflow, "the report misses the stack overflow");
+	assert!(get_stack_guard().contains(&VirtAddr::new(task.esp as usize)));
+	assert!(get_kernel_text().contains(&VirtAddr::new(task.eip as usize)));
+	assert_eq!(task.cs & 0xffff, 0x08);
+	assert_eq!(task.ss & 0xffff, 0x10);
+
+	println_serial!("[ok]");
+	exit_qemu(QSUCCES);
+}
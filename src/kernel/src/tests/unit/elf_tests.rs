@@ -0,0 +1,198 @@
+use crate::{
+	arch::x86::usermode,
+	memory::{
+		frame::FRAME_ALLOCATOR, paging::phys_to_virt, VirtAddr, PAGE_SIZE,
+	},
+	task::{
+		elf::{self, ElfError, USER_END, USER_STACK_BOTTOM},
+		process, thread,
+	},
+};
+use alloc::vec::Vec;
+
+const BASE: u32 = 0x0800_0000;
+const CODE_OFFSET: usize = 84;
+const BSS_SIZE: u32 = 0x2000;
+
+/// `mov eax, 1; mov ebx, 42; int 0x80; jmp $`, the exit syscall.
+const CODE: [u8; 14] = [
+	0xb8, 0x01, 0x00, 0x00, 0x00, 0xbb, 0x2a, 0x00, 0x00, 0x00, 0xcd, 0x80,
+	0xeb, 0xfe,
+];
+
+// Helper building an executable with a single segment holding the headers
+// and `CODE`, followed by `BSS_SIZE` zeroed bytes
+fn executable() -> Vec<u8> {
+	let file_size = (CODE_OFFSET + CODE.len()) as u32;
+	let mut bytes = Vec::new();
+
+	// File header
+	bytes.extend_from_slice(&[0x7f, b'E', b'L', b'F', 1, 1, 1, 0]);
+	bytes.extend_from_slice(&[0; 8]);
+	bytes.extend_from_slice(&2u16.to_le_bytes()); // ET_EXEC
+	bytes.extend_from_slice(&3u16.to_le_bytes()); // EM_386
+	bytes.extend_from_slice(&1u32.to_le_bytes());
+	bytes.extend_from_slice(&(BASE + CODE_OFFSET as u32).to_le_bytes());
+	bytes.extend_from_slice(&52u32.to_le_bytes()); // e_phoff
+	bytes.extend_from_slice(&0u32.to_le_bytes());
+	bytes.extend_from_slice(&0u32.to_le_bytes());
+	bytes.extend_from_slice(&52u16.to_le_bytes());
+	bytes.extend_from_slice(&32u16.to_le_bytes()); // e_phentsize
+	bytes.extend_from_slice(&1u16.to_le_bytes()); // e_phnum
+	bytes.extend_from_slice(&[0; 6]);
+
+	// PT_LOAD, readable, writable and executable
+	for word in [1, 0, BASE, BASE, file_size, file_size + BSS_SIZE, 7, 0x1000] {
+		bytes.extend_from_slice(&u32::to_le_bytes(word));
+	}
+
+	bytes.extend_from_slice(&CODE);
+	return bytes;
+}
+
+// Helper writing the little endian `value` at `offset`
+fn set_u32(bytes: &mut [u8], offset: usize, value: u32) {
+	bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+fn free_frames() -> usize {
+	return FRAME_ALLOCATOR
+		.lock()
+		.map_or(0, |frames| frames.stats().free_frames);
+}
+
+#[test_case]
+fn test_parse_valid_executable() {
+	let executable = elf::parse(&executable()).unwrap();
+
+	assert_eq!(executable.entry, VirtAddr::new(0x0800_0054));
+	assert_eq!(executable.segments.len(), 1);
+	let segment = executable.segments[0];
+	assert_eq!(segment.vaddr, BASE as usize);
+	assert_eq!(segment.file_size, CODE_OFFSET + CODE.len());
+	assert!(segment.writable && segment.executable);
+}
+
+#[test_case]
+fn test_parse_rejects_bad_headers() {
+	let bytes = executable();
+	assert_eq!(elf::parse(&bytes[..40]), Err(ElfError::TooShort));
+
+	let mut bad = bytes.clone();
+	bad[0] = 0;
+	assert_eq!(elf::parse(&bad), Err(ElfError::BadMagic));
+
+	let mut bad = bytes.clone();
+	bad[4] = 2;
+	assert_eq!(elf::parse(&bad), Err(ElfError::Not32Bit));
+
+	let mut bad = bytes.clone();
+	bad[5] = 2;
+	assert_eq!(elf::parse(&bad), Err(ElfError::NotLittleEndian));
+
+	let mut bad = bytes.clone();
+	bad[16] = 1;
+	assert_eq!(elf::parse(&bad), Err(ElfError::NotExecutable));
+
+	let mut bad = bytes.clone();
+	bad[18] = 0x3e;
+	assert_eq!(elf::parse(&bad), Err(ElfError::WrongMachine));
+
+	let mut bad = bytes.clone();
+	bad[44] = 0xff;
+	assert_eq!(elf::parse(&bad), Err(ElfError::BadProgramHeaders));
+}
+
+#[test_case]
+fn test_parse_rejects_bad_segments() {
+	let bytes = executable();
+
+	let mut bad = bytes.clone();
+	set_u32(&mut bad, 52, 0);
+	assert_eq!(elf::parse(&bad), Err(ElfError::NoLoadSegments));
+
+	let mut bad = bytes.clone();
+	set_u32(&mut bad, 52 + 16, 0x10_0000);
+	assert_eq!(elf::parse(&bad), Err(ElfError::SegmentTooBig));
+
+	let mut bad = bytes.clone();
+	set_u32(&mut bad, 52 + 4, 0x1000);
+	assert_eq!(elf::parse(&bad), Err(ElfError::SegmentOutsideFile));
+
+	for vaddr in [0x1000, USER_STACK_BOTTOM as u32, 0xc010_0000] {
+		let mut bad = bytes.clone();
+		set_u32(&mut bad, 52 + 8, vaddr);
+		assert_eq!(
+			elf::parse(&bad),
+			Err(ElfError::BadSegmentAddress(VirtAddr::new(vaddr as usize)))
+		);
+	}
+
+	// Nearly all of user memory, with nothing of it in the file
+	let mut bad = bytes.clone();
+	set_u32(&mut bad, 52 + 8, 0x0040_0000);
+	set_u32(&mut bad, 52 + 20, (USER_STACK_BOTTOM - 0x0040_0000) as u32);
+	set_u32(&mut bad, 24, 0x0040_0000);
+	assert_eq!(elf::parse(&bad), Err(ElfError::ImageTooBig));
+
+	let mut bad = bytes.clone();
+	set_u32(&mut bad, 24, BASE + 0x10_0000);
+	assert_eq!(
+		elf::parse(&bad),
+		Err(ElfError::BadEntry(VirtAddr::new(0x0810_0000)))
+	);
+}
+
+#[test_case]
+fn test_load_maps_segments_and_stack() {
+	let before = free_frames();
+	let image = elf::load(&executable()).unwrap();
+
+	let entry = image.entry.as_usize();
+	let frame = image
+		.space
+		.translate(VirtAddr::new(entry & !(PAGE_SIZE - 1)))
+		.unwrap();
+	let code: &[u8] = unsafe {
+		core::slice::from_raw_parts(
+			phys_to_virt(frame).as_ptr::<u8>().add(entry % PAGE_SIZE),
+			CODE.len(),
+		)
+	};
+	assert_eq!(code, CODE);
+
+	let bss = VirtAddr::new(BASE as usize + PAGE_SIZE);
+	let bss_frame = image.space.translate(bss).unwrap();
+	let bss_word = unsafe { phys_to_virt(bss_frame).as_ptr::<u32>().read() };
+	assert_eq!(bss_word, 0);
+
+	assert_eq!(image.stack_top, VirtAddr::new(USER_END));
+	assert!(image
+		.space
+		.translate(VirtAddr::new(USER_END - PAGE_SIZE))
+		.is_some());
+
+	drop(image);
+	assert_eq!(free_frames(), before);
+}
+
+#[test_case]
+fn test_user_process_runs_and_is_torn_down() {
+	let mut before = 0;
+
+	// The first round may grow the caches the table and threads use
+	for round in 0..2 {
+		if round == 1 {
+			before = free_frames();
+		}
+
+		let image = elf::load(&executable()).unwrap();
+		let pid = process::create_user_process("exit42", image).unwrap();
+		let id = process::main_thread(pid).unwrap();
+
+		thread::join(id);
+		assert!(!usermode::is_running());
+		assert_eq!(process::main_thread(pid), None);
+	}
+	assert_eq!(free_frames(), before);
+}
@@ -0,0 +1,387 @@
+use crate::{
+	device::block::{self, check_block, BlockDevice},
+	error::KernelError,
+	fs::fat::{Fat, FatError, FatType, DIR_ENTRY_SIZE},
+	sync::Mutex,
+};
+use alloc::{boxed::Box, collections::BTreeMap, vec, vec::Vec};
+
+const SECTOR: usize = 512;
+const FAT16_CLUSTERS: u64 = 4200;
+const FAT32_CLUSTERS: u64 = 66000;
+const END_OF_CHAIN: u32 = 0x0fff_ffff;
+
+/// A disk that only stores the sectors written to it, the others read as
+/// zeros, so a FAT32 sized volume costs a few pages.
+struct SparseDisk {
+	blocks: u64,
+	sectors: Mutex<BTreeMap<u64, Vec<u8>>>,
+}
+
+impl BlockDevice for SparseDisk {
+	fn block_size(&self) -> usize {
+		return SECTOR;
+	}
+
+	fn num_blocks(&self) -> u64 {
+		return self.blocks;
+	}
+
+	fn read_block(&self, lba: u64, buf: &mut [u8]) -> Result<(), KernelError> {
+		check_block(self, lba, buf.len())?;
+		match self.sectors.lock().get(&lba) {
+			Some(sector) => buf[..SECTOR].copy_from_slice(sector),
+			None => buf[..SECTOR].fill(0),
+		}
+		return Ok(());
+	}
+
+	fn write_block(&self, lba: u64, buf: &[u8]) -> Result<(), KernelError> {
+		check_block(self, lba, buf.len())?;
+		self.sectors.lock().insert(lba, Vec::from(&buf[..SECTOR]));
+		return Ok(());
+	}
+}
+
+/// A FAT volume being put together in memory, one FAT.
+struct Image {
+	sectors: BTreeMap<u64, Vec<u8>>,
+	fat32: bool,
+	fat_start: u64,
+	root_start: u64,
+	data_start: u64,
+	total: u64,
+}
+
+impl Image {
+	// Helper laying out a volume of `clusters` one sector clusters starting
+	// at sector `base`, with an MBR in front if `base` is not 0
+	fn new(base: u64, clusters: u64, fat32: bool) -> Self {
+		let entry_size = if fat32 { 4 } else { 2 };
+		let fat_sectors = ((clusters + 2) * entry_size).div_ceil(SECTOR as u64);
+		let reserved = if fat32 { 32 } else { 1 };
+		let root_sectors = if fat32 { 0 } else { 32 };
+
+		let mut image = Image {
+			sectors: BTreeMap::new(),
+			fat32,
+			fat_start: base + reserved,
+			root_start: base + reserved + fat_sectors,
+			data_start: base + reserved + fat_sectors + root_sectors,
+			total: 0,
+		};
+		image.total = image.data_start + clusters;
+
+		let mut boot = vec![0; SECTOR];
+		boot[0] = 0xeb;
+		boot[11..13].copy_from_slice(&(SECTOR as u16).to_le_bytes());
+		boot[13] = 1;
+		boot[14..16].copy_from_slice(&(reserved as u16).to_le_bytes());
+		boot[16] = 1;
+		let volume = (image.total - base) as u32;
+		if fat32 {
+			boot[32..36].copy_from_slice(&volume.to_le_bytes());
+			boot[36..40].copy_from_slice(&(fat_sectors as u32).to_le_bytes());
+			boot[44..48].copy_from_slice(&2u32.to_le_bytes());
+		} else {
+			boot[17..19].copy_from_slice(&512u16.to_le_bytes());
+			boot[32..36].copy_from_slice(&volume.to_le_bytes());
+			boot[22..24].copy_from_slice(&(fat_sectors as u16).to_le_bytes());
+		}
+		boot[510..512].copy_from_slice(&0xaa55u16.to_le_bytes());
+		image.sectors.insert(base, boot);
+
+		if base != 0 {
+			let mut mbr = vec![0; SECTOR];
+			mbr[446 + 4] = 0x06;
+			mbr[446 + 8..446 + 12]
+				.copy_from_slice(&(base as u32).to_le_bytes());
+			mbr[510..512].copy_from_slice(&0xaa55u16.to_le_bytes());
+			image.sectors.insert(0, mbr);
+		}
+
+		return image;
+	}
+
+	// Helper returning sector `lba` to change, zeroed if it is new
+	fn sector(&mut self, lba: u64) -> &mut Vec<u8> {
+		return self.sectors.entry(lba).or_insert_with(|| vec![0; SECTOR]);
+	}
+
+	// Helper setting the FAT entry of `cluster`
+	fn set_fat(&mut self, cluster: u32, next: u32) {
+		let (offset, bytes) = match self.fat32 {
+			true => (cluster as usize * 4, next.to_le_bytes().to_vec()),
+			false => {
+				(cluster as usize * 2, (next as u16).to_le_bytes().to_vec())
+			}
+		};
+		let sector = self.fat_start + (offset / SECTOR) as u64;
+		let offset = offset % SECTOR;
+		self.sector(sector)[offset..offset + bytes.len()]
+			.copy_from_slice(&bytes);
+	}
+
+	// Helper chaining `clusters` in order, the last one ending the chain
+	fn chain(&mut self, clusters: &[u32]) {
+		for pair in clusters.windows(2) {
+			self.set_fat(pair[0], pair[1]);
+		}
+		if let Some(&last) = clusters.last() {
+			self.set_fat(last, END_OF_CHAIN);
+		}
+	}
+
+	// Helper writing a directory entry, `index` counted from the start of
+	// `sector`
+	fn entry(
+		&mut self,
+		sector: u64,
+		index: usize,
+		name: &[u8; 11],
+		attr: u8,
+		cluster: u32,
+		size: u32,
+	) {
+		let lba = sector + (index * DIR_ENTRY_SIZE / SECTOR) as u64;
+		let offset = index * DIR_ENTRY_SIZE % SECTOR;
+		let raw = &mut self.sector(lba)[offset..offset + DIR_ENTRY_SIZE];
+		raw[..11].copy_from_slice(name);
+		raw[11] = attr;
+		raw[20..22].copy_from_slice(&((cluster >> 16) as u16).to_le_bytes());
+		raw[26..28].copy_from_slice(&(cluster as u16).to_le_bytes());
+		raw[28..32].copy_from_slice(&size.to_le_bytes());
+	}
+
+	// Helper filling the clusters of a file with `data`
+	fn data(&mut self, clusters: &[u32], data: &[u8]) {
+		for (&cluster, chunk) in clusters.iter().zip(data.chunks(SECTOR)) {
+			let lba = self.data_start + cluster as u64 - 2;
+			self.sector(lba)[..chunk.len()].copy_from_slice(chunk);
+		}
+	}
+
+	// Helper returning the sector of `cluster`
+	fn cluster(&self, cluster: u32) -> u64 {
+		return self.data_start + cluster as u64 - 2;
+	}
+
+	// Helper registering the image as block device `name`
+	fn register(self, name: &str) -> Result<(), KernelError> {
+		return block::register(
+			name,
+			Box::new(SparseDisk {
+				blocks: self.total,
+				sectors: Mutex::new(self.sectors),
+			}),
+		);
+	}
+}
+
+// Helper returning `len` bytes of a pattern no two sectors share
+fn pattern(len: usize, seed: u8) -> Vec<u8> {
+	return (0..len)
+		.map(|index| {
+			(index as u8).wrapping_mul(31) ^ (index / SECTOR) as u8 ^ seed
+		})
+		.collect();
+}
+
+// Helper building the FAT16 volume most tests use, at sector `base`
+fn fat16_image(base: u64) -> Image {
+	let mut image = Image::new(base, FAT16_CLUSTERS, false);
+	let root = image.root_start;
+
+	image.entry(root, 0, b"TESTVOL    ", 0x08, 0, 0);
+	image.entry(root, 1, b"HELLO   TXT", 0x20, 2, 1300);
+	image.chain(&[2, 3, 4]);
+	image.data(&[2, 3, 4], &pattern(1300, 1));
+
+	// A long name entry and a deleted file, both skipped
+	image.entry(root, 2, b"Asub-dir   ", 0x0f, 0, 0);
+	image.entry(root, 3, b"\xe5OLD    TXT", 0x20, 2, 10);
+	image.entry(root, 4, b"SUB        ", 0x10, 5, 0);
+	image.chain(&[5]);
+	let sub = image.cluster(5);
+	image.entry(sub, 0, b".          ", 0x10, 5, 0);
+	image.entry(sub, 1, b"..         ", 0x10, 0, 0);
+	image.entry(sub, 2, b"DATA    BIN", 0x20, 6, 700);
+	image.chain(&[6, 7]);
+	image.data(&[6, 7], &pattern(700, 2));
+
+	image.entry(root, 5, b"EMPTY      ", 0x20, 0, 0);
+
+	// Corrupt chains
+	image.entry(root, 6, b"LOOPDIR    ", 0x10, 10, 0);
+	image.set_fat(10, 11);
+	image.set_fat(11, 10);
+	image.entry(root, 7, b"BAD     BIN", 0x20, 12, 1024);
+	image.set_fat(12, 0x9000);
+	image.entry(root, 8, b"SHORT   BIN", 0x20, 13, 2000);
+	image.chain(&[13]);
+	image.entry(root, 9, b"FREE    BIN", 0x20, 14, 1024);
+	image.set_fat(14, 0);
+
+	return image;
+}
+
+#[test_case]
+fn test_fat16_read_dir() {
+	fat16_image(0).register("fat16-dir").unwrap();
+	let fat = Fat::mount("fat16-dir").unwrap();
+	assert_eq!(fat.fat_type(), FatType::Fat16);
+	assert_eq!(fat.cluster_size(), SECTOR);
+
+	let root = fat.read_dir("/").unwrap();
+	let names: Vec<&str> =
+		root.iter().map(|entry| entry.name.as_str()).collect();
+	assert_eq!(
+		names,
+		[
+			"HELLO.TXT",
+			"SUB",
+			"EMPTY",
+			"LOOPDIR",
+			"BAD.BIN",
+			"SHORT.BIN",
+			"FREE.BIN"
+		]
+	);
+	assert_eq!(root[0].size, 1300);
+	assert!(root[1].is_dir);
+
+	let sub = fat.read_dir("sub").unwrap();
+	assert_eq!(sub.len(), 3);
+	assert_eq!(sub[2].name, "DATA.BIN");
+	assert_eq!(fat.read_dir("/SUB/..").unwrap(), root);
+
+	block::unregister("fat16-dir");
+}
+
+#[test_case]
+fn test_fat16_read_files() {
+	fat16_image(0).register("fat16-read").unwrap();
+	let fat = Fat::mount("fat16-read").unwrap();
+
+	let mut file = fat.open("/hello.txt").unwrap();
+	assert_eq!(file.size(), 1300);
+	assert_eq!(file.read_to_end().unwrap(), pattern(1300, 1));
+
+	// Small reads across the cluster boundaries
+	let mut file = fat.open("sub/Data.Bin").unwrap();
+	let mut data = Vec::new();
+	let mut chunk = [0; 100];
+	loop {
+		let len = file.read(&mut chunk).unwrap();
+		if len == 0 {
+			break;
+		}
+		data.extend_from_slice(&chunk[..len]);
+	}
+	assert_eq!(data, pattern(700, 2));
+
+	let mut empty = fat.open("EMPTY").unwrap();
+	assert_eq!(empty.read(&mut chunk), Ok(0));
+
+	block::unregister("fat16-read");
+}
+
+#[test_case]
+fn test_fat16_path_errors() {
+	fat16_image(0).register("fat16-paths").unwrap();
+	let fat = Fat::mount("fat16-paths").unwrap();
+
+	assert_eq!(fat.open("missing.txt").err(), Some(FatError::NotFound));
+	assert_eq!(fat.open("sub").err(), Some(FatError::IsADirectory));
+	assert_eq!(fat.open("/").err(), Some(FatError::IsADirectory));
+	assert_eq!(
+		fat.open("hello.txt/inside").err(),
+		Some(FatError::NotADirectory)
+	);
+	assert_eq!(fat.open("old.txt").err(), Some(FatError::NotFound));
+
+	block::unregister("fat16-paths");
+}
+
+#[test_case]
+fn test_fat16_corrupt_chains() {
+	fat16_image(0).register("fat16-corrupt").unwrap();
+	let fat = Fat::mount("fat16-corrupt").unwrap();
+
+	assert_eq!(fat.read_dir("loopdir"), Err(FatError::ChainLoop));
+	assert_eq!(
+		fat.open("bad.bin").unwrap().read_to_end(),
+		Err(FatError::BadCluster(0x9000))
+	);
+	assert_eq!(
+		fat.open("short.bin").unwrap().read_to_end(),
+		Err(FatError::ChainTooShort)
+	);
+	assert_eq!(
+		fat.open("free.bin").unwrap().read_to_end(),
+		Err(FatError::BadCluster(0))
+	);
+	assert_eq!(fat.next_cluster(1), Err(FatError::BadCluster(1)));
+
+	block::unregister("fat16-corrupt");
+}
+
+#[test_case]
+fn test_fat16_in_first_partition() {
+	fat16_image(2048).register("fat16-mbr").unwrap();
+	let fat = Fat::mount("fat16-mbr").unwrap();
+
+	let mut file = fat.open("hello.txt").unwrap();
+	assert_eq!(file.read_to_end().unwrap(), pattern(1300, 1));
+
+	block::unregister("fat16-mbr");
+}
+
+#[test_case]
+fn test_fat32_root_chain() {
+	let mut image = Image::new(0, FAT32_CLUSTERS, true);
+
+	// The root directory fills cluster 2 and goes on in cluster 9
+	image.chain(&[2, 9]);
+	let first = image.cluster(2);
+	for index in 0..SECTOR / DIR_ENTRY_SIZE {
+		let mut name = *b"FILE       ";
+		name[4] = b'A' + index as u8;
+		image.entry(first, index, &name, 0x20, 0, 0);
+	}
+	let second = image.cluster(9);
+	image.entry(second, 0, b"LAST    TXT", 0x20, 70_000 - 4, 600);
+	image.chain(&[70_000 - 4, 3]);
+	image.data(&[70_000 - 4, 3], &pattern(600, 3));
+	image.register("fat32").unwrap();
+
+	let fat = Fat::mount("fat32").unwrap();
+	assert_eq!(fat.fat_type(), FatType::Fat32);
+
+	let root = fat.read_dir("").unwrap();
+	assert_eq!(root.len(), SECTOR / DIR_ENTRY_SIZE + 1);
+	assert_eq!(root[0].name, "FILEA");
+	assert_eq!(root[16].name, "LAST.TXT");
+	assert_eq!(
+		fat.open("last.txt").unwrap().read_to_end().unwrap(),
+		pattern(600, 3)
+	);
+
+	block::unregister("fat32");
+}
+
+#[test_case]
+fn test_fat_mount_rejects() {
+	assert_eq!(Fat::mount("no-such-disk").err(), Some(FatError::NoDevice));
+
+	let mut image = Image::new(0, FAT16_CLUSTERS, false);
+	image.sector(0)[0] = 0;
+	image.register("fat-blank").unwrap();
+	assert_eq!(Fat::mount("fat-blank").err(), Some(FatError::NotFat));
+	block::unregister("fat-blank");
+
+	// Too few clusters for FAT16, a FAT12 volume
+	Image::new(0, 1000, false).register("fat12").unwrap();
+	assert_eq!(Fat::mount("fat12").err(), Some(FatError::Unsupported));
+	block::unregister("fat12");
+}
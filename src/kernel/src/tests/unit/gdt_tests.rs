@@ -1,8 +1,9 @@
-use crate::println;
-use core::{
-	arch::asm,
-	ptr::{read_volatile, write_volatile},
+use crate::arch::x86::{
+	gdt::{self, Gate, TSS_AVAILABLE},
+	idt::{DOUBLE_FAULT_VECTOR, IDT_ENTRIES},
+	tss::{self, TaskStateSegment, DOUBLE_FAULT_TSS_SELECTOR, TSS_SELECTOR},
 };
+use core::{arch::asm, mem::size_of, ptr};
 
 #[test_case]
 fn test_low_memory_access() {
@@ -36,3 +37,48 @@ fn test_safe_memory_region() {
 		assert_eq!(value, 0xaa);
 	}
 }
+
+#[test_case]
+fn test_tss_layout() {
+	assert_eq!(size_of::<TaskStateSegment>(), 104);
+	assert_eq!(TaskStateSegment::LIMIT, 103);
+
+	let mut gate = Gate::tss(0x1234_5678, TaskStateSegment::LIMIT);
+	assert_eq!(gate.base(), 0x1234_5678);
+	assert_eq!(gate.limit(), 103);
+	assert_eq!(gate.access(), TSS_AVAILABLE);
+	assert_eq!(gate.flags(), 0);
+	assert!(!gate.is_busy_tss());
+}
+
+#[test_case]
+fn test_read_descriptor_stops_at_the_limit() {
+	let (limit, _) = gdt::gdtr();
+
+	assert_eq!(limit as usize + 1, size_of::<gdt::GdtGates>());
+	assert_eq!(gdt::read_descriptor(0).map(|gate| gate.0), Some(0));
+	assert!(gdt::read_descriptor(7).is_none());
+}
+
+#[test_case]
+fn test_tss_loaded() {
+	assert_eq!(tss::task_register(), TSS_SELECTOR);
+
+	let mut kernel = gdt::read_descriptor(TSS_SELECTOR as usize >> 3).unwrap();
+	assert!(kernel.is_busy_tss());
+	assert_eq!(kernel.base(), tss::tss_address());
+
+	// Its task only runs on a double fault
+	let mut double_fault =
+		gdt::read_descriptor(DOUBLE_FAULT_TSS_SELECTOR as usize >> 3).unwrap();
+	assert_eq!(double_fault.access(), TSS_AVAILABLE);
+	assert_eq!(double_fault.base(), tss::double_fault_tss_address());
+}
+
+#[test_case]
+fn test_double_fault_uses_task_gate() {
+	let entry = unsafe { (*(&raw const IDT_ENTRIES))[DOUBLE_FAULT_VECTOR] };
+
+	assert!(entry.is_task_gate());
+	assert_eq!(entry.selector(), DOUBLE_FAULT_TSS_SELECTOR);
+}
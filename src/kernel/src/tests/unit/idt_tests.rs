@@ -0,0 +1,41 @@
+use crate::arch::x86::exceptions::{
+	default_handler, ExceptionContext, Registers, EXCEPTION_COUNT,
+};
+use core::{arch::asm, mem};
+
+#[test_case]
+fn test_default_handler_skips_exceptions() {
+	for vector in 0..EXCEPTION_COUNT as u8 {
+		assert!(default_handler(vector).is_none());
+	}
+	for vector in [32, 47, 128, 255] {
+		assert!(default_handler(vector).is_some());
+	}
+}
+
+#[test_case]
+fn test_exception_context_layout() {
+	// Must match what exception_common in isr.asm pushes
+	assert_eq!(mem::size_of::<Registers>(), 12 * 4);
+	assert_eq!(mem::offset_of!(ExceptionContext, vector), 12 * 4);
+	assert_eq!(mem::offset_of!(ExceptionContext, error_code), 13 * 4);
+	assert_eq!(mem::offset_of!(ExceptionContext, frame), 14 * 4);
+}
+
+#[test_case]
+fn test_breakpoint_keeps_registers() {
+	let (mut a, mut b): (u32, u32) = (0x1234_5678, 0x9abc_def0);
+
+	// The breakpoint handler returns through exception_common
+	unsafe { asm!("int3", inout("ecx") a, inout("edx") b) };
+	assert_eq!((a, b), (0x1234_5678, 0x9abc_def0));
+}
+
+#[test_case]
+fn test_stray_software_interrupt_returns() {
+	let mut value: u32 = 1;
+
+	// Nothing is installed on 0xf0, the default handler reports and returns
+	unsafe { asm!("int 0xf0", "add {0}, 1", inout(reg) value) };
+	assert_eq!(value, 2);
+}
@@ -0,0 +1,93 @@
+use crate::collections::intrusive_linked_list::{
+	IntrusiveLinkedList, IntrusiveNode,
+};
+use alloc::{boxed::Box, vec::Vec};
+use core::ptr::{self, NonNull};
+
+struct Item {
+	value: u32,
+	node: IntrusiveNode<Item>,
+}
+
+// Helper leaking an item that points back at itself and returning its node
+fn item(value: u32) -> NonNull<IntrusiveNode<Item>> {
+	let item = Box::leak(Box::new(Item {
+		value,
+		node: IntrusiveNode::default(),
+	}));
+	item.node = IntrusiveNode::new(Some(NonNull::from(&mut *item)));
+
+	return NonNull::from(&mut item.node);
+}
+
+// Helper listing the values front to back
+fn values(list: &IntrusiveLinkedList<Item>) -> Vec<u32> {
+	return list.containers().map(|item| item.value).collect();
+}
+
+#[test_case]
+fn test_intrusive_iter_and_len() {
+	let mut list = IntrusiveLinkedList::new();
+	assert_eq!(list.iter().count(), 0);
+	assert_eq!(list.debug_validate(), Ok(()));
+
+	let nodes = [item(1), item(2), item(3)];
+	list.push_back(Some(nodes[1]));
+	list.push_back(Some(nodes[2]));
+	list.push_front(Some(nodes[0]));
+
+	assert_eq!(list.len(), 3);
+	assert_eq!(values(&list), [1, 2, 3]);
+	assert!(list.iter().zip(nodes).all(|(a, b)| ptr::eq(a, b.as_ptr())));
+	assert_eq!((&list).into_iter().count(), 3);
+	assert_eq!(list.debug_validate(), Ok(()));
+}
+
+#[test_case]
+fn test_intrusive_contains_and_is_linked() {
+	let mut list = IntrusiveLinkedList::new();
+	let mut other = IntrusiveLinkedList::new();
+	let (a, b) = (item(1), item(2));
+
+	list.push_back(Some(a));
+	other.push_back(Some(b));
+	assert!(list.contains(a));
+	assert!(!list.contains(b));
+	assert!(unsafe { a.as_ref() }.is_linked());
+
+	list.remove(Some(a));
+	assert!(!list.contains(a));
+	assert!(!unsafe { a.as_ref() }.is_linked());
+	assert!(list.is_empty());
+
+	// Popped nodes are unlinked too and can go on another list
+	let popped = other.pop_front();
+	assert_eq!(popped, Some(b));
+	assert!(!unsafe { b.as_ref() }.is_linked());
+	list.push_front(popped);
+	assert!(list.contains(b));
+	assert_eq!(list.debug_validate(), Ok(()));
+	assert_eq!(other.debug_validate(), Ok(()));
+}
+
+#[test_case]
+fn test_intrusive_validate_after_removals() {
+	let mut list = IntrusiveLinkedList::new();
+	let nodes = [item(1), item(2), item(3), item(4), item(5)];
+	for node in nodes {
+		list.push_back(Some(node));
+	}
+
+	// Middle, head and tail
+	list.remove(Some(nodes[2]));
+	assert_eq!(list.debug_validate(), Ok(()));
+	list.remove(Some(nodes[0]));
+	assert_eq!(list.pop_back(), Some(nodes[4]));
+	assert_eq!(list.debug_validate(), Ok(()));
+	assert_eq!(values(&list), [2, 4]);
+
+	list.remove(Some(nodes[1]));
+	list.remove(Some(nodes[3]));
+	assert_eq!(list.len(), 0);
+	assert_eq!(list.debug_validate(), Ok(()));
+}
@@ -0,0 +1,53 @@
+use crate::tty::{
+	klog::{KernelLog, MAX_RECORD_TEXT},
+	log::LogLevel,
+};
+
+#[test_case]
+fn test_klog_read_in_order() {
+	let mut log: KernelLog<128> = KernelLog::new();
+	let mut text = [0; MAX_RECORD_TEXT];
+
+	log.push(5, LogLevel::Info, b"first");
+	log.push(1250, LogLevel::Warn, b"second");
+
+	let record = log.read(0, &mut text);
+	assert_eq!(record.map(|r| (r.seq, r.level)), Some((0, LogLevel::Info)));
+	assert_eq!(&text[..5], b"first");
+
+	let record = log.read(1, &mut text);
+	assert_eq!(record.map(|r| (r.seq, r.level)), Some((1, LogLevel::Warn)));
+	assert_eq!(record.map(|r| r.timestamp_ms), Some(1250));
+	assert_eq!(&text[..6], b"second");
+
+	assert!(log.read(2, &mut text).is_none());
+}
+
+#[test_case]
+fn test_klog_drops_oldest() {
+	let mut log: KernelLog<96> = KernelLog::new();
+	let mut text = [0; MAX_RECORD_TEXT];
+
+	// Each record takes 18 header bytes plus 20 text bytes.
+	for _ in 0..3 {
+		log.push(0, LogLevel::Debug, &[b'x'; 20]);
+	}
+
+	assert_eq!(log.dropped(), 1);
+	assert_eq!(log.read(0, &mut text).map(|r| r.seq), Some(1));
+	assert_eq!(log.read(2, &mut text).map(|r| r.len), Some(20));
+}
+
+#[test_case]
+fn test_klog_truncates_and_clears() {
+	let mut log: KernelLog<512> = KernelLog::new();
+	let mut text = [0; MAX_RECORD_TEXT];
+
+	log.write(0, LogLevel::Error, format_args!("{:>300}", "end"));
+	let record = log.read(0, &mut text);
+	assert_eq!(record.map(|r| r.len), Some(MAX_RECORD_TEXT));
+
+	log.clear();
+	assert!(log.read(0, &mut text).is_none());
+	assert_eq!(log.next_seq(), 1);
+}
@@ -193,6 +193,322 @@ fn test_push_pop_alternating() {
 	assert_eq!(*list.back().unwrap(), 4);
 }
 
+#[test_case]
+#[allow(clippy::unwrap_used)]
+fn test_cursor_insert_and_remove() {
+	let mut list = create_test_list();
+
+	{
+		let mut cursor = list.cursor_front_mut();
+		cursor.insert_before(0);
+		assert_eq!(*cursor.current().unwrap(), 1);
+		assert_eq!(cursor.index(), Some(1));
+
+		cursor.insert_after(5);
+		assert_eq!(*cursor.peek_next().unwrap(), 5);
+
+		cursor.move_next();
+		cursor.move_next();
+		assert_eq!(cursor.remove_current(), Some(2));
+		assert_eq!(*cursor.current().unwrap(), 3);
+
+		// Past the tail, before the ghost is the back, after it the front
+		cursor.move_next();
+		cursor.insert_before(4);
+		cursor.insert_after(-1);
+	}
+
+	let values: Vec<i32> = core::iter::from_fn(|| list.pop_front()).collect();
+	assert_eq!(values, [-1, 0, 1, 5, 3, 4]);
+}
+
+#[test_case]
+fn test_iter_both_ends() {
+	let list = create_test_list();
+
+	let forward: Vec<i32> = list.iter().copied().collect();
+	let backward: Vec<i32> = list.iter().rev().copied().collect();
+	assert_eq!(forward, [1, 2, 3]);
+	assert_eq!(backward, [3, 2, 1]);
+	assert_eq!(list.iter().len(), 3);
+
+	// The two ends stop once they meet
+	let mut iter = list.iter();
+	assert_eq!(iter.next(), Some(&1));
+	assert_eq!(iter.next_back(), Some(&3));
+	assert_eq!(iter.len(), 1);
+	assert_eq!(iter.next_back(), Some(&2));
+	assert_eq!(iter.next(), None);
+	assert_eq!(iter.next_back(), None);
+
+	let mut sum = 0;
+	for value in &list {
+		sum += value;
+	}
+	assert_eq!(sum, 6);
+}
+
+#[test_case]
+fn test_iter_mut() {
+	let mut list = create_test_list();
+
+	for value in list.iter_mut() {
+		*value *= 10;
+	}
+	if let Some(back) = list.iter_mut().next_back() {
+		*back += 1;
+	}
+	for value in &mut list {
+		*value += 1;
+	}
+
+	let values: Vec<i32> = list.iter().copied().collect();
+	assert_eq!(values, [11, 21, 32]);
+}
+
+#[test_case]
+fn test_into_iter() {
+	let values: Vec<i32> = create_test_list().into_iter().collect();
+	assert_eq!(values, [1, 2, 3]);
+
+	let mut iter = create_test_list().into_iter();
+	assert_eq!(iter.next_back(), Some(3));
+	assert_eq!(iter.next(), Some(1));
+	assert_eq!(iter.len(), 1);
+	assert_eq!(iter.next(), Some(2));
+	assert_eq!(iter.next(), None);
+}
+
+#[test_case]
+fn test_iter_empty_and_cleared() {
+	let mut list: LinkedList<i32> = LinkedList::default();
+	assert_eq!(list.iter().next(), None);
+	assert_eq!(list.iter_mut().next_back(), None);
+
+	list.push_back(1);
+	list.push_front(0);
+	list.clear();
+	assert_eq!(list.iter().count(), 0);
+	assert_eq!(list.iter().next_back(), None);
+	assert_eq!(list.into_iter().next(), None);
+}
+
+// Helper inserting `value` before the first larger element, keeping the list
+// sorted
+fn insert_sorted(list: &mut LinkedList<i32>, value: i32) {
+	let mut cursor = list.cursor_front_mut();
+	while let Some(&mut current) = cursor.current() {
+		if current > value {
+			break;
+		}
+		cursor.move_next();
+	}
+
+	// On the ghost this appends
+	cursor.insert_before(value);
+}
+
+#[test_case]
+fn test_cursor_sorted_insertion() {
+	let mut list = LinkedList::default();
+	for value in [5, 1, 4, 9, 1, -3, 7, 0] {
+		insert_sorted(&mut list, value);
+	}
+
+	assert_eq!(list.len(), 8);
+	assert_eq!(list.front(), Some(&-3));
+	assert_eq!(list.back(), Some(&9));
+	let values: Vec<i32> = list.iter().copied().collect();
+	assert_eq!(values, [-3, 0, 1, 1, 4, 5, 7, 9]);
+	let backward: Vec<i32> = list.iter().rev().copied().collect();
+	assert_eq!(backward, [9, 7, 5, 4, 1, 1, 0, -3]);
+}
+
+#[test_case]
+fn test_cursor_move_prev_and_peek() {
+	let list = create_test_list();
+
+	let mut cursor = list.cursor_front();
+	assert_eq!(cursor.peek_prev(), None);
+	assert_eq!(cursor.peek_next(), Some(&2));
+
+	// Backward from the head is the ghost, then the tail
+	cursor.move_prev();
+	assert_eq!(cursor.current(), None);
+	assert_eq!(cursor.index(), None);
+	assert_eq!(cursor.peek_prev(), Some(&3));
+	assert_eq!(cursor.peek_next(), Some(&1));
+	cursor.move_prev();
+	assert_eq!(cursor.current(), Some(&3));
+	assert_eq!(cursor.index(), Some(2));
+	cursor.move_prev();
+	assert_eq!(cursor.current(), Some(&2));
+	assert_eq!(cursor.index(), Some(1));
+	assert_eq!(cursor.peek_prev(), Some(&1));
+	assert_eq!(cursor.peek_next(), Some(&3));
+}
+
+#[test_case]
+#[allow(clippy::unwrap_used)]
+fn test_cursor_mut_move_prev_and_insert() {
+	let mut list = create_test_list();
+
+	{
+		let mut cursor = list.cursor_front_mut();
+		cursor.move_prev();
+		cursor.move_prev();
+		assert_eq!(*cursor.current().unwrap(), 3);
+		*cursor.peek_prev().unwrap() = 20;
+
+		cursor.insert_after(4);
+		cursor.insert_before(25);
+		assert_eq!(cursor.index(), Some(3));
+		assert_eq!(*cursor.peek_prev().unwrap(), 25);
+
+		cursor.move_prev();
+		cursor.move_prev();
+		cursor.move_prev();
+		cursor.move_prev();
+		assert_eq!(cursor.current(), None);
+		cursor.insert_after(0);
+	}
+
+	assert_eq!(list.len(), 6);
+	assert_eq!(list.back(), Some(&4));
+	let values: Vec<i32> = list.iter().copied().collect();
+	assert_eq!(values, [0, 1, 20, 25, 3, 4]);
+}
+
+// Helper collecting the elements front to back
+fn values(list: &LinkedList<i32>) -> Vec<i32> {
+	return list.iter().copied().collect();
+}
+
+#[test_case]
+fn test_append() {
+	let mut list = create_test_list();
+	let mut other = LinkedList::default();
+	other.push_back(4);
+	other.push_back(5);
+
+	list.append(&mut other);
+	assert_eq!(values(&list), [1, 2, 3, 4, 5]);
+	assert_eq!((list.len(), list.back()), (5, Some(&5)));
+	assert!(other.is_empty());
+	assert_eq!(other.front(), None);
+
+	// Empty on either side
+	list.append(&mut other);
+	assert_eq!(list.len(), 5);
+	let mut empty = LinkedList::default();
+	empty.append(&mut list);
+	assert_eq!(values(&empty), [1, 2, 3, 4, 5]);
+	assert!(list.is_empty());
+
+	// Both halves still link up in both directions
+	let backward: Vec<i32> = empty.iter().rev().copied().collect();
+	assert_eq!(backward, [5, 4, 3, 2, 1]);
+}
+
+#[test_case]
+fn test_split_off_at_the_ends() {
+	let mut list = create_test_list();
+	let all = list.split_off(0);
+	assert!(list.is_empty());
+	assert_eq!((list.front(), list.back()), (None, None));
+	assert_eq!(values(&all), [1, 2, 3]);
+
+	let mut list = all;
+	let none = list.split_off(3);
+	assert!(none.is_empty());
+	assert_eq!(values(&list), [1, 2, 3]);
+	assert_eq!(list.len(), 3);
+}
+
+#[test_case]
+fn test_split_off_in_the_middle() {
+	let mut list: LinkedList<i32> = LinkedList::default();
+	for value in 0..7 {
+		list.push_back(value);
+	}
+
+	// Both halves of the index, walked from either end
+	let mut tail = list.split_off(5);
+	assert_eq!(values(&list), [0, 1, 2, 3, 4]);
+	assert_eq!(values(&tail), [5, 6]);
+	let mut middle = list.split_off(1);
+	assert_eq!(values(&list), [0]);
+	assert_eq!(values(&middle), [1, 2, 3, 4]);
+	assert_eq!((middle.len(), middle.back()), (4, Some(&4)));
+	assert_eq!(middle.iter().next_back(), Some(&4));
+
+	// Joining again restores the order
+	tail.push_back(7);
+	list.append(&mut middle);
+	list.append(&mut tail);
+	assert_eq!(values(&list), [0, 1, 2, 3, 4, 5, 6, 7]);
+	assert_eq!(list.len(), 8);
+}
+
+#[test_case]
+fn test_contains() {
+	let mut list = create_test_list();
+	assert!(list.contains(&1));
+	assert!(list.contains(&3));
+	assert!(!list.contains(&4));
+
+	list.clear();
+	assert!(!list.contains(&1));
+}
+
+#[test_case]
+fn test_remove_first() {
+	let mut list = create_test_list();
+	list.push_back(2);
+	list.push_back(5);
+
+	// Middle, only the first of two equal elements
+	assert!(list.remove_first(&2));
+	assert_eq!(values(&list), [1, 3, 2, 5]);
+
+	// Head and tail
+	assert!(list.remove_first(&1));
+	assert!(list.remove_first(&5));
+	assert_eq!(values(&list), [3, 2]);
+	assert_eq!((list.front(), list.back()), (Some(&3), Some(&2)));
+
+	// Absent
+	assert!(!list.remove_first(&7));
+	assert_eq!(list.len(), 2);
+
+	assert!(list.remove_first(&3));
+	assert!(list.remove_first(&2));
+	assert!(list.is_empty());
+	assert_eq!((list.front(), list.back()), (None, None));
+	assert!(!list.remove_first(&2));
+}
+
+#[test_case]
+fn test_retain() {
+	let mut list: LinkedList<i32> = LinkedList::default();
+	for value in 0..10 {
+		list.push_back(value);
+	}
+
+	// Drops the head, the tail and every other one between
+	list.retain(|&value| value % 2 == 1);
+	assert_eq!(values(&list), [1, 3, 5, 7, 9]);
+	assert_eq!(list.len(), 5);
+	let backward: Vec<i32> = list.iter().rev().copied().collect();
+	assert_eq!(backward, [9, 7, 5, 3, 1]);
+
+	list.retain(|_| true);
+	assert_eq!(list.len(), 5);
+	list.retain(|_| false);
+	assert!(list.is_empty());
+	assert_eq!((list.front(), list.back()), (None, None));
+}
+
 /* #[test_case]
 fn test_memory_management() {
 	// This test uses a custom Drop-tracking type to ensure memory is
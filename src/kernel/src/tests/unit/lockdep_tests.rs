@@ -0,0 +1,72 @@
+use crate::sync::{
+	lockdep::{check, LockdepError},
+	Mutex,
+};
+
+#[test_case]
+fn test_lockdep_detects_abba() {
+	static A: Mutex<u32> = Mutex::named("lockdep-test-a", 0);
+	static B: Mutex<u32> = Mutex::named("lockdep-test-b", 0);
+
+	// A then B records the order
+	let a = A.lock();
+	assert_eq!(check(&B), Ok(()));
+	let b = B.lock();
+	drop(b);
+	drop(a);
+
+	// B then A is the opposite one, even though it would not hang now
+	let b = B.lock();
+	assert_eq!(
+		check(&A),
+		Err(LockdepError::Inversion {
+			held: "lockdep-test-b",
+			acquiring: "lockdep-test-a",
+		})
+	);
+	drop(b);
+
+	// Alone, either is fine
+	assert_eq!(check(&A), Ok(()));
+	assert_eq!(check(&B), Ok(()));
+}
+
+#[test_case]
+fn test_lockdep_follows_longer_cycles() {
+	static A: Mutex<u32> = Mutex::named("lockdep-test-chain-a", 0);
+	static B: Mutex<u32> = Mutex::named("lockdep-test-chain-b", 0);
+	static C: Mutex<u32> = Mutex::named("lockdep-test-chain-c", 0);
+
+	// A then B, then B then C, so C must never be held when taking A
+	drop((A.lock(), B.lock()));
+	drop((B.lock(), C.lock()));
+
+	let c = C.lock();
+	assert!(matches!(check(&A), Err(LockdepError::Inversion { .. })));
+	drop(c);
+}
+
+#[test_case]
+fn test_lockdep_recursion_and_try_lock() {
+	static A: Mutex<u32> = Mutex::named("lockdep-test-recursive", 0);
+	static B: Mutex<u32> = Mutex::named("lockdep-test-try", 0);
+	let unnamed = Mutex::new(0);
+
+	let a = A.lock();
+	assert_eq!(
+		check(&A),
+		Err(LockdepError::Recursive("lockdep-test-recursive"))
+	);
+	assert_eq!(check(&unnamed), Ok(()));
+
+	// A lock `try_lock` took is held, but its own order is not recorded
+	let b = B.try_lock();
+	assert!(b.is_some());
+	assert_eq!(check(&B), Err(LockdepError::Recursive("lockdep-test-try")));
+	drop(b);
+	drop(a);
+
+	let b = B.lock();
+	assert_eq!(check(&A), Ok(()));
+	drop(b);
+}
@@ -0,0 +1,170 @@
+use crate::libc::mem::{memcmp, memcpy, memmove, memset};
+use core::mem::size_of;
+
+/// Lengths tried, 0..MAX_LEN.
+const MAX_LEN: usize = 64;
+
+/// Offsets tried at each end, enough to cover every offset in a word twice.
+const OFFSETS: usize = 2 * size_of::<usize>();
+
+const BUF_LEN: usize = MAX_LEN + 2 * OFFSETS;
+
+/// What the byte at `index` of a source buffer holds.
+const fn pattern(index: usize) -> u8 {
+	return (index as u8).wrapping_mul(7).wrapping_add(1);
+}
+
+const GUARD: u8 = 0xee;
+
+/// A buffer aligned to a word, so the offsets are offsets in a word.
+#[repr(align(8))]
+struct Buffer([u8; BUF_LEN]);
+
+impl Buffer {
+	// Helper returning a buffer holding [`pattern`]
+	fn pattern() -> Self {
+		let mut buffer = Buffer([0; BUF_LEN]);
+		for (index, byte) in buffer.0.iter_mut().enumerate() {
+			*byte = pattern(index);
+		}
+		return buffer;
+	}
+}
+
+// The checks below compare byte by byte rather than slices, a slice
+// comparison could call the very functions under test.
+
+#[test_case]
+fn test_memcpy_lengths_and_offsets() {
+	let src = Buffer::pattern();
+
+	for len in 0..MAX_LEN {
+		for src_offset in 0..OFFSETS {
+			for dest_offset in 0..OFFSETS {
+				let mut dest = Buffer([GUARD; BUF_LEN]);
+				let returned = unsafe {
+					memcpy(
+						dest.0.as_mut_ptr().add(dest_offset),
+						src.0.as_ptr().add(src_offset),
+						len,
+					)
+				};
+				assert_eq!(
+					returned,
+					dest.0.as_mut_ptr().wrapping_add(dest_offset)
+				);
+
+				for (index, &byte) in dest.0.iter().enumerate() {
+					let expected = match index.checked_sub(dest_offset) {
+						Some(at) if at < len => pattern(src_offset + at),
+						_ => GUARD,
+					};
+					assert_eq!(byte, expected, "len {} at {}", len, index);
+				}
+			}
+		}
+	}
+}
+
+#[test_case]
+fn test_memmove_overlapping() {
+	for len in 0..MAX_LEN {
+		for src_offset in 0..OFFSETS {
+			for dest_offset in 0..OFFSETS {
+				let mut buffer = Buffer::pattern();
+				let base = buffer.0.as_mut_ptr();
+				unsafe {
+					memmove(base.add(dest_offset), base.add(src_offset), len)
+				};
+
+				for (index, &byte) in buffer.0.iter().enumerate() {
+					let expected = match index.checked_sub(dest_offset) {
+						Some(at) if at < len => pattern(src_offset + at),
+						_ => pattern(index),
+					};
+					assert_eq!(
+						byte, expected,
+						"len {} from {} to {} at {}",
+						len, src_offset, dest_offset, index
+					);
+				}
+			}
+		}
+	}
+}
+
+#[test_case]
+fn test_memmove_far_apart() {
+	let mut buffer = Buffer::pattern();
+	let base = buffer.0.as_mut_ptr();
+
+	// The source is above the destination and clear of it
+	unsafe { memmove(base, base.add(OFFSETS + MAX_LEN / 2), MAX_LEN / 2) };
+	for index in 0..MAX_LEN / 2 {
+		assert_eq!(buffer.0[index], pattern(OFFSETS + MAX_LEN / 2 + index));
+	}
+}
+
+#[test_case]
+fn test_memset_lengths_and_offsets() {
+	for len in 0..MAX_LEN {
+		for offset in 0..OFFSETS {
+			let mut buffer = Buffer([GUARD; BUF_LEN]);
+			// Only the low byte counts
+			let returned = unsafe {
+				memset(buffer.0.as_mut_ptr().add(offset), 0x1a5, len)
+			};
+			assert_eq!(returned, buffer.0.as_mut_ptr().wrapping_add(offset));
+
+			for (index, &byte) in buffer.0.iter().enumerate() {
+				let expected = match (offset..offset + len).contains(&index) {
+					true => 0xa5,
+					false => GUARD,
+				};
+				assert_eq!(byte, expected, "len {} at {}", len, index);
+			}
+		}
+	}
+}
+
+#[test_case]
+fn test_memcmp_lengths_and_offsets() {
+	let a = Buffer::pattern();
+
+	for len in 0..MAX_LEN {
+		for offset_a in 0..OFFSETS {
+			for offset_b in 0..OFFSETS {
+				let mut b = Buffer([0; BUF_LEN]);
+				for at in 0..BUF_LEN - offset_b {
+					b.0[offset_b + at] = pattern((offset_a + at) % BUF_LEN);
+				}
+				let s1 = a.0.as_ptr().wrapping_add(offset_a);
+				let s2 = b.0.as_ptr().wrapping_add(offset_b);
+				assert_eq!(unsafe { memcmp(s1, s2, len) }, 0);
+
+				// One byte off at every position, the first one decides
+				for at in 0..len {
+					let original = b.0[offset_b + at];
+					b.0[offset_b + at] = original.wrapping_add(1);
+					let s2 = b.0.as_ptr().wrapping_add(offset_b);
+					let expected =
+						original as i32 - original.wrapping_add(1) as i32;
+					assert_eq!(unsafe { memcmp(s1, s2, len) }, expected);
+					assert_eq!(unsafe { memcmp(s2, s1, len) }, -expected);
+					// Past the compared bytes nothing counts
+					assert_eq!(unsafe { memcmp(s1, s2, at) }, 0);
+					b.0[offset_b + at] = original;
+				}
+			}
+		}
+	}
+}
+
+#[test_case]
+fn test_memcmp_is_unsigned() {
+	let a = [0x80u8, 0];
+	let b = [0x7fu8, 0];
+
+	assert!(unsafe { memcmp(a.as_ptr(), b.as_ptr(), 2) } > 0);
+	assert!(unsafe { memcmp(b.as_ptr(), a.as_ptr(), 2) } < 0);
+}
@@ -0,0 +1,191 @@
+use crate::memory::{memblock::MemRegion, MemBlockAllocator, PhysAddr};
+
+// Helper returning the available regions as (base, size) pairs
+fn regions(memblock: &MemBlockAllocator) -> alloc::vec::Vec<(usize, usize)> {
+	return memblock.mem_region()[..memblock.mem_count()]
+		.iter()
+		.map(|region| (region.base().as_usize(), region.size()))
+		.collect();
+}
+
+#[test_case]
+fn test_memblock_add_sorts_regions() {
+	let mut memblock = MemBlockAllocator::new();
+
+	assert!(memblock.add(PhysAddr::new(0x30_0000), 0x1000));
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x1000));
+	assert!(memblock.add(PhysAddr::new(0x20_0000), 0x1000));
+
+	assert_eq!(
+		regions(&memblock),
+		[
+			(0x10_0000, 0x1000),
+			(0x20_0000, 0x1000),
+			(0x30_0000, 0x1000)
+		]
+	);
+}
+
+#[test_case]
+fn test_memblock_add_merges_adjacent() {
+	let mut memblock = MemBlockAllocator::new();
+
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x1000));
+	assert!(memblock.add(PhysAddr::new(0x10_2000), 0x1000));
+	assert!(memblock.add(PhysAddr::new(0x10_1000), 0x1000));
+
+	assert_eq!(regions(&memblock), [(0x10_0000, 0x3000)]);
+}
+
+#[test_case]
+fn test_memblock_add_merges_overlapping() {
+	let mut memblock = MemBlockAllocator::new();
+
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x2000));
+	assert!(memblock.add(PhysAddr::new(0x10_4000), 0x2000));
+	assert!(memblock.add(PhysAddr::new(0x20_0000), 0x1000));
+	assert!(memblock.add(PhysAddr::new(0x10_1000), 0x4000));
+
+	assert_eq!(
+		regions(&memblock),
+		[(0x10_0000, 0x6000), (0x20_0000, 0x1000)]
+	);
+	assert_eq!(memblock.mem_count(), 2);
+}
+
+#[test_case]
+fn test_memblock_full_array() {
+	let mut memblock = MemBlockAllocator::new();
+
+	let mut base = 0x10_0000;
+	while memblock.add(PhysAddr::new(base), 0x1000) {
+		base += 0x2000;
+	}
+	let count = memblock.mem_count();
+
+	// Regions that merge still fit
+	assert!(memblock.add(PhysAddr::new(0x10_1000), 0x1000));
+	assert_eq!(memblock.mem_count(), count - 1);
+}
+
+#[test_case]
+fn test_memblock_allocations_coalesce() {
+	let mut memblock = MemBlockAllocator::new();
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x10_0000));
+
+	for _ in 0..20 {
+		assert!(memblock.find_free_region(0x1000, 0x1000).is_some());
+	}
+
+	assert_eq!(regions(&memblock), [(0x11_4000, 0xe_c000)]);
+	assert_eq!(memblock.reserved_count(), 1);
+	assert_eq!(memblock.reserved_region()[0].base().as_usize(), 0x10_0000);
+	assert_eq!(memblock.reserved_region()[0].size(), 0x1_4000);
+}
+
+#[test_case]
+fn test_memblock_alignment_gap_is_kept() {
+	let mut memblock = MemBlockAllocator::new();
+	assert!(memblock.add(PhysAddr::new(0x10_1000), 0x1f_f000));
+
+	let addr = memblock.find_free_region(0x1000, 0x10_0000).unwrap();
+
+	assert_eq!(addr.as_usize(), 0x20_0000);
+	assert_eq!(
+		regions(&memblock),
+		[(0x10_1000, 0xf_f000), (0x20_1000, 0xf_f000)]
+	);
+}
+
+#[test_case]
+fn test_memblock_reserve_splits_region() {
+	let mut memblock = MemBlockAllocator::new();
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x10_0000));
+
+	assert!(memblock.reserve(PhysAddr::new(0x14_0800), 0x1000));
+
+	assert_eq!(
+		regions(&memblock),
+		[(0x10_0000, 0x4_0000), (0x14_2000, 0xb_e000)]
+	);
+	assert_eq!(memblock.reserved_count(), 1);
+	assert_eq!(memblock.reserved_region()[0].base().as_usize(), 0x14_0000);
+	assert_eq!(memblock.reserved_region()[0].size(), 0x2000);
+}
+
+#[test_case]
+fn test_memblock_reserve_spans_regions() {
+	let mut memblock = MemBlockAllocator::new();
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x1_0000));
+	assert!(memblock.add(PhysAddr::new(0x12_0000), 0x1_0000));
+	assert!(memblock.add(PhysAddr::new(0x14_0000), 0x1_0000));
+
+	assert!(memblock.reserve(PhysAddr::new(0x10_8000), 0x3_c000));
+
+	assert_eq!(
+		regions(&memblock),
+		[(0x10_0000, 0x8000), (0x14_4000, 0xc000)]
+	);
+}
+
+#[test_case]
+fn test_memblock_allocations_avoid_reserved() {
+	let mut memblock = MemBlockAllocator::new();
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x2_0000));
+	assert!(memblock.add(PhysAddr::new(0x20_0000), 0x2_0000));
+
+	let reserved = [(0x10_4000, 0x3000), (0x11_f000, 0x2_2000)];
+	for (base, size) in reserved {
+		assert!(memblock.reserve(PhysAddr::new(base), size));
+	}
+
+	let mut allocations = 0;
+	while let Some(addr) = memblock.find_free_region(0x2000, 0x1000) {
+		let start = addr.as_usize();
+		for (base, size) in reserved {
+			assert!(start + 0x2000 <= base || start >= base + size);
+		}
+		allocations += 1;
+	}
+
+	// 0x4000 before and 0x1_8000 between the reserved ranges, 0x2_0000 after
+	assert_eq!(allocations, 2 + 12 + 16);
+}
+
+#[test_case]
+fn test_memblock_small_allocations_share_a_page() {
+	let mut memblock = MemBlockAllocator::new();
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x1_0000));
+
+	let first = memblock.alloc_small(64, 8).unwrap();
+	let second = memblock.alloc_small(10, 1).unwrap();
+	let third = memblock.alloc_small(64, 64).unwrap();
+
+	assert_eq!(first.as_usize(), 0x10_0000);
+	assert_eq!(second.as_usize(), 0x10_0040);
+	assert_eq!(third.as_usize(), 0x10_0080);
+	assert_eq!(memblock.reserved_count(), 1);
+	assert_eq!(memblock.reserved_region()[0].size(), 0x1000);
+	assert_eq!(regions(&memblock), [(0x10_1000, 0xf000)]);
+}
+
+#[test_case]
+fn test_memblock_small_allocation_takes_new_page() {
+	let mut memblock = MemBlockAllocator::new();
+	assert!(memblock.add(PhysAddr::new(0x10_0000), 0x1_0000));
+
+	let first = memblock.alloc_small(3000, 8).unwrap();
+	let second = memblock.alloc_small(3000, 8).unwrap();
+	let big = unsafe {
+		memblock.alloc(core::alloc::Layout::from_size_align(0x2000, 8).unwrap())
+	};
+
+	assert_eq!(first.as_usize(), 0x10_0000);
+	assert_eq!(second.as_usize(), 0x10_1000);
+	assert_eq!(big as usize, 0x10_2000);
+	assert_eq!(memblock.alloc_small(0, 8), None);
+
+	// The pages taken for the chunk and the big request merge
+	assert_eq!(memblock.reserved_count(), 1);
+	assert_eq!(memblock.reserved_region()[0].size(), 0x4000);
+}
@@ -1,5 +1,45 @@
-use crate::{log_debug, memory::paging::translate, println_serial};
-use alloc::{boxed::Box, vec};
+use crate::{
+	arch::x86::cpu::{cr3, set_cr3},
+	collections::linked_list::Node,
+	error::KernelError,
+	log_debug,
+	memory::{
+		allocate_dynamic_virt_range,
+		allocator::{shrink_caches, slab_stats, BUDDY_PAGE_ALLOCATOR},
+		frame::{DMA_RESERVE_FRAMES, FRAME_ALLOCATOR},
+		free_dynamic_virt_range,
+		heapcheck::heap_check,
+		kalloc::{kfree, kfree_obj, kmalloc, kmalloc_obj, kzalloc},
+		kmem::{
+			kmem_cache_alloc, kmem_cache_create, kmem_cache_free,
+			kmem_cache_stats,
+		},
+		node_pool::NodeAllocatorWrapper,
+		paging::{
+			flags, for_each_mapped_run, map_page, map_range, page_flags,
+			page_walk, protect_page, protect_range, translate, unmap_page,
+			unmap_range, AddressSpace, MappedRun, ProtectError,
+		},
+		slab::SlabCheckError,
+		virt_range::VirtRangeAllocator,
+		vmalloc::{vfree, vmalloc, vmalloc_size},
+		zone::{zone_range, DMA_ZONE_END, ZONE_DMA, ZONE_NORMAL},
+		BuddyAllocator, MemorySegment, PhysAddr, RegionType, SlabCache,
+		VirtAddr, PAGE_SIZE,
+	},
+	println_serial,
+	util::rand::Rng,
+};
+use alloc::{
+	alloc::{alloc, dealloc, realloc, Layout},
+	boxed::Box,
+	vec,
+	vec::Vec,
+};
+use core::{
+	alloc::Allocator,
+	ptr::{self, NonNull},
+};
 
 #[test_case]
 fn test_translate_1() {
@@ -54,3 +94,1066 @@ fn test_global_allocator_large_vec() {
 		assert_eq!(v, &i);
 	}
 }
+
+/// Fills a vector of `len` bytes, too big for any slab cache, and reads it
+/// back.
+fn check_page_vec(len: usize) {
+	let mut vec = vec![0u8; len];
+	for (i, v) in vec.iter_mut().enumerate() {
+		*v = i as u8;
+	}
+	for (i, v) in vec.iter().enumerate() {
+		assert_eq!(*v, i as u8);
+	}
+}
+
+#[test_case]
+fn test_global_allocator_4kib_vec() {
+	check_page_vec(4 * 1024);
+}
+
+#[test_case]
+fn test_global_allocator_64kib_vec() {
+	check_page_vec(64 * 1024);
+}
+
+#[test_case]
+fn test_global_allocator_1mib_vec() {
+	check_page_vec(1024 * 1024);
+}
+
+#[test_case]
+fn test_global_allocator_page_vec_unmapped_on_drop() {
+	let vec = vec![1u8; 16 * 1024];
+	let addr = VirtAddr::new(vec.as_ptr() as usize);
+	assert!(translate(addr).is_some());
+
+	drop(vec);
+	assert_eq!(translate(addr), None);
+}
+
+#[test_case]
+fn test_global_allocator_rejects_huge_alignment() {
+	let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE * 2).unwrap();
+	assert!(unsafe { alloc(layout) }.is_null());
+}
+
+#[test_case]
+fn test_global_allocator_vec_grows_across_classes() {
+	let mut vec = Vec::new();
+	for i in 0..3000u32 {
+		vec.push(i);
+		assert_eq!(vec[(i / 2) as usize], i / 2);
+	}
+	for (i, v) in vec.iter().enumerate() {
+		assert_eq!(*v, i as u32);
+	}
+}
+
+#[test_case]
+fn test_global_allocator_realloc_in_place_within_class() {
+	let layout = Layout::from_size_align(20, 4).unwrap();
+	unsafe {
+		let ptr = alloc(layout);
+		ptr.write(42);
+
+		let grown = realloc(ptr, layout, 30);
+		assert_eq!(grown, ptr);
+		assert_eq!(grown.read(), 42);
+
+		dealloc(grown, Layout::from_size_align(30, 4).unwrap());
+	}
+}
+
+#[test_case]
+fn test_global_allocator_honours_alignment() {
+	for align in [8, 64, 512, 2048, PAGE_SIZE] {
+		let layout = Layout::from_size_align(8, align).unwrap();
+		unsafe {
+			let ptr = alloc(layout);
+			assert!(!ptr.is_null());
+			assert_eq!(ptr as usize % align, 0);
+			dealloc(ptr, layout);
+		}
+	}
+}
+
+#[test_case]
+fn test_global_allocator_zeroed_vec() {
+	let vec = vec![0u8; 700];
+	assert!(vec.iter().all(|&v| v == 0));
+}
+
+#[test_case]
+fn test_kmalloc_reports_exhaustion() {
+	// 4 MiB blocks, enough slots to cover a 512 MiB machine.
+	let layout = Layout::from_size_align(4 * 1024 * 1024, PAGE_SIZE).unwrap();
+	let mut blocks: [Option<NonNull<u8>>; 128] = [None; 128];
+	let mut exhausted = false;
+
+	for slot in blocks.iter_mut() {
+		match kmalloc(layout) {
+			Ok(ptr) => *slot = Some(ptr),
+			Err(_) => {
+				exhausted = true;
+				break;
+			}
+		}
+	}
+
+	for ptr in blocks.iter().flatten() {
+		unsafe { kfree(*ptr, layout) };
+	}
+
+	assert!(exhausted);
+	assert_eq!(heap_check(), Ok(()));
+
+	let ptr = kmalloc(layout).unwrap();
+	unsafe { kfree(ptr, layout) };
+}
+
+#[test_case]
+fn test_kmalloc_rejects_zero_size() {
+	assert_eq!(
+		kmalloc(Layout::new::<()>()),
+		Err(KernelError::InvalidArgument)
+	);
+	let layout = Layout::from_size_align(64, 2 * PAGE_SIZE).unwrap();
+	assert_eq!(kmalloc(layout), Err(KernelError::InvalidArgument));
+}
+
+#[test_case]
+fn test_kzalloc_zeroes() {
+	let layout = Layout::from_size_align(200, 8).unwrap();
+
+	// Leaves garbage in the block kzalloc gets next
+	let dirty = kmalloc(layout).unwrap();
+	unsafe { ptr::write_bytes(dirty.as_ptr(), 0xaa, 200) };
+	unsafe { kfree(dirty, layout) };
+
+	let ptr = kzalloc(layout).unwrap();
+	let bytes = unsafe { core::slice::from_raw_parts(ptr.as_ptr(), 200) };
+	assert!(bytes.iter().all(|&b| b == 0));
+	unsafe { kfree(ptr, layout) };
+}
+
+#[test_case]
+fn test_kmalloc_obj() {
+	let obj = kmalloc_obj::<[u64; 3]>().unwrap();
+	assert!(obj.is_aligned());
+
+	unsafe { obj.write([1, 2, 3]) };
+	assert_eq!(unsafe { obj.read() }, [1, 2, 3]);
+	unsafe { kfree_obj(obj) };
+
+	assert_eq!(kmalloc_obj::<()>(), Err(KernelError::InvalidArgument));
+}
+
+#[test_case]
+fn test_buddy_free_lists_consistent_after_random_use() {
+	let mut guard = BUDDY_PAGE_ALLOCATOR.lock().unwrap();
+	let buddy = &mut *guard;
+	let mut rng = Rng::new(0x5eed);
+	let mut blocks: [Option<(*mut u8, Layout)>; 32] = [None; 32];
+
+	for _ in 0..256 {
+		let slot = &mut blocks[rng.range(0, 31)];
+		match slot.take() {
+			Some((ptr, layout)) => unsafe { buddy.dealloc(ptr, layout) },
+			None => {
+				let pages = rng.range(1, 16);
+				let layout =
+					Layout::from_size_align(pages * PAGE_SIZE, PAGE_SIZE)
+						.unwrap();
+				let ptr = unsafe { buddy.alloc(layout) };
+				if !ptr.is_null() {
+					*slot = Some((ptr, layout));
+				}
+			}
+		}
+	}
+	assert_eq!(buddy.debug_check(), Ok(()));
+
+	for (ptr, layout) in blocks.iter().flatten() {
+		unsafe { buddy.dealloc(*ptr, *layout) };
+	}
+	assert_eq!(buddy.debug_check(), Ok(()));
+
+	drop(guard);
+	assert_eq!(heap_check(), Ok(()));
+}
+
+#[test_case]
+fn test_buddy_dealloc_uses_recorded_order() {
+	let mut guard = BUDDY_PAGE_ALLOCATOR.lock().unwrap();
+	let buddy = &mut *guard;
+	let free_before = buddy.stats().free_bytes;
+
+	// Aligned to two pages, so the block is order 1 although the size fits
+	// order 0.
+	let alloc_layout =
+		Layout::from_size_align(PAGE_SIZE, PAGE_SIZE * 2).unwrap();
+	let free_layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+
+	unsafe {
+		let ptr = buddy.alloc(alloc_layout);
+		assert!(!ptr.is_null());
+		assert_eq!(buddy.stats().free_bytes, free_before - PAGE_SIZE * 2);
+
+		buddy.dealloc(ptr, free_layout);
+	}
+
+	assert_eq!(buddy.stats().free_bytes, free_before);
+	assert_eq!(buddy.debug_check(), Ok(()));
+}
+
+/// Pages the metadata of [`synthetic_buddy`] covers, the span of the
+/// largest memory map below.
+const SYNTHETIC_PAGES: usize = 96;
+
+static mut SYNTHETIC_MAP: [usize; SYNTHETIC_PAGES / usize::BITS as usize] =
+	[0; SYNTHETIC_PAGES / usize::BITS as usize];
+static mut SYNTHETIC_ORDERS: [u8; SYNTHETIC_PAGES] = [0; SYNTHETIC_PAGES];
+
+// Helper building a buddy allocator over the synthetic memory map
+// `segments`. Nothing behind the addresses is touched, the allocator only
+// keeps metadata, which all of them share as tests run one at a time.
+fn synthetic_buddy(
+	base: PhysAddr,
+	segments: &[MemorySegment],
+) -> BuddyAllocator {
+	assert!(
+		BuddyAllocator::span(base, segments) <= SYNTHETIC_PAGES * PAGE_SIZE
+	);
+
+	let (map, orders) = unsafe {
+		(
+			&mut *ptr::addr_of_mut!(SYNTHETIC_MAP),
+			&mut *ptr::addr_of_mut!(SYNTHETIC_ORDERS),
+		)
+	};
+	return BuddyAllocator::with_metadata(base, segments, map, orders);
+}
+
+#[test_case]
+fn test_buddy_never_hands_out_a_hole() {
+	// 32 pages of RAM, a 16 page hole, then 48 pages of RAM
+	let base = PhysAddr::new(0x4000_0000);
+	let hole_start = base + 32 * PAGE_SIZE;
+	let hole_end = hole_start + 16 * PAGE_SIZE;
+	let segments = [
+		MemorySegment::new(base, 32 * PAGE_SIZE, RegionType::Available),
+		MemorySegment::new(hole_start, 16 * PAGE_SIZE, RegionType::Reserved),
+		MemorySegment::new(hole_end, 48 * PAGE_SIZE, RegionType::Available),
+	];
+	assert_eq!(BuddyAllocator::span(base, &segments), 96 * PAGE_SIZE);
+
+	let mut buddy = synthetic_buddy(base, &segments);
+	assert_eq!(buddy.stats().total_bytes, 80 * PAGE_SIZE);
+	assert_eq!(buddy.debug_check(), Ok(()));
+
+	let mut rng = Rng::new(0x401e);
+	let mut blocks: [Option<(*mut u8, Layout)>; 16] = [None; 16];
+
+	for _ in 0..200 {
+		let slot = &mut blocks[rng.range(0, 15)];
+		match slot.take() {
+			Some((ptr, layout)) => unsafe { buddy.dealloc(ptr, layout) },
+			None => {
+				let pages = rng.range(1, 8);
+				let layout =
+					Layout::from_size_align(pages * PAGE_SIZE, PAGE_SIZE)
+						.unwrap();
+				let ptr = unsafe { buddy.alloc(layout) };
+				if ptr.is_null() {
+					continue;
+				}
+
+				let start = PhysAddr::new(ptr as usize);
+				let end = start + pages.next_power_of_two() * PAGE_SIZE;
+				assert!(end <= hole_start || start >= hole_end);
+				*slot = Some((ptr, layout));
+			}
+		}
+	}
+	assert_eq!(buddy.debug_check(), Ok(()));
+
+	for (ptr, layout) in blocks.iter().flatten() {
+		unsafe { buddy.dealloc(*ptr, *layout) };
+	}
+	assert_eq!(buddy.stats().free_bytes, 80 * PAGE_SIZE);
+	assert_eq!(buddy.debug_check(), Ok(()));
+}
+
+/// Pages of the synthetic region of the zone test, straddling the end of the
+/// DMA zone.
+const ZONE_TEST_PAGES: usize = 16;
+
+#[test_case]
+fn test_buddy_alloc_in_zone_splits_across_the_boundary() {
+	// One order 4 block, half of it below 16 MiB
+	let base = PhysAddr::new(
+		DMA_ZONE_END.as_usize() - ZONE_TEST_PAGES / 2 * PAGE_SIZE,
+	);
+	let segments = [MemorySegment::new(
+		base,
+		ZONE_TEST_PAGES * PAGE_SIZE,
+		RegionType::Available,
+	)];
+	let mut buddy = synthetic_buddy(base, &segments);
+	let page = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+	let half = Layout::from_size_align(8 * PAGE_SIZE, PAGE_SIZE).unwrap();
+
+	let normal = unsafe { buddy.alloc_in_zone(page, ZONE_NORMAL) };
+	assert_eq!(normal as usize, DMA_ZONE_END.as_usize());
+
+	let dma = unsafe { buddy.alloc_in_zone(half, ZONE_DMA) };
+	assert_eq!(dma as usize, base.as_usize());
+	assert!(unsafe { buddy.alloc_in_zone(page, ZONE_DMA) }.is_null());
+	assert!(unsafe { buddy.alloc_in_zone(page, 0) }.is_null());
+	assert_eq!(buddy.debug_check(), Ok(()));
+
+	unsafe {
+		buddy.dealloc(normal, page);
+		buddy.dealloc(dma, half);
+	}
+	assert_eq!(buddy.stats().free_bytes, ZONE_TEST_PAGES * PAGE_SIZE);
+	assert_eq!(buddy.stats().free_blocks[4], 1);
+	assert_eq!(buddy.debug_check(), Ok(()));
+}
+
+#[test_case]
+fn test_frame_allocate_in_dma_zone() {
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+	let dma = zone_range(ZONE_DMA).unwrap();
+	let free_before = frames.free_frames_in(dma.clone());
+
+	let frame = frames.allocate_frame_in(dma.clone()).unwrap();
+	assert!(frame < DMA_ZONE_END);
+	assert_eq!(frames.refcount(frame), 1);
+	assert_eq!(frames.free_frames_in(dma.clone()), free_before - 1);
+
+	frames.deallocate_frame(frame);
+	assert_eq!(frames.free_frames_in(dma), free_before);
+	assert_eq!(frames.dma_reserve_free(), DMA_RESERVE_FRAMES);
+}
+
+#[test_case]
+fn test_slab_free_slabs_are_returned() {
+	const COUNT: usize = 70;
+	let layout = Layout::from_size_align(512, 4).unwrap();
+	let free_slabs = || {
+		let stats = slab_stats().unwrap();
+		return stats
+			.iter()
+			.find(|c| c.object_size == 512)
+			.unwrap()
+			.slabs_free;
+	};
+
+	let mut ptrs = [ptr::null_mut(); COUNT];
+	for ptr in ptrs.iter_mut() {
+		*ptr = unsafe { alloc(layout) };
+		assert!(!ptr.is_null());
+	}
+	for ptr in ptrs.iter() {
+		unsafe { dealloc(*ptr, layout) };
+	}
+
+	// Freeing ten slabs worth of objects only keeps a couple cached.
+	assert!(free_slabs() <= 2);
+
+	shrink_caches();
+	assert_eq!(free_slabs(), 0);
+}
+
+#[test_case]
+fn test_slab_lists_consistent_after_random_use() {
+	let mut cache = SlabCache::new("test-random", 64, 1, 0);
+	let layout = Layout::from_size_align(64, 4).unwrap();
+	let mut rng = Rng::new(0x51ab);
+	let mut objects: [*mut u8; 96] = [ptr::null_mut(); 96];
+
+	for _ in 0..1000 {
+		let object = &mut objects[rng.range(0, 95)];
+		if object.is_null() {
+			*object = unsafe { cache.alloc(layout) };
+			assert!(!object.is_null());
+		} else {
+			unsafe { cache.dealloc(*object, layout) };
+			*object = ptr::null_mut();
+		}
+	}
+	assert_eq!(cache.debug_check(), Ok(()));
+	let live = objects.iter().filter(|o| !o.is_null()).count();
+	assert_eq!(cache.stats().objects_in_use, live);
+
+	for object in objects.iter().filter(|o| !o.is_null()) {
+		unsafe { cache.dealloc(*object, layout) };
+	}
+	assert_eq!(cache.debug_check(), Ok(()));
+	assert_eq!(cache.stats().objects_in_use, 0);
+
+	cache.shrink();
+	assert_eq!(cache.stats().slabs_free, 0);
+	assert_eq!(heap_check(), Ok(()));
+}
+
+#[test_case]
+fn test_slab_check_catches_broken_free_list() {
+	let mut cache = SlabCache::new("test-check", 64, 1, 0);
+	let layout = Layout::from_size_align(64, 8).unwrap();
+	let first = unsafe { cache.alloc(layout) };
+	let second = unsafe { cache.alloc(layout) };
+	unsafe { cache.dealloc(first, layout) };
+
+	let link = first.cast::<usize>();
+	let saved = unsafe { link.read() };
+
+	unsafe { link.write(0x1234) };
+	assert_eq!(
+		cache.debug_check(),
+		Err(SlabCheckError::FreeListEscapes {
+			object: 0x1234
+		})
+	);
+
+	unsafe { link.write(first as usize) };
+	assert_eq!(cache.debug_check(), Err(SlabCheckError::FreeListLength));
+
+	unsafe { link.write(saved) };
+	assert_eq!(cache.debug_check(), Ok(()));
+
+	unsafe { cache.dealloc(second, layout) };
+	cache.shrink();
+}
+
+#[test_case]
+fn test_heap_check_counts_leaked_nodes() {
+	let layout = Layout::new::<Node<usize>>();
+	let node = NodeAllocatorWrapper.allocate(layout).unwrap();
+
+	assert_eq!(heap_check(), Err(1));
+
+	unsafe { NodeAllocatorWrapper.deallocate(node.cast(), layout) };
+	assert_eq!(heap_check(), Ok(()));
+}
+
+#[test_case]
+fn test_kmem_cache_objects() {
+	let cache = kmem_cache_create("test-objects", 24, 8, 0);
+	let mut objects = [None; 200];
+
+	for (i, object) in objects.iter_mut().enumerate() {
+		let ptr = kmem_cache_alloc(cache).unwrap();
+		assert_eq!(ptr.as_ptr() as usize % 8, 0);
+		unsafe { ptr.as_ptr().cast::<usize>().write(i) };
+		*object = Some(ptr);
+	}
+
+	let stats = kmem_cache_stats()
+		.into_iter()
+		.flatten()
+		.find(|stats| stats.name == "test-objects")
+		.unwrap();
+	assert_eq!(stats.object_size, 24);
+	assert_eq!(stats.objects_in_use, 200);
+
+	for (i, object) in objects.iter().flatten().enumerate() {
+		assert_eq!(unsafe { object.as_ptr().cast::<usize>().read() }, i);
+		unsafe { kmem_cache_free(cache, *object) };
+	}
+	assert_eq!(cache.lock().stats().objects_in_use, 0);
+}
+
+#[test_case]
+fn test_kmem_cache_multi_page_slabs() {
+	let cache = kmem_cache_create("test-order-2", 3000, 1, 2);
+	let a = kmem_cache_alloc(cache).unwrap();
+	let b = kmem_cache_alloc(cache).unwrap();
+	assert_ne!(a, b);
+
+	unsafe {
+		kmem_cache_free(cache, a);
+		kmem_cache_free(cache, b);
+	}
+	assert_eq!(cache.lock().debug_check(), Ok(()));
+}
+
+#[test_case]
+fn test_slab_stats_count_pinned_bytes() {
+	let cache = kmem_cache_create("test-pinned", 128, 1, 1);
+	let object = kmem_cache_alloc(cache).unwrap();
+
+	let stats = cache.lock().stats();
+	assert_eq!(stats.slabs(), 1);
+	assert_eq!(stats.bytes(), PAGE_SIZE * 2);
+
+	unsafe { kmem_cache_free(cache, object) };
+}
+
+#[test_case]
+fn test_frame_contiguous_run_across_bitmap_words() {
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+	let used = frames.stats().used_frames;
+
+	// Any run longer than one bitmap word straddles a word boundary.
+	let run = frames.allocate_contiguous(96, 32).unwrap();
+	assert_eq!(run.as_usize() % (32 * PAGE_SIZE), 0);
+	assert_eq!(frames.stats().used_frames, used + 96);
+
+	frames.deallocate_contiguous(run, 96);
+	assert_eq!(frames.stats().used_frames, used);
+	assert_eq!(frames.allocate_contiguous(96, 32), Some(run));
+	frames.deallocate_contiguous(run, 96);
+}
+
+#[test_case]
+fn test_frame_contiguous_run_ends_in_next_word() {
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+
+	// Take two whole words, then free the back half of the first and the
+	// front half of the second, leaving a free run across the boundary.
+	let words = frames.allocate_contiguous(128, 64).unwrap();
+	frames.deallocate_contiguous(words + 32 * PAGE_SIZE, 64);
+
+	let run = frames.allocate_contiguous(64, 32).unwrap();
+	assert_eq!(run, words + 32 * PAGE_SIZE);
+
+	frames.deallocate_contiguous(words, 128);
+}
+
+#[test_case]
+fn test_frame_single_after_contiguous() {
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+	let used = frames.stats().used_frames;
+
+	let run = frames.allocate_contiguous(3, 1).unwrap();
+	let frame = frames.allocate_frame().unwrap();
+	assert!(frame < run || frame >= run + 3 * PAGE_SIZE);
+
+	frames.deallocate_frame(frame);
+	frames.deallocate_contiguous(run, 3);
+	assert_eq!(frames.stats().used_frames, used);
+	assert_eq!(frames.allocate_contiguous(0, 1), None);
+}
+
+#[test_case]
+fn test_frame_refcount_lifecycle() {
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+	let used = frames.stats().used_frames;
+
+	let frame = frames.allocate_frame().unwrap();
+	assert_eq!(frames.refcount(frame), 1);
+
+	frames.frame_get(frame);
+	assert_eq!(frames.refcount(frame), 2);
+	assert!(!frames.frame_put(frame));
+	assert_eq!(frames.stats().used_frames, used + 1);
+
+	assert!(frames.frame_put(frame));
+	assert_eq!(frames.refcount(frame), 0);
+	assert_eq!(frames.stats().used_frames, used);
+}
+
+#[test_case]
+fn test_frame_over_put_is_ignored() {
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+
+	let frame = frames.allocate_frame().unwrap();
+	assert!(frames.frame_put(frame));
+	let used = frames.stats().used_frames;
+
+	// Logs a warning and leaves the bitmap alone.
+	assert!(!frames.frame_put(frame));
+	assert_eq!(frames.refcount(frame), 0);
+	assert_eq!(frames.stats().used_frames, used);
+}
+
+#[test_case]
+fn test_unmap_keeps_shared_frame() {
+	let virt = allocate_dynamic_virt_range(2 * PAGE_SIZE).unwrap();
+	let second = virt + PAGE_SIZE;
+	let frame = FRAME_ALLOCATOR.lock().unwrap().allocate_frame().unwrap();
+
+	map_page(frame, virt, flags::PRESENT | flags::WRITABLE);
+	FRAME_ALLOCATOR.lock().unwrap().frame_get(frame);
+	map_page(frame, second, flags::PRESENT | flags::WRITABLE);
+
+	unmap_page(virt);
+	assert_eq!(FRAME_ALLOCATOR.lock().unwrap().refcount(frame), 1);
+	assert_eq!(translate(second), Some(frame));
+
+	unmap_page(second);
+	assert_eq!(FRAME_ALLOCATOR.lock().unwrap().refcount(frame), 0);
+}
+
+#[test_case]
+fn test_virt_ranges_coalesce_on_free() {
+	let base = VirtAddr::new(0x1000_0000);
+	let mut ranges = VirtRangeAllocator::new(base, 16 * PAGE_SIZE);
+
+	let a = ranges.allocate(4 * PAGE_SIZE, PAGE_SIZE).unwrap();
+	let b = ranges.allocate(4 * PAGE_SIZE, PAGE_SIZE).unwrap();
+	let c = ranges.allocate(4 * PAGE_SIZE, PAGE_SIZE).unwrap();
+	assert_eq!((a, b, c), (base, base + 0x4000, base + 0x8000));
+	assert_eq!(ranges.free_ranges(), 1);
+
+	assert!(ranges.free(b, 4 * PAGE_SIZE));
+	assert_eq!(ranges.free_ranges(), 2);
+	// Merges with the hole before it and the tail after it
+	assert!(ranges.free(c, 4 * PAGE_SIZE));
+	assert_eq!(ranges.free_ranges(), 1);
+	assert!(ranges.free(a, 4 * PAGE_SIZE));
+	assert_eq!(ranges.free_ranges(), 1);
+	assert_eq!(ranges.free_bytes(), 16 * PAGE_SIZE);
+
+	assert_eq!(ranges.allocate(16 * PAGE_SIZE, PAGE_SIZE), Some(base));
+	assert_eq!(ranges.allocate(PAGE_SIZE, PAGE_SIZE), None);
+}
+
+#[test_case]
+fn test_virt_ranges_reuse_first_fit() {
+	let base = VirtAddr::new(0x1000_0000);
+	let mut ranges = VirtRangeAllocator::new(base, 16 * PAGE_SIZE);
+
+	let a = ranges.allocate(PAGE_SIZE, PAGE_SIZE).unwrap();
+	let b = ranges.allocate(2 * PAGE_SIZE, PAGE_SIZE).unwrap();
+	assert!(ranges.free(a, PAGE_SIZE));
+	assert!(!ranges.free(a, PAGE_SIZE));
+	assert!(!ranges.free(b, PAGE_SIZE));
+
+	// Too big for the hole at `a`, fits after `b`
+	let c = ranges.allocate(2 * PAGE_SIZE, PAGE_SIZE).unwrap();
+	assert_eq!(c, b + 2 * PAGE_SIZE);
+	assert_eq!(ranges.allocate(100, PAGE_SIZE), Some(a));
+
+	// Aligned blocks split the free range around them
+	let d = ranges.allocate(PAGE_SIZE, 8 * PAGE_SIZE).unwrap();
+	assert_eq!(d, base + 8 * PAGE_SIZE);
+	assert_eq!(ranges.free_ranges(), 2);
+}
+
+#[test_case]
+fn test_virt_ranges_never_overlap() {
+	let base = VirtAddr::new(0x1000_0000);
+	let mut ranges = VirtRangeAllocator::new(base, 64 * PAGE_SIZE);
+	let mut rng = Rng::new(0x5eed);
+	let mut live: [Option<(VirtAddr, usize)>; 16] = [None; 16];
+
+	for _ in 0..500 {
+		let slot = rng.range(0, 15);
+		match live[slot].take() {
+			Some((addr, size)) => assert!(ranges.free(addr, size)),
+			None => {
+				let size = rng.range(1, 6) * PAGE_SIZE;
+				let Some(addr) = ranges.allocate(size, PAGE_SIZE) else {
+					continue;
+				};
+				assert!(addr >= base && addr + size <= base + 64 * PAGE_SIZE);
+				for (other, other_size) in live.iter().flatten() {
+					assert!(
+						addr + size <= *other || *other + *other_size <= addr
+					);
+				}
+				live[slot] = Some((addr, size));
+			}
+		}
+	}
+
+	for (addr, size) in live.iter().flatten() {
+		assert!(ranges.free(*addr, *size));
+	}
+	assert_eq!(ranges.free_ranges(), 1);
+	assert_eq!(ranges.free_bytes(), 64 * PAGE_SIZE);
+
+	drop(ranges);
+	assert_eq!(heap_check(), Ok(()));
+}
+
+#[test_case]
+fn test_dynamic_virt_range_free_and_reuse() {
+	let a = allocate_dynamic_virt_range(3 * PAGE_SIZE).unwrap();
+	assert!(free_dynamic_virt_range(a, 3 * PAGE_SIZE));
+	assert!(!free_dynamic_virt_range(a, 3 * PAGE_SIZE));
+
+	let b = allocate_dynamic_virt_range(3 * PAGE_SIZE).unwrap();
+	assert_eq!(a, b);
+	assert!(free_dynamic_virt_range(b, 3 * PAGE_SIZE));
+}
+
+#[test_case]
+fn test_vmalloc_maps_and_frees_pages() {
+	let used = FRAME_ALLOCATOR.lock().unwrap().stats().used_frames;
+
+	let start = vmalloc(5 * PAGE_SIZE - 100).unwrap();
+	assert_eq!(vmalloc_size(start), Some(5 * PAGE_SIZE));
+
+	let buffer = unsafe {
+		core::slice::from_raw_parts_mut(start.as_mut_ptr::<u8>(), 5 * PAGE_SIZE)
+	};
+	for (i, byte) in buffer.iter_mut().enumerate() {
+		*byte = i as u8;
+	}
+	assert!(buffer.iter().enumerate().all(|(i, &byte)| byte == i as u8));
+
+	vfree(start);
+	assert_eq!(vmalloc_size(start), None);
+	for page in 0..5 {
+		assert_eq!(translate(start + page * PAGE_SIZE), None);
+	}
+	assert_eq!(FRAME_ALLOCATOR.lock().unwrap().stats().used_frames, used);
+}
+
+#[test_case]
+fn test_vmalloc_reuses_freed_range() {
+	assert_eq!(vmalloc(0), None);
+
+	let first = vmalloc(1024 * 1024).unwrap();
+	vfree(first);
+	let again = vmalloc(1024 * 1024).unwrap();
+	assert_eq!(first, again);
+	vfree(again);
+
+	// Logs a warning and changes nothing
+	vfree(again);
+}
+
+#[test_case]
+fn test_map_range_and_unmap_range() {
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+	let phys = frames.allocate_contiguous(3, 1).unwrap();
+	drop(guard);
+
+	let virt = allocate_dynamic_virt_range(3 * PAGE_SIZE).unwrap();
+	map_range(
+		phys,
+		virt,
+		3 * PAGE_SIZE - 1,
+		flags::PRESENT | flags::WRITABLE,
+	)
+	.unwrap();
+	for page in 0..3 {
+		let offset = page * PAGE_SIZE;
+		assert_eq!(translate(virt + offset), Some(phys + offset));
+	}
+
+	unmap_range(virt, 3 * PAGE_SIZE);
+	for page in 0..3 {
+		assert_eq!(translate(virt + page * PAGE_SIZE), None);
+	}
+
+	// The frames are still the caller's
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let frames = &*guard;
+	assert_eq!(frames.refcount(phys), 1);
+	frames.deallocate_contiguous(phys, 3);
+	drop(guard);
+	free_dynamic_virt_range(virt, 3 * PAGE_SIZE);
+}
+
+#[test_case]
+fn test_unmap_range_flushes_large_ranges() {
+	let pages = 64;
+	let guard = FRAME_ALLOCATOR.lock().unwrap();
+	let phys = guard.allocate_contiguous(pages, 1).unwrap();
+	drop(guard);
+
+	let virt = allocate_dynamic_virt_range(pages * PAGE_SIZE).unwrap();
+	map_range(
+		phys,
+		virt,
+		pages * PAGE_SIZE,
+		flags::PRESENT | flags::WRITABLE,
+	)
+	.unwrap();
+	unsafe { virt.as_mut_ptr::<u32>().write_volatile(0xfeed) };
+
+	unmap_range(virt, pages * PAGE_SIZE);
+	assert_eq!(translate(virt), None);
+	assert_eq!(translate(virt + (pages - 1) * PAGE_SIZE), None);
+
+	// Mapped again, the data is still in the frame
+	map_range(phys, virt, PAGE_SIZE, flags::PRESENT).unwrap();
+	assert_eq!(unsafe { virt.as_ptr::<u32>().read_volatile() }, 0xfeed);
+	unmap_range(virt, PAGE_SIZE);
+
+	FRAME_ALLOCATOR
+		.lock()
+		.unwrap()
+		.deallocate_contiguous(phys, pages);
+	free_dynamic_virt_range(virt, pages * PAGE_SIZE);
+}
+
+#[test_case]
+fn test_protect_page_keeps_frame() {
+	let start = vmalloc(2 * PAGE_SIZE).unwrap();
+	let frame = translate(start).unwrap();
+
+	protect_page(start, flags::PRESENT).unwrap();
+	assert_eq!(page_flags(start).unwrap() & flags::WRITABLE, 0);
+	assert_eq!(translate(start), Some(frame));
+
+	protect_range(start, 2 * PAGE_SIZE, flags::PRESENT | flags::WRITABLE)
+		.unwrap();
+	assert_ne!(page_flags(start).unwrap() & flags::WRITABLE, 0);
+	assert_ne!(page_flags(start + PAGE_SIZE).unwrap() & flags::WRITABLE, 0);
+	vfree(start);
+}
+
+#[test_case]
+fn test_protect_rejects_unmapped_and_large_pages() {
+	let start = vmalloc(PAGE_SIZE).unwrap();
+	let unmapped = start + PAGE_SIZE;
+	assert_eq!(translate(unmapped), None);
+
+	// The range check fails before the first page is changed
+	assert_eq!(
+		protect_range(start, 2 * PAGE_SIZE, flags::PRESENT),
+		Err(ProtectError::NotMapped(unmapped))
+	);
+	assert_ne!(page_flags(start).unwrap() & flags::WRITABLE, 0);
+	vfree(start);
+
+	let large = VirtAddr::new(0xc0c0_0000);
+	assert_eq!(
+		protect_page(large, flags::PRESENT),
+		Err(ProtectError::LargePage(large))
+	);
+}
+
+#[test_case]
+fn test_kernel_text_is_read_only() {
+	let text = VirtAddr::new(translate as usize);
+	assert_eq!(page_flags(text).unwrap() & flags::WRITABLE, 0);
+
+	let rodata = VirtAddr::new("read-only".as_ptr() as usize);
+	assert_eq!(page_flags(rodata).unwrap() & flags::WRITABLE, 0);
+}
+
+#[test_case]
+fn test_mapped_runs_merge_contiguous_pages() {
+	let phys = FRAME_ALLOCATOR
+		.lock()
+		.unwrap()
+		.allocate_contiguous(3, 1)
+		.unwrap();
+	let virt = allocate_dynamic_virt_range(3 * PAGE_SIZE).unwrap();
+	map_range(phys, virt, 3 * PAGE_SIZE, flags::PRESENT | flags::WRITABLE)
+		.unwrap();
+
+	let mut found: Option<MappedRun> = None;
+	let mut boot_mapping = false;
+	for_each_mapped_run(|run| {
+		if run.virt <= virt && virt < run.virt + run.size {
+			found = Some(run);
+		}
+		if run.virt.as_usize() == 0xc000_0000 {
+			boot_mapping = run.phys.as_usize() == 0;
+		}
+	});
+
+	let run = found.unwrap();
+	let offset = virt.as_usize() - run.virt.as_usize();
+	assert!(offset + 3 * PAGE_SIZE <= run.size);
+	assert_eq!(run.phys + offset, phys);
+	assert_eq!(run.flags, flags::PRESENT | flags::WRITABLE);
+	assert!(boot_mapping);
+
+	let walk = page_walk(virt + PAGE_SIZE);
+	assert_eq!(walk.pde_index, virt.as_usize() >> 22);
+	assert_eq!(
+		walk.pte.unwrap() & 0xffff_f000,
+		(phys + PAGE_SIZE).as_usize() as u32
+	);
+
+	unmap_range(virt, 3 * PAGE_SIZE);
+	free_dynamic_virt_range(virt, 3 * PAGE_SIZE);
+	FRAME_ALLOCATOR
+		.lock()
+		.unwrap()
+		.deallocate_contiguous(phys, 3);
+}
+
+#[test_case]
+fn test_address_space_clone_shares_kernel_half() {
+	let space = AddressSpace::new_kernel_clone().unwrap();
+
+	assert!(!space.is_active());
+	assert_eq!(
+		space.translate(VirtAddr::new(0xc000_1000)),
+		translate(VirtAddr::new(0xc000_1000))
+	);
+	assert!(translate(VirtAddr::new(0x1000)).is_some());
+	assert_eq!(space.translate(VirtAddr::new(0x1000)), None);
+}
+
+#[test_case]
+fn test_address_space_map_is_private() {
+	let free_before = FRAME_ALLOCATOR.lock().unwrap().stats().free_frames;
+	let frame = FRAME_ALLOCATOR.lock().unwrap().allocate_frame().unwrap();
+	let virt = VirtAddr::new(0x4000_0000);
+
+	let mut space = AddressSpace::new_kernel_clone().unwrap();
+	space
+		.map(frame, virt, flags::PRESENT | flags::WRITABLE)
+		.unwrap();
+	space
+		.map(frame, virt + PAGE_SIZE, flags::PRESENT | flags::WRITABLE)
+		.unwrap();
+
+	assert_eq!(space.translate(virt), Some(frame));
+	assert_eq!(translate(virt), None);
+	assert_eq!(space.unmap(virt + PAGE_SIZE), frame);
+	assert_eq!(space.translate(virt + PAGE_SIZE), None);
+
+	// Dropping with a page still mapped frees its page table
+	drop(space);
+	FRAME_ALLOCATOR.lock().unwrap().deallocate_frame(frame);
+	assert_eq!(
+		FRAME_ALLOCATOR.lock().unwrap().stats().free_frames,
+		free_before
+	);
+}
+
+#[test_case]
+fn test_address_space_switch() {
+	static VALUE: usize = 0x5a5a;
+	let kernel_directory = cr3();
+	let space = AddressSpace::new_kernel_clone().unwrap();
+
+	unsafe { space.switch_to() };
+	let active = space.is_active();
+	let value = unsafe { ptr::read_volatile(&VALUE) };
+	unsafe { set_cr3(kernel_directory) };
+
+	assert!(active);
+	assert_eq!(value, 0x5a5a);
+	assert!(!space.is_active());
+}
+
+#[cfg(feature = "buddy-debug")]
+#[test_case]
+fn test_buddy_debug_poison_catches_use_after_free() {
+	use crate::memory::{buddy::POISON_FREE, paging::unmap_page_keep_frame};
+
+	let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+	let virt = allocate_dynamic_virt_range(PAGE_SIZE).unwrap();
+
+	// Held throughout, so nothing else gets the freed page in between.
+	let mut guard = BUDDY_PAGE_ALLOCATOR.lock().unwrap();
+	let buddy = &mut *guard;
+
+	let block = unsafe { buddy.alloc(layout) };
+	assert!(!block.is_null());
+	unsafe { buddy.dealloc(block, layout) };
+
+	let block = PhysAddr::new(block as usize);
+	assert_eq!(buddy.check_poison(block, 0), Ok(()));
+
+	map_page(block, virt, flags::PRESENT | flags::WRITABLE);
+	let page = virt.as_mut_ptr::<u8>();
+	assert_eq!(unsafe { page.add(0x123).read() }, POISON_FREE);
+
+	unsafe { page.add(0x123).write(0) };
+	assert_eq!(buddy.check_poison(block, 0), Err(0x123));
+
+	unsafe { page.add(0x123).write(POISON_FREE) };
+	unmap_page_keep_frame(virt);
+	assert_eq!(buddy.check_poison(block, 0), Ok(()));
+
+	drop(guard);
+	free_dynamic_virt_range(virt, PAGE_SIZE);
+}
+
+#[cfg(feature = "slab-debug")]
+#[test_case]
+fn test_slab_debug_red_zone_catches_overflow() {
+	use crate::memory::slab::{SlabDebugError, RED_ZONE_CANARY};
+
+	let mut cache = SlabCache::new("test-redzone", 64, 1, 0);
+	let layout = Layout::from_size_align(64, 4).unwrap();
+	assert!(
+		cache.stats().objects_per_slab * (64 + size_of::<usize>()) <= PAGE_SIZE
+	);
+
+	let object = NonNull::new(unsafe { cache.alloc(layout) }).unwrap();
+	assert_eq!(cache.check_red_zone(object), Ok(()));
+
+	unsafe { object.as_ptr().add(64).write(0) };
+	assert_eq!(cache.check_red_zone(object), Err(SlabDebugError::Overflow));
+
+	unsafe {
+		object
+			.as_ptr()
+			.add(64)
+			.cast::<usize>()
+			.write_unaligned(RED_ZONE_CANARY)
+	};
+	unsafe { cache.dealloc(object.as_ptr(), layout) };
+	cache.shrink();
+}
+
+#[cfg(feature = "slab-debug")]
+#[test_case]
+fn test_slab_debug_poison_catches_use_after_free() {
+	use crate::memory::slab::{SlabDebugError, POISON_FREE};
+
+	let mut cache = SlabCache::new("test-poison", 64, 1, 0);
+	let layout = Layout::from_size_align(64, 4).unwrap();
+
+	let object = NonNull::new(unsafe { cache.alloc(layout) }).unwrap();
+	unsafe { cache.dealloc(object.as_ptr(), layout) };
+	assert_eq!(cache.check_poison(object), Ok(()));
+
+	unsafe { object.as_ptr().add(16).write(0) };
+	assert_eq!(
+		cache.check_poison(object),
+		Err(SlabDebugError::UseAfterFree {
+			offset: 16
+		})
+	);
+
+	unsafe { object.as_ptr().add(16).write(POISON_FREE) };
+	let again = unsafe { cache.alloc(layout) };
+	assert_eq!(again, object.as_ptr());
+	unsafe { cache.dealloc(again, layout) };
+	cache.shrink();
+}
+
+#[cfg(feature = "slab-debug")]
+#[test_case]
+fn test_slab_debug_samples_free_objects() {
+	use crate::memory::slab::{SlabDebugError, POISON_FREE};
+
+	// Two objects to a slab, so the one left free is the only sample
+	let mut cache = SlabCache::new("test-sample", 1600, 1, 0);
+	let layout = Layout::from_size_align(1600, 4).unwrap();
+	assert_eq!(cache.stats().objects_per_slab, 2);
+
+	let first = NonNull::new(unsafe { cache.alloc(layout) }).unwrap();
+	let second = NonNull::new(unsafe { cache.alloc(layout) }).unwrap();
+	assert_eq!(cache.sample_free(first), None);
+
+	unsafe { cache.dealloc(second.as_ptr(), layout) };
+	assert_eq!(cache.sample_free(first), Some(second));
+
+	unsafe { second.as_ptr().add(100).write(0) };
+	assert_eq!(
+		cache.check_poison(second),
+		Err(SlabDebugError::UseAfterFree {
+			offset: 100
+		})
+	);
+
+	unsafe { second.as_ptr().add(100).write(POISON_FREE) };
+	unsafe { cache.dealloc(first.as_ptr(), layout) };
+	cache.shrink();
+}
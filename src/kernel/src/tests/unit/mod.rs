@@ -1,7 +1,46 @@
 #[allow(clippy::unwrap_used)]
 /* -------------------------------------- */
+pub mod array_vec_tests;
+pub mod ata_tests;
+pub mod backtrace_tests;
+pub mod bitmap_tests;
+pub mod block_tests;
+pub mod bootargs_tests;
+pub mod bytes_tests;
+pub mod console_tests;
+pub mod elf_tests;
+pub mod fat_tests;
 pub mod gdt_tests;
+pub mod idt_tests;
+pub mod intrusive_linked_list_tests;
+pub mod klog_tests;
 pub mod linked_list_tests;
+#[cfg(feature = "lockdep")]
+pub mod lockdep_tests;
+pub mod mem_tests;
+pub mod memblock_tests;
 pub mod mm_tests;
+pub mod multiboot_tests;
+pub mod mutex_tests;
+pub mod once_tests;
+pub mod page_fault_tests;
+pub mod pci_tests;
+pub mod pic_tests;
+pub mod pit_tests;
+pub mod process_tests;
+pub mod ramfs_tests;
+pub mod rand_tests;
+pub mod range_map_tests;
+pub mod ring_buffer_tests;
+pub mod rwlock_tests;
+pub mod speaker_tests;
+pub mod symbols_tests;
+pub mod syscall_tests;
+pub mod task_tests;
 pub mod tty_tests;
-// pub mod pic_tests;
+pub mod usermode_tests;
+pub mod vt_tests;
+pub mod workqueue_tests;
+// Last, the test ends the run from the double fault handler
+#[cfg(feature = "double-fault-test")]
+pub mod double_fault_tests;
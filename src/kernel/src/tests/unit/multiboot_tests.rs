@@ -0,0 +1,148 @@
+use crate::{
+	arch::x86::multiboot::{
+		boot_modules, parse_memory_map, Module, G_SEGMENTS, MAX_MEMORY_SEGMENTS,
+	},
+	memory::{
+		allocator::BUDDY_PAGE_ALLOCATOR, PhysAddr, RegionType, PAGE_SIZE,
+	},
+};
+use alloc::{alloc::Layout, vec::Vec};
+use core::ptr;
+
+// Helper appending a memory map entry to `mmap`
+fn push_entry(mmap: &mut Vec<u8>, addr: u64, len: u64, entry_type: u32) {
+	mmap.extend_from_slice(&20u32.to_le_bytes());
+	mmap.extend_from_slice(&addr.to_le_bytes());
+	mmap.extend_from_slice(&len.to_le_bytes());
+	mmap.extend_from_slice(&entry_type.to_le_bytes());
+}
+
+#[test_case]
+fn test_memory_map_keeps_typed_regions() {
+	let saved = G_SEGMENTS.read().clone();
+
+	let mut mmap = Vec::new();
+	push_entry(&mut mmap, 0x0, 0x9_fc00, 1);
+	push_entry(&mut mmap, 0x9_fc00, 0x400, 2);
+	push_entry(&mut mmap, 0x1000_0000, 0x10_0000, 1);
+	push_entry(&mut mmap, 0x1010_0000, 0x1000, 3);
+	push_entry(&mut mmap, 0x1010_1000, 0x1000, 9);
+
+	let count = parse_memory_map(&mmap);
+	let segments = G_SEGMENTS.read().clone();
+	let segments = segments.as_slice();
+	*G_SEGMENTS.write() = saved;
+
+	// The zero-base available entry is low memory and skipped
+	assert_eq!(count, 4);
+	assert_eq!(segments[0].start_addr().as_usize(), 0x9_fc00);
+	assert_eq!(segments[0].segment_type(), RegionType::Reserved);
+	assert_eq!(segments[1].start_addr().as_usize(), 0x1000_0000);
+	assert_eq!(segments[1].size(), 0x10_0000);
+	assert_eq!(segments[1].segment_type(), RegionType::Available);
+	assert_eq!(segments[2].segment_type(), RegionType::AcpiReclaimable);
+	assert_eq!(segments[3].segment_type(), RegionType::Unknown);
+	assert_eq!(segments.len(), 4);
+}
+
+#[test_case]
+fn test_memory_map_drops_extra_regions() {
+	let saved = G_SEGMENTS.read().clone();
+
+	let mut mmap = Vec::new();
+	for i in 0..MAX_MEMORY_SEGMENTS as u64 + 4 {
+		push_entry(&mut mmap, 0x1000_0000 + i * 0x2000, 0x1000, 1);
+	}
+
+	let count = parse_memory_map(&mmap);
+	let segments = G_SEGMENTS.read().clone();
+	let segments = segments.as_slice();
+	*G_SEGMENTS.write() = saved;
+
+	assert_eq!(count, MAX_MEMORY_SEGMENTS);
+	let last = segments[MAX_MEMORY_SEGMENTS - 1];
+	assert_eq!(
+		last.start_addr().as_usize(),
+		0x1000_0000 + (MAX_MEMORY_SEGMENTS - 1) * 0x2000
+	);
+}
+
+#[test_case]
+fn test_memory_map_interleaved_reserved_regions() {
+	let saved = G_SEGMENTS.read().clone();
+
+	let mut mmap = Vec::new();
+	for i in 0..24 {
+		let base = 0x1000_0000 + i * 0x10_0000;
+		push_entry(&mut mmap, base, 0x8_0000 + i * 0x1000, 1);
+		push_entry(
+			&mut mmap,
+			base + 0x8_0000 + i * 0x1000,
+			0x1000,
+			2 + i as u32 % 3,
+		);
+	}
+
+	parse_memory_map(&mmap);
+	let segments = G_SEGMENTS.read().clone();
+	*G_SEGMENTS.write() = saved;
+
+	assert_eq!(segments.len(), 48);
+	assert_eq!(segments.available_segments().count(), 24);
+	assert!(segments
+		.available_segments()
+		.all(|segment| segment.segment_type() == RegionType::Available));
+	assert_eq!(
+		segments
+			.iter()
+			.filter(|s| s.segment_type() == RegionType::AcpiNvs)
+			.count(),
+		8
+	);
+
+	let biggest = segments.biggest_available().unwrap();
+	assert_eq!(
+		biggest.start_addr().as_usize(),
+		0x1000_0000 + 23 * 0x10_0000
+	);
+	assert_eq!(biggest.size(), 0x8_0000 + 23 * 0x1000);
+}
+
+#[test_case]
+fn test_module_size_and_bytes() {
+	let module = Module {
+		start: PhysAddr::new(0x1000),
+		end: PhysAddr::new(0x1800),
+		cmdline: "/boot/init",
+	};
+	assert_eq!(module.len(), 0x800);
+	assert!(!module.is_empty());
+	assert_eq!(module.bytes().map(<[u8]>::len), Some(0x800));
+
+	let empty = Module {
+		end: module.start,
+		..module
+	};
+	assert!(empty.is_empty());
+	assert_eq!(empty.bytes().map(<[u8]>::len), Some(0));
+}
+
+#[test_case]
+fn test_boot_modules_are_not_handed_out() {
+	let layout = Layout::from_size_align(PAGE_SIZE, PAGE_SIZE).unwrap();
+	let mut guard = BUDDY_PAGE_ALLOCATOR.lock().unwrap();
+	let buddy = &mut *guard;
+
+	let mut pages = [ptr::null_mut(); 64];
+	for page in pages.iter_mut() {
+		*page = unsafe { buddy.alloc(layout) };
+	}
+	for &page in pages.iter().filter(|page| !page.is_null()) {
+		let addr = page.addr();
+		assert!(boot_modules().iter().all(|module| {
+			addr + PAGE_SIZE <= module.start.as_usize()
+				|| addr >= module.end.as_usize()
+		}));
+		unsafe { buddy.dealloc(page, layout) };
+	}
+}
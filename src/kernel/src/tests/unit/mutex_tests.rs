@@ -0,0 +1,137 @@
+use crate::{
+	arch::x86::cpu::{interrupts_enabled, without_interrupts},
+	sync::{
+		preempt, IrqMutex, Locked, MappedMutexGuard, Mutex, MutexGuard,
+		PreemptGuard,
+	},
+};
+
+#[test_case]
+fn test_mutex_try_lock() {
+	let mutex = Mutex::new(1);
+
+	let guard = mutex.try_lock();
+	assert!(guard.is_some());
+	assert!(mutex.is_locked());
+	assert!(mutex.try_lock().is_none());
+
+	drop(guard);
+	assert!(!mutex.is_locked());
+	assert_eq!(mutex.try_lock().map(|value| *value), Some(1));
+}
+
+#[test_case]
+fn test_mutex_force_lock() {
+	let mutex = Mutex::new(1);
+
+	// Unlocked, it is a plain lock
+	*unsafe { mutex.force_lock() } += 1;
+	assert!(!mutex.is_locked());
+
+	// A holder that never comes back, as for a panic
+	core::mem::forget(mutex.lock());
+	*unsafe { mutex.force_lock() } += 1;
+	assert_eq!(*mutex.lock(), 3);
+}
+
+#[test_case]
+fn test_mutex_guard_map() {
+	let mutex = Mutex::new([1, 2, 3]);
+
+	let mut second = MutexGuard::map(mutex.lock(), |values| &mut values[1]);
+	*second += 10;
+	assert!(mutex.is_locked());
+	drop(second);
+	assert!(!mutex.is_locked());
+	assert_eq!(*mutex.lock(), [1, 12, 3]);
+}
+
+#[test_case]
+fn test_mapped_mutex_guard_map() {
+	let mutex = Mutex::new(([1, 2], 3));
+
+	let pair = MutexGuard::map(mutex.lock(), |(pair, _)| pair);
+	let mut first = MappedMutexGuard::map(pair, |pair| &mut pair[0]);
+	*first = 5;
+	// Only the last guard releases the lock
+	assert!(mutex.is_locked());
+	assert!(mutex.try_lock().is_none());
+	drop(first);
+	assert!(!mutex.is_locked());
+	assert_eq!(*mutex.lock(), ([5, 2], 3));
+}
+
+#[test_case]
+fn test_locked_with() {
+	let locked = Locked::new(1);
+
+	assert_eq!(
+		locked.with(|value| {
+			*value += 1;
+			*value * 10
+		}),
+		20
+	);
+	// Spins forever if the first call kept the lock
+	assert_eq!(locked.with(|value| *value), 2);
+}
+
+#[test_case]
+fn test_irq_mutex_restores_interrupts() {
+	let mutex = IrqMutex::new(0);
+	let enabled = interrupts_enabled();
+
+	let guard = mutex.lock();
+	assert!(!interrupts_enabled());
+	assert!(mutex.try_lock().is_none());
+	// A failed attempt leaves them off for the holder
+	assert!(!interrupts_enabled());
+	drop(guard);
+	assert_eq!(interrupts_enabled(), enabled);
+
+	let value = without_interrupts(|| {
+		assert!(!interrupts_enabled());
+		// Nested, the inner guard must not turn them back on
+		*mutex.lock() += 1;
+		assert!(!interrupts_enabled());
+		*mutex.lock()
+	});
+	assert_eq!(value, 1);
+	assert_eq!(interrupts_enabled(), enabled);
+}
+
+#[test_case]
+fn test_guards_disable_preemption() {
+	let mutex = Mutex::new(0);
+	let irq_mutex = IrqMutex::new(0);
+	let before = preempt::count();
+
+	let guard = mutex.lock();
+	assert!(!preempt::is_preemptible());
+	let irq_guard = irq_mutex.lock();
+	assert_eq!(preempt::count(), before + 2);
+	drop(irq_guard);
+	drop(guard);
+	assert_eq!(preempt::count(), before);
+
+	let mapped = MutexGuard::map(mutex.lock(), |value| value);
+	assert_eq!(preempt::count(), before + 1);
+	drop(mapped);
+	assert!(mutex.try_lock().is_some());
+	assert_eq!(preempt::count(), before);
+}
+
+#[test_case]
+fn test_force_unlock_gives_back_preemption() {
+	let mutex = Mutex::new(0);
+	let before = preempt::count();
+
+	core::mem::forget(mutex.lock());
+	unsafe { mutex.force_unlock() };
+	assert_eq!(preempt::count(), before);
+
+	let guard = PreemptGuard::new();
+	assert_eq!(preempt::count(), before + 1);
+	drop(guard);
+	assert!(preempt::is_preemptible());
+}
@@ -0,0 +1,63 @@
+use crate::sync::{Global, Lazy, Once, OnceLock};
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+#[test_case]
+fn test_once_runs_once() {
+	let once = Once::new();
+	let mut runs = 0;
+
+	assert!(!once.is_completed());
+	once.call_once(|| runs += 1);
+	once.call_once(|| runs += 1);
+	assert!(once.is_completed());
+	assert_eq!(runs, 1);
+}
+
+#[test_case]
+fn test_once_lock_get_or_init_and_set() {
+	let cell = OnceLock::new();
+	assert_eq!(cell.get(), None);
+
+	assert_eq!(*cell.get_or_init(|| 4), 4);
+	assert_eq!(*cell.get_or_init(|| 5), 4);
+	assert_eq!(cell.set(6), Err(6));
+	assert_eq!(cell.get(), Some(&4));
+
+	let other = OnceLock::new();
+	assert_eq!(other.set(7), Ok(()));
+	assert_eq!(other.get(), Some(&7));
+}
+
+#[test_case]
+fn test_lazy_builds_on_first_use() {
+	static BUILDS: AtomicUsize = AtomicUsize::new(0);
+	static VALUE: Lazy<usize> = Lazy::new(|| {
+		BUILDS.fetch_add(1, Ordering::Relaxed);
+		return 42;
+	});
+
+	assert_eq!(BUILDS.load(Ordering::Relaxed), 0);
+	assert_eq!(*VALUE, 42);
+	assert_eq!(*VALUE + 1, 43);
+	assert_eq!(BUILDS.load(Ordering::Relaxed), 1);
+}
+
+#[test_case]
+fn test_global_before_and_after_init() {
+	let global: Global<u32> = Global::new();
+	assert!(!global.is_initialized());
+	assert!(global.lock().is_none());
+	assert_eq!(global.with(|value| *value), None);
+
+	assert_eq!(global.init(1), Ok(()));
+	assert_eq!(global.init(2), Err(2));
+	assert_eq!(
+		global.with(|value| {
+			*value += 10;
+			return *value;
+		}),
+		Some(11)
+	);
+	assert_eq!(global.lock().map(|value| *value), Some(11));
+	assert_eq!(*global.get_or_init(|| 0).lock(), 11);
+}
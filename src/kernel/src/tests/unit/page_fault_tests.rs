@@ -0,0 +1,93 @@
+use crate::{
+	arch::x86::page_fault::{
+		register_fault_handler, unregister_fault_handler, PageFault,
+	},
+	memory::{
+		allocate_dynamic_virt_range, classify_address,
+		frame::FRAME_ALLOCATOR,
+		free_dynamic_virt_range,
+		paging::{flags, map_page, translate, unmap_page},
+		KernelRegion, VirtAddr, PAGE_SIZE,
+	},
+	sync::Mutex,
+};
+use alloc::format;
+
+static LAST_FAULT: Mutex<Option<PageFault>> = Mutex::new(None);
+
+fn map_on_fault(fault: &PageFault) -> bool {
+	let Some(frame) = FRAME_ALLOCATOR
+		.lock()
+		.and_then(|frames| frames.allocate_frame())
+	else {
+		return false;
+	};
+
+	let page = fault.address.align_down(PAGE_SIZE);
+	map_page(frame, page, flags::PRESENT | flags::WRITABLE);
+	*LAST_FAULT.lock() = Some(*fault);
+	return true;
+}
+
+#[test_case]
+fn test_decode_error_code() {
+	let address = VirtAddr::new(0xd000_1234);
+
+	let fault = PageFault::decode(address, 0b00010);
+	assert!(!fault.present && fault.write && !fault.user);
+	assert!(!fault.reserved && !fault.instruction_fetch);
+	assert_eq!(
+		format!("{}", fault),
+		"write to 0xd0001234 (not present, kernel, dynamic ranges)"
+	);
+
+	let fault = PageFault::decode(VirtAddr::new(0x10), 0b11101);
+	assert!(fault.present && !fault.write && fault.user);
+	assert!(fault.reserved && fault.instruction_fetch);
+	assert_eq!(
+		format!("{}", fault),
+		"fetch from 0x00000010 (protection, user, wild, reserved bit)"
+	);
+}
+
+#[test_case]
+fn test_classify_address() {
+	let text = VirtAddr::new(translate as usize);
+	assert_eq!(classify_address(text), KernelRegion::KernelImage);
+	assert_eq!(
+		classify_address(VirtAddr::new(0xc100_0000)),
+		KernelRegion::NodePool
+	);
+	assert_eq!(
+		classify_address(VirtAddr::new(0xe000_0000)),
+		KernelRegion::HeapWindow
+	);
+	assert_eq!(
+		classify_address(VirtAddr::new(0xf000_0000)),
+		KernelRegion::Wild
+	);
+}
+
+#[test_case]
+fn test_fault_handler_maps_page_on_touch() {
+	let start = allocate_dynamic_virt_range(2 * PAGE_SIZE).unwrap();
+	register_fault_handler(start, 2 * PAGE_SIZE, map_on_fault);
+
+	let target = start + PAGE_SIZE + 8;
+	unsafe { target.as_mut_ptr::<u32>().write_volatile(0x1234_5678) };
+	assert_eq!(
+		unsafe { target.as_ptr::<u32>().read_volatile() },
+		0x1234_5678
+	);
+
+	let fault = LAST_FAULT.lock().take().unwrap();
+	assert_eq!(fault.address, target);
+	assert!(!fault.present && fault.write && !fault.user);
+	assert_eq!(fault.region(), KernelRegion::DynamicRanges);
+
+	// Only the touched page was mapped
+	assert_eq!(translate(start), None);
+	unregister_fault_handler(start);
+	unmap_page(start + PAGE_SIZE);
+	free_dynamic_virt_range(start, 2 * PAGE_SIZE);
+}
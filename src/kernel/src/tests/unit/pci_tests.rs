@@ -0,0 +1,77 @@
+use crate::device::pci::{
+	class_name, devices, find_class, pci_read_config_u32, Bar, PciAddress,
+};
+
+#[test_case]
+fn test_pci_bar_decode_memory() {
+	let bar = Bar::decode(0xfebf_0008, 0xffff_ffff_fff0_0008);
+	assert_eq!(
+		bar,
+		Bar::Memory {
+			base: 0xfebf_0000,
+			size: 0x10_0000,
+			prefetchable: true,
+			wide: false,
+		}
+	);
+
+	let wide = Bar::decode(0x1_e000_000c, 0xffff_fffe_0000_000c);
+	assert_eq!(
+		wide,
+		Bar::Memory {
+			base: 0x1_e000_0000,
+			size: 0x2_0000_0000,
+			prefetchable: true,
+			wide: true,
+		}
+	);
+}
+
+#[test_case]
+fn test_pci_bar_decode_io_and_unused() {
+	assert_eq!(
+		Bar::decode(0xc041, 0xffff_ffff_0000_ffe1),
+		Bar::Io {
+			port: 0xc040,
+			size: 0x20,
+		}
+	);
+	assert_eq!(Bar::decode(0, 0xffff_ffff_0000_0000), Bar::None);
+}
+
+#[test_case]
+fn test_pci_class_names() {
+	assert_eq!(class_name(0x06, 0x00), "host bridge");
+	assert_eq!(class_name(0x01, 0x01), "IDE controller");
+	assert_eq!(class_name(0x01, 0x42), "mass storage controller");
+	assert_eq!(class_name(0xfe, 0x00), "unknown device");
+}
+
+#[test_case]
+fn test_pci_scan_finds_host_bridge() {
+	// QEMU's machines all have the host bridge at 00:00.0
+	let first = devices().first().unwrap();
+	assert_eq!(
+		first.address,
+		PciAddress {
+			bus: 0,
+			device: 0,
+			function: 0,
+		}
+	);
+	assert_eq!(first.class_name(), "host bridge");
+	assert_eq!(find_class(0x06, 0x00), Some(first));
+
+	let id = pci_read_config_u32(0, 0, 0, 0);
+	assert_eq!(id as u16, first.vendor_id);
+	assert_eq!((id >> 16) as u16, first.device_id);
+}
+
+#[test_case]
+fn test_pci_scan_sorted_and_present() {
+	let devices = devices();
+	assert!(devices
+		.windows(2)
+		.all(|pair| pair[0].address < pair[1].address));
+	assert!(devices.iter().all(|device| device.vendor_id != 0xffff));
+}
@@ -1,53 +1,36 @@
-use crate::{println, PIC_1_OFFSET, PIC_2_OFFSET};
+use crate::arch::x86::pic::{clear_mask, get_masks, irq_lines, set_mask};
 
-#[test_case]
-fn test_pic_initialization() {
-	use x86_64::instructions::port::{Port, PortReadOnly, PortWriteOnly};
-
-	// Constants for PIC ports
-	const PIC1_COMMAND: u16 = 0x20;
-	const PIC1_DATA: u16 = 0x21;
-	const PIC2_COMMAND: u16 = 0xa0;
-	const PIC2_DATA: u16 = 0xa1;
-
-	unsafe {
-		// First save the current mask values
-		let mut pic1_data_port = Port::new(PIC1_DATA);
-		let mut pic2_data_port = Port::new(PIC2_DATA);
-		let original_mask1: u8 = pic1_data_port.read();
-		let original_mask2: u8 = pic2_data_port.read();
-
-		// Read the mask registers to verify they've been set properly
-		let mask1: u8 = pic1_data_port.read();
-		let mask2: u8 = pic2_data_port.read();
-
-		// Verify that the masks match expected values
-		// This will depend on your initialization function's behavior
-		// For example, if your init function sets all interrupts to be masked:
-		assert_eq!(
-			mask1, 0xff,
-			"PIC1 mask should be 0xFF after initialization"
-		);
-		assert_eq!(
-			mask2, 0xff,
-			"PIC2 mask should be 0xFF after initialization"
-		);
+/// LPT1, which nothing registers.
+const UNUSED_IRQ: u8 = 7;
 
-		// Or, if you're expecting specific values:
-		// assert_eq!(mask1, expected_mask1, "PIC1 mask doesn't match expected
-		// value"); assert_eq!(mask2, expected_mask2, "PIC2 mask doesn't match
-		// expected value");
-
-		// Test if we can modify the mask and read it back
-		let test_mask1 = 0xfe; // All masked except IRQ0
-		pic1_data_port.write(test_mask1);
-		let read_mask1: u8 = pic1_data_port.read();
-		assert_eq!(read_mask1, test_mask1, "PIC1 mask wasn't properly updated");
+#[test_case]
+fn test_pic_set_and_clear_mask() {
+	let original = get_masks();
+
+	set_mask(UNUSED_IRQ);
+	assert_eq!(get_masks().0 & (1 << UNUSED_IRQ), 1 << UNUSED_IRQ);
+	// The other lines keep their state
+	assert_eq!(
+		get_masks().0 | (1 << UNUSED_IRQ),
+		original.0 | (1 << UNUSED_IRQ)
+	);
+	assert_eq!(get_masks().1, original.1);
+
+	// Masked, so nothing arrives on the line while it is clear
+	crate::arch::x86::cpu::without_interrupts(|| {
+		clear_mask(UNUSED_IRQ);
+		assert_eq!(get_masks().0 & (1 << UNUSED_IRQ), 0);
+		set_mask(UNUSED_IRQ);
+	});
+	assert!(irq_lines()[UNUSED_IRQ as usize].masked);
+}
 
-		// Restore original masks
-		pic1_data_port.write(original_mask1);
-		pic2_data_port.write(original_mask2);
-	}
+#[test_case]
+fn test_pic_registered_lines() {
+	let lines = irq_lines();
 
-	println!("PIC initialization test passed!");
+	// The timer is registered before the tests run
+	assert_eq!(lines[0].handler, Some("timer"));
+	assert!(!lines[0].masked);
+	assert_eq!(lines[UNUSED_IRQ as usize].handler, None);
 }
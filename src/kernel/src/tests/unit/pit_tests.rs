@@ -0,0 +1,50 @@
+use crate::device::pit;
+use core::hint;
+
+/// Spins far longer than a tick at the lowest rate takes on any machine.
+const SPIN_LIMIT: u32 = 500_000_000;
+
+// Helper busy-waiting until the tick counter passes `ticks`
+fn wait_past(ticks: u64) -> bool {
+	for _ in 0..SPIN_LIMIT {
+		if pit::ticks() > ticks {
+			return true;
+		}
+		hint::spin_loop();
+	}
+
+	return false;
+}
+
+#[test_case]
+fn test_pit_ticks_advance() {
+	let start = pit::ticks();
+
+	assert!(wait_past(start + 2));
+	assert!(pit::uptime_ms() > 0);
+}
+
+#[test_case]
+fn test_pit_clamp_frequency() {
+	assert_eq!(pit::clamp_frequency(0), pit::MIN_FREQUENCY_HZ);
+	assert_eq!(pit::clamp_frequency(u32::MAX), pit::MAX_FREQUENCY_HZ);
+	assert_eq!(pit::clamp_frequency(100), 100);
+
+	// Mode 3 does not take a divisor of 1
+	assert!(pit::MAX_FREQUENCY_HZ <= 1_193_182 / 2);
+}
+
+#[test_case]
+fn test_pit_set_frequency_keeps_uptime() {
+	assert_eq!(pit::set_frequency(100), 100);
+
+	let before = pit::uptime_ms();
+	assert!(wait_past(pit::ticks()));
+	assert!(pit::uptime_ms() >= before);
+
+	assert_eq!(
+		pit::set_frequency(pit::DEFAULT_FREQUENCY_HZ),
+		pit::DEFAULT_FREQUENCY_HZ
+	);
+	assert!(pit::uptime_ms() >= before);
+}
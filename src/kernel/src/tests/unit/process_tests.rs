@@ -0,0 +1,91 @@
+use crate::{
+	memory::frame::FRAME_ALLOCATOR,
+	sync::PreemptGuard,
+	task::{
+		process::{self, Pid},
+		thread::{self, ThreadState, STACK_SIZE},
+	},
+	time,
+};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+static SEEN_PID: AtomicU32 = AtomicU32::new(0);
+
+fn nothing() {}
+
+fn record_pid_and_sleep() {
+	SEEN_PID.store(process::current_pid().0, Ordering::Relaxed);
+	time::sleep_ms(50);
+}
+
+fn free_frames() -> usize {
+	return FRAME_ALLOCATOR
+		.lock()
+		.map_or(0, |frames| frames.stats().free_frames);
+}
+
+#[test_case]
+fn test_process_is_listed_until_it_exits() {
+	// Not run before the checks
+	let no_preempt = PreemptGuard::new();
+	let pid = process::create_kernel_process("listed", nothing).unwrap();
+	let id = process::main_thread(pid).unwrap();
+
+	let listed = process::list();
+	let info = listed.iter().find(|info| info.pid == pid).unwrap();
+	assert_eq!(info.name, "listed");
+	assert_eq!(info.state, ThreadState::Ready);
+	assert_ne!(pid, Pid(0));
+	drop(no_preempt);
+
+	thread::join(id);
+	assert!(process::list().iter().all(|info| info.pid != pid));
+	assert_eq!(process::main_thread(pid), None);
+}
+
+#[test_case]
+fn test_pid_of_an_exited_process_is_reused() {
+	let first = process::create_kernel_process("first", nothing).unwrap();
+	thread::join(process::main_thread(first).unwrap());
+
+	let second = process::create_kernel_process("second", nothing).unwrap();
+	assert_eq!(second, first);
+	thread::join(process::main_thread(second).unwrap());
+}
+
+#[test_case]
+fn test_thread_outside_of_a_process_has_pid_0() {
+	assert_eq!(process::current_pid(), Pid(0));
+}
+
+#[test_case]
+fn test_process_sees_its_pid_and_stack_use() {
+	SEEN_PID.store(0, Ordering::Relaxed);
+	let pid = process::create_kernel_process("sleeper", record_pid_and_sleep)
+		.unwrap();
+	let id = process::main_thread(pid).unwrap();
+
+	time::sleep_ms(10);
+	assert_eq!(SEEN_PID.load(Ordering::Relaxed), pid.0);
+
+	let listed = process::list();
+	let info = listed.iter().find(|info| info.pid == pid).unwrap();
+	assert_eq!(info.state, ThreadState::Sleeping);
+	let used = info.stack_used.unwrap();
+	assert!(used > 0 && used < STACK_SIZE);
+
+	thread::join(id);
+}
+
+#[test_case]
+fn test_exit_frees_the_address_space() {
+	// The first round may grow the caches the table and threads use
+	let warm = process::create_kernel_process("warm", nothing).unwrap();
+	thread::join(process::main_thread(warm).unwrap());
+
+	let before = free_frames();
+	let pid = process::create_kernel_process("freed", nothing).unwrap();
+	thread::join(process::main_thread(pid).unwrap());
+
+	assert_eq!(free_frames(), before);
+}
@@ -0,0 +1,91 @@
+use crate::fs::ramfs::{Ramfs, RamfsError, BLOCK_SIZE};
+use alloc::{format, vec::Vec};
+
+// Helper appending a ustar header and the padded data of a file to `tar`
+fn push_file(tar: &mut Vec<u8>, name: &str, typeflag: u8, data: &[u8]) {
+	let mut header = [0u8; BLOCK_SIZE];
+	header[..name.len()].copy_from_slice(name.as_bytes());
+	header[100..108].copy_from_slice(b"0000644\0");
+	let size = format!("{:011o}\0", data.len());
+	header[124..136].copy_from_slice(size.as_bytes());
+	header[156] = typeflag;
+	header[257..263].copy_from_slice(b"ustar\0");
+	header[263..265].copy_from_slice(b"00");
+
+	header[148..156].fill(b' ');
+	let sum: usize = header.iter().map(|&byte| byte as usize).sum();
+	let checksum = format!("{:06o}\0 ", sum);
+	header[148..156].copy_from_slice(checksum.as_bytes());
+
+	tar.extend_from_slice(&header);
+	tar.extend_from_slice(data);
+	tar.resize(tar.len().div_ceil(BLOCK_SIZE) * BLOCK_SIZE, 0);
+}
+
+// Helper building a small archive with a directory and two files
+fn sample_tar() -> Vec<u8> {
+	let mut tar = Vec::new();
+	push_file(&mut tar, "./etc/", b'5', b"");
+	push_file(&mut tar, "./etc/motd", b'0', b"hello from the initrd\n");
+	push_file(&mut tar, "bin/init", b'0', &[0x7f; 600]);
+	tar.resize(tar.len() + 2 * BLOCK_SIZE, 0);
+	return tar;
+}
+
+#[test_case]
+fn test_ramfs_lists_regular_files() {
+	let tar = sample_tar();
+	let ramfs = Ramfs::parse(&tar).unwrap();
+
+	assert_eq!(ramfs.len(), 2);
+	let entries = ramfs.entries();
+	assert_eq!(entries[0].name, "etc/motd");
+	assert_eq!(entries[0].size(), 22);
+	assert_eq!(entries[0].offset, 2 * BLOCK_SIZE);
+	assert_eq!(entries[1].name, "bin/init");
+	assert_eq!(entries[1].size(), 600);
+	assert_eq!(entries[1].offset, 4 * BLOCK_SIZE);
+}
+
+#[test_case]
+fn test_ramfs_open() {
+	let tar = sample_tar();
+	let ramfs = Ramfs::parse(&tar).unwrap();
+
+	assert_eq!(
+		ramfs.open("etc/motd"),
+		Some(&b"hello from the initrd\n"[..])
+	);
+	assert_eq!(ramfs.open("/bin/init").map(<[u8]>::len), Some(600));
+	assert_eq!(ramfs.open("etc"), None);
+	assert_eq!(ramfs.open("missing"), None);
+}
+
+#[test_case]
+fn test_ramfs_rejects_bad_headers() {
+	let mut tar = sample_tar();
+	tar[BLOCK_SIZE] ^= 1;
+	assert_eq!(
+		Ramfs::parse(&tar).unwrap_err(),
+		RamfsError::BadChecksum(BLOCK_SIZE)
+	);
+
+	let tar = sample_tar();
+	assert_eq!(
+		Ramfs::parse(&tar[..4 * BLOCK_SIZE + 100]).unwrap_err(),
+		RamfsError::Truncated(3 * BLOCK_SIZE)
+	);
+
+	let mut tar = sample_tar();
+	tar[257] = b'x';
+	assert_eq!(Ramfs::parse(&tar).unwrap_err(), RamfsError::BadMagic(0));
+}
+
+#[test_case]
+fn test_ramfs_empty_archive() {
+	let ramfs = Ramfs::parse(&[0; 2 * BLOCK_SIZE]).unwrap();
+	assert!(ramfs.is_empty());
+
+	let ramfs = Ramfs::parse(&[]).unwrap();
+	assert!(ramfs.is_empty());
+}
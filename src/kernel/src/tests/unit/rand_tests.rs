@@ -0,0 +1,101 @@
+use crate::util::rand::{self, Rng};
+
+#[test_case]
+fn test_rng_is_deterministic() {
+	let mut a = Rng::new(42);
+	let mut b = Rng::new(42);
+
+	for _ in 0..16 {
+		assert_eq!(a.next_u64(), b.next_u64());
+	}
+}
+
+#[test_case]
+fn test_rng_zero_seed_is_not_stuck() {
+	let mut rng = Rng::new(0);
+
+	assert_ne!(rng.next_u64(), 0);
+}
+
+#[test_case]
+fn test_rng_range_is_inclusive() {
+	let mut rng = Rng::new(7);
+	let mut seen = [false; 4];
+
+	for _ in 0..256 {
+		let n = rng.range(3, 6);
+		assert!((3..=6).contains(&n));
+		seen[n - 3] = true;
+	}
+
+	assert!(seen.iter().all(|&seen| seen));
+	assert_eq!(rng.range(5, 5), 5);
+}
+
+#[test_case]
+fn test_rng_shuffle_keeps_items() {
+	let mut rng = Rng::new(1);
+	let mut items = [0, 1, 2, 3, 4, 5, 6, 7];
+
+	rng.shuffle(&mut items);
+	items.sort_unstable();
+
+	assert_eq!(items, [0, 1, 2, 3, 4, 5, 6, 7]);
+}
+
+#[test_case]
+fn test_rng_fill_bytes_matches_next_u64() {
+	let mut a = Rng::new(9);
+	let mut b = Rng::new(9);
+	let mut bytes = [0; 13];
+
+	a.fill_bytes(&mut bytes);
+
+	assert_eq!(bytes[..8], b.next_u64().to_le_bytes());
+	assert_eq!(bytes[8..], b.next_u64().to_le_bytes()[..5]);
+}
+
+#[test_case]
+fn test_hardware_seed_changes() {
+	// The time stamp counter moves on between the calls
+	assert_ne!(rand::hardware_seed(), rand::hardware_seed());
+}
+
+#[test_case]
+fn test_rand_u32_is_not_constant() {
+	let first = rand::rand_u32();
+
+	assert!((0..16).any(|_| rand::rand_u32() != first));
+}
+
+#[test_case]
+fn test_fill_bytes_monobit() {
+	let mut bytes = [0u8; 4096];
+	rand::fill_bytes(&mut bytes);
+
+	// 32768 bits, a standard deviation of about 91 ones
+	let ones: u32 = bytes.iter().map(|byte| byte.count_ones()).sum();
+	assert!(ones.abs_diff(16384) < 600, "{} ones", ones);
+}
+
+#[test_case]
+fn test_rand_range_chi_square() {
+	const BUCKETS: usize = 16;
+	const DRAWS: usize = 3200;
+	let mut counts = [0usize; BUCKETS];
+
+	for _ in 0..DRAWS {
+		let n = rand::rand_range(10, 10 + BUCKETS - 1);
+		assert!((10..10 + BUCKETS).contains(&n));
+		counts[n - 10] += 1;
+	}
+
+	// 15 degrees of freedom reach 37.7 once in a thousand runs
+	let expected = DRAWS / BUCKETS;
+	let chi_square: usize = counts
+		.iter()
+		.map(|&count| count.abs_diff(expected).pow(2))
+		.sum::<usize>()
+		/ expected;
+	assert!(chi_square < 60, "chi square {}", chi_square);
+}
@@ -0,0 +1,129 @@
+use crate::collections::range_map::{Range, RangeMap, RangeMapError};
+use alloc::vec::Vec;
+
+// Helper listing the ranges as (start, len, tag)
+fn entries(map: &RangeMap<char>) -> Vec<(usize, usize, char)> {
+	return map.iter().map(|r| (r.start, r.len, r.tag)).collect();
+}
+
+#[test_case]
+fn test_range_map_inserts_in_address_order() {
+	let mut map = RangeMap::new();
+	assert!(map.is_empty());
+
+	assert_eq!(map.insert(0x3000, 0x1000, 'c'), Ok(()));
+	assert_eq!(map.insert(0x1000, 0x1000, 'a'), Ok(()));
+	assert_eq!(map.insert(0x8000, 0x2000, 'd'), Ok(()));
+	// Touching both neighbours is fine
+	assert_eq!(map.insert(0x2000, 0x1000, 'b'), Ok(()));
+
+	assert_eq!(map.len(), 4);
+	assert_eq!(
+		entries(&map),
+		[
+			(0x1000, 0x1000, 'a'),
+			(0x2000, 0x1000, 'b'),
+			(0x3000, 0x1000, 'c'),
+			(0x8000, 0x2000, 'd'),
+		]
+	);
+	assert_eq!((&map).into_iter().count(), 4);
+}
+
+#[test_case]
+fn test_range_map_rejects_overlaps() {
+	let mut map = RangeMap::new();
+	assert_eq!(map.insert(0x2000, 0x2000, 'a'), Ok(()));
+
+	// Over the start, the end, inside and around it
+	assert_eq!(map.insert(0x1000, 0x1001, 'x'), Err(RangeMapError::Overlap));
+	assert_eq!(map.insert(0x3fff, 0x10, 'x'), Err(RangeMapError::Overlap));
+	assert_eq!(map.insert(0x2800, 0x100, 'x'), Err(RangeMapError::Overlap));
+	assert_eq!(map.insert(0x1000, 0x4000, 'x'), Err(RangeMapError::Overlap));
+	assert_eq!(map.insert(0x2000, 0x2000, 'x'), Err(RangeMapError::Overlap));
+
+	assert_eq!(map.insert(0x5000, 0, 'x'), Err(RangeMapError::Empty));
+	assert_eq!(
+		map.insert(usize::MAX - 0xfff, 0x1000, 'x'),
+		Err(RangeMapError::Overflow)
+	);
+	assert_eq!(entries(&map), [(0x2000, 0x2000, 'a')]);
+}
+
+#[test_case]
+fn test_range_map_find_and_remove() {
+	let mut map = RangeMap::new();
+	let _ = map.insert(0x1000, 0x1000, 'a');
+	let _ = map.insert(0x4000, 0x2000, 'b');
+
+	assert_eq!(map.find(0x1000).map(|r| r.tag), Some('a'));
+	assert_eq!(map.find(0x1fff).map(|r| r.tag), Some('a'));
+	assert_eq!(map.find(0x5fff).map(|r| r.tag), Some('b'));
+	assert!(map.find(0x2000).is_none());
+	assert!(map.find(0x6000).is_none());
+	assert!(map.find(0).is_none());
+
+	if let Some(range) = map.find_mut(0x4800) {
+		range.tag = 'c';
+	}
+
+	// Only by start address
+	assert_eq!(map.remove(0x4800), None);
+	assert_eq!(
+		map.remove(0x4000),
+		Some(Range {
+			start: 0x4000,
+			len: 0x2000,
+			tag: 'c'
+		})
+	);
+	assert!(map.find(0x4800).is_none());
+	assert_eq!(map.len(), 1);
+}
+
+#[test_case]
+fn test_range_map_gap_at_start_middle_and_end() {
+	let mut map = RangeMap::new();
+	let window = 0x10000..0x20000;
+	assert_eq!(map.find_gap(0x10000, window.clone()), Some(0x10000));
+	assert_eq!(map.find_gap(0x10001, window.clone()), None);
+
+	let _ = map.insert(0x11000, 0x1000, 'a');
+	let _ = map.insert(0x14000, 0x4000, 'b');
+
+	// Start, then the middle once the start is too small, then the end
+	assert_eq!(map.find_gap(0x1000, window.clone()), Some(0x10000));
+	assert_eq!(map.find_gap(0x2000, window.clone()), Some(0x12000));
+	assert_eq!(map.find_gap(0x3000, window.clone()), Some(0x18000));
+	assert_eq!(map.find_gap(0x8000, window.clone()), Some(0x18000));
+	assert_eq!(map.find_gap(0x8001, window.clone()), None);
+	assert_eq!(map.find_gap(0, window.clone()), None);
+
+	let gaps: Vec<_> = map.gaps(window).collect();
+	assert_eq!(gaps, [0x10000..0x11000, 0x12000..0x14000, 0x18000..0x20000]);
+}
+
+#[test_case]
+fn test_range_map_gap_within_and_aligned() {
+	let mut map = RangeMap::new();
+	let _ = map.insert(0x1000, 0x1000, 'a');
+	let _ = map.insert(0x3000, 0x3000, 'b');
+
+	// Ranges sticking out of the window cut it down
+	assert_eq!(map.find_gap(0x1000, 0x1800..0x8000), Some(0x2000));
+	assert_eq!(map.find_gap(0x2000, 0x1800..0x8000), Some(0x6000));
+	assert_eq!(map.find_gap(0x1000, 0x4000..0x6000), None);
+	assert_eq!(map.find_gap(0x1000, 0x4000..0x7000), Some(0x6000));
+	let gaps: Vec<_> = map.gaps(0x5000..0x6800).collect();
+	assert_eq!(gaps, [0x6000..0x6800]);
+
+	assert_eq!(map.find_gap_aligned(0x1000, 0x4000, 0..0x10000), Some(0));
+	assert_eq!(
+		map.find_gap_aligned(0x1000, 0x4000, 0x1000..0x10000),
+		Some(0x8000)
+	);
+	assert_eq!(
+		map.find_gap_aligned(0x1000, 0x2000, 0x1000..0x10000),
+		Some(0x2000)
+	);
+}
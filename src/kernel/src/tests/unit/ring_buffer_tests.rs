@@ -0,0 +1,83 @@
+use crate::collections::ring_buffer::SpscRing;
+
+#[test_case]
+fn test_spsc_ring_full_and_empty() {
+	let ring: SpscRing<u8, 4> = SpscRing::new();
+	assert!(ring.is_empty());
+	assert_eq!(ring.capacity(), 4);
+	assert_eq!(ring.pop(), None);
+
+	for byte in 0..4 {
+		assert!(ring.push(byte));
+	}
+	assert!(ring.is_full());
+	assert_eq!(ring.len(), 4);
+
+	// A full ring drops what arrives and keeps what it has
+	assert!(!ring.push(9));
+	for byte in 0..4 {
+		assert_eq!(ring.pop(), Some(byte));
+	}
+	assert!(ring.is_empty());
+	assert_eq!(ring.pop(), None);
+}
+
+#[test_case]
+fn test_spsc_ring_wraps_around() {
+	let ring: SpscRing<u32, 8> = SpscRing::new();
+
+	// Enough rounds to pass the end of the slots many times over
+	for round in 0..100 {
+		for i in 0..5 {
+			assert!(ring.push(round * 5 + i));
+		}
+		assert_eq!(ring.len(), 5);
+		for i in 0..5 {
+			assert_eq!(ring.pop(), Some(round * 5 + i));
+		}
+	}
+	assert!(ring.is_empty());
+}
+
+#[test_case]
+fn test_spsc_ring_interleaved() {
+	static RING: SpscRing<u32, 16> = SpscRing::new();
+	let mut next_push = 0;
+	let mut next_pop = 0;
+	let mut seed: u32 = 0x1234_5678;
+
+	// Bursts of pushes and pops of varying size, like an IRQ firing
+	// between reads
+	for _ in 0..2000 {
+		seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+		let burst = (seed >> 16) % 20;
+
+		if seed & 1 == 0 {
+			for _ in 0..burst {
+				if !RING.push(next_push) {
+					assert!(RING.is_full());
+					break;
+				}
+				next_push += 1;
+			}
+		} else {
+			for _ in 0..burst {
+				match RING.pop() {
+					Some(value) => {
+						assert_eq!(value, next_pop);
+						next_pop += 1;
+					}
+					None => break,
+				}
+			}
+		}
+
+		assert_eq!(RING.len() as u32, next_push - next_pop);
+	}
+
+	while let Some(value) = RING.pop() {
+		assert_eq!(value, next_pop);
+		next_pop += 1;
+	}
+	assert_eq!(next_pop, next_push);
+}
@@ -0,0 +1,87 @@
+use crate::sync::RwLock;
+use alloc::vec::Vec;
+
+#[test_case]
+fn test_rwlock_readers_share() {
+	let lock = RwLock::new(5);
+
+	let first = lock.read();
+	let second = lock.read();
+	assert_eq!(*first + *second, 10);
+	assert_eq!(lock.readers(), 2);
+	assert!(lock.try_write().is_none());
+
+	drop(first);
+	assert!(lock.try_write().is_none());
+	drop(second);
+	assert_eq!(lock.readers(), 0);
+
+	*lock.write() += 1;
+	assert_eq!(*lock.read(), 6);
+}
+
+#[test_case]
+fn test_rwlock_writer_excludes() {
+	let lock = RwLock::new(0);
+
+	let mut writer = lock.write();
+	assert!(lock.is_write_locked());
+	assert!(lock.try_read().is_none());
+	assert!(lock.try_write().is_none());
+	*writer = 7;
+	drop(writer);
+
+	assert!(!lock.is_write_locked());
+	assert_eq!(lock.try_read().map(|value| *value), Some(7));
+	assert!(lock.try_write().is_some());
+}
+
+#[test_case]
+fn test_rwlock_waiting_writer_keeps_readers_out() {
+	let lock = RwLock::new(0);
+	let reader = lock.read();
+
+	// A writer spinning in `write` while the reader is in
+	assert!(lock.try_write().is_none());
+	lock.mark_writer_waiting();
+	assert!(lock.try_read().is_none());
+	assert_eq!(lock.readers(), 1);
+
+	// Once the last reader leaves, the writer gets in before new readers
+	drop(reader);
+	assert!(lock.try_read().is_none());
+	let writer = lock.try_write();
+	assert!(writer.is_some());
+	drop(writer);
+	assert!(lock.try_read().is_some());
+}
+
+#[test_case]
+fn test_rwlock_interleaved() {
+	let lock = RwLock::new(0u32);
+	let mut seed: u32 = 0x9e37_79b9;
+	let mut readers = Vec::new();
+	let mut expected = 0;
+
+	// Random mixes of taking and dropping read guards with writes whenever
+	// none are held
+	for _ in 0..1000 {
+		seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+
+		match (seed >> 16) % 3 {
+			0 => readers.push(lock.read()),
+			1 => drop(readers.pop()),
+			_ => match lock.try_write() {
+				Some(mut value) => {
+					assert!(readers.is_empty());
+					*value += 1;
+					expected += 1;
+				}
+				None => assert!(!readers.is_empty()),
+			},
+		}
+
+		assert_eq!(lock.readers(), readers.len());
+		assert!(readers.iter().all(|value| **value == expected));
+	}
+}
@@ -0,0 +1,35 @@
+use crate::{
+	arch::x86::cpu::without_interrupts,
+	device::{pit, speaker},
+	time,
+};
+
+#[test_case]
+fn test_speaker_play_and_stop() {
+	assert_eq!(speaker::play(1000), 1000);
+	assert!(speaker::is_playing());
+	speaker::stop();
+	assert!(!speaker::is_playing());
+
+	assert_eq!(speaker::play(0), pit::MIN_FREQUENCY_HZ);
+	assert_eq!(speaker::play(u32::MAX), pit::MAX_SPEAKER_FREQUENCY_HZ);
+	speaker::stop();
+}
+
+#[test_case]
+fn test_speaker_beep_lasts() {
+	let start = time::uptime_ms();
+	speaker::beep(440, 20);
+
+	assert!(time::uptime_ms() - start >= 20);
+	assert!(!speaker::is_playing());
+}
+
+#[test_case]
+fn test_speaker_beep_without_interrupts() {
+	assert!(speaker::spins_per_ms() > 0);
+
+	// The ticks stand still, so this spins
+	without_interrupts(|| speaker::beep(440, 5));
+	assert!(!speaker::is_playing());
+}
@@ -0,0 +1,44 @@
+use crate::{
+	memory::{get_kernel_text, VirtAddr},
+	println_serial,
+	symbols::{self, Symbolized},
+};
+use alloc::format;
+
+#[test_case]
+fn test_symbols_outside_text_are_unknown() {
+	let text = get_kernel_text();
+
+	assert_eq!(symbols::resolve(VirtAddr::new(0)), None);
+	assert_eq!(symbols::resolve(text.end), None);
+	assert_eq!(
+		format!("{}", Symbolized(VirtAddr::new(0x1000))),
+		"0x00001000"
+	);
+}
+
+#[inline(never)]
+fn named_function() -> usize {
+	return core::hint::black_box(1);
+}
+
+#[test_case]
+fn test_symbols_resolve_function() {
+	// `make test` writes a listing of this binary, a build without one only
+	// passes when it says so with FERRITE_NO_SYMBOLS
+	assert!(!symbols::is_stale(), "stale symbol listing, run make test");
+	if symbols::count() == 0 && option_env!("FERRITE_NO_SYMBOLS").is_some() {
+		println_serial!("[skipped: no symbol listing, FERRITE_NO_SYMBOLS set]");
+		return;
+	}
+	assert!(symbols::count() > 0, "no symbol listing, run make test");
+
+	let here = VirtAddr::new(named_function as usize);
+	let (name, offset) = symbols::resolve(here).unwrap_or(("", 1));
+	assert!(name.ends_with("named_function"));
+	assert_eq!(offset, 0);
+
+	let (_, offset) =
+		symbols::resolve(VirtAddr::new(here.as_usize() + 4)).unwrap_or(("", 0));
+	assert_eq!(offset, 4);
+}
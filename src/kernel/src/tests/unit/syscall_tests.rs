@@ -0,0 +1,63 @@
+use crate::syscall::{Errno, SYS_EXIT, SYS_GETPID, SYS_WRITE};
+use core::arch::asm;
+
+// Helper making a system call from ring 0. LLVM keeps EBX for itself, so
+// the first argument goes through another register
+fn syscall(number: u32, args: [u32; 3]) -> u32 {
+	let result: u32;
+	unsafe {
+		asm!(
+			"xchg {arg0}, ebx",
+			"int 0x80",
+			"xchg {arg0}, ebx",
+			arg0 = inout(reg) args[0] => _,
+			inout("eax") number => result,
+			in("ecx") args[1],
+			in("edx") args[2],
+		);
+	}
+
+	return result;
+}
+
+#[test_case]
+fn test_syscall_getpid() {
+	assert_eq!(syscall(SYS_GETPID, [0; 3]), 0);
+}
+
+#[test_case]
+fn test_syscall_unknown_number() {
+	assert_eq!(syscall(999, [0; 3]), Errno::NoSys.as_return());
+	assert_eq!(syscall(0, [0; 3]), Errno::NoSys.as_return());
+	assert_eq!(Errno::NoSys.as_return() as i32, -38);
+}
+
+#[test_case]
+fn test_syscall_write() {
+	let message = b"syscall write\n";
+	let buf = message.as_ptr() as u32;
+	let len = message.len() as u32;
+
+	assert_eq!(syscall(SYS_WRITE, [1, buf, len]), len);
+	assert_eq!(syscall(SYS_WRITE, [1, buf, 0]), 0);
+	assert_eq!(syscall(SYS_WRITE, [3, buf, len]), Errno::BadFd.as_return());
+}
+
+#[test_case]
+fn test_syscall_write_checks_buffer() {
+	// Not mapped, in the user half
+	assert_eq!(
+		syscall(SYS_WRITE, [1, 0x1000_0000, 16]),
+		Errno::Fault.as_return()
+	);
+	// Wraps around the address space
+	assert_eq!(
+		syscall(SYS_WRITE, [1, 0xffff_fff0, 32]),
+		Errno::Fault.as_return()
+	);
+}
+
+#[test_case]
+fn test_syscall_exit_without_user_code() {
+	assert_eq!(syscall(SYS_EXIT, [0; 3]), Errno::Srch.as_return());
+}
@@ -0,0 +1,209 @@
+use crate::{
+	device::pit,
+	memory::allocator::BUDDY_PAGE_ALLOCATOR,
+	sync::{Mutex, PreemptGuard},
+	task::{
+		self, scheduler,
+		thread::{self, ThreadId},
+		workqueue,
+	},
+	time,
+};
+use alloc::vec::Vec;
+use core::{
+	hint,
+	sync::atomic::{AtomicBool, AtomicU32, AtomicUsize, Ordering},
+};
+
+static RAN: AtomicBool = AtomicBool::new(false);
+static TURNS: Mutex<Vec<char>> = Mutex::new(Vec::new());
+static EXITED_EARLY: AtomicUsize = AtomicUsize::new(0);
+static STOP_SPINNING: AtomicBool = AtomicBool::new(false);
+static SPINS: [AtomicU32; 2] = [const { AtomicU32::new(0) }; 2];
+static WAKE_ORDER: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+fn set_ran() {
+	RAN.store(true, Ordering::Relaxed);
+}
+
+// Helper recording three turns of `name`, yielding after each
+fn take_turns(name: char) {
+	for _ in 0..3 {
+		TURNS.lock().push(name);
+		task::yield_now();
+	}
+}
+
+fn turns_a() {
+	take_turns('a');
+}
+
+fn turns_b() {
+	take_turns('b');
+}
+
+fn exit_early() {
+	EXITED_EARLY.fetch_add(1, Ordering::Relaxed);
+	thread::exit();
+}
+
+// Helper counting loop rounds until the test says stop, never yielding
+fn spin(counter: &AtomicU32) {
+	while !STOP_SPINNING.load(Ordering::Relaxed) {
+		counter.fetch_add(1, Ordering::Relaxed);
+		hint::spin_loop();
+	}
+}
+
+fn spin_a() {
+	spin(&SPINS[0]);
+}
+
+fn spin_b() {
+	spin(&SPINS[1]);
+}
+
+// Helper sleeping `ms` and recording it once woken
+fn sleep_and_record(ms: u64) {
+	time::sleep_ms(ms);
+	WAKE_ORDER.lock().push(ms);
+}
+
+fn sleep_30() {
+	sleep_and_record(30);
+}
+
+fn sleep_10() {
+	sleep_and_record(10);
+}
+
+// Helper counting the threads `task::init` spawned that stay alive
+fn background_threads() -> usize {
+	return match workqueue::worker_id() {
+		Some(_) => 1,
+		None => 0,
+	};
+}
+
+fn free_bytes() -> usize {
+	return BUDDY_PAGE_ALLOCATOR
+		.lock()
+		.map_or(0, |buddy| buddy.stats().free_bytes);
+}
+
+#[test_case]
+fn test_yield_without_threads_returns() {
+	let before = thread::current();
+	task::yield_now();
+
+	assert_eq!(thread::current(), before);
+	assert_eq!(thread::count(), 1 + background_threads());
+}
+
+#[test_case]
+fn test_spawned_thread_runs_on_join() {
+	RAN.store(false, Ordering::Relaxed);
+	// No tick may run it before the checks
+	let no_preempt = PreemptGuard::new();
+	let id = thread::spawn(set_ran).unwrap();
+
+	assert!(!RAN.load(Ordering::Relaxed));
+	assert!(thread::is_alive(id));
+	assert_ne!(id, thread::current());
+	drop(no_preempt);
+
+	thread::join(id);
+	assert!(RAN.load(Ordering::Relaxed));
+	assert!(!thread::is_alive(id));
+}
+
+#[test_case]
+fn test_threads_take_turns() {
+	TURNS.lock().clear();
+	let a = thread::spawn(turns_a).unwrap();
+	let b = thread::spawn(turns_b).unwrap();
+
+	thread::join(a);
+	thread::join(b);
+	assert_eq!(*TURNS.lock(), ['a', 'b', 'a', 'b', 'a', 'b']);
+}
+
+#[test_case]
+fn test_ids_count_up() {
+	let first = thread::spawn(set_ran).unwrap();
+	let second = thread::spawn(set_ran).unwrap();
+
+	assert!(second > first);
+	assert!(first > ThreadId(0));
+	thread::join(second);
+	assert!(!thread::is_alive(first));
+}
+
+#[test_case]
+fn test_exit_frees_the_stack() {
+	// The first round may grow the slab caches the queue nodes come from
+	thread::join(thread::spawn(exit_early).unwrap());
+
+	let before = free_bytes();
+	let id = thread::spawn(exit_early).unwrap();
+	assert!(free_bytes() < before);
+
+	thread::join(id);
+	assert_eq!(free_bytes(), before);
+	assert_eq!(EXITED_EARLY.load(Ordering::Relaxed), 2);
+	assert_eq!(thread::count(), 1 + background_threads());
+}
+
+#[test_case]
+fn test_timer_preempts_compute_loops() {
+	STOP_SPINNING.store(false, Ordering::Relaxed);
+	for counter in &SPINS {
+		counter.store(0, Ordering::Relaxed);
+	}
+	let a = thread::spawn(spin_a).unwrap();
+	let b = thread::spawn(spin_b).unwrap();
+
+	// Neither thread nor this loop yields, only the timer switches
+	let deadline = pit::uptime_ms() + 1000;
+	while SPINS
+		.iter()
+		.any(|counter| counter.load(Ordering::Relaxed) == 0)
+		&& pit::uptime_ms() < deadline
+	{
+		hint::spin_loop();
+	}
+	STOP_SPINNING.store(true, Ordering::Relaxed);
+
+	thread::join(a);
+	thread::join(b);
+	assert!(SPINS[0].load(Ordering::Relaxed) > 0);
+	assert!(SPINS[1].load(Ordering::Relaxed) > 0);
+}
+
+#[test_case]
+fn test_sleep_ms_waits_long_enough() {
+	let start = time::uptime_ms();
+	time::sleep_ms(20);
+
+	assert!(time::uptime_ms() - start >= 20);
+}
+
+#[test_case]
+fn test_sleepers_wake_shortest_first() {
+	WAKE_ORDER.lock().clear();
+	let long = thread::spawn(sleep_30).unwrap();
+	let short = thread::spawn(sleep_10).unwrap();
+
+	thread::join(long);
+	thread::join(short);
+	assert_eq!(*WAKE_ORDER.lock(), [10, 30]);
+}
+
+#[test_case]
+fn test_idle_thread_runs_while_all_sleep() {
+	let before = scheduler::idle_ticks();
+	time::sleep_ms(20);
+
+	assert!(scheduler::idle_ticks() > before);
+	assert_eq!(thread::count(), 1 + background_threads());
+}
@@ -1,8 +1,45 @@
 use crate::{
+	libc::console::console::Console,
 	println, println_serial,
-	tty::{tty::WRITER, Buffer, ColourCode, VgaColour, VGA_HEIGHT, VGA_WIDTH},
+	sync::Mutex,
+	tty::{
+		cp437,
+		font::{glyph_8x16, glyph_8x8, FONT_8X16, FONT_8X8},
+		output::{self, ConsoleOutput},
+		pager,
+		tty::{Writer, WRITER},
+		Buffer, ColourCode, VgaChar, VgaColour, MAX_VGA_HEIGHT, VGA_HEIGHT,
+		VGA_WIDTH,
+	},
+};
+use core::{
+	ptr::addr_of_mut,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+const BLANK: VgaChar = VgaChar {
+	ascii_character: b' ',
+	colour_code: ColourCode::new(VgaColour::LightGrey, VgaColour::Black),
+};
+
+static mut FAKE_BUFFER: Buffer = Buffer {
+	chars: [[BLANK; VGA_WIDTH]; MAX_VGA_HEIGHT],
 };
 
+/// A writer drawing into an off-screen buffer. Tests run one at a time, so
+/// only one of these exists at once.
+fn fake_writer() -> Writer {
+	return Writer::with_buffer(unsafe { &mut *addr_of_mut!(FAKE_BUFFER) });
+}
+
+fn row_text(writer: &Writer, row: usize, len: usize) -> [u8; VGA_WIDTH] {
+	let mut text = [b' '; VGA_WIDTH];
+	for (col, byte) in text.iter_mut().enumerate().take(len) {
+		*byte = writer.buffer.chars[row][col].ascii_character;
+	}
+	return text;
+}
+
 #[test_case]
 fn test_println_simple() {
 	println!("test_println_simple output");
@@ -18,8 +55,369 @@ fn test_println_many() {
 fn test_println_output() {
 	let s = "Some test string that fits on a single line";
 	println!("{}", s);
+	let row = WRITER.lock().position().1 - 1;
 	for (i, c) in s.chars().enumerate() {
-		let screen_char = WRITER.lock().buffer.chars[VGA_HEIGHT - 2][i];
+		let screen_char = WRITER.lock().buffer.chars[row][i];
 		assert_eq!(char::from(screen_char.ascii_character), c);
 	}
 }
+
+#[test_case]
+fn test_clear_char_wraps_to_previous_row() {
+	let mut writer = WRITER.lock();
+
+	writer.set_position(0, 10);
+	writer.clear_char();
+	assert_eq!(writer.position(), (VGA_WIDTH - 1, 9));
+
+	writer.set_position(VGA_WIDTH, 10);
+	writer.clear_char();
+	assert_eq!(writer.position(), (VGA_WIDTH - 1, 10));
+
+	writer.set_position(0, 0);
+	writer.clear_char();
+	assert_eq!(writer.position(), (0, 0));
+
+	writer.set_position(0, VGA_HEIGHT - 1);
+}
+
+#[test_case]
+fn test_screen_position_same_row() {
+	let prompt = 6;
+	let pos =
+		Console::screen_position(prompt, (prompt + 5, 24), VGA_HEIGHT, 5, 2);
+	assert_eq!(pos, (prompt + 2, 24));
+}
+
+#[test_case]
+fn test_screen_position_across_wrap() {
+	let prompt = 6;
+	let end = VGA_WIDTH - prompt + 3;
+
+	// From the end of a line that wrapped once back to its first character
+	let pos = Console::screen_position(prompt, (3, 24), VGA_HEIGHT, end, 0);
+	assert_eq!(pos, (prompt, 23));
+
+	// A pending wrap counts as the start of the next row
+	let pos = Console::screen_position(
+		prompt,
+		(VGA_WIDTH, 23),
+		VGA_HEIGHT,
+		VGA_WIDTH - prompt,
+		end,
+	);
+	assert_eq!(pos, (3, 24));
+}
+
+#[test_case]
+fn test_screen_position_clamps_below_screen() {
+	let pos = Console::screen_position(
+		0,
+		(0, VGA_HEIGHT - 1),
+		VGA_HEIGHT,
+		0,
+		VGA_WIDTH,
+	);
+	assert_eq!(pos, (VGA_WIDTH, VGA_HEIGHT - 1));
+}
+
+static PAGER_PAUSES: AtomicUsize = AtomicUsize::new(0);
+
+fn quit_pager() -> char {
+	PAGER_PAUSES.fetch_add(1, Ordering::Relaxed);
+	return 'q';
+}
+
+#[test_case]
+fn test_pager_short_output_does_not_pause() {
+	PAGER_PAUSES.store(0, Ordering::Relaxed);
+
+	let rows = WRITER.lock().usable_height();
+	pager::begin(quit_pager);
+	for i in 0..rows - 1 {
+		println!("pager line {}", i);
+	}
+	pager::end();
+
+	assert_eq!(PAGER_PAUSES.load(Ordering::Relaxed), 0);
+}
+
+#[test_case]
+fn test_pager_quit_discards_output() {
+	PAGER_PAUSES.store(0, Ordering::Relaxed);
+
+	pager::begin(quit_pager);
+	for i in 0..VGA_HEIGHT * 2 {
+		println!("pager line {}", i);
+	}
+	assert!(pager::aborted());
+	pager::end();
+
+	assert_eq!(PAGER_PAUSES.load(Ordering::Relaxed), 1);
+	assert!(!pager::aborted());
+}
+
+#[test_case]
+fn test_writer_starts_top_left() {
+	let writer = fake_writer();
+
+	assert_eq!(writer.position(), (0, 0));
+	assert_eq!(writer.buffer.chars[0][0], BLANK);
+}
+
+#[test_case]
+fn test_writer_rows_advance_without_scrolling() {
+	let mut writer = fake_writer();
+
+	writer.write_string("ab\ncd");
+
+	assert_eq!(writer.position(), (2, 1));
+	assert_eq!(&row_text(&writer, 0, 2)[..2], b"ab");
+	assert_eq!(&row_text(&writer, 1, 2)[..2], b"cd");
+	assert_eq!(writer.rows_written(), 1);
+}
+
+#[test_case]
+fn test_writer_scrolls_past_last_row() {
+	let mut writer = fake_writer();
+
+	writer.write_string("first\n");
+	for _ in 0..VGA_HEIGHT - 2 {
+		writer.write_string("x\n");
+	}
+	writer.write_string("last");
+	assert_eq!(writer.position(), (4, VGA_HEIGHT - 1));
+	assert_eq!(&row_text(&writer, 0, 5)[..5], b"first");
+
+	writer.write_string("\n");
+	assert_eq!(writer.position(), (0, VGA_HEIGHT - 1));
+	assert_eq!(&row_text(&writer, 0, 5)[..5], b"x    ");
+	assert_eq!(&row_text(&writer, VGA_HEIGHT - 2, 4)[..4], b"last");
+	assert_eq!(writer.buffer.chars[VGA_HEIGHT - 1][0], BLANK);
+}
+
+#[test_case]
+fn test_writer_wraps_on_next_byte() {
+	let mut writer = fake_writer();
+
+	for _ in 0..VGA_WIDTH {
+		writer.write_string("a");
+	}
+	assert_eq!(writer.position(), (VGA_WIDTH, 0));
+
+	writer.write_string("b");
+	assert_eq!(writer.position(), (1, 1));
+	assert_eq!(writer.buffer.chars[1][0].ascii_character, b'b');
+}
+
+#[test_case]
+fn test_writer_clear_line_uses_current_row() {
+	let mut writer = fake_writer();
+
+	writer.write_string("keep\ndrop");
+	writer.clear_line();
+
+	assert_eq!(writer.position(), (0, 1));
+	assert_eq!(&row_text(&writer, 0, 4)[..4], b"keep");
+	assert_eq!(writer.buffer.chars[1][0], BLANK);
+}
+
+#[test_case]
+fn test_writer_set_position_clamps() {
+	let mut writer = fake_writer();
+
+	writer.set_position(VGA_WIDTH + 5, VGA_HEIGHT + 5);
+	assert_eq!(writer.position(), (VGA_WIDTH, VGA_HEIGHT - 1));
+
+	writer.set_position(3, 2);
+	writer.write_string("z");
+	assert_eq!(writer.buffer.chars[2][3].ascii_character, b'z');
+}
+
+#[test_case]
+fn test_writer_tall_mode_scrolls_on_last_row() {
+	let mut writer = fake_writer();
+	writer.set_height(MAX_VGA_HEIGHT);
+	assert_eq!(writer.height(), MAX_VGA_HEIGHT);
+
+	writer.set_position(0, VGA_HEIGHT - 1);
+	writer.write_string("top\n");
+	assert_eq!(writer.position(), (0, VGA_HEIGHT));
+
+	writer.set_position(VGA_WIDTH + 5, MAX_VGA_HEIGHT + 5);
+	assert_eq!(writer.position(), (VGA_WIDTH, MAX_VGA_HEIGHT - 1));
+
+	writer.set_position(0, MAX_VGA_HEIGHT - 1);
+	writer.write_string("last\n");
+	assert_eq!(writer.position(), (0, MAX_VGA_HEIGHT - 1));
+	assert_eq!(&row_text(&writer, MAX_VGA_HEIGHT - 2, 4)[..4], b"last");
+	assert_eq!(&row_text(&writer, VGA_HEIGHT - 2, 3)[..3], b"top");
+}
+
+#[test_case]
+fn test_writer_status_bar_is_not_scrolled() {
+	let mut writer = fake_writer();
+	writer.set_status_bar(true);
+	writer.draw_status_bar("status");
+	assert_eq!(writer.usable_height(), VGA_HEIGHT - 1);
+
+	for _ in 0..VGA_HEIGHT * 2 {
+		writer.write_string("line\n");
+	}
+	assert_eq!(writer.position(), (0, VGA_HEIGHT - 2));
+
+	writer.clear_screen();
+	writer.set_position(0, VGA_HEIGHT + 5);
+	assert_eq!(writer.position(), (0, VGA_HEIGHT - 2));
+
+	let bar = writer.buffer.chars[VGA_HEIGHT - 1];
+	assert_eq!(&row_text(&writer, VGA_HEIGHT - 1, 6)[..6], b"status");
+	assert_eq!(
+		bar[0].colour_code,
+		ColourCode::new(VgaColour::Black, VgaColour::LightGrey)
+	);
+
+	writer.set_status_bar(false);
+	assert_eq!(writer.usable_height(), VGA_HEIGHT);
+}
+
+/// Sink recording what it is asked to do.
+struct CaptureSink {
+	text: Mutex<([u8; 32], usize)>,
+	cursor: Mutex<Option<(usize, usize)>>,
+}
+
+impl ConsoleOutput for CaptureSink {
+	fn write_str(&self, s: &str) {
+		let mut text = self.text.lock();
+		let (buf, len) = &mut *text;
+
+		let n = s.len().min(buf.len() - *len);
+		buf[*len..*len + n].copy_from_slice(&s.as_bytes()[..n]);
+		*len += n;
+	}
+
+	fn set_colour(&self, _colour: ColourCode) {}
+
+	fn clear(&self) {}
+
+	fn cursor_to(&self, col: usize, row: usize) {
+		*self.cursor.lock() = Some((col, row));
+	}
+}
+
+static CAPTURE: CaptureSink = CaptureSink {
+	text: Mutex::new(([0; 32], 0)),
+	cursor: Mutex::new(None),
+};
+
+#[test_case]
+fn test_output_reaches_registered_sinks() {
+	assert!(output::register(&CAPTURE));
+	assert!(output::is_registered(&CAPTURE));
+
+	println!("sink {}", 42);
+	let (col, row) = WRITER.lock().position();
+	output::cursor_to(col, row);
+
+	assert!(output::unregister(&CAPTURE));
+	println!("not captured");
+
+	let text = CAPTURE.text.lock();
+	assert_eq!(&text.0[..text.1], b"sink 42\n");
+	assert_eq!(*CAPTURE.cursor.lock(), Some((col, row)));
+	assert!(!output::is_registered(&CAPTURE));
+}
+
+#[test_case]
+fn test_font_glyphs() {
+	assert_eq!(glyph_8x8(b' '), [0; 8]);
+	assert_eq!(glyph_8x8(b'A'), FONT_8X8[b'A' as usize]);
+	// The upper half has glyphs of its own, not a placeholder
+	assert_ne!(glyph_8x8(0x80), glyph_8x8(b'?'));
+	assert_ne!(glyph_8x8(0xe1), glyph_8x8(b'?'));
+
+	assert_eq!(glyph_8x16(b' '), [0; 16]);
+	assert_eq!(glyph_8x16(b'A'), FONT_8X16[b'A' as usize]);
+	assert_ne!(glyph_8x16(0x01), glyph_8x16(b'?'));
+}
+
+#[test_case]
+fn test_font_8x16_lines_reach_the_edges() {
+	// │ runs from the top row to the bottom one and ─ across a whole row, so
+	// the cells above, below and beside join up
+	let vertical = glyph_8x16(0xb3);
+	assert!(vertical
+		.iter()
+		.all(|&bits| bits == vertical[0] && bits != 0));
+	assert!(glyph_8x16(0xc4).contains(&0xff));
+
+	let cross = glyph_8x16(0xc5);
+	assert!(cross.contains(&0xff));
+	assert_eq!(cross[0], vertical[0]);
+	assert_eq!(cross[15], vertical[15]);
+	assert_eq!(glyph_8x16(0xdb), [0xff; 16]);
+}
+
+#[test_case]
+fn test_font_8x8_has_every_glyph() {
+	// 80x50 loads every slot of the VGA font from it, only the three blank
+	// glyphs of CP437 may be empty
+	for byte in 0..=u8::MAX {
+		let glyph = glyph_8x8(byte);
+		match byte {
+			0x00 | b' ' | 0xff => assert_eq!(glyph, [0; 8]),
+			_ => assert_ne!(glyph, [0; 8], "byte {:#04x}", byte),
+		}
+		if byte != b'?' {
+			assert_ne!(glyph, glyph_8x8(b'?'), "byte {:#04x}", byte);
+		}
+	}
+}
+
+#[test_case]
+fn test_font_8x8_lines_reach_the_edges() {
+	let vertical = glyph_8x8(0xba);
+	assert!(vertical
+		.iter()
+		.all(|&bits| bits == vertical[0] && bits != 0));
+	assert!(glyph_8x8(0xcd).contains(&0xff));
+
+	let cross = glyph_8x8(0xce);
+	assert_eq!(cross[0], vertical[0]);
+	assert_eq!(cross[7], vertical[7]);
+	assert_eq!(glyph_8x8(0xdb), [0xff; 8]);
+}
+
+#[test_case]
+fn test_cp437_box_drawing() {
+	assert_eq!(cp437::from_char('A'), Some(b'A'));
+	assert_eq!(cp437::from_char('┌'), Some(0xda));
+	assert_eq!(cp437::from_char('─'), Some(0xc4));
+	assert_eq!(cp437::from_char('╬'), Some(0xce));
+	assert_eq!(cp437::from_char('\x07'), None);
+	assert_eq!(cp437::from_char('€'), None);
+
+	for byte in 0x80..=0xff {
+		let c = cp437::to_char(byte);
+		assert_eq!(c.and_then(cp437::from_char), Some(byte));
+	}
+}
+
+#[test_case]
+fn test_writer_cp437_output() {
+	let mut writer = fake_writer();
+
+	writer.write_string("┌─┐\x07");
+	let glyphs = [0xda, 0xc4, 0xbf, 0xfe];
+	for (col, glyph) in glyphs.iter().enumerate() {
+		assert_eq!(writer.buffer.chars[0][col].ascii_character, *glyph);
+	}
+	assert_eq!(writer.position(), (4, 0));
+
+	writer.write_cp437(&[0x01, b'\n', 0xdb]);
+	assert_eq!(writer.buffer.chars[0][4].ascii_character, 0x01);
+	assert_eq!(writer.buffer.chars[0][5].ascii_character, b'\n');
+	assert_eq!(writer.buffer.chars[0][6].ascii_character, 0xdb);
+	assert_eq!(writer.position(), (7, 0));
+}
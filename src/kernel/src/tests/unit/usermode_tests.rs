@@ -0,0 +1,38 @@
+use crate::{
+	arch::x86::{
+		gdt,
+		idt::{IDT_ENTRIES, SYSCALL_VECTOR},
+		usermode::{self, DEMO_CODE, DEMO_STACK, DEMO_VALUE},
+	},
+	memory::paging::translate,
+};
+use core::{arch::asm, ptr};
+
+#[test_case]
+fn test_user_descriptors_are_ring_3() {
+	let mut code = gdt::read_descriptor(3).unwrap();
+	let mut data = gdt::read_descriptor(4).unwrap();
+
+	assert_eq!((code.access() >> 5) & 0x3, 3);
+	assert_eq!((data.access() >> 5) & 0x3, 3);
+	assert_eq!(code.access() & 0b1000, 0b1000);
+	assert_eq!(data.access() & 0b1000, 0);
+}
+
+#[test_case]
+fn test_syscall_gate_allows_ring_3() {
+	let entry = unsafe { (*(&raw const IDT_ENTRIES))[SYSCALL_VECTOR] };
+
+	assert_eq!(entry.privilege_level(), 3);
+	assert_eq!(entry.selector(), 0x08);
+}
+
+#[test_case]
+fn test_user_demo_returns_to_kernel() {
+	assert_eq!(usermode::demo(), Ok(DEMO_VALUE));
+
+	// Twice, the first run left nothing behind
+	assert_eq!(usermode::demo(), Ok(DEMO_VALUE));
+	assert!(translate(DEMO_CODE).is_none());
+	assert!(translate(DEMO_STACK).is_none());
+}
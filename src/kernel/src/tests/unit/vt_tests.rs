@@ -0,0 +1,86 @@
+use crate::tty::{
+	tty::WRITER,
+	vt::{self, VT_COUNT},
+	Buffer, VgaChar, VGA_WIDTH,
+};
+
+// Helper returning row `row` of VGA memory
+#[allow(fuzzy_provenance_casts)]
+fn vga_row(row: usize) -> [VgaChar; VGA_WIDTH] {
+	// Safety: 0xB8000 is the VGA buffer, mapped for the kernel's lifetime.
+	return unsafe { (*(0xb8000 as *const Buffer)).chars[row] };
+}
+
+// Helper returning the start of row `row` of the visible terminal
+fn shown_text(row: usize, len: usize) -> [u8; VGA_WIDTH] {
+	let writer = WRITER.lock();
+	let mut text = [b' '; VGA_WIDTH];
+	for (col, byte) in text.iter_mut().enumerate().take(len) {
+		*byte = writer.buffer.chars[row][col].ascii_character;
+	}
+	return text;
+}
+
+// Helper returning `s` padded to a row
+fn padded(s: &[u8]) -> [u8; VGA_WIDTH] {
+	let mut text = [b' '; VGA_WIDTH];
+	text[..s.len()].copy_from_slice(s);
+	return text;
+}
+
+#[test_case]
+fn test_vt_out_of_range() {
+	assert!(!vt::switch_to(VT_COUNT));
+	assert!(!vt::write_str(VT_COUNT, "nowhere"));
+	assert_eq!(vt::active(), 0);
+}
+
+#[test_case]
+fn test_vt_background_output() {
+	let before = WRITER.lock().buffer.chars;
+	let framebuffer = WRITER.lock().has_framebuffer();
+
+	assert!(vt::write_str(1, "on vt 2\nsecond line"));
+
+	// Neither the visible cells nor the screen changed
+	assert!(WRITER.lock().buffer.chars == before);
+	if !framebuffer {
+		for (row, cells) in before.iter().enumerate().take(3) {
+			assert_eq!(vga_row(row), *cells);
+		}
+	}
+
+	assert!(vt::switch_to(1));
+	assert_eq!(vt::active(), 1);
+	assert_eq!(shown_text(0, 7), padded(b"on vt 2"));
+	assert_eq!(shown_text(1, 11), padded(b"second line"));
+	assert_eq!(WRITER.lock().position(), (11, 1));
+	if !framebuffer {
+		assert_eq!(vga_row(0), WRITER.lock().buffer.chars[0]);
+	}
+
+	assert!(vt::switch_to(0));
+	assert!(WRITER.lock().buffer.chars == before);
+	if !framebuffer {
+		assert_eq!(vga_row(0), before[0]);
+	}
+}
+
+#[test_case]
+fn test_vt_switch_keeps_each_terminal() {
+	let (col, row) = WRITER.lock().position();
+
+	assert!(vt::switch_to(2));
+	assert!(vt::write_str(2, "shown"));
+	assert_eq!(shown_text(0, 5), padded(b"shown"));
+
+	// Writing to it while parked continues where it left off
+	assert!(vt::switch_to(0));
+	assert_eq!(WRITER.lock().position(), (col, row));
+	assert!(vt::write_str(2, " then parked"));
+
+	assert!(vt::switch_to(2));
+	assert_eq!(shown_text(0, 17), padded(b"shown then parked"));
+	assert!(vt::switch_to(0));
+	assert_eq!(WRITER.lock().position(), (col, row));
+}
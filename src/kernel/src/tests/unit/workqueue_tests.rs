@@ -0,0 +1,60 @@
+use crate::{
+	sync::{Mutex, PreemptGuard},
+	task::{thread, workqueue},
+	time,
+};
+use alloc::vec::Vec;
+
+static RUNS: Mutex<Vec<(usize, thread::ThreadId)>> = Mutex::new(Vec::new());
+
+fn record(arg: usize) {
+	RUNS.lock().push((arg, thread::current()));
+}
+
+fn ignore(_: usize) {}
+
+// Helper sleeping until the queue is empty, for at most a second
+fn wait_for_worker() {
+	let deadline = time::uptime_ms() + 1000;
+	while workqueue::stats().pending > 0 && time::uptime_ms() < deadline {
+		time::sleep_ms(1);
+	}
+	// The last item may still be running
+	time::sleep_ms(1);
+}
+
+#[test_case]
+fn test_worker_runs_work_in_order() {
+	RUNS.lock().clear();
+	let before = workqueue::stats();
+
+	for arg in 1..=3 {
+		assert!(workqueue::schedule_work(record, arg));
+	}
+	wait_for_worker();
+
+	let worker = workqueue::worker_id().unwrap();
+	assert_eq!(*RUNS.lock(), [(1, worker), (2, worker), (3, worker)]);
+
+	let after = workqueue::stats();
+	assert_eq!(after.scheduled - before.scheduled, 3);
+	assert!(after.executed - before.executed >= 3);
+}
+
+#[test_case]
+fn test_full_queue_drops_work() {
+	let before = workqueue::stats();
+	{
+		// The worker cannot run until the queue is full
+		let _no_preempt = PreemptGuard::new();
+		let room = workqueue::QUEUE_SIZE - workqueue::stats().pending;
+		for _ in 0..room {
+			assert!(workqueue::schedule_work(ignore, 0));
+		}
+		assert!(!workqueue::schedule_work(ignore, 0));
+	}
+	assert_eq!(workqueue::stats().dropped - before.dropped, 1);
+
+	wait_for_worker();
+	assert_eq!(workqueue::stats().pending, 0);
+}
@@ -0,0 +1,135 @@
+//! Time keeping driven by the timer interrupt.
+//!
+//! The timer interrupt calls [`tick`] on every interrupt. Until a timer source
+//! has announced its rate with [`set_tick_rate`], uptime reads as zero.
+//!
+//! [`sleep_ms`] lets the running thread sleep without burning the CPU, the
+//! scheduler wakes it from the timer interrupt.
+
+use crate::{
+	arch::x86::cpu::halt,
+	sync::IrqMutex,
+	task::{self, scheduler},
+};
+use core::sync::atomic::{AtomicU32, Ordering};
+
+static TICKS: TickCounter = TickCounter::new();
+static EPOCH: IrqMutex<Epoch> = IrqMutex::named(
+	"EPOCH",
+	Epoch {
+		ticks: 0,
+		ms: 0,
+		hz: 0,
+	},
+);
+
+/// The tick count and uptime when the tick rate last changed. Ticks after it
+/// count at `hz`, so changing the rate does not make the uptime jump.
+#[derive(Clone, Copy)]
+struct Epoch {
+	ticks: u64,
+	ms: u64,
+	hz: u32,
+}
+
+impl Epoch {
+	/// Returns the uptime at tick count `ticks`.
+	fn uptime_ms(&self, ticks: u64) -> u64 {
+		if self.hz == 0 {
+			return self.ms;
+		}
+
+		return self.ms + (ticks - self.ticks) * 1000 / self.hz as u64;
+	}
+}
+
+/// A 64-bit counter built from two `AtomicU32`s, as i386 has no 64-bit
+/// atomics. Only the timer interrupt increments it, readers retry if the high
+/// word changed while they read.
+struct TickCounter {
+	low: AtomicU32,
+	high: AtomicU32,
+}
+
+impl TickCounter {
+	const fn new() -> Self {
+		return Self {
+			low: AtomicU32::new(0),
+			high: AtomicU32::new(0),
+		};
+	}
+
+	fn increment(&self) {
+		if self.low.fetch_add(1, Ordering::AcqRel) == u32::MAX {
+			self.high.fetch_add(1, Ordering::AcqRel);
+		}
+	}
+
+	fn load(&self) -> u64 {
+		loop {
+			let high = self.high.load(Ordering::Acquire);
+			let low = self.low.load(Ordering::Acquire);
+
+			if high == self.high.load(Ordering::Acquire) {
+				return ((high as u64) << 32) | low as u64;
+			}
+		}
+	}
+}
+
+/// Records the frequency the timer interrupt fires at from now on.
+pub fn set_tick_rate(hz: u32) {
+	let mut epoch = EPOCH.lock();
+	let now = ticks();
+
+	*epoch = Epoch {
+		ticks: now,
+		ms: epoch.uptime_ms(now),
+		hz,
+	};
+}
+
+/// Returns the frequency of the timer interrupt, or 0 if no timer runs yet.
+pub fn tick_rate() -> u32 {
+	return EPOCH.lock().hz;
+}
+
+/// Advances the tick counter. Called from the timer interrupt handler.
+#[inline]
+pub fn tick() {
+	TICKS.increment();
+}
+
+/// Returns the number of timer interrupts since boot.
+#[inline]
+pub fn ticks() -> u64 {
+	return TICKS.load();
+}
+
+/// Returns the milliseconds since the timer started, or 0 before that.
+pub fn uptime_ms() -> u64 {
+	let epoch = EPOCH.lock();
+
+	return epoch.uptime_ms(ticks());
+}
+
+/// Puts the running thread to sleep for at least `ms` milliseconds. Other
+/// threads run meanwhile, or the idle thread halts the CPU.
+///
+/// Only yields if no timer runs yet, and halts until the time is up if there
+/// is no other thread to run, before [`task::init`].
+pub fn sleep_ms(ms: u64) {
+	let hz = tick_rate();
+	if hz == 0 {
+		task::yield_now();
+		return;
+	}
+
+	// One more, the current tick is partly over
+	let wake_at = ticks() + (ms * hz as u64).div_ceil(1000) + 1;
+	if !scheduler::sleep_until(wake_at) {
+		while ticks() < wake_at {
+			halt();
+		}
+	}
+}
@@ -0,0 +1,42 @@
+//! Code page 437, the character set VGA text mode draws.
+//!
+//! Its lower half is ASCII. The upper half holds accented letters, Greek
+//! letters, maths symbols and the line and block drawing glyphs, which
+//! [`from_char`] maps from their Unicode code points so `println!("┌─┐")`
+//! draws a box.
+
+/// Unicode code points of CP437 bytes 0x80 to 0xFF.
+const UPPER_HALF: [char; 128] = [
+	'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä',
+	'Å', 'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥',
+	'₧', 'ƒ', 'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼',
+	'¡', '«', '»', '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗',
+	'╝', '╜', '╛', '┐', '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩',
+	'╦', '╠', '═', '╬', '╧', '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘',
+	'┌', '█', '▄', '▌', '▐', '▀', 'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ',
+	'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩', '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈',
+	'°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Returns the CP437 byte that draws `c`, if there is one. Printable ASCII
+/// maps to itself, control characters have no glyph.
+pub fn from_char(c: char) -> Option<u8> {
+	if matches!(c, ' '..='~') {
+		return Some(c as u8);
+	}
+
+	return UPPER_HALF
+		.iter()
+		.position(|&glyph| glyph == c)
+		.map(|index| 0x80 + index as u8);
+}
+
+/// Returns the Unicode code point drawn for CP437 byte `byte`, or `None` for
+/// the control range below 0x20.
+pub fn to_char(byte: u8) -> Option<char> {
+	match byte {
+		0x20..=0x7e => return Some(byte as char),
+		0x80..=0xff => return Some(UPPER_HALF[(byte - 0x80) as usize]),
+		_ => return None,
+	}
+}
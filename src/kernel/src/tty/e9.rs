@@ -0,0 +1,95 @@
+//! Debug console on port 0xE9.
+//!
+//! Bochs and QEMU (with `-debugcon`) print every byte written to port 0xE9.
+//! It needs no setup, so it works from the first instruction on. Serial
+//! output falls back to it while there is no UART, before serial init for
+//! example, and can be copied to it with [`set_enabled`]. `debugcon=on` on the
+//! kernel command line does that and registers [`E9_OUTPUT`], so the console
+//! shows up there too.
+
+use super::{output::ConsoleOutput, ColourCode};
+use crate::arch::x86::io::{inb, outb};
+use core::{
+	fmt,
+	sync::atomic::{AtomicBool, AtomicU8, Ordering},
+};
+
+const PORT: u16 = 0xe9;
+
+const UNKNOWN: u8 = 0;
+const PRESENT: u8 = 1;
+const ABSENT: u8 = 2;
+
+static PROBED: AtomicU8 = AtomicU8::new(UNKNOWN);
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Returns whether the emulator has a debug console, which reads back as
+/// 0xE9. The answer is cached after the first probe.
+pub fn is_present() -> bool {
+	match PROBED.load(Ordering::Relaxed) {
+		PRESENT => return true,
+		ABSENT => return false,
+		_ => {
+			let present = inb(PORT) == PORT as u8;
+			let state = if present { PRESENT } else { ABSENT };
+			PROBED.store(state, Ordering::Relaxed);
+			return present;
+		}
+	}
+}
+
+/// Copies serial output to the debug console even while a UART is present.
+pub fn set_enabled(enabled: bool) {
+	ENABLED.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether serial output is copied to the debug console.
+pub fn enabled() -> bool {
+	return ENABLED.load(Ordering::Relaxed);
+}
+
+/// Writes `s` to the debug console, if there is one.
+pub fn write_str(s: &str) {
+	if !is_present() {
+		return;
+	}
+
+	for byte in s.bytes() {
+		outb(PORT, byte);
+	}
+}
+
+/// Formats `args` straight to the debug console.
+pub fn print(args: fmt::Arguments) {
+	let _ = fmt::write(&mut DebugCon, args);
+}
+
+struct DebugCon;
+
+impl fmt::Write for DebugCon {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		write_str(s);
+		return Ok(());
+	}
+}
+
+/* -------------------------------------- */
+
+/// The debug console as a console sink, see [`E9Output`].
+pub static E9_OUTPUT: E9Output = E9Output;
+
+/// Console sink copying text to the debug console. It is a plain log with
+/// no colours or cursor, so only text and line breaks come through.
+pub struct E9Output;
+
+impl ConsoleOutput for E9Output {
+	fn write_str(&self, s: &str) {
+		write_str(s);
+	}
+
+	fn set_colour(&self, _colour: ColourCode) {}
+
+	fn clear(&self) {}
+
+	fn cursor_to(&self, _col: usize, _row: usize) {}
+}
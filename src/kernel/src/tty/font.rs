@@ -0,0 +1,800 @@
+//! Bitmap fonts for drawing text on a pixel framebuffer and in VGA text mode.
+//!
+//! Both fonts have all 256 CP437 glyphs. The 8x16 one is the IBM VGA font,
+//! the 8x8 one the public domain `font8x8_basic` set by Daniel Hepper for
+//! printable ASCII and the IBM PC 8x8 font for the rest. The line and block
+//! drawing characters reach the cell edges so boxes join up. Bit 0 of each
+//! row is the leftmost pixel.
+
+/// Width of a glyph in pixels.
+pub const GLYPH_WIDTH: usize = 8;
+/// Height of a framebuffer glyph in pixels.
+pub const GLYPH_HEIGHT: usize = 16;
+
+/// Every CP437 glyph, indexed by its byte.
+#[rustfmt::skip]
+pub const FONT_8X8: [[u8; 8]; 256] = [
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x00
+	[0x7e, 0x81, 0xa5, 0x81, 0xbd, 0x99, 0x81, 0x7e], // 0x01
+	[0x7e, 0xff, 0xdb, 0xff, 0xc3, 0xe7, 0xff, 0x7e], // 0x02
+	[0x36, 0x7f, 0x7f, 0x7f, 0x3e, 0x1c, 0x08, 0x00], // 0x03
+	[0x08, 0x1c, 0x3e, 0x7f, 0x3e, 0x1c, 0x08, 0x00], // 0x04
+	[0x1c, 0x3e, 0x1c, 0x7f, 0x7f, 0x6b, 0x08, 0x1c], // 0x05
+	[0x08, 0x1c, 0x3e, 0x7f, 0x7f, 0x3e, 0x08, 0x1c], // 0x06
+	[0x00, 0x00, 0x18, 0x3c, 0x3c, 0x18, 0x00, 0x00], // 0x07
+	[0xff, 0xff, 0xe7, 0xc3, 0xc3, 0xe7, 0xff, 0xff], // 0x08
+	[0x00, 0x3c, 0x66, 0x42, 0x42, 0x66, 0x3c, 0x00], // 0x09
+	[0xff, 0xc3, 0x99, 0xbd, 0xbd, 0x99, 0xc3, 0xff], // 0x0a
+	[0xf0, 0xe0, 0xf0, 0xbe, 0x33, 0x33, 0x33, 0x1e], // 0x0b
+	[0x3c, 0x66, 0x66, 0x66, 0x3c, 0x18, 0x7e, 0x18], // 0x0c
+	[0xfc, 0xcc, 0xfc, 0x0c, 0x0c, 0x0e, 0x0f, 0x07], // 0x0d
+	[0xfe, 0xc6, 0xfe, 0xc6, 0xc6, 0xe6, 0x67, 0x03], // 0x0e
+	[0x18, 0xdb, 0x3c, 0xe7, 0xe7, 0x3c, 0xdb, 0x18], // 0x0f
+	[0x01, 0x07, 0x1f, 0x7f, 0x1f, 0x07, 0x01, 0x00], // 0x10
+	[0x40, 0x70, 0x7c, 0x7f, 0x7c, 0x70, 0x40, 0x00], // 0x11
+	[0x18, 0x3c, 0x7e, 0x18, 0x18, 0x7e, 0x3c, 0x18], // 0x12
+	[0x66, 0x66, 0x66, 0x66, 0x66, 0x00, 0x66, 0x00], // 0x13
+	[0xfe, 0xdb, 0xdb, 0xde, 0xd8, 0xd8, 0xd8, 0x00], // 0x14
+	[0x7c, 0x86, 0x3c, 0x66, 0x66, 0x3c, 0x61, 0x3e], // 0x15
+	[0x00, 0x00, 0x00, 0x00, 0x7e, 0x7e, 0x7e, 0x00], // 0x16
+	[0x18, 0x3c, 0x7e, 0x18, 0x7e, 0x3c, 0x18, 0xff], // 0x17
+	[0x18, 0x3c, 0x7e, 0x18, 0x18, 0x18, 0x18, 0x00], // 0x18
+	[0x18, 0x18, 0x18, 0x18, 0x7e, 0x3c, 0x18, 0x00], // 0x19
+	[0x00, 0x18, 0x30, 0x7f, 0x30, 0x18, 0x00, 0x00], // 0x1a
+	[0x00, 0x0c, 0x06, 0x7f, 0x06, 0x0c, 0x00, 0x00], // 0x1b
+	[0x00, 0x00, 0x03, 0x03, 0x03, 0x7f, 0x00, 0x00], // 0x1c
+	[0x00, 0x24, 0x66, 0xff, 0x66, 0x24, 0x00, 0x00], // 0x1d
+	[0x00, 0x18, 0x3c, 0x7e, 0xff, 0xff, 0x00, 0x00], // 0x1e
+	[0x00, 0xff, 0xff, 0x7e, 0x3c, 0x18, 0x00, 0x00], // 0x1f
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x20
+	[0x18, 0x3c, 0x3c, 0x18, 0x18, 0x00, 0x18, 0x00], // 0x21 !
+	[0x36, 0x36, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x22 "
+	[0x36, 0x36, 0x7f, 0x36, 0x7f, 0x36, 0x36, 0x00], // 0x23 #
+	[0x0c, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x0c, 0x00], // 0x24 $
+	[0x00, 0x63, 0x33, 0x18, 0x0c, 0x66, 0x63, 0x00], // 0x25 %
+	[0x1c, 0x36, 0x1c, 0x6e, 0x3b, 0x33, 0x6e, 0x00], // 0x26 &
+	[0x06, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x27 '
+	[0x18, 0x0c, 0x06, 0x06, 0x06, 0x0c, 0x18, 0x00], // 0x28 (
+	[0x06, 0x0c, 0x18, 0x18, 0x18, 0x0c, 0x06, 0x00], // 0x29 )
+	[0x00, 0x66, 0x3c, 0xff, 0x3c, 0x66, 0x00, 0x00], // 0x2a *
+	[0x00, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x00], // 0x2b +
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x06], // 0x2c ,
+	[0x00, 0x00, 0x00, 0x3f, 0x00, 0x00, 0x00, 0x00], // 0x2d -
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x0c, 0x00], // 0x2e .
+	[0x60, 0x30, 0x18, 0x0c, 0x06, 0x03, 0x01, 0x00], // 0x2f /
+	[0x3e, 0x63, 0x73, 0x7b, 0x6f, 0x67, 0x3e, 0x00], // 0x30 0
+	[0x0c, 0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x3f, 0x00], // 0x31 1
+	[0x1e, 0x33, 0x30, 0x1c, 0x06, 0x33, 0x3f, 0x00], // 0x32 2
+	[0x1e, 0x33, 0x30, 0x1c, 0x30, 0x33, 0x1e, 0x00], // 0x33 3
+	[0x38, 0x3c, 0x36, 0x33, 0x7f, 0x30, 0x78, 0x00], // 0x34 4
+	[0x3f, 0x03, 0x1f, 0x30, 0x30, 0x33, 0x1e, 0x00], // 0x35 5
+	[0x1c, 0x06, 0x03, 0x1f, 0x33, 0x33, 0x1e, 0x00], // 0x36 6
+	[0x3f, 0x33, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x00], // 0x37 7
+	[0x1e, 0x33, 0x33, 0x1e, 0x33, 0x33, 0x1e, 0x00], // 0x38 8
+	[0x1e, 0x33, 0x33, 0x3e, 0x30, 0x18, 0x0e, 0x00], // 0x39 9
+	[0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x00], // 0x3a :
+	[0x00, 0x0c, 0x0c, 0x00, 0x00, 0x0c, 0x0c, 0x06], // 0x3b ;
+	[0x18, 0x0c, 0x06, 0x03, 0x06, 0x0c, 0x18, 0x00], // 0x3c <
+	[0x00, 0x00, 0x3f, 0x00, 0x00, 0x3f, 0x00, 0x00], // 0x3d =
+	[0x06, 0x0c, 0x18, 0x30, 0x18, 0x0c, 0x06, 0x00], // 0x3e >
+	[0x1e, 0x33, 0x30, 0x18, 0x0c, 0x00, 0x0c, 0x00], // 0x3f ?
+	[0x3e, 0x63, 0x7b, 0x7b, 0x7b, 0x03, 0x1e, 0x00], // 0x40 @
+	[0x0c, 0x1e, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x00], // 0x41 A
+	[0x3f, 0x66, 0x66, 0x3e, 0x66, 0x66, 0x3f, 0x00], // 0x42 B
+	[0x3c, 0x66, 0x03, 0x03, 0x03, 0x66, 0x3c, 0x00], // 0x43 C
+	[0x1f, 0x36, 0x66, 0x66, 0x66, 0x36, 0x1f, 0x00], // 0x44 D
+	[0x7f, 0x46, 0x16, 0x1e, 0x16, 0x46, 0x7f, 0x00], // 0x45 E
+	[0x7f, 0x46, 0x16, 0x1e, 0x16, 0x06, 0x0f, 0x00], // 0x46 F
+	[0x3c, 0x66, 0x03, 0x03, 0x73, 0x66, 0x7c, 0x00], // 0x47 G
+	[0x33, 0x33, 0x33, 0x3f, 0x33, 0x33, 0x33, 0x00], // 0x48 H
+	[0x1e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 0x49 I
+	[0x78, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e, 0x00], // 0x4a J
+	[0x67, 0x66, 0x36, 0x1e, 0x36, 0x66, 0x67, 0x00], // 0x4b K
+	[0x0f, 0x06, 0x06, 0x06, 0x46, 0x66, 0x7f, 0x00], // 0x4c L
+	[0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63, 0x63, 0x00], // 0x4d M
+	[0x63, 0x67, 0x6f, 0x7b, 0x73, 0x63, 0x63, 0x00], // 0x4e N
+	[0x1c, 0x36, 0x63, 0x63, 0x63, 0x36, 0x1c, 0x00], // 0x4f O
+	[0x3f, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00], // 0x50 P
+	[0x1e, 0x33, 0x33, 0x33, 0x3b, 0x1e, 0x38, 0x00], // 0x51 Q
+	[0x3f, 0x66, 0x66, 0x3e, 0x36, 0x66, 0x67, 0x00], // 0x52 R
+	[0x1e, 0x33, 0x07, 0x0e, 0x38, 0x33, 0x1e, 0x00], // 0x53 S
+	[0x3f, 0x2d, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 0x54 T
+	[0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x3f, 0x00], // 0x55 U
+	[0x33, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 0x56 V
+	[0x63, 0x63, 0x63, 0x6b, 0x7f, 0x77, 0x63, 0x00], // 0x57 W
+	[0x63, 0x63, 0x36, 0x1c, 0x1c, 0x36, 0x63, 0x00], // 0x58 X
+	[0x33, 0x33, 0x33, 0x1e, 0x0c, 0x0c, 0x1e, 0x00], // 0x59 Y
+	[0x7f, 0x63, 0x31, 0x18, 0x4c, 0x66, 0x7f, 0x00], // 0x5a Z
+	[0x1e, 0x06, 0x06, 0x06, 0x06, 0x06, 0x1e, 0x00], // 0x5b [
+	[0x03, 0x06, 0x0c, 0x18, 0x30, 0x60, 0x40, 0x00], // 0x5c \
+	[0x1e, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1e, 0x00], // 0x5d ]
+	[0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x5e ^
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff], // 0x5f _
+	[0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x60 `
+	[0x00, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x6e, 0x00], // 0x61 a
+	[0x07, 0x06, 0x06, 0x3e, 0x66, 0x66, 0x3b, 0x00], // 0x62 b
+	[0x00, 0x00, 0x1e, 0x33, 0x03, 0x33, 0x1e, 0x00], // 0x63 c
+	[0x38, 0x30, 0x30, 0x3e, 0x33, 0x33, 0x6e, 0x00], // 0x64 d
+	[0x00, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // 0x65 e
+	[0x1c, 0x36, 0x06, 0x0f, 0x06, 0x06, 0x0f, 0x00], // 0x66 f
+	[0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 0x67 g
+	[0x07, 0x06, 0x36, 0x6e, 0x66, 0x66, 0x67, 0x00], // 0x68 h
+	[0x0c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 0x69 i
+	[0x30, 0x00, 0x30, 0x30, 0x30, 0x33, 0x33, 0x1e], // 0x6a j
+	[0x07, 0x06, 0x66, 0x36, 0x1e, 0x36, 0x67, 0x00], // 0x6b k
+	[0x0e, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 0x6c l
+	[0x00, 0x00, 0x33, 0x7f, 0x7f, 0x6b, 0x63, 0x00], // 0x6d m
+	[0x00, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x33, 0x00], // 0x6e n
+	[0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1e, 0x00], // 0x6f o
+	[0x00, 0x00, 0x3b, 0x66, 0x66, 0x3e, 0x06, 0x0f], // 0x70 p
+	[0x00, 0x00, 0x6e, 0x33, 0x33, 0x3e, 0x30, 0x78], // 0x71 q
+	[0x00, 0x00, 0x3b, 0x6e, 0x66, 0x06, 0x0f, 0x00], // 0x72 r
+	[0x00, 0x00, 0x3e, 0x03, 0x1e, 0x30, 0x1f, 0x00], // 0x73 s
+	[0x08, 0x0c, 0x3e, 0x0c, 0x0c, 0x2c, 0x18, 0x00], // 0x74 t
+	[0x00, 0x00, 0x33, 0x33, 0x33, 0x33, 0x6e, 0x00], // 0x75 u
+	[0x00, 0x00, 0x33, 0x33, 0x33, 0x1e, 0x0c, 0x00], // 0x76 v
+	[0x00, 0x00, 0x63, 0x6b, 0x7f, 0x7f, 0x36, 0x00], // 0x77 w
+	[0x00, 0x00, 0x63, 0x36, 0x1c, 0x36, 0x63, 0x00], // 0x78 x
+	[0x00, 0x00, 0x33, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 0x79 y
+	[0x00, 0x00, 0x3f, 0x19, 0x0c, 0x26, 0x3f, 0x00], // 0x7a z
+	[0x38, 0x0c, 0x0c, 0x07, 0x0c, 0x0c, 0x38, 0x00], // 0x7b {
+	[0x18, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x00], // 0x7c |
+	[0x07, 0x0c, 0x0c, 0x38, 0x0c, 0x0c, 0x07, 0x00], // 0x7d }
+	[0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x7e ~
+	[0x00, 0x08, 0x1c, 0x36, 0x63, 0x63, 0x7f, 0x00], // 0x7f
+	[0x1e, 0x33, 0x03, 0x33, 0x1e, 0x18, 0x30, 0x1e], // 0x80
+	[0x00, 0x33, 0x00, 0x33, 0x33, 0x33, 0x7e, 0x00], // 0x81
+	[0x38, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // 0x82
+	[0x7e, 0xc3, 0x3c, 0x60, 0x7c, 0x66, 0xfc, 0x00], // 0x83
+	[0x33, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x7e, 0x00], // 0x84
+	[0x07, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x7e, 0x00], // 0x85
+	[0x0c, 0x0c, 0x1e, 0x30, 0x3e, 0x33, 0x7e, 0x00], // 0x86
+	[0x00, 0x00, 0x1e, 0x03, 0x03, 0x1e, 0x30, 0x1c], // 0x87
+	[0x7e, 0xc3, 0x3c, 0x66, 0x7e, 0x06, 0x3c, 0x00], // 0x88
+	[0x33, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // 0x89
+	[0x07, 0x00, 0x1e, 0x33, 0x3f, 0x03, 0x1e, 0x00], // 0x8a
+	[0x33, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 0x8b
+	[0x3e, 0x63, 0x1c, 0x18, 0x18, 0x18, 0x3c, 0x00], // 0x8c
+	[0x07, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 0x8d
+	[0x63, 0x1c, 0x36, 0x63, 0x7f, 0x63, 0x63, 0x00], // 0x8e
+	[0x0c, 0x0c, 0x00, 0x1e, 0x33, 0x3f, 0x33, 0x00], // 0x8f
+	[0x38, 0x00, 0x3f, 0x06, 0x1e, 0x06, 0x3f, 0x00], // 0x90
+	[0x00, 0x00, 0xfe, 0x30, 0xfe, 0x33, 0xfe, 0x00], // 0x91
+	[0x7c, 0x36, 0x33, 0x7f, 0x33, 0x33, 0x73, 0x00], // 0x92
+	[0x1e, 0x33, 0x00, 0x1e, 0x33, 0x33, 0x1e, 0x00], // 0x93
+	[0x00, 0x33, 0x00, 0x1e, 0x33, 0x33, 0x1e, 0x00], // 0x94
+	[0x00, 0x07, 0x00, 0x1e, 0x33, 0x33, 0x1e, 0x00], // 0x95
+	[0x1e, 0x33, 0x00, 0x33, 0x33, 0x33, 0x7e, 0x00], // 0x96
+	[0x00, 0x07, 0x00, 0x33, 0x33, 0x33, 0x7e, 0x00], // 0x97
+	[0x00, 0x33, 0x00, 0x33, 0x33, 0x3e, 0x30, 0x1f], // 0x98
+	[0xc3, 0x18, 0x3c, 0x66, 0x66, 0x3c, 0x18, 0x00], // 0x99
+	[0x33, 0x00, 0x33, 0x33, 0x33, 0x33, 0x1e, 0x00], // 0x9a
+	[0x18, 0x18, 0x7e, 0x03, 0x03, 0x7e, 0x18, 0x18], // 0x9b
+	[0x1c, 0x36, 0x26, 0x0f, 0x06, 0x67, 0x3f, 0x00], // 0x9c
+	[0x33, 0x33, 0x1e, 0x3f, 0x0c, 0x3f, 0x0c, 0x0c], // 0x9d
+	[0x1f, 0x33, 0x33, 0x5f, 0x63, 0xf3, 0x63, 0xe3], // 0x9e
+	[0x70, 0xd8, 0x18, 0x3c, 0x18, 0x18, 0x1b, 0x0e], // 0x9f
+	[0x38, 0x00, 0x1e, 0x30, 0x3e, 0x33, 0x7e, 0x00], // 0xa0
+	[0x1c, 0x00, 0x0e, 0x0c, 0x0c, 0x0c, 0x1e, 0x00], // 0xa1
+	[0x00, 0x38, 0x00, 0x1e, 0x33, 0x33, 0x1e, 0x00], // 0xa2
+	[0x00, 0x38, 0x00, 0x33, 0x33, 0x33, 0x7e, 0x00], // 0xa3
+	[0x00, 0x1f, 0x00, 0x1f, 0x33, 0x33, 0x33, 0x00], // 0xa4
+	[0x3f, 0x00, 0x33, 0x37, 0x3f, 0x3b, 0x33, 0x00], // 0xa5
+	[0x3c, 0x36, 0x36, 0x7c, 0x00, 0x7e, 0x00, 0x00], // 0xa6
+	[0x1c, 0x36, 0x36, 0x1c, 0x00, 0x3e, 0x00, 0x00], // 0xa7
+	[0x0c, 0x00, 0x0c, 0x06, 0x03, 0x33, 0x1e, 0x00], // 0xa8
+	[0x00, 0x00, 0x00, 0x3f, 0x03, 0x03, 0x00, 0x00], // 0xa9
+	[0x00, 0x00, 0x00, 0x3f, 0x30, 0x30, 0x00, 0x00], // 0xaa
+	[0xc3, 0x63, 0x33, 0x7b, 0xcc, 0x66, 0x33, 0xf0], // 0xab
+	[0xc3, 0x63, 0x33, 0xdb, 0xec, 0xf6, 0xf3, 0xc0], // 0xac
+	[0x18, 0x18, 0x00, 0x18, 0x18, 0x18, 0x18, 0x00], // 0xad
+	[0x00, 0xcc, 0x66, 0x33, 0x66, 0xcc, 0x00, 0x00], // 0xae
+	[0x00, 0x33, 0x66, 0xcc, 0x66, 0x33, 0x00, 0x00], // 0xaf
+	[0x88, 0x22, 0x88, 0x22, 0x88, 0x22, 0x88, 0x22], // 0xb0
+	[0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55], // 0xb1
+	[0xbb, 0xee, 0xbb, 0xee, 0xbb, 0xee, 0xbb, 0xee], // 0xb2
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xb3
+	[0x18, 0x18, 0x18, 0x18, 0x1f, 0x18, 0x18, 0x18], // 0xb4
+	[0x18, 0x18, 0x1f, 0x18, 0x1f, 0x18, 0x18, 0x18], // 0xb5
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6f, 0x6c, 0x6c, 0x6c], // 0xb6
+	[0x00, 0x00, 0x00, 0x00, 0x7f, 0x6c, 0x6c, 0x6c], // 0xb7
+	[0x00, 0x00, 0x1f, 0x18, 0x1f, 0x18, 0x18, 0x18], // 0xb8
+	[0x6c, 0x6c, 0x6f, 0x60, 0x6f, 0x6c, 0x6c, 0x6c], // 0xb9
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xba
+	[0x00, 0x00, 0x7f, 0x60, 0x6f, 0x6c, 0x6c, 0x6c], // 0xbb
+	[0x6c, 0x6c, 0x6f, 0x60, 0x7f, 0x00, 0x00, 0x00], // 0xbc
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x7f, 0x00, 0x00, 0x00], // 0xbd
+	[0x18, 0x18, 0x1f, 0x18, 0x1f, 0x00, 0x00, 0x00], // 0xbe
+	[0x00, 0x00, 0x00, 0x00, 0x1f, 0x18, 0x18, 0x18], // 0xbf
+	[0x18, 0x18, 0x18, 0x18, 0xf8, 0x00, 0x00, 0x00], // 0xc0
+	[0x18, 0x18, 0x18, 0x18, 0xff, 0x00, 0x00, 0x00], // 0xc1
+	[0x00, 0x00, 0x00, 0x00, 0xff, 0x18, 0x18, 0x18], // 0xc2
+	[0x18, 0x18, 0x18, 0x18, 0xf8, 0x18, 0x18, 0x18], // 0xc3
+	[0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00], // 0xc4
+	[0x18, 0x18, 0x18, 0x18, 0xff, 0x18, 0x18, 0x18], // 0xc5
+	[0x18, 0x18, 0xf8, 0x18, 0xf8, 0x18, 0x18, 0x18], // 0xc6
+	[0x6c, 0x6c, 0x6c, 0x6c, 0xec, 0x6c, 0x6c, 0x6c], // 0xc7
+	[0x6c, 0x6c, 0xec, 0x0c, 0xfc, 0x00, 0x00, 0x00], // 0xc8
+	[0x00, 0x00, 0xfc, 0x0c, 0xec, 0x6c, 0x6c, 0x6c], // 0xc9
+	[0x6c, 0x6c, 0xef, 0x00, 0xff, 0x00, 0x00, 0x00], // 0xca
+	[0x00, 0x00, 0xff, 0x00, 0xef, 0x6c, 0x6c, 0x6c], // 0xcb
+	[0x6c, 0x6c, 0xec, 0x0c, 0xec, 0x6c, 0x6c, 0x6c], // 0xcc
+	[0x00, 0x00, 0xff, 0x00, 0xff, 0x00, 0x00, 0x00], // 0xcd
+	[0x6c, 0x6c, 0xef, 0x00, 0xef, 0x6c, 0x6c, 0x6c], // 0xce
+	[0x18, 0x18, 0xff, 0x00, 0xff, 0x00, 0x00, 0x00], // 0xcf
+	[0x6c, 0x6c, 0x6c, 0x6c, 0xff, 0x00, 0x00, 0x00], // 0xd0
+	[0x00, 0x00, 0xff, 0x00, 0xff, 0x18, 0x18, 0x18], // 0xd1
+	[0x00, 0x00, 0x00, 0x00, 0xff, 0x6c, 0x6c, 0x6c], // 0xd2
+	[0x6c, 0x6c, 0x6c, 0x6c, 0xfc, 0x00, 0x00, 0x00], // 0xd3
+	[0x18, 0x18, 0xf8, 0x18, 0xf8, 0x00, 0x00, 0x00], // 0xd4
+	[0x00, 0x00, 0xf8, 0x18, 0xf8, 0x18, 0x18, 0x18], // 0xd5
+	[0x00, 0x00, 0x00, 0x00, 0xfc, 0x6c, 0x6c, 0x6c], // 0xd6
+	[0x6c, 0x6c, 0x6c, 0x6c, 0xff, 0x6c, 0x6c, 0x6c], // 0xd7
+	[0x18, 0x18, 0xff, 0x18, 0xff, 0x18, 0x18, 0x18], // 0xd8
+	[0x18, 0x18, 0x18, 0x18, 0x1f, 0x00, 0x00, 0x00], // 0xd9
+	[0x00, 0x00, 0x00, 0x00, 0xf8, 0x18, 0x18, 0x18], // 0xda
+	[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], // 0xdb
+	[0x00, 0x00, 0x00, 0x00, 0xff, 0xff, 0xff, 0xff], // 0xdc
+	[0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f], // 0xdd
+	[0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0], // 0xde
+	[0xff, 0xff, 0xff, 0xff, 0x00, 0x00, 0x00, 0x00], // 0xdf
+	[0x00, 0x00, 0x6e, 0x3b, 0x13, 0x3b, 0x6e, 0x00], // 0xe0
+	[0x00, 0x1e, 0x33, 0x1f, 0x33, 0x1f, 0x03, 0x03], // 0xe1
+	[0x00, 0x3f, 0x33, 0x03, 0x03, 0x03, 0x03, 0x00], // 0xe2
+	[0x00, 0x7f, 0x36, 0x36, 0x36, 0x36, 0x36, 0x00], // 0xe3
+	[0x3f, 0x33, 0x06, 0x0c, 0x06, 0x33, 0x3f, 0x00], // 0xe4
+	[0x00, 0x00, 0x7e, 0x1b, 0x1b, 0x1b, 0x0e, 0x00], // 0xe5
+	[0x00, 0x66, 0x66, 0x66, 0x66, 0x3e, 0x06, 0x03], // 0xe6
+	[0x00, 0x6e, 0x3b, 0x18, 0x18, 0x18, 0x18, 0x00], // 0xe7
+	[0x3f, 0x0c, 0x1e, 0x33, 0x33, 0x1e, 0x0c, 0x3f], // 0xe8
+	[0x1c, 0x36, 0x63, 0x7f, 0x63, 0x36, 0x1c, 0x00], // 0xe9
+	[0x1c, 0x36, 0x63, 0x63, 0x36, 0x36, 0x77, 0x00], // 0xea
+	[0x38, 0x0c, 0x18, 0x3e, 0x33, 0x33, 0x1e, 0x00], // 0xeb
+	[0x00, 0x00, 0x7e, 0xdb, 0xdb, 0x7e, 0x00, 0x00], // 0xec
+	[0x60, 0x30, 0x7e, 0xdb, 0xdb, 0x7e, 0x06, 0x03], // 0xed
+	[0x1c, 0x06, 0x03, 0x1f, 0x03, 0x06, 0x1c, 0x00], // 0xee
+	[0x1e, 0x33, 0x33, 0x33, 0x33, 0x33, 0x33, 0x00], // 0xef
+	[0x00, 0x3f, 0x00, 0x3f, 0x00, 0x3f, 0x00, 0x00], // 0xf0
+	[0x0c, 0x0c, 0x3f, 0x0c, 0x0c, 0x00, 0x3f, 0x00], // 0xf1
+	[0x06, 0x0c, 0x18, 0x0c, 0x06, 0x00, 0x3f, 0x00], // 0xf2
+	[0x18, 0x0c, 0x06, 0x0c, 0x18, 0x00, 0x3f, 0x00], // 0xf3
+	[0x70, 0xd8, 0xd8, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xf4
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x1b, 0x1b, 0x0e], // 0xf5
+	[0x0c, 0x0c, 0x00, 0x3f, 0x00, 0x0c, 0x0c, 0x00], // 0xf6
+	[0x00, 0x6e, 0x3b, 0x00, 0x6e, 0x3b, 0x00, 0x00], // 0xf7
+	[0x1c, 0x36, 0x36, 0x1c, 0x00, 0x00, 0x00, 0x00], // 0xf8
+	[0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00], // 0xf9
+	[0x00, 0x00, 0x00, 0x00, 0x18, 0x00, 0x00, 0x00], // 0xfa
+	[0xf0, 0x30, 0x30, 0x30, 0x37, 0x36, 0x3c, 0x38], // 0xfb
+	[0x1e, 0x36, 0x36, 0x36, 0x36, 0x00, 0x00, 0x00], // 0xfc
+	[0x0e, 0x18, 0x0c, 0x06, 0x1e, 0x00, 0x00, 0x00], // 0xfd
+	[0x00, 0x00, 0x3c, 0x3c, 0x3c, 0x3c, 0x00, 0x00], // 0xfe
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xff
+];
+
+/// Every CP437 glyph, indexed by its byte.
+#[rustfmt::skip]
+pub static FONT_8X16: [[u8; GLYPH_HEIGHT]; 256] = [
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x00
+	[0x00, 0x00, 0x7e, 0x81, 0xa5, 0x81, 0x81, 0xbd,
+	 0x99, 0x81, 0x81, 0x7e, 0x00, 0x00, 0x00, 0x00], // 0x01
+	[0x00, 0x00, 0x7e, 0xff, 0xdb, 0xff, 0xff, 0xc3,
+	 0xe7, 0xff, 0xff, 0x7e, 0x00, 0x00, 0x00, 0x00], // 0x02
+	[0x00, 0x00, 0x00, 0x00, 0x36, 0x7f, 0x7f, 0x7f,
+	 0x7f, 0x3e, 0x1c, 0x08, 0x00, 0x00, 0x00, 0x00], // 0x03
+	[0x00, 0x00, 0x00, 0x00, 0x08, 0x1c, 0x3e, 0x7f,
+	 0x3e, 0x1c, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x04
+	[0x00, 0x00, 0x00, 0x18, 0x3c, 0x3c, 0xe7, 0xe7,
+	 0xe7, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x05
+	[0x00, 0x00, 0x00, 0x18, 0x3c, 0x7e, 0xff, 0xff,
+	 0x7e, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x06
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x3c,
+	 0x3c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x07
+	[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xe7, 0xc3,
+	 0xc3, 0xe7, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], // 0x08
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3c, 0x66, 0x42,
+	 0x42, 0x66, 0x3c, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x09
+	[0xff, 0xff, 0xff, 0xff, 0xff, 0xc3, 0x99, 0xbd,
+	 0xbd, 0x99, 0xc3, 0xff, 0xff, 0xff, 0xff, 0xff], // 0x0a
+	[0x00, 0x00, 0x78, 0x70, 0x58, 0x4c, 0x1e, 0x33,
+	 0x33, 0x33, 0x33, 0x1e, 0x00, 0x00, 0x00, 0x00], // 0x0b
+	[0x00, 0x00, 0x3c, 0x66, 0x66, 0x66, 0x66, 0x3c,
+	 0x18, 0x7e, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x0c
+	[0x00, 0x00, 0xfc, 0xcc, 0xfc, 0x0c, 0x0c, 0x0c,
+	 0x0c, 0x0e, 0x0f, 0x07, 0x00, 0x00, 0x00, 0x00], // 0x0d
+	[0x00, 0x00, 0xfe, 0xc6, 0xfe, 0xc6, 0xc6, 0xc6,
+	 0xc6, 0xe6, 0xe7, 0x67, 0x03, 0x00, 0x00, 0x00], // 0x0e
+	[0x00, 0x00, 0x00, 0x18, 0x18, 0xdb, 0x3c, 0xe7,
+	 0x3c, 0xdb, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x0f
+	[0x00, 0x01, 0x03, 0x07, 0x0f, 0x1f, 0x7f, 0x1f,
+	 0x0f, 0x07, 0x03, 0x01, 0x00, 0x00, 0x00, 0x00], // 0x10
+	[0x00, 0x40, 0x60, 0x70, 0x78, 0x7c, 0x7f, 0x7c,
+	 0x78, 0x70, 0x60, 0x40, 0x00, 0x00, 0x00, 0x00], // 0x11
+	[0x00, 0x00, 0x18, 0x3c, 0x7e, 0x18, 0x18, 0x18,
+	 0x7e, 0x3c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x12
+	[0x00, 0x00, 0x66, 0x66, 0x66, 0x66, 0x66, 0x66,
+	 0x66, 0x00, 0x66, 0x66, 0x00, 0x00, 0x00, 0x00], // 0x13
+	[0x00, 0x00, 0xfe, 0xdb, 0xdb, 0xdb, 0xde, 0xd8,
+	 0xd8, 0xd8, 0xd8, 0xd8, 0x00, 0x00, 0x00, 0x00], // 0x14
+	[0x00, 0x3e, 0x63, 0x06, 0x1c, 0x36, 0x63, 0x63,
+	 0x36, 0x1c, 0x30, 0x63, 0x3e, 0x00, 0x00, 0x00], // 0x15
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x7f, 0x7f, 0x7f, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0x16
+	[0x00, 0x00, 0x18, 0x3c, 0x7e, 0x18, 0x18, 0x18,
+	 0x7e, 0x3c, 0x18, 0x7e, 0x00, 0x00, 0x00, 0x00], // 0x17
+	[0x00, 0x00, 0x18, 0x3c, 0x7e, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x18
+	[0x00, 0x00, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18,
+	 0x18, 0x7e, 0x3c, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x19
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x30, 0x7f,
+	 0x30, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x1a
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x0c, 0x06, 0x7f,
+	 0x06, 0x0c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x1b
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x03, 0x03,
+	 0x03, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x1c
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x14, 0x36, 0x7f,
+	 0x36, 0x14, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x1d
+	[0x00, 0x00, 0x00, 0x00, 0x08, 0x1c, 0x1c, 0x3e,
+	 0x3e, 0x7f, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x1e
+	[0x00, 0x00, 0x00, 0x00, 0x7f, 0x7f, 0x3e, 0x3e,
+	 0x1c, 0x1c, 0x08, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x1f
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x20
+	[0x00, 0x00, 0x18, 0x3c, 0x3c, 0x3c, 0x18, 0x18,
+	 0x18, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x21 !
+	[0x00, 0x66, 0x66, 0x66, 0x24, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x22 "
+	[0x00, 0x00, 0x00, 0x36, 0x36, 0x7f, 0x36, 0x36,
+	 0x36, 0x7f, 0x36, 0x36, 0x00, 0x00, 0x00, 0x00], // 0x23 #
+	[0x18, 0x18, 0x3e, 0x63, 0x43, 0x03, 0x3e, 0x60,
+	 0x60, 0x61, 0x63, 0x3e, 0x18, 0x18, 0x00, 0x00], // 0x24 $
+	[0x00, 0x00, 0x00, 0x00, 0x43, 0x63, 0x30, 0x18,
+	 0x0c, 0x06, 0x63, 0x61, 0x00, 0x00, 0x00, 0x00], // 0x25 %
+	[0x00, 0x00, 0x1c, 0x36, 0x36, 0x1c, 0x6e, 0x3b,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x26 &
+	[0x00, 0x0c, 0x0c, 0x0c, 0x06, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x27 '
+	[0x00, 0x00, 0x30, 0x18, 0x0c, 0x0c, 0x0c, 0x0c,
+	 0x0c, 0x0c, 0x18, 0x30, 0x00, 0x00, 0x00, 0x00], // 0x28 (
+	[0x00, 0x00, 0x0c, 0x18, 0x30, 0x30, 0x30, 0x30,
+	 0x30, 0x30, 0x18, 0x0c, 0x00, 0x00, 0x00, 0x00], // 0x29 )
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x66, 0x3c, 0xff,
+	 0x3c, 0x66, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x2a *
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x7e,
+	 0x18, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x2b +
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x18, 0x18, 0x18, 0x0c, 0x00, 0x00, 0x00], // 0x2c ,
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7f,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x2d -
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x2e .
+	[0x00, 0x00, 0x00, 0x00, 0x40, 0x60, 0x30, 0x18,
+	 0x0c, 0x06, 0x03, 0x01, 0x00, 0x00, 0x00, 0x00], // 0x2f /
+	[0x00, 0x00, 0x1c, 0x36, 0x63, 0x63, 0x6b, 0x6b,
+	 0x63, 0x63, 0x36, 0x1c, 0x00, 0x00, 0x00, 0x00], // 0x30 0
+	[0x00, 0x00, 0x18, 0x1c, 0x1e, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x7e, 0x00, 0x00, 0x00, 0x00], // 0x31 1
+	[0x00, 0x00, 0x3e, 0x63, 0x60, 0x30, 0x18, 0x0c,
+	 0x06, 0x03, 0x63, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0x32 2
+	[0x00, 0x00, 0x3e, 0x63, 0x60, 0x60, 0x3c, 0x60,
+	 0x60, 0x60, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x33 3
+	[0x00, 0x00, 0x30, 0x38, 0x3c, 0x36, 0x33, 0x7f,
+	 0x30, 0x30, 0x30, 0x78, 0x00, 0x00, 0x00, 0x00], // 0x34 4
+	[0x00, 0x00, 0x7f, 0x03, 0x03, 0x03, 0x3f, 0x60,
+	 0x60, 0x60, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x35 5
+	[0x00, 0x00, 0x1c, 0x06, 0x03, 0x03, 0x3f, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x36 6
+	[0x00, 0x00, 0x7f, 0x63, 0x60, 0x60, 0x30, 0x18,
+	 0x0c, 0x0c, 0x0c, 0x0c, 0x00, 0x00, 0x00, 0x00], // 0x37 7
+	[0x00, 0x00, 0x3e, 0x63, 0x63, 0x63, 0x3e, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x38 8
+	[0x00, 0x00, 0x3e, 0x63, 0x63, 0x63, 0x7e, 0x60,
+	 0x60, 0x60, 0x30, 0x1e, 0x00, 0x00, 0x00, 0x00], // 0x39 9
+	[0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00,
+	 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x3a :
+	[0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x00,
+	 0x00, 0x18, 0x18, 0x0c, 0x00, 0x00, 0x00, 0x00], // 0x3b ;
+	[0x00, 0x00, 0x00, 0x60, 0x30, 0x18, 0x0c, 0x06,
+	 0x0c, 0x18, 0x30, 0x60, 0x00, 0x00, 0x00, 0x00], // 0x3c <
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x7e, 0x00, 0x00,
+	 0x7e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x3d =
+	[0x00, 0x00, 0x00, 0x06, 0x0c, 0x18, 0x30, 0x60,
+	 0x30, 0x18, 0x0c, 0x06, 0x00, 0x00, 0x00, 0x00], // 0x3e >
+	[0x00, 0x00, 0x3e, 0x63, 0x63, 0x30, 0x18, 0x18,
+	 0x18, 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x3f ?
+	[0x00, 0x00, 0x00, 0x3e, 0x63, 0x63, 0x7b, 0x7b,
+	 0x7b, 0x3b, 0x03, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x40 @
+	[0x00, 0x00, 0x08, 0x1c, 0x36, 0x63, 0x63, 0x7f,
+	 0x63, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x41 A
+	[0x00, 0x00, 0x3f, 0x66, 0x66, 0x66, 0x3e, 0x66,
+	 0x66, 0x66, 0x66, 0x3f, 0x00, 0x00, 0x00, 0x00], // 0x42 B
+	[0x00, 0x00, 0x3c, 0x66, 0x43, 0x03, 0x03, 0x03,
+	 0x03, 0x43, 0x66, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x43 C
+	[0x00, 0x00, 0x1f, 0x36, 0x66, 0x66, 0x66, 0x66,
+	 0x66, 0x66, 0x36, 0x1f, 0x00, 0x00, 0x00, 0x00], // 0x44 D
+	[0x00, 0x00, 0x7f, 0x66, 0x46, 0x16, 0x1e, 0x16,
+	 0x06, 0x46, 0x66, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0x45 E
+	[0x00, 0x00, 0x7f, 0x66, 0x46, 0x16, 0x1e, 0x16,
+	 0x06, 0x06, 0x06, 0x0f, 0x00, 0x00, 0x00, 0x00], // 0x46 F
+	[0x00, 0x00, 0x3c, 0x66, 0x43, 0x03, 0x03, 0x7b,
+	 0x63, 0x63, 0x66, 0x5c, 0x00, 0x00, 0x00, 0x00], // 0x47 G
+	[0x00, 0x00, 0x63, 0x63, 0x63, 0x63, 0x7f, 0x63,
+	 0x63, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x48 H
+	[0x00, 0x00, 0x3c, 0x18, 0x18, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x49 I
+	[0x00, 0x00, 0x78, 0x30, 0x30, 0x30, 0x30, 0x30,
+	 0x33, 0x33, 0x33, 0x1e, 0x00, 0x00, 0x00, 0x00], // 0x4a J
+	[0x00, 0x00, 0x67, 0x66, 0x66, 0x36, 0x1e, 0x1e,
+	 0x36, 0x66, 0x66, 0x67, 0x00, 0x00, 0x00, 0x00], // 0x4b K
+	[0x00, 0x00, 0x0f, 0x06, 0x06, 0x06, 0x06, 0x06,
+	 0x06, 0x46, 0x66, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0x4c L
+	[0x00, 0x00, 0x63, 0x77, 0x7f, 0x7f, 0x6b, 0x63,
+	 0x63, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x4d M
+	[0x00, 0x00, 0x63, 0x67, 0x6f, 0x7f, 0x7b, 0x73,
+	 0x63, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x4e N
+	[0x00, 0x00, 0x3e, 0x63, 0x63, 0x63, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x4f O
+	[0x00, 0x00, 0x3f, 0x66, 0x66, 0x66, 0x3e, 0x06,
+	 0x06, 0x06, 0x06, 0x0f, 0x00, 0x00, 0x00, 0x00], // 0x50 P
+	[0x00, 0x00, 0x3e, 0x63, 0x63, 0x63, 0x63, 0x63,
+	 0x63, 0x6b, 0x7b, 0x3e, 0x30, 0x70, 0x00, 0x00], // 0x51 Q
+	[0x00, 0x00, 0x3f, 0x66, 0x66, 0x66, 0x3e, 0x36,
+	 0x66, 0x66, 0x66, 0x67, 0x00, 0x00, 0x00, 0x00], // 0x52 R
+	[0x00, 0x00, 0x3e, 0x63, 0x63, 0x06, 0x1c, 0x30,
+	 0x60, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x53 S
+	[0x00, 0x00, 0x7e, 0x7e, 0x5a, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x54 T
+	[0x00, 0x00, 0x63, 0x63, 0x63, 0x63, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x55 U
+	[0x00, 0x00, 0x63, 0x63, 0x63, 0x63, 0x63, 0x63,
+	 0x63, 0x36, 0x1c, 0x08, 0x00, 0x00, 0x00, 0x00], // 0x56 V
+	[0x00, 0x00, 0x63, 0x63, 0x63, 0x63, 0x6b, 0x6b,
+	 0x6b, 0x7f, 0x77, 0x36, 0x00, 0x00, 0x00, 0x00], // 0x57 W
+	[0x00, 0x00, 0x63, 0x63, 0x36, 0x3e, 0x1c, 0x1c,
+	 0x3e, 0x36, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x58 X
+	[0x00, 0x00, 0x66, 0x66, 0x66, 0x66, 0x3c, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x59 Y
+	[0x00, 0x00, 0x7f, 0x63, 0x61, 0x30, 0x18, 0x0c,
+	 0x06, 0x43, 0x63, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0x5a Z
+	[0x00, 0x00, 0x3c, 0x0c, 0x0c, 0x0c, 0x0c, 0x0c,
+	 0x0c, 0x0c, 0x0c, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x5b [
+	[0x00, 0x00, 0x00, 0x01, 0x03, 0x07, 0x0e, 0x1c,
+	 0x38, 0x70, 0x60, 0x40, 0x00, 0x00, 0x00, 0x00], // 0x5c \
+	[0x00, 0x00, 0x3c, 0x30, 0x30, 0x30, 0x30, 0x30,
+	 0x30, 0x30, 0x30, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x5d ]
+	[0x08, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x5e ^
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0x00], // 0x5f _
+	[0x0c, 0x0c, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x60 `
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x1e, 0x30, 0x3e,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x61 a
+	[0x00, 0x00, 0x07, 0x06, 0x06, 0x1e, 0x36, 0x66,
+	 0x66, 0x66, 0x66, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x62 b
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3e, 0x63, 0x03,
+	 0x03, 0x03, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x63 c
+	[0x00, 0x00, 0x38, 0x30, 0x30, 0x3c, 0x36, 0x33,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x64 d
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3e, 0x63, 0x7f,
+	 0x03, 0x03, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x65 e
+	[0x00, 0x00, 0x1c, 0x36, 0x26, 0x06, 0x0f, 0x06,
+	 0x06, 0x06, 0x06, 0x0f, 0x00, 0x00, 0x00, 0x00], // 0x66 f
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x6e, 0x33, 0x33,
+	 0x33, 0x33, 0x33, 0x3e, 0x30, 0x33, 0x1e, 0x00], // 0x67 g
+	[0x00, 0x00, 0x07, 0x06, 0x06, 0x36, 0x6e, 0x66,
+	 0x66, 0x66, 0x66, 0x67, 0x00, 0x00, 0x00, 0x00], // 0x68 h
+	[0x00, 0x00, 0x18, 0x18, 0x00, 0x1c, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x69 i
+	[0x00, 0x00, 0x60, 0x60, 0x00, 0x70, 0x60, 0x60,
+	 0x60, 0x60, 0x60, 0x60, 0x66, 0x66, 0x3c, 0x00], // 0x6a j
+	[0x00, 0x00, 0x07, 0x06, 0x06, 0x66, 0x36, 0x1e,
+	 0x1e, 0x36, 0x66, 0x67, 0x00, 0x00, 0x00, 0x00], // 0x6b k
+	[0x00, 0x00, 0x1c, 0x18, 0x18, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x6c l
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x37, 0x7f, 0x6b,
+	 0x6b, 0x6b, 0x6b, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x6d m
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3b, 0x66, 0x66,
+	 0x66, 0x66, 0x66, 0x66, 0x00, 0x00, 0x00, 0x00], // 0x6e n
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3e, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x6f o
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3b, 0x66, 0x66,
+	 0x66, 0x66, 0x66, 0x3e, 0x06, 0x06, 0x0f, 0x00], // 0x70 p
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x6e, 0x33, 0x33,
+	 0x33, 0x33, 0x33, 0x3e, 0x30, 0x30, 0x78, 0x00], // 0x71 q
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3b, 0x6e, 0x66,
+	 0x06, 0x06, 0x06, 0x0f, 0x00, 0x00, 0x00, 0x00], // 0x72 r
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x3e, 0x63, 0x06,
+	 0x1c, 0x30, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x73 s
+	[0x00, 0x00, 0x08, 0x0c, 0x0c, 0x3f, 0x0c, 0x0c,
+	 0x0c, 0x0c, 0x6c, 0x38, 0x00, 0x00, 0x00, 0x00], // 0x74 t
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x33, 0x33, 0x33,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x75 u
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x66, 0x66, 0x66,
+	 0x66, 0x66, 0x3c, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x76 v
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x63, 0x63, 0x6b,
+	 0x6b, 0x6b, 0x7f, 0x36, 0x00, 0x00, 0x00, 0x00], // 0x77 w
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x63, 0x36, 0x1c,
+	 0x1c, 0x1c, 0x36, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x78 x
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x63, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x7e, 0x60, 0x30, 0x1f, 0x00], // 0x79 y
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x7f, 0x33, 0x18,
+	 0x0c, 0x06, 0x63, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0x7a z
+	[0x00, 0x00, 0x70, 0x18, 0x18, 0x18, 0x0e, 0x18,
+	 0x18, 0x18, 0x18, 0x70, 0x00, 0x00, 0x00, 0x00], // 0x7b {
+	[0x00, 0x00, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x7c |
+	[0x00, 0x00, 0x0e, 0x18, 0x18, 0x18, 0x70, 0x18,
+	 0x18, 0x18, 0x18, 0x0e, 0x00, 0x00, 0x00, 0x00], // 0x7d }
+	[0x00, 0x00, 0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x7e ~
+	[0x00, 0x00, 0x00, 0x00, 0x08, 0x1c, 0x36, 0x63,
+	 0x63, 0x63, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x00], // 0x7f
+	[0x00, 0x00, 0x3c, 0x66, 0x43, 0x03, 0x03, 0x03,
+	 0x43, 0x66, 0x3c, 0x30, 0x60, 0x3e, 0x00, 0x00], // 0x80
+	[0x00, 0x00, 0x33, 0x00, 0x00, 0x33, 0x33, 0x33,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x81
+	[0x00, 0x30, 0x18, 0x0c, 0x00, 0x3e, 0x63, 0x7f,
+	 0x03, 0x03, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x82
+	[0x00, 0x08, 0x1c, 0x36, 0x00, 0x1e, 0x30, 0x3e,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x83
+	[0x00, 0x00, 0x33, 0x00, 0x00, 0x1e, 0x30, 0x3e,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x84
+	[0x00, 0x06, 0x0c, 0x18, 0x00, 0x1e, 0x30, 0x3e,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x85
+	[0x00, 0x1c, 0x36, 0x1c, 0x00, 0x1e, 0x30, 0x3e,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x86
+	[0x00, 0x00, 0x00, 0x00, 0x3c, 0x66, 0x06, 0x06,
+	 0x66, 0x3c, 0x30, 0x60, 0x3c, 0x00, 0x00, 0x00], // 0x87
+	[0x00, 0x08, 0x1c, 0x36, 0x00, 0x3e, 0x63, 0x7f,
+	 0x03, 0x03, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x88
+	[0x00, 0x00, 0x63, 0x00, 0x00, 0x3e, 0x63, 0x7f,
+	 0x03, 0x03, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x89
+	[0x00, 0x06, 0x0c, 0x18, 0x00, 0x3e, 0x63, 0x7f,
+	 0x03, 0x03, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x8a
+	[0x00, 0x00, 0x66, 0x00, 0x00, 0x1c, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x8b
+	[0x00, 0x18, 0x3c, 0x66, 0x00, 0x1c, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x8c
+	[0x00, 0x06, 0x0c, 0x18, 0x00, 0x1c, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0x8d
+	[0x00, 0x63, 0x00, 0x08, 0x1c, 0x36, 0x63, 0x63,
+	 0x7f, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x8e
+	[0x1c, 0x36, 0x1c, 0x00, 0x1c, 0x36, 0x63, 0x63,
+	 0x7f, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x8f
+	[0x18, 0x0c, 0x06, 0x00, 0x7f, 0x66, 0x06, 0x3e,
+	 0x06, 0x06, 0x66, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0x90
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x33, 0x6e, 0x6c,
+	 0x7e, 0x1b, 0x1b, 0x76, 0x00, 0x00, 0x00, 0x00], // 0x91
+	[0x00, 0x00, 0x7c, 0x36, 0x33, 0x33, 0x7f, 0x33,
+	 0x33, 0x33, 0x33, 0x73, 0x00, 0x00, 0x00, 0x00], // 0x92
+	[0x00, 0x08, 0x1c, 0x36, 0x00, 0x3e, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x93
+	[0x00, 0x00, 0x63, 0x00, 0x00, 0x3e, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x94
+	[0x00, 0x06, 0x0c, 0x18, 0x00, 0x3e, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x95
+	[0x00, 0x0c, 0x1e, 0x33, 0x00, 0x33, 0x33, 0x33,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x96
+	[0x00, 0x06, 0x0c, 0x18, 0x00, 0x33, 0x33, 0x33,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0x97
+	[0x00, 0x00, 0x63, 0x00, 0x00, 0x63, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x7e, 0x60, 0x30, 0x1e, 0x00], // 0x98
+	[0x00, 0x63, 0x00, 0x3e, 0x63, 0x63, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x99
+	[0x00, 0x63, 0x00, 0x63, 0x63, 0x63, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0x9a
+	[0x00, 0x18, 0x18, 0x3e, 0x63, 0x03, 0x03, 0x03,
+	 0x63, 0x3e, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x9b
+	[0x00, 0x1c, 0x36, 0x26, 0x06, 0x0f, 0x06, 0x06,
+	 0x06, 0x06, 0x67, 0x3f, 0x00, 0x00, 0x00, 0x00], // 0x9c
+	[0x00, 0x00, 0x66, 0x66, 0x3c, 0x18, 0x7e, 0x18,
+	 0x7e, 0x18, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0x9d
+	[0x00, 0x1f, 0x33, 0x33, 0x1f, 0x23, 0x33, 0x7b,
+	 0x33, 0x33, 0x33, 0x63, 0x00, 0x00, 0x00, 0x00], // 0x9e
+	[0x00, 0x70, 0xd8, 0x18, 0x18, 0x18, 0x7e, 0x18,
+	 0x18, 0x18, 0x1b, 0x0e, 0x00, 0x00, 0x00, 0x00], // 0x9f
+	[0x00, 0x18, 0x0c, 0x06, 0x00, 0x1e, 0x30, 0x3e,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0xa0
+	[0x00, 0x30, 0x18, 0x0c, 0x00, 0x1c, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0xa1
+	[0x00, 0x18, 0x0c, 0x06, 0x00, 0x3e, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0xa2
+	[0x00, 0x18, 0x0c, 0x06, 0x00, 0x33, 0x33, 0x33,
+	 0x33, 0x33, 0x33, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0xa3
+	[0x00, 0x00, 0x6e, 0x3b, 0x00, 0x3b, 0x66, 0x66,
+	 0x66, 0x66, 0x66, 0x66, 0x00, 0x00, 0x00, 0x00], // 0xa4
+	[0x6e, 0x3b, 0x00, 0x63, 0x67, 0x6f, 0x7f, 0x7b,
+	 0x73, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0xa5
+	[0x00, 0x3c, 0x36, 0x36, 0x7c, 0x00, 0x7e, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xa6
+	[0x00, 0x1c, 0x36, 0x36, 0x1c, 0x00, 0x3e, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xa7
+	[0x00, 0x00, 0x0c, 0x0c, 0x00, 0x0c, 0x0c, 0x06,
+	 0x03, 0x63, 0x63, 0x3e, 0x00, 0x00, 0x00, 0x00], // 0xa8
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7f, 0x03,
+	 0x03, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xa9
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7f, 0x60,
+	 0x60, 0x60, 0x60, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xaa
+	[0x00, 0x06, 0x07, 0x46, 0x66, 0x36, 0x18, 0x0c,
+	 0x06, 0x3b, 0x61, 0x30, 0x18, 0x7c, 0x00, 0x00], // 0xab
+	[0x00, 0x06, 0x07, 0x46, 0x66, 0x36, 0x18, 0x0c,
+	 0x66, 0x73, 0x59, 0xfc, 0x60, 0x60, 0x00, 0x00], // 0xac
+	[0x00, 0x00, 0x18, 0x18, 0x00, 0x18, 0x18, 0x18,
+	 0x3c, 0x3c, 0x3c, 0x18, 0x00, 0x00, 0x00, 0x00], // 0xad
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x6c, 0x36, 0x1b,
+	 0x36, 0x6c, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xae
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x1b, 0x36, 0x6c,
+	 0x36, 0x1b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xaf
+	[0x88, 0x22, 0x88, 0x22, 0x88, 0x22, 0x88, 0x22,
+	 0x88, 0x22, 0x88, 0x22, 0x88, 0x22, 0x88, 0x22], // 0xb0
+	[0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55,
+	 0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55, 0xaa, 0x55], // 0xb1
+	[0xbb, 0xee, 0xbb, 0xee, 0xbb, 0xee, 0xbb, 0xee,
+	 0xbb, 0xee, 0xbb, 0xee, 0xbb, 0xee, 0xbb, 0xee], // 0xb2
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xb3
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1f,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xb4
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x1f, 0x18, 0x1f,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xb5
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6f,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xb6
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x7f,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xb7
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x1f, 0x18, 0x1f,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xb8
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6f, 0x60, 0x6f,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xb9
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xba
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x7f, 0x60, 0x6f,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xbb
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6f, 0x60, 0x7f,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xbc
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x7f,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xbd
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x1f, 0x18, 0x1f,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xbe
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x1f,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xbf
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0xf8,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xc0
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0xff,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xc1
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xc2
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0xf8,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xc3
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xc4
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0xff,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xc5
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0xf8, 0x18, 0xf8,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xc6
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xec,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xc7
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xec, 0x0c, 0xfc,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xc8
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0xfc, 0x0c, 0xec,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xc9
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xef, 0x00, 0xff,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xca
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0xef,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xcb
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xec, 0x0c, 0xec,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xcc
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0xff,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xcd
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xef, 0x00, 0xef,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xce
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0xff, 0x00, 0xff,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xcf
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xff,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xd0
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0xff, 0x00, 0xff,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xd1
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xd2
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xfc,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xd3
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0xf8, 0x18, 0xf8,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xd4
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0xf8, 0x18, 0xf8,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xd5
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xfc,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xd6
+	[0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0xff,
+	 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c, 0x6c], // 0xd7
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0xff, 0x18, 0xff,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xd8
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x1f,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xd9
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf8,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xda
+	[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff,
+	 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], // 0xdb
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xff,
+	 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff], // 0xdc
+	[0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f,
+	 0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f, 0x0f], // 0xdd
+	[0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0,
+	 0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0, 0xf0], // 0xde
+	[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xdf
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x6e, 0x3b, 0x1b,
+	 0x1b, 0x1b, 0x3b, 0x6e, 0x00, 0x00, 0x00, 0x00], // 0xe0
+	[0x00, 0x00, 0x1e, 0x33, 0x33, 0x33, 0x1b, 0x33,
+	 0x63, 0x63, 0x63, 0x33, 0x00, 0x00, 0x00, 0x00], // 0xe1
+	[0x00, 0x00, 0x7f, 0x63, 0x63, 0x03, 0x03, 0x03,
+	 0x03, 0x03, 0x03, 0x03, 0x00, 0x00, 0x00, 0x00], // 0xe2
+	[0x00, 0x00, 0x00, 0x00, 0x7f, 0x36, 0x36, 0x36,
+	 0x36, 0x36, 0x36, 0x36, 0x00, 0x00, 0x00, 0x00], // 0xe3
+	[0x00, 0x00, 0x00, 0x7f, 0x63, 0x06, 0x0c, 0x18,
+	 0x0c, 0x06, 0x63, 0x7f, 0x00, 0x00, 0x00, 0x00], // 0xe4
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x7e, 0x1b, 0x1b,
+	 0x1b, 0x1b, 0x1b, 0x0e, 0x00, 0x00, 0x00, 0x00], // 0xe5
+	[0x00, 0x00, 0x00, 0x00, 0x66, 0x66, 0x66, 0x66,
+	 0x66, 0x3e, 0x06, 0x06, 0x03, 0x00, 0x00, 0x00], // 0xe6
+	[0x00, 0x00, 0x00, 0x00, 0x6e, 0x3b, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00], // 0xe7
+	[0x00, 0x00, 0x00, 0x7e, 0x18, 0x3c, 0x66, 0x66,
+	 0x66, 0x3c, 0x18, 0x7e, 0x00, 0x00, 0x00, 0x00], // 0xe8
+	[0x00, 0x00, 0x00, 0x1c, 0x36, 0x63, 0x63, 0x7f,
+	 0x63, 0x63, 0x36, 0x1c, 0x00, 0x00, 0x00, 0x00], // 0xe9
+	[0x00, 0x00, 0x1c, 0x36, 0x63, 0x63, 0x63, 0x36,
+	 0x36, 0x36, 0x36, 0x77, 0x00, 0x00, 0x00, 0x00], // 0xea
+	[0x00, 0x00, 0x78, 0x0c, 0x18, 0x30, 0x7c, 0x66,
+	 0x66, 0x66, 0x66, 0x3c, 0x00, 0x00, 0x00, 0x00], // 0xeb
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x7e, 0xdb, 0xdb,
+	 0xdb, 0x7e, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xec
+	[0x00, 0x00, 0x00, 0xc0, 0x60, 0x7e, 0xdb, 0xdb,
+	 0xcf, 0x7e, 0x06, 0x03, 0x00, 0x00, 0x00, 0x00], // 0xed
+	[0x00, 0x00, 0x38, 0x0c, 0x06, 0x06, 0x3e, 0x06,
+	 0x06, 0x06, 0x0c, 0x38, 0x00, 0x00, 0x00, 0x00], // 0xee
+	[0x00, 0x00, 0x00, 0x3e, 0x63, 0x63, 0x63, 0x63,
+	 0x63, 0x63, 0x63, 0x63, 0x00, 0x00, 0x00, 0x00], // 0xef
+	[0x00, 0x00, 0x00, 0x00, 0x7f, 0x00, 0x00, 0x7f,
+	 0x00, 0x00, 0x7f, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xf0
+	[0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x7e, 0x18,
+	 0x18, 0x00, 0x00, 0xff, 0x00, 0x00, 0x00, 0x00], // 0xf1
+	[0x00, 0x00, 0x00, 0x0c, 0x18, 0x30, 0x60, 0x30,
+	 0x18, 0x0c, 0x00, 0x7e, 0x00, 0x00, 0x00, 0x00], // 0xf2
+	[0x00, 0x00, 0x00, 0x30, 0x18, 0x0c, 0x06, 0x0c,
+	 0x18, 0x30, 0x00, 0x7e, 0x00, 0x00, 0x00, 0x00], // 0xf3
+	[0x00, 0x00, 0x70, 0xd8, 0xd8, 0x18, 0x18, 0x18,
+	 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18], // 0xf4
+	[0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18, 0x18,
+	 0x1b, 0x1b, 0x1b, 0x0e, 0x00, 0x00, 0x00, 0x00], // 0xf5
+	[0x00, 0x00, 0x00, 0x00, 0x18, 0x18, 0x00, 0x7e,
+	 0x00, 0x18, 0x18, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xf6
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x6e, 0x3b, 0x00,
+	 0x6e, 0x3b, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xf7
+	[0x00, 0x1c, 0x36, 0x36, 0x1c, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xf8
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18,
+	 0x18, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xf9
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x18,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xfa
+	[0x00, 0xf0, 0x30, 0x30, 0x30, 0x30, 0x30, 0x37,
+	 0x36, 0x36, 0x3c, 0x38, 0x00, 0x00, 0x00, 0x00], // 0xfb
+	[0x00, 0x1b, 0x36, 0x36, 0x36, 0x36, 0x36, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xfc
+	[0x00, 0x0e, 0x1b, 0x0c, 0x06, 0x13, 0x1f, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xfd
+	[0x00, 0x00, 0x00, 0x00, 0x3e, 0x3e, 0x3e, 0x3e,
+	 0x3e, 0x3e, 0x3e, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xfe
+	[0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
+	 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00], // 0xff
+];
+
+/// Returns the 8x8 glyph of `byte`.
+pub fn glyph_8x8(byte: u8) -> [u8; 8] {
+	return FONT_8X8[byte as usize];
+}
+
+/// Returns the 8x16 glyph of `byte`.
+pub fn glyph_8x16(byte: u8) -> [u8; GLYPH_HEIGHT] {
+	return FONT_8X16[byte as usize];
+}
@@ -0,0 +1,208 @@
+//! Text console on a linear pixel framebuffer.
+//!
+//! When the bootloader sets up a graphics mode instead of VGA text mode, the
+//! [`Writer`](super::tty::Writer) keeps its character cells in an off-screen
+//! [`Buffer`] and draws every changed cell here with the 8x16
+//! [font](super::font). The text grid stays `VGA_WIDTH` x `VGA_HEIGHT`, drawn
+//! in the top left corner of the screen, the 80x50 text mode is not available.
+
+use super::{
+	font::{glyph_8x16, GLYPH_HEIGHT, GLYPH_WIDTH},
+	tty::WRITER,
+	Buffer, ColourCode, VgaChar, VgaColour, MAX_VGA_HEIGHT, VGA_HEIGHT,
+	VGA_WIDTH,
+};
+use crate::{
+	arch::x86::multiboot::{ColourField, FramebufferInfo, MultibootInfo},
+	log_info, log_warn,
+	memory::{
+		allocate_dynamic_virt_range,
+		paging::{flags, map_page},
+		VirtAddr, PAGE_SIZE,
+	},
+};
+use core::{
+	ptr,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+/// RGB value of each of the 16 VGA colours.
+const PALETTE: [(u8, u8, u8); 16] = [
+	(0x00, 0x00, 0x00),
+	(0x00, 0x00, 0xaa),
+	(0x00, 0xaa, 0x00),
+	(0x00, 0xaa, 0xaa),
+	(0xaa, 0x00, 0x00),
+	(0xaa, 0x00, 0xaa),
+	(0xaa, 0x55, 0x00),
+	(0xaa, 0xaa, 0xaa),
+	(0x55, 0x55, 0x55),
+	(0x55, 0x55, 0xff),
+	(0x55, 0xff, 0x55),
+	(0x55, 0xff, 0xff),
+	(0xff, 0x55, 0x55),
+	(0xff, 0x55, 0xff),
+	(0xff, 0xff, 0x55),
+	(0xff, 0xff, 0xff),
+];
+
+/// Whether a framebuffer was attached already.
+static ATTACHED: AtomicBool = AtomicBool::new(false);
+
+/// A mapped framebuffer the writer draws its cells into.
+#[derive(Debug)]
+pub struct Framebuffer {
+	base: VirtAddr,
+	pitch: usize,
+	bytes_per_pixel: usize,
+	red: ColourField,
+	green: ColourField,
+	blue: ColourField,
+}
+
+impl Framebuffer {
+	/// Draws `cell` at text position `col`, `row`.
+	pub fn draw_cell(&self, col: usize, row: usize, cell: VgaChar) {
+		let glyph = glyph_8x16(cell.ascii_character);
+		let fg = self.pixel(cell.colour_code.get_foreground_colour());
+		let bg = self.pixel(cell.colour_code.get_background_colour());
+
+		let x = col * GLYPH_WIDTH;
+		let y = row * GLYPH_HEIGHT;
+
+		for (dy, bits) in glyph.iter().enumerate() {
+			for dx in 0..GLYPH_WIDTH {
+				let value = match bits & (1 << dx) {
+					0 => bg,
+					_ => fg,
+				};
+				self.put_pixel(x + dx, y + dy, value);
+			}
+		}
+	}
+
+	/// Draws every cell of text row `row`.
+	pub fn draw_row(&self, row: usize, cells: &[VgaChar; VGA_WIDTH]) {
+		for (col, cell) in cells.iter().enumerate() {
+			self.draw_cell(col, row, *cell);
+		}
+	}
+
+	/// Draws the whole text grid.
+	pub fn draw_all(&self, buffer: &Buffer) {
+		for (row, cells) in buffer.chars[..VGA_HEIGHT].iter().enumerate() {
+			self.draw_row(row, cells);
+		}
+	}
+
+	/// Moves the pixels of text rows 1 and below up by one text row. The last
+	/// row keeps its old pixels until it is redrawn.
+	pub fn scroll_up(&self) {
+		let row_bytes = GLYPH_HEIGHT * self.pitch;
+		let base = ptr::with_exposed_provenance_mut::<u8>(self.base.as_usize());
+
+		unsafe {
+			ptr::copy(base.add(row_bytes), base, (VGA_HEIGHT - 1) * row_bytes);
+		}
+	}
+
+	/// Encodes `colour` in the framebuffer's pixel format.
+	fn pixel(&self, colour: VgaColour) -> u32 {
+		let (r, g, b) = PALETTE[colour as usize];
+
+		return encode(r, self.red)
+			| encode(g, self.green)
+			| encode(b, self.blue);
+	}
+
+	fn put_pixel(&self, x: usize, y: usize, value: u32) {
+		let offset = y * self.pitch + x * self.bytes_per_pixel;
+		let pixel = ptr::with_exposed_provenance_mut::<u8>(
+			self.base.as_usize() + offset,
+		);
+
+		for (i, byte) in value.to_le_bytes()[..self.bytes_per_pixel]
+			.iter()
+			.enumerate()
+		{
+			unsafe { pixel.add(i).write_volatile(*byte) };
+		}
+	}
+}
+
+/// Scales the 8-bit channel `value` down to `field` and shifts it in place.
+fn encode(value: u8, field: ColourField) -> u32 {
+	let size = field.size.min(8);
+	if size == 0 {
+		return 0;
+	}
+
+	return ((value as u32) >> (8 - size)) << field.position;
+}
+
+/// Switches the console to the bootloader's framebuffer, if it set one up
+/// that the text grid fits on. Returns whether the framebuffer is used.
+///
+/// Must run once paging and the frame allocator are up, as the framebuffer is
+/// mapped into the kernel address space.
+pub fn init(boot_info: &MultibootInfo) -> bool {
+	let Some(info) = boot_info.framebuffer() else {
+		return false;
+	};
+
+	if !matches!(info.bpp, 16 | 24 | 32)
+		|| info.width < VGA_WIDTH * GLYPH_WIDTH
+		|| info.height < VGA_HEIGHT * GLYPH_HEIGHT
+	{
+		log_warn!(
+			"framebuffer {}x{}x{} is not supported",
+			info.width,
+			info.height,
+			info.bpp
+		);
+		return false;
+	}
+
+	if ATTACHED.swap(true, Ordering::AcqRel) {
+		return false;
+	}
+
+	let Some(base) = map(&info) else {
+		log_warn!("framebuffer: no virtual address space left to map it");
+		return false;
+	};
+
+	let framebuffer = Framebuffer {
+		base,
+		pitch: info.pitch,
+		bytes_per_pixel: info.bpp as usize / 8,
+		red: info.red,
+		green: info.green,
+		blue: info.blue,
+	};
+
+	WRITER.lock().attach_framebuffer(framebuffer);
+
+	log_info!(
+		"framebuffer console at {:#x}, {}x{}x{}",
+		info.addr.as_usize(),
+		info.width,
+		info.height,
+		info.bpp
+	);
+	return true;
+}
+
+/// Maps the pixel rows the text grid covers.
+fn map(info: &FramebufferInfo) -> Option<VirtAddr> {
+	let start = info.addr.align_down(PAGE_SIZE);
+	let offset = info.addr.as_usize() - start.as_usize();
+	let size = offset + VGA_HEIGHT * GLYPH_HEIGHT * info.pitch;
+
+	let virt = allocate_dynamic_virt_range(size)?;
+	for page in (0..size).step_by(PAGE_SIZE) {
+		map_page(start + page, virt + page, flags::PRESENT | flags::WRITABLE);
+	}
+
+	return Some(virt + offset);
+}
@@ -0,0 +1,201 @@
+//! Kernel log ring buffer.
+//!
+//! Every message passed through the `log_*!` macros is also stored here as a
+//! record of `[seq: u64][timestamp: u64][level: u8][len: u8][text]`, packed
+//! back to back in a fixed-size byte ring. When a new record does not fit, the
+//! oldest records are dropped until it does. Writing never allocates and copies
+//! at most [`MAX_RECORD_TEXT`] bytes, so it is safe from exception handlers.
+
+use super::log::LogLevel;
+use crate::sync::Mutex;
+use core::fmt;
+
+/// Size in bytes of the global kernel log.
+pub const KLOG_SIZE: usize = 8192;
+
+/// Longest record text kept, longer messages are truncated.
+pub const MAX_RECORD_TEXT: usize = u8::MAX as usize;
+
+const HEADER_LEN: usize = 18;
+
+/// Global kernel log, read by `dmesg`.
+pub static KLOG: Mutex<KernelLog<KLOG_SIZE>> =
+	Mutex::named("KLOG", KernelLog::new());
+
+/// Metadata of a record copied out by [`KernelLog::read`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Record {
+	/// Sequence number, increasing by one for every record ever written.
+	pub seq: u64,
+	/// Milliseconds since boot when the record was written.
+	pub timestamp_ms: u64,
+	/// Level the message was logged at.
+	pub level: LogLevel,
+	/// Number of text bytes copied into the caller's buffer.
+	pub len: usize,
+}
+
+/// A ring of log records stored in `N` bytes.
+pub struct KernelLog<const N: usize> {
+	buf: [u8; N],
+	head: usize,
+	used: usize,
+	next_seq: u64,
+	dropped: u64,
+}
+
+impl<const N: usize> Default for KernelLog<N> {
+	fn default() -> Self {
+		return Self::new();
+	}
+}
+
+impl<const N: usize> KernelLog<N> {
+	/// Creates an empty log. `N` must leave room for a record header, which
+	/// is checked at compile time.
+	pub const fn new() -> Self {
+		const { assert!(N > HEADER_LEN, "log too small for a record header") };
+
+		return Self {
+			buf: [0; N],
+			head: 0,
+			used: 0,
+			next_seq: 0,
+			dropped: 0,
+		};
+	}
+
+	/// Formats `args` and appends it as one record, truncating the text to
+	/// [`MAX_RECORD_TEXT`] bytes.
+	pub fn write(
+		&mut self,
+		timestamp_ms: u64,
+		level: LogLevel,
+		args: fmt::Arguments,
+	) {
+		let mut text = TextBuffer {
+			bytes: [0; MAX_RECORD_TEXT],
+			len: 0,
+		};
+		let _ = fmt::Write::write_fmt(&mut text, args);
+
+		self.push(timestamp_ms, level, &text.bytes[..text.len]);
+	}
+
+	/// Appends `text` as one record, dropping the oldest records to make room.
+	pub fn push(&mut self, timestamp_ms: u64, level: LogLevel, text: &[u8]) {
+		let len = text.len().min(MAX_RECORD_TEXT).min(N - HEADER_LEN);
+		let record_len = HEADER_LEN + len;
+
+		while N - self.used < record_len {
+			self.drop_oldest();
+		}
+
+		let mut header = [0; HEADER_LEN];
+		header[..8].copy_from_slice(&self.next_seq.to_le_bytes());
+		header[8..16].copy_from_slice(&timestamp_ms.to_le_bytes());
+		header[16] = level as u8;
+		header[17] = len as u8;
+
+		let tail = (self.head + self.used) % N;
+		self.copy_in(tail, &header);
+		self.copy_in((tail + HEADER_LEN) % N, &text[..len]);
+
+		self.used += record_len;
+		self.next_seq += 1;
+	}
+
+	/// Copies the text of the oldest record with a sequence number of at least
+	/// `seq` into `out`, returning its metadata.
+	pub fn read(
+		&self,
+		seq: u64,
+		out: &mut [u8; MAX_RECORD_TEXT],
+	) -> Option<Record> {
+		let mut offset = 0;
+
+		while offset < self.used {
+			let start = (self.head + offset) % N;
+			let record = self.header_at(start);
+
+			if record.seq >= seq {
+				self.copy_out((start + HEADER_LEN) % N, &mut out[..record.len]);
+				return Some(record);
+			}
+
+			offset += HEADER_LEN + record.len;
+		}
+
+		return None;
+	}
+
+	/// Removes every record. Sequence numbers keep counting.
+	pub fn clear(&mut self) {
+		self.head = 0;
+		self.used = 0;
+		self.dropped = 0;
+	}
+
+	/// Returns the number of records dropped to make room since the last
+	/// [`clear`](Self::clear).
+	pub fn dropped(&self) -> u64 {
+		return self.dropped;
+	}
+
+	/// Returns the sequence number the next record will get.
+	pub fn next_seq(&self) -> u64 {
+		return self.next_seq;
+	}
+
+	fn drop_oldest(&mut self) {
+		let record_len = HEADER_LEN + self.header_at(self.head).len;
+
+		self.head = (self.head + record_len) % N;
+		self.used -= record_len;
+		self.dropped += 1;
+	}
+
+	fn header_at(&self, start: usize) -> Record {
+		let mut header = [0; HEADER_LEN];
+		self.copy_out(start, &mut header);
+
+		let mut seq = [0; 8];
+		seq.copy_from_slice(&header[..8]);
+		let mut timestamp_ms = [0; 8];
+		timestamp_ms.copy_from_slice(&header[8..16]);
+
+		return Record {
+			seq: u64::from_le_bytes(seq),
+			timestamp_ms: u64::from_le_bytes(timestamp_ms),
+			level: LogLevel::from_u8(header[16]).unwrap_or(LogLevel::Error),
+			len: header[17] as usize,
+		};
+	}
+
+	fn copy_in(&mut self, start: usize, bytes: &[u8]) {
+		for (i, byte) in bytes.iter().enumerate() {
+			self.buf[(start + i) % N] = *byte;
+		}
+	}
+
+	fn copy_out(&self, start: usize, bytes: &mut [u8]) {
+		for (i, byte) in bytes.iter_mut().enumerate() {
+			*byte = self.buf[(start + i) % N];
+		}
+	}
+}
+
+/// Stack buffer that silently truncates once full.
+struct TextBuffer {
+	bytes: [u8; MAX_RECORD_TEXT],
+	len: usize,
+}
+
+impl fmt::Write for TextBuffer {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let n = s.len().min(MAX_RECORD_TEXT - self.len);
+		self.bytes[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+		self.len += n;
+		return Ok(());
+	}
+}
@@ -1,17 +1,92 @@
 use crate::{
-	print, println, println_serial,
-	tty::{ColourCode, VgaColour},
+	print, println, println_serial, time,
+	tty::{klog::KLOG, ColourCode, VgaColour},
 	with_fg_color,
 };
-use core::fmt;
+use core::{
+	fmt,
+	sync::atomic::{AtomicU8, Ordering},
+};
 
+#[repr(u8)]
 #[allow(missing_docs)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 pub enum LogLevel {
-	Error,
-	Warn,
-	Info,
-	Debug,
+	Error = 0,
+	Warn = 1,
+	Info = 2,
+	Debug = 3,
+	Trace = 4,
+}
+
+impl LogLevel {
+	/// Every level, from least to most verbose.
+	pub const ALL: [LogLevel; 5] = [
+		LogLevel::Error,
+		LogLevel::Warn,
+		LogLevel::Info,
+		LogLevel::Debug,
+		LogLevel::Trace,
+	];
+
+	/// Converts the `repr(u8)` value back to a level.
+	pub const fn from_u8(value: u8) -> Option<LogLevel> {
+		match value {
+			0 => return Some(LogLevel::Error),
+			1 => return Some(LogLevel::Warn),
+			2 => return Some(LogLevel::Info),
+			3 => return Some(LogLevel::Debug),
+			4 => return Some(LogLevel::Trace),
+			_ => return None,
+		}
+	}
+
+	/// Returns the lowercase name used by the `loglevel` command.
+	pub const fn name(self) -> &'static str {
+		match self {
+			LogLevel::Error => return "error",
+			LogLevel::Warn => return "warn",
+			LogLevel::Info => return "info",
+			LogLevel::Debug => return "debug",
+			LogLevel::Trace => return "trace",
+		}
+	}
+
+	/// Parses a level from its [`name`](Self::name).
+	pub fn from_name(name: &str) -> Option<LogLevel> {
+		return LogLevel::ALL.into_iter().find(|level| level.name() == name);
+	}
+}
+
+/// Most verbose level that is currently emitted. `Trace` is off by default.
+static LEVEL: AtomicU8 = AtomicU8::new(LogLevel::Debug as u8);
+
+/// Sets the most verbose level the `log_*!` macros emit.
+pub fn set_level(level: LogLevel) {
+	LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Returns the most verbose level the `log_*!` macros emit.
+pub fn level() -> LogLevel {
+	return LogLevel::from_u8(LEVEL.load(Ordering::Relaxed))
+		.unwrap_or(LogLevel::Trace);
+}
+
+/// Returns whether messages at `level` are currently emitted. Checked by the
+/// `log_*!` macros before anything is formatted.
+#[inline]
+pub fn enabled(level: LogLevel) -> bool {
+	return level as u8 <= LEVEL.load(Ordering::Relaxed);
+}
+
+/// Milliseconds since boot, displayed as `[seconds.millis]`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timestamp(pub u64);
+
+impl fmt::Display for Timestamp {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		return write!(f, "[{}.{:03}]", self.0 / 1000, self.0 % 1000);
+	}
 }
 
 #[allow(missing_docs, unused)]
@@ -27,17 +102,29 @@ pub fn _log(
 		LogLevel::Warn => ("[WARN]", VgaColour::Yellow),
 		LogLevel::Info => ("[INFO]", VgaColour::LightCyan),
 		LogLevel::Debug => ("[DEBUG]", VgaColour::LightGreen),
+		LogLevel::Trace => ("[TRACE]", VgaColour::DarkGrey),
 	};
 
-	if LogLevel::Debug == level {
+	let now = time::uptime_ms();
+
+	KLOG.lock()
+		.write(now, level, format_args!("[{}] {}", module, args));
+
+	if level >= LogLevel::Debug {
 		println_serial!(
-			"[{}] {} {}",
+			"{} [{}] {} {}",
+			Timestamp(now),
 			format_args!("{}", module),
 			level_str,
 			args
 		);
 	}
 
+	// Trace output is far too chatty for the screen.
+	if level == LogLevel::Trace {
+		return;
+	}
+
 	with_fg_color!(color, {
 		println!("[{}] {} {}", format_args!("{}", module), level_str, args);
 	});
@@ -1,9 +1,31 @@
+/// Code page 437 and its Unicode equivalents
+pub mod cp437;
+/// Bochs and QEMU debug console on port 0xE9
+pub mod e9;
+/// Bitmap font for pixel framebuffers
+pub mod font;
+/// Text console drawn on a multiboot framebuffer
+pub mod framebuffer;
+/// Ring buffer holding every log record for `dmesg`
+pub mod klog;
 /// A simple log function
 pub mod log;
+/// Console sinks `print!` writes to
+pub mod output;
+/// Pauses long command output a screenful at a time
+pub mod pager;
+/// Full screen report drawn by the panic handler
+pub mod panic_screen;
 /// Impl of the SERIAL function to write to the terminal
 pub mod serial;
+/// Uptime and log level on the bottom row
+pub mod status;
 /// Impl of the WRITER function to write to the VGA
 pub mod tty;
+/// VGA text mode registers and fonts
+pub mod vga;
+/// Virtual terminals sharing the VGA screen
+pub mod vt;
 
 /* -------------------------------------- */
 
@@ -12,9 +34,15 @@ use core::mem::transmute;
 #[doc(hidden)]
 pub const VGA_WIDTH: usize = 80;
 
+/// Rows of the default 80x25 text mode. The rows currently shown are
+/// [`Writer::height`](tty::Writer::height).
 #[doc(hidden)]
 pub const VGA_HEIGHT: usize = 25;
 
+/// Rows of the tallest text mode, 80x50.
+#[doc(hidden)]
+pub const MAX_VGA_HEIGHT: usize = 50;
+
 #[doc(hidden)]
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(u8)]
@@ -45,17 +73,20 @@ pub enum VgaColour {
 pub struct ColourCode(u8);
 
 impl ColourCode {
-	pub fn new(foreground: VgaColour, background: VgaColour) -> ColourCode {
+	pub const fn new(
+		foreground: VgaColour,
+		background: VgaColour,
+	) -> ColourCode {
 		ColourCode(((background as u8) << 4) | (foreground as u8))
 	}
 
 	pub fn get_foreground_colour(&self) -> VgaColour {
-		let value = (self.0 >> 4) & 0x0f;
+		let value = self.0 & 0x0f;
 		unsafe { transmute::<u8, VgaColour>(value) }
 	}
 
 	pub fn get_background_colour(&self) -> VgaColour {
-		let value = self.0 << 4;
+		let value = (self.0 >> 4) & 0x0f;
 		unsafe { transmute::<u8, VgaColour>(value) }
 	}
 
@@ -86,8 +117,11 @@ pub struct VgaChar {
 }
 
 /// Buffer which is the a 2D Array of the VGA
+///
+/// It covers the rows of the tallest text mode, only the first
+/// [`Writer::height`](tty::Writer::height) of them are on screen.
 #[doc(hidden)]
 #[repr(transparent)]
 pub struct Buffer {
-	pub chars: [[VgaChar; VGA_WIDTH]; VGA_HEIGHT],
+	pub chars: [[VgaChar; VGA_WIDTH]; MAX_VGA_HEIGHT],
 }
@@ -0,0 +1,210 @@
+//! Console output sinks.
+//!
+//! `print!` formats its arguments once into a small stack buffer and hands the
+//! text to every registered [`ConsoleOutput`], so mirroring to serial or a
+//! future framebuffer is a matter of registering another sink. Each sink takes
+//! its own device lock per chunk, the VGA writer is never held while a slow
+//! UART drains.
+
+use super::{
+	serial::{SERIAL, SERIAL_OUTPUT},
+	tty::{VGA_OUTPUT, WRITER},
+	ColourCode,
+};
+use crate::sync::Mutex;
+use core::{fmt, ptr, str::from_utf8};
+
+/// Number of sinks that can be registered at once.
+pub const MAX_SINKS: usize = 4;
+
+/// Bytes formatted before they are handed to the sinks.
+const CHUNK_SIZE: usize = 128;
+
+type Sinks = [Option<&'static dyn ConsoleOutput>; MAX_SINKS];
+
+static SINKS: Mutex<Sinks> =
+	Mutex::named("SINKS", [Some(&VGA_OUTPUT), None, None, None]);
+
+/// Something console text can be written to.
+///
+/// Positions are cells of the VGA text screen, `VGA_WIDTH` columns by the
+/// writer's current height, so every sink shows the same layout.
+pub trait ConsoleOutput: Sync {
+	/// Writes `s` at the cursor.
+	fn write_str(&self, s: &str);
+	/// Sets the colours of text written from now on.
+	fn set_colour(&self, colour: ColourCode);
+	/// Clears the screen and moves the cursor to the top left.
+	fn clear(&self);
+	/// Moves the cursor to `col`, `row`.
+	fn cursor_to(&self, col: usize, row: usize);
+}
+
+/// Adds `sink` to the list `print!` writes to.
+///
+/// Returns `false` if every slot is taken. Registering a sink twice is a no-op.
+pub fn register(sink: &'static dyn ConsoleOutput) -> bool {
+	let mut sinks = SINKS.lock();
+
+	if sinks.iter().flatten().any(|&other| same_sink(other, sink)) {
+		return true;
+	}
+
+	match sinks.iter_mut().find(|slot| slot.is_none()) {
+		Some(slot) => {
+			*slot = Some(sink);
+			return true;
+		}
+		None => return false,
+	}
+}
+
+/// Removes `sink`, returning whether it was registered.
+pub fn unregister(sink: &'static dyn ConsoleOutput) -> bool {
+	let mut sinks = SINKS.lock();
+
+	for slot in sinks.iter_mut() {
+		if slot.is_some_and(|other| same_sink(other, sink)) {
+			*slot = None;
+			return true;
+		}
+	}
+
+	return false;
+}
+
+/// Returns whether `sink` is registered.
+pub fn is_registered(sink: &'static dyn ConsoleOutput) -> bool {
+	return SINKS
+		.lock()
+		.iter()
+		.flatten()
+		.any(|&other| same_sink(other, sink));
+}
+
+fn same_sink(a: &dyn ConsoleOutput, b: &dyn ConsoleOutput) -> bool {
+	return ptr::addr_eq(a, b);
+}
+
+/// Copies the sink list so no lock is held while writing.
+fn sinks() -> Sinks {
+	return *SINKS.lock();
+}
+
+/* -------------------------------------- */
+
+/// Formats `args` and writes the text to every sink.
+pub fn print(args: fmt::Arguments) {
+	let mut out = Broadcast {
+		sinks: sinks(),
+		buf: [0; CHUNK_SIZE],
+		len: 0,
+	};
+
+	let _ = fmt::write(&mut out, args);
+	out.flush();
+}
+
+/// Returns whether [`print`] would wait for a lock. In an exception handler
+/// that means the interrupted code holds it, and the wait would never end.
+pub fn is_locked() -> bool {
+	return SINKS.is_locked()
+		|| WRITER.is_locked()
+		|| SERIAL.is_locked()
+		|| SERIAL_OUTPUT.is_locked();
+}
+
+/// Breaks every lock [`print`] takes.
+///
+/// # Safety
+/// As for [`Mutex::force_unlock`]: whoever holds them, typically the code a
+/// fatal exception stopped, must never run again.
+pub unsafe fn force_unlock() {
+	unsafe {
+		SINKS.force_unlock();
+		WRITER.force_unlock();
+		SERIAL.force_unlock();
+		SERIAL_OUTPUT.force_unlock();
+	}
+}
+
+/// Returns the colours text is currently written in.
+pub fn colour() -> ColourCode {
+	return WRITER.lock().colour_code;
+}
+
+/// Changes the colours of every sink with `f` and returns the colours from
+/// before, for code that restores them afterwards.
+pub fn update_colour(f: impl FnOnce(&mut ColourCode)) -> ColourCode {
+	let original = colour();
+	let mut colour = original;
+	f(&mut colour);
+	set_colour(colour);
+
+	return original;
+}
+
+/// Sets the colours of every sink.
+pub fn set_colour(colour: ColourCode) {
+	for sink in sinks().into_iter().flatten() {
+		sink.set_colour(colour);
+	}
+}
+
+/// Clears every sink.
+pub fn clear() {
+	for sink in sinks().into_iter().flatten() {
+		sink.clear();
+	}
+}
+
+/// Moves the cursor of every sink.
+pub fn cursor_to(col: usize, row: usize) {
+	for sink in sinks().into_iter().flatten() {
+		sink.cursor_to(col, row);
+	}
+}
+
+/// Collects formatted pieces into chunks. Only whole `&str`s are buffered, so
+/// a chunk is always valid UTF-8.
+struct Broadcast {
+	sinks: Sinks,
+	buf: [u8; CHUNK_SIZE],
+	len: usize,
+}
+
+impl Broadcast {
+	fn write_all(&self, s: &str) {
+		for sink in self.sinks.into_iter().flatten() {
+			sink.write_str(s);
+		}
+	}
+
+	fn flush(&mut self) {
+		if self.len == 0 {
+			return;
+		}
+
+		let chunk = from_utf8(&self.buf[..self.len]).unwrap_or_default();
+		self.write_all(chunk);
+		self.len = 0;
+	}
+}
+
+impl fmt::Write for Broadcast {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		if self.len + s.len() > CHUNK_SIZE {
+			self.flush();
+		}
+
+		if s.len() > CHUNK_SIZE {
+			self.write_all(s);
+		} else {
+			self.buf[self.len..self.len + s.len()]
+				.copy_from_slice(s.as_bytes());
+			self.len += s.len();
+		}
+
+		return Ok(());
+	}
+}
@@ -0,0 +1,133 @@
+//! Output pager, the builtin equivalent of `more`.
+//!
+//! While a pager is active, `print!` output is written one character at a time
+//! and stops once a screenful has been shown since the last pause. The bottom
+//! row then shows `--More--` until a key is read:
+//! - Space shows the next page
+//! - Enter shows one more line
+//! - `q` or Ctrl+C discards the rest of the output until [`end`]
+
+use super::{
+	tty::{Writer, WRITER},
+	ColourCode, VgaColour, VGA_WIDTH,
+};
+use crate::sync::Mutex;
+use core::fmt;
+
+const PROMPT: &str = "--More--";
+
+static PAGER: Mutex<Pager> = Mutex::new(Pager {
+	read_key: None,
+	page_start: 0,
+	aborted: false,
+});
+
+struct Pager {
+	/// Blocks until a key is pressed and returns it, set while active.
+	read_key: Option<fn() -> char>,
+	/// Value of [`Writer::rows_written`] at the top of the current page.
+	page_start: usize,
+	aborted: bool,
+}
+
+/// Starts paging output, reading answers to `--More--` with `read_key`.
+pub fn begin(read_key: fn() -> char) {
+	let mut pager = PAGER.lock();
+
+	pager.read_key = Some(read_key);
+	pager.page_start = WRITER.lock().rows_written();
+	pager.aborted = false;
+}
+
+/// Stops paging, output is written straight through again.
+pub fn end() {
+	let mut pager = PAGER.lock();
+
+	pager.read_key = None;
+	pager.aborted = false;
+}
+
+/// Returns whether output is currently paged.
+pub fn active() -> bool {
+	return PAGER.lock().read_key.is_some();
+}
+
+/// Returns whether the user quit the pager, so long running output can stop
+/// early instead of being discarded line by line.
+pub fn aborted() -> bool {
+	return PAGER.lock().aborted;
+}
+
+/// Writes `s` to the screen, pausing after every screenful.
+pub fn write_str(s: &str) {
+	let mut pager = PAGER.lock();
+	let _ = fmt::Write::write_str(&mut *pager, s);
+}
+
+impl fmt::Write for Pager {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		for (i, c) in s.char_indices() {
+			if !self.aborted && self.page_full(&WRITER.lock()) {
+				self.pause();
+			}
+			if self.aborted {
+				break;
+			}
+
+			WRITER.lock().write_string(&s[i..i + c.len_utf8()]);
+		}
+
+		return Ok(());
+	}
+}
+
+impl Pager {
+	/// Returns whether the next character would start a row past the
+	/// screenful, leaving the bottom row for the prompt.
+	fn page_full(&self, writer: &Writer) -> bool {
+		let (col, _) = writer.position();
+		let new_row = col == 0 || col >= VGA_WIDTH;
+		let rows = writer.rows_written().wrapping_sub(self.page_start)
+			+ usize::from(col > 0);
+
+		return new_row && rows >= writer.usable_height() - 1;
+	}
+
+	fn pause(&mut self) {
+		let Some(read_key) = self.read_key else {
+			return;
+		};
+
+		{
+			let mut writer = WRITER.lock();
+			if writer.position().0 != 0 {
+				writer.write_string("\n");
+			}
+
+			let colour_code = writer.colour_code;
+			writer.colour_code =
+				ColourCode::new(VgaColour::Black, VgaColour::LightGrey);
+			writer.write_string(PROMPT);
+			writer.colour_code = colour_code;
+		}
+
+		let key = loop {
+			if let key @ (' ' | '\n' | 'q' | '\x03') = read_key() {
+				break key;
+			}
+		};
+
+		let mut writer = WRITER.lock();
+		writer.clear_line();
+
+		match key {
+			' ' => self.page_start = writer.rows_written(),
+			'\n' => {
+				self.page_start = writer
+					.rows_written()
+					.wrapping_sub(writer.usable_height() - 2);
+			}
+			_ => self.aborted = true,
+		}
+	}
+}
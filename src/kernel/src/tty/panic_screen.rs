@@ -0,0 +1,108 @@
+//! Full screen report shown when the kernel panics.
+//!
+//! The panic handler calls [`show`]. It draws straight into the writer,
+//! bypassing the console sinks and the pager, whose locks the panicking code
+//! may hold. Nothing here allocates.
+
+use super::{
+	klog::{KLOG, MAX_RECORD_TEXT},
+	log::Timestamp,
+	tty::{Writer, WRITER},
+	ColourCode, VgaColour, VGA_WIDTH,
+};
+use crate::{
+	arch::x86::backtrace::{current_ebp, frames},
+	symbols,
+	sync::IrqMutexGuard,
+	NAME, VERSION,
+};
+use core::{fmt::Write, panic::PanicInfo, str::from_utf8};
+
+/// Number of log records shown below the panic message.
+const LOG_LINES: u64 = 10;
+
+/// Number of return addresses shown, one row each.
+const BACKTRACE_FRAMES: usize = 4;
+
+/// Clears the screen and prints the panic message, its location, the
+/// innermost [`BACKTRACE_FRAMES`] return addresses, the kernel version and
+/// the last [`LOG_LINES`] log records.
+pub fn show(info: &PanicInfo) {
+	let mut writer = take_writer();
+
+	writer.set_status_bar(false);
+	writer.colour_code = ColourCode::new(VgaColour::White, VgaColour::Blue);
+	writer.clear_screen();
+
+	let _ = writeln!(writer, " KERNEL PANIC - {} {}", NAME, VERSION);
+	let _ = writeln!(writer, " {:=<1$}", "", VGA_WIDTH - 2);
+	let _ = writeln!(writer);
+
+	writer.colour_code = ColourCode::new(VgaColour::Yellow, VgaColour::Blue);
+	let _ = writeln!(writer, " {}", info.message());
+	if let Some(location) = info.location() {
+		let _ = writeln!(
+			writer,
+			" at {}:{}:{}",
+			location.file(),
+			location.line(),
+			location.column()
+		);
+	}
+
+	let _ = writeln!(writer, " Backtrace:");
+	for address in frames(current_ebp()).take(BACKTRACE_FRAMES) {
+		let _ = write!(writer, "  {:08x}", address.as_usize());
+		if let Some((name, offset)) = symbols::resolve(address) {
+			// Cut to fit the row, address and offset take up to 22 columns
+			let _ =
+				write!(writer, " {:.2$}+{:#x}", name, offset, VGA_WIDTH - 22);
+		}
+		let _ = writeln!(writer);
+	}
+
+	writer.colour_code = ColourCode::new(VgaColour::White, VgaColour::Blue);
+	let _ = writeln!(writer);
+	let _ = writeln!(writer, " Last log messages:");
+	print_log(&mut writer);
+
+	let _ = writeln!(writer);
+	let _ = write!(writer, " System halted.");
+}
+
+/// Locks the writer, breaking the lock if the panicking code held it.
+fn take_writer() -> IrqMutexGuard<'static, Writer> {
+	// Safety: code only panics with the lock held when it was writing, and
+	// it never runs again, so nobody else uses the writer.
+	return unsafe { WRITER.force_lock() };
+}
+
+/// Prints the newest log records, one row each.
+fn print_log(writer: &mut Writer) {
+	// Safety: as for the writer, the holder is the panicking code.
+	let klog = unsafe { KLOG.force_lock() };
+
+	let mut seq = klog.next_seq().saturating_sub(LOG_LINES);
+	let mut text = [0; MAX_RECORD_TEXT];
+
+	while let Some(record) = klog.read(seq, &mut text) {
+		let bytes = &text[..record.len];
+		let text = match from_utf8(bytes) {
+			Ok(text) => text,
+			Err(e) => from_utf8(&bytes[..e.valid_up_to()]).unwrap_or_default(),
+		};
+
+		// Cut to one row, the timestamp and level take up to 24 columns.
+		let room = VGA_WIDTH - 24;
+		let text = text.get(..room.min(text.len())).unwrap_or(text);
+		let _ = writeln!(
+			writer,
+			" {} {:<5} {}",
+			Timestamp(record.timestamp_ms),
+			record.level.name(),
+			text
+		);
+
+		seq = record.seq + 1;
+	}
+}
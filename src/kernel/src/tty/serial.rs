@@ -1,18 +1,104 @@
 #![allow(missing_docs)]
 
+use super::{cp437, output::ConsoleOutput, tty::WRITER, ColourCode, VGA_WIDTH};
 use crate::{
-	arch::x86::io::{inb, outb},
-	sync::Mutex,
+	arch::x86::{
+		exceptions::{InterruptFrame, InterruptHandler},
+		io::{inb, outb},
+		pic::{register_irq, send_eoi},
+	},
+	collections::ring_buffer::SpscRing,
+	sync::{IrqMutex, Mutex},
+	task::workqueue::schedule_work,
+};
+use core::{
+	fmt, hint,
+	sync::atomic::{AtomicBool, Ordering},
 };
-use core::fmt;
-use lazy_static::lazy_static;
 
 /* -------------------------------------- */
 
-const PORT: u16 = 0x3f8;
+/// I/O base of the first serial port.
+pub const COM1_PORT: u16 = 0x3f8;
+/// I/O base of the second serial port.
+pub const COM2_PORT: u16 = 0x2f8;
+
+/// Baud rate [`Serial::init`] programs.
+pub const DEFAULT_BAUD: u32 = 115_200;
+/// Rate of the UART clock divided by 16, the divisor latch divides it down.
+const MAX_BAUD: u32 = 115_200;
+
+/// Register offsets from the port base.
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+const SCRATCH: u16 = 7;
+
+/// Interrupt enable register bit for "received data available".
+const IER_RX_AVAILABLE: u8 = 0x01;
+/// Line control register bit exposing the divisor latch at offsets 0 and 1.
+const LCR_DLAB: u8 = 0x80;
+/// Line status register bits.
+const LSR_DATA_READY: u8 = 0x01;
+const LSR_TRANSMIT_EMPTY: u8 = 0x20;
+
+/// Received bytes buffered until they are read.
+pub const RX_BUFFER_SIZE: usize = 256;
+
+static COM1_RX: RxRing = RxRing::new();
+static COM2_RX: RxRing = RxRing::new();
+
+/// Number of data bits in a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DataBits {
+	Five = 0x00,
+	Six = 0x01,
+	Seven = 0x02,
+	Eight = 0x03,
+}
+
+/// Number of stop bits after a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum StopBits {
+	One = 0x00,
+	/// Two stop bits, or one and a half with five data bits.
+	Two = 0x04,
+}
+
+/// Parity bit sent with a character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Parity {
+	None = 0x00,
+	Odd = 0x08,
+	Even = 0x18,
+	Mark = 0x28,
+	Space = 0x38,
+}
+
+/// Received bytes, written by the worker for the interrupt handler and read
+/// by everyone else. Bytes arriving while it is full are dropped.
+type RxRing = SpscRing<u8, RX_BUFFER_SIZE>;
 
-#[derive(Default)]
-pub struct Serial {}
+/// A 16550 UART at a fixed I/O port.
+pub struct Serial {
+	port: u16,
+	irq: u8,
+	name: &'static str,
+	rx: &'static RxRing,
+	handler: InterruptHandler,
+	present: AtomicBool,
+	/// Set once the IRQ feeds `rx`, the UART is then only read by the handler.
+	rx_interrupts: AtomicBool,
+	/// Whether the last line [`read_line`](Self::read_line) returned ended
+	/// with a CR, so the LF of a CR LF pair is not taken for an empty line.
+	after_cr: AtomicBool,
+}
 
 // Implement the core::fmt::Write trait so we can use Rust's formatting macros
 impl fmt::Write for Serial {
@@ -23,14 +109,110 @@ impl fmt::Write for Serial {
 }
 
 impl Serial {
+	const fn new(
+		port: u16,
+		irq: u8,
+		name: &'static str,
+		rx: &'static RxRing,
+		handler: InterruptHandler,
+	) -> Self {
+		return Self {
+			port,
+			irq,
+			name,
+			rx,
+			handler,
+			present: AtomicBool::new(false),
+			rx_interrupts: AtomicBool::new(false),
+			after_cr: AtomicBool::new(false),
+		};
+	}
+
+	/// Returns the port's name, such as `COM1`.
+	pub fn name(&self) -> &'static str {
+		return self.name;
+	}
+
 	fn is_transmit_empty(&self) -> u8 {
-		return inb(PORT + 5) & 0x20;
+		return inb(self.port + LINE_STATUS) & LSR_TRANSMIT_EMPTY;
+	}
+
+	/// Returns whether [`init_with`](Self::init_with) found a working UART.
+	pub fn is_present(&self) -> bool {
+		return self.present.load(Ordering::Relaxed);
+	}
+
+	/// Returns the next received byte, if there is one waiting.
+	pub fn read_byte(&self) -> Option<u8> {
+		if self.rx_interrupts.load(Ordering::Acquire) {
+			return self.rx.pop();
+		}
+
+		if !self.is_present()
+			|| inb(self.port + LINE_STATUS) & LSR_DATA_READY == 0
+		{
+			return None;
+		}
+
+		return Some(inb(self.port + DATA));
+	}
+
+	/// Waits for the next received byte.
+	pub fn read_byte_blocking(&self) -> u8 {
+		loop {
+			if let Some(byte) = self.read_byte() {
+				return byte;
+			}
+			hint::spin_loop();
+		}
+	}
+
+	/// Waits for a line and copies it into `buf` without its line ending,
+	/// returning the number of bytes copied. CR, LF and CR LF all end a line.
+	/// Whatever does not fit in `buf` is discarded.
+	pub fn read_line(&self, buf: &mut [u8]) -> usize {
+		let mut len = 0;
+
+		loop {
+			let byte = self.read_byte_blocking();
+			let after_cr = self.after_cr.swap(false, Ordering::Relaxed);
+
+			match byte {
+				b'\n' if after_cr && len == 0 => continue,
+				b'\r' | b'\n' => {
+					self.after_cr.store(byte == b'\r', Ordering::Relaxed);
+					return len;
+				}
+				byte => {
+					if let Some(slot) = buf.get_mut(len) {
+						*slot = byte;
+						len += 1;
+					}
+				}
+			}
+		}
+	}
+
+	/// Buffers received bytes from the port's IRQ from now on, instead of
+	/// reading them from the UART on demand. Does nothing without a UART.
+	pub fn enable_rx_interrupt(&self) {
+		if !self.is_present() {
+			return;
+		}
+
+		self.rx_interrupts.store(true, Ordering::Release);
+		outb(self.port + INTERRUPT_ENABLE, IER_RX_AVAILABLE);
+		register_irq(self.irq, self.name, self.handler);
 	}
 
 	fn write_serial_byte(&self, a: u8) {
+		if !self.is_present() {
+			return;
+		}
+
 		while self.is_transmit_empty() == 0 {}
 
-		outb(PORT, a);
+		outb(self.port + DATA, a);
 	}
 
 	fn write_serial_string(&self, s: &str) {
@@ -39,28 +221,261 @@ impl Serial {
 		}
 	}
 
-	pub fn init(&self) {
-		outb(PORT + 1, 0x00); // Disable all interrupts
-		outb(PORT + 3, 0x80); // Enable DLAB (set baud rate divisor)
-		outb(PORT, 0x03); // Set divisor to 3 (lo byte) 38400 baud
-		outb(PORT + 1, 0x00); //                  (hi byte)
-		outb(PORT + 3, 0x03); // 8 bits, no parity, one stop bit
-		outb(PORT + 2, 0xc7); // Enable FIFO, clear them, with 14-byte threshold
-		outb(PORT + 4, 0x0b); // IRQs enabled, RTS/DSR set
-		outb(PORT + 4, 0x1e); // Set in loopback mode, test the serial chip
-		outb(PORT, 0xae); // Test serial chip (send byte 0xAE and check if serial returns same
-					// byte)
-
-		if inb(PORT) != 0xae {
-			panic!("Port: {} unusable", PORT);
+	/// Sets the UART up for [`DEFAULT_BAUD`] 8N1, see
+	/// [`init_with`](Self::init_with).
+	pub fn init(&self) -> bool {
+		return self.init_with(
+			DEFAULT_BAUD,
+			DataBits::Eight,
+			StopBits::One,
+			Parity::None,
+		);
+	}
+
+	/// Programs the UART for `baud` and the given character format, after
+	/// checking that there is one with the scratch register and a loopback
+	/// test. Returns whether it is present; without a UART every read and
+	/// write is a no-op afterwards.
+	///
+	/// `baud` is rounded to a rate the divisor latch can make, at most
+	/// 115200.
+	pub fn init_with(
+		&self,
+		baud: u32,
+		data_bits: DataBits,
+		stop_bits: StopBits,
+		parity: Parity,
+	) -> bool {
+		let port = self.port;
+		self.present.store(false, Ordering::Relaxed);
+
+		// Nothing answers on an empty port, reads float to 0xff.
+		outb(port + SCRATCH, 0x5a);
+		if inb(port + SCRATCH) != 0x5a {
+			return false;
 		}
 
-		outb(PORT + 4, 0x0f);
+		let divisor = (MAX_BAUD / baud.clamp(1, MAX_BAUD)) as u16;
+
+		outb(port + INTERRUPT_ENABLE, 0x00); // Disable all interrupts
+		outb(port + LINE_CONTROL, LCR_DLAB);
+		outb(port + DATA, divisor as u8);
+		outb(port + INTERRUPT_ENABLE, (divisor >> 8) as u8);
+		outb(
+			port + LINE_CONTROL,
+			data_bits as u8 | stop_bits as u8 | parity as u8,
+		);
+		outb(port + FIFO_CONTROL, 0xc7); // Enable FIFO, clear them, with 14-byte threshold
+		outb(port + MODEM_CONTROL, 0x0b); // IRQs enabled, RTS/DSR set
+		outb(port + MODEM_CONTROL, 0x1e); // Set in loopback mode, test the serial chip
+		outb(port + DATA, 0xae); // Loopback returns what is sent if the chip works
+
+		let present = inb(port + DATA) == 0xae;
+		self.present.store(present, Ordering::Relaxed);
+
+		outb(port + MODEM_CONTROL, 0x0f);
+		return present;
+	}
+}
+
+/// Reads every byte the UART at `port` has received and schedules `queue`
+/// for each on the workqueue.
+fn drain_rx(port: u16, queue: fn(usize)) {
+	while inb(port + LINE_STATUS) & LSR_DATA_READY != 0 {
+		schedule_work(queue, inb(port + DATA) as usize);
 	}
 }
 
+// Helpers the worker runs for the bytes the interrupts read
+fn queue_com1_byte(byte: usize) {
+	let _ = COM1_RX.push(byte as u8);
+}
+
+fn queue_com2_byte(byte: usize) {
+	let _ = COM2_RX.push(byte as u8);
+}
+
+extern "x86-interrupt" fn com1_interrupt(_frame: InterruptFrame) {
+	drain_rx(COM1_PORT, queue_com1_byte);
+	send_eoi(4);
+}
+
+extern "x86-interrupt" fn com2_interrupt(_frame: InterruptFrame) {
+	drain_rx(COM2_PORT, queue_com2_byte);
+	send_eoi(3);
+}
+
 /* -------------------------------------- */
 
-lazy_static! {
-	pub static ref SERIAL: Mutex<Serial> = Mutex::new(Serial::default());
+/// The first serial port, on IRQ 4.
+pub static COM1: IrqMutex<Serial> = IrqMutex::named(
+	"COM1",
+	Serial::new(COM1_PORT, 4, "COM1", &COM1_RX, com1_interrupt),
+);
+
+/// The second serial port, on IRQ 3.
+pub static COM2: IrqMutex<Serial> = IrqMutex::named(
+	"COM2",
+	Serial::new(COM2_PORT, 3, "COM2", &COM2_RX, com2_interrupt),
+);
+
+/// The port carrying the kernel log and `print_serial!`: COM1, or COM2 when
+/// built with the `serial-com2` feature.
+#[cfg(not(feature = "serial-com2"))]
+pub static SERIAL: &IrqMutex<Serial> = &COM1;
+#[cfg(feature = "serial-com2")]
+pub static SERIAL: &IrqMutex<Serial> = &COM2;
+
+/// I/O base of the [`SERIAL`] port.
+#[cfg(not(feature = "serial-com2"))]
+const LOG_PORT: u16 = COM1_PORT;
+#[cfg(feature = "serial-com2")]
+const LOG_PORT: u16 = COM2_PORT;
+
+/// Spins waiting for the transmitter before [`print_unlocked`] drops a byte.
+const UNLOCKED_TRANSMIT_SPINS: u32 = 100_000;
+
+/// Writes `args` to the [`SERIAL`] UART without locking it, for reports from
+/// code that may be stuck on its lock or interrupted its holder. The output
+/// may interleave with the holder's.
+pub fn print_unlocked(args: fmt::Arguments) {
+	struct Unlocked;
+
+	impl fmt::Write for Unlocked {
+		fn write_str(&mut self, s: &str) -> fmt::Result {
+			// Reads as all ones without a UART
+			if inb(LOG_PORT + LINE_STATUS) == 0xff {
+				return Ok(());
+			}
+
+			for byte in s.bytes() {
+				for _ in 0..UNLOCKED_TRANSMIT_SPINS {
+					if inb(LOG_PORT + LINE_STATUS) & LSR_TRANSMIT_EMPTY != 0 {
+						break;
+					}
+				}
+				outb(LOG_PORT + DATA, byte);
+			}
+
+			return Ok(());
+		}
+	}
+
+	let _ = fmt::Write::write_fmt(&mut Unlocked, args);
+}
+
+/// The [`SERIAL`] port as a console sink, see [`SerialOutput`].
+pub static SERIAL_OUTPUT: SerialOutput = SerialOutput::new();
+
+/// Console sink driving a serial terminal with ANSI escape codes.
+///
+/// It lays text out like the VGA writer does, wrapping after `VGA_WIDTH`
+/// columns and replacing characters without a [CP437](super::cp437) glyph
+/// with a ■, and tracks where that puts the cursor. Cursor moves are then
+/// sent as relative ANSI moves, which work no matter where the terminal's
+/// own cursor started.
+pub struct SerialOutput {
+	position: Mutex<(usize, usize)>,
+}
+
+impl SerialOutput {
+	const fn new() -> Self {
+		return Self {
+			position: Mutex::new((0, 0)),
+		};
+	}
+
+	/// Makes the tracked position match `col`, `row` without moving the
+	/// terminal cursor, e.g. when the sink is registered mid-line.
+	pub fn sync_position(&self, col: usize, row: usize) {
+		*self.position.lock() = (col, row);
+	}
+
+	/// Returns whether a write would wait for the position lock.
+	pub(super) fn is_locked(&self) -> bool {
+		return self.position.is_locked();
+	}
+
+	/// Breaks the position lock, see [`Mutex::force_unlock`].
+	pub(super) unsafe fn force_unlock(&self) {
+		unsafe { self.position.force_unlock() };
+	}
+}
+
+impl ConsoleOutput for SerialOutput {
+	fn write_str(&self, s: &str) {
+		let height = WRITER.lock().usable_height();
+		let mut position = self.position.lock();
+		let serial = SERIAL.lock();
+
+		for c in s.chars() {
+			let (col, row) = &mut *position;
+
+			if c == '\n' || *col >= VGA_WIDTH {
+				serial.write_serial_string("\r\n");
+				*col = 0;
+				*row = (*row + 1).min(height - 1);
+			}
+
+			match c {
+				'\n' => continue,
+				' '..='~' => serial.write_serial_byte(c as u8),
+				c if cp437::from_char(c).is_some() => {
+					serial.write_serial_string(c.encode_utf8(&mut [0; 4]));
+				}
+				_ => serial.write_serial_string("\u{25a0}"),
+			}
+			*col += 1;
+		}
+	}
+
+	fn set_colour(&self, colour: ColourCode) {
+		let fg = ansi_colour(colour.get_foreground_colour() as u8);
+		let bg = ansi_colour(colour.get_background_colour() as u8);
+
+		let _ = fmt::write(
+			&mut *SERIAL.lock(),
+			format_args!("\x1b[{};{}m", 30 + fg, 40 + bg),
+		);
+	}
+
+	fn clear(&self) {
+		SERIAL.lock().write_serial_string("\x1b[2J\x1b[H");
+		*self.position.lock() = (0, 0);
+	}
+
+	fn cursor_to(&self, col: usize, row: usize) {
+		let mut position = self.position.lock();
+		let (from_col, from_row) = *position;
+		let mut serial = SERIAL.lock();
+
+		let moves = [
+			(row < from_row, from_row.saturating_sub(row), 'A'),
+			(row > from_row, row.saturating_sub(from_row), 'B'),
+			(col > from_col, col.saturating_sub(from_col), 'C'),
+			(col < from_col, from_col.saturating_sub(col), 'D'),
+		];
+		for (needed, n, code) in moves {
+			if needed {
+				let _ = fmt::write(
+					&mut *serial,
+					format_args!("\x1b[{}{}", n, code),
+				);
+			}
+		}
+
+		*position = (col, row);
+	}
+}
+
+/// Maps a VGA colour to an ANSI colour offset: 0 to 7 for the normal colours
+/// and 60 to 67 for the bright ones.
+fn ansi_colour(vga: u8) -> u8 {
+	const ANSI: [u8; 8] = [0, 4, 2, 6, 1, 5, 3, 7];
+
+	let base = ANSI[(vga & 0x7) as usize];
+	match vga & 0x8 {
+		0 => return base,
+		_ => return base + 60,
+	}
 }
@@ -0,0 +1,105 @@
+//! Status bar on the bottom row of the screen.
+//!
+//! Shows the kernel name and version, the uptime and the log level. The timer
+//! interrupt calls [`tick`], which redraws the bar whenever the uptime second
+//! or the log level changed since it was last drawn. If the interrupted code
+//! holds the writer, the redraw waits for the next tick.
+
+use super::{
+	log,
+	tty::{Writer, WRITER},
+	VGA_WIDTH,
+};
+use crate::{time, NAME, VERSION};
+use core::{
+	fmt::{self, Write},
+	sync::atomic::{AtomicU32, AtomicU8, Ordering},
+};
+
+/// Marks the shown values as stale, so the next tick draws the bar.
+const STALE: u32 = u32::MAX;
+
+static SHOWN_SECONDS: AtomicU32 = AtomicU32::new(STALE);
+static SHOWN_LEVEL: AtomicU8 = AtomicU8::new(0);
+
+/// Reserves the bottom row of the screen and draws the bar on it.
+pub fn init() {
+	let mut writer = WRITER.lock();
+
+	writer.set_status_bar(true);
+	draw(&mut writer);
+}
+
+/// Gives the bottom row back to normal output, so a panic or a fatal
+/// exception can scroll over the bar and nothing it prints is hidden.
+///
+/// Does not wait for the writer: if it is held, the bar stays until the next
+/// [`init`].
+pub fn release() {
+	if let Some(mut writer) = WRITER.try_lock() {
+		writer.set_status_bar(false);
+	}
+	SHOWN_SECONDS.store(STALE, Ordering::Relaxed);
+}
+
+/// Redraws the bar if what it shows changed. Called from the timer interrupt.
+pub fn tick() {
+	let seconds = (time::uptime_ms() / 1000) as u32;
+	let level = log::level() as u8;
+
+	if seconds == SHOWN_SECONDS.load(Ordering::Relaxed)
+		&& level == SHOWN_LEVEL.load(Ordering::Relaxed)
+	{
+		return;
+	}
+
+	if let Some(mut writer) = WRITER.try_lock() {
+		draw(&mut writer);
+	}
+}
+
+/// Formats the bar and draws it through `writer`.
+fn draw(writer: &mut Writer) {
+	let seconds = (time::uptime_ms() / 1000) as u32;
+	let level = log::level();
+
+	let mut line = Line {
+		bytes: [b' '; VGA_WIDTH],
+		len: 0,
+	};
+	let _ = write!(
+		line,
+		" {} {} | up {}:{:02}:{:02} | log: {}",
+		NAME,
+		VERSION,
+		seconds / 3600,
+		seconds / 60 % 60,
+		seconds % 60,
+		level.name()
+	);
+
+	writer.draw_status_bar(line.as_str());
+	SHOWN_SECONDS.store(seconds, Ordering::Relaxed);
+	SHOWN_LEVEL.store(level as u8, Ordering::Relaxed);
+}
+
+/// Text of the bar, silently cut at the screen width.
+struct Line {
+	bytes: [u8; VGA_WIDTH],
+	len: usize,
+}
+
+impl Line {
+	fn as_str(&self) -> &str {
+		return core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("");
+	}
+}
+
+impl Write for Line {
+	fn write_str(&mut self, s: &str) -> fmt::Result {
+		let n = s.len().min(VGA_WIDTH - self.len);
+		self.bytes[self.len..self.len + n].copy_from_slice(&s.as_bytes()[..n]);
+		self.len += n;
+		return Ok(());
+	}
+}
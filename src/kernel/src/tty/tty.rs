@@ -2,8 +2,8 @@
 //
 // This module implements a text-mode display driver for the VGA hardware,
 // allowing us to write text to the screen. The VGA text buffer is located
-// at physical address 0xB8000 and provides a 80x25 character display where
-// each character cell consists of:
+// at physical address 0xB8000 and provides a 80x25 (or 80x50) character display
+// where each character cell consists of:
 //   - An ASCII character (8 bits)
 //   - A colour code (8 bits) specifying foreground and background colours
 //
@@ -11,10 +11,33 @@
 // implements basic console functionality like newlines and screen clearing.
 //------------------------------------------------------------------------------
 
-use super::{Buffer, ColourCode, VgaChar, VgaColour, VGA_HEIGHT, VGA_WIDTH};
-use crate::sync::Mutex;
-use core::fmt;
-use lazy_static::lazy_static;
+use super::{
+	cp437,
+	framebuffer::Framebuffer,
+	output::ConsoleOutput,
+	pager,
+	vt::{self, Screen},
+	Buffer, ColourCode, VgaChar, VgaColour, MAX_VGA_HEIGHT, VGA_HEIGHT,
+	VGA_WIDTH,
+};
+use crate::{
+	arch::x86::io::{inb, outb},
+	sync::{IrqMutex, Lazy},
+};
+use core::{fmt, mem};
+
+/* -------------------------------------- */
+
+const CRTC_ADDRESS: u16 = 0x3d4;
+const CRTC_DATA: u16 = 0x3d5;
+
+const CRTC_CURSOR_START: u8 = 0x0a;
+const CRTC_CURSOR_END: u8 = 0x0b;
+const CRTC_CURSOR_HIGH: u8 = 0x0e;
+const CRTC_CURSOR_LOW: u8 = 0x0f;
+
+/// Cursor start register bit that hides the cursor.
+const CURSOR_DISABLE: u8 = 0x20;
 
 /* -------------------------------------- */
 
@@ -24,8 +47,14 @@ use lazy_static::lazy_static;
 pub struct Writer {
 	column_position: usize,
 	row_position: usize,
+	rows_written: usize,
+	height: usize,
+	status_bar: Option<[u8; VGA_WIDTH]>,
+	hardware_cursor: bool,
+	framebuffer: Option<Framebuffer>,
+	display: Option<&'static mut Buffer>, // VGA memory at 0xB8000
 	pub colour_code: ColourCode,
-	pub buffer: &'static mut Buffer, // Points to VGA memory at 0xB8000
+	pub buffer: &'static mut Buffer, // The cells of the visible terminal
 }
 
 // Implement the core::fmt::Write trait so we can use Rust's formatting macros
@@ -39,32 +68,164 @@ impl fmt::Write for Writer {
 impl Writer {
 	#[allow(fuzzy_provenance_casts)]
 	fn new() -> Writer {
+		// Safety: terminal 0 is the visible one at boot, no one else takes
+		// its cells.
+		let mut writer = Writer::with_buffer(unsafe { vt::cells(0) });
+
+		// Safety: 0xB8000 is the VGA buffer's physical address.
+		// This is safe because we know this memory is always mapped
+		// and we have exclusive access to it at kernel level.
+		writer.display = Some(unsafe { &mut *(0xb8000 as *mut Buffer) });
+		writer.draw_all();
+
+		writer.hardware_cursor = true;
+		writer.enable_cursor(14, 15);
+		writer.update_cursor();
+		return writer;
+	}
+
+	/// Creates a writer that draws into `buffer` and clears it. Only the
+	/// writer of the visible terminal copies its cells to VGA memory and
+	/// moves the hardware cursor.
+	pub fn with_buffer(buffer: &'static mut Buffer) -> Writer {
 		let mut writer = Writer {
 			column_position: 0,
 			row_position: VGA_HEIGHT - 1,
+			rows_written: 0,
+			height: VGA_HEIGHT,
+			status_bar: None,
+			hardware_cursor: false,
+			framebuffer: None,
+			display: None,
 			colour_code: ColourCode::new(
 				VgaColour::LightGrey,
 				VgaColour::Black,
 			),
-			// Safety: 0xB8000 is the VGA buffer's physical address.
-			// This is safe because we know this memory is always mapped
-			// and we have exclusive access to it at kernel level.
-			buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
+			buffer,
 		};
 
 		writer.clear_screen();
 		return writer;
 	}
 
-	/// Writes a string to the screen, handling both printable ASCII characters
-	/// and newlines. Any unprintable characters are replaced with ■ (0xFE).
+	/// Draws the cells to `framebuffer` from now on instead of VGA memory,
+	/// starting with what is on screen so far.
+	pub fn attach_framebuffer(&mut self, framebuffer: Framebuffer) {
+		// There is no hardware cursor to move in a graphics mode.
+		self.disable_cursor();
+		self.hardware_cursor = false;
+		self.display = None;
+
+		framebuffer.draw_all(self.buffer);
+		self.framebuffer = Some(framebuffer);
+	}
+
+	/// Draws cell (`col`, `row`) on the screen, if the writer has one.
+	fn draw_cell(&mut self, col: usize, row: usize) {
+		let cell = self.buffer.chars[row][col];
+		if let Some(display) = &mut self.display {
+			display.chars[row][col] = cell;
+		}
+		if let Some(framebuffer) = &self.framebuffer {
+			framebuffer.draw_cell(col, row, cell);
+		}
+	}
+
+	/// Draws row `row` on the screen, if the writer has one.
+	fn draw_row(&mut self, row: usize) {
+		if let Some(display) = &mut self.display {
+			display.chars[row] = self.buffer.chars[row];
+		}
+		if let Some(framebuffer) = &self.framebuffer {
+			framebuffer.draw_row(row, &self.buffer.chars[row]);
+		}
+	}
+
+	/// Draws every cell on the screen, if the writer has one.
+	fn draw_all(&mut self) {
+		if let Some(display) = &mut self.display {
+			display.chars = self.buffer.chars;
+		}
+		if let Some(framebuffer) = &self.framebuffer {
+			framebuffer.draw_all(self.buffer);
+		}
+	}
+
+	/// Parks the visible terminal in `parked` and shows the one parked in
+	/// `shown`, handing the writer its cells and drawing them. Does nothing
+	/// if `shown` has no cells, as it is already visible.
+	pub fn switch_screen(&mut self, parked: &mut Screen, shown: &mut Screen) {
+		let Some(cells) = shown.cells.take() else {
+			return;
+		};
+
+		parked.cells = Some(mem::replace(&mut self.buffer, cells));
+		parked.column_position = self.column_position;
+		parked.row_position = self.row_position;
+		parked.colour_code = self.colour_code;
+
+		self.column_position = shown.column_position;
+		self.row_position = shown.row_position.min(self.usable_height() - 1);
+		self.colour_code = shown.colour_code;
+		self.update_cursor();
+		self.draw_status_bar_row();
+		self.draw_all();
+	}
+
+	/// Writes `s` into the cells of the terminal parked in `screen`, which
+	/// are not on screen, using the rows text goes to on the visible one.
+	pub fn write_parked(&self, screen: &mut Screen, s: &str) {
+		let Some(cells) = screen.cells.take() else {
+			return;
+		};
+
+		let height = self.usable_height();
+		let mut parked = Writer {
+			column_position: screen.column_position,
+			row_position: screen.row_position.min(height - 1),
+			rows_written: 0,
+			height,
+			status_bar: None,
+			hardware_cursor: false,
+			framebuffer: None,
+			display: None,
+			colour_code: screen.colour_code,
+			buffer: cells,
+		};
+		parked.write_string(s);
+
+		screen.column_position = parked.column_position;
+		screen.row_position = parked.row_position;
+		screen.cells = Some(parked.buffer);
+	}
+
+	/// Writes a string to the screen, handling printable ASCII characters,
+	/// newlines and the characters [CP437](super::cp437) has a glyph for, such
+	/// as box drawing. Anything else, control characters included, is
+	/// replaced with ■ (0xFE).
 	pub fn write_string(&mut self, str: &str) {
-		for byte in str.bytes() {
+		for c in str.chars() {
+			match c {
+				'\n' => self.new_line(),
+				c => self.write_glyph(cp437::from_char(c).unwrap_or(0xfe)),
+			}
+		}
+
+		self.update_cursor();
+	}
+
+	/// Writes raw CP437 bytes, drawing the glyph of every byte from 0x01 to
+	/// 0xFF, control range included. There is no newline handling, and the
+	/// blank glyph 0x00 is written as a space.
+	pub fn write_cp437(&mut self, bytes: &[u8]) {
+		for &byte in bytes {
 			match byte {
-				0x20..=0x7e | b'\n' => self.write_byte(byte),
-				_ => self.write_byte(0xfe),
+				0x00 => self.write_glyph(b' '),
+				byte => self.write_glyph(byte),
 			}
 		}
+
+		self.update_cursor();
 	}
 
 	#[inline]
@@ -73,39 +234,172 @@ impl Writer {
 		return (self.column_position, self.row_position);
 	}
 
+	/// Returns the number of text rows on screen.
+	#[inline]
+	pub fn height(&self) -> usize {
+		return self.height;
+	}
+
+	/// Switches to a text mode with `height` rows and clears the screen. Only
+	/// the writer state changes, see [`vga`](super::vga) for programming the
+	/// hardware to match.
+	pub fn set_height(&mut self, height: usize) {
+		self.height = height.clamp(2, MAX_VGA_HEIGHT);
+		self.clear_screen();
+		self.draw_status_bar_row();
+	}
+
+	/// Returns the number of rows text is written to, which leaves out the
+	/// status bar while it is shown.
+	#[inline]
+	pub fn usable_height(&self) -> usize {
+		return self.height - usize::from(self.status_bar.is_some());
+	}
+
+	/// Reserves the bottom row for [`draw_status_bar`](Self::draw_status_bar),
+	/// or hands it back to normal output. Disabling leaves the bar on screen
+	/// until output scrolls over it.
+	pub fn set_status_bar(&mut self, enabled: bool) {
+		if enabled == self.status_bar.is_some() {
+			return;
+		}
+
+		if !enabled {
+			self.status_bar = None;
+			return;
+		}
+
+		// Make room if the writer is on the row that becomes the bar.
+		if self.row_position >= self.height - 1 {
+			self.shift_lines_up();
+			self.row_position = self.height - 2;
+		}
+
+		self.status_bar = Some([b' '; VGA_WIDTH]);
+		self.draw_status_bar_row();
+		self.update_cursor();
+	}
+
+	/// Shows `text` on the status bar in inverted colours, cut or padded to
+	/// the screen width. Does nothing while the bar is disabled.
+	pub fn draw_status_bar(&mut self, text: &str) {
+		let Some(bar) = &mut self.status_bar else {
+			return;
+		};
+
+		*bar = [b' '; VGA_WIDTH];
+		for (cell, c) in bar.iter_mut().zip(text.chars()) {
+			*cell = cp437::from_char(c).unwrap_or(0xfe);
+		}
+
+		self.draw_status_bar_row();
+	}
+
+	/// Writes the stored status bar text to the bottom row.
+	fn draw_status_bar_row(&mut self) {
+		let Some(bar) = self.status_bar else {
+			return;
+		};
+
+		// The default colours inverted, whatever text is written in now.
+		let colour_code =
+			ColourCode::new(VgaColour::Black, VgaColour::LightGrey);
+
+		let row = self.height - 1;
+		for (cell, byte) in self.buffer.chars[row].iter_mut().zip(bar) {
+			*cell = VgaChar {
+				ascii_character: byte,
+				colour_code,
+			};
+		}
+		self.draw_row(row);
+	}
+
+	/// Returns whether the cells are drawn to a pixel framebuffer.
+	#[inline]
+	pub fn has_framebuffer(&self) -> bool {
+		return self.framebuffer.is_some();
+	}
+
+	/// Returns how many rows output has advanced since boot, counting both
+	/// newlines and wrapped lines. Wraps around on overflow.
+	#[inline]
+	pub fn rows_written(&self) -> usize {
+		return self.rows_written;
+	}
+
+	/// Moves the writer, clamped to the rows above the status bar. Column
+	/// `VGA_WIDTH` is the pending wrap after the last column of a row.
 	#[inline]
-	#[doc(hidden)]
 	pub fn set_position(&mut self, col: usize, row: usize) {
-		self.column_position = col;
-		self.row_position = row;
-	}
-
-	/// Writes a single byte to the screen, handling newlines and screen
-	/// wrapping. Updates cursor position after writing.
-	fn write_byte(&mut self, byte: u8) {
-		match byte {
-			b'\n' => self.new_line(),
-			byte => {
-				if self.column_position >= VGA_WIDTH {
-					self.new_line();
-				}
-
-				let row = self.row_position;
-				let col = self.column_position;
-				let colour_code = self.colour_code;
-
-				self.buffer.chars[row][col] = VgaChar {
-					ascii_character: byte,
-					colour_code,
-				};
-
-				self.column_position += 1;
-			}
+		self.column_position = col.min(VGA_WIDTH);
+		self.row_position = row.min(self.usable_height() - 1);
+		self.update_cursor();
+	}
+
+	/// Shows the hardware cursor as the block between scanlines `start` and
+	/// `end` (0 to 15, or 0 to 7 in 80x50) of a character cell.
+	pub fn enable_cursor(&mut self, start: u8, end: u8) {
+		if !self.hardware_cursor {
+			return;
 		}
+
+		outb(CRTC_ADDRESS, CRTC_CURSOR_START);
+		outb(CRTC_DATA, (inb(CRTC_DATA) & 0xc0) | (start & 0x1f));
+		outb(CRTC_ADDRESS, CRTC_CURSOR_END);
+		outb(CRTC_DATA, (inb(CRTC_DATA) & 0xe0) | (end & 0x1f));
+	}
+
+	/// Hides the hardware cursor.
+	pub fn disable_cursor(&mut self) {
+		if !self.hardware_cursor {
+			return;
+		}
+
+		outb(CRTC_ADDRESS, CRTC_CURSOR_START);
+		outb(CRTC_DATA, CURSOR_DISABLE);
+	}
+
+	/// Moves the hardware cursor to the cell the next byte goes to. A pending
+	/// wrap keeps it on the last column.
+	fn update_cursor(&self) {
+		if !self.hardware_cursor {
+			return;
+		}
+
+		let col = self.column_position.min(VGA_WIDTH - 1);
+		let offset = (self.row_position * VGA_WIDTH + col) as u16;
+
+		outb(CRTC_ADDRESS, CRTC_CURSOR_LOW);
+		outb(CRTC_DATA, offset as u8);
+		outb(CRTC_ADDRESS, CRTC_CURSOR_HIGH);
+		outb(CRTC_DATA, (offset >> 8) as u8);
+	}
+
+	/// Draws the glyph `byte` at the writer and advances it, wrapping to the
+	/// next row first if the last one is full.
+	fn write_glyph(&mut self, byte: u8) {
+		if self.column_position >= VGA_WIDTH {
+			self.wrap_line();
+		}
+
+		let row = self.row_position;
+		let col = self.column_position;
+		let colour_code = self.colour_code;
+
+		self.buffer.chars[row][col] = VgaChar {
+			ascii_character: byte,
+			colour_code,
+		};
+		self.draw_cell(col, row);
+
+		self.column_position += 1;
 	}
 
 	fn shift_lines_up(&mut self) {
-		for row in 1..VGA_HEIGHT {
+		let last = self.usable_height() - 1;
+
+		for row in 1..=last {
 			for col in 0..VGA_WIDTH {
 				let character = self.buffer.chars[row][col];
 				self.buffer.chars[row - 1][col] = character;
@@ -117,46 +411,84 @@ impl Writer {
 			colour_code: self.colour_code,
 		};
 		for col in 0..VGA_WIDTH {
-			self.buffer.chars[VGA_HEIGHT - 1][col] = blank;
+			self.buffer.chars[last][col] = blank;
+		}
+
+		if let Some(display) = &mut self.display {
+			display.chars[..=last].copy_from_slice(&self.buffer.chars[..=last]);
+		}
+		if let Some(framebuffer) = &self.framebuffer {
+			framebuffer.scroll_up();
+			framebuffer.draw_row(last, &self.buffer.chars[last]);
 		}
 	}
 
+	/// Continues a line that ran past the last column on the next row.
+	#[inline]
+	fn wrap_line(&mut self) {
+		self.new_line();
+	}
+
+	/// Moves to the start of the next row, scrolling only when the writer is
+	/// already on the last one above the status bar.
 	#[inline]
 	fn new_line(&mut self) {
 		self.column_position = 0;
-		self.shift_lines_up();
+		self.rows_written = self.rows_written.wrapping_add(1);
+
+		if self.row_position < self.usable_height() - 1 {
+			self.row_position += 1;
+		} else {
+			self.shift_lines_up();
+		}
 	}
 
-	/// Clears the entire screen by filling it with spaces
+	/// Clears the entire screen by filling it with spaces, except for the
+	/// status bar
 	/// Resets column & row value to 0
 	pub fn clear_screen(&mut self) {
 		self.column_position = 0;
-		self.row_position = VGA_HEIGHT - 1;
+		self.row_position = 0;
 
 		let blank = VgaChar {
 			ascii_character: b' ',
 			colour_code: self.colour_code,
 		};
-		self.buffer.chars = [[blank; VGA_WIDTH]; VGA_HEIGHT];
+		let rows = self.usable_height();
+		for row in &mut self.buffer.chars[..rows] {
+			*row = [blank; VGA_WIDTH];
+		}
+		self.update_cursor();
+		self.draw_all();
 	}
 
-	/// Clears an entire line by filling it with spaces
-	/// Resets column & row value to 0
+	/// Clears the current line by filling it with spaces
+	/// Resets column value to 0
 	pub fn clear_line(&mut self) {
 		self.column_position = 0;
 		let blank = VgaChar {
 			ascii_character: b' ',
 			colour_code: self.colour_code,
 		};
-		for col in 0..VGA_WIDTH {
-			self.buffer.chars[VGA_HEIGHT - 1][col] = blank;
-		}
+		self.buffer.chars[self.row_position] = [blank; VGA_WIDTH];
+		self.update_cursor();
+		self.draw_row(self.row_position);
 	}
 
 	/// Clears an last shown char by filling it with blank
-	/// Sets column value by -= 1
+	/// Sets column value by -= 1, moving to the end of the previous row when
+	/// erasing across a wrapped line. Does nothing in the top left corner.
 	pub fn clear_char(&mut self) {
-		self.column_position -= 1;
+		if self.column_position == 0 {
+			if self.row_position == 0 {
+				return;
+			}
+
+			self.row_position -= 1;
+			self.column_position = VGA_WIDTH;
+		}
+
+		self.column_position = self.column_position.min(VGA_WIDTH) - 1;
 
 		let row = self.row_position;
 		let column = self.column_position;
@@ -166,13 +498,45 @@ impl Writer {
 			colour_code: self.colour_code,
 		};
 		self.buffer.chars[row][column] = blank;
+		self.update_cursor();
+		self.draw_row(row);
 	}
 }
 
 /* -------------------------------------- */
 
-lazy_static! {
-	/// Global writer to the VGA instance protected by a mutex for safe concurrent access.
-	/// This allows us to use the writer from anywhere in the kernel.
-	pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer::new());
+/// Global writer to the VGA instance protected by a mutex for safe concurrent
+/// access. This allows us to use the writer from anywhere in the kernel.
+/// Interrupt handlers print too, so it is an [`IrqMutex`].
+pub static WRITER: Lazy<IrqMutex<Writer>> =
+	Lazy::new(|| IrqMutex::named("WRITER", Writer::new()));
+
+/// The [`WRITER`] as a console sink, registered from boot.
+pub static VGA_OUTPUT: VgaOutput = VgaOutput;
+
+/// Console sink drawing through [`WRITER`], and through the pager while a
+/// command's output is paged.
+pub struct VgaOutput;
+
+impl ConsoleOutput for VgaOutput {
+	fn write_str(&self, s: &str) {
+		if pager::active() {
+			pager::write_str(s);
+			return;
+		}
+
+		WRITER.lock().write_string(s);
+	}
+
+	fn set_colour(&self, colour: ColourCode) {
+		WRITER.lock().colour_code = colour;
+	}
+
+	fn clear(&self) {
+		WRITER.lock().clear_screen();
+	}
+
+	fn cursor_to(&self, col: usize, row: usize) {
+		WRITER.lock().set_position(col, row);
+	}
 }
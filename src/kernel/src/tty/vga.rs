@@ -0,0 +1,165 @@
+//! VGA text mode switching.
+//!
+//! Both text modes keep the 720x400 timing the BIOS set up and only change
+//! how many scanlines a character cell is tall: 16 gives 80x25, 8 gives 80x50.
+//! The 8x8 glyphs are loaded into font plane 2 with the usual sequencer and
+//! graphics controller dance, all 256 of them, so the line and block drawing
+//! characters look the same in both modes. The BIOS font is read back the
+//! first time so switching to 80x25 again restores it.
+
+use super::{font::glyph_8x8, tty::WRITER, MAX_VGA_HEIGHT, VGA_HEIGHT};
+use crate::arch::x86::io::{inb, outb};
+use core::{
+	ptr,
+	sync::atomic::{AtomicBool, Ordering},
+};
+
+/* -------------------------------------- */
+
+const SEQ_ADDRESS: u16 = 0x3c4;
+const SEQ_DATA: u16 = 0x3c5;
+const GC_ADDRESS: u16 = 0x3ce;
+const GC_DATA: u16 = 0x3cf;
+const CRTC_ADDRESS: u16 = 0x3d4;
+const CRTC_DATA: u16 = 0x3d5;
+
+const SEQ_RESET: u8 = 0x00;
+const SEQ_MAP_MASK: u8 = 0x02;
+const SEQ_MEMORY_MODE: u8 = 0x04;
+const GC_READ_MAP: u8 = 0x04;
+const GC_MODE: u8 = 0x05;
+const GC_MISC: u8 = 0x06;
+const CRTC_MAX_SCAN_LINE: u8 = 0x09;
+
+/// Where plane 2 shows up while it is mapped for font access.
+const FONT_ADDRESS: usize = 0xa0000;
+/// Bytes reserved per glyph in plane 2, whatever the cell height.
+const GLYPH_STRIDE: usize = 32;
+/// Scanlines of a glyph in the BIOS 8x16 font.
+const BIOS_GLYPH_HEIGHT: usize = 16;
+
+/// The BIOS font, saved before the 8x8 font first replaces it.
+static mut BIOS_FONT: [[u8; BIOS_GLYPH_HEIGHT]; 256] =
+	[[0; BIOS_GLYPH_HEIGHT]; 256];
+static BIOS_FONT_SAVED: AtomicBool = AtomicBool::new(false);
+
+/* -------------------------------------- */
+
+/// Switches to 80x25 with the BIOS font and clears the screen.
+///
+/// Returns `false` if the console is drawn on a framebuffer, which has no
+/// text modes.
+pub fn set_text_mode_80x25() -> bool {
+	let mut writer = WRITER.lock();
+	if writer.has_framebuffer() {
+		return false;
+	}
+
+	if BIOS_FONT_SAVED.load(Ordering::Acquire) {
+		// Safety: only written once, before BIOS_FONT_SAVED is set.
+		let font = unsafe { &*ptr::addr_of!(BIOS_FONT) };
+		with_font_plane(|plane| {
+			for (byte, glyph) in font.iter().enumerate() {
+				for (line, bits) in glyph.iter().enumerate() {
+					unsafe {
+						plane
+							.add(byte * GLYPH_STRIDE + line)
+							.write_volatile(*bits)
+					};
+				}
+			}
+		});
+	}
+
+	set_cell_height(BIOS_GLYPH_HEIGHT as u8);
+	writer.set_height(VGA_HEIGHT);
+	writer.enable_cursor(14, 15);
+	return true;
+}
+
+/// Switches to 80x50 with the 8x8 font and clears the screen.
+///
+/// Returns `false` if the console is drawn on a framebuffer, which has no
+/// text modes.
+pub fn set_text_mode_80x50() -> bool {
+	let mut writer = WRITER.lock();
+	if writer.has_framebuffer() {
+		return false;
+	}
+
+	if !BIOS_FONT_SAVED.load(Ordering::Acquire) {
+		save_bios_font();
+	}
+
+	with_font_plane(|plane| {
+		for byte in 0..=u8::MAX {
+			// Plane 2 has the leftmost pixel in bit 7, the font in bit 0.
+			for (line, bits) in glyph_8x8(byte).iter().enumerate() {
+				let offset = byte as usize * GLYPH_STRIDE + line;
+				unsafe {
+					plane.add(offset).write_volatile(bits.reverse_bits())
+				};
+			}
+		}
+	});
+
+	set_cell_height(8);
+	writer.set_height(MAX_VGA_HEIGHT);
+	writer.enable_cursor(6, 7);
+	return true;
+}
+
+/* -------------------------------------- */
+
+/// Copies the 8x16 font out of plane 2. The caller holds the writer lock.
+fn save_bios_font() {
+	// Safety: the writer lock serialises mode switches, and readers wait for
+	// BIOS_FONT_SAVED.
+	let font = unsafe { &mut *ptr::addr_of_mut!(BIOS_FONT) };
+
+	with_font_plane(|plane| {
+		for (byte, glyph) in font.iter_mut().enumerate() {
+			for (line, bits) in glyph.iter_mut().enumerate() {
+				*bits = unsafe {
+					plane.add(byte * GLYPH_STRIDE + line).read_volatile()
+				};
+			}
+		}
+	});
+
+	BIOS_FONT_SAVED.store(true, Ordering::Release);
+}
+
+/// Maps font plane 2 at 0xA0000 for `f`, then puts back the odd/even text
+/// mode layout at 0xB8000.
+fn with_font_plane(f: impl FnOnce(*mut u8)) {
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_RESET, 0x01);
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_MAP_MASK, 0x04);
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_MEMORY_MODE, 0x07);
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_RESET, 0x03);
+	write_register(GC_ADDRESS, GC_DATA, GC_READ_MAP, 0x02);
+	write_register(GC_ADDRESS, GC_DATA, GC_MODE, 0x00);
+	write_register(GC_ADDRESS, GC_DATA, GC_MISC, 0x04);
+
+	f(ptr::with_exposed_provenance_mut::<u8>(FONT_ADDRESS));
+
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_RESET, 0x01);
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_MAP_MASK, 0x03);
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_MEMORY_MODE, 0x03);
+	write_register(SEQ_ADDRESS, SEQ_DATA, SEQ_RESET, 0x03);
+	write_register(GC_ADDRESS, GC_DATA, GC_READ_MAP, 0x00);
+	write_register(GC_ADDRESS, GC_DATA, GC_MODE, 0x10);
+	write_register(GC_ADDRESS, GC_DATA, GC_MISC, 0x0e);
+}
+
+/// Makes every character cell `scanlines` tall.
+fn set_cell_height(scanlines: u8) {
+	outb(CRTC_ADDRESS, CRTC_MAX_SCAN_LINE);
+	let value = (inb(CRTC_DATA) & 0xe0) | ((scanlines - 1) & 0x1f);
+	outb(CRTC_DATA, value);
+}
+
+fn write_register(address: u16, data: u16, index: u8, value: u8) {
+	outb(address, index);
+	outb(data, value);
+}
@@ -0,0 +1,120 @@
+//! Virtual terminals.
+//!
+//! Every terminal has cells of its own, the visible one's are held by
+//! `WRITER`, which copies what it writes on to the screen, and the others are
+//! parked in [`Screen`]s. Output for a parked terminal goes into its cells
+//! only. Switching hands the writer the cells of the selected terminal and
+//! draws them, nothing is copied between terminals. Output from anywhere in
+//! the kernel (panics, exceptions, the console) still goes through `WRITER`,
+//! so it lands on whatever the user is looking at.
+
+use super::{
+	tty::WRITER, Buffer, ColourCode, VgaChar, VgaColour, MAX_VGA_HEIGHT,
+	VGA_WIDTH,
+};
+use crate::sync::{Lazy, Mutex};
+use core::{
+	ptr,
+	sync::atomic::{AtomicUsize, Ordering},
+};
+
+/// Number of virtual terminals, reachable with Alt+F1 to Alt+F4.
+pub const VT_COUNT: usize = 4;
+
+const DEFAULT_COLOUR: ColourCode =
+	ColourCode::new(VgaColour::LightGrey, VgaColour::Black);
+
+/// The cells of every terminal, see [`cells`].
+static mut CELLS: [Buffer; VT_COUNT] = [const {
+	Buffer {
+		chars: [[VgaChar {
+			ascii_character: b' ',
+			colour_code: DEFAULT_COLOUR,
+		}; VGA_WIDTH]; MAX_VGA_HEIGHT],
+	}
+}; VT_COUNT];
+
+/// Returns the cells of terminal `index`.
+///
+/// # Safety
+/// Must be called at most once per terminal. The writer takes those of
+/// terminal 0, visible at boot, and [`SCREENS`] those of the others.
+pub(super) unsafe fn cells(index: usize) -> &'static mut Buffer {
+	// Safety: the caller's promise, this is the only reference made to them.
+	return unsafe { &mut *ptr::addr_of_mut!(CELLS[index]) };
+}
+
+/// The writer state of a terminal that is not visible.
+pub struct Screen {
+	/// The terminal's cells, `None` while the writer holds them.
+	pub(super) cells: Option<&'static mut Buffer>,
+	pub(super) column_position: usize,
+	pub(super) row_position: usize,
+	pub(super) colour_code: ColourCode,
+}
+
+static SCREENS: Lazy<Mutex<[Screen; VT_COUNT]>> = Lazy::new(|| {
+	return Mutex::new(core::array::from_fn(|index| {
+		return Screen {
+			cells: match index {
+				0 => None,
+				// Safety: terminal 0 is the only one the writer takes.
+				_ => Some(unsafe { cells(index) }),
+			},
+			column_position: 0,
+			row_position: 0,
+			colour_code: DEFAULT_COLOUR,
+		};
+	}));
+});
+
+static ACTIVE: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns the index of the visible terminal.
+pub fn active() -> usize {
+	return ACTIVE.load(Ordering::Relaxed);
+}
+
+/// Makes terminal `index` visible, parking the current one.
+///
+/// Returns `false` if `index` is out of range.
+pub fn switch_to(index: usize) -> bool {
+	if index >= VT_COUNT {
+		return false;
+	}
+
+	let mut writer = WRITER.lock();
+	let mut screens = SCREENS.lock();
+	let current = ACTIVE.load(Ordering::Relaxed);
+
+	if index != current {
+		// Borrow both screens, one from each side of the split
+		let (low, high) = screens.split_at_mut(index.max(current));
+		let (parked, shown) = match index < current {
+			true => (&mut high[0], &mut low[index]),
+			false => (&mut low[current], &mut high[0]),
+		};
+		writer.switch_screen(parked, shown);
+		ACTIVE.store(index, Ordering::Relaxed);
+	}
+
+	return true;
+}
+
+/// Writes `s` to terminal `index`, on screen if it is the visible one and
+/// into its cells only otherwise.
+///
+/// Returns `false` if `index` is out of range.
+pub fn write_str(index: usize, s: &str) -> bool {
+	if index >= VT_COUNT {
+		return false;
+	}
+
+	let mut writer = WRITER.lock();
+	match index == ACTIVE.load(Ordering::Relaxed) {
+		true => writer.write_string(s),
+		false => writer.write_parked(&mut SCREENS.lock()[index], s),
+	}
+
+	return true;
+}
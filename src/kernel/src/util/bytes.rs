@@ -0,0 +1,22 @@
+//! Reading the little endian fields of on-disk and in-file structures.
+
+/// Returns the little endian `u16` at `offset` in `bytes`.
+///
+/// # Panics
+/// Panics if `bytes` ends before the field does.
+pub fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+	return u16::from_le_bytes([bytes[offset], bytes[offset + 1]]);
+}
+
+/// Returns the little endian `u32` at `offset` in `bytes`.
+///
+/// # Panics
+/// Panics if `bytes` ends before the field does.
+pub fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+	return u32::from_le_bytes([
+		bytes[offset],
+		bytes[offset + 1],
+		bytes[offset + 2],
+		bytes[offset + 3],
+	]);
+}
@@ -0,0 +1,9 @@
+//! Small helpers that do not belong to a subsystem.
+
+/// Module containing the little endian field readers of the FAT and ELF
+/// code.
+pub mod bytes;
+
+/// Module containing the xorshift pseudo random number generator and its
+/// hardware seed.
+pub mod rand;
@@ -0,0 +1,192 @@
+//! Xorshift pseudo random number generator.
+//!
+//! Good enough for stress tests and jitter, **not** for anything that needs
+//! unpredictable numbers.
+//!
+//! [`hardware_seed`] mixes what the machine offers: the time stamp counter,
+//! the RTC time, the tick count and RDRAND if CPUID reports it. Each source
+//! only has to vary between boots or calls, the mix spreads its bits over
+//! the whole seed. [`rand_u32`], [`rand_range`], [`fill_bytes`] and
+//! [`shuffle`] draw from a shared generator seeded that way on first use. None
+//! of them allocate.
+
+use crate::{
+	arch::x86::cpu::{cpu_features, Feature},
+	device::rtc,
+	sync::IrqMutex,
+	time,
+};
+use core::arch::{asm, x86::_rdtsc};
+
+/// Attempts at RDRAND before giving up, the count Intel recommends.
+const RDRAND_RETRIES: usize = 10;
+
+/// The generator behind [`rand_u32`] and friends, seeded on first use.
+static GLOBAL: IrqMutex<Option<Rng>> = IrqMutex::named("RNG", None);
+
+/// A xorshift64 generator.
+#[derive(Debug, Clone)]
+pub struct Rng {
+	state: u64,
+}
+
+impl Rng {
+	/// Creates a generator from `seed`. A zero seed is replaced, as xorshift
+	/// would only ever return zero.
+	pub const fn new(seed: u64) -> Self {
+		let state = match seed {
+			0 => 0x9e37_79b9_7f4a_7c15,
+			seed => seed,
+		};
+
+		return Self {
+			state,
+		};
+	}
+
+	/// Creates a generator seeded from [`hardware_seed`].
+	pub fn from_entropy() -> Self {
+		return Self::new(hardware_seed());
+	}
+
+	/// Returns the next 64 random bits.
+	pub fn next_u64(&mut self) -> u64 {
+		let mut x = self.state;
+		x ^= x << 13;
+		x ^= x >> 7;
+		x ^= x << 17;
+		self.state = x;
+
+		return x;
+	}
+
+	/// Returns the next 32 random bits.
+	pub fn next_u32(&mut self) -> u32 {
+		return (self.next_u64() >> 32) as u32;
+	}
+
+	/// Returns a number in `low..=high`.
+	///
+	/// # Panics
+	/// Panics if `high` is below `low`.
+	pub fn range(&mut self, low: usize, high: usize) -> usize {
+		assert!(low <= high, "empty range {}..={}", low, high);
+		let span = (high - low) as u64 + 1;
+
+		return low + (self.next_u64() % span) as usize;
+	}
+
+	/// Fills `buf` with random bytes.
+	pub fn fill_bytes(&mut self, buf: &mut [u8]) {
+		for chunk in buf.chunks_mut(size_of::<u64>()) {
+			let bytes = self.next_u64().to_le_bytes();
+			chunk.copy_from_slice(&bytes[..chunk.len()]);
+		}
+	}
+
+	/// Shuffles `items` in place (Fisher-Yates).
+	pub fn shuffle<T>(&mut self, items: &mut [T]) {
+		for i in (1..items.len()).rev() {
+			items.swap(i, self.range(0, i));
+		}
+	}
+}
+
+// Helper spreading the bits of `x` over the result, the splitmix64 finalizer
+fn mix(x: u64) -> u64 {
+	let mut z = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+	z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+	z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+
+	return z ^ (z >> 31);
+}
+
+/// Returns 32 bits from RDRAND, `None` if the CPU has no RDRAND or it
+/// came up empty [`RDRAND_RETRIES`] times in a row.
+pub fn rdrand_u32() -> Option<u32> {
+	if !cpu_features().has(Feature::Rdrand) {
+		return None;
+	}
+
+	for _ in 0..RDRAND_RETRIES {
+		let value: u32;
+		let ok: u8;
+		// Safety: CPUID reports RDRAND, it only writes the registers.
+		unsafe {
+			asm!(
+				"rdrand {value}",
+				"setc {ok}",
+				value = out(reg) value,
+				ok = out(reg_byte) ok,
+				options(nomem, nostack)
+			);
+		}
+		if ok != 0 {
+			return Some(value);
+		}
+	}
+
+	return None;
+}
+
+/// Returns a seed mixed from the hardware sources, see the module
+/// documentation. Two calls return different seeds as long as the time
+/// stamp counter or RDRAND is there.
+pub fn hardware_seed() -> u64 {
+	let mut seed = mix(time::ticks());
+
+	if cpu_features().has(Feature::Tsc) {
+		// Safety: CPUID reports the time stamp counter.
+		seed = mix(seed ^ unsafe { _rdtsc() });
+	}
+
+	let now = rtc::rtc();
+	let date = ((now.year as u64) << 40)
+		| ((now.month as u64) << 32)
+		| ((now.day as u64) << 24)
+		| ((now.hour as u64) << 16)
+		| ((now.minute as u64) << 8)
+		| now.second as u64;
+	seed = mix(seed ^ date);
+
+	// Without it the other sources have to do
+	if let (Some(high), Some(low)) = (rdrand_u32(), rdrand_u32()) {
+		seed = mix(seed ^ (((high as u64) << 32) | low as u64));
+	}
+
+	return seed;
+}
+
+// Helper running `f` on the shared generator, seeding it first if needed
+fn with_global<R>(f: impl FnOnce(&mut Rng) -> R) -> R {
+	let mut global = GLOBAL.lock();
+	let rng = global.get_or_insert_with(Rng::from_entropy);
+
+	return f(rng);
+}
+
+/// Returns 32 random bits from the shared generator.
+pub fn rand_u32() -> u32 {
+	return with_global(Rng::next_u32);
+}
+
+/// Returns a number in `low..=high` from the shared generator.
+///
+/// # Panics
+/// Panics if `high` is below `low`.
+pub fn rand_range(low: usize, high: usize) -> usize {
+	// Checked before the lock is taken, a panic would leave it held
+	assert!(low <= high, "empty range {}..={}", low, high);
+
+	return with_global(|rng| rng.range(low, high));
+}
+
+/// Fills `buf` with random bytes from the shared generator.
+pub fn fill_bytes(buf: &mut [u8]) {
+	with_global(|rng| rng.fill_bytes(buf));
+}
+
+/// Shuffles `items` in place with the shared generator.
+pub fn shuffle<T>(items: &mut [T]) {
+	with_global(|rng| rng.shuffle(items));
+}